@@ -0,0 +1,120 @@
+//! Repairs and formats arbitrary input as formatjson5 does, with options chosen by the first two
+//! bytes of the input, and checks that the formatted document parses and formats to the same text
+//! again, so formatting never produces invalid output or output that changes on the next run.
+//!
+//! The document goes through `jsonpretty5er::format_str()`, which runs the formatter's own code
+//! around json5format: the repairs, the edits of the quote and number options, the comment
+//! attachment, and the passes that rewrite json5format's output (comments in empty containers and
+//! at the ends of lines, array filling, long values, and tabs). The lexer is checked to cover the
+//! input with its tokens.
+
+#![no_main]
+
+use json5format::FormatOptions;
+use jsonpretty5er::{
+  array_wrap::ArrayWrap,
+  comment_attachment::CommentAttachment,
+  empty::EmptyComments,
+  eol_comments::EolComments,
+  format_str, lexer,
+  literals::{HexNumbers, NormalizeFloats},
+  long_values::LongValues,
+  quotes::Quotes,
+  repair::FixLevel,
+  syntax, FormatError, Options,
+};
+use libfuzzer_sys::fuzz_target;
+
+/// The width that arrays are filled to, and long values wrapped at.
+const MAX_WIDTH: usize = 40;
+
+/// Returns the options chosen by the number `choice`.
+fn options(mut choice: usize) -> Options {
+  let mut pick = |count: usize| {
+    let picked = choice % count;
+    choice /= count;
+    picked
+  };
+  Options {
+    fix: [FixLevel::None, FixLevel::Safe, FixLevel::All][pick(3)],
+    format: FormatOptions {
+      trailing_commas: pick(2) == 0,
+      indent_by: [4, 2][pick(2)],
+      ..FormatOptions::default()
+    },
+    quotes: [Quotes::Preserve, Quotes::Double, Quotes::Single][pick(3)],
+    hex_numbers: [
+      HexNumbers::Preserve,
+      HexNumbers::Decimal,
+      HexNumbers::Lower,
+      HexNumbers::Upper,
+    ][pick(4)],
+    normalize_floats: [NormalizeFloats::Preserve, NormalizeFloats::Shortest][pick(2)],
+    comment_attachment: [
+      CommentAttachment::Next,
+      CommentAttachment::Previous,
+      CommentAttachment::BlankLineAware,
+    ][pick(3)],
+    empty_comments: [EmptyComments::Expand, EmptyComments::Inline][pick(2)],
+    eol_comments: [
+      EolComments::Keep,
+      EolComments::MoveAbove,
+      EolComments::Align,
+    ][pick(3)],
+    array_wrap: [ArrayWrap::Vertical, ArrayWrap::Fill, ArrayWrap::FillNumbers][pick(3)],
+    long_values: [LongValues::Allow, LongValues::WrapMember][pick(2)],
+    max_width: MAX_WIDTH,
+    // Keeps the indentation of documents indented with tabs.
+    auto_indent: pick(2) == 1,
+    ..Options::default()
+  }
+}
+
+/// Returns the document `source` repaired and formatted with the `options`, or `None` if it
+/// cannot be parsed.
+fn format(source: &str, options: &Options) -> Option<String> {
+  let tokens = lexer::tokenize(source);
+  let mut end = 0;
+  for token in &tokens {
+    assert_eq!(token.start, end, "the tokens do not cover the input");
+    end = token.end;
+  }
+  assert_eq!(end, source.len(), "the tokens do not cover the input");
+
+  match format_str(source, "fuzz.json5", options) {
+    Ok(outcome) => Some(outcome.formatted),
+    Err(FormatError::Parse { .. } | FormatError::Conflict { .. }) => None,
+    Err(err) => panic!("{}\n{:?}", err, options),
+  }
+}
+
+fuzz_target!(|data: &[u8]| {
+  let [first, second, rest @ ..] = data else {
+    return;
+  };
+  let Ok(source) = std::str::from_utf8(rest) else {
+    return;
+  };
+  let options = options(usize::from(*first) << 8 | usize::from(*second));
+  let Some(formatted) = format(source, &options) else {
+    return;
+  };
+  if let Err(err) = syntax::parse(&formatted) {
+    panic!(
+      "the formatted document cannot be parsed: {}\n{}\n{:?}",
+      err, formatted, options
+    );
+  }
+  let again = match format(&formatted, &options) {
+    Some(again) => again,
+    None => panic!(
+      "the formatted document cannot be formatted:\n{}\n{:?}",
+      formatted, options
+    ),
+  };
+  assert_eq!(
+    formatted, again,
+    "formatting the formatted document changed it, with {:?}",
+    options
+  );
+});
@@ -0,0 +1,388 @@
+//! Formats JSON5 members of a tar or zip archive in place (the `--archive` option). The archive is
+//! rewritten atomically: the new archive is written next to the original and renamed over it, and
+//! only if every selected member was formatted successfully.
+//!
+//! Uncompressed tar archives (ustar, GNU, and pax) are read and written directly: the long names of
+//! GNU and pax headers are used to match members, and the other extension headers (such as GNU
+//! long link names) are copied unchanged. Zip archives are read and updated with the `unzip` and
+//! `zip` commands, which handle their compression.
+
+use crate::{
+  glob::PathGlob,
+  paths,
+  write_mode::{private_directory, temporary_path},
+};
+use std::{
+  fs,
+  path::{Component, Path, PathBuf},
+  process::Command,
+};
+
+/// The size of a tar header or data block.
+const BLOCK: usize = 512;
+
+/// Formats each regular file member of the archive at `path` whose name matches one of the
+/// `patterns`, with `format` (given the member's display name and content), and returns the
+/// number of members that changed. Member names are matched like value paths, with a segment for
+/// each directory and the file name (so `configs/*.json5` matches the `.json5` files in any
+/// `configs` directory).
+pub fn format_archive(
+  path: &Path,
+  patterns: &[PathGlob],
+  format: impl Fn(&str, String) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<usize, anyhow::Error> {
+  let matches = |name: &str| {
+    let segments = name.split('/').map(str::to_string).collect::<Vec<_>>();
+    patterns.iter().any(|pattern| pattern.matches(&segments))
+  };
+  let display = |name: &str| format!("{}:{}", path.display(), name);
+  let extension = path.extension().and_then(|ext| ext.to_str());
+  match extension {
+    Some("tar") => format_tar(path, matches, |name, content| {
+      format(&display(name), content)
+    }),
+    Some("zip") => format_zip(path, matches, |name, content| {
+      format(&display(name), content)
+    }),
+    _ => Err(anyhow::anyhow!(
+      "{}: Unsupported archive type (expected a .tar or .zip file)",
+      path.display()
+    )),
+  }
+}
+
+/// Formats the selected members of the tar archive at `path`.
+fn format_tar(
+  path: &Path,
+  matches: impl Fn(&str) -> bool,
+  format: impl Fn(&str, String) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<usize, anyhow::Error> {
+  let error = |message: &str| anyhow::anyhow!("{}: {}", path.display(), message);
+  let archive = fs::read(paths::extended(path))?;
+  let mut output = Vec::with_capacity(archive.len());
+  let mut offset = 0;
+  let mut long_name: Option<String> = None;
+  let mut pax_size = false;
+  let mut changed = 0;
+  loop {
+    let header = archive
+      .get(offset..offset + BLOCK)
+      .ok_or_else(|| error("Unexpected end of the archive"))?;
+    if header.iter().all(|byte| *byte == 0) {
+      // The end-of-archive marker, and any padding after it, are copied unchanged.
+      output.extend_from_slice(&archive[offset..]);
+      break;
+    }
+    let size = tar_number(&header[124..136]).ok_or_else(|| error("Invalid member size"))?;
+    let data_start = offset + BLOCK;
+    let data_end = data_start.checked_add(size);
+    let next = size
+      .div_ceil(BLOCK)
+      .checked_mul(BLOCK)
+      .and_then(|padded| data_start.checked_add(padded));
+    let (Some(data_end), Some(next)) = (data_end, next) else {
+      return Err(error(
+        "The archive is corrupt (a member's size is too large)",
+      ));
+    };
+    let data = archive
+      .get(data_start..data_end)
+      .ok_or_else(|| error("Unexpected end of the archive"))?;
+    let typeflag = header[156];
+    match typeflag {
+      b'L' => {
+        long_name = Some(c_string(data));
+        output.extend_from_slice(&archive[offset..next.min(archive.len())]);
+      }
+      // The long link name of the next member, which is copied unchanged, as links are not
+      // formatted; a pax global header applies to every member, not a name of the next one.
+      b'K' | b'g' => output.extend_from_slice(&archive[offset..next.min(archive.len())]),
+      b'x' => {
+        let text = String::from_utf8_lossy(data);
+        for record in text.lines() {
+          if let Some((_, keyword)) = record.split_once(' ') {
+            if let Some(name) = keyword.strip_prefix("path=") {
+              long_name = Some(name.to_string());
+            }
+            pax_size |= keyword.starts_with("size=");
+          }
+        }
+        output.extend_from_slice(&archive[offset..next.min(archive.len())]);
+      }
+      _ => {
+        let name = long_name.take().unwrap_or_else(|| {
+          let (name, prefix) = (c_string(&header[0..100]), c_string(&header[345..500]));
+          match prefix.is_empty() || &header[257..262] != b"ustar" {
+            true => name,
+            false => format!("{}/{}", prefix, name),
+          }
+        });
+        let regular_file = matches!(typeflag, b'0' | 0 | b'7');
+        if regular_file && matches(&name) {
+          if std::mem::take(&mut pax_size) {
+            return Err(error(&format!(
+              "{}: Members with a pax size record are not supported",
+              name
+            )));
+          }
+          let content = String::from_utf8(data.to_vec())
+            .map_err(|_| error(&format!("{}: The member is not UTF-8 text", name)))?;
+          let formatted = format(&name, content)?;
+          if formatted != data {
+            changed += 1;
+          }
+          let mut header = header.to_vec();
+          write_tar_size(&mut header, formatted.len())
+            .ok_or_else(|| error(&format!("{}: The formatted member is too large", name)))?;
+          output.extend_from_slice(&header);
+          output.extend_from_slice(&formatted);
+          output.resize(output.len().div_ceil(BLOCK) * BLOCK, 0);
+        } else {
+          pax_size = false;
+          output.extend_from_slice(&archive[offset..next.min(archive.len())]);
+        }
+      }
+    }
+    offset = next;
+  }
+  if changed > 0 {
+    write_atomically(path, &output)?;
+  }
+  Ok(changed)
+}
+
+/// Parses a tar numeric field: octal digits, or a base-256 number if the high bit of the first
+/// byte is set.
+fn tar_number(field: &[u8]) -> Option<usize> {
+  if field[0] & 0x80 != 0 {
+    let mut value = (field[0] & 0x7f) as usize;
+    for byte in &field[1..] {
+      value = value.checked_mul(256)?.checked_add(*byte as usize)?;
+    }
+    return Some(value);
+  }
+  let text = String::from_utf8_lossy(field);
+  let digits = text.trim_matches(|c: char| c == '\0' || c == ' ');
+  if digits.is_empty() {
+    return Some(0);
+  }
+  usize::from_str_radix(digits, 8).ok()
+}
+
+/// Sets the size field of the tar `header` to `size`, and updates its checksum. Returns `None` if
+/// the size does not fit in the field.
+fn write_tar_size(header: &mut [u8], size: usize) -> Option<()> {
+  let digits = format!("{:011o}", size);
+  if digits.len() > 11 {
+    return None;
+  }
+  header[124..135].copy_from_slice(digits.as_bytes());
+  header[135] = 0;
+  header[148..156].fill(b' ');
+  let checksum = header.iter().map(|byte| *byte as usize).sum::<usize>();
+  header[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+  Some(())
+}
+
+/// Returns the NUL-terminated string at the start of `bytes`.
+fn c_string(bytes: &[u8]) -> String {
+  let end = bytes
+    .iter()
+    .position(|byte| *byte == 0)
+    .unwrap_or(bytes.len());
+  String::from_utf8_lossy(&bytes[..end]).to_string()
+}
+
+/// Formats the selected members of the zip archive at `path`.
+fn format_zip(
+  path: &Path,
+  matches: impl Fn(&str) -> bool,
+  format: impl Fn(&str, String) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<usize, anyhow::Error> {
+  let listing = run(Command::new("unzip").arg("-Z1").arg(path))?;
+  let names = String::from_utf8(listing)?
+    .lines()
+    .filter(|name| !name.ends_with('/') && matches(name))
+    .map(str::to_string)
+    .collect::<Vec<_>>();
+
+  let mut formatted = Vec::new();
+  for name in &names {
+    // `unzip` treats member names as wildcard patterns, so escape the wildcard characters.
+    let pattern = ["[", "*", "?"]
+      .iter()
+      .fold(name.replace('\\', "\\\\"), |pattern, c| {
+        pattern.replace(c, &format!("\\{}", c))
+      });
+    // A leading `-` would start an option, so match it with a character class instead.
+    let pattern = match pattern.strip_prefix('-') {
+      Some(rest) => format!("[-]{}", rest),
+      None => pattern,
+    };
+    let content = run(Command::new("unzip").arg("-p").arg(path).arg(&pattern))?;
+    let content = String::from_utf8(content.clone())
+      .map_err(|_| anyhow::anyhow!("{}:{}: The member is not UTF-8 text", path.display(), name))?;
+    let bytes = format(name, content.clone())?;
+    if bytes != content.as_bytes() {
+      formatted.push((name, bytes));
+    }
+  }
+  if formatted.is_empty() {
+    return Ok(0);
+  }
+
+  let staging = private_directory("json5format-zip")?;
+  let result = (|| {
+    for (name, bytes) in &formatted {
+      let member = paths::extended(&member_path(path, &staging, name)?);
+      fs::create_dir_all(member.parent().unwrap_or(&staging))?;
+      fs::write(member, bytes)?;
+    }
+    let path = paths::extended(path);
+    let temporary = temporary_path(&path);
+    fs::copy(&path, &temporary)?;
+    let absolute = fs::canonicalize(&temporary)?;
+    let mut zip = Command::new("zip");
+    // `--` ends the options, so a member name starting with `-` is not taken for one.
+    zip.current_dir(&staging).arg("-q").arg(&absolute).arg("--");
+    zip.args(formatted.iter().map(|(name, _)| name.as_str()));
+    if let Err(err) = run(&mut zip) {
+      let _ = fs::remove_file(&temporary);
+      return Err(err);
+    }
+    fs::rename(&temporary, &path)?;
+    Ok(formatted.len())
+  })();
+  let _ = fs::remove_dir_all(&staging);
+  result
+}
+
+/// Returns the path of the zip member `name` of the archive at `archive` under the directory
+/// `staging`, or an error if the name is absolute or leads outside of the directory (with `..`).
+fn member_path(archive: &Path, staging: &Path, name: &str) -> Result<PathBuf, anyhow::Error> {
+  let mut path = staging.to_path_buf();
+  for component in Path::new(name).components() {
+    match component {
+      Component::Normal(name) => path.push(name),
+      Component::CurDir => {}
+      Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+        return Err(anyhow::anyhow!(
+          "{}:{}: Cannot format a member whose name is absolute or contains `..`",
+          archive.display(),
+          name
+        ));
+      }
+    }
+  }
+  Ok(path)
+}
+
+/// Runs `command`, and returns its output, or an error with its stderr if it fails.
+fn run(command: &mut Command) -> Result<Vec<u8>, anyhow::Error> {
+  let program = command.get_program().to_string_lossy().to_string();
+  let output = command
+    .output()
+    .map_err(|err| anyhow::anyhow!("Cannot run {}: {}", program, err))?;
+  if !output.status.success() {
+    return Err(anyhow::anyhow!(
+      "{} failed: {}",
+      program,
+      String::from_utf8_lossy(&output.stderr).trim()
+    ));
+  }
+  Ok(output.stdout)
+}
+
+/// Replaces the file at `path` with `bytes`, by writing a temporary file next to it and renaming
+/// it, so readers never see a partially written file.
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), anyhow::Error> {
+  let path = paths::extended(path);
+  let temporary = temporary_path(&path);
+  fs::write(&temporary, bytes)?;
+  if let Err(err) = fs::rename(&temporary, &path) {
+    let _ = fs::remove_file(&temporary);
+    return Err(err.into());
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns a tar header for a member named `name`, of the type `typeflag`, with `size` bytes.
+  fn header(name: &str, typeflag: u8, size: usize) -> Vec<u8> {
+    let mut header = vec![0; BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[156] = typeflag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    write_tar_size(&mut header, size).unwrap();
+    header
+  }
+
+  /// Appends a member named `name`, of the type `typeflag`, holding `data`, to `archive`.
+  fn member(archive: &mut Vec<u8>, name: &str, typeflag: u8, data: &[u8]) {
+    archive.extend(header(name, typeflag, data.len()));
+    archive.extend(data);
+    archive.resize(archive.len().div_ceil(BLOCK) * BLOCK, 0);
+  }
+
+  /// Writes `archive` to a file, and formats its `.json5` members by appending `!` to them.
+  fn format(archive: &[u8]) -> (Result<usize, anyhow::Error>, Vec<u8>) {
+    let path = private_directory("jsonpretty5er-archive-test")
+      .unwrap()
+      .join("a.tar");
+    fs::write(&path, archive).unwrap();
+    let result = format_tar(
+      &path,
+      |name| name.ends_with(".json5"),
+      |_, content| Ok(format!("{}!", content).into_bytes()),
+    );
+    let formatted = fs::read(&path).unwrap();
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+    (result, formatted)
+  }
+
+  #[test]
+  fn formats_members_with_long_names() {
+    let long_name = format!("{}.json5", "a".repeat(120));
+    let mut archive = Vec::new();
+    member(&mut archive, "././@LongLink", b'L', long_name.as_bytes());
+    member(&mut archive, "././@LongLink", b'K', b"link-target");
+    member(&mut archive, "short", b'0', b"{}");
+    member(&mut archive, "b.json", b'0', b"{}");
+    archive.extend([0; 2 * BLOCK]);
+    let (result, formatted) = format(&archive);
+    assert_eq!(result.unwrap(), 1);
+
+    let mut expected = Vec::new();
+    member(&mut expected, "././@LongLink", b'L', long_name.as_bytes());
+    member(&mut expected, "././@LongLink", b'K', b"link-target");
+    member(&mut expected, "short", b'0', b"{}!");
+    member(&mut expected, "b.json", b'0', b"{}");
+    expected.extend([0; 2 * BLOCK]);
+    assert_eq!(formatted, expected);
+  }
+
+  #[test]
+  fn reports_sizes_past_the_end_of_the_archive() {
+    let mut archive = header("a.json5", b'0', 0);
+    // A base-256 size of 2^64 - 1 bytes.
+    archive[124..128].copy_from_slice(&[0x80, 0, 0, 0]);
+    archive[128..136].fill(0xff);
+    archive.extend([0; 2 * BLOCK]);
+    let (result, _) = format(&archive);
+    assert!(result
+      .unwrap_err()
+      .to_string()
+      .ends_with("The archive is corrupt (a member's size is too large)"));
+
+    let mut archive = header("a.json5", b'0', 4096);
+    archive.extend([0; 2 * BLOCK]);
+    let (result, _) = format(&archive);
+    assert!(result
+      .unwrap_err()
+      .to_string()
+      .ends_with("Unexpected end of the archive"));
+  }
+}
@@ -0,0 +1,147 @@
+//! Wraps the items of arrays across lines (the `array_wrap` option).
+//!
+//! With `vertical` (the default), json5format writes each array item on a line of its own. With
+//! `fill`, the items of an array of strings and other primitive values are packed onto as few lines
+//! as fit in `max_width` (see the [long_values](../long_values/index.html) module), and with
+//! `fill-numbers`, only the items of arrays of numbers are, as in:
+//!
+//!     ports: [
+//!         80, 443, 8080, 8443,
+//!         9000,
+//!     ],
+//!
+//! Arrays holding objects, arrays, or comments, and arrays written on a single line (with
+//! `one_element_lines`), are left vertical.
+
+use crate::{
+  edit::Edits,
+  format::FormatError,
+  lexer::{self, TokenKind},
+  syntax::{self, Node},
+};
+use std::str::FromStr;
+
+/// How to wrap the items of arrays across lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArrayWrap {
+  /// Write each item on a line of its own.
+  #[default]
+  Vertical,
+  /// Pack as many primitive items on each line as fit.
+  Fill,
+  /// Pack as many items on each line as fit, in arrays of numbers only.
+  FillNumbers,
+}
+
+impl ArrayWrap {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["vertical", "fill", "fill-numbers"];
+
+  /// Returns the spelling of the style, as in the configuration file.
+  pub fn name(self) -> &'static str {
+    match self {
+      ArrayWrap::Vertical => "vertical",
+      ArrayWrap::Fill => "fill",
+      ArrayWrap::FillNumbers => "fill-numbers",
+    }
+  }
+}
+
+impl FromStr for ArrayWrap {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "vertical" => Ok(ArrayWrap::Vertical),
+      "fill" => Ok(ArrayWrap::Fill),
+      "fill-numbers" => Ok(ArrayWrap::FillNumbers),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", ArrayWrap::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// Returns `formatted` (the formatted text of a document, without any preamble or trailing
+/// content) with the items of each array of primitive values (or if `numbers_only` is true, of
+/// numbers) packed onto lines of at most `max_width` characters.
+pub fn fill(formatted: &str, max_width: usize, numbers_only: bool) -> String {
+  let Ok(Some(root)) = syntax::parse(formatted) else {
+    return formatted.to_string();
+  };
+  let mut edits = Edits::default();
+  visit(&root, formatted, max_width, numbers_only, &mut edits);
+  edits.apply(formatted)
+}
+
+/// Adds the edits packing the items of the arrays in `node`, in `formatted`.
+fn visit(node: &Node, formatted: &str, max_width: usize, numbers_only: bool, edits: &mut Edits) {
+  let items = match node {
+    Node::Object { members, .. } => {
+      for member in members {
+        visit(&member.value, formatted, max_width, numbers_only, edits);
+      }
+      return;
+    }
+    Node::Array { items, .. } => items,
+    Node::String { .. } | Node::Literal { .. } => return,
+  };
+  let span = node.span();
+  let (Some(first), Some(last)) = (items.first(), items.last()) else {
+    return;
+  };
+  let packable = items.len() > 1
+    && first.span().line != span.line
+    && items.iter().all(|item| match item {
+      Node::String { .. } => !numbers_only,
+      Node::Literal { .. } => !numbers_only || item.type_name(formatted) == "number",
+      Node::Object { .. } | Node::Array { .. } => false,
+    });
+  if !packable {
+    for item in items {
+      visit(item, formatted, max_width, numbers_only, edits);
+    }
+    return;
+  }
+  let inner = &formatted[span.start + 1..span.end - 1];
+  let has_comments = lexer::tokenize(inner)
+    .iter()
+    .any(|token| matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment));
+  if has_comments {
+    return;
+  }
+
+  let indent = |at: usize| {
+    let line_start = formatted[..at].rfind('\n').map_or(0, |newline| newline + 1);
+    let line = &formatted[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+  };
+  let item_indent = indent(first.span().start);
+  let trailing_comma = formatted[last.span().end..].starts_with(',');
+  let mut lines: Vec<String> = Vec::new();
+  let mut line = String::new();
+  for (index, item) in items.iter().enumerate() {
+    let item = item.span();
+    let mut text = formatted[item.start..item.end].to_string();
+    if index + 1 < items.len() || trailing_comma {
+      text.push(',');
+    }
+    let width = item_indent.chars().count() + line.chars().count() + 1 + text.chars().count();
+    if !line.is_empty() && width > max_width {
+      lines.push(std::mem::take(&mut line));
+    }
+    if !line.is_empty() {
+      line.push(' ');
+    }
+    line.push_str(&text);
+  }
+  lines.push(line);
+  let mut replacement = String::from("\n");
+  for line in lines {
+    replacement.push_str(item_indent);
+    replacement.push_str(&line);
+    replacement.push('\n');
+  }
+  replacement.push_str(indent(span.end - 1));
+  edits.replace(span.start + 1..span.end - 1, replacement);
+}
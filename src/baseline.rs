@@ -0,0 +1,201 @@
+//! Baseline files, which record the known problems of each file so `--check` only fails on new
+//! problems, for adopting the formatter gradually in a large repository.
+//!
+//! `--check --baseline <file> --update-baseline` records the current problems of the files
+//! checked, and later `--check --baseline <file>` runs fail only on problems that are not in the
+//! baseline. A baseline is a JSON5 document with the problems of each file:
+//!
+//!     {
+//!         "legacy/config.json5": {unformatted: true, errors: {J5F008: 2}},
+//!         "legacy/broken.json5": {failed: true},
+//!     }
+//!
+//! where `unformatted` means the file is not formatted, `failed` means it could not be formatted
+//! (such as because of a parse error), and `errors` counts the error diagnostics of each code. A
+//! file with more errors of a code than its baseline has new problems.
+
+use crate::{
+  codes::Code,
+  paths,
+  syntax::{self, Node},
+};
+use std::{collections::BTreeMap, fs, path::Path, sync::Mutex};
+
+/// The problems of a file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Problems {
+  /// True if the file is not formatted.
+  pub unformatted: bool,
+  /// True if the file could not be formatted.
+  pub failed: bool,
+  /// The number of error diagnostics of each code, by code.
+  pub errors: BTreeMap<String, usize>,
+}
+
+impl Problems {
+  /// Returns true if the file has no problems.
+  pub fn is_empty(&self) -> bool {
+    !self.unformatted && !self.failed && self.errors.is_empty()
+  }
+
+  /// Returns a description of each of the problems that are not in `known`.
+  pub fn new_since(&self, known: &Problems) -> Vec<String> {
+    let mut new = Vec::new();
+    if self.failed && !known.failed {
+      new.push("Could not be formatted".to_string());
+    }
+    if self.unformatted && !known.unformatted {
+      new.push("Not formatted".to_string());
+    }
+    for (code, count) in &self.errors {
+      let known = known.errors.get(code).copied().unwrap_or(0);
+      if *count > known {
+        new.push(format!(
+          "{} {} error(s), where the baseline has {}",
+          count, code, known
+        ));
+      }
+    }
+    new
+  }
+
+  /// Returns the number of separate problems, for summaries.
+  pub fn count(&self) -> usize {
+    usize::from(self.unformatted) + usize::from(self.failed) + self.errors.values().sum::<usize>()
+  }
+}
+
+/// The known problems of each file, by filename.
+#[derive(Debug, Default)]
+pub struct Baseline {
+  /// The problems of each file that has any.
+  pub files: BTreeMap<String, Problems>,
+}
+
+impl Baseline {
+  /// Reads the baseline file at `path`. A missing file is an empty baseline, so the first
+  /// `--update-baseline` run can create it.
+  pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+    let error =
+      |message: String| anyhow::anyhow!("Baseline error: {}: {}", path.display(), message);
+    let source = match fs::read_to_string(paths::extended(path)) {
+      Ok(source) => source,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+      Err(err) => return Err(error(err.to_string())),
+    };
+    let root = syntax::parse(&source).map_err(|err| error(err.to_string()))?;
+    let mut baseline = Self::default();
+    let Some(root) = root else {
+      return Ok(baseline);
+    };
+    let expected = |node: &Node, what: &str| {
+      let span = node.span();
+      error(format!(
+        "{}:{}: Expected {}, but found {}",
+        span.line,
+        span.col,
+        what,
+        node.type_name(&source)
+      ))
+    };
+    let Node::Object { members, .. } = &root else {
+      return Err(expected(&root, "an object"));
+    };
+    for file in members {
+      let Node::Object { members, .. } = &file.value else {
+        return Err(expected(&file.value, "an object"));
+      };
+      let mut problems = Problems::default();
+      for member in members {
+        let value = &member.value;
+        match (member.name.as_str(), value) {
+          ("unformatted", Node::Literal { span }) => {
+            problems.unformatted = &source[span.start..span.end] == "true"
+          }
+          ("failed", Node::Literal { span }) => {
+            problems.failed = &source[span.start..span.end] == "true"
+          }
+          ("errors", Node::Object { members, .. }) => {
+            for error in members {
+              let count = match &error.value {
+                Node::Literal { span } => source[span.start..span.end].parse::<usize>().ok(),
+                _ => None,
+              };
+              let count = count.ok_or_else(|| expected(&error.value, "a count"))?;
+              problems.errors.insert(error.name.clone(), count);
+            }
+          }
+          _ => {
+            return Err(error(format!(
+              "{}:{}: Unexpected property `{}`",
+              member.key.line, member.key.col, member.name
+            )))
+          }
+        }
+      }
+      baseline.files.insert(file.name.clone(), problems);
+    }
+    Ok(baseline)
+  }
+
+  /// Writes the baseline to the file at `path`.
+  pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+    let mut text = String::new();
+    text.push_str(
+      "// The known problems that `--check` does not fail on. Regenerate this file with\n",
+    );
+    text.push_str("// `--check --baseline <file> --update-baseline`.\n");
+    text.push_str("{\n");
+    for (filename, problems) in &self.files {
+      let mut fields = Vec::new();
+      if problems.unformatted {
+        fields.push("unformatted: true".to_string());
+      }
+      if problems.failed {
+        fields.push("failed: true".to_string());
+      }
+      if !problems.errors.is_empty() {
+        let errors = problems
+          .errors
+          .iter()
+          .map(|(code, count)| format!("{}: {}", code, count))
+          .collect::<Vec<_>>();
+        fields.push(format!("errors: {{{}}}", errors.join(", ")));
+      }
+      text.push_str(&format!(
+        "    {}: {{{}}},\n",
+        syntax::quote(filename, '"'),
+        fields.join(", ")
+      ));
+    }
+    text.push_str("}\n");
+    fs::write(paths::extended(path), text)
+      .map_err(|err| anyhow::anyhow!("Baseline error: {}: {}", path.display(), err))
+  }
+}
+
+/// The error diagnostics reported for each file in a run, which may be recorded from several
+/// threads.
+#[derive(Debug, Default)]
+pub struct Findings {
+  /// The number of error diagnostics of each code, by filename and code.
+  errors: Mutex<BTreeMap<String, BTreeMap<String, usize>>>,
+}
+
+impl Findings {
+  /// Counts an error diagnostic of the kind `code` in the file `filename`.
+  pub fn record(&self, filename: &str, code: &Code) {
+    let mut errors = self.errors.lock().unwrap_or_else(|err| err.into_inner());
+    *errors
+      .entry(filename.to_string())
+      .or_default()
+      .entry(code.id.to_string())
+      .or_default() += 1;
+  }
+
+  /// Returns the number of error diagnostics of each code in the file `filename`.
+  pub fn errors(&self, filename: &str) -> BTreeMap<String, usize> {
+    let errors = self.errors.lock().unwrap_or_else(|err| err.into_inner());
+    errors.get(filename).cloned().unwrap_or_default()
+  }
+}
@@ -0,0 +1,301 @@
+//! Builds JSON5 documents value by value, with comments attached to the values, for programs that
+//! generate configuration files with documentation in them. A document is rendered through the
+//! same formatter as a document that was read, with
+//! [Value::render()](struct.Value.html#method.render):
+//!
+//!     use jsonpretty5er::{builder::{Object, Value}, Options};
+//!
+//!     let document = Value::from(
+//!         Object::new()
+//!             .property("host", "localhost")
+//!             .property("timeout", Value::from(30).comment("In seconds.")),
+//!     )
+//!     .comment("Generated; do not edit.");
+//!     let text = document.render(&Options::default())?;
+//!
+//! renders:
+//!
+//!     // Generated; do not edit.
+//!     {
+//!         host: "localhost",
+//!
+//!         // In seconds.
+//!         timeout: 30,
+//!     }
+//!
+//! Each comment is written as line comments on the lines before its value (or before the property
+//! whose value it is).
+
+use crate::{
+  format::{self, FormatError, Options},
+  syntax,
+};
+
+/// A JSON5 value, with the comments before it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Value {
+  /// The value.
+  kind: Kind,
+  /// The comments before the value, each of one or more lines.
+  comments: Vec<String>,
+}
+
+/// A kind of JSON5 value.
+#[derive(Clone, Debug, PartialEq)]
+enum Kind {
+  /// `null`.
+  Null,
+  /// A boolean, or a number, as its literal.
+  Literal(String),
+  /// A string.
+  String(String),
+  /// An object.
+  Object(Object),
+  /// An array.
+  Array(Array),
+}
+
+impl Value {
+  /// Returns the value `null`.
+  pub fn null() -> Self {
+    Value::new(Kind::Null)
+  }
+
+  /// Returns a value of the kind `kind`, without comments.
+  fn new(kind: Kind) -> Self {
+    Value {
+      kind,
+      comments: Vec::new(),
+    }
+  }
+
+  /// Returns the value with the comment `text` (of one or more lines) after its other comments.
+  pub fn comment(mut self, text: &str) -> Self {
+    self.comments.push(text.to_string());
+    self
+  }
+
+  /// Returns the JSON5 text of the value, before it is formatted.
+  pub fn to_source(&self) -> String {
+    let mut source = String::new();
+    self.write(&mut source);
+    source.push('\n');
+    source
+  }
+
+  /// Returns the value formatted with the `options` (see the [format](../format/index.html)
+  /// module).
+  pub fn render(&self, options: &Options) -> Result<String, FormatError> {
+    format::format_str(&self.to_source(), "<builder>", options).map(|outcome| outcome.formatted)
+  }
+
+  /// Appends the comments and the text of the value to `source`.
+  fn write(&self, source: &mut String) {
+    write_comments(&self.comments, source);
+    self.write_value(source);
+  }
+
+  /// Appends the text of the value, without its comments, to `source`.
+  fn write_value(&self, source: &mut String) {
+    match &self.kind {
+      Kind::Null => source.push_str("null"),
+      Kind::Literal(literal) => source.push_str(literal),
+      Kind::String(value) => source.push_str(&syntax::quote(value, '"')),
+      Kind::Object(object) => {
+        source.push_str("{\n");
+        for (name, value) in &object.properties {
+          write_comments(&value.comments, source);
+          match syntax::is_identifier(name) {
+            true => source.push_str(name),
+            false => source.push_str(&syntax::quote(name, '"')),
+          }
+          source.push_str(": ");
+          value.write_value(source);
+          source.push_str(",\n");
+        }
+        source.push('}');
+      }
+      Kind::Array(array) => {
+        source.push_str("[\n");
+        for item in &array.items {
+          item.write(source);
+          source.push_str(",\n");
+        }
+        source.push(']');
+      }
+    }
+  }
+}
+
+/// Appends the `comments`, as line comments on lines of their own, to `source`.
+fn write_comments(comments: &[String], source: &mut String) {
+  for line in comments.iter().flat_map(|comment| comment.lines()) {
+    match line.is_empty() {
+      true => source.push_str("//\n"),
+      false => {
+        source.push_str("// ");
+        source.push_str(line);
+        source.push('\n');
+      }
+    }
+  }
+}
+
+impl From<bool> for Value {
+  fn from(value: bool) -> Self {
+    Value::new(Kind::Literal(value.to_string()))
+  }
+}
+
+impl From<i64> for Value {
+  fn from(value: i64) -> Self {
+    Value::new(Kind::Literal(value.to_string()))
+  }
+}
+
+impl From<i32> for Value {
+  fn from(value: i32) -> Self {
+    Value::new(Kind::Literal(value.to_string()))
+  }
+}
+
+impl From<u64> for Value {
+  fn from(value: u64) -> Self {
+    Value::new(Kind::Literal(value.to_string()))
+  }
+}
+
+impl From<f64> for Value {
+  /// Returns the number `value`, written as the shortest text that parses to it (or as `NaN`,
+  /// `Infinity`, or `-Infinity`).
+  fn from(value: f64) -> Self {
+    let literal = match value {
+      _ if value.is_nan() => "NaN".to_string(),
+      f64::INFINITY => "Infinity".to_string(),
+      f64::NEG_INFINITY => "-Infinity".to_string(),
+      _ => format!("{:?}", value),
+    };
+    Value::new(Kind::Literal(literal))
+  }
+}
+
+impl From<&str> for Value {
+  fn from(value: &str) -> Self {
+    Value::new(Kind::String(value.to_string()))
+  }
+}
+
+impl From<String> for Value {
+  fn from(value: String) -> Self {
+    Value::new(Kind::String(value))
+  }
+}
+
+impl From<Object> for Value {
+  fn from(object: Object) -> Self {
+    Value::new(Kind::Object(object))
+  }
+}
+
+impl From<Array> for Value {
+  fn from(array: Array) -> Self {
+    Value::new(Kind::Array(array))
+  }
+}
+
+/// An object, built property by property.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Object {
+  /// The names and values of the properties, in order.
+  properties: Vec<(String, Value)>,
+}
+
+impl Object {
+  /// Returns an empty object.
+  pub fn new() -> Self {
+    Object::default()
+  }
+
+  /// Returns the object with the property `name` appended, whose value is `value` (and whose
+  /// comments are those of `value`).
+  pub fn property(mut self, name: &str, value: impl Into<Value>) -> Self {
+    self.properties.push((name.to_string(), value.into()));
+    self
+  }
+}
+
+/// An array, built item by item.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Array {
+  /// The items, in order.
+  items: Vec<Value>,
+}
+
+impl Array {
+  /// Returns an empty array.
+  pub fn new() -> Self {
+    Array::default()
+  }
+
+  /// Returns the array with `item` appended.
+  pub fn item(mut self, item: impl Into<Value>) -> Self {
+    self.items.push(item.into());
+    self
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn renders_values_with_their_comments() {
+    let document = Value::from(
+      Object::new()
+        .property("host", "localhost")
+        .property("timeout", Value::from(30).comment("In seconds."))
+        .property(
+          "ports",
+          Array::new()
+            .item(80)
+            .item(Value::from(443).comment("TLS\n\nOnly with a certificate.")),
+        )
+        .property("not an identifier", Value::null())
+        .property("null", true),
+    )
+    .comment("Generated; do not edit.");
+    assert_eq!(
+      document.render(&Options::default()).unwrap(),
+      "// Generated; do not edit.\n{\n    host: \"localhost\",\n\n    // In seconds.\n    timeout: \
+       30,\n    ports: [\n        80,\n\n        // TLS\n        //\n        // Only with a \
+       certificate.\n        443,\n    ],\n    \"not an identifier\": null,\n    \"null\": true,\n}\n"
+    );
+  }
+
+  #[test]
+  fn writes_numbers_that_parse_to_the_same_value() {
+    let literals = [0.1, 1.0, 1e300, -0.0, f64::NAN, f64::NEG_INFINITY]
+      .into_iter()
+      .map(|number| Value::from(number).to_source())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      literals,
+      [
+        "0.1\n",
+        "1.0\n",
+        "1e300\n",
+        "-0.0\n",
+        "NaN\n",
+        "-Infinity\n"
+      ]
+    );
+  }
+
+  #[test]
+  fn escapes_strings() {
+    assert_eq!(
+      Value::from("a \"quote\"\n").to_source(),
+      "\"a \\\"quote\\\"\\n\"\n"
+    );
+  }
+}
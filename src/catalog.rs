@@ -0,0 +1,199 @@
+//! Lists the lint rules and the options of the configuration file (the `rules` subcommand), with
+//! their identifiers, defaults, and configuration keys, so that configuration UIs and
+//! documentation can be generated from the formatter itself.
+//!
+//! With `--json`, the list is a JSON object:
+//!
+//!     {
+//!       "rules": [
+//!         {"name": "max-depth", "code": "J5F010", "title": "Nesting too deep",
+//!          "config_key": "lint.max-depth", "default_level": "off",
+//!          "option": {"description": "a positive integer", "default": "10"}, "allowlist": false},
+//!         ...
+//!       ],
+//!       "options": [
+//!         {"name": "indent", "config_key": "indent", "env_var": "JSON5FMT_INDENT",
+//!          "type": "integer_or_auto", "description": "The number of spaces to indent by, or `auto`
+//!          to keep each file's indentation", "default": 4, "deprecated_names": ["indent_by"]},
+//!         ...
+//!       ]
+//!     }
+//!
+//! An option of type `choice` also has its `choices`, and an option whose `default` is `null` has
+//! no default value (its description tells what leaving it unset means).
+
+use crate::{
+  config::{self, OptionSpec, SettingType, DEPRECATED, OPTIONS},
+  json::Json,
+  lint::rules::{Rule, RULES},
+};
+
+/// The width of the names in the human-readable list, before the descriptions.
+const NAME_WIDTH: usize = 26;
+
+/// Returns the list of the lint rules and the configuration options, for people.
+pub fn human() -> String {
+  let mut text = String::from(
+    "LINT RULES (set with `lint: {\"<rule>\": \"<level>\"}`; every rule is off by default):\n",
+  );
+  let indent = " ".repeat(4 + 8 + NAME_WIDTH);
+  for rule in RULES {
+    text.push_str(&format!(
+      "    {}  {:<width$}{}\n",
+      rule.code.id,
+      rule.name,
+      rule.code.title,
+      width = NAME_WIDTH
+    ));
+    let mut notes = Vec::new();
+    if let Some(option) = &rule.option {
+      notes.push(format!(
+        "option: {} [default: {}]",
+        option.description, option.default
+      ));
+    }
+    if rule.allowlist {
+      notes.push("takes an allowlist of paths".to_string());
+    }
+    if !notes.is_empty() {
+      text.push_str(&format!("{}{}\n", indent, notes.join("; ")));
+    }
+  }
+  text.push_str(&format!(
+    "\nOPTIONS (set in the configuration file, or with {}<OPTION>):\n",
+    config::ENV_PREFIX
+  ));
+  let indent = " ".repeat(4 + NAME_WIDTH);
+  for spec in OPTIONS {
+    text.push_str(&format!(
+      "    {:<width$}{}\n",
+      spec.name,
+      spec.description,
+      width = NAME_WIDTH
+    ));
+    let default = match spec.default {
+      Some(default) => format!(" [default: {}]", default),
+      None => String::new(),
+    };
+    text.push_str(&format!("{}{}{}\n", indent, spec.kind.describe(), default));
+    let deprecated = deprecated_names(spec);
+    if !deprecated.is_empty() {
+      text.push_str(&format!(
+        "{}deprecated names: {}\n",
+        indent,
+        deprecated.join(", ")
+      ));
+    }
+  }
+  text
+}
+
+/// Returns the list of the lint rules and the configuration options, as JSON.
+pub fn json() -> Json {
+  Json::Object(vec![
+    ("rules", Json::Array(RULES.iter().map(rule).collect())),
+    ("options", Json::Array(OPTIONS.iter().map(option).collect())),
+  ])
+}
+
+/// Returns the description of the lint `rule`, as JSON.
+fn rule(rule: &Rule) -> Json {
+  let option = match &rule.option {
+    Some(option) => Json::Object(vec![
+      ("description", Json::String(option.description.to_string())),
+      ("default", Json::String(option.default.to_string())),
+    ]),
+    None => Json::Null,
+  };
+  Json::Object(vec![
+    ("name", Json::String(rule.name.to_string())),
+    ("code", Json::String(rule.code.id.to_string())),
+    ("title", Json::String(rule.code.title.to_string())),
+    ("config_key", Json::String(format!("lint.{}", rule.name))),
+    ("default_level", Json::String("off".to_string())),
+    ("option", option),
+    ("allowlist", Json::Bool(rule.allowlist)),
+  ])
+}
+
+/// Returns the description of the configuration option `spec`, as JSON.
+fn option(spec: &OptionSpec) -> Json {
+  let mut properties = vec![
+    ("name", Json::String(spec.name.to_string())),
+    ("config_key", Json::String(spec.name.to_string())),
+    (
+      "env_var",
+      Json::String(format!(
+        "{}{}",
+        config::ENV_PREFIX,
+        spec.name.to_uppercase()
+      )),
+    ),
+    ("type", Json::String(type_name(spec.kind).to_string())),
+  ];
+  if let SettingType::Choice(choices) = spec.kind {
+    properties.push((
+      "choices",
+      Json::Array(
+        choices
+          .iter()
+          .map(|choice| Json::String(choice.to_string()))
+          .collect(),
+      ),
+    ));
+  }
+  properties.push(("description", Json::String(spec.description.to_string())));
+  properties.push(("default", spec.default.map_or(Json::Null, value)));
+  properties.push((
+    "deprecated_names",
+    Json::Array(
+      deprecated_names(spec)
+        .into_iter()
+        .map(|name| Json::String(name.to_string()))
+        .collect(),
+    ),
+  ));
+  Json::Object(properties)
+}
+
+/// Returns the names of the deprecated options that `spec` replaces.
+fn deprecated_names(spec: &OptionSpec) -> Vec<&'static str> {
+  DEPRECATED
+    .iter()
+    .filter(|deprecation| deprecation.replacement == spec.name)
+    .map(|deprecation| deprecation.name)
+    .collect()
+}
+
+/// Returns the identifier of the type `kind`, for JSON.
+fn type_name(kind: SettingType) -> &'static str {
+  match kind {
+    SettingType::Bool => "bool",
+    SettingType::Integer => "integer",
+    SettingType::Indent => "integer_or_auto",
+    SettingType::String => "string",
+    SettingType::Choice(_) => "choice",
+    SettingType::StringList => "string_list",
+    SettingType::Rules => "rules",
+    SettingType::Sections => "sections",
+    SettingType::Priorities => "priorities",
+    SettingType::PathRules => "path_rules",
+    SettingType::FileTypes => "file_types",
+    SettingType::LintOverrides => "lint_overrides",
+  }
+}
+
+/// Returns the default value `text` of an option (`true`, an integer, a double-quoted string, or
+/// an empty array or object) as JSON.
+fn value(text: &str) -> Json {
+  match text {
+    "true" => Json::Bool(true),
+    "false" => Json::Bool(false),
+    "[]" => Json::Array(Vec::new()),
+    "{}" => Json::Object(Vec::new()),
+    _ => match text.parse() {
+      Ok(number) => Json::Number(number),
+      Err(_) => Json::String(text.trim_matches('"').to_string()),
+    },
+  }
+}
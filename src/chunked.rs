@@ -0,0 +1,336 @@
+//! Parses and formats a very large document on several threads (with `--jobs`), by splitting its
+//! top-level object into chunks of properties, parsing and formatting each chunk as a document of
+//! its own, and joining the results, so a single enormous generated file does not keep the whole
+//! run on one core.
+//!
+//! A document is split only if it is at least [CHUNK_THRESHOLD](constant.CHUNK_THRESHOLD.html)
+//! bytes (or the threshold of its [Chunking](struct.Chunking.html)) and its top-level value is an
+//! object, and it is formatted in chunks only if no path option applies to that object itself (the
+//! path `/*`): the order of its properties, and whether it is collapsed or has a trailing comma,
+//! would otherwise be decided for each chunk on its own. The object is split only after a property
+//! that ends its line,
+//! so each comment stays in the chunk of the property it belongs to, and each chunk holds at least
+//! two properties. The joined output is the same as the output of formatting the document as a
+//! whole. If a chunk cannot be parsed, the document is parsed as a whole, so the error is reported
+//! at its position in the document.
+
+use crate::{
+  format::FormatError,
+  lexer::{self, TokenKind},
+  parallel,
+};
+use json5format::{FormatOptions, Json5Format, ParsedDocument};
+use std::io::Write;
+
+/// The size, in bytes, of the smallest document that is split into chunks.
+pub const CHUNK_THRESHOLD: usize = 16 << 20;
+
+/// The property that starts each chunk but the first, and ends each chunk but the last, so the
+/// properties at the edges of each chunk are written as if the properties of the next and previous
+/// chunks were around them (with a comma, and with the blank lines the formatter keeps or inserts
+/// between properties). Its lines are removed from the output.
+const SENTINEL: &str = "\"jsonpretty5er: end of chunk\"";
+
+/// When and how finely a document is split into chunks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chunking {
+  /// The number of threads the chunks are parsed and formatted on, and the most chunks a document
+  /// is split into.
+  pub threads: usize,
+  /// The size, in bytes, of the smallest document that is split.
+  pub threshold: usize,
+}
+
+impl Chunking {
+  /// Returns the chunking of documents of at least [CHUNK_THRESHOLD](constant.CHUNK_THRESHOLD.html)
+  /// bytes on up to `threads` threads. Each chunk is parsed twice (once to check it, and once to
+  /// format it), so a document is only split into as many chunks as there are CPUs to format them
+  /// at once.
+  pub fn new(threads: usize) -> Self {
+    Chunking {
+      threads: threads.min(parallel::thread_count(0)),
+      threshold: CHUNK_THRESHOLD,
+    }
+  }
+}
+
+/// Returns the documents of the chunks of `source` (the JSON5 source of a document), split as
+/// `chunking` selects, or `None` if the document is not split (see the module documentation).
+pub fn chunks(source: &str, chunking: Chunking) -> Option<Vec<String>> {
+  if chunking.threads < 2 || source.len() < chunking.threshold || source.contains(SENTINEL) {
+    return None;
+  }
+  split(source, chunking.threads)
+}
+
+/// Returns true if each of the `chunks`, read from `filename`, can be parsed, parsing them on up
+/// to `threads` threads.
+pub fn parse(chunks: &[String], filename: &str, threads: usize) -> bool {
+  let mut parsed = true;
+  parallel::run(
+    chunks,
+    threads,
+    |chunk| ParsedDocument::from_string(chunk.clone(), Some(filename.to_string())).is_ok(),
+    |_, ok| parsed &= ok,
+  );
+  parsed
+}
+
+/// Writes the document of the `chunks`, read from `filename`, formatted with `options` on up to
+/// `threads` threads, to `writer`, one chunk at a time. Returns false (writing nothing) if a path
+/// option of `options` applies to its top-level object, so it must be formatted as a whole.
+pub fn format(
+  chunks: &[String],
+  options: &FormatOptions,
+  threads: usize,
+  filename: &str,
+  writer: &mut impl Write,
+) -> Result<bool, FormatError> {
+  // json5format's path of the top-level object is `/*`.
+  if options.options_by_path.contains_key("/*") {
+    return Ok(false);
+  }
+  let mut results = chunks.iter().map(|_| None).collect::<Vec<_>>();
+  parallel::run(
+    chunks,
+    threads,
+    |chunk| {
+      // A parsed document cannot be shared between threads, so each chunk is parsed again by the
+      // thread that formats it, with a formatter of its own.
+      let formatted = Json5Format::with_options(options.clone())
+        .and_then(|format| {
+          let parsed = ParsedDocument::from_string(chunk.clone(), Some(filename.to_string()))?;
+          format.to_utf8(&parsed)
+        })
+        .map_err(|err| FormatError::json5format(err, filename))?;
+      String::from_utf8(formatted).map_err(|err| FormatError::Format {
+        file: filename.to_string(),
+        message: err.to_string(),
+      })
+    },
+    |index, result: Result<String, FormatError>| results[index] = Some(result),
+  );
+
+  let last = chunks.len() - 1;
+  for (index, result) in results.into_iter().enumerate() {
+    let formatted = result.expect("every chunk is formatted")?;
+    let lost = || FormatError::Format {
+      file: filename.to_string(),
+      message: "A chunk lost its sentinel".to_string(),
+    };
+    // Each chunk after the first drops its `{` line and its starting sentinel, and each chunk
+    // before the last drops its ending sentinel and its `}` line.
+    let start = match index {
+      0 => 0,
+      _ => formatted
+        .find(SENTINEL)
+        .and_then(|at| formatted[at..].find('\n').map(|newline| at + newline + 1))
+        .ok_or_else(lost)?,
+    };
+    let end = match index == last {
+      true => formatted.len(),
+      false => formatted
+        .rfind(SENTINEL)
+        .map(|at| formatted[..at].rfind('\n').map_or(0, |newline| newline + 1))
+        .ok_or_else(lost)?,
+    };
+    writer
+      .write_all(&formatted.as_bytes()[start..end])
+      .map_err(|error| FormatError::Io {
+        file: filename.to_string(),
+        error,
+      })?;
+  }
+  Ok(true)
+}
+
+/// Splits the top-level object of `source` into up to `count` chunks of about the same size, and
+/// returns the document of each chunk, or `None` if `source` holds no object that can be split.
+fn split(source: &str, count: usize) -> Option<Vec<String>> {
+  let tokens = lexer::tokenize(source);
+  let open = tokens.iter().position(|token| !token.is_trivia())?;
+  if tokens[open].kind != TokenKind::OpenBrace {
+    return None;
+  }
+
+  // The offsets after each line break that ends a line ending with a top-level comma, with the
+  // number of top-level commas up to that line.
+  let mut candidates = Vec::new();
+  let mut depth = 0;
+  let mut commas = 0;
+  let mut close = None;
+  for (index, token) in tokens.iter().enumerate().skip(open) {
+    match token.kind {
+      TokenKind::OpenBrace | TokenKind::OpenBracket => depth += 1,
+      TokenKind::CloseBrace | TokenKind::CloseBracket => {
+        depth -= 1;
+        if depth == 0 {
+          close = Some(token.start);
+          break;
+        }
+      }
+      TokenKind::Comma if depth == 1 => {
+        commas += 1;
+        // The first token after the comma that is not a space or a comment on the same line.
+        let rest = tokens[index + 1..].iter().find(|token| match token.kind {
+          TokenKind::Whitespace | TokenKind::LineComment => false,
+          TokenKind::BlockComment => source[token.start..token.end].contains('\n'),
+          _ => true,
+        });
+        if let Some(newline) = rest.filter(|token| token.kind == TokenKind::Newline) {
+          candidates.push((newline.end, commas));
+        }
+      }
+      _ => {}
+    }
+  }
+  let (start, end) = (tokens[open].start, close?);
+
+  let target = (end - start) / count;
+  let mut boundaries = Vec::new();
+  let (mut last_offset, mut last_commas) = (start, 0);
+  for (offset, commas_before) in candidates {
+    // Each chunk holds at least two properties, so none is collapsed onto one line.
+    if offset - last_offset >= target
+      && commas_before - last_commas >= 2
+      && commas - commas_before >= 2
+      && boundaries.len() + 1 < count
+    {
+      boundaries.push(offset);
+      (last_offset, last_commas) = (offset, commas_before);
+    }
+  }
+  if boundaries.is_empty() {
+    return None;
+  }
+
+  let mut chunks = Vec::new();
+  let mut from = 0;
+  for boundary in boundaries.into_iter().chain([source.len()]) {
+    let mut text = match from {
+      0 => String::new(),
+      _ => format!("{{\n{}: 0,\n", SENTINEL),
+    };
+    text.push_str(&source[from..boundary]);
+    if boundary < source.len() {
+      text.push_str(&format!("{}: 0\n}}\n", SENTINEL));
+    }
+    chunks.push(text);
+    from = boundary;
+  }
+  Some(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use json5format::PathOption;
+  use std::collections::HashSet;
+
+  /// Splits documents of any size into up to four chunks.
+  const CHUNKING: Chunking = Chunking {
+    threads: 4,
+    threshold: 0,
+  };
+
+  /// Returns a document of many properties, with comments before, after, and between them, so
+  /// there are comments at the edges of each chunk.
+  fn document() -> String {
+    let mut source = String::from("// The document.\n{\n");
+    for index in 0..60 {
+      match index % 4 {
+        0 => source.push_str(&format!("  // Before {}.\n", index)),
+        1 => source.push_str(&format!("  /* A block\n     before {}. */\n", index)),
+        2 => source.push('\n'),
+        _ => {}
+      }
+      source.push_str(&format!("  p{}: [{}, {{a: 1}}],", index, index));
+      match index % 3 {
+        0 => source.push_str(&format!(" // After {}.", index)),
+        1 => source.push_str(" /* After. */"),
+        _ => {}
+      }
+      source.push('\n');
+    }
+    source.push_str("  // The end.\n}\n// After the document.\n");
+    source
+  }
+
+  /// Returns `source` formatted with `options` as a whole.
+  fn format_whole(source: &str, options: &FormatOptions) -> Vec<u8> {
+    let parsed = ParsedDocument::from_string(source.to_string(), None).unwrap();
+    let format = Json5Format::with_options(options.clone()).unwrap();
+    format.to_utf8(&parsed).unwrap()
+  }
+
+  #[test]
+  fn formats_chunks_as_the_whole_document() {
+    let source = document();
+    let chunks = chunks(&source, CHUNKING).unwrap();
+    assert_eq!(chunks.len(), 4);
+    assert!(parse(&chunks, "a.json5", 2));
+    for options in [
+      FormatOptions::default(),
+      FormatOptions {
+        indent_by: 2,
+        trailing_commas: false,
+        collapse_containers_of_one: true,
+        ..FormatOptions::default()
+      },
+    ] {
+      let mut formatted = Vec::new();
+      assert!(format(&chunks, &options, 2, "a.json5", &mut formatted).unwrap());
+      assert_eq!(
+        String::from_utf8(formatted).unwrap(),
+        String::from_utf8(format_whole(&source, &options)).unwrap()
+      );
+    }
+  }
+
+  #[test]
+  fn splits_only_large_objects() {
+    let source = document();
+    let threshold = source.len() + 1;
+    assert_eq!(
+      chunks(
+        &source,
+        Chunking {
+          threshold,
+          ..CHUNKING
+        }
+      ),
+      None
+    );
+    let threads = 1;
+    assert_eq!(
+      chunks(
+        &source,
+        Chunking {
+          threads,
+          ..CHUNKING
+        }
+      ),
+      None
+    );
+    assert_eq!(chunks("[1, 2, 3, 4, 5, 6]", CHUNKING), None);
+    // Each chunk holds at least two properties.
+    assert_eq!(chunks("{\n  a: 1,\n  b: 2,\n  c: 3,\n}\n", CHUNKING), None);
+  }
+
+  #[test]
+  fn formats_objects_with_options_of_their_own_as_a_whole() {
+    let chunks = chunks(&document(), CHUNKING).unwrap();
+    let options = FormatOptions {
+      options_by_path: [(
+        "/*",
+        HashSet::from([PathOption::PropertyNameOrder(vec!["p1"])]),
+      )]
+      .into_iter()
+      .collect(),
+      ..FormatOptions::default()
+    };
+    let mut formatted = Vec::new();
+    assert!(!format(&chunks, &options, 2, "a.json5", &mut formatted).unwrap());
+    assert!(formatted.is_empty());
+  }
+}
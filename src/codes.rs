@@ -0,0 +1,384 @@
+//! The stable codes of the problems the formatter reports (such as `J5F003`), and their
+//! explanations, which `formatjson5 explain <code>` prints.
+//!
+//! Codes are never reused or renumbered, so they can be searched for and referred to in
+//! documentation; a code whose check is removed is kept, with an explanation saying so.
+
+/// A kind of problem the formatter reports.
+#[derive(Debug)]
+pub struct Code {
+  /// The stable code, such as `J5F003`.
+  pub id: &'static str,
+  /// A short name of the problem, such as `unknown-key`. For a lint rule, this is the rule name.
+  pub name: &'static str,
+  /// A one-line summary of the problem.
+  pub title: &'static str,
+  /// The detailed description of the problem, with examples and how to configure or silence it.
+  pub explanation: &'static str,
+}
+
+/// A document that is not valid JSON5.
+pub const PARSE_ERROR: Code = Code {
+  id: "J5F001",
+  name: "parse-error",
+  title: "The document is not valid JSON5",
+  explanation: "\
+The document could not be parsed as JSON5, so it cannot be formatted. The message points at the
+first character that does not fit the JSON5 grammar, such as a missing comma or value:
+
+    {a: 1 b: 2}     // a comma is missing after `1`
+    {a: }           // `a` has no value
+
+This error cannot be silenced. `formatjson5 fix` repairs some common mistakes (stray or missing
+commas, and with `--fix-level all`, unterminated strings and unclosed objects and arrays).",
+};
+
+/// Content after the end of the document's value.
+pub const TRAILING_CONTENT: Code = Code {
+  id: "J5F002",
+  name: "trailing-content",
+  title: "Content after the end of the document",
+  explanation: "\
+The document holds more than one value, or other content after its value:
+
+    {a: 1}
+    {b: 2}          // a second document
+
+Only whitespace and comments may follow the value. Use `--allow-trailing-garbage` (or
+`allow_trailing_garbage: true` in the configuration file) to keep the trailing content verbatim.",
+};
+
+/// A property name similar to, but not the same as, a known name.
+pub const UNKNOWN_KEY: Code = Code {
+  id: "J5F003",
+  name: "unknown-key",
+  title: "Property name is similar to a known name",
+  explanation: "\
+With `--known-keys <file>`, each property name that is not in the file, but is similar to a name
+in it, is reported as a likely misspelling:
+
+    {colour: \"red\"}   // Unknown key `colour` (did you mean `color`?)
+
+Names that are not similar to any known name are not reported. Add the name to the known keys
+file if it is intended.",
+};
+
+/// A property name that does not follow `--key-case`.
+pub const KEY_CASE: Code = Code {
+  id: "J5F004",
+  name: "key-case",
+  title: "Property name does not follow --key-case",
+  explanation: "\
+With `--key-case camel`, `snake`, or `kebab`, each property name that does not follow the naming
+convention is reported:
+
+    {max_size: 1}   // with --key-case camel: expected `maxSize`
+
+Use `--fix` to rename the properties instead, or `--key-case-exclude <name>` to exempt a name that
+is defined by an external format.",
+};
+
+/// A property that `--fix` cannot rename to `--key-case`.
+pub const KEY_CASE_CONFLICT: Code = Code {
+  id: "J5F005",
+  name: "key-case-conflict",
+  title: "Property cannot be renamed to --key-case",
+  explanation: "\
+With `--key-case` and `--fix`, a property is not renamed if another property of the same object
+has, or would be renamed to, the same name, since that would change the document's meaning:
+
+    {max_size: 1, maxSize: 2}   // with --key-case camel
+
+Rename or remove one of the properties by hand, or exempt the name with `--key-case-exclude`.",
+};
+
+/// `Infinity` or `NaN`, with `--forbid-non-finite`.
+pub const NON_FINITE: Code = Code {
+  id: "J5F006",
+  name: "non-finite",
+  title: "`Infinity` or `NaN` is not allowed",
+  explanation: "\
+With `--forbid-non-finite`, the numbers `Infinity`, `-Infinity`, and `NaN` (which JSON5 allows,
+but JSON and most JSON parsers do not) are errors:
+
+    {timeout: Infinity}
+
+Use a finite number, or `null`, instead.",
+};
+
+/// A JSON5 extension that the dialect does not allow.
+pub const DIALECT: Code = Code {
+  id: "J5F007",
+  name: "dialect",
+  title: "Construct not allowed in the dialect",
+  explanation: "\
+With `--dialect jsonc` or `--dialect json` (or `--input-format json`), the document may only use
+the extensions of that dialect: JSONC allows comments and trailing commas, and JSON allows none.
+Each other JSON5 extension is an error:
+
+    {name: 'x'}     // with --dialect json: an unquoted property name and a single-quoted string
+
+Rewrite the construct in the dialect's syntax, or use a dialect that allows it.",
+};
+
+/// An object with no properties (the `no-empty-object` lint rule).
+pub const NO_EMPTY_OBJECT: Code = Code {
+  id: "J5F008",
+  name: "no-empty-object",
+  title: "Empty object",
+  explanation: "\
+With the lint rule `no-empty-object` enabled, each object with no properties is reported:
+
+    {options: {}}
+
+Enable or disable the rule in the configuration file, with `lint: {\"no-empty-object\": \"warn\"}`
+(or `\"error\"`, or `\"off\"`).",
+};
+
+/// An array item equal to an earlier item (the `no-duplicate-array-items` lint rule).
+pub const NO_DUPLICATE_ARRAY_ITEMS: Code = Code {
+  id: "J5F009",
+  name: "no-duplicate-array-items",
+  title: "Duplicate array item",
+  explanation: "\
+With the lint rule `no-duplicate-array-items` enabled, each array item that is equal to an earlier
+item of the same array is reported. Whitespace, comments, and how strings are quoted are ignored:
+
+    {tags: [\"a\", 'b', 'a']}     // the third item duplicates the first
+
+Enable or disable the rule in the configuration file, with
+`lint: {\"no-duplicate-array-items\": \"warn\"}` (or `\"error\"`, or `\"off\"`).",
+};
+
+/// Objects and arrays nested too deeply (the `max-depth` lint rule).
+pub const MAX_DEPTH: Code = Code {
+  id: "J5F010",
+  name: "max-depth",
+  title: "Nesting too deep",
+  explanation: "\
+With the lint rule `max-depth` enabled, each object or array nested more deeply than the rule's
+option (by default, 10) is reported, where the top-level value is at depth 1. Only the outermost
+container of each too-deep part of the document is reported:
+
+    {a: {b: {c: 1}}}    // with max-depth 2, the object at `b` is at depth 3
+
+Configure the rule and its limit in the configuration file, with
+`lint: {\"max-depth\": [\"warn\", 8]}`, or disable it with `\"off\"`.",
+};
+
+/// A property name that does not follow the convention (the `key-naming-convention` lint rule).
+pub const KEY_NAMING_CONVENTION: Code = Code {
+  id: "J5F011",
+  name: "key-naming-convention",
+  title: "Property name does not follow the naming convention",
+  explanation: "\
+With the lint rule `key-naming-convention` enabled, each property name that does not follow the
+rule's option (`camelCase`, the default, or `snake_case`) is reported:
+
+    {max_size: 1}       // not camelCase
+
+Configure the rule in the configuration file, with
+`lint: {\"key-naming-convention\": [\"warn\", \"snake_case\"]}`, or disable it with `\"off\"`. To
+rename the properties automatically, use `--key-case` with `--fix` instead.",
+};
+
+/// An object whose properties are not sorted by name (the `keys-sorted` lint rule).
+pub const KEYS_SORTED: Code = Code {
+  id: "J5F012",
+  name: "keys-sorted",
+  title: "Properties are not sorted by name",
+  explanation: "\
+With the lint rule `keys-sorted` enabled, each object whose property names are not in order is
+reported, at the first property that comes before the one preceding it. The properties are not
+reordered, so the rule can be enabled before `sort_keys` to sort files gradually:
+
+    {name: \"x\", id: 1}    // `id` should come before `name`
+
+The rule's option selects the order: `bytes` (the default, which is the order `sort_keys` uses),
+`case-insensitive`, or `natural` (which compares runs of digits as numbers, so `item2` comes
+before `item10`). Configure the rule in the configuration file, with
+`lint: {\"keys-sorted\": [\"warn\", \"natural\"]}`, or disable it with `\"off\"`.",
+};
+
+/// A value too long for the maximum line width, with `--long-value warn`.
+pub const LONG_VALUE: Code = Code {
+  id: "J5F013",
+  name: "long-value",
+  title: "Value is too long for the maximum line width",
+  explanation: "\
+With `--long-value warn`, each value (usually a long string or URL) that makes its formatted line
+wider than `--max-width` (by default, 100 characters) is reported, since the formatter cannot break
+it:
+
+    {homepage: \"https://example.com/a/very/long/path/...\"}
+
+Shorten the value, raise `--max-width`, or use `--long-value wrap-member` to start the values of
+properties on the line after their names. Silence a value that must stay long with a
+`// json5format-allow: long-value` comment on the line before it.",
+};
+
+/// A top-level value of the wrong type (the `require-root` lint rule).
+pub const REQUIRE_ROOT: Code = Code {
+  id: "J5F014",
+  name: "require-root",
+  title: "Top-level value has the wrong type",
+  explanation: "\
+With the lint rule `require-root` enabled, a document whose top-level value is not of the type
+given by the rule's option is reported. The option is `object` (the default), `array`, or `any`,
+which accepts every document:
+
+    [\"a\", \"b\"]    // with require-root object, the top-level value is an array
+
+Configure the rule in the configuration file, with `lint: {\"require-root\": \"error\"}`, or
+on the command line with `--require-root object`. Files that need another root are matched by
+path in `lint_overrides`, as in `lint_overrides: {\"*.list.json5\": {\"require-root\":
+[\"error\", \"array\"]}}`.",
+};
+
+/// A property name that is too long (the `max-key-length` lint rule).
+pub const MAX_KEY_LENGTH: Code = Code {
+  id: "J5F015",
+  name: "max-key-length",
+  title: "Property name too long",
+  explanation: "\
+With the lint rule `max-key-length` enabled, each property name longer than the rule's option (by
+default, 64 bytes of UTF-8) is reported, for programs that only store names up to a fixed length:
+
+    {a_very_long_property_name_that_is_cut_off: 1}    // with max-key-length 16
+
+Configure the rule and its limit in the configuration file, with
+`lint: {\"max-key-length\": [\"error\", 32]}`, and exempt properties by path with an
+allowlist of patterns after the limit, as in `[\"error\", 32, [\"/generated/*\"]]`.",
+};
+
+/// A string value that is too long (the `max-string-length` lint rule).
+pub const MAX_STRING_LENGTH: Code = Code {
+  id: "J5F016",
+  name: "max-string-length",
+  title: "String too long",
+  explanation: "\
+With the lint rule `max-string-length` enabled, each string value longer than the rule's option
+(by default, 255 bytes of UTF-8) is reported, for programs that silently cut off long strings:
+
+    {greeting: \"Hello, world!\"}    // with max-string-length 8
+
+Configure the rule and its limit in the configuration file, with
+`lint: {\"max-string-length\": [\"error\", 128]}`, and exempt values by path with an
+allowlist of patterns after the limit, as in `[\"error\", 128, [\"/description\"]]`.",
+};
+
+/// An array whose order looks significant, kept in source order instead of sorted.
+pub const ORDER_SENSITIVE_ARRAY: Code = Code {
+  id: "J5F017",
+  name: "order-sensitive-array",
+  title: "Array order looks significant",
+  explanation: "\
+With `--sort-arrays` and `--sort-warn-heuristics`, each array that sorting would reorder, but
+whose order looks significant, is kept in source order and reported:
+
+    {args: [\"--jobs\", 4]}                 // mixed types
+    {servers: [\"primary.example.com\", \"fallback.example.com\"]}
+
+An array looks order-sensitive if its items are of more than one type, if a string item holds a
+word about order (such as `first`, `fallback`, or `then`), or if its property name is about order
+(such as `args` or `steps`). To sort the array anyway, set `sort_arrays: true` for its path in the
+`paths` option; to keep it in order without the warning, set `sort_arrays: false`.",
+};
+
+/// A string at a path given to `--format-embedded` that is not a JSON5 document.
+pub const EMBEDDED_DOCUMENT: Code = Code {
+  id: "J5F018",
+  name: "embedded-document",
+  title: "Embedded document is not valid JSON5",
+  explanation: "\
+With `--format-embedded <path-glob>`, each string value whose path matches the pattern is parsed
+as a JSON5 document and formatted. A matching string that cannot be parsed is left unchanged and
+reported:
+
+    {policy: \"{allow: [read}\"}   // with --format-embedded /policy
+
+Fix the embedded document, or narrow the pattern so it matches only strings that hold documents.",
+};
+
+/// Unresolved merge conflicts in a file.
+pub const MERGE_CONFLICT: Code = Code {
+  id: "J5F019",
+  name: "merge-conflict",
+  title: "The file contains unresolved merge conflicts",
+  explanation: "\
+The file holds the conflict markers that git leaves where a merge could not combine two changes,
+so it is not formatted:
+
+    <<<<<<< HEAD
+        timeout: 30,
+    =======
+        timeout: 60,
+    >>>>>>> feature
+
+Resolve each conflict and remove its markers, or use `--take ours` or `--take theirs` to keep one
+side of every conflict (the lines before or after the `=======` marker) and format the result.
+With `formatjson5 merge-driver` as git's merge driver, changes to different properties do not
+conflict in the first place.",
+};
+
+/// An integer that a double cannot hold exactly.
+pub const NO_UNSAFE_INTEGER: Code = Code {
+  id: "J5F020",
+  name: "no-unsafe-integer",
+  title: "Integer outside the range a double holds exactly",
+  explanation: "\
+With the lint rule `no-unsafe-integer` enabled, each integer larger in magnitude than 2^53
+(9007199254740992) is reported. Many JSON parsers (including JavaScript's `JSON.parse`) read every
+number as a double, which holds integers exactly only up to 2^53, so they silently round larger
+ones:
+
+    {id: 12345678901234567890}     // read as 12345678901234567000
+
+Write such values as strings, as in `{id: \"12345678901234567890\"}`. Configure the rule in the
+configuration file, with `lint: {\"no-unsafe-integer\": \"error\"}`, and exempt the values at
+paths whose consumers read big integers exactly with an allowlist of patterns, as in
+`[\"error\", [\"/ledger/**\"]]`.",
+};
+
+/// Every code, in order.
+pub const CODES: &[&Code] = &[
+  &PARSE_ERROR,
+  &TRAILING_CONTENT,
+  &UNKNOWN_KEY,
+  &KEY_CASE,
+  &KEY_CASE_CONFLICT,
+  &NON_FINITE,
+  &DIALECT,
+  &NO_EMPTY_OBJECT,
+  &NO_DUPLICATE_ARRAY_ITEMS,
+  &MAX_DEPTH,
+  &KEY_NAMING_CONVENTION,
+  &KEYS_SORTED,
+  &LONG_VALUE,
+  &REQUIRE_ROOT,
+  &MAX_KEY_LENGTH,
+  &MAX_STRING_LENGTH,
+  &ORDER_SENSITIVE_ARRAY,
+  &EMBEDDED_DOCUMENT,
+  &MERGE_CONFLICT,
+  &NO_UNSAFE_INTEGER,
+];
+
+/// Returns the code with the given `id` (ignoring case) or name, or an error message suggesting the
+/// most similar code, if any.
+pub fn find(id: &str) -> Result<&'static Code, String> {
+  CODES
+    .iter()
+    .copied()
+    .find(|code| code.id.eq_ignore_ascii_case(id) || code.name == id)
+    .ok_or_else(|| {
+      let names = CODES.iter().map(|code| code.name);
+      let ids = CODES.iter().map(|code| code.id);
+      match crate::suggest::closest(id, names)
+        .or_else(|| crate::suggest::closest(&id.to_ascii_uppercase(), ids))
+      {
+        Some(suggestion) => format!("Unknown code `{}` (did you mean `{}`?)", id, suggestion),
+        None => format!("Unknown code `{}`", id),
+      }
+    })
+}
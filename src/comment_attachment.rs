@@ -0,0 +1,395 @@
+//! Decides which item a comment on a line of its own belongs to (the `comment_attachment` option,
+//! and `--comment-attachment`), which is the item it moves with when sorting reorders the
+//! properties of an object or the items of an array:
+//!
+//!     {
+//!         timeout: 30,
+//!         // The timeout is in seconds.
+//!
+//!         // Retries after the first attempt.
+//!         retries: 3,
+//!     }
+//!
+//! - `next` (the default) attaches each comment to the item after it, as json5format does. The
+//!   comments after the last item stay at the end of the object or array.
+//! - `previous` attaches each comment to the item before it, so both comments above move with
+//!   `timeout`. The comments before the first item attach to it.
+//! - `blank-line-aware` attaches a comment to the item it is not separated from by a blank line: a
+//!   comment directly below an item, with a blank line after it, moves with that item (as the
+//!   first comment above moves with `timeout`), and any other comment attaches to the next item
+//!   (as the second comment moves with `retries`). A blank line before the closing bracket does
+//!   not count, so the comments after the last item stay at the end of the object or array.
+//!
+//! json5format moves only the comments before an item with it, so a comment that attaches to the
+//! previous item is moved before that item (between two marker comments) for json5format, and
+//! moved back below it, wherever it was sorted to, after formatting. Comments at the ends of lines
+//! always stay with the value they follow.
+
+use crate::{
+  edit::Edits,
+  format::FormatError,
+  lexer::{self, TokenKind},
+  syntax::{self, Comments, Node, Span},
+};
+use std::{collections::HashMap, str::FromStr};
+
+/// The text shared by the marker comments, which a document must not contain already.
+const MARKER: &str = "json5format-internal: attached comments";
+
+/// The comment before the comments attached to the previous item.
+const BEGIN: &str = "// json5format-internal: attached comments begin";
+
+/// The comment after the comments attached to the previous item.
+const END: &str = "// json5format-internal: attached comments end";
+
+/// The end of a `BEGIN` comment if a blank line separates the attached comments from the item
+/// before them, or of an `END` comment if a blank line follows them. json5format adds blank lines
+/// of its own around the block comments between the markers, so the blank lines around the
+/// attached comments are kept in the markers instead.
+const BLANK: &str = ", with a blank line";
+
+/// Which item a comment on a line of its own belongs to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CommentAttachment {
+  /// The item after the comment.
+  #[default]
+  Next,
+  /// The item before the comment.
+  Previous,
+  /// The item before the comment, if only the comment's next item is separated from it by a
+  /// blank line, and otherwise the item after it.
+  BlankLineAware,
+}
+
+impl CommentAttachment {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["next", "previous", "blank-line-aware"];
+
+  /// Returns the spelling of the attachment, as in the configuration file.
+  pub fn name(self) -> &'static str {
+    match self {
+      CommentAttachment::Next => "next",
+      CommentAttachment::Previous => "previous",
+      CommentAttachment::BlankLineAware => "blank-line-aware",
+    }
+  }
+}
+
+impl FromStr for CommentAttachment {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "next" => Ok(CommentAttachment::Next),
+      "previous" => Ok(CommentAttachment::Previous),
+      "blank-line-aware" => Ok(CommentAttachment::BlankLineAware),
+      _ => Err(FormatError::Config {
+        message: format!(
+          "Expected one of: {}",
+          CommentAttachment::VARIANTS.join(", ")
+        ),
+      }),
+    }
+  }
+}
+
+/// Returns the document `source` with each comment that `attachment` attaches to the item before
+/// it moved before that item, between marker comments, or `None` if no comment moves (or the
+/// document cannot be parsed, or already holds a marker comment).
+pub fn mark(source: &str, attachment: CommentAttachment) -> Option<String> {
+  if attachment == CommentAttachment::Next || source.contains(MARKER) {
+    return None;
+  }
+  let root = syntax::parse(source).ok()??;
+  let comments = Comments::new(source);
+  let mut edits = Edits::default();
+  let mut pending = vec![&root];
+  while let Some(node) = pending.pop() {
+    // The start and end of each item (the start of a property is that of its name).
+    let (elements, close) = match node {
+      Node::Object { span, members } => {
+        pending.extend(members.iter().map(|member| &member.value));
+        let elements = members
+          .iter()
+          .map(|member| (member.key.start, member.value.span().end))
+          .collect::<Vec<_>>();
+        (elements, span.end)
+      }
+      Node::Array { span, items } => {
+        pending.extend(items);
+        let elements = items
+          .iter()
+          .map(|item| (item.span().start, item.span().end))
+          .collect::<Vec<_>>();
+        (elements, span.end)
+      }
+      Node::String { .. } | Node::Literal { .. } => continue,
+    };
+    // The comments after each item, and the number of them attached to it.
+    let gaps = (0..elements.len())
+      .map(|index| {
+        let gap = match elements.get(index + 1) {
+          Some(&(next, _)) => comments.leading(next),
+          None => comments.closing(close),
+        };
+        let closing = index + 1 == elements.len();
+        (gap, attached(source, gap, closing, attachment))
+      })
+      .collect::<Vec<_>>();
+    for (index, &(start, _)) in elements.iter().enumerate() {
+      let (gap, count) = gaps[index];
+      if count == 0 {
+        continue;
+      }
+      let attached = &gap[..count];
+      // The comments before the item that stay with it (those not attached to the item before).
+      let own = match index {
+        0 => comments.leading(start),
+        _ => &gaps[index - 1].0[gaps[index - 1].1..],
+      };
+      let first = attached[0].start;
+      let last = attached[count - 1].end;
+      // From the end of the item (or of its comma, or of a comment at the end of its line).
+      let cut = source[..first].trim_end().len();
+      let before = own.first().map_or(start, |comment| comment.start);
+      // The blank lines before and after the comments move with them.
+      let following = source.len() - source[last..].trim_start().len();
+      let marker = |marker: &str, blank: bool| match blank {
+        true => format!("{}{}", marker, BLANK),
+        false => marker.to_string(),
+      };
+      let begin = marker(BEGIN, source[cut..first].matches('\n').count() > 1);
+      let end = marker(END, source[last..following].matches('\n').count() > 1);
+      // From the start of the first comment's line, so it keeps its indentation.
+      let from = source[cut..first]
+        .rfind('\n')
+        .map_or(first, |newline| cut + newline + 1);
+      edits.insert(
+        before,
+        format!("\n{}\n{}\n{}\n", begin, &source[from..last], end),
+      );
+      edits.replace(cut..last, "");
+    }
+  }
+  (!edits.is_empty()).then(|| edits.apply(source))
+}
+
+/// Returns the number of the comments in `gap`, the comments on lines of their own after an item
+/// (the last item, if `closing`), that `attachment` attaches to that item.
+fn attached(source: &str, gap: &[Span], closing: bool, attachment: CommentAttachment) -> usize {
+  let Some(first) = gap.first() else {
+    return 0;
+  };
+  let blank = |start: usize, end: usize| source[start..end].matches('\n').count() > 1;
+  let mut count = match attachment {
+    CommentAttachment::Next => 0,
+    CommentAttachment::Previous => gap.len(),
+    CommentAttachment::BlankLineAware => {
+      let item_end = source[..first.start].trim_end().len();
+      let paragraph = 1
+        + gap
+          .windows(2)
+          .take_while(|pair| !blank(pair[0].end, pair[1].start))
+          .count();
+      let last = gap[paragraph - 1].end;
+      let next = gap.get(paragraph).map_or_else(
+        || source.len() - source[last..].trim_start().len(),
+        |comment| comment.start,
+      );
+      // json5format removes a blank line before the closing bracket, so it does not separate the
+      // comments from the end of the object or array.
+      let separated = blank(last, next) && !(closing && paragraph == gap.len());
+      match !blank(item_end, first.start) && separated {
+        true => paragraph,
+        false => 0,
+      }
+    }
+  };
+  // A comment followed by something else on its line stays with it.
+  while count > 0 && !line_rest_is_blank(source, gap[count - 1].end) {
+    count -= 1;
+  }
+  count
+}
+
+/// Returns true if nothing but whitespace follows the byte `offset` of `text` on its line.
+fn line_rest_is_blank(text: &str, offset: usize) -> bool {
+  let rest = &text[offset..];
+  rest[..rest.find('\n').unwrap_or(rest.len())]
+    .trim()
+    .is_empty()
+}
+
+/// Returns the formatted document `formatted` with the comments between each pair of marker
+/// comments moved below the item after the markers, and the markers removed.
+pub fn restore(formatted: &str) -> String {
+  if !formatted.contains(MARKER) {
+    return formatted.to_string();
+  }
+  let Ok(Some(root)) = syntax::parse(formatted) else {
+    return formatted.to_string();
+  };
+  // The end of each item, by the start of the item (or of the name of a property).
+  let mut ends = HashMap::new();
+  let mut pending = vec![&root];
+  while let Some(node) = pending.pop() {
+    match node {
+      Node::Object { members, .. } => {
+        for member in members {
+          ends.insert(member.key.start, member.value.span().end);
+          pending.push(&member.value);
+        }
+      }
+      Node::Array { items, .. } => {
+        for item in items {
+          ends.insert(item.span().start, item.span().end);
+          pending.push(item);
+        }
+      }
+      Node::String { .. } | Node::Literal { .. } => {}
+    }
+  }
+  let tokens = lexer::tokenize(formatted);
+  let text = |index: usize| &formatted[tokens[index].start..tokens[index].end];
+  // The indexes of each pair of markers, the end of the item after them, and the start of the
+  // text to remove with them.
+  let mut groups = Vec::new();
+  let mut index = 0;
+  while index < tokens.len() {
+    if tokens[index].kind != TokenKind::LineComment || !text(index).starts_with(BEGIN) {
+      index += 1;
+      continue;
+    }
+    let begin = index;
+    let Some(end) = (begin..tokens.len()).find(|&index| text(index).starts_with(END)) else {
+      break;
+    };
+    index = end + 1;
+    let Some(item) = tokens[index..].iter().find(|token| !token.is_trivia()) else {
+      break;
+    };
+    let Some(&item_end) = ends.get(&item.start) else {
+      continue;
+    };
+    let own_comments = tokens[index..]
+      .iter()
+      .find(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Newline))
+      .is_some_and(|token| token.start != item.start);
+    let mut remove_start = line_start(formatted, tokens[begin].start);
+    // json5format separates the comments before an item from the item before with a blank line.
+    if !own_comments && formatted[..remove_start].ends_with("\n\n") {
+      remove_start -= 1;
+    }
+    groups.push((begin, end, item_end, remove_start));
+  }
+  let mut edits = Edits::default();
+  for &(begin, end, item_end, remove_start) in &groups {
+    edits.replace(remove_start..line_end(formatted, tokens[end].end), "");
+    // The comments, without the blank lines before and after them.
+    let moved =
+      &formatted[line_end(formatted, tokens[begin].end)..line_start(formatted, tokens[end].start)];
+    let moved = moved.trim_start_matches(['\r', '\n']);
+    let moved = &moved[..moved.trim_end().len()];
+    let insert = line_end(formatted, item_end);
+    let next_line = formatted[insert..].lines().next().unwrap_or("").trim();
+    // A blank line after the item stays unless it is removed with the markers after it.
+    let blank_follows = next_line.is_empty()
+      && !groups
+        .iter()
+        .any(|&(_, _, _, remove_start)| remove_start == insert);
+    let blank_after =
+      text(end).ends_with(BLANK) && !(blank_follows || next_line.starts_with(['}', ']']));
+    let blank_before = match text(begin).ends_with(BLANK) {
+      true => "\n",
+      false => "",
+    };
+    let blank_after = match blank_after {
+      true => "\n",
+      false => "",
+    };
+    edits.insert(
+      insert,
+      format!("{}{}\n{}", blank_before, moved, blank_after),
+    );
+  }
+  edits.apply(formatted)
+}
+
+/// Returns the start of the line holding the byte `offset` of `text`.
+fn line_start(text: &str, offset: usize) -> usize {
+  text[..offset].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+/// Returns the start of the line after the one holding the byte `offset` of `text` (or the end of
+/// `text`).
+fn line_end(text: &str, offset: usize) -> usize {
+  text[offset..]
+    .find('\n')
+    .map_or(text.len(), |newline| offset + newline + 1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use json5format::{FormatOptions, Json5Format, ParsedDocument};
+
+  /// Returns `source` formatted by json5format with the comments attached by `attachment`.
+  fn formatted(source: &str, attachment: CommentAttachment) -> String {
+    let marked = mark(source, attachment);
+    let text = marked.clone().unwrap_or_else(|| source.to_string());
+    let parsed = ParsedDocument::from_string(text, None).unwrap();
+    let bytes = Json5Format::with_options(FormatOptions::default())
+      .unwrap()
+      .to_utf8(&parsed)
+      .unwrap();
+    let formatted = String::from_utf8(bytes).unwrap();
+    match marked {
+      Some(_) => restore(&formatted),
+      None => formatted,
+    }
+  }
+
+  /// Asserts that `source` is formatted as `expected` with `attachment`, and that formatting it
+  /// again does not change it.
+  fn assert_formats(source: &str, attachment: CommentAttachment, expected: &str) {
+    assert_eq!(formatted(source, attachment), expected);
+    assert_eq!(formatted(expected, attachment), expected);
+  }
+
+  #[test]
+  fn next_marks_nothing() {
+    assert_eq!(mark("{a: 1,\n// a\nb: 2}", CommentAttachment::Next), None);
+  }
+
+  #[test]
+  fn previous_keeps_comments_below_their_item() {
+    assert_formats(
+      "{\n  b: 1,\n  // about b\n  a: 2,\n  // about a\n}\n",
+      CommentAttachment::Previous,
+      "{\n    b: 1,\n    // about b\n    a: 2,\n    // about a\n}\n",
+    );
+  }
+
+  #[test]
+  fn blank_line_aware_keeps_paragraphs_with_their_item() {
+    assert_formats(
+      "{\n  b: 1,\n  // about b\n\n  // about a\n  a: 2,\n}\n",
+      CommentAttachment::BlankLineAware,
+      "{\n    b: 1,\n    // about b\n\n    // about a\n    a: 2,\n}\n",
+    );
+  }
+
+  #[test]
+  fn blank_line_aware_leaves_comments_before_the_closing_bracket() {
+    let source = "{\n  c: 3,\n  /* about c */\n\n}\n";
+    assert_eq!(mark(source, CommentAttachment::BlankLineAware), None);
+    let once = formatted(source, CommentAttachment::BlankLineAware);
+    assert_eq!(formatted(&once, CommentAttachment::BlankLineAware), once);
+  }
+
+  #[test]
+  fn does_not_mark_documents_with_markers() {
+    let source = format!("{{\n  a: 1,\n  {}\n  // about a\n}}\n", BEGIN);
+    assert_eq!(mark(&source, CommentAttachment::Previous), None);
+  }
+}
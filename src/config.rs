@@ -0,0 +1,1940 @@
+//! Loads formatting options from a JSON5 configuration file. The file holds a single object whose
+//! properties are named after the command line options, for example:
+//!
+//!     {
+//!         indent: 2,
+//!         trailing_commas: false,
+//!     }
+//!
+//! The `pre_format_plugins` and `post_format_plugins` options run external commands on each
+//! document; see the [plugin](../plugin/index.html) module for the protocol. The `lint` option
+//! enables lint rules, and `lint_overrides` sets them for the files at matching paths; see the
+//! [lint](../lint/index.html) module. The `sections` option groups
+//! properties under header comments; see the [sections](../sections/index.html) module. The
+//! `sort_priority` option moves chosen properties first in the objects at matching paths; see the
+//! [sorting](../sorting/index.html) module. The `paths` option sets options for the objects and
+//! arrays at matching paths; see the [path_options](../path_options/index.html) module. The
+//! `required_version` option (such as `">=0.3, <0.5"`) makes the formatter refuse to run if its
+//! version does not match; see the [version](../version/index.html) module. The
+//! `empty_container_comments` option formats the comments in empty objects and arrays; see the
+//! [empty](../empty/index.html) module, and the `eol_comments` option places the comments at the
+//! ends of lines; see the [eol_comments](../eol_comments/index.html) module, and the
+//! `comment_attachment` option chooses which item each comment moves with when items are sorted;
+//! see the [comment_attachment](../comment_attachment/index.html) module. The `max_width` and
+//! `long_value` options handle values too long for their lines; see the
+//! [long_values](../long_values/index.html) module, and the `array_wrap` option packs array items
+//! onto lines; see the [array_wrap](../array_wrap/index.html) module. The `quotes` option rewrites
+//! the quotes of strings; see the [quotes](../quotes/index.html) module. The `indent` option can
+//! be `auto`, which keeps the indentation of each file; see the
+//! [indentation](../indentation/index.html) module. The `style` option sets
+//! the options of a preset, such as `prettier`, as defaults; see the [style](../style/index.html)
+//! module. The `file_types` option sets default options for files by extension; see the
+//! [file_types](../file_types/index.html) module, and the `embedded` option formats the JSON5
+//! blocks inside files of other kinds; see the [islands](../islands/index.html) module. The
+//! `write_mode` option chooses how `--replace` writes files; see the
+//! [write_mode](../write_mode/index.html) module, and the `include` and `exclude` options select
+//! the files given on the command line that are formatted; see the
+//! [selection](../selection/index.html) module.
+//!
+//! A configuration file can extend a base configuration, overriding some of its options, so
+//! projects can share a style. The base is either a file relative to the configuration file (or a
+//! directory holding a `.json5fmt.json5` file), or the name of a package installed in a
+//! `node_modules` directory:
+//!
+//!     {
+//!         extends: "../base.json5fmt.json5", // or "@acme/json5-style"
+//!         indent: 2,
+//!     }
+//!
+//! The file is validated against the options in [OPTIONS](constant.OPTIONS.html): unknown options
+//! and values of the wrong type are errors (with a suggestion, if the name looks like a typo), and
+//! deprecated options are accepted with a warning.
+//!
+//! Options are layered from lowest to highest precedence: the configuration file (unless
+//! `--no-config` is given), `JSON5FMT_*` environment variables, `--options-json`, each
+//! `--config-override <option>=<value>`, the other command line flags, and then a directive in the
+//! first line of the file being formatted (after any preamble), which applies to that file only:
+//!
+//!     // json5format: indent=2 sort_keys=true
+
+use crate::{
+  array_wrap::ArrayWrap,
+  comment_attachment::CommentAttachment,
+  document::{error_context, SourceOptions},
+  edit::Edits,
+  empty::EmptyComments,
+  eol_comments::EolComments,
+  features::Dialect,
+  file_types::{self, FileType},
+  glob::PathGlob,
+  lint::{self, LintOverride, RuleConfig},
+  long_values::LongValues,
+  path_options::{self, PathRule},
+  paths,
+  quotes::Quotes,
+  sections::{self, Section},
+  sorting::{self, Priority},
+  style::Style,
+  suggest,
+  syntax::{self, Member, Node},
+  write_mode::WriteMode,
+};
+use json5format::FormatOptions;
+use std::{
+  collections::BTreeMap,
+  fs,
+  path::{Path, PathBuf},
+};
+
+/// The name of the configuration file, searched for in the current directory and its ancestors.
+pub const CONFIG_FILENAME: &str = ".json5fmt.json5";
+
+/// The prefix of environment variables that set options, such as `JSON5FMT_INDENT=2` for the
+/// `indent` option.
+pub const ENV_PREFIX: &str = "JSON5FMT_";
+
+/// The environment variable naming the configuration file to use, if `--config` is not given.
+pub const CONFIG_ENV_VAR: &str = "JSON5FMT_CONFIG";
+
+/// The marker that starts an options directive in a line comment, as in
+/// `// json5format: indent=2`.
+pub const DIRECTIVE_MARKER: &str = "json5format:";
+
+/// The name of the option that names a base configuration, which the configuration file overrides.
+pub const EXTENDS: &str = "extends";
+
+/// The type of value an option accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingType {
+  /// `true` or `false`.
+  Bool,
+  /// A non-negative integer.
+  Integer,
+  /// A non-negative integer, or `auto` (see the [indentation](../indentation/index.html) module).
+  Indent,
+  /// A string.
+  String,
+  /// One of the given strings.
+  Choice(&'static [&'static str]),
+  /// An array of strings.
+  StringList,
+  /// An object mapping lint rule names to their levels (see the [lint](../lint/index.html)
+  /// module).
+  Rules,
+  /// An object mapping section headers to arrays of property names (see the
+  /// [sections](../sections/index.html) module).
+  Sections,
+  /// An object mapping path patterns to arrays of property names (see the
+  /// [sorting](../sorting/index.html) module).
+  Priorities,
+  /// An object mapping path patterns to objects of options (see the
+  /// [path_options](../path_options/index.html) module).
+  PathRules,
+  /// An object mapping file extensions to objects of options (see the
+  /// [file_types](../file_types/index.html) module).
+  FileTypes,
+  /// An object mapping file path patterns to objects of lint rule levels (see the
+  /// [lint](../lint/index.html) module).
+  LintOverrides,
+}
+
+impl SettingType {
+  /// Describes the type, for messages.
+  pub fn describe(self) -> String {
+    match self {
+      SettingType::Bool => "a boolean (true or false)",
+      SettingType::Integer => "a non-negative integer",
+      SettingType::Indent => "a non-negative integer, or `auto`",
+      SettingType::String => "a string",
+      SettingType::Choice(choices) => {
+        let choices = choices
+          .iter()
+          .map(|choice| format!("`{}`", choice))
+          .collect::<Vec<_>>();
+        return match choices.split_last() {
+          Some((last, rest)) if !rest.is_empty() => {
+            format!("one of {} or {}", rest.join(", "), last)
+          }
+          _ => choices.join(""),
+        };
+      }
+      SettingType::StringList => "an array of strings",
+      SettingType::Rules => "an object of lint rule levels (or `rule=level[:option]` pairs)",
+      SettingType::Sections => {
+        "an object of arrays of property names (or `header=key,key;...` sections)"
+      }
+      SettingType::Priorities => {
+        "an object of path patterns to arrays of property names (or `pattern=key,key;...` pairs)"
+      }
+      SettingType::PathRules => {
+        "an object of path patterns to objects of options (or `pattern=option:value,...;...` pairs)"
+      }
+      SettingType::FileTypes => {
+        "an object of file extensions to objects of options (or `extension=option:value,...;...` \
+         pairs)"
+      }
+      SettingType::LintOverrides => {
+        "an object of file path patterns to objects of lint rule levels (or \
+         `pattern=rule=level[:option],...;...` pairs)"
+      }
+    }
+    .to_string()
+  }
+
+  /// Parses a value of this type from `text` (such as an environment variable value), accepting
+  /// `1` and `0` as booleans, a comma-separated list as an array of strings, and a comma-separated
+  /// list of `rule=level[:option]` pairs as lint rule levels, and a semicolon-separated list of
+  /// `header=key,key` pairs as sections, and a semicolon-separated list of `pattern=key,key` pairs
+  /// as priorities, and a semicolon-separated list of `pattern=option:value,...` pairs as path
+  /// rules, and a semicolon-separated list of `extension=option:value,...` pairs as file types, and
+  /// a semicolon-separated list of `pattern=rule=level[:option],...` pairs as lint overrides.
+  fn parse(self, text: &str) -> Option<Setting> {
+    match (self, text) {
+      (SettingType::Bool, "true" | "1") => Some(Setting::Bool(true)),
+      (SettingType::Bool, "false" | "0") => Some(Setting::Bool(false)),
+      (SettingType::Integer, text) => text.parse().ok().map(Setting::Integer),
+      (SettingType::Indent, "auto") => Some(Setting::String("auto".to_string())),
+      (SettingType::Indent, text) => text.parse().ok().map(Setting::Integer),
+      (SettingType::String, text) => Some(Setting::String(text.to_string())),
+      (SettingType::Choice(choices), text) => choices
+        .contains(&text)
+        .then(|| Setting::String(text.to_string())),
+      (SettingType::StringList, "") => Some(Setting::StringList(Vec::new())),
+      (SettingType::StringList, text) => Some(Setting::StringList(
+        text
+          .split(',')
+          .map(|item| item.trim().to_string())
+          .collect(),
+      )),
+      (SettingType::Rules, text) => lint::parse_rules(text).ok().map(Setting::Rules),
+      (SettingType::Sections, text) => sections::parse(text).map(Setting::Sections),
+      (SettingType::Priorities, text) => sorting::parse_priorities(text).map(Setting::Priorities),
+      (SettingType::PathRules, text) => path_options::parse(text).map(Setting::PathRules),
+      (SettingType::FileTypes, text) => file_types::parse(text).map(Setting::FileTypes),
+      (SettingType::LintOverrides, text) => lint::parse_overrides(text).map(Setting::LintOverrides),
+      _ => None,
+    }
+  }
+}
+
+/// The value of an option.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Setting {
+  /// The value of a `SettingType::Bool` option.
+  Bool(bool),
+  /// The value of a `SettingType::Integer` or `SettingType::Indent` option.
+  Integer(usize),
+  /// The value of a `SettingType::String` or `SettingType::Choice` option, or `auto` for a
+  /// `SettingType::Indent` option.
+  String(String),
+  /// The value of a `SettingType::StringList` option.
+  StringList(Vec<String>),
+  /// The value of a `SettingType::Rules` option, by rule name.
+  Rules(BTreeMap<String, RuleConfig>),
+  /// The value of a `SettingType::Sections` option, in order.
+  Sections(Vec<Section>),
+  /// The value of a `SettingType::Priorities` option, in order.
+  Priorities(Vec<Priority>),
+  /// The value of a `SettingType::PathRules` option, in order.
+  PathRules(Vec<PathRule>),
+  /// The value of a `SettingType::FileTypes` option, in order.
+  FileTypes(Vec<FileType>),
+  /// The value of a `SettingType::LintOverrides` option, in order.
+  LintOverrides(Vec<LintOverride>),
+}
+
+/// Describes an option accepted in the configuration file.
+#[derive(Debug)]
+pub struct OptionSpec {
+  /// The option name.
+  pub name: &'static str,
+  /// The type of value the option accepts.
+  pub kind: SettingType,
+  /// The value used if the option is not set, as JSON5, or `None` if an unset option means more
+  /// than a value (as the description tells).
+  pub default: Option<&'static str>,
+  /// A one-line description of the option, for the `rules` subcommand.
+  pub description: &'static str,
+}
+
+/// The options accepted in the configuration file.
+pub const OPTIONS: &[OptionSpec] = &[
+  OptionSpec {
+    name: "indent",
+    kind: SettingType::Indent,
+    default: Some("4"),
+    description: "The number of spaces to indent by, or `auto` to keep each file's indentation",
+  },
+  OptionSpec {
+    name: "trailing_commas",
+    kind: SettingType::Bool,
+    default: Some("true"),
+    description: "Whether to add a comma after the last item of each multi-line object and array",
+  },
+  OptionSpec {
+    name: "one_element_lines",
+    kind: SettingType::Bool,
+    default: Some("false"),
+    description: "Whether objects and arrays with a single child collapse to a single line",
+  },
+  OptionSpec {
+    name: "sort_arrays",
+    kind: SettingType::Bool,
+    default: Some("false"),
+    description: "Whether arrays of primitive values are sorted",
+  },
+  OptionSpec {
+    name: "sort_keys",
+    kind: SettingType::Bool,
+    default: Some("false"),
+    description: "Whether the properties of each object are sorted by name",
+  },
+  OptionSpec {
+    name: "allow_trailing_garbage",
+    kind: SettingType::Bool,
+    default: Some("false"),
+    description:
+      "Whether content after the end of the document is kept verbatim, instead of being an error",
+  },
+  OptionSpec {
+    name: "preserve_preamble_lines",
+    kind: SettingType::Integer,
+    default: None,
+    description:
+      "The number of leading lines passed through unchanged (by default, only a leading `#!` line)",
+  },
+  OptionSpec {
+    name: "front_matter",
+    kind: SettingType::Bool,
+    default: Some("false"),
+    description: "Whether only the JSON5 front matter of Markdown files is formatted",
+  },
+  OptionSpec {
+    name: "embedded",
+    kind: SettingType::StringList,
+    default: Some("[]"),
+    description: "The rules that select the JSON5 blocks inside files of other kinds",
+  },
+  OptionSpec {
+    name: "include",
+    kind: SettingType::StringList,
+    default: Some("[]"),
+    description: "The patterns of the files that are formatted (all files, if empty)",
+  },
+  OptionSpec {
+    name: "exclude",
+    kind: SettingType::StringList,
+    default: Some("[]"),
+    description: "The patterns of the files that are not formatted",
+  },
+  OptionSpec {
+    name: "pre_format_plugins",
+    kind: SettingType::StringList,
+    default: Some("[]"),
+    description: "The commands run on each document before it is formatted",
+  },
+  OptionSpec {
+    name: "post_format_plugins",
+    kind: SettingType::StringList,
+    default: Some("[]"),
+    description: "The commands run on each document after it is formatted",
+  },
+  OptionSpec {
+    name: "lint",
+    kind: SettingType::Rules,
+    default: Some("{}"),
+    description: "The level (and option) of each lint rule",
+  },
+  OptionSpec {
+    name: "sections",
+    kind: SettingType::Sections,
+    default: Some("{}"),
+    description: "The header comments to group properties under, with their properties",
+  },
+  OptionSpec {
+    name: "section_comments",
+    kind: SettingType::Bool,
+    default: Some("true"),
+    description: "Whether the header comments of sections are written",
+  },
+  OptionSpec {
+    name: "sort_priority",
+    kind: SettingType::Priorities,
+    default: Some("{}"),
+    description: "The properties moved first in the objects at matching paths",
+  },
+  OptionSpec {
+    name: "paths",
+    kind: SettingType::PathRules,
+    default: Some("{}"),
+    description: "The options of the objects and arrays at matching paths",
+  },
+  OptionSpec {
+    name: "required_version",
+    kind: SettingType::String,
+    default: None,
+    description: "The versions of the formatter the configuration requires",
+  },
+  OptionSpec {
+    name: "empty_container_comments",
+    kind: SettingType::Choice(EmptyComments::VARIANTS),
+    default: Some("\"expand\""),
+    description: "How to write the comments in empty objects and arrays",
+  },
+  OptionSpec {
+    name: "eol_comments",
+    kind: SettingType::Choice(EolComments::VARIANTS),
+    default: Some("\"keep\""),
+    description: "Where to place the comments at the ends of lines",
+  },
+  OptionSpec {
+    name: "comment_attachment",
+    kind: SettingType::Choice(CommentAttachment::VARIANTS),
+    default: Some("\"next\""),
+    description: "Which item a comment on a line of its own moves with when items are sorted",
+  },
+  OptionSpec {
+    name: "max_width",
+    kind: SettingType::Integer,
+    default: Some("100"),
+    description: "The maximum line width",
+  },
+  OptionSpec {
+    name: "long_value",
+    kind: SettingType::Choice(LongValues::VARIANTS),
+    default: Some("\"allow\""),
+    description: "What to do with a value too long for the maximum line width",
+  },
+  OptionSpec {
+    name: "array_wrap",
+    kind: SettingType::Choice(ArrayWrap::VARIANTS),
+    default: Some("\"vertical\""),
+    description: "How to lay out the items of arrays that do not fit on one line",
+  },
+  OptionSpec {
+    name: "quotes",
+    kind: SettingType::Choice(Quotes::VARIANTS),
+    default: Some("\"preserve\""),
+    description: "How to quote string values",
+  },
+  OptionSpec {
+    name: "style",
+    kind: SettingType::Choice(Style::VARIANTS),
+    default: None,
+    description: "The style preset whose options are the defaults",
+  },
+  OptionSpec {
+    name: "dialect",
+    kind: SettingType::Choice(Dialect::VARIANTS),
+    default: Some("\"json5\""),
+    description: "The JSON dialect of the input and output (by default, that of the file's type)",
+  },
+  OptionSpec {
+    name: "file_types",
+    kind: SettingType::FileTypes,
+    default: Some("{}"),
+    description: "The default options of the files with each extension",
+  },
+  OptionSpec {
+    name: "write_mode",
+    kind: SettingType::Choice(WriteMode::VARIANTS),
+    default: Some("\"direct\""),
+    description: "How `--replace` writes the files it replaces",
+  },
+  OptionSpec {
+    name: "lint_overrides",
+    kind: SettingType::LintOverrides,
+    default: Some("{}"),
+    description: "The lint rules of the files at matching paths",
+  },
+];
+
+/// A deprecated option, which is still accepted in place of its replacement.
+#[derive(Debug)]
+pub struct Deprecation {
+  /// The deprecated option name.
+  pub name: &'static str,
+  /// The option that replaces it.
+  pub replacement: &'static str,
+  /// If true, the deprecated option is a boolean with the opposite meaning of its replacement.
+  pub inverted: bool,
+}
+
+/// Deprecated option names. These are the names of the equivalent json5format `FormatOptions`
+/// fields, or of the negative command line flags.
+pub const DEPRECATED: &[Deprecation] = &[
+  Deprecation {
+    name: "indent_by",
+    replacement: "indent",
+    inverted: false,
+  },
+  Deprecation {
+    name: "no_trailing_commas",
+    replacement: "trailing_commas",
+    inverted: true,
+  },
+  Deprecation {
+    name: "collapse_containers_of_one",
+    replacement: "one_element_lines",
+    inverted: false,
+  },
+  Deprecation {
+    name: "sort_array_items",
+    replacement: "sort_arrays",
+    inverted: false,
+  },
+];
+
+impl Deprecation {
+  /// Returns a warning that the option is deprecated, where `name` is how it was spelled.
+  fn warning(&self, name: &str, replacement: &str) -> String {
+    format!(
+      "Option `{}` is deprecated; use `{}`{} instead",
+      name,
+      replacement,
+      if self.inverted {
+        " with the opposite value"
+      } else {
+        ""
+      }
+    )
+  }
+
+  /// Converts a `value` of the deprecated option to the equivalent value of its replacement.
+  fn convert(&self, value: Setting) -> Setting {
+    match value {
+      Setting::Bool(value) if self.inverted => Setting::Bool(!value),
+      value => value,
+    }
+  }
+}
+
+/// Returns the specification of the option with the given `name`.
+fn spec(name: &str) -> Option<&'static OptionSpec> {
+  OPTIONS.iter().find(|spec| spec.name == name)
+}
+
+/// Looks up the option `name`, which may be deprecated. If there is no such option, returns an
+/// error message suggesting the most similar option name, if any.
+fn resolve(name: &str) -> Result<(&'static OptionSpec, Option<&'static Deprecation>), String> {
+  if let Some(spec) = spec(name) {
+    return Ok((spec, None));
+  }
+  if let Some(deprecation) = DEPRECATED
+    .iter()
+    .find(|deprecation| deprecation.name == name)
+  {
+    let spec = spec(deprecation.replacement).expect("deprecated options have known replacements");
+    return Ok((spec, Some(deprecation)));
+  }
+  Err(
+    match suggest::closest(name, OPTIONS.iter().map(|spec| spec.name)) {
+      Some(suggestion) => format!("Unknown option `{}` (did you mean `{}`?)", name, suggestion),
+      None => format!("Unknown option `{}`", name),
+    },
+  )
+}
+
+/// Parses the value of the option `name` (which may be deprecated) from `text`, as for an
+/// environment variable, and returns the option it sets and its value, if both are valid.
+pub fn parse_setting(name: &str, text: &str) -> Option<(&'static str, Setting)> {
+  let (spec, deprecation) = resolve(name).ok()?;
+  let value = spec.kind.parse(text)?;
+  Some(match deprecation {
+    Some(deprecation) => (spec.name, deprecation.convert(value)),
+    None => (spec.name, value),
+  })
+}
+
+/// A set of option values. Configurations from different sources are layered with
+/// [merge()](#method.merge), so each option takes its value from the last layer that set it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+  /// The option values that were set, by option name.
+  values: BTreeMap<&'static str, Setting>,
+  /// The directory of the configuration file that set each option, by option name, for the
+  /// options that were set in a configuration file.
+  directories: BTreeMap<&'static str, PathBuf>,
+}
+
+impl Config {
+  /// Sets the option `name` to `value`.
+  pub fn set(&mut self, name: &'static str, value: Setting) {
+    self.values.insert(name, value);
+    self.directories.remove(name);
+  }
+
+  /// Overrides any options in `self` with the options set in `other`. Lint rule levels are
+  /// overridden rule by rule.
+  pub fn merge(&mut self, other: Config) {
+    for name in other.values.keys() {
+      match other.directories.get(name) {
+        Some(directory) => self.directories.insert(name, directory.clone()),
+        None => self.directories.remove(name),
+      };
+    }
+    for (name, value) in other.values {
+      match (self.values.get_mut(name), value) {
+        (Some(Setting::Rules(rules)), Setting::Rules(overrides)) => rules.extend(overrides),
+        (_, value) => {
+          self.values.insert(name, value);
+        }
+      }
+    }
+  }
+
+  /// Returns the directory of the configuration file that set the option `name`, if it was set in
+  /// a configuration file.
+  pub fn directory(&self, name: &str) -> Option<&Path> {
+    self.directories.get(name).map(PathBuf::as_path)
+  }
+
+  /// Returns the value of the boolean option `name`, if set.
+  pub fn bool(&self, name: &str) -> Option<bool> {
+    match self.values.get(name) {
+      Some(Setting::Bool(value)) => Some(*value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value of the integer option `name`, if set.
+  pub fn integer(&self, name: &str) -> Option<usize> {
+    match self.values.get(name) {
+      Some(Setting::Integer(value)) => Some(*value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value of the string option `name`, if set.
+  pub fn string(&self, name: &str) -> Option<&str> {
+    match self.values.get(name) {
+      Some(Setting::String(value)) => Some(value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value of the string list option `name`, or an empty list if it is not set.
+  pub fn strings(&self, name: &str) -> &[String] {
+    match self.values.get(name) {
+      Some(Setting::StringList(value)) => value,
+      _ => &[],
+    }
+  }
+
+  /// Returns the lint rule levels of the option `name`, by rule name, if set.
+  pub fn rules(&self, name: &str) -> Option<&BTreeMap<String, RuleConfig>> {
+    match self.values.get(name) {
+      Some(Setting::Rules(rules)) => Some(rules),
+      _ => None,
+    }
+  }
+
+  /// Returns the sections of the option `name`, or an empty list if it is not set.
+  pub fn sections(&self, name: &str) -> &[Section] {
+    match self.values.get(name) {
+      Some(Setting::Sections(sections)) => sections,
+      _ => &[],
+    }
+  }
+
+  /// Returns the priorities of the option `name`, or an empty list if it is not set.
+  pub fn priorities(&self, name: &str) -> &[Priority] {
+    match self.values.get(name) {
+      Some(Setting::Priorities(priorities)) => priorities,
+      _ => &[],
+    }
+  }
+
+  /// Returns the path rules of the option `name`, or an empty list if it is not set.
+  pub fn path_rules(&self, name: &str) -> &[PathRule] {
+    match self.values.get(name) {
+      Some(Setting::PathRules(rules)) => rules,
+      _ => &[],
+    }
+  }
+
+  /// Returns the file types of the option `name`, or an empty list if it is not set.
+  pub fn file_types(&self, name: &str) -> &[FileType] {
+    match self.values.get(name) {
+      Some(Setting::FileTypes(file_types)) => file_types,
+      _ => &[],
+    }
+  }
+
+  /// Returns the lint overrides of the option `name`, or an empty list if it is not set.
+  pub fn lint_overrides(&self, name: &str) -> &[LintOverride] {
+    match self.values.get(name) {
+      Some(Setting::LintOverrides(overrides)) => overrides,
+      _ => &[],
+    }
+  }
+
+  /// Returns the json5format options selected by this configuration, with json5format's defaults
+  /// for any options that are not set.
+  pub fn format_options(&self) -> FormatOptions {
+    let defaults = FormatOptions::default();
+    FormatOptions {
+      indent_by: self.integer("indent").unwrap_or(defaults.indent_by),
+      trailing_commas: self
+        .bool("trailing_commas")
+        .unwrap_or(defaults.trailing_commas),
+      collapse_containers_of_one: self
+        .bool("one_element_lines")
+        .unwrap_or(defaults.collapse_containers_of_one),
+      sort_array_items: self
+        .bool("sort_arrays")
+        .unwrap_or(defaults.sort_array_items),
+      ..defaults
+    }
+  }
+
+  /// Returns the source options selected by this configuration.
+  pub fn source_options(&self) -> SourceOptions {
+    SourceOptions {
+      allow_trailing_garbage: self.bool("allow_trailing_garbage").unwrap_or(false),
+      preamble_lines: self.integer("preserve_preamble_lines"),
+      front_matter: self.bool("front_matter").unwrap_or(false),
+    }
+  }
+}
+
+/// Returns the path of the configuration file in `dir` or its nearest ancestor, if any.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+  dir
+    .ancestors()
+    .map(|dir| dir.join(CONFIG_FILENAME))
+    .find(|path| paths::extended(path).is_file())
+}
+
+/// Reads and validates the configuration file at `path`, and the base configurations it extends.
+/// Returns the configuration and a warning for each deprecated option it uses. If the file has any
+/// errors, they are all reported together.
+pub fn load(path: &Path) -> Result<(Config, Vec<String>), anyhow::Error> {
+  load_extending(path, &mut Vec::new())
+}
+
+/// Loads the configuration file at `path`, as for [load()](fn.load.html), where `chain` holds the
+/// files that extend it (directly or indirectly), to detect cycles.
+fn load_extending(
+  path: &Path,
+  chain: &mut Vec<PathBuf>,
+) -> Result<(Config, Vec<String>), anyhow::Error> {
+  let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+  if let Some(start) = chain.iter().position(|extending| *extending == canonical) {
+    let cycle = chain[start..]
+      .iter()
+      .chain([&canonical])
+      .map(|path| path.display().to_string())
+      .collect::<Vec<_>>();
+    return Err(anyhow::anyhow!(
+      "Configuration error: The `{}` options form a cycle: {}",
+      EXTENDS,
+      cycle.join(" -> ")
+    ));
+  }
+  let source = fs::read_to_string(paths::extended(path))
+    .map_err(|err| anyhow::anyhow!("Configuration error: {}: {}", path.display(), err))?;
+  let filename = path.to_string_lossy();
+  let (mut config, warnings, extends) = parse_file(&source, &filename)?;
+  let directory = directory(path);
+  config.directories = config
+    .values
+    .keys()
+    .map(|name| (*name, directory.to_path_buf()))
+    .collect();
+  let Some(extends) = extends else {
+    return Ok((config, warnings));
+  };
+  let base_path = resolve_base(path, &extends.value).map_err(|message| {
+    anyhow::anyhow!(
+      "Configuration error: {}:{}:{}: {}:\n{}",
+      filename,
+      extends.line,
+      extends.col,
+      message,
+      error_context(&source, extends.line, extends.col)
+    )
+  })?;
+  chain.push(canonical);
+  let (mut base, mut all_warnings) = load_extending(&base_path, chain)?;
+  chain.pop();
+  base.merge(config);
+  all_warnings.extend(warnings);
+  Ok((base, all_warnings))
+}
+
+/// Returns the path of the base configuration named by the `extends` option of the configuration
+/// file at `path`, or a description of why it cannot be found. A name starting with `./` or `../`,
+/// or an absolute path, is a file relative to the configuration file (or a directory holding a
+/// [CONFIG_FILENAME](constant.CONFIG_FILENAME.html) file). Any other name is a package, whose
+/// configuration file is in `node_modules/<name>` in the configuration file's directory or its
+/// nearest ancestor.
+fn resolve_base(path: &Path, name: &str) -> Result<PathBuf, String> {
+  let dir = directory(path);
+  let is_path = name.starts_with("./")
+    || name.starts_with("../")
+    || matches!(name, "." | "..")
+    || Path::new(name).is_absolute();
+  if is_path {
+    let mut base = dir.join(name);
+    if base.is_dir() {
+      base.push(CONFIG_FILENAME);
+    }
+    return match base.is_file() {
+      true => Ok(base),
+      false => Err(format!(
+        "Base configuration `{}` not found (looked for {})",
+        name,
+        base.display()
+      )),
+    };
+  }
+  dir
+    .ancestors()
+    .map(|dir| dir.join("node_modules").join(name).join(CONFIG_FILENAME))
+    .find(|base| base.is_file())
+    .ok_or_else(|| {
+      format!(
+        "Base configuration package `{}` not found (looked for node_modules/{}/{} in {} and its \
+         ancestors)",
+        name,
+        name,
+        CONFIG_FILENAME,
+        dir.display()
+      )
+    })
+}
+
+/// Returns the directory of the configuration file at `path`.
+fn directory(path: &Path) -> &Path {
+  match path.parent() {
+    Some(dir) if !dir.as_os_str().is_empty() => dir,
+    _ => Path::new("."),
+  }
+}
+
+/// The value of the `extends` option, and its position in the configuration file.
+struct Extends {
+  /// The name of the base configuration.
+  value: String,
+  /// The 1-based line number of the option's value.
+  line: usize,
+  /// The 1-based column number of the option's value.
+  col: usize,
+}
+
+/// Parses and validates the configuration in `source`, read from `filename`. See
+/// [load()](fn.load.html). The `extends` option is accepted but not followed.
+pub fn parse(source: &str, filename: &str) -> Result<(Config, Vec<String>), anyhow::Error> {
+  let (config, warnings, _) = parse_file(source, filename)?;
+  Ok((config, warnings))
+}
+
+/// Parses and validates the options given as a JSON5 object in `text` by the command line `flag`
+/// (such as `--options-json`), with the same options as the configuration file. Returns the options
+/// and a warning for each deprecated option. The `extends` option is an error, since there is no
+/// file to resolve its base configuration from.
+pub fn from_json(text: &str, flag: &str) -> Result<(Config, Vec<String>), anyhow::Error> {
+  let (config, warnings, extends) = parse_file(text, flag)?;
+  if extends.is_some() {
+    return Err(anyhow::anyhow!(
+      "Configuration error: {}: Option `{}` can only be set in a configuration file",
+      flag,
+      EXTENDS
+    ));
+  }
+  Ok((config, warnings))
+}
+
+/// Parses and validates the configuration in `source`, read from `filename`, and returns the
+/// configuration, its warnings, and its `extends` option, if set.
+fn parse_file(
+  source: &str,
+  filename: &str,
+) -> Result<(Config, Vec<String>, Option<Extends>), anyhow::Error> {
+  let root = syntax::parse(source)
+    .map_err(|err| anyhow::anyhow!("Configuration error: {}:{}", filename, err))?;
+  let mut problems = Problems {
+    source,
+    filename,
+    errors: Vec::new(),
+    warnings: Vec::new(),
+  };
+  let mut config = Config::default();
+  let mut extends = None;
+  match &root {
+    None => {}
+    Some(Node::Object { members, .. }) => {
+      for (index, member) in members.iter().enumerate() {
+        if members[..index]
+          .iter()
+          .any(|previous| previous.name == member.name)
+        {
+          problems.error(
+            member,
+            format!("Option `{}` is set more than once", member.name),
+          );
+        } else if member.name == EXTENDS {
+          match &member.value {
+            Node::String { value, span } => {
+              extends = Some(Extends {
+                value: value.clone(),
+                line: span.line,
+                col: span.col,
+              })
+            }
+            _ => problems.error(
+              member,
+              format!(
+                "Option `{}` must be the path or package name of a base configuration",
+                EXTENDS
+              ),
+            ),
+          }
+        } else if let Some((name, value)) = problems.validate(member) {
+          config.set(name, value);
+        }
+      }
+    }
+    Some(node) => {
+      let span = node.span();
+      problems.errors.push(format!(
+        "{}:{}:{}: The configuration must be an object, but found {}:\n{}",
+        filename,
+        span.line,
+        span.col,
+        match node.type_name(source) {
+          "array" => "an array",
+          "string" => "a string",
+          "number" => "a number",
+          "boolean" => "a boolean",
+          _ => "null",
+        },
+        error_context(source, span.line, span.col)
+      ));
+    }
+  }
+  if !problems.errors.is_empty() {
+    return Err(anyhow::anyhow!(
+      "Configuration error: {}",
+      problems.errors.join("\n")
+    ));
+  }
+  Ok((config, problems.warnings, extends))
+}
+
+/// Returns the options set by `JSON5FMT_*` environment variables in `vars` (other than
+/// `JSON5FMT_CONFIG`), and a warning for each deprecated option and each unknown variable (which is
+/// ignored, since another tool may set it). Boolean options accept `true`, `false`, `1`, or `0`.
+/// Invalid values are reported together as an error.
+pub fn from_env(
+  vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<(Config, Vec<String>), anyhow::Error> {
+  let mut config = Config::default();
+  let mut errors = Vec::new();
+  let mut warnings = Vec::new();
+  for (var, text) in vars {
+    let Some(name) = var.strip_prefix(ENV_PREFIX) else {
+      continue;
+    };
+    if var == CONFIG_ENV_VAR {
+      continue;
+    }
+    let name = name.to_lowercase();
+    let env_name = |name: &str| format!("{}{}", ENV_PREFIX, name.to_uppercase());
+    let (spec, deprecation) = match resolve(&name) {
+      Ok(resolved) => resolved,
+      Err(_) => {
+        let names = OPTIONS
+          .iter()
+          .map(|spec| env_name(spec.name))
+          .collect::<Vec<_>>();
+        warnings.push(
+          match suggest::closest(&var, names.iter().map(String::as_str)) {
+            Some(suggestion) => format!(
+              "Unknown variable `{}` (did you mean `{}`?)",
+              var, suggestion
+            ),
+            None => format!("Unknown variable `{}`", var),
+          },
+        );
+        continue;
+      }
+    };
+    let Some(value) = spec.kind.parse(&text) else {
+      errors.push(format!(
+        "`{}` must be {}, but found `{}`",
+        var,
+        spec.kind.describe(),
+        text
+      ));
+      continue;
+    };
+    if let Some(deprecation) = deprecation {
+      warnings.push(deprecation.warning(&var, &env_name(deprecation.replacement)));
+      config.set(spec.name, deprecation.convert(value));
+    } else {
+      config.set(spec.name, value);
+    }
+  }
+  if !errors.is_empty() {
+    return Err(anyhow::anyhow!("Environment error: {}", errors.join("\n")));
+  }
+  Ok((config, warnings))
+}
+
+/// Returns the options set by the `name=value` query parameters of a formatting request to the
+/// [server](../serve/index.html). Boolean options accept `true`, `false`, `1`, or `0`. Plugins
+/// cannot be set, so that a request never runs a command it names. Unknown options and invalid
+/// values are reported together as an error.
+#[cfg(feature = "serve")]
+pub fn from_query(pairs: &[(String, String)]) -> Result<(Config, Vec<String>), anyhow::Error> {
+  let mut config = Config::default();
+  let mut errors = Vec::new();
+  let mut warnings = Vec::new();
+  for (name, text) in pairs {
+    let (spec, deprecation) = match resolve(name) {
+      Ok(resolved) => resolved,
+      Err(message) => {
+        errors.push(message);
+        continue;
+      }
+    };
+    if matches!(
+      spec.name,
+      "pre_format_plugins" | "post_format_plugins" | "file_types" | "lint_overrides"
+    ) {
+      errors.push(format!("Option `{}` cannot be set by a request", name));
+      continue;
+    }
+    let Some(value) = spec.kind.parse(text) else {
+      errors.push(format!(
+        "Option `{}` must be {}, but found `{}`",
+        name,
+        spec.kind.describe(),
+        text
+      ));
+      continue;
+    };
+    match deprecation {
+      Some(deprecation) => {
+        warnings.push(deprecation.warning(name, deprecation.replacement));
+        config.set(spec.name, deprecation.convert(value));
+      }
+      None => config.set(spec.name, value),
+    }
+  }
+  if !errors.is_empty() {
+    return Err(anyhow::anyhow!("Request error: {}", errors.join("\n")));
+  }
+  Ok((config, warnings))
+}
+
+/// Returns the options set by `text`, the value of the command line flag `flag` (such as
+/// `--candidate` of `compare-options`), which holds space-separated `name=value` pairs, as in a
+/// directive, and a warning for each deprecated option. Unknown options and invalid values are
+/// reported together as an error.
+pub fn from_pairs(text: &str, flag: &str) -> Result<(Config, Vec<String>), anyhow::Error> {
+  parse_pairs(text.split_whitespace(), flag)
+}
+
+/// Returns the options set by the `values` of the repeatable command line flag `flag` (such as
+/// `--config-override`), each a `name=value` pair whose value may hold spaces, and a warning for
+/// each deprecated option. Unknown options and invalid values are reported together as an error.
+pub fn from_overrides(
+  values: &[String],
+  flag: &str,
+) -> Result<(Config, Vec<String>), anyhow::Error> {
+  parse_pairs(values.iter().map(String::as_str), flag)
+}
+
+/// Returns the options set by the `name=value` `pairs` of the command line flag `flag`, and a
+/// warning for each deprecated option.
+fn parse_pairs<'a>(
+  pairs: impl IntoIterator<Item = &'a str>,
+  flag: &str,
+) -> Result<(Config, Vec<String>), anyhow::Error> {
+  let mut config = Config::default();
+  let mut errors = Vec::new();
+  let mut warnings = Vec::new();
+  for pair in pairs {
+    let Some((name, text)) = pair.split_once('=') else {
+      errors.push(format!("Expected `name=value`, but found `{}`", pair));
+      continue;
+    };
+    let (spec, deprecation) = match resolve(name) {
+      Ok(resolved) => resolved,
+      Err(message) => {
+        errors.push(message);
+        continue;
+      }
+    };
+    let Some(value) = spec.kind.parse(text) else {
+      errors.push(format!(
+        "Option `{}` must be {}, but found `{}`",
+        name,
+        spec.kind.describe(),
+        text
+      ));
+      continue;
+    };
+    match deprecation {
+      Some(deprecation) => {
+        warnings.push(deprecation.warning(name, deprecation.replacement));
+        config.set(spec.name, deprecation.convert(value));
+      }
+      None => config.set(spec.name, value),
+    }
+  }
+  if !errors.is_empty() {
+    return Err(anyhow::anyhow!(
+      "Option error: {}: {}",
+      flag,
+      errors.join("\n")
+    ));
+  }
+  Ok((config, warnings))
+}
+
+/// Parses the options directive in `text`, the first line of the document being formatted (line
+/// number `line` of `filename`), if the line is a comment starting with
+/// [DIRECTIVE_MARKER](constant.DIRECTIVE_MARKER.html). Returns `None` if there is no directive, and
+/// otherwise the options it sets and a warning for each deprecated option. The directive holds
+/// space-separated `name=value` pairs. `preserve_preamble_lines` and `front_matter` cannot be set,
+/// because the preamble is found before the directive is read, and plugins cannot be set, so that
+/// formatting a file never runs a command named by the file itself.
+pub fn directive(
+  text: &str,
+  line: usize,
+  filename: &str,
+) -> Result<Option<(Config, Vec<String>)>, anyhow::Error> {
+  let Some(comment) = text.trim_start().strip_prefix("//") else {
+    return Ok(None);
+  };
+  let Some(pairs) = comment.trim_start().strip_prefix(DIRECTIVE_MARKER) else {
+    return Ok(None);
+  };
+  let mut config = Config::default();
+  let mut errors = Vec::new();
+  let mut warnings = Vec::new();
+  let mut offset = text.len() - pairs.len();
+  for pair in pairs.split(' ') {
+    let col = text[..offset].chars().count() + 1;
+    offset += pair.len() + 1;
+    if pair.is_empty() {
+      continue;
+    }
+    let at = |message: String| {
+      format!(
+        "{}:{}:{}: {}:\n{}",
+        filename,
+        line,
+        col,
+        message,
+        error_context(text, 1, col)
+      )
+    };
+    let Some((name, value)) = pair.split_once('=') else {
+      errors.push(at(format!("Expected `name=value`, but found `{}`", pair)));
+      continue;
+    };
+    let (spec, deprecation) = match resolve(name) {
+      Ok(resolved) => resolved,
+      Err(message) => {
+        errors.push(at(message));
+        continue;
+      }
+    };
+    if matches!(
+      spec.name,
+      "preserve_preamble_lines"
+        | "front_matter"
+        | "pre_format_plugins"
+        | "post_format_plugins"
+        | "file_types"
+        | "lint_overrides"
+    ) {
+      errors.push(at(format!(
+        "Option `{}` cannot be set in the file it applies to",
+        name
+      )));
+      continue;
+    }
+    let Some(value) = spec.kind.parse(value) else {
+      errors.push(at(format!(
+        "Option `{}` must be {}, but found `{}`",
+        name,
+        spec.kind.describe(),
+        value
+      )));
+      continue;
+    };
+    match deprecation {
+      Some(deprecation) => {
+        warnings.push(at(deprecation.warning(name, deprecation.replacement)));
+        config.set(spec.name, deprecation.convert(value));
+      }
+      None => config.set(spec.name, value),
+    }
+  }
+  if !errors.is_empty() {
+    return Err(anyhow::anyhow!(
+      "Configuration error: {}",
+      errors.join("\n")
+    ));
+  }
+  Ok(Some((config, warnings)))
+}
+
+/// A deprecated command line flag spelling, and the flag that replaces it.
+#[derive(Debug)]
+pub struct FlagDeprecation {
+  /// The deprecated flag.
+  pub flag: &'static str,
+  /// The flag to use instead.
+  pub replacement: &'static str,
+}
+
+/// Deprecated command line flags. These are the underscore spellings shown in earlier versions of
+/// the usage documentation, which are still accepted as hidden aliases.
+pub const DEPRECATED_FLAGS: &[FlagDeprecation] = &[
+  FlagDeprecation {
+    flag: "--no_trailing_commas",
+    replacement: "--no-trailing-commas",
+  },
+  FlagDeprecation {
+    flag: "--one_element_lines",
+    replacement: "--one-element-lines",
+  },
+  FlagDeprecation {
+    flag: "--sort_arrays",
+    replacement: "--sort-arrays",
+  },
+];
+
+/// Rewrites each deprecated option in the configuration in `source` (read from `filename`) to its
+/// replacement, inverting boolean values where the replacement has the opposite meaning. Comments
+/// and the layout of the file are preserved. Returns the migrated source and a description of each
+/// change. The configuration must be valid, and must not set both an option and its deprecated
+/// name.
+pub fn migrate(source: &str, filename: &str) -> Result<(String, Vec<String>), anyhow::Error> {
+  parse(source, filename)?;
+  let Some(Node::Object { members, .. }) = syntax::parse(source)? else {
+    return Ok((source.to_string(), Vec::new()));
+  };
+  let mut edits = Edits::default();
+  let mut changes = Vec::new();
+  for member in &members {
+    let Ok((spec, Some(deprecation))) = resolve(&member.name) else {
+      continue;
+    };
+    if let Some(other) = members.iter().find(|other| other.name == spec.name) {
+      return Err(anyhow::anyhow!(
+        "Configuration error: {}:{}:{}: Option `{}` is also set by its deprecated name `{}`; \
+         remove one of them before migrating:\n{}",
+        filename,
+        other.key.line,
+        other.key.col,
+        spec.name,
+        member.name,
+        error_context(source, other.key.line, other.key.col)
+      ));
+    }
+    // Keep the quotes, if the deprecated name was quoted.
+    let key = &source[member.key.start..member.key.end];
+    let quote = &key[..key.len() - key.trim_start_matches(['"', '\'']).len()];
+    edits.replace(
+      member.key.start..member.key.end,
+      format!("{}{}{}", quote, spec.name, quote),
+    );
+    let span = member.value.span();
+    let mut change = format!(
+      "{}:{}:{}: Renamed `{}` to `{}`",
+      filename, member.key.line, member.key.col, member.name, spec.name
+    );
+    if deprecation.inverted {
+      let inverted = match &source[span.start..span.end] {
+        "true" => "false",
+        _ => "true",
+      };
+      edits.replace(span.start..span.end, inverted);
+      change.push_str(&format!(" and changed its value to `{}`", inverted));
+    }
+    changes.push(change);
+  }
+  let migrated = edits.apply(source);
+  Ok((migrated, changes))
+}
+
+/// Collects the problems found while validating a configuration file.
+struct Problems<'a> {
+  /// The configuration source.
+  source: &'a str,
+  /// The configuration filename, for messages.
+  filename: &'a str,
+  /// Problems that prevent the configuration from being used.
+  errors: Vec<String>,
+  /// Problems that do not prevent the configuration from being used.
+  warnings: Vec<String>,
+}
+
+impl<'a> Problems<'a> {
+  /// Formats `message` with the location of the property `member`.
+  fn at(&self, member: &Member, message: String) -> String {
+    format!(
+      "{}:{}:{}: {}:\n{}",
+      self.filename,
+      member.key.line,
+      member.key.col,
+      message,
+      error_context(self.source, member.key.line, member.key.col)
+    )
+  }
+
+  /// Records an error about the property `member`.
+  fn error(&mut self, member: &Member, message: String) {
+    let error = self.at(member, message);
+    self.errors.push(error);
+  }
+
+  /// Validates a configuration property, returning the option it sets and its value, or recording
+  /// an error if it is invalid.
+  fn validate(&mut self, member: &Member) -> Option<(&'static str, Setting)> {
+    let (spec, deprecation) = match resolve(&member.name) {
+      Ok(resolved) => resolved,
+      Err(message) => {
+        self.error(member, message);
+        return None;
+      }
+    };
+    if let Some(deprecation) = deprecation {
+      let warning = self.at(
+        member,
+        deprecation.warning(&member.name, deprecation.replacement),
+      );
+      self.warnings.push(warning);
+    }
+
+    let span = member.value.span();
+    let text = &self.source[span.start..span.end];
+    let value = match (spec.kind, &member.value) {
+      (SettingType::Bool, Node::Literal { .. }) => text.parse().ok().map(Setting::Bool),
+      (SettingType::Integer | SettingType::Indent, Node::Literal { .. }) => {
+        text.parse().ok().map(Setting::Integer)
+      }
+      (SettingType::Indent, Node::String { value, .. }) => {
+        (value == "auto").then(|| Setting::String(value.clone()))
+      }
+      (SettingType::String, Node::String { value, .. }) => Some(Setting::String(value.clone())),
+      (SettingType::Choice(choices), Node::String { value, .. }) => choices
+        .contains(&value.as_str())
+        .then(|| Setting::String(value.clone())),
+      (SettingType::StringList, Node::Array { items, .. }) => items
+        .iter()
+        .map(|item| match item {
+          Node::String { value, .. } => Some(value.clone()),
+          _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(Setting::StringList),
+      (SettingType::Rules, Node::Object { members, .. }) => {
+        let mut rules = BTreeMap::new();
+        for rule in members {
+          match self.rule(rule) {
+            Ok(config) => {
+              rules.insert(rule.name.clone(), config);
+            }
+            Err(message) => self.error(rule, message),
+          }
+        }
+        // The errors about each rule are more useful than a type error about the whole option.
+        return Some((spec.name, Setting::Rules(rules)));
+      }
+      (SettingType::Sections, Node::Object { members, .. }) => members
+        .iter()
+        .map(|section| match &section.value {
+          Node::Array { items, .. } => items
+            .iter()
+            .map(|item| match item {
+              Node::String { value, .. } => Some(value.clone()),
+              _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|keys| Section {
+              header: section.name.clone(),
+              keys,
+            }),
+          _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(Setting::Sections),
+      (SettingType::Priorities, Node::Object { members, .. }) => {
+        let mut priorities = Vec::new();
+        for priority in members {
+          if let Err(err) = priority.name.parse::<PathGlob>() {
+            self.error(priority, err.to_string());
+            continue;
+          }
+          let keys = match &priority.value {
+            Node::Array { items, .. } => items
+              .iter()
+              .map(|item| match item {
+                Node::String { value, .. } => Some(value.clone()),
+                _ => None,
+              })
+              .collect::<Option<Vec<_>>>(),
+            _ => None,
+          };
+          match keys {
+            Some(keys) => priorities.push(Priority {
+              pattern: priority.name.clone(),
+              keys,
+            }),
+            None => self.error(
+              priority,
+              format!(
+                "The priority of `{}` must be an array of property names",
+                priority.name
+              ),
+            ),
+          }
+        }
+        // The errors about each pattern are more useful than a type error about the whole option.
+        return Some((spec.name, Setting::Priorities(priorities)));
+      }
+      (SettingType::FileTypes, Node::Object { members, .. }) => {
+        let mut file_types = Vec::new();
+        for file_type in members {
+          if let Some(file_type) = self.file_type(file_type) {
+            file_types.push(file_type);
+          }
+        }
+        // The errors about each file type are more useful than a type error about the whole option.
+        return Some((spec.name, Setting::FileTypes(file_types)));
+      }
+      (SettingType::LintOverrides, Node::Object { members, .. }) => {
+        let mut overrides = Vec::new();
+        for pattern in members {
+          if let Err(err) = pattern.name.parse::<PathGlob>() {
+            self.error(pattern, err.to_string());
+            continue;
+          }
+          let Node::Object { members: rules, .. } = &pattern.value else {
+            self.error(
+              pattern,
+              format!(
+                "The lint rules of `{}` must be an object of lint rule levels",
+                pattern.name
+              ),
+            );
+            continue;
+          };
+          let mut configs = BTreeMap::new();
+          for rule in rules {
+            match self.rule(rule) {
+              Ok(config) => {
+                configs.insert(rule.name.clone(), config);
+              }
+              Err(message) => self.error(rule, message),
+            }
+          }
+          overrides.push(LintOverride {
+            pattern: pattern.name.clone(),
+            rules: configs,
+          });
+        }
+        // The errors about each pattern are more useful than a type error about the whole option.
+        return Some((spec.name, Setting::LintOverrides(overrides)));
+      }
+      (SettingType::PathRules, Node::Object { members, .. }) => {
+        let mut rules = Vec::new();
+        for rule in members {
+          match self.path_rule(rule) {
+            Ok(rule) => rules.push(rule),
+            Err((member, message)) => self.error(member, message),
+          }
+        }
+        // The errors about each pattern are more useful than a type error about the whole option.
+        return Some((spec.name, Setting::PathRules(rules)));
+      }
+      _ => None,
+    };
+    let Some(value) = value else {
+      let found = match &member.value {
+        Node::Literal { .. } => format!("`{}`", text),
+        Node::String { value, .. } => format!("the string \"{}\"", value),
+        Node::Array { items, .. } => format!("an array of {} item(s)", items.len()),
+        Node::Object { .. } => "an object".to_string(),
+      };
+      let message = format!(
+        "Option `{}` must be {}, but found {}",
+        member.name,
+        spec.kind.describe(),
+        found
+      );
+      self.error(member, message);
+      return None;
+    };
+    match deprecation {
+      Some(deprecation) => Some((spec.name, deprecation.convert(value))),
+      None => Some((spec.name, value)),
+    }
+  }
+
+  /// Validates the options set for the objects and arrays whose paths match the pattern named by
+  /// `rule`: an object of boolean options. An error is returned with the property it is about.
+  fn path_rule<'m>(&self, rule: &'m Member) -> Result<PathRule, (&'m Member, String)> {
+    rule
+      .name
+      .parse::<PathGlob>()
+      .map_err(|err| (rule, err.to_string()))?;
+    let Node::Object { members, .. } = &rule.value else {
+      return Err((
+        rule,
+        format!(
+          "The options of `{}` must be an object, such as {{sort_keys: true}}",
+          rule.name
+        ),
+      ));
+    };
+    let mut options = BTreeMap::new();
+    for option in members {
+      if !path_options::OPTIONS.contains(&option.name.as_str()) {
+        return Err((
+          option,
+          match suggest::closest(&option.name, path_options::OPTIONS.iter().copied()) {
+            Some(suggestion) => format!(
+              "Option `{}` cannot be set for a path (did you mean `{}`?)",
+              option.name, suggestion
+            ),
+            None => format!(
+              "Option `{}` cannot be set for a path (expected one of: {})",
+              option.name,
+              path_options::OPTIONS.join(", ")
+            ),
+          },
+        ));
+      }
+      let span = option.value.span();
+      match &self.source[span.start..span.end] {
+        "true" => options.insert(option.name.clone(), true),
+        "false" => options.insert(option.name.clone(), false),
+        text => {
+          return Err((
+            option,
+            format!(
+              "Option `{}` must be a boolean (true or false), but found `{}`",
+              option.name, text
+            ),
+          ))
+        }
+      };
+    }
+    Ok(PathRule {
+      pattern: rule.name.clone(),
+      options,
+    })
+  }
+
+  /// Validates the options of the file type `file_type`, whose name is its extension, recording
+  /// errors about any that are invalid.
+  fn file_type(&mut self, file_type: &Member) -> Option<FileType> {
+    if file_type.name.trim_start_matches('.').is_empty() {
+      self.error(
+        file_type,
+        "A file type must be a file extension, such as `.cml`".to_string(),
+      );
+      return None;
+    }
+    let Node::Object { members, .. } = &file_type.value else {
+      self.error(
+        file_type,
+        format!(
+          "The options of `{}` must be an object, such as {{dialect: \"jsonc\"}}",
+          file_type.name
+        ),
+      );
+      return None;
+    };
+    let mut options = Config::default();
+    for option in members {
+      if matches!(option.name.as_str(), "file_types" | EXTENDS) {
+        self.error(
+          option,
+          format!("Option `{}` cannot be set for a file type", option.name),
+        );
+      } else if let Some((name, value)) = self.validate(option) {
+        options.set(name, value);
+      }
+    }
+    Some(FileType {
+      extension: file_types::normalize(&file_type.name),
+      options,
+    })
+  }
+
+  /// Validates the configuration of the lint rule `rule`: a level, or an array of a level, the
+  /// rule's option, and an array of the path patterns of its allowlist (either of which may be
+  /// left out).
+  fn rule(&self, rule: &Member) -> Result<RuleConfig, String> {
+    let text = |node: &Node| match node {
+      Node::String { value, .. } => Some(value.clone()),
+      Node::Literal { span } => Some(self.source[span.start..span.end].to_string()),
+      _ => None,
+    };
+    let (items, allow) = match &rule.value {
+      Node::Array { items, .. } => match items.split_last() {
+        Some((Node::Array { items: allow, .. }, items)) => (
+          items.iter().map(text).collect::<Option<Vec<_>>>(),
+          allow
+            .iter()
+            .map(|pattern| match pattern {
+              Node::String { value, .. } => Some(value.clone()),
+              _ => None,
+            })
+            .collect::<Option<Vec<_>>>(),
+        ),
+        _ => (items.iter().map(text).collect(), Some(Vec::new())),
+      },
+      value => (text(value).map(|level| vec![level]), Some(Vec::new())),
+    };
+    match (items.as_deref(), allow) {
+      (Some([level]), Some(allow)) => lint::configure(&rule.name, level, None, &allow),
+      (Some([level, option]), Some(allow)) => {
+        lint::configure(&rule.name, level, Some(option), &allow)
+      }
+      _ => Err(format!(
+        "Lint rule `{}` must be set to a level ({}), or an array of a level, an option, and an \
+         array of allowed path patterns",
+        rule.name,
+        lint::Level::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_the_options_of_a_file() {
+    let (config, warnings) = parse(
+      "{\n  // Two spaces.\n  indent: 2,\n  trailing_commas: false,\n  quotes: 'double',\n}\n",
+      "a.json5",
+    )
+    .unwrap();
+    assert_eq!(config.integer("indent"), Some(2));
+    assert_eq!(config.bool("trailing_commas"), Some(false));
+    assert_eq!(config.string("quotes"), Some("double"));
+    assert_eq!(config.bool("sort_arrays"), None);
+    assert!(warnings.is_empty());
+    assert_eq!(
+      parse("", "a.json5").unwrap(),
+      (Config::default(), Vec::new())
+    );
+  }
+
+  #[test]
+  fn reports_every_invalid_option_together() {
+    let source = "{indnt: 2, trailing_commas: 1,\n indent: 3, indent: 4}";
+    assert_eq!(
+      parse(source, "a.json5").unwrap_err().to_string(),
+      "\
+Configuration error: a.json5:1:2: Unknown option `indnt` (did you mean `indent`?):
+{indnt: 2, trailing_commas: 1,
+ ^
+a.json5:1:12: Option `trailing_commas` must be a boolean (true or false), but found `1`:
+{indnt: 2, trailing_commas: 1,
+           ^
+a.json5:2:13: Option `indent` is set more than once:
+ indent: 3, indent: 4}
+            ^"
+    );
+    assert_eq!(
+      parse("[1]", "a.json5").unwrap_err().to_string(),
+      "Configuration error: a.json5:1:1: The configuration must be an object, but found an \
+       array:\n[1]\n^"
+    );
+  }
+
+  #[test]
+  fn accepts_deprecated_options_with_a_warning() {
+    let (config, warnings) = parse("{no_trailing_commas: true}", "a.json5").unwrap();
+    assert_eq!(config.bool("trailing_commas"), Some(false));
+    assert_eq!(
+      warnings,
+      [
+        "a.json5:1:2: Option `no_trailing_commas` is deprecated; use `trailing_commas` with the \
+        opposite value instead:\n{no_trailing_commas: true}\n ^"
+      ]
+    );
+  }
+
+  #[test]
+  fn takes_each_option_from_the_last_layer_that_sets_it() {
+    let (mut config, _) = parse("{indent: 2, sort_arrays: true}", "a.json5").unwrap();
+    let (layer, _) = parse("{indent: 8, trailing_commas: false}", "b.json5").unwrap();
+    config.merge(layer);
+    assert_eq!(config.integer("indent"), Some(8));
+    assert_eq!(config.bool("sort_arrays"), Some(true));
+    assert_eq!(config.bool("trailing_commas"), Some(false));
+  }
+
+  #[test]
+  fn finds_the_file_in_the_nearest_ancestor() {
+    let dir = crate::write_mode::private_directory("jsonpretty5er-config-test").unwrap();
+    let nested = dir.join("a").join("b");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(dir.join(CONFIG_FILENAME), "{}").unwrap();
+    assert_eq!(find(&nested), Some(dir.join(CONFIG_FILENAME)));
+    fs::write(dir.join("a").join(CONFIG_FILENAME), "{}").unwrap();
+    assert_eq!(find(&nested), Some(dir.join("a").join(CONFIG_FILENAME)));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn migrates_deprecated_options() {
+    let source =
+      "{\n  // Keep this.\n  'no_trailing_commas': true,\n  indent_by: 2, sort_arrays: true,\n}\n";
+    assert_eq!(
+      migrate(source, "a.json5").unwrap(),
+      (
+        "{\n  // Keep this.\n  'trailing_commas': false,\n  indent: 2, sort_arrays: true,\n}\n"
+          .to_string(),
+        vec![
+          "a.json5:3:3: Renamed `no_trailing_commas` to `trailing_commas` and changed its value \
+           to `false`"
+            .to_string(),
+          "a.json5:4:3: Renamed `indent_by` to `indent`".to_string(),
+        ]
+      )
+    );
+  }
+
+  #[test]
+  fn does_not_migrate_options_also_set_by_their_replacements() {
+    let source = "{indent_by: 2, indent: 4}";
+    assert_eq!(
+      migrate(source, "a.json5").unwrap_err().to_string(),
+      format!(
+        "Configuration error: a.json5:1:16: Option `indent` is also set by its deprecated name \
+         `indent_by`; remove one of them before migrating:\n{}",
+        error_context(source, 1, 16)
+      )
+    );
+    assert!(migrate("{indent_by: 'two'}", "a.json5").is_err());
+  }
+
+  #[test]
+  fn parses_directives() {
+    let (config, warnings) = directive("// json5format: indent=2  sort_keys=true", 1, "a.json5")
+      .unwrap()
+      .unwrap();
+    assert_eq!(config.integer("indent"), Some(2));
+    assert_eq!(config.bool("sort_keys"), Some(true));
+    assert!(warnings.is_empty());
+    let (config, warnings) = directive("// json5format: indent_by=2", 3, "a.json5")
+      .unwrap()
+      .unwrap();
+    assert_eq!(config.integer("indent"), Some(2));
+    assert_eq!(
+      warnings,
+      [format!(
+        "a.json5:3:17: Option `indent_by` is deprecated; use `indent` instead:\n{}",
+        error_context("// json5format: indent_by=2", 1, 17)
+      )]
+    );
+  }
+
+  #[test]
+  fn ignores_lines_without_a_directive() {
+    for line in ["{", "// indent=2", "/* json5format: indent=2 */", ""] {
+      assert_eq!(directive(line, 1, "a.json5").unwrap(), None);
+    }
+  }
+
+  #[test]
+  fn reports_the_position_of_each_invalid_pair() {
+    let line = "// json5format: indent=x front_matter=true sort";
+    assert_eq!(
+      directive(line, 1, "a.json5").unwrap_err().to_string(),
+      format!(
+        "Configuration error: a.json5:1:17: Option `indent` must be a non-negative integer, or \
+         `auto`, but found `x`:\n{}\na.json5:1:26: Option `front_matter` cannot be set in the \
+         file it applies to:\n{}\na.json5:1:44: Expected `name=value`, but found `sort`:\n{}",
+        error_context(line, 1, 17),
+        error_context(line, 1, 26),
+        error_context(line, 1, 44)
+      )
+    );
+  }
+
+  #[test]
+  fn extends_base_files_and_packages() {
+    let dir = crate::write_mode::private_directory("jsonpretty5er-config-test").unwrap();
+    let package = dir.join("node_modules").join("@acme").join("style");
+    fs::create_dir_all(&package).unwrap();
+    fs::create_dir_all(dir.join("project")).unwrap();
+    fs::write(
+      package.join(CONFIG_FILENAME),
+      "{indent: 8, sort_arrays: true, indent_by: 8}",
+    )
+    .unwrap();
+    fs::write(
+      dir.join("base.json5"),
+      "{extends: '@acme/style', indent: 2, trailing_commas: false}",
+    )
+    .unwrap();
+    let path = dir.join("project").join(CONFIG_FILENAME);
+    fs::write(&path, "{extends: '../base.json5', trailing_commas: true}").unwrap();
+    let (config, warnings) = load(&path).unwrap();
+    assert_eq!(config.integer("indent"), Some(2));
+    assert_eq!(config.bool("sort_arrays"), Some(true));
+    assert_eq!(config.bool("trailing_commas"), Some(true));
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("Option `indent_by` is deprecated"));
+    // Each option records the directory of the file that set it, for the paths it names.
+    assert_eq!(
+      config.directory("indent"),
+      Some(dir.join("project").join("..").as_path())
+    );
+    assert_eq!(
+      config.directory("trailing_commas"),
+      Some(dir.join("project").as_path())
+    );
+    let mut config = config;
+    config.merge(parse("{indent: 4}", "--options-json").unwrap().0);
+    assert_eq!(config.directory("indent"), None);
+    // A directory holds the base configuration in its configuration file.
+    fs::write(&path, "{extends: '../node_modules/@acme/style'}").unwrap();
+    assert_eq!(load(&path).unwrap().0.integer("indent"), Some(8));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn reports_missing_bases_and_cycles() {
+    let dir = crate::write_mode::private_directory("jsonpretty5er-config-test").unwrap();
+    let path = dir.join("a.json5");
+    fs::write(&path, "{\n  extends: './b.json5',\n}").unwrap();
+    let err = load(&path).unwrap_err().to_string();
+    assert!(
+      err.starts_with(&format!(
+        "Configuration error: {}:2:12: Base configuration `./b.json5` not found",
+        path.display()
+      )),
+      "{}",
+      err
+    );
+    fs::write(dir.join("b.json5"), "{extends: './a.json5'}").unwrap();
+    let (a, b) = (
+      fs::canonicalize(&path).unwrap(),
+      fs::canonicalize(dir.join("b.json5")).unwrap(),
+    );
+    assert_eq!(
+      load(&path).unwrap_err().to_string(),
+      format!(
+        "Configuration error: The `extends` options form a cycle: {} -> {} -> {}",
+        a.display(),
+        b.display(),
+        a.display()
+      )
+    );
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn parses_options_given_as_json() {
+    let (config, _) = from_json("{indent: 2, sort_arrays: true}", "--options-json").unwrap();
+    assert_eq!(config.integer("indent"), Some(2));
+    assert_eq!(config.bool("sort_arrays"), Some(true));
+    assert_eq!(
+      from_json("{extends: 'base.json5'}", "--options-json")
+        .unwrap_err()
+        .to_string(),
+      "Configuration error: --options-json: Option `extends` can only be set in a configuration \
+       file"
+    );
+    assert!(from_json("{indent: 'two'}", "--options-json")
+      .unwrap_err()
+      .to_string()
+      .starts_with("Configuration error: --options-json:1:2: "));
+  }
+
+  #[test]
+  fn parses_overrides_with_spaces_in_their_values() {
+    let overrides = [
+      "indent=3".to_string(),
+      "sections=Networking=host,port;Storage=data dir".to_string(),
+      "no_trailing_commas=1".to_string(),
+    ];
+    let (config, warnings) = from_overrides(&overrides, "--config-override").unwrap();
+    assert_eq!(config.integer("indent"), Some(3));
+    assert_eq!(config.sections("sections").len(), 2);
+    assert_eq!(config.bool("trailing_commas"), Some(false));
+    assert_eq!(
+      warnings,
+      [
+        "Option `no_trailing_commas` is deprecated; use `trailing_commas` with the opposite value \
+        instead"
+      ]
+    );
+    let overrides = ["indent".to_string(), "indnt=2".to_string()];
+    assert_eq!(
+      from_overrides(&overrides, "--config-override")
+        .unwrap_err()
+        .to_string(),
+      "Option error: --config-override: Expected `name=value`, but found `indent`\nUnknown option \
+       `indnt` (did you mean `indent`?)"
+    );
+  }
+
+  #[test]
+  fn reads_options_from_the_environment() {
+    let vars = [
+      ("JSON5FMT_INDENT", "2"),
+      ("JSON5FMT_SORT_ARRAYS", "1"),
+      ("JSON5FMT_NO_TRAILING_COMMAS", "true"),
+      ("JSON5FMT_CONFIG", "other.json5"),
+      ("HOME", "/home/user"),
+    ]
+    .map(|(var, text)| (var.to_string(), text.to_string()));
+    let (config, warnings) = from_env(vars).unwrap();
+    assert_eq!(config.integer("indent"), Some(2));
+    assert_eq!(config.bool("sort_arrays"), Some(true));
+    assert_eq!(config.bool("trailing_commas"), Some(false));
+    assert_eq!(
+      warnings,
+      [
+        "Option `JSON5FMT_NO_TRAILING_COMMAS` is deprecated; use `JSON5FMT_TRAILING_COMMAS` with \
+        the opposite value instead"
+      ]
+    );
+  }
+
+  #[test]
+  fn warns_of_unknown_variables() {
+    let vars = [("JSON5FMT_INDNT", "2"), ("JSON5FMT_VERBOSE", "1")]
+      .map(|(var, text)| (var.to_string(), text.to_string()));
+    let (config, warnings) = from_env(vars).unwrap();
+    assert_eq!(config, Config::default());
+    assert_eq!(
+      warnings,
+      [
+        "Unknown variable `JSON5FMT_INDNT` (did you mean `JSON5FMT_INDENT`?)",
+        "Unknown variable `JSON5FMT_VERBOSE`",
+      ]
+    );
+  }
+
+  #[test]
+  fn reports_invalid_values_of_the_environment() {
+    let vars = [("JSON5FMT_INDENT", "two"), ("JSON5FMT_SORT_ARRAYS", "yes")]
+      .map(|(var, text)| (var.to_string(), text.to_string()));
+    assert_eq!(
+      from_env(vars).unwrap_err().to_string(),
+      "Environment error: `JSON5FMT_INDENT` must be a non-negative integer, or `auto`, but found \
+       `two`\n`JSON5FMT_SORT_ARRAYS` must be a boolean (true or false), but found `yes`"
+    );
+  }
+}
@@ -0,0 +1,142 @@
+//! Finds the unresolved merge conflicts that git leaves in a file, so they are reported as such
+//! rather than as the syntax error at their first marker, and resolves them by taking one side
+//! (the `--take` option):
+//!
+//!     {
+//!     <<<<<<< HEAD
+//!         timeout: 30,
+//!     =======
+//!         timeout: 60,
+//!     >>>>>>> feature
+//!     }
+//!
+//! A conflict is the lines from a `<<<<<<<` marker to the next `>>>>>>>` marker, with a `=======`
+//! marker between our side and their side, and, in git's `diff3` style, a `|||||||` marker before
+//! the common ancestor's lines. Each marker starts its line; markers that do not form a whole
+//! conflict are left alone, as ordinary (and most likely invalid) content.
+
+use crate::format::FormatError;
+use std::{ops::Range, str::FromStr};
+
+/// Which side of each conflict to keep.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Take {
+  /// Keep our side (between `<<<<<<<` and `|||||||` or `=======`).
+  Ours,
+  /// Keep their side (between `=======` and `>>>>>>>`).
+  Theirs,
+}
+
+impl Take {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["ours", "theirs"];
+}
+
+impl FromStr for Take {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "ours" => Ok(Take::Ours),
+      "theirs" => Ok(Take::Theirs),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", Take::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// An unresolved merge conflict.
+#[derive(Debug)]
+pub struct Conflict {
+  /// The 1-based line of the `<<<<<<<` marker.
+  pub start_line: usize,
+  /// The 1-based line of the `>>>>>>>` marker.
+  pub end_line: usize,
+  /// The bytes of the whole conflict, from the start of its first marker to the end of its last
+  /// marker's line.
+  span: Range<usize>,
+  /// The bytes of the lines of our side.
+  ours: Range<usize>,
+  /// The bytes of the lines of their side.
+  theirs: Range<usize>,
+}
+
+/// Returns the conflicts in `buffer`, in order.
+pub fn find(buffer: &str) -> Vec<Conflict> {
+  let mut conflicts = Vec::new();
+  // The line and offset of the open conflict's `<<<<<<<` marker, and the end of its marker line.
+  let mut open: Option<(usize, usize, usize)> = None;
+  // The start of the `|||||||` marker, if any, and the start and end of the `=======` marker.
+  let mut base: Option<usize> = None;
+  let mut separator: Option<(usize, usize)> = None;
+  let mut offset = 0;
+  for (index, line) in buffer.split_inclusive('\n').enumerate() {
+    let (start, end) = (offset, offset + line.len());
+    offset = end;
+    if line.starts_with("<<<<<<<") {
+      // A new marker restarts an incomplete conflict.
+      open = Some((index + 1, start, end));
+      base = None;
+      separator = None;
+    } else if open.is_none() {
+      continue;
+    } else if line.starts_with("|||||||") && base.is_none() && separator.is_none() {
+      base = Some(start);
+    } else if line.trim_end() == "=======" && separator.is_none() {
+      separator = Some((start, end));
+    } else if let (true, Some((start_line, conflict_start, ours_start)), Some((ours_end, theirs))) =
+      (line.starts_with(">>>>>>>"), open, separator)
+    {
+      conflicts.push(Conflict {
+        start_line,
+        end_line: index + 1,
+        span: conflict_start..end,
+        ours: ours_start..base.unwrap_or(ours_end),
+        theirs: theirs..start,
+      });
+      open = None;
+      base = None;
+      separator = None;
+    }
+  }
+  conflicts
+}
+
+/// Returns `buffer` with each of its `conflicts` replaced by the side selected by `take`.
+pub fn resolve(buffer: &str, conflicts: &[Conflict], take: Take) -> String {
+  let mut resolved = String::with_capacity(buffer.len());
+  let mut offset = 0;
+  for conflict in conflicts {
+    resolved.push_str(&buffer[offset..conflict.span.start]);
+    let side = match take {
+      Take::Ours => &conflict.ours,
+      Take::Theirs => &conflict.theirs,
+    };
+    resolved.push_str(&buffer[side.clone()]);
+    offset = conflict.span.end;
+  }
+  resolved.push_str(&buffer[offset..]);
+  resolved
+}
+
+/// Returns the first and last lines of each of the `conflicts`.
+pub fn lines(conflicts: &[Conflict]) -> Vec<(usize, usize)> {
+  conflicts
+    .iter()
+    .map(|conflict| (conflict.start_line, conflict.end_line))
+    .collect()
+}
+
+/// Returns the `lines` of conflicts (as returned by [lines()](fn.lines.html)), such as `3-7 and
+/// 12-16`.
+pub fn describe(lines: &[(usize, usize)]) -> String {
+  let ranges = lines
+    .iter()
+    .map(|(start, end)| format!("{}-{}", start, end))
+    .collect::<Vec<_>>();
+  match ranges.split_last() {
+    Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+    _ => ranges.join(""),
+  }
+}
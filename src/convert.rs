@@ -0,0 +1,463 @@
+//! Converts JSON5 documents to JSON or YAML, for the `convert` subcommand (and its `to-json` and
+//! `to-yaml` spellings).
+//!
+//! Comments are dropped, since neither JSON nor YAML output keeps them in place. Numbers are
+//! written in a form both formats accept: hexadecimal numbers are converted to decimal, and a
+//! leading `+`, a leading or trailing decimal point are normalized (`+.5` becomes `0.5`).
+//! `Infinity` and `NaN` have no JSON representation, so they are errors when converting to JSON,
+//! and are written as `.inf` and `.nan` in YAML.
+//!
+//! With `convert --to json --verify-roundtrip`, the converted JSON is parsed again and compared
+//! with the original document (see [verify()](fn.verify.html)), so a migration can be sure that no
+//! data was lost.
+//!
+//! Conversion is part of the `convert` feature, which is enabled by default.
+
+#[cfg(feature = "convert")]
+use crate::{
+  lexer::{self, TokenKind},
+  syntax::{self, Member, Node},
+};
+#[cfg(feature = "convert")]
+use std::path::Path;
+use std::str::FromStr;
+
+/// A format that documents are converted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+  /// Strict JSON.
+  Json,
+  /// YAML, in block style.
+  Yaml,
+}
+
+impl Target {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["json", "yaml"];
+
+  /// Returns the file extension of the format, which replaces the extension of converted files.
+  #[cfg(feature = "convert")]
+  pub fn extension(self) -> &'static str {
+    match self {
+      Target::Json => "json",
+      Target::Yaml => "yaml",
+    }
+  }
+}
+
+impl FromStr for Target {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "json" => Ok(Target::Json),
+      "yaml" => Ok(Target::Yaml),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        Target::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// Converts the document `root`, parsed from `source`, to `target`, indenting nested values by
+/// `indent` spaces. Returns the converted text, ending with a line break, or an error message with
+/// the location of a value that cannot be converted.
+#[cfg(feature = "convert")]
+pub fn convert(root: &Node, source: &str, target: Target, indent: usize) -> Result<String, String> {
+  let writer = Writer {
+    source,
+    // An array item marker and its space need at least 2 columns.
+    indent: match target {
+      Target::Json => indent,
+      Target::Yaml => indent.max(2),
+    },
+  };
+  let mut text = String::new();
+  match target {
+    Target::Json => writer.json(root, 0, &mut text)?,
+    Target::Yaml => writer.yaml(root, 0, &mut text)?,
+  }
+  if !text.ends_with('\n') {
+    text.push('\n');
+  }
+  Ok(text)
+}
+
+/// Returns the path of the converted `file` under `out_dir`, with the directories of `file`
+/// (relative to `current_dir`) preserved and its extension replaced by the extension of `target`,
+/// or an error message if `file` is outside of `current_dir`.
+#[cfg(feature = "convert")]
+pub fn output_path(
+  file: &Path,
+  current_dir: &Path,
+  out_dir: &Path,
+  target: Target,
+) -> Result<std::path::PathBuf, String> {
+  use std::path::Component;
+
+  let outside = || {
+    format!(
+      "{}: Cannot write the converted file under the output directory, since the file is \
+       outside of the current directory",
+      file.display()
+    )
+  };
+  let relative = match file.is_absolute() {
+    true => file.strip_prefix(current_dir).map_err(|_| outside())?,
+    false => file,
+  };
+  let mut path = out_dir.to_path_buf();
+  for component in relative.components() {
+    match component {
+      Component::Normal(name) => path.push(name),
+      Component::CurDir => {}
+      Component::ParentDir | Component::RootDir | Component::Prefix(_) => return Err(outside()),
+    }
+  }
+  path.set_extension(target.extension());
+  Ok(path)
+}
+
+/// Parses the `converted` text of the document `root`, parsed from `source`, and compares the two
+/// documents. Returns a message, with the location in the original document, for each comment
+/// that was dropped and each value that differs (ignoring how strings are quoted and how numbers
+/// are written, such as `0xff` and `255`).
+#[cfg(feature = "convert")]
+pub fn verify(root: &Node, source: &str, converted: &str) -> Vec<String> {
+  let mut losses = lexer::tokenize(source)
+    .iter()
+    .filter(|token| matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment))
+    .map(|token| format!("{}:{}: The comment is lost", token.line, token.col))
+    .collect::<Vec<_>>();
+  match syntax::parse(converted) {
+    Ok(Some(converted_root)) => compare(root, source, &converted_root, converted, &mut losses),
+    Ok(None) => losses.push("The converted document holds no value".to_string()),
+    Err(err) => losses.push(format!("The converted document does not parse: {}", err)),
+  }
+  losses
+}
+
+/// Adds a message to `losses` for each difference between the `original` value, parsed from
+/// `source`, and the `converted` value, parsed from `converted_source`.
+#[cfg(feature = "convert")]
+fn compare(
+  original: &Node,
+  source: &str,
+  converted: &Node,
+  converted_source: &str,
+  losses: &mut Vec<String>,
+) {
+  let span = original.span();
+  let mut lost = |message: String| losses.push(format!("{}:{}: {}", span.line, span.col, message));
+  match (original, converted) {
+    (
+      Node::Object { members, .. },
+      Node::Object {
+        members: converted_members,
+        ..
+      },
+    ) => {
+      let names = |members: &[Member]| {
+        members
+          .iter()
+          .map(|member| member.name.clone())
+          .collect::<Vec<_>>()
+      };
+      if names(members) != names(converted_members) {
+        lost(format!(
+          "The object's properties changed from {:?} to {:?}",
+          names(members),
+          names(converted_members)
+        ));
+        return;
+      }
+      for (member, converted_member) in members.iter().zip(converted_members) {
+        compare(
+          &member.value,
+          source,
+          &converted_member.value,
+          converted_source,
+          losses,
+        );
+      }
+    }
+    (
+      Node::Array { items, .. },
+      Node::Array {
+        items: converted_items,
+        ..
+      },
+    ) => {
+      if items.len() != converted_items.len() {
+        lost(format!(
+          "The array's length changed from {} to {}",
+          items.len(),
+          converted_items.len()
+        ));
+        return;
+      }
+      for (item, converted_item) in items.iter().zip(converted_items) {
+        compare(item, source, converted_item, converted_source, losses);
+      }
+    }
+    (
+      Node::String { value, .. },
+      Node::String {
+        value: converted_value,
+        ..
+      },
+    ) => {
+      if value != converted_value {
+        lost(format!(
+          "The string changed from {:?} to {:?}",
+          value, converted_value
+        ));
+      }
+    }
+    (
+      Node::Literal { span },
+      Node::Literal {
+        span: converted_span,
+      },
+    ) => {
+      let text = &source[span.start..span.end];
+      let converted_text = &converted_source[converted_span.start..converted_span.end];
+      if !same_literal(text, converted_text) {
+        lost(format!(
+          "The value changed from `{}` to `{}`",
+          text, converted_text
+        ));
+      }
+    }
+    _ => lost(format!(
+      "The {} became a {}",
+      original.type_name(source),
+      converted.type_name(converted_source)
+    )),
+  }
+}
+
+/// Returns true if the literals `a` and `b` have the same value, however their numbers are
+/// written.
+#[cfg(feature = "convert")]
+fn same_literal(a: &str, b: &str) -> bool {
+  if a == b {
+    return true;
+  }
+  match (a.parse::<Number>(), b.parse::<Number>()) {
+    (Ok(Number::Finite(a)), Ok(Number::Finite(b))) => {
+      a == b
+        || match (a.parse::<i128>(), b.parse::<i128>()) {
+          (Ok(a), Ok(b)) => a == b,
+          _ => matches!((a.parse::<f64>(), b.parse::<f64>()), (Ok(a), Ok(b)) if a == b),
+        }
+    }
+    _ => false,
+  }
+}
+
+/// A number literal, classified for conversion.
+#[cfg(feature = "convert")]
+enum Number {
+  /// A finite number, in a form JSON and YAML accept.
+  Finite(String),
+  /// `Infinity` or `-Infinity`, with true if it is negative.
+  Infinite(bool),
+  /// `NaN`.
+  NaN,
+}
+
+#[cfg(feature = "convert")]
+impl FromStr for Number {
+  type Err = String;
+
+  fn from_str(literal: &str) -> Result<Self, Self::Err> {
+    let (negative, magnitude) = match literal.as_bytes().first() {
+      Some(b'-') => (true, &literal[1..]),
+      Some(b'+') => (false, &literal[1..]),
+      _ => (false, literal),
+    };
+    let sign = if negative { "-" } else { "" };
+    if magnitude == "Infinity" {
+      return Ok(Number::Infinite(negative));
+    }
+    if magnitude == "NaN" {
+      return Ok(Number::NaN);
+    }
+    if let Some(hex) = magnitude
+      .strip_prefix("0x")
+      .or_else(|| magnitude.strip_prefix("0X"))
+    {
+      let value = u128::from_str_radix(hex, 16).map_err(|_| {
+        format!(
+          "The hexadecimal number `{}` is too large to convert",
+          literal
+        )
+      })?;
+      return Ok(Number::Finite(format!("{}{}", sign, value)));
+    }
+    let (mantissa, exponent) = match magnitude.find(['e', 'E']) {
+      Some(index) => magnitude.split_at(index),
+      None => (magnitude, ""),
+    };
+    let mantissa = match mantissa.strip_suffix('.') {
+      Some(integer) => integer.to_string(),
+      None if mantissa.starts_with('.') => format!("0{}", mantissa),
+      None => mantissa.to_string(),
+    };
+    Ok(Number::Finite(format!("{}{}{}", sign, mantissa, exponent)))
+  }
+}
+
+/// Writes converted values.
+#[cfg(feature = "convert")]
+struct Writer<'a> {
+  /// The source the values were parsed from.
+  source: &'a str,
+  /// The number of spaces to indent nested values by.
+  indent: usize,
+}
+
+#[cfg(feature = "convert")]
+impl Writer<'_> {
+  /// Returns the indentation of a value nested at `depth`.
+  fn indentation(&self, depth: usize) -> String {
+    " ".repeat(self.indent * depth)
+  }
+
+  /// Returns the JSON or YAML text of the literal `node` (a number, `true`, `false`, or `null`),
+  /// where `yaml` selects the format.
+  fn literal(&self, node: &Node, yaml: bool) -> Result<String, String> {
+    let span = node.span();
+    let text = &self.source[span.start..span.end];
+    if matches!(text, "true" | "false" | "null") {
+      return Ok(text.to_string());
+    }
+    let error = |message: String| format!("{}:{}: {}", span.line, span.col, message);
+    match (text.parse::<Number>().map_err(error)?, yaml) {
+      (Number::Finite(number), _) => Ok(number),
+      (Number::Infinite(negative), true) => Ok(if negative { "-.inf" } else { ".inf" }.to_string()),
+      (Number::NaN, true) => Ok(".nan".to_string()),
+      (Number::Infinite(_) | Number::NaN, false) => Err(error(format!(
+        "`{}` cannot be converted, since JSON has no infinite or NaN numbers",
+        text
+      ))),
+    }
+  }
+
+  /// Appends the JSON text of `node`, nested at `depth`, to `text`.
+  fn json(&self, node: &Node, depth: usize, text: &mut String) -> Result<(), String> {
+    match node {
+      Node::Object { members, .. } if members.is_empty() => text.push_str("{}"),
+      Node::Object { members, .. } => {
+        text.push_str("{\n");
+        for (index, Member { name, value, .. }) in members.iter().enumerate() {
+          text.push_str(&self.indentation(depth + 1));
+          text.push_str(&syntax::quote(name, '"'));
+          text.push_str(": ");
+          self.json(value, depth + 1, text)?;
+          text.push_str(if index + 1 < members.len() {
+            ",\n"
+          } else {
+            "\n"
+          });
+        }
+        text.push_str(&self.indentation(depth));
+        text.push('}');
+      }
+      Node::Array { items, .. } if items.is_empty() => text.push_str("[]"),
+      Node::Array { items, .. } => {
+        text.push_str("[\n");
+        for (index, item) in items.iter().enumerate() {
+          text.push_str(&self.indentation(depth + 1));
+          self.json(item, depth + 1, text)?;
+          text.push_str(if index + 1 < items.len() { ",\n" } else { "\n" });
+        }
+        text.push_str(&self.indentation(depth));
+        text.push(']');
+      }
+      Node::String { value, .. } => text.push_str(&syntax::quote(value, '"')),
+      Node::Literal { .. } => text.push_str(&self.literal(node, false)?),
+    }
+    Ok(())
+  }
+
+  /// Appends the YAML text of `node`, nested at `depth`, to `text`. A scalar or empty collection
+  /// is written inline, with no line break; other collections are written as indented lines, each
+  /// ending with a line break, starting on the current line (after a `- ` of an enclosing array
+  /// item) if `text` does not end with a line break.
+  fn yaml(&self, node: &Node, depth: usize, text: &mut String) -> Result<(), String> {
+    let start_line = |text: &mut String, first: bool| {
+      if !first || text.is_empty() || text.ends_with('\n') {
+        text.push_str(&self.indentation(depth));
+      }
+    };
+    match node {
+      Node::Object { members, .. } if members.is_empty() => text.push_str("{}"),
+      Node::Object { members, .. } => {
+        for (index, Member { name, value, .. }) in members.iter().enumerate() {
+          start_line(text, index == 0);
+          text.push_str(&yaml_key(name));
+          text.push(':');
+          if is_block(value) {
+            text.push('\n');
+            self.yaml(value, depth + 1, text)?;
+          } else {
+            text.push(' ');
+            self.yaml(value, depth + 1, text)?;
+            text.push('\n');
+          }
+        }
+      }
+      Node::Array { items, .. } if items.is_empty() => text.push_str("[]"),
+      Node::Array { items, .. } => {
+        for (index, item) in items.iter().enumerate() {
+          start_line(text, index == 0);
+          // The item marker is as wide as the indentation, so the lines of an object item line up
+          // with its first property, which follows the marker.
+          text.push('-');
+          text.push_str(&" ".repeat(self.indent - 1));
+          self.yaml(item, depth + 1, text)?;
+          if !is_block(item) {
+            text.push('\n');
+          }
+        }
+      }
+      Node::String { value, .. } => text.push_str(&syntax::quote(value, '"')),
+      Node::Literal { .. } => text.push_str(&self.literal(node, true)?),
+    }
+    Ok(())
+  }
+}
+
+/// Returns true if `node` is written as indented YAML lines: a non-empty object or array.
+#[cfg(feature = "convert")]
+fn is_block(node: &Node) -> bool {
+  match node {
+    Node::Object { members, .. } => !members.is_empty(),
+    Node::Array { items, .. } => !items.is_empty(),
+    Node::String { .. } | Node::Literal { .. } => false,
+  }
+}
+
+/// Returns the YAML form of the property name `name`: unquoted if it is a plain word that YAML
+/// does not read as another type (such as `true` or `null`), and double-quoted otherwise.
+#[cfg(feature = "convert")]
+fn yaml_key(name: &str) -> String {
+  let plain = name
+    .chars()
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+    && !matches!(
+      name.to_ascii_lowercase().as_str(),
+      "true" | "false" | "null" | "yes" | "no" | "on" | "off" | "y" | "n"
+    );
+  match plain {
+    true => name.to_string(),
+    false => syntax::quote(name, '"'),
+  }
+}
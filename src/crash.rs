@@ -0,0 +1,217 @@
+//! Reports internal errors (panics) with a bug report bundle, so users can send a reproducer.
+//!
+//! When the formatter panics, the panic hook writes a directory (under the system's temporary
+//! directory) holding `report.txt`, with the formatter's version, platform, command line, and the
+//! panic message, location, and backtrace, and `input.json5`, the file that was being formatted,
+//! if any (read only when the report is written; a document read from stdin or a URL is not
+//! included). It then prints where the bundle is and how to report the bug, and exits. The strings
+//! in the document are replaced with a placeholder, which usually keeps the problem reproducible
+//! without sharing the document's content, unless `--unredacted-crash-report` is given. The
+//! directory is created under a new name, and it and its files can be read only by the user (on
+//! Unix).
+//!
+//! A panic from printing to a closed stdout (such as when the output is piped to `head`) is not a
+//! bug, so it exits quietly instead.
+
+use crate::{
+  lexer::{self, TokenKind},
+  paths, redact, remote, version, write_mode,
+};
+use std::{
+  backtrace::Backtrace,
+  cell::RefCell,
+  fmt::Write as _,
+  fs,
+  io::Write as _,
+  panic::{self, PanicHookInfo},
+  path::{Path, PathBuf},
+  process,
+};
+
+/// Where bugs are reported.
+const ISSUES_URL: &str = "https://github.com/gamma-delta/jsonpretty5er/issues";
+
+/// The exit status after an internal error, which is the status of a panic.
+const EXIT_STATUS: i32 = 101;
+
+thread_local! {
+  /// The name of the document being formatted on this thread, if any.
+  static INPUT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Records the document being formatted on the current thread, for crash reports, until the guard
+/// is dropped.
+pub struct Input(());
+
+impl Input {
+  /// Records that the document read from `filename` is being formatted.
+  pub fn enter(filename: &str) -> Input {
+    INPUT.with(|input| *input.borrow_mut() = Some(filename.to_string()));
+    Input(())
+  }
+}
+
+impl Drop for Input {
+  fn drop(&mut self) {
+    // A panic unwinding through the guard has already been reported by the hook.
+    let _ = INPUT.try_with(|input| input.borrow_mut().take());
+  }
+}
+
+/// Installs the panic hook. Unless `unredacted` is true, the strings of the document in a report
+/// are replaced with a placeholder.
+pub fn install(unredacted: bool) {
+  panic::set_hook(Box::new(move |info| {
+    let message = payload(info);
+    if message.contains("failed printing to std") && message.contains("Broken pipe") {
+      process::exit(1);
+    }
+    let input = INPUT
+      .try_with(|input| input.borrow().clone())
+      .ok()
+      .flatten();
+    let during = match &input {
+      Some(filename) => format!(" while formatting {}", filename),
+      None => String::new(),
+    };
+    eprintln!("Internal error{}: {}", during, message);
+    match write_bundle(info, &message, input.as_deref(), unredacted) {
+      Ok((path, included)) => eprintln!(
+        "This is a bug in formatjson5. Please report it at {}, and attach the files in\n  {}{}",
+        ISSUES_URL,
+        path.display(),
+        match (included, unredacted) {
+          (false, _) => "",
+          (true, false) =>
+            "\nwhich include the document being formatted, with its strings redacted",
+          (true, true) => {
+            "\nwhich include the document being formatted, with its strings; review them before \
+             sharing"
+          }
+        }
+      ),
+      Err(err) => eprintln!(
+        "This is a bug in formatjson5. Please report it at {} (the bug report could not be \
+         written: {})",
+        ISSUES_URL, err
+      ),
+    }
+    process::exit(EXIT_STATUS);
+  }));
+}
+
+/// Returns the message of a panic.
+fn payload(info: &PanicHookInfo<'_>) -> String {
+  match (
+    info.payload().downcast_ref::<&str>(),
+    info.payload().downcast_ref::<String>(),
+  ) {
+    (Some(message), _) => message.to_string(),
+    (_, Some(message)) => message.clone(),
+    _ => "(no message)".to_string(),
+  }
+}
+
+/// Writes the bug report bundle for the panic described by `info`, while formatting the document
+/// read from `input` (if any), and returns its directory, and whether it includes the document.
+fn write_bundle(
+  info: &PanicHookInfo<'_>,
+  message: &str,
+  input: Option<&str>,
+  unredacted: bool,
+) -> std::io::Result<(PathBuf, bool)> {
+  let dir = write_mode::private_directory("formatjson5-crash")?;
+
+  let mut report = String::new();
+  let _ = writeln!(report, "formatjson5 {}", version::VERSION);
+  let _ = writeln!(
+    report,
+    "Platform: {}-{}",
+    std::env::consts::ARCH,
+    std::env::consts::OS
+  );
+  let arguments = std::env::args().collect::<Vec<_>>();
+  let _ = writeln!(report, "Command line: {:?}", arguments);
+  // Stdin and remote documents cannot be read again.
+  let source = input
+    .filter(|filename| *filename != "-" && !remote::is_url(filename))
+    .and_then(|filename| fs::read_to_string(paths::extended(Path::new(filename))).ok());
+  if let Some(filename) = input {
+    let _ = writeln!(report, "Document: {}", filename);
+    if source.is_none() {
+      let _ = writeln!(
+        report,
+        "(The document could not be read again for this report.)"
+      );
+    }
+  }
+  let _ = writeln!(report, "Panic: {}", message);
+  if let Some(location) = info.location() {
+    let _ = writeln!(report, "Location: {}", location);
+  }
+  let _ = writeln!(report, "\n{}", Backtrace::force_capture());
+  write_private(&dir.join("report.txt"), &report)?;
+
+  if let Some(source) = &source {
+    let source = match unredacted {
+      true => source.clone(),
+      false => redact_strings(source),
+    };
+    write_private(&dir.join("input.json5"), &source)?;
+  }
+  Ok((dir, source.is_some()))
+}
+
+/// Writes `contents` to the new file `path`, which only the user can read and write (on Unix).
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+  let mut options = fs::OpenOptions::new();
+  options.write(true).create_new(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+  }
+  options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// Returns `source` with each string replaced with the redaction placeholder.
+fn redact_strings(source: &str) -> String {
+  let mut redacted = String::with_capacity(source.len());
+  let mut copied = 0;
+  for token in lexer::tokenize(source) {
+    if token.kind == TokenKind::String {
+      redacted.push_str(&source[copied..token.start]);
+      redacted.push_str(redact::PLACEHOLDER);
+      copied = token.end;
+    }
+  }
+  redacted.push_str(&source[copied..]);
+  redacted
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn redacts_the_strings_of_a_document() {
+    assert_eq!(
+      redact_strings("{name: 'secret', // 'kept'\n}"),
+      format!("{{name: {}, // 'kept'\n}}", redact::PLACEHOLDER)
+    );
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn writes_files_only_the_user_can_read() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = write_mode::private_directory("jsonpretty5er-crash-test").unwrap();
+    let path = dir.join("report.txt");
+    write_private(&path, "report").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "report");
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,42 @@
+//! Finds the inputs that could make the output differ between machines, for `--deterministic`.
+//!
+//! Formatting itself does not depend on the platform or locale: property names and array items
+//! are sorted by comparing their UTF-8 bytes (not with a locale's collation), output lines always
+//! end with `\n` (whatever the input or platform uses), files are formatted and written in the
+//! order they are given (unless `--output-order completion` is used), and the configuration file
+//! is found from the current directory. The remaining inputs are outside of the repository being
+//! formatted: environment variables that set options, plugin commands, documents fetched from
+//! URLs, and the order formatted documents are completed in. With `--deterministic`, the run fails
+//! before formatting if any of them would be used, so CI can ensure that the output depends only
+//! on the files and the configuration in the repository.
+
+/// The inputs found that could make the output differ between machines.
+#[derive(Debug, Default)]
+pub struct Audit {
+  /// A description of each input.
+  sources: Vec<String>,
+}
+
+impl Audit {
+  /// Records an input that could make the output differ between machines.
+  pub fn flag(&mut self, source: String) {
+    self.sources.push(source);
+  }
+
+  /// Returns an error describing the inputs found, if any.
+  pub fn finish(self) -> Result<(), anyhow::Error> {
+    if self.sources.is_empty() {
+      return Ok(());
+    }
+    let sources = self
+      .sources
+      .iter()
+      .map(|source| format!("\n  - {}", source))
+      .collect::<String>();
+    Err(anyhow::anyhow!(
+      "Determinism error: The output could depend on more than the files and configuration \
+       (--deterministic):{}",
+      sources
+    ))
+  }
+}
@@ -0,0 +1,88 @@
+//! Reports problems found in a document (such as lint findings) at their location in the source,
+//! in a consistent format:
+//!
+//!     Warning[J5F008]: config.json5:3:5: Empty object [no-empty-object]:
+//!         options: {},
+//!         ~~~~^
+//!
+//! Each problem has a stable [code](../codes/index.html), which `formatjson5 explain` describes.
+
+use crate::{codes::Code, document::error_context};
+
+/// How serious a problem is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+  /// A likely mistake, which does not stop the document from being formatted.
+  Warning,
+  /// A problem that makes formatting the document fail.
+  Error,
+}
+
+impl Severity {
+  /// Returns the label of the severity, for messages.
+  pub fn label(self) -> &'static str {
+    match self {
+      Severity::Warning => "Warning",
+      Severity::Error => "Error",
+    }
+  }
+}
+
+/// A problem found in a document.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+  /// The kind of problem.
+  pub code: &'static Code,
+  /// How serious the problem is.
+  pub severity: Severity,
+  /// The 1-based line number of the problem.
+  pub line: usize,
+  /// The 1-based character column of the problem.
+  pub col: usize,
+  /// A description of the problem.
+  pub message: String,
+  /// The name of the lint rule that found the problem, if any.
+  pub rule: Option<&'static str>,
+}
+
+impl Diagnostic {
+  /// Returns a warning of the kind `code` at `line` and `col`.
+  pub fn warning(code: &'static Code, line: usize, col: usize, message: String) -> Self {
+    Diagnostic {
+      code,
+      severity: Severity::Warning,
+      line,
+      col,
+      message,
+      rule: None,
+    }
+  }
+
+  /// Returns an error of the kind `code` at `line` and `col`.
+  pub fn error(code: &'static Code, line: usize, col: usize, message: String) -> Self {
+    Diagnostic {
+      severity: Severity::Error,
+      ..Diagnostic::warning(code, line, col, message)
+    }
+  }
+}
+
+/// Returns the report of `diagnostic`, found in the document `source` read from `filename`,
+/// followed by the source line it refers to.
+pub fn render(diagnostic: &Diagnostic, filename: &str, source: &str) -> String {
+  let rule = match diagnostic.rule {
+    Some(rule) => format!(" [{}]", rule),
+    None => String::new(),
+  };
+  format!(
+    "{}[{}]: {}:{}:{}: {}{}:\n{}",
+    diagnostic.severity.label(),
+    diagnostic.code.id,
+    filename,
+    diagnostic.line,
+    diagnostic.col,
+    diagnostic.message,
+    rule,
+    error_context(source, diagnostic.line, diagnostic.col)
+  )
+}
@@ -0,0 +1,798 @@
+//! Line diffs, for keeping unchanged lines with `--minimal-diff` and for printing the changes
+//! formatting would make with the `diff` subcommand (and with `check`, if a diff option is given),
+//! and between two sets of options with the `compare-options` subcommand.
+//!
+//! Diffs are printed in one of two styles (`--diff-style`): `unified`, the changed lines one after
+//! the other, as `diff -u` prints them, or `side-by-side`, each old line beside the new line that
+//! replaces it, with their line numbers and a column that tells them apart (`|` for a changed
+//! line, `<` for a deleted line, and `>` for an inserted line). Each change is shown with
+//! `--diff-context` unchanged lines around it. With `--word-diff`, the words that changed within
+//! a changed line are highlighted: in reverse video in a colored diff, and otherwise between
+//! `[-` and `-]` (deleted words) and `{+` and `+}` (inserted words), as `git diff --word-diff`
+//! marks them.
+
+use std::{collections::HashMap, fmt::Write as _, ops::Range, str::FromStr};
+
+/// The number of unchanged lines shown before and after each change, by default.
+pub const DEFAULT_CONTEXT: usize = 3;
+
+/// The most words changed within a line that are highlighted; a line with more changes is shown
+/// without highlights, since most of its words changed.
+const MAX_WORD_CHANGES: usize = 256;
+
+/// The note after a line without a line break.
+const NO_NEWLINE: &str = "\\ No newline at end of file";
+
+/// The escape sequence for the file headers of a colored diff (bold).
+const HEADER_COLOR: &str = "\x1b[1m";
+/// The escape sequence for the hunk headers of a colored diff (cyan).
+const HUNK_COLOR: &str = "\x1b[36m";
+/// The escape sequence for deleted lines in a colored diff (red).
+const DELETE_COLOR: &str = "\x1b[31m";
+/// The escape sequence for inserted lines in a colored diff (green).
+const INSERT_COLOR: &str = "\x1b[32m";
+/// The escape sequence that starts the highlight of changed words (reverse video).
+const WORD_COLOR: &str = "\x1b[7m";
+/// The escape sequence that ends the highlight of changed words.
+const WORD_RESET: &str = "\x1b[27m";
+/// The escape sequence that ends a color.
+const RESET: &str = "\x1b[0m";
+
+/// How a diff lays out the old and new lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiffStyle {
+  /// The changed lines one after the other.
+  #[default]
+  Unified,
+  /// The old lines beside the new lines.
+  SideBySide,
+}
+
+impl DiffStyle {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["unified", "side-by-side"];
+}
+
+impl FromStr for DiffStyle {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "unified" => Ok(DiffStyle::Unified),
+      "side-by-side" => Ok(DiffStyle::SideBySide),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        DiffStyle::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// How a diff is printed.
+#[derive(Clone, Copy, Debug)]
+pub struct Rendering {
+  /// The layout of the old and new lines.
+  pub style: DiffStyle,
+  /// The number of unchanged lines shown before and after each change.
+  pub context: usize,
+  /// Whether the words that changed within changed lines are highlighted.
+  pub words: bool,
+  /// Whether the diff is colored for a terminal.
+  pub color: bool,
+}
+
+impl Default for Rendering {
+  fn default() -> Self {
+    Rendering {
+      style: DiffStyle::Unified,
+      context: DEFAULT_CONTEXT,
+      words: false,
+      color: false,
+    }
+  }
+}
+
+/// Returns a diff of the changes from the document `old`, read from `filename`, to its formatted
+/// text `new`, printed as `rendering` directs, or an empty string if they are the same.
+pub fn file(filename: &str, old: &str, new: &str, rendering: &Rendering) -> String {
+  let new_label = format!("{} (formatted)", filename);
+  labeled(filename, &new_label, old, new, rendering)
+}
+
+/// Returns a diff of the changes from the text `old`, labeled `old_label` in the file headers, to
+/// the text `new`, labeled `new_label`, printed as `rendering` directs, or an empty string if they
+/// are the same.
+pub fn labeled(
+  old_label: &str,
+  new_label: &str,
+  old: &str,
+  new: &str,
+  rendering: &Rendering,
+) -> String {
+  if old == new {
+    return String::new();
+  }
+  // Each line keeps its line break, so a change to the last line break is a change to the line.
+  let old = old.split_inclusive('\n').collect::<Vec<_>>();
+  let new = new.split_inclusive('\n').collect::<Vec<_>>();
+  let ops = lines(&old, &new, usize::MAX).unwrap_or_default();
+
+  // The ranges of steps shown in each hunk, with their context.
+  let mut hunks: Vec<(usize, usize)> = Vec::new();
+  for (index, op) in ops.iter().enumerate() {
+    if *op == Op::Equal {
+      continue;
+    }
+    let (start, end) = (
+      index.saturating_sub(rendering.context),
+      (index + 1 + rendering.context).min(ops.len()),
+    );
+    match hunks.last_mut() {
+      Some(last) if start <= last.1 => last.1 = end,
+      _ => hunks.push((start, end)),
+    }
+  }
+  // The number of old and new lines before each step.
+  let mut positions = Vec::with_capacity(ops.len() + 1);
+  let (mut x, mut y) = (0, 0);
+  for op in &ops {
+    positions.push((x, y));
+    match op {
+      Op::Equal => (x, y) = (x + 1, y + 1),
+      Op::Delete => x += 1,
+      Op::Insert => y += 1,
+    }
+  }
+  positions.push((x, y));
+
+  let paint = |text: String, code: &str| paint(text, code, rendering.color);
+  let mut diff = String::new();
+  let _ = writeln!(
+    diff,
+    "{}",
+    paint(format!("--- {}", old_label), HEADER_COLOR)
+  );
+  let _ = writeln!(
+    diff,
+    "{}",
+    paint(format!("+++ {}", new_label), HEADER_COLOR)
+  );
+  let rows = hunks
+    .iter()
+    .map(|&(start, end)| rows(&ops[start..end], &positions[start..end]))
+    .collect::<Vec<_>>();
+  let layout = Layout::new(&old, &new, &rows, rendering);
+  for (&(start, end), rows) in hunks.iter().zip(&rows) {
+    let ((old_start, new_start), (old_end, new_end)) = (positions[start], positions[end]);
+    // A hunk with no lines on one side starts after the line before it.
+    let first = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+    let _ = writeln!(
+      diff,
+      "{}",
+      paint(
+        format!(
+          "@@ -{},{} +{},{} @@",
+          first(old_start, old_end - old_start),
+          old_end - old_start,
+          first(new_start, new_end - new_start),
+          new_end - new_start
+        ),
+        HUNK_COLOR
+      )
+    );
+    match rendering.style {
+      DiffStyle::Unified => unified_hunk(
+        &mut diff,
+        &old,
+        &new,
+        &ops[start..end],
+        &positions[start..end],
+        rows,
+        rendering,
+      ),
+      DiffStyle::SideBySide => side_by_side_hunk(&mut diff, &old, &new, rows, &layout, rendering),
+    }
+  }
+  diff
+}
+
+/// A row of a hunk.
+#[derive(Clone, Copy, Debug)]
+enum Row {
+  /// An unchanged line, by its indexes in the old and new texts.
+  Equal(usize, usize),
+  /// A deleted line, an inserted line, or a deleted line and the inserted line that replaces it,
+  /// by their indexes in the old and new texts.
+  Changed(Option<usize>, Option<usize>),
+}
+
+/// Returns the rows of the hunk of the steps `ops`, before each of which are the numbers of old and
+/// new lines in `positions`. In each run of changes, the deleted lines are paired with the
+/// inserted lines in order.
+fn rows(ops: &[Op], positions: &[(usize, usize)]) -> Vec<Row> {
+  let mut rows = Vec::new();
+  let (mut deleted, mut inserted) = (Vec::new(), Vec::new());
+  for (index, (op, &(x, y))) in ops.iter().zip(positions).enumerate() {
+    match op {
+      Op::Equal => {}
+      Op::Delete => deleted.push(x),
+      Op::Insert => inserted.push(y),
+    }
+    if *op == Op::Equal || index + 1 == ops.len() {
+      for pair in 0..deleted.len().max(inserted.len()) {
+        rows.push(Row::Changed(
+          deleted.get(pair).copied(),
+          inserted.get(pair).copied(),
+        ));
+      }
+      deleted.clear();
+      inserted.clear();
+    }
+    if *op == Op::Equal {
+      rows.push(Row::Equal(x, y));
+    }
+  }
+  rows
+}
+
+/// Writes the lines of the hunk of the steps `ops` (with the numbers of old and new lines before
+/// each in `positions`, and the `rows` they make) to `diff`, one after the other, each after a
+/// marker: ` ` for an unchanged line, `-` for a deleted line, and `+` for an inserted line.
+fn unified_hunk(
+  diff: &mut String,
+  old: &[&str],
+  new: &[&str],
+  ops: &[Op],
+  positions: &[(usize, usize)],
+  rows: &[Row],
+  rendering: &Rendering,
+) {
+  // The line each changed line is paired with.
+  let (mut old_partners, mut new_partners) = (HashMap::new(), HashMap::new());
+  for row in rows {
+    if let Row::Changed(Some(x), Some(y)) = *row {
+      old_partners.insert(x, y);
+      new_partners.insert(y, x);
+    }
+  }
+  for (op, &(x, y)) in ops.iter().zip(positions) {
+    let (marker, line, code, highlights) = match op {
+      Op::Equal => (' ', old[x], "", Vec::new()),
+      Op::Delete => (
+        '-',
+        old[x],
+        DELETE_COLOR,
+        old_partners
+          .get(&x)
+          .and_then(|&y| changed_words(old[x], new[y], rendering))
+          .map_or_else(Vec::new, |words| words.0),
+      ),
+      Op::Insert => (
+        '+',
+        new[y],
+        INSERT_COLOR,
+        new_partners
+          .get(&y)
+          .and_then(|&x| changed_words(old[x], new[y], rendering))
+          .map_or_else(Vec::new, |words| words.1),
+      ),
+    };
+    let (text, _) = highlight(line, &highlights, *op == Op::Delete, rendering.color);
+    let text = format!("{}{}", marker, text);
+    let _ = writeln!(
+      diff,
+      "{}",
+      if code.is_empty() {
+        text
+      } else {
+        paint(text, code, rendering.color)
+      }
+    );
+    if !line.ends_with('\n') {
+      diff.push_str(NO_NEWLINE);
+      diff.push('\n');
+    }
+  }
+}
+
+/// The widths of the columns of a side-by-side diff.
+#[derive(Debug)]
+struct Layout {
+  /// The width of the line numbers.
+  numbers: usize,
+  /// The width of the old lines (or the notes in their place).
+  text: usize,
+}
+
+impl Layout {
+  /// Returns the widths of the columns of the side-by-side diff of the lines `old` and `new` with
+  /// the `rows` of each hunk, printed as `rendering` directs.
+  fn new(old: &[&str], new: &[&str], rows: &[Vec<Row>], rendering: &Rendering) -> Self {
+    let mut text = 0;
+    if rendering.style == DiffStyle::SideBySide {
+      for row in rows.iter().flatten() {
+        let (Some(cell), _, _) = cells(old, new, *row, rendering) else {
+          continue;
+        };
+        text = text.max(cell.width);
+        if !old[cell.index].ends_with('\n') {
+          text = text.max(NO_NEWLINE.len());
+        }
+      }
+    }
+    Layout {
+      numbers: old.len().max(new.len()).to_string().len(),
+      text,
+    }
+  }
+}
+
+/// A line in a column of a side-by-side diff.
+#[derive(Debug)]
+struct Cell {
+  /// The index of the line.
+  index: usize,
+  /// The text shown, which may be colored.
+  text: String,
+  /// The number of characters shown.
+  width: usize,
+}
+
+/// Returns the old and new lines of the `row` in a side-by-side diff from the lines `old` to `new`,
+/// printed as `rendering` directs, and the separator between them.
+fn cells(
+  old: &[&str],
+  new: &[&str],
+  row: Row,
+  rendering: &Rendering,
+) -> (Option<Cell>, Option<Cell>, char) {
+  let (x, y, separator) = match row {
+    Row::Equal(x, y) => (Some(x), Some(y), ' '),
+    Row::Changed(Some(x), Some(y)) => (Some(x), Some(y), '|'),
+    Row::Changed(x, None) => (x, None, '<'),
+    Row::Changed(None, y) => (None, y, '>'),
+  };
+  let words = match (x, y, separator) {
+    (Some(x), Some(y), '|') => changed_words(old[x], new[y], rendering),
+    _ => None,
+  };
+  let cell = |index: usize, lines: &[&str], deleted: bool| {
+    let highlights = match (&words, deleted) {
+      (Some(words), true) => &words.0[..],
+      (Some(words), false) => &words.1[..],
+      (None, _) => &[],
+    };
+    let (text, width) = highlight(lines[index], highlights, deleted, rendering.color);
+    let text = match (separator, deleted) {
+      (' ', _) => text,
+      (_, true) => paint(text, DELETE_COLOR, rendering.color),
+      (_, false) => paint(text, INSERT_COLOR, rendering.color),
+    };
+    Cell { index, text, width }
+  };
+  (
+    x.map(|x| cell(x, old, true)),
+    y.map(|y| cell(y, new, false)),
+    separator,
+  )
+}
+
+/// Writes the `rows` of a hunk of the diff from the lines `old` to `new` to `diff`, with each old
+/// line beside the line that replaces it, in the columns of `layout`.
+fn side_by_side_hunk(
+  diff: &mut String,
+  old: &[&str],
+  new: &[&str],
+  rows: &[Row],
+  layout: &Layout,
+  rendering: &Rendering,
+) {
+  let number = |cell: &Option<Cell>| match cell {
+    Some(cell) => format!("{:>1$}", cell.index + 1, layout.numbers),
+    None => " ".repeat(layout.numbers),
+  };
+  for &row in rows {
+    let (old_cell, new_cell, separator) = cells(old, new, row, rendering);
+    let (old_text, old_width) = old_cell
+      .as_ref()
+      .map_or(("", 0), |cell| (&cell.text[..], cell.width));
+    let line = format!(
+      "{} {}{} {} {} {}",
+      number(&old_cell),
+      old_text,
+      " ".repeat(layout.text - old_width),
+      separator,
+      number(&new_cell),
+      new_cell.as_ref().map_or("", |cell| &cell.text[..])
+    );
+    let _ = writeln!(diff, "{}", line.trim_end());
+    let old_note = old_cell.is_some_and(|cell| !old[cell.index].ends_with('\n'));
+    let new_note = new_cell.is_some_and(|cell| !new[cell.index].ends_with('\n'));
+    if old_note || new_note {
+      let note = |shown: bool| if shown { NO_NEWLINE } else { "" };
+      let numbers = " ".repeat(layout.numbers);
+      let line = format!(
+        "{} {:<width$}   {} {}",
+        numbers,
+        note(old_note),
+        numbers,
+        note(new_note),
+        width = layout.text
+      );
+      let _ = writeln!(diff, "{}", line.trim_end());
+    }
+  }
+}
+
+/// Returns `text` colored with the escape sequence `code`, if `color` is true.
+fn paint(text: String, code: &str, color: bool) -> String {
+  match color {
+    true => format!("{}{}{}", code, text, RESET),
+    false => text,
+  }
+}
+
+/// The byte ranges of the words that changed in a line.
+type Highlights = Vec<Range<usize>>;
+
+/// Returns the byte ranges of the words that changed in the line `old` and in the line `new` that
+/// replaces it, or `None` if the words are not highlighted (or the lines share too little to be
+/// shown as an edit of one another).
+fn changed_words(old: &str, new: &str, rendering: &Rendering) -> Option<(Highlights, Highlights)> {
+  if !rendering.words {
+    return None;
+  }
+  let old = words(old.strip_suffix('\n').unwrap_or(old));
+  let new = words(new.strip_suffix('\n').unwrap_or(new));
+  let old_words = old.iter().map(|(_, word)| *word).collect::<Vec<_>>();
+  let new_words = new.iter().map(|(_, word)| *word).collect::<Vec<_>>();
+  let ops = lines(&old_words, &new_words, MAX_WORD_CHANGES)?;
+  let (mut deleted, mut inserted) = (Vec::new(), Vec::new());
+  let add = |ranges: &mut Highlights, (start, word): (usize, &str)| match ranges.last_mut() {
+    Some(last) if last.end == start => last.end = start + word.len(),
+    _ => ranges.push(start..start + word.len()),
+  };
+  // The number of characters other than whitespace in the unchanged words, and in each line.
+  let (mut unchanged, mut old_len, mut new_len) = (0, 0, 0);
+  let (mut x, mut y) = (0, 0);
+  for op in ops {
+    match op {
+      Op::Equal => {
+        unchanged += old[x].1.trim().len();
+        old_len += old[x].1.trim().len();
+        new_len += new[y].1.trim().len();
+        (x, y) = (x + 1, y + 1);
+      }
+      Op::Delete => {
+        old_len += old[x].1.trim().len();
+        add(&mut deleted, old[x]);
+        x += 1;
+      }
+      Op::Insert => {
+        new_len += new[y].1.trim().len();
+        add(&mut inserted, new[y]);
+        y += 1;
+      }
+    }
+  }
+  // Lines that share no more than half of their text are shown as replaced, not as edited.
+  (unchanged * 2 > old_len.max(new_len)).then_some((deleted, inserted))
+}
+
+/// Returns the words of `line`, with their byte offsets: each run of letters, digits, and
+/// underscores, each run of whitespace, and each other character.
+fn words(line: &str) -> Vec<(usize, &str)> {
+  let class = |c: char| match c {
+    _ if c.is_alphanumeric() || c == '_' => 0,
+    _ if c.is_whitespace() => 1,
+    _ => 2,
+  };
+  let mut words: Vec<(usize, &str)> = Vec::new();
+  let mut previous = None;
+  for (offset, c) in line.char_indices() {
+    match words.last_mut() {
+      Some((start, word)) if previous == Some(class(c)) && class(c) != 2 => {
+        *word = &line[*start..offset + c.len_utf8()];
+      }
+      _ => words.push((offset, &line[offset..offset + c.len_utf8()])),
+    }
+    previous = Some(class(c));
+  }
+  words
+}
+
+/// Returns the text of `line`, without its line break, with the byte ranges `highlights`
+/// highlighted as deleted words (if `deleted` is true) or inserted words, and the number of
+/// characters it shows.
+fn highlight(
+  line: &str,
+  highlights: &[Range<usize>],
+  deleted: bool,
+  color: bool,
+) -> (String, usize) {
+  let line = line.strip_suffix('\n').unwrap_or(line);
+  let (open, close) = match (color, deleted) {
+    (true, _) => (WORD_COLOR, WORD_RESET),
+    (false, true) => ("[-", "-]"),
+    (false, false) => ("{+", "+}"),
+  };
+  let mut text = String::with_capacity(line.len());
+  let mut copied = 0;
+  for range in highlights {
+    text.push_str(&line[copied..range.start]);
+    text.push_str(open);
+    text.push_str(&line[range.clone()]);
+    text.push_str(close);
+    copied = range.end;
+  }
+  text.push_str(&line[copied..]);
+  let markers = match color {
+    true => 0,
+    false => highlights.len() * (open.len() + close.len()),
+  };
+  (text, line.chars().count() + markers)
+}
+
+/// A step of a line diff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+  /// A line that is in both texts.
+  Equal,
+  /// A line that is only in the old text.
+  Delete,
+  /// A line that is only in the new text.
+  Insert,
+}
+
+/// Returns the shortest sequence of steps that turns the lines `old` into `new` (with Myers'
+/// algorithm), or None if it has more than `max_changes` inserted and deleted lines.
+pub fn lines(old: &[&str], new: &[&str], max_changes: usize) -> Option<Vec<Op>> {
+  let (n, m) = (old.len() as isize, new.len() as isize);
+  let max = (n + m) as usize;
+  let offset = max as isize + 1;
+  let mut v = vec![0isize; 2 * max + 3];
+  // The values of `v` for the diagonals -d..=d before each step d, for backtracking.
+  let mut trace: Vec<Vec<isize>> = Vec::new();
+  for d in 0..=max.min(max_changes) as isize {
+    trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+    for k in (-d..=d).step_by(2) {
+      let down = k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]);
+      let mut x = match down {
+        true => v[(offset + k + 1) as usize],
+        false => v[(offset + k - 1) as usize] + 1,
+      };
+      let mut y = x - k;
+      while x < n && y < m && old[x as usize] == new[y as usize] {
+        x += 1;
+        y += 1;
+      }
+      v[(offset + k) as usize] = x;
+      if x >= n && y >= m {
+        return Some(backtrack(&trace, n, m));
+      }
+    }
+  }
+  None
+}
+
+/// Returns the steps of the diff found by [lines()](fn.lines.html) from its `trace`, ending at the
+/// end of both texts, of `n` and `m` lines.
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Op> {
+  let mut ops = Vec::new();
+  let (mut x, mut y) = (n, m);
+  for (d, v) in trace.iter().enumerate().rev() {
+    let d = d as isize;
+    let at = |k: isize| v[(k + d) as usize];
+    let k = x - y;
+    let (prev_x, prev_y) = match d {
+      0 => (0, 0),
+      _ => {
+        let down = k == -d || (k != d && at(k - 1) < at(k + 1));
+        let prev_k = if down { k + 1 } else { k - 1 };
+        (at(prev_k), at(prev_k) - prev_k)
+      }
+    };
+    while x > prev_x && y > prev_y {
+      ops.push(Op::Equal);
+      x -= 1;
+      y -= 1;
+    }
+    if d > 0 {
+      ops.push(if x == prev_x { Op::Insert } else { Op::Delete });
+    }
+    x = prev_x;
+    y = prev_y;
+  }
+  ops.reverse();
+  ops
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn finds_the_shortest_line_diff() {
+    use Op::*;
+    assert_eq!(
+      lines(&["a", "b", "c", "d"], &["a", "c", "x", "d"], usize::MAX),
+      Some(vec![Equal, Delete, Equal, Insert, Equal])
+    );
+    assert_eq!(lines(&[], &["a"], usize::MAX), Some(vec![Insert]));
+    assert_eq!(lines(&["a"], &[], usize::MAX), Some(vec![Delete]));
+    assert_eq!(lines(&["a"], &["a"], 0), Some(vec![Equal]));
+  }
+
+  #[test]
+  fn gives_up_past_the_maximum_number_of_changes() {
+    assert_eq!(lines(&["a", "b"], &["c", "d"], 3), None);
+    assert!(lines(&["a", "b"], &["c", "d"], 4).is_some());
+  }
+
+  #[test]
+  fn prints_unified_diffs() {
+    let old = "{\n  a: 1,\n  b: 2,\n}\n";
+    let new = "{\n  a: 1,\n  b: 3,\n  c: 4,\n}\n";
+    assert_eq!(
+      file("a.json5", old, new, &Rendering::default()),
+      "\
+--- a.json5
++++ a.json5 (formatted)
+@@ -1,4 +1,5 @@
+ {
+   a: 1,
+-  b: 2,
++  b: 3,
++  c: 4,
+ }
+"
+    );
+  }
+
+  #[test]
+  fn prints_nothing_for_equal_texts() {
+    assert_eq!(file("a.json5", "{}\n", "{}\n", &Rendering::default()), "");
+  }
+
+  /// The lines of a document, and the formatted document with its third line changed and without
+  /// the last line break.
+  const COUNTED: (&str, &str) = (
+    "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n",
+    "1\n2\nthree\n4\n5\n6\n7\n8\n9\nten",
+  );
+
+  /// A document, and the formatted document with a changed quote, a changed number, and a deleted
+  /// line.
+  const EDITED: (&str, &str) = (
+    "{\n  name: 'formatter',\n  version: 1,\n  deleted: true,\n}\n",
+    "{\n  name: \"formatter\",\n  version: 2,\n}\n",
+  );
+
+  #[test]
+  fn shows_the_given_context_around_each_change() {
+    let rendering = Rendering {
+      context: 1,
+      ..Rendering::default()
+    };
+    assert_eq!(
+      file("a.json5", COUNTED.0, COUNTED.1, &rendering),
+      "\
+--- a.json5
++++ a.json5 (formatted)
+@@ -2,3 +2,3 @@
+ 2
+-3
++three
+ 4
+@@ -9,2 +9,2 @@
+ 9
+-10
++ten
+\\ No newline at end of file
+"
+    );
+  }
+
+  #[test]
+  fn prints_side_by_side_diffs() {
+    let rendering = Rendering {
+      style: DiffStyle::SideBySide,
+      context: 1,
+      ..Rendering::default()
+    };
+    assert_eq!(
+      file("a.json5", COUNTED.0, COUNTED.1, &rendering),
+      "\
+--- a.json5
++++ a.json5 (formatted)
+@@ -2,3 +2,3 @@
+ 2 2     2 2
+ 3 3  |  3 three
+ 4 4     4 4
+@@ -9,2 +9,2 @@
+ 9 9     9 9
+10 10 | 10 ten
+           \\ No newline at end of file
+"
+    );
+  }
+
+  #[test]
+  fn marks_the_changed_words_of_changed_lines() {
+    let rendering = Rendering {
+      words: true,
+      ..Rendering::default()
+    };
+    assert_eq!(
+      file("a.json5", EDITED.0, EDITED.1, &rendering),
+      "\
+--- a.json5
++++ a.json5 (formatted)
+@@ -1,5 +1,4 @@
+ {
+-  name: [-'-]formatter[-'-],
+-  version: [-1-],
+-  deleted: true,
++  name: {+\"+}formatter{+\"+},
++  version: {+2+},
+ }
+"
+    );
+    let rendering = Rendering {
+      style: DiffStyle::SideBySide,
+      ..rendering
+    };
+    assert_eq!(
+      file("a.json5", EDITED.0, EDITED.1, &rendering),
+      "\
+--- a.json5
++++ a.json5 (formatted)
+@@ -1,5 +1,4 @@
+1 {                              1 {
+2   name: [-'-]formatter[-'-], | 2   name: {+\"+}formatter{+\"+},
+3   version: [-1-],            | 3   version: {+2+},
+4   deleted: true,             <
+5 }                              4 }
+"
+    );
+  }
+
+  #[test]
+  fn highlights_the_changed_words_of_colored_diffs() {
+    let rendering = Rendering {
+      words: true,
+      color: true,
+      ..Rendering::default()
+    };
+    assert_eq!(
+      file("a.json5", "a: 1,\n", "a: 2,\n", &rendering),
+      "\x1b[1m--- a.json5\x1b[0m\n\x1b[1m+++ a.json5 (formatted)\x1b[0m\n\
+       \x1b[36m@@ -1,1 +1,1 @@\x1b[0m\n\
+       \x1b[31m-a: \x1b[7m1\x1b[27m,\x1b[0m\n\
+       \x1b[32m+a: \x1b[7m2\x1b[27m,\x1b[0m\n"
+    );
+  }
+
+  #[test]
+  fn shows_lines_that_share_little_as_replaced() {
+    let rendering = Rendering {
+      words: true,
+      ..Rendering::default()
+    };
+    assert_eq!(
+      file("a.json5", "alpha beta\n", "gamma delta\n", &rendering),
+      "--- a.json5\n+++ a.json5 (formatted)\n@@ -1,1 +1,1 @@\n-alpha beta\n+gamma delta\n"
+    );
+  }
+
+  #[test]
+  fn parses_diff_styles() {
+    for name in DiffStyle::VARIANTS {
+      assert!(name.parse::<DiffStyle>().is_ok());
+    }
+    assert_eq!(
+      "split".parse::<DiffStyle>().unwrap_err().to_string(),
+      "Expected one of: unified, side-by-side"
+    );
+  }
+}
@@ -0,0 +1,319 @@
+//! Splits an input buffer into the JSON5 document to format and any surrounding source text that
+//! is passed through to the output verbatim.
+//!
+//! With the `front_matter` option (set by default for `.md` files), the document is the front
+//! matter of a Markdown file: the lines between a first line of `---` (or `---json5` or `---json`)
+//! and the next line of `---`. The fences and the rest of the file are passed through unchanged,
+//! and a file without front matter is written unchanged.
+//!
+//!     ---json5
+//!     {title: "Getting started", tags: ["intro"]}
+//!     ---
+//!     # Getting started
+
+use crate::{
+  chunked::{self, Chunking},
+  codes,
+  format::FormatError,
+  lexer,
+};
+use json5format::{FormatOptions, Json5Format, ParsedDocument};
+use std::io::Write;
+
+/// Options that determine which parts of an input buffer belong to the JSON5 document.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SourceOptions {
+  /// Retain content after the end of the document's top-level value, instead of reporting it as an
+  /// error.
+  pub allow_trailing_garbage: bool,
+
+  /// The number of leading lines to pass through without parsing. If `None`, a single leading line
+  /// is passed through only if it starts with `#!`.
+  pub preamble_lines: Option<usize>,
+
+  /// Format only the front matter of a Markdown file (see the module documentation).
+  pub front_matter: bool,
+}
+
+/// The opening fences of front matter.
+const FRONT_MATTER_OPENERS: &[&str] = &["---", "---json5", "---json"];
+
+/// The closing fence of front matter.
+const FRONT_MATTER_CLOSER: &str = "---";
+
+/// A parsed JSON5 document, along with any source text that is not part of the document itself.
+pub struct Document {
+  /// The parsed JSON5 document, or `None` if it was parsed in chunks (see the
+  /// [chunked](../chunked/index.html) module).
+  pub parsed: Option<ParsedDocument>,
+
+  /// The JSON5 source of the document, with any preamble replaced by blank lines and without any
+  /// trailing content.
+  pub source: String,
+
+  /// Leading lines (such as a `#!` line, or the opening fence of front matter) that are not JSON5,
+  /// written before the formatted output unchanged.
+  pub preamble: Option<String>,
+
+  /// Content following the document's top-level value, if it was allowed by
+  /// `--allow-trailing-garbage`, and the rest of the file after its front matter (from the closing
+  /// fence on). It is appended to the formatted output unchanged.
+  pub trailing: Option<String>,
+
+  /// How a very large document is split to be parsed and formatted on several threads.
+  chunking: Chunking,
+}
+
+impl Document {
+  /// Parses the JSON5 document in `buffer`, after setting aside any preamble lines selected by
+  /// `options`. A JSON5 document holds a single top-level value, so any content after that value
+  /// (other than whitespace and comments) is reported as an error at its exact position, unless
+  /// `options.allow_trailing_garbage` is true, in which case it is retained in `trailing`. A very
+  /// large document may be parsed on up to `threads` threads.
+  pub fn parse(
+    mut buffer: String,
+    filename: String,
+    options: &SourceOptions,
+    threads: usize,
+  ) -> Result<Self, FormatError> {
+    let preamble_len = preamble_len(&buffer, options);
+    let body_end = body_end(&buffer, options);
+    // The closing fence of the front matter, and the rest of the file.
+    let rest = (body_end < buffer.len()).then(|| buffer.split_off(body_end));
+    let preamble = if preamble_len > 0 {
+      // Replace the preamble with blank lines (which the formatter ignores), so parse errors still
+      // report line numbers relative to the start of the file.
+      let body = buffer.split_off(preamble_len);
+      let padding = "\n".repeat(buffer.matches('\n').count());
+      Some(std::mem::replace(&mut buffer, padding + &body))
+    } else {
+      None
+    };
+
+    let tokens = lexer::tokenize(&buffer);
+    let trailing_token = lexer::trailing_content(&tokens);
+    let trailing_start = trailing_token.map(|token| token.start);
+    let trailing_error = match trailing_token {
+      Some(token) if !options.allow_trailing_garbage => Some(FormatError::Parse {
+        code: &codes::TRAILING_CONTENT,
+        file: filename.clone(),
+        line: token.line,
+        col: token.col,
+        message: format!(
+          "Unexpected content after the end of the document (use --allow-trailing-garbage to \
+           preserve it):\n{}",
+          error_context(&buffer, token.line, token.col)
+        ),
+      }),
+      _ => None,
+    };
+
+    let trailing = match (trailing_start.map(|start| buffer.split_off(start)), rest) {
+      (Some(trailing), Some(rest)) => Some(trailing + &rest),
+      (trailing, rest) => trailing.or(rest),
+    };
+    let chunking = Chunking::new(threads);
+    let parsed = parse_json5(&buffer, &filename, chunking)?;
+    if let Some(err) = trailing_error {
+      return Err(err);
+    }
+    Ok(Self {
+      parsed,
+      source: buffer,
+      preamble,
+      trailing,
+      chunking,
+    })
+  }
+
+  /// Replaces the JSON5 source of the document with `source` (an edited version of `self.source`,
+  /// read from `filename`), and parses it again.
+  pub fn set_source(&mut self, source: String, filename: &str) -> Result<(), FormatError> {
+    self.parsed = parse_json5(&source, filename, self.chunking)?;
+    self.source = source;
+    Ok(())
+  }
+
+  /// Returns the part of `formatted` (the formatted text of the document) after any preamble and
+  /// before any trailing content.
+  pub fn body<'a>(&self, formatted: &'a str) -> &'a str {
+    let formatted = match &self.preamble {
+      Some(preamble) => formatted
+        .strip_prefix(preamble.as_str())
+        .unwrap_or(formatted),
+      None => formatted,
+    };
+    match &self.trailing {
+      Some(trailing) => formatted
+        .strip_suffix(trailing.as_str())
+        .unwrap_or(formatted),
+      None => formatted,
+    }
+  }
+
+  /// Returns `formatted` (the formatted text of the document) with the part after any preamble and
+  /// before any trailing content replaced by `map` of that part.
+  pub fn map_body(&self, formatted: &str, map: impl FnOnce(&str) -> String) -> String {
+    let body = self.body(formatted);
+    let start = match &self.preamble {
+      Some(preamble) if formatted.starts_with(preamble.as_str()) => preamble.len(),
+      _ => 0,
+    };
+    let end = start + body.len();
+    format!("{}{}{}", &formatted[..start], map(body), &formatted[end..])
+  }
+
+  /// Formats the document, read from `filename`, with the given `options`, and restores any
+  /// preamble and trailing content around the result (see
+  /// [format_to_writer()](#method.format_to_writer)).
+  pub fn format(&self, options: FormatOptions, filename: &str) -> Result<Vec<u8>, FormatError> {
+    // Without a preamble, json5format's buffer becomes the output, instead of being copied into it.
+    if let (None, Some(parsed)) = (&self.preamble, &self.parsed) {
+      let mut bytes = format_whole(parsed, options, filename)?;
+      if let Some(trailing) = &self.trailing {
+        bytes.extend_from_slice(trailing.as_bytes());
+      }
+      return Ok(bytes);
+    }
+    let mut bytes = Vec::with_capacity(self.source.len());
+    self.format_to_writer(options, filename, &mut bytes)?;
+    Ok(bytes)
+  }
+
+  /// Formats the document, read from `filename`, with the given `options`, and writes the result,
+  /// with any preamble and trailing content around it, to `writer`. A document formatted in chunks
+  /// is written one chunk at a time, without joining the chunks in memory first.
+  pub fn format_to_writer(
+    &self,
+    options: FormatOptions,
+    filename: &str,
+    writer: &mut impl Write,
+  ) -> Result<(), FormatError> {
+    let write_error = |error| FormatError::Io {
+      file: filename.to_string(),
+      error,
+    };
+    if let Some(preamble) = &self.preamble {
+      writer.write_all(preamble.as_bytes()).map_err(write_error)?;
+    }
+    match &self.parsed {
+      Some(parsed) => writer
+        .write_all(&format_whole(parsed, options, filename)?)
+        .map_err(write_error)?,
+      None => {
+        let chunks = chunked::chunks(&self.source, self.chunking).unwrap_or_default();
+        let threads = self.chunking.threads;
+        if !chunked::format(&chunks, &options, threads, filename, writer)? {
+          let parsed = parse_whole(self.source.clone(), filename.to_string())?;
+          writer
+            .write_all(&format_whole(&parsed, options, filename)?)
+            .map_err(write_error)?;
+        }
+      }
+    }
+    if let Some(trailing) = &self.trailing {
+      writer.write_all(trailing.as_bytes()).map_err(write_error)?;
+    }
+    Ok(())
+  }
+}
+
+/// Parses `buffer`, read from `filename`, with json5format, and returns the parsed document, or
+/// `None` if the document is large enough to be parsed in the chunks that `chunking` selects and
+/// each chunk can be parsed.
+fn parse_json5(
+  buffer: &str,
+  filename: &str,
+  chunking: Chunking,
+) -> Result<Option<ParsedDocument>, FormatError> {
+  if let Some(chunks) = chunked::chunks(buffer, chunking) {
+    if chunked::parse(&chunks, filename, chunking.threads) {
+      return Ok(None);
+    }
+  }
+  parse_whole(buffer.to_string(), filename.to_string()).map(Some)
+}
+
+/// Parses `buffer`, read from `filename`, with json5format as a single document.
+fn parse_whole(buffer: String, filename: String) -> Result<ParsedDocument, FormatError> {
+  ParsedDocument::from_string(buffer, Some(filename.clone()))
+    .map_err(|err| FormatError::json5format(err, &filename))
+}
+
+/// Formats the document `parsed`, read from `filename`, with json5format and the `options`, as a
+/// single document.
+fn format_whole(
+  parsed: &ParsedDocument,
+  options: FormatOptions,
+  filename: &str,
+) -> Result<Vec<u8>, FormatError> {
+  Json5Format::with_options(options)
+    .and_then(|format| format.to_utf8(parsed))
+    .map_err(|err| FormatError::json5format(err, filename))
+}
+
+/// Returns the 1-based line number and the text of the first line of `buffer` after the preamble
+/// selected by `options`.
+pub fn first_line<'a>(buffer: &'a str, options: &SourceOptions) -> (usize, &'a str) {
+  let preamble_len = preamble_len(buffer, options);
+  let line = buffer[..preamble_len].matches('\n').count() + 1;
+  let text = buffer[preamble_len..].lines().next().unwrap_or("");
+  (line, text)
+}
+
+/// Returns the length in bytes of the preamble at the start of `buffer`, selected by `options`: the
+/// opening fence of its front matter, if it is formatted and found, or otherwise the first
+/// `preamble_lines` lines if given, or otherwise the first line if it starts with `#!`. The
+/// preamble includes the line break that ends its last line.
+pub fn preamble_len(buffer: &str, options: &SourceOptions) -> usize {
+  if let Some((start, _)) = front_matter(buffer).filter(|_| options.front_matter) {
+    return start;
+  }
+  let lines = match options.preamble_lines {
+    Some(lines) => lines,
+    None if buffer.starts_with("#!") => 1,
+    None => 0,
+  };
+  if lines == 0 {
+    return 0;
+  }
+  match buffer.match_indices('\n').nth(lines - 1) {
+    Some((index, _)) => index + 1,
+    None => buffer.len(),
+  }
+}
+
+/// Returns the byte offset of the end of the JSON5 source in `buffer`: the start of the closing
+/// fence of its front matter, if it is formatted (as selected by `options`) and found, or otherwise
+/// the length of `buffer`.
+pub fn body_end(buffer: &str, options: &SourceOptions) -> usize {
+  match front_matter(buffer).filter(|_| options.front_matter) {
+    Some((_, end)) => end,
+    None => buffer.len(),
+  }
+}
+
+/// Returns the byte offsets of the start and end of the front matter of the Markdown file
+/// `buffer`: the lines between its opening fence (its first line) and its closing fence, if any.
+pub fn front_matter(buffer: &str) -> Option<(usize, usize)> {
+  let mut lines = buffer.split_inclusive('\n');
+  let opener = lines.next()?;
+  if !FRONT_MATTER_OPENERS.contains(&opener.trim_end()) {
+    return None;
+  }
+  let mut offset = opener.len();
+  for line in lines {
+    if line.trim_end() == FRONT_MATTER_CLOSER {
+      return Some((opener.len(), offset));
+    }
+    offset += line.len();
+  }
+  None
+}
+
+/// Returns the given 1-based `line` of `buffer`, followed by a second line with a caret under the
+/// 1-based column `col`, in the same style as json5format's parse errors.
+pub fn error_context(buffer: &str, line: usize, col: usize) -> String {
+  let text = buffer.lines().nth(line - 1).unwrap_or("");
+  format!("{}\n{}^", text, " ".repeat(col - 1))
+}
@@ -0,0 +1,52 @@
+//! Replaces spans of a document's source text, for features that change parts of a document
+//! before it is formatted while preserving everything else, including comments.
+
+use std::ops::Range;
+
+/// A set of non-overlapping replacements of byte ranges in a source text.
+#[derive(Debug, Default)]
+pub struct Edits {
+  /// Each replaced byte range and its replacement text, in the order they were added.
+  edits: Vec<(Range<usize>, String)>,
+}
+
+impl Edits {
+  /// Replaces the bytes in `range` with `text`.
+  pub fn replace(&mut self, range: Range<usize>, text: impl Into<String>) {
+    self.edits.push((range, text.into()));
+  }
+
+  /// Inserts `text` at the byte `offset`. Text inserted at the same offset appears in the order
+  /// it was inserted.
+  pub fn insert(&mut self, offset: usize, text: impl Into<String>) {
+    self.replace(offset..offset, text);
+  }
+
+  /// Returns true if there are no edits.
+  pub fn is_empty(&self) -> bool {
+    self.edits.is_empty()
+  }
+
+  /// Returns the replaced byte ranges and their replacement texts, in the order they were added.
+  pub fn into_vec(self) -> Vec<(Range<usize>, String)> {
+    self.edits
+  }
+
+  /// Returns `source` with the edits applied. An insertion at the start of a replaced range
+  /// appears before the replacement.
+  pub fn apply(mut self, source: &str) -> String {
+    self
+      .edits
+      .sort_by_key(|(range, _)| (range.start, range.end));
+    let mut result = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (range, text) in self.edits {
+      debug_assert!(range.start >= copied, "edits must not overlap");
+      result.push_str(&source[copied..range.start]);
+      result.push_str(&text);
+      copied = range.end;
+    }
+    result.push_str(&source[copied..]);
+    result
+  }
+}
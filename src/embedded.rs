@@ -0,0 +1,84 @@
+//! Formats JSON5 documents embedded in string values (the `--format-embedded` option), such as a
+//! policy stored as a string:
+//!
+//!     {
+//!         policy: "{\n    allow: [\n        \"read\",\n    ],\n}",
+//!     }
+//!
+//! Each string whose path matches one of the patterns is parsed as a JSON5 document and formatted
+//! with the options of the host document, and each line of the embedded document after the first
+//! is indented by the indentation of the line that holds the string, so the decoded value lines up
+//! with the host document. The string keeps its quotes, and its line breaks, quotes, and other
+//! special characters are escaped. Strings that hold only whitespace are left unchanged, and
+//! strings that are not valid JSON5 are left unchanged with a warning.
+
+use crate::{
+  codes,
+  diagnostics::Diagnostic,
+  edit::Edits,
+  glob::PathGlob,
+  syntax::{self, Node},
+};
+use json5format::{FormatOptions, Json5Format, ParsedDocument};
+
+/// Adds an edit to `edits` that replaces each string in the document `root`, read from `source`,
+/// whose path matches one of the `globs` with its value formatted with `options`, and returns a
+/// warning for each matching string that is not valid JSON5.
+pub fn format(
+  root: &Node,
+  source: &str,
+  globs: &[PathGlob],
+  options: &FormatOptions,
+  edits: &mut Edits,
+) -> Vec<Diagnostic> {
+  let mut options = options.clone();
+  options.options_by_path.clear();
+  let mut warnings = Vec::new();
+  root.walk(&mut |node, path| {
+    let Node::String { span, value } = node else {
+      return;
+    };
+    if value.trim().is_empty() || !globs.iter().any(|glob| glob.matches(path)) {
+      return;
+    }
+    let formatted = match embed(value, &options) {
+      Ok(formatted) => formatted,
+      Err(message) => {
+        warnings.push(Diagnostic::warning(
+          &codes::EMBEDDED_DOCUMENT,
+          span.line,
+          span.col,
+          format!(
+            "The string is not a valid JSON5 document, so it is not formatted \
+             (--format-embedded): {}",
+            message
+          ),
+        ));
+        return;
+      }
+    };
+    // The line of a property is indented once for each object or array it is in.
+    let indent = " ".repeat(options.indent_by * path.len());
+    let formatted = formatted.trim_end().replace('\n', &format!("\n{}", indent));
+    let quote = source[span.start..].chars().next().unwrap_or('"');
+    let literal = syntax::quote(&formatted, quote);
+    if literal != source[span.start..span.end] {
+      edits.replace(span.start..span.end, literal);
+    }
+  });
+  warnings
+}
+
+/// Returns the JSON5 document `text` formatted with `options`, or a description of why it cannot
+/// be parsed.
+fn embed(text: &str, options: &FormatOptions) -> Result<String, String> {
+  // json5format's parse errors end with the line of the error and a caret, after a `:`.
+  let parsed = ParsedDocument::from_str(text, None).map_err(|err| {
+    let message = err.to_string();
+    let line = message.lines().next().unwrap_or_default();
+    line.trim_end_matches(':').to_string()
+  })?;
+  let format = Json5Format::with_options(options.clone()).map_err(|err| err.to_string())?;
+  let bytes = format.to_utf8(&parsed).map_err(|err| err.to_string())?;
+  String::from_utf8(bytes).map_err(|err| err.to_string())
+}
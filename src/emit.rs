@@ -0,0 +1,339 @@
+//! Dumps how a document is interpreted (the `--emit` option), for debugging and for tools that
+//! build on the formatter's view of a document.
+
+use crate::{
+  json::Json,
+  lexer::{self, TokenKind},
+  syntax::{self, Comments, Node, Span},
+};
+use std::str::FromStr;
+
+/// What to write for each document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emit {
+  /// The formatted document.
+  Formatted,
+  /// The document's syntax tree as JSON, with source spans and the comments attached to each
+  /// value.
+  Ast,
+  /// The document's tokens as JSON, with source spans.
+  Tokens,
+}
+
+impl Emit {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["formatted", "ast", "tokens"];
+}
+
+impl FromStr for Emit {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "formatted" => Ok(Emit::Formatted),
+      "ast" => Ok(Emit::Ast),
+      "tokens" => Ok(Emit::Tokens),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        Emit::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// Returns the syntax tree of the document in `source`, read from `filename`, as JSON.
+///
+/// Each value has a `span` (byte offsets and 1-based line and column numbers of its start and
+/// end, as for [tokens()](fn.tokens.html)), and each property a `key_span` of its name. Comments
+/// are attached the way the formatter moves them: a comment on the same line after a value (and
+/// its comma) is one of that value's `trailing_comments`; other comments are `leading_comments` of
+/// the next property or item (or `value_leading_comments`, between a property name and its
+/// value), or `end_comments` of the enclosing object or array if no value follows.
+pub fn ast(source: &str, filename: &str) -> Result<Json, anyhow::Error> {
+  let root =
+    syntax::parse(source).map_err(|err| anyhow::anyhow!("Parse error: {}:{}", filename, err))?;
+  let comments = Ast {
+    source,
+    comments: Comments::new(source),
+  };
+  let mut properties = vec![("filename", Json::String(filename.to_string()))];
+  match &root {
+    Some(root) => {
+      properties.push(("leading_comments", comments.leading(root.span().start)));
+      properties.push(("root", comments.node(root)));
+      properties.push(("trailing_comments", comments.trailing(root.span().end)));
+    }
+    None => properties.push(("root", Json::Null)),
+  }
+  properties.push(("end_comments", comments.json(comments.comments.end())));
+  Ok(Json::Object(properties))
+}
+
+/// Returns every token of `buffer`, read from `filename`, as JSON, including whitespace and
+/// comments, for syntax highlighters and other editor tools. The first `preamble_len` bytes (see
+/// [SourceOptions](../document/struct.SourceOptions.html)) are a single `preamble` token.
+///
+/// Each token has its lexer `kind`, its `text`, and a `span` with the byte offsets and 1-based line
+/// and column numbers of its start and end (just past its last character). Strings and words also
+/// have a `role`: `property_name`, `string`, `number`, `boolean`, or `null`.
+pub fn tokens(buffer: &str, preamble_len: usize, filename: &str) -> Json {
+  let body = &buffer[preamble_len..];
+  let preamble_lines = buffer[..preamble_len].matches('\n').count();
+  let tokens = lexer::tokenize(body);
+  let mut json = Vec::new();
+  if preamble_len > 0 {
+    json.push(token(
+      "preamble",
+      None,
+      &buffer[..preamble_len],
+      Span {
+        start: 0,
+        end: preamble_len,
+        line: 1,
+        col: 1,
+      },
+    ));
+  }
+  for (index, current) in tokens.iter().enumerate() {
+    let text = &body[current.start..current.end];
+    let role = match current.kind {
+      TokenKind::String | TokenKind::Word
+        if tokens[index + 1..]
+          .iter()
+          .find(|next| !next.is_trivia())
+          .is_some_and(|next| next.kind == TokenKind::Colon) =>
+      {
+        Some("property_name")
+      }
+      TokenKind::String => Some("string"),
+      TokenKind::Word => Some(match text {
+        "true" | "false" => "boolean",
+        "null" => "null",
+        _ => "number",
+      }),
+      _ => None,
+    };
+    let span = Span {
+      start: preamble_len + current.start,
+      end: preamble_len + current.end,
+      line: preamble_lines + current.line,
+      col: current.col,
+    };
+    json.push(token(current.kind.name(), role, text, span));
+  }
+  Json::Object(vec![
+    ("filename", Json::String(filename.to_string())),
+    ("tokens", Json::Array(json)),
+  ])
+}
+
+/// Returns a token, of the given `kind` and `role`, with its `text` and `span` as JSON.
+fn token(kind: &str, role: Option<&str>, text: &str, span: Span) -> Json {
+  let mut properties = vec![("kind", Json::String(kind.to_string()))];
+  if let Some(role) = role {
+    properties.push(("role", Json::String(role.to_string())));
+  }
+  properties.push(("text", Json::String(text.to_string())));
+  properties.push(("span", span_json(span, text)));
+  Json::Object(properties)
+}
+
+/// Returns the `span` of the source `text` as JSON: the byte offsets of its start and end, and the
+/// 1-based line and column numbers of its start and of its end (just past its last character). The
+/// values of a syntax tree and the tokens have spans of the same form.
+fn span_json(span: Span, text: &str) -> Json {
+  let (mut end_line, mut end_col) = (span.line, span.col);
+  for c in text.chars() {
+    if c == '\n' {
+      end_line += 1;
+      end_col = 1;
+    } else {
+      end_col += 1;
+    }
+  }
+  Json::Object(vec![
+    ("start", Json::Number(span.start)),
+    ("end", Json::Number(span.end)),
+    ("start_line", Json::Number(span.line)),
+    ("start_col", Json::Number(span.col)),
+    ("end_line", Json::Number(end_line)),
+    ("end_col", Json::Number(end_col)),
+  ])
+}
+
+/// The syntax tree of a document, with its comments.
+struct Ast<'a> {
+  /// The document source.
+  source: &'a str,
+  /// The comments of the document.
+  comments: Comments,
+}
+
+impl Ast<'_> {
+  /// Returns the span of the source at `span` as JSON (see [span_json()](fn.span_json.html)).
+  fn span(&self, span: Span) -> Json {
+    span_json(span, &self.source[span.start..span.end])
+  }
+
+  /// Returns `comments` as JSON.
+  fn json(&self, comments: &[Span]) -> Json {
+    Json::Array(
+      comments
+        .iter()
+        .map(|comment| {
+          Json::Object(vec![
+            (
+              "text",
+              Json::String(self.source[comment.start..comment.end].to_string()),
+            ),
+            ("span", self.span(*comment)),
+          ])
+        })
+        .collect(),
+    )
+  }
+
+  /// Returns the comments that lead the element starting at byte offset `start`.
+  fn leading(&self, start: usize) -> Json {
+    self.json(self.comments.leading(start))
+  }
+
+  /// Returns the comments that trail the element ending at byte offset `end`, including comments
+  /// after its comma.
+  fn trailing(&self, end: usize) -> Json {
+    self.json(&self.comments.trailing(end))
+  }
+
+  /// Returns the comments before the closing brace or bracket that ends at byte offset `end`, which
+  /// do not trail the last value.
+  fn closing(&self, end: usize) -> Json {
+    self.json(self.comments.closing(end))
+  }
+
+  /// Returns `root` as JSON, with its comments. The objects and arrays whose JSON is being built
+  /// are kept on a stack of their own, so deeply nested values cannot overflow the call stack.
+  fn node(&self, root: &Node) -> Json {
+    // Each object or array whose JSON is being built, innermost last, with the JSON of the
+    // properties or items built so far.
+    let mut open: Vec<(&Node, Vec<Json>)> = Vec::new();
+    let mut node = root;
+    loop {
+      if let Some(first) = child(node, 0) {
+        open.push((node, Vec::new()));
+        node = first;
+        continue;
+      }
+      let mut json = self.value(node, Vec::new());
+      // Add the value to the innermost open container, finishing each container that ends with it.
+      loop {
+        let Some((parent, elements)) = open.last_mut() else {
+          return json;
+        };
+        let index = elements.len();
+        elements.push(self.element(parent, index, json));
+        if let Some(next) = child(parent, index + 1) {
+          node = next;
+          break;
+        }
+        let (parent, elements) = open.pop().expect("the container is open");
+        json = self.value(parent, elements);
+      }
+    }
+  }
+
+  /// Returns the JSON of the property or item of `parent` at `index`, whose value's JSON is
+  /// `value`, with its comments.
+  fn element(&self, parent: &Node, index: usize, value: Json) -> Json {
+    match parent {
+      Node::Object { members, .. } => {
+        let member = &members[index];
+        Json::Object(vec![
+          ("name", Json::String(member.name.clone())),
+          ("key_span", self.span(member.key)),
+          ("leading_comments", self.leading(member.key.start)),
+          (
+            "value_leading_comments",
+            self.leading(member.value.span().start),
+          ),
+          ("value", value),
+          ("trailing_comments", self.trailing(member.value.span().end)),
+        ])
+      }
+      _ => {
+        let item = child(parent, index).expect("the item exists");
+        Json::Object(vec![
+          ("leading_comments", self.leading(item.span().start)),
+          ("value", value),
+          ("trailing_comments", self.trailing(item.span().end)),
+        ])
+      }
+    }
+  }
+
+  /// Returns `node` as JSON, with its comments, given the JSON of its properties or items (see
+  /// `element`), if it is an object or array.
+  fn value(&self, node: &Node, elements: Vec<Json>) -> Json {
+    let node_span = node.span();
+    let mut properties = vec![
+      (
+        "type",
+        Json::String(node.type_name(self.source).to_string()),
+      ),
+      ("span", self.span(node_span)),
+    ];
+    match node {
+      Node::Object { .. } => properties.push(("members", Json::Array(elements))),
+      Node::Array { .. } => properties.push(("items", Json::Array(elements))),
+      Node::String { value, .. } => properties.push(("value", Json::String(value.clone()))),
+      Node::Literal { span } => properties.push((
+        "text",
+        Json::String(self.source[span.start..span.end].to_string()),
+      )),
+    }
+    if let Node::Object { span, .. } | Node::Array { span, .. } = node {
+      properties.push(("end_comments", self.closing(span.end)));
+    }
+    Json::Object(properties)
+  }
+}
+
+/// Returns the value of the property or item of `node` at `index`, if any.
+fn child(node: &Node, index: usize) -> Option<&Node> {
+  match node {
+    Node::Object { members, .. } => members.get(index).map(|member| &member.value),
+    Node::Array { items, .. } => items.get(index),
+    Node::String { .. } | Node::Literal { .. } => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns `json` as text without whitespace.
+  fn compact(json: &Json) -> String {
+    json.to_pretty_string().split_whitespace().collect()
+  }
+
+  #[test]
+  fn gives_values_and_tokens_spans_of_the_same_form() {
+    let source = "{\n  a: 'é\\\nx',\n}";
+    let span =
+      r#""span":{"start":7,"end":14,"start_line":2,"start_col":6,"end_line":3,"end_col":3}"#;
+    let ast = compact(&ast(source, "a.json5").unwrap());
+    assert!(
+      ast.contains(&format!(r#"{{"type":"string",{},"value":"éx"}}"#, span)),
+      "{}",
+      ast
+    );
+    let tokens = compact(&tokens(source, 0, "a.json5"));
+    assert!(
+      tokens.contains(&format!(
+        r#"{{"kind":"string","role":"string","text":"'é\\\nx'",{}}}"#,
+        span
+      )),
+      "{}",
+      tokens
+    );
+  }
+}
@@ -0,0 +1,110 @@
+//! Formats the comments in otherwise empty objects and arrays (the `empty_container_comments`
+//! option), which are often placeholders, as in `allowed_hosts: [ /* none yet */ ]`.
+//!
+//! With `expand` (the default), the container is written across lines, with each comment on a
+//! line of its own, indented as an item would be. With `inline`, a container whose comments are
+//! all block comments on a single line is written on one line, as in `{ /* todo */ }`. A container
+//! with a line comment is always expanded, since the comment ends its line.
+
+use crate::{
+  edit::Edits,
+  format::FormatError,
+  lexer::{self, TokenKind},
+};
+use std::str::FromStr;
+
+/// How to write an object or array that holds only comments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmptyComments {
+  /// Write each comment on a line of its own.
+  #[default]
+  Expand,
+  /// Write the container and its block comments on one line.
+  Inline,
+}
+
+impl EmptyComments {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["expand", "inline"];
+
+  /// Returns the spelling of the style, as in the configuration file.
+  pub fn name(self) -> &'static str {
+    match self {
+      EmptyComments::Expand => "expand",
+      EmptyComments::Inline => "inline",
+    }
+  }
+}
+
+impl FromStr for EmptyComments {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "expand" => Ok(EmptyComments::Expand),
+      "inline" => Ok(EmptyComments::Inline),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", EmptyComments::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// Returns `formatted` (a formatted document, after a preamble that ends at byte offset `start`)
+/// with each object and array that holds only comments written in the given `style`, with
+/// expanded comments indented by `indent_by` spaces more than the container's line.
+pub fn format(formatted: &str, start: usize, style: EmptyComments, indent_by: usize) -> String {
+  let tokens = lexer::tokenize(formatted);
+  let mut edits = Edits::default();
+  let mut index = 0;
+  while index < tokens.len() {
+    let open = &tokens[index];
+    index += 1;
+    let close_kind = match open.kind {
+      TokenKind::OpenBrace => TokenKind::CloseBrace,
+      TokenKind::OpenBracket => TokenKind::CloseBracket,
+      _ => continue,
+    };
+    if open.start < start {
+      continue;
+    }
+    let Some(offset) = tokens[index..].iter().position(|token| !token.is_trivia()) else {
+      break;
+    };
+    let close = &tokens[index + offset];
+    let comments = tokens[index..index + offset]
+      .iter()
+      .filter(|token| matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment))
+      .map(|token| &formatted[token.start..token.end])
+      .collect::<Vec<_>>();
+    if close.kind != close_kind || comments.is_empty() {
+      continue;
+    }
+    let inline = style == EmptyComments::Inline
+      && tokens[index..index + offset]
+        .iter()
+        .all(|token| token.kind != TokenKind::LineComment)
+      && comments.iter().all(|comment| !comment.contains('\n'));
+    let replacement = match inline {
+      true => format!(" {} ", comments.join(" ")),
+      false => {
+        let line_start = formatted[..open.start].rfind('\n').map_or(0, |at| at + 1);
+        let line = &formatted[line_start..open.start];
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let item_indent = format!("{}{}", indent, " ".repeat(indent_by));
+        let mut replacement = String::new();
+        for comment in &comments {
+          replacement.push('\n');
+          replacement.push_str(&item_indent);
+          replacement.push_str(comment);
+        }
+        replacement.push('\n');
+        replacement.push_str(indent);
+        replacement
+      }
+    };
+    edits.replace(open.end..close.start, replacement);
+    index += offset + 1;
+  }
+  edits.apply(formatted)
+}
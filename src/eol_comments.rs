@@ -0,0 +1,323 @@
+//! Places the comments at the ends of lines (the `eol_comments` option), as in `port: 80, // http`.
+//!
+//! With `keep` (the default), each comment stays after its value, separated by a space. With
+//! `move-above`, each comment is moved to a line of its own above its value, indented as the value
+//! is; the comment after a closing brace or bracket is moved above the line that opens the object
+//! or array. A comment moved above a value that follows another is separated from it by a blank
+//! line, as json5format separates the comments above values, so that formatting the document again
+//! does not change it. With `align`, the comments on consecutive lines start in the same column,
+//! one space after the longest of the lines.
+//!
+//! json5format writes only line comments at the ends of lines, so block comments are not moved. It
+//! continues a comment at the end of a line with the line comments below it that start in the same
+//! column, which move (or are aligned) with it.
+
+use crate::{
+  edit::Edits,
+  format::FormatError,
+  lexer::{self, Token, TokenKind},
+};
+use std::str::FromStr;
+
+/// Where to place the comments at the ends of lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EolComments {
+  /// Keep each comment after its value.
+  #[default]
+  Keep,
+  /// Move each comment to a line of its own above its value.
+  MoveAbove,
+  /// Align the comments on consecutive lines to the same column.
+  Align,
+}
+
+impl EolComments {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["keep", "move-above", "align"];
+
+  /// Returns the spelling of the placement, as in the configuration file.
+  pub fn name(self) -> &'static str {
+    match self {
+      EolComments::Keep => "keep",
+      EolComments::MoveAbove => "move-above",
+      EolComments::Align => "align",
+    }
+  }
+}
+
+impl FromStr for EolComments {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "keep" => Ok(EolComments::Keep),
+      "move-above" => Ok(EolComments::MoveAbove),
+      "align" => Ok(EolComments::Align),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", EolComments::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// A comment at the end of a line.
+struct EolComment {
+  /// The offset of the start of the comment's line.
+  line_start: usize,
+  /// The offset of the end of the last token before the comment.
+  code_end: usize,
+  /// The comment token.
+  comment: Token,
+  /// The index of the first token of the line.
+  first: usize,
+  /// The line comments below the comment that continue it.
+  continued: Vec<Token>,
+}
+
+/// Returns `formatted` (a formatted document, after a preamble that ends at byte offset `start`)
+/// with its end-of-line comments placed as given by `placement`.
+pub fn place(formatted: &str, start: usize, placement: EolComments) -> String {
+  if placement == EolComments::Keep {
+    return formatted.to_string();
+  }
+  let tokens = lexer::tokenize(formatted);
+  let comments = eol_comments(formatted, &tokens, start);
+  let mut edits = Edits::default();
+  match placement {
+    EolComments::Keep => {}
+    EolComments::MoveAbove => {
+      let mut separated = Vec::new();
+      // The comments that continue others, whose lines are removed with them.
+      let continued = comments
+        .iter()
+        .flat_map(|eol| eol.continued.iter().map(|comment| comment.start))
+        .collect::<Vec<_>>();
+      for eol in &comments {
+        let line_start = match tokens[eol.first].kind {
+          TokenKind::CloseBrace | TokenKind::CloseBracket => {
+            let open = opening(&tokens, eol.first);
+            line_start(formatted, tokens[open].start)
+          }
+          _ => eol.line_start,
+        };
+        let line = &formatted[line_start..];
+        let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        if !separated.contains(&line_start) && follows_value(&tokens, line_start, &continued) {
+          separated.push(line_start);
+          edits.insert(line_start, "\n");
+        }
+        let mut end = eol.comment.end;
+        for comment in std::iter::once(&eol.comment).chain(&eol.continued) {
+          let text = &formatted[comment.start..comment.end];
+          edits.insert(line_start, format!("{}{}\n", indent, text));
+          end = comment.end;
+        }
+        edits.replace(eol.code_end..end, "");
+      }
+    }
+    EolComments::Align => {
+      let mut run: Vec<&EolComment> = Vec::new();
+      for eol in &comments {
+        // The previous comment ends the previous line.
+        let follows = run.last().is_some_and(|last| {
+          formatted[last.comment.end..eol.line_start].trim_start_matches('\r') == "\n"
+        });
+        if !follows {
+          align(formatted, &run, &mut edits);
+          run.clear();
+        }
+        run.push(eol);
+      }
+      align(formatted, &run, &mut edits);
+    }
+  }
+  edits.apply(formatted)
+}
+
+/// Returns the comments at the ends of lines in `formatted`, whose tokens are `tokens`, after the
+/// offset `start`.
+fn eol_comments(formatted: &str, tokens: &[Token], start: usize) -> Vec<EolComment> {
+  let mut comments = Vec::new();
+  let mut first = None;
+  let mut code_end = None;
+  for (index, token) in tokens.iter().enumerate() {
+    match token.kind {
+      TokenKind::Newline => {
+        first = None;
+        code_end = None;
+      }
+      TokenKind::Whitespace | TokenKind::BlockComment => {}
+      TokenKind::LineComment => {
+        if let (Some(first), Some(code_end)) = (first, code_end) {
+          if token.start >= start {
+            comments.push(EolComment {
+              line_start: line_start(formatted, token.start),
+              code_end,
+              comment: *token,
+              first,
+              continued: continued(formatted, tokens, index),
+            });
+          }
+        }
+      }
+      _ => {
+        first.get_or_insert(index);
+        code_end = Some(token.end);
+      }
+    }
+  }
+  comments
+}
+
+/// Pads the end-of-line comments in `run`, on consecutive lines of `formatted`, to start in the
+/// same column.
+fn align(formatted: &str, run: &[&EolComment], edits: &mut Edits) {
+  if run.len() < 2 {
+    return;
+  }
+  let width = |eol: &EolComment| formatted[eol.line_start..eol.code_end].chars().count();
+  let column = run.iter().map(|eol| width(eol)).max().unwrap_or(0) + 1;
+  for eol in run {
+    edits.replace(
+      eol.code_end..eol.comment.start,
+      " ".repeat(column - width(eol)),
+    );
+    for comment in &eol.continued {
+      edits.replace(
+        line_start(formatted, comment.start)..comment.start,
+        " ".repeat(column),
+      );
+    }
+  }
+}
+
+/// Returns the line comments on the lines after the comment at the end of a line, the token at
+/// `index` of the `tokens` of `formatted`, that start in the same column as it (and so continue
+/// it, for json5format).
+fn continued(formatted: &str, tokens: &[Token], index: usize) -> Vec<Token> {
+  let column = |token: &Token| {
+    formatted[line_start(formatted, token.start)..token.start]
+      .chars()
+      .count()
+  };
+  let start = column(&tokens[index]);
+  let mut continued = Vec::new();
+  let mut next = index + 1;
+  loop {
+    let mut at = next;
+    if tokens.get(at).map(|token| token.kind) != Some(TokenKind::Newline) {
+      break;
+    }
+    at += 1;
+    if tokens.get(at).map(|token| token.kind) == Some(TokenKind::Whitespace) {
+      at += 1;
+    }
+    match tokens.get(at) {
+      Some(token) if token.kind == TokenKind::LineComment && column(token) == start => {
+        continued.push(*token);
+        next = at + 1;
+      }
+      _ => break,
+    }
+  }
+  continued
+}
+
+/// Returns whether the line before the one starting at `line_start` ends with a comma after a
+/// value (before any comment at its end), given the `tokens` of the document, skipping the lines
+/// of the comments that start at the offsets `continued`.
+fn follows_value(tokens: &[Token], line_start: usize, continued: &[usize]) -> bool {
+  let mut newlines = 0;
+  for token in tokens
+    .iter()
+    .rev()
+    .skip_while(|token| token.start >= line_start)
+  {
+    match token.kind {
+      TokenKind::Newline if newlines > 0 => return false,
+      TokenKind::Newline => newlines += 1,
+      TokenKind::LineComment if continued.contains(&token.start) => newlines = 0,
+      TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment => {}
+      kind => return kind == TokenKind::Comma,
+    }
+  }
+  false
+}
+
+/// Returns the index of the token that opens the object or array closed by the token at `close`.
+fn opening(tokens: &[Token], close: usize) -> usize {
+  let mut depth = 0;
+  for index in (0..close).rev() {
+    match tokens[index].kind {
+      TokenKind::CloseBrace | TokenKind::CloseBracket => depth += 1,
+      TokenKind::OpenBrace | TokenKind::OpenBracket if depth == 0 => return index,
+      TokenKind::OpenBrace | TokenKind::OpenBracket => depth -= 1,
+      _ => {}
+    }
+  }
+  close
+}
+
+/// Returns the offset of the start of the line holding the offset `at` in `formatted`.
+fn line_start(formatted: &str, at: usize) -> usize {
+  formatted[..at].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keep_leaves_the_document_unchanged() {
+    let formatted = "{\n    a: 1, // one\n}\n";
+    assert_eq!(place(formatted, 0, EolComments::Keep), formatted);
+  }
+
+  #[test]
+  fn moves_comments_above_their_values() {
+    assert_eq!(
+      place(
+        "{\n    a: [ // list\n        1, // one\n    ], // after\n}\n",
+        0,
+        EolComments::MoveAbove
+      ),
+      "{\n    // list\n    // after\n    a: [\n        // one\n        1,\n    ],\n}\n"
+    );
+  }
+
+  #[test]
+  fn separates_moved_comments_from_the_value_before() {
+    assert_eq!(
+      place(
+        "{\n    a: 1,\n    b: 2, // two\n}\n",
+        0,
+        EolComments::MoveAbove
+      ),
+      "{\n    a: 1,\n\n    // two\n    b: 2,\n}\n"
+    );
+  }
+
+  #[test]
+  fn moves_the_comments_that_continue_a_comment_with_it() {
+    assert_eq!(
+      place(
+        "{\n    y: 1, // one\n          // more\n    zz: 2, // two\n}\n",
+        0,
+        EolComments::MoveAbove
+      ),
+      "{\n    // one\n    // more\n    y: 1,\n\n    // two\n    zz: 2,\n}\n"
+    );
+  }
+
+  #[test]
+  fn aligns_the_comments_on_consecutive_lines() {
+    assert_eq!(
+      place(
+        "{\n    bb: 2, // two\n    a: 1, // one\n          // more\n    c: 3,\n}\n",
+        0,
+        EolComments::Align
+      ),
+      "{\n    bb: 2, // two\n    a: 1,  // one\n           // more\n    c: 3,\n}\n"
+    );
+  }
+}
@@ -0,0 +1,263 @@
+//! Chooses how problems are reported on stderr (the `--error-format` option): as messages for
+//! people (`human`, the default), or as GitHub Actions workflow commands (`github`), which the
+//! runner turns into annotations shown inline on the lines of a pull request:
+//!
+//!     ::error file=a.json5,line=3,col=6,title=Parse error [J5F001]::Property 'b' must have a value
+//!     ::warning file=a.json5,line=5,col=3,title=Warning [J5F008]::Empty object [no-empty-object]
+//!     ::error file=a.json5,line=2,title=Check failed::Not formatted
+//!
+//! Diagnostics are reported at their line and column, errors whose message starts with a location
+//! (such as `Parse error[J5F001]: a.json5:3:6: ...`) at that location, and the files that are
+//! not formatted at the first line that formatting changes. Other errors are reported for their
+//! file, or for the whole run.
+
+use crate::diagnostics::{self, Diagnostic, Severity};
+use std::str::FromStr;
+
+/// How problems are reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+  /// As messages for people, with the source line of each problem.
+  #[default]
+  Human,
+  /// As GitHub Actions workflow commands.
+  Github,
+}
+
+impl ErrorFormat {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["human", "github"];
+
+  /// Returns the report of `diagnostic`, found in the document `source` read from `filename`.
+  pub fn diagnostic(self, diagnostic: &Diagnostic, filename: &str, source: &str) -> String {
+    match self {
+      ErrorFormat::Human => diagnostics::render(diagnostic, filename, source),
+      ErrorFormat::Github => {
+        let rule = match diagnostic.rule {
+          Some(rule) => format!(" [{}]", rule),
+          None => String::new(),
+        };
+        let level = match diagnostic.severity {
+          Severity::Warning => "warning",
+          Severity::Error => "error",
+        };
+        command(
+          level,
+          &Location {
+            file: Some(filename),
+            line: Some(diagnostic.line),
+            col: Some(diagnostic.col),
+          },
+          &format!("{} [{}]", diagnostic.severity.label(), diagnostic.code.id),
+          &format!("{}{}", diagnostic.message, rule),
+        )
+      }
+    }
+  }
+
+  /// Returns the report of the error `err`, which happened while processing the file `filename`,
+  /// if any.
+  pub fn error(self, err: &anyhow::Error, filename: Option<&str>) -> String {
+    let message = err.to_string();
+    match self {
+      ErrorFormat::Human => format!("Error: {}", message),
+      ErrorFormat::Github => {
+        let parts = ErrorParts::new(&message, filename);
+        command(
+          "error",
+          &parts.location,
+          &parts.title.replace('[', " ["),
+          parts.text,
+        )
+      }
+    }
+  }
+
+  /// Returns the report of the `problem` that the check of the file `filename` found, at the
+  /// 1-based `line`, if any.
+  pub fn problem(self, filename: &str, line: Option<usize>, problem: &str) -> String {
+    match self {
+      ErrorFormat::Human => format!("{}: {}", filename, problem),
+      ErrorFormat::Github => command(
+        "error",
+        &Location {
+          file: Some(filename),
+          line,
+          col: None,
+        },
+        "Check failed",
+        problem,
+      ),
+    }
+  }
+}
+
+impl FromStr for ErrorFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "human" => Ok(ErrorFormat::Human),
+      "github" => Ok(ErrorFormat::Github),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        ErrorFormat::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// Where a problem is, as precisely as it is known.
+#[derive(Debug, Default)]
+pub struct Location<'a> {
+  /// The file.
+  pub file: Option<&'a str>,
+  /// The 1-based line.
+  pub line: Option<usize>,
+  /// The 1-based character column.
+  pub col: Option<usize>,
+}
+
+/// An error message, split into its title (such as `Parse error[J5F001]`), the location it starts
+/// with, and the description of the problem.
+#[derive(Debug)]
+pub struct ErrorParts<'a> {
+  /// The title, or `Error` if the message has none.
+  pub title: &'a str,
+  /// Where the problem is.
+  pub location: Location<'a>,
+  /// The description of the problem, without the source excerpt (or the whole message, if it has
+  /// no title).
+  pub text: &'a str,
+}
+
+impl<'a> ErrorParts<'a> {
+  /// Splits the error `message`, of an error that happened while processing the file `filename`,
+  /// if any.
+  pub fn new(message: &'a str, filename: Option<&'a str>) -> Self {
+    let first_line = message.lines().next().unwrap_or("");
+    let Some((title, rest)) = split_title(first_line) else {
+      return ErrorParts {
+        title: "Error",
+        location: Location {
+          file: filename,
+          ..Location::default()
+        },
+        text: message,
+      };
+    };
+    let (location, text) = locate(rest, filename);
+    // The line ends with a colon before the source excerpt.
+    let text = match message.lines().nth(1) {
+      Some(_) => text.strip_suffix(':').unwrap_or(text),
+      None => text,
+    };
+    ErrorParts {
+      title,
+      location,
+      text,
+    }
+  }
+
+  /// Returns the code in the title, such as `J5F001`, if any.
+  pub fn code(&self) -> Option<&'a str> {
+    let (_, code) = self.title.split_once('[')?;
+    code.strip_suffix(']')
+  }
+}
+
+/// Returns the workflow command that reports a problem at the `level` (`error` or `warning`) at
+/// `location`, with the `title` and `message`.
+fn command(level: &str, location: &Location<'_>, title: &str, message: &str) -> String {
+  let mut properties = Vec::new();
+  if let Some(file) = location.file {
+    properties.push(format!("file={}", escape_property(file)));
+  }
+  if let Some(line) = location.line {
+    properties.push(format!("line={}", line));
+  }
+  if let Some(col) = location.col {
+    properties.push(format!("col={}", col));
+  }
+  properties.push(format!("title={}", escape_property(title)));
+  format!(
+    "::{} {}::{}",
+    level,
+    properties.join(","),
+    escape_data(message)
+  )
+}
+
+/// Returns the title and the rest of the first line of an error message, such as `Parse
+/// error[J5F001]` and `config.json5:3:6: ...`, if it starts with a title that ends with `error`
+/// (and any code).
+fn split_title(line: &str) -> Option<(&str, &str)> {
+  let (title, rest) = line.split_once(": ")?;
+  let name = title.split('[').next().unwrap_or(title);
+  name
+    .to_lowercase()
+    .ends_with("error")
+    .then_some((title, rest))
+}
+
+/// Returns the location that `text`, the rest of the first line of an error message, starts with
+/// (`<file>:<line>:<col>: `, or `<file>: `, if the file is `filename` or has no spaces in its
+/// name), and the text after it.
+fn locate<'a>(text: &'a str, filename: Option<&'a str>) -> (Location<'a>, &'a str) {
+  // The first `:<line>:<col>` followed by `: ` or the end of the line.
+  let mut search = 0;
+  while let Some(colon) = text[search..].find(':').map(|colon| search + colon) {
+    search = colon + 1;
+    let numbers = text[colon + 1..].splitn(3, ':').collect::<Vec<_>>();
+    let [line, col, rest] = numbers[..] else {
+      continue;
+    };
+    let (Ok(line), Ok(col)) = (line.parse(), col.parse()) else {
+      continue;
+    };
+    if colon > 0 && (rest.is_empty() || rest.starts_with(' ')) {
+      let location = Location {
+        file: Some(&text[..colon]),
+        line: Some(line),
+        col: Some(col),
+      };
+      return (location, rest.trim_start());
+    }
+  }
+  let file = match (filename, text.split_once(": ")) {
+    (Some(filename), Some((file, rest))) if file == filename => Some((file, rest)),
+    (_, Some((file, rest))) if !file.is_empty() && !file.contains(char::is_whitespace) => {
+      Some((file, rest))
+    }
+    _ => None,
+  };
+  match file {
+    Some((file, rest)) => (
+      Location {
+        file: Some(file),
+        ..Location::default()
+      },
+      rest,
+    ),
+    None => (
+      Location {
+        file: filename,
+        ..Location::default()
+      },
+      text,
+    ),
+  }
+}
+
+/// Returns `message` escaped for the data of a workflow command.
+fn escape_data(message: &str) -> String {
+  message
+    .replace('%', "%25")
+    .replace('\r', "%0D")
+    .replace('\n', "%0A")
+}
+
+/// Returns `value` escaped for a property of a workflow command.
+fn escape_property(value: &str) -> String {
+  escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
@@ -0,0 +1,281 @@
+//! Finds the JSON5 extensions to JSON that a document uses, to tell strict JSON input apart from
+//! JSON5 input (the `--input-format` option), and to limit a document to a dialect that allows
+//! only some of the extensions (the `--dialect` option).
+
+use crate::{
+  codes,
+  diagnostics::Diagnostic,
+  edit::Edits,
+  format::FormatError,
+  lexer::{self, TokenKind},
+};
+use std::str::FromStr;
+
+/// A JSON5 extension to JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+  /// A `//` or `/* */` comment.
+  Comment,
+  /// A comma after the last property of an object or the last item of an array.
+  TrailingComma,
+  /// A property name that is not quoted.
+  UnquotedKey,
+  /// A string enclosed in single quotes.
+  SingleQuotedString,
+  /// An escape sequence that JSON does not allow, such as `\x41`, `\v`, or a line continuation.
+  StringEscape,
+  /// A hexadecimal number, such as `0xFF`.
+  HexNumber,
+  /// `Infinity` or `NaN`.
+  NonFiniteNumber,
+  /// A number with a leading `+` sign, or a leading or trailing decimal point.
+  NumberSyntax,
+}
+
+impl Feature {
+  /// Returns a description of the feature, for messages.
+  pub fn describe(self) -> &'static str {
+    match self {
+      Feature::Comment => "a comment",
+      Feature::TrailingComma => "a trailing comma",
+      Feature::UnquotedKey => "an unquoted property name",
+      Feature::SingleQuotedString => "a single-quoted string",
+      Feature::StringEscape => "a JSON5-only string escape",
+      Feature::HexNumber => "a hexadecimal number",
+      Feature::NonFiniteNumber => "Infinity or NaN",
+      Feature::NumberSyntax => "a JSON5-only number syntax",
+    }
+  }
+}
+
+/// A use of a JSON5 extension in a document.
+#[derive(Clone, Copy, Debug)]
+pub struct Usage {
+  /// The extension used.
+  pub feature: Feature,
+  /// The 1-based line number where it is used.
+  pub line: usize,
+  /// The 1-based column number where it is used.
+  pub col: usize,
+}
+
+impl std::fmt::Display for Usage {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{} at {}:{}",
+      self.feature.describe(),
+      self.line,
+      self.col
+    )
+  }
+}
+
+/// Returns each use of a JSON5 extension in the document in `buffer`, after the preamble that ends
+/// at byte offset `start`, in source order.
+pub fn find(buffer: &str, start: usize) -> Vec<Usage> {
+  let tokens = lexer::tokenize(buffer);
+  let tokens = tokens
+    .iter()
+    .filter(|token| token.start >= start)
+    .collect::<Vec<_>>();
+  let mut usages = Vec::new();
+  for (index, token) in tokens.iter().enumerate() {
+    let text = &buffer[token.start..token.end];
+    let next = tokens[index + 1..]
+      .iter()
+      .find(|token| !token.is_trivia())
+      .map(|token| token.kind);
+    let feature = match token.kind {
+      TokenKind::LineComment | TokenKind::BlockComment => Some(Feature::Comment),
+      TokenKind::Comma
+        if matches!(
+          next,
+          Some(TokenKind::CloseBrace) | Some(TokenKind::CloseBracket)
+        ) =>
+      {
+        Some(Feature::TrailingComma)
+      }
+      TokenKind::Word if next == Some(TokenKind::Colon) => Some(Feature::UnquotedKey),
+      TokenKind::Word => number_feature(text),
+      TokenKind::String if text.starts_with('\'') => Some(Feature::SingleQuotedString),
+      TokenKind::String if has_json5_escape(text) => Some(Feature::StringEscape),
+      _ => None,
+    };
+    if let Some(feature) = feature {
+      usages.push(Usage {
+        feature,
+        line: token.line,
+        col: token.col,
+      });
+    }
+  }
+  usages
+}
+
+/// Returns the JSON5 extension used by the unquoted primitive `word`, if any.
+fn number_feature(word: &str) -> Option<Feature> {
+  let unsigned = word.trim_start_matches(['+', '-']);
+  if matches!(unsigned, "Infinity" | "NaN") {
+    Some(Feature::NonFiniteNumber)
+  } else if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+    Some(Feature::HexNumber)
+  } else if word.starts_with('+') || unsigned.starts_with('.') || unsigned.ends_with('.') {
+    Some(Feature::NumberSyntax)
+  } else {
+    None
+  }
+}
+
+/// Returns true if the double-quoted string `literal` contains an escape sequence that JSON does
+/// not allow.
+fn has_json5_escape(literal: &str) -> bool {
+  let mut chars = literal.chars();
+  while let Some(c) = chars.next() {
+    if c == '\\' {
+      match chars.next() {
+        Some('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' | 'u') => {}
+        _ => return true,
+      }
+    }
+  }
+  false
+}
+
+/// How to treat the input: as JSON5, as strict JSON, or according to the features it uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+  /// Format the input as JSON5.
+  Json5,
+  /// Require strict JSON input, and keep the output strict JSON.
+  Json,
+  /// Treat the input as strict JSON if it uses no JSON5 extensions, and as JSON5 otherwise.
+  Auto,
+}
+
+impl InputFormat {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["json5", "json", "auto"];
+}
+
+impl FromStr for InputFormat {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "json5" => Ok(InputFormat::Json5),
+      "json" => Ok(InputFormat::Json),
+      "auto" => Ok(InputFormat::Auto),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", InputFormat::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// A JSON dialect: the JSON5 extensions a document may use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dialect {
+  /// JSON5, with all of its extensions.
+  Json5,
+  /// JSON with comments and trailing commas, as used by VS Code settings files.
+  Jsonc,
+  /// Strict JSON, with no extensions.
+  Json,
+}
+
+impl Dialect {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["json5", "jsonc", "json"];
+
+  /// Returns the spelling of the dialect, as on the command line.
+  pub fn name(self) -> &'static str {
+    match self {
+      Dialect::Json5 => "json5",
+      Dialect::Jsonc => "jsonc",
+      Dialect::Json => "json",
+    }
+  }
+
+  /// Returns true if documents in the dialect may use `feature`.
+  pub fn allows(self, feature: Feature) -> bool {
+    match self {
+      Dialect::Json5 => true,
+      Dialect::Jsonc => matches!(feature, Feature::Comment | Feature::TrailingComma),
+      Dialect::Json => false,
+    }
+  }
+
+  /// Returns the name of the dialect, for messages.
+  pub fn describe(self) -> &'static str {
+    match self {
+      Dialect::Json5 => "JSON5",
+      Dialect::Jsonc => "JSONC",
+      Dialect::Json => "JSON",
+    }
+  }
+}
+
+impl FromStr for Dialect {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "json5" => Ok(Dialect::Json5),
+      "jsonc" => Ok(Dialect::Jsonc),
+      "json" => Ok(Dialect::Json),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", Dialect::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// Returns an error for each of the `usages` of JSON5 extensions that `dialect` does not allow.
+pub fn violations(usages: &[Usage], dialect: Dialect) -> Vec<Diagnostic> {
+  usages
+    .iter()
+    .filter(|usage| !dialect.allows(usage.feature))
+    .map(|usage| {
+      Diagnostic::error(
+        &codes::DIALECT,
+        usage.line,
+        usage.col,
+        format!(
+          "{} is not allowed in {}",
+          capitalize(usage.feature.describe()),
+          dialect.describe()
+        ),
+      )
+    })
+    .collect()
+}
+
+/// Returns `text` with its first letter in uppercase, to start a sentence.
+fn capitalize(text: &str) -> String {
+  let mut chars = text.chars();
+  match chars.next() {
+    Some(first) => first.to_uppercase().chain(chars).collect(),
+    None => String::new(),
+  }
+}
+
+/// Returns `formatted` with each unquoted property name after the preamble that ends at byte
+/// offset `start` enclosed in double quotes. The formatter removes the quotes from property names
+/// that are valid identifiers, so this restores them when the output must be strict JSON.
+pub fn quote_property_names(formatted: &str, start: usize) -> String {
+  let tokens = lexer::tokenize(formatted);
+  let mut edits = Edits::default();
+  let mut significant = tokens
+    .iter()
+    .filter(|token| token.start >= start && !token.is_trivia())
+    .peekable();
+  while let Some(token) = significant.next() {
+    let next = significant.peek().map(|token| token.kind);
+    if token.kind == TokenKind::Word && next == Some(TokenKind::Colon) {
+      edits.insert(token.start, "\"");
+      edits.insert(token.end, "\"");
+    }
+  }
+  edits.apply(formatted)
+}
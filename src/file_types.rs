@@ -0,0 +1,135 @@
+//! Selects default options by file type (the `file_types` option), so each file in a run is
+//! formatted as its kind of file should be. A file's type is the longest extension in the registry
+//! that its name ends with, and its options are defaults that the configuration file, the
+//! environment, and the command line take precedence over. The built-in registry is:
+//!
+//! | Extension   | Options                                                  |
+//! |-------------|----------------------------------------------------------|
+//! | `.json5`    | none (JSON5)                                             |
+//! | `.jsonc`    | `dialect: "jsonc"`                                       |
+//! | `.cml`      | `style: "fuchsia"` (Fuchsia component manifests)         |
+//! | `.gni.json` | `dialect: "json"` (JSON written and read by GN builds)   |
+//! | `.md`       | `front_matter: true` (Markdown with JSON5 front matter)  |
+//!
+//! The configuration file can add file types and change the options of built-in ones:
+//!
+//!     {
+//!         file_types: {
+//!             ".jsonc": {indent: 2},
+//!             ".code-workspace": {dialect: "jsonc"},
+//!         },
+//!     }
+//!
+//! Directories and archives are formatted with the registry too: a directory given as a file is
+//! searched for the files with an extension in the registry, and without patterns, every member of
+//! an archive with an extension in the registry is formatted (see `--archive`).
+
+use crate::config::{self, Config};
+
+/// The built-in file types, as pairs of an extension and its options (as `name=value` pairs).
+pub const BUILTIN: &[(&str, &[(&str, &str)])] = &[
+  (".json5", &[]),
+  (".jsonc", &[("dialect", "jsonc")]),
+  (".cml", &[("style", "fuchsia")]),
+  (".gni.json", &[("dialect", "json")]),
+  (".md", &[("front_matter", "true")]),
+];
+
+/// A file extension and the options of the files that have it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileType {
+  /// The extension, starting with a `.`, such as `.cml`.
+  pub extension: String,
+  /// The options of the files with the extension.
+  pub options: Config,
+}
+
+/// Returns `extension` with a leading `.`, as stored in a [FileType](struct.FileType.html).
+pub fn normalize(extension: &str) -> String {
+  match extension.starts_with('.') {
+    true => extension.to_string(),
+    false => format!(".{}", extension),
+  }
+}
+
+/// Parses file types from `text`, a semicolon-separated list of `extension=option:value,...` pairs,
+/// such as `.cml=style:fuchsia;.jsonc=dialect:jsonc,indent:2` (for environment variables and
+/// directives).
+pub fn parse(text: &str) -> Option<Vec<FileType>> {
+  text
+    .split(';')
+    .map(str::trim)
+    .filter(|file_type| !file_type.is_empty())
+    .map(|file_type| {
+      let (extension, options) = file_type.split_once('=')?;
+      let extension = extension.trim();
+      if extension.trim_start_matches('.').is_empty() {
+        return None;
+      }
+      let mut config = Config::default();
+      for option in options
+        .split(',')
+        .filter(|option| !option.trim().is_empty())
+      {
+        let (name, value) = option.split_once(':')?;
+        if name.trim() == "file_types" {
+          return None;
+        }
+        let (name, value) = config::parse_setting(name.trim(), value.trim())?;
+        config.set(name, value);
+      }
+      Some(FileType {
+        extension: normalize(extension),
+        options: config,
+      })
+    })
+    .collect()
+}
+
+/// Returns the file types of the registry, with those configured in `config` overriding the
+/// options of the built-in ones they share an extension with.
+pub fn registry(config: &Config) -> Vec<FileType> {
+  let mut registry = BUILTIN
+    .iter()
+    .map(|(extension, options)| {
+      let mut config = Config::default();
+      for (name, value) in options.iter() {
+        if let Some((name, value)) = config::parse_setting(name, value) {
+          config.set(name, value);
+        }
+      }
+      FileType {
+        extension: extension.to_string(),
+        options: config,
+      }
+    })
+    .collect::<Vec<_>>();
+  for file_type in config.file_types("file_types") {
+    match registry
+      .iter_mut()
+      .find(|known| known.extension == file_type.extension)
+    {
+      Some(known) => known.options.merge(file_type.options.clone()),
+      None => registry.push(file_type.clone()),
+    }
+  }
+  registry
+}
+
+/// Returns the options of `config` for the file `filename`: the options of its file type in the
+/// registry, if any, overridden by those of `config`.
+pub fn options(config: &Config, filename: &str) -> Config {
+  let name = filename.to_lowercase();
+  let file_type = registry(config)
+    .into_iter()
+    .filter(|file_type| name.ends_with(&file_type.extension.to_lowercase()))
+    .max_by_key(|file_type| file_type.extension.len());
+  match file_type {
+    Some(file_type) => {
+      let mut options = file_type.options;
+      options.merge(config.clone());
+      options
+    }
+    None => config.clone(),
+  }
+}
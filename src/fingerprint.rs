@@ -0,0 +1,54 @@
+//! Detects files that change on disk while they are being formatted, so `--replace` does not
+//! overwrite an edit made (for example, by an editor) after the file was read.
+
+use std::{
+  collections::hash_map::DefaultHasher,
+  fs,
+  hash::{Hash, Hasher},
+  io,
+  path::Path,
+  time::SystemTime,
+};
+
+/// The state of a file when it was read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fingerprint {
+  /// The modification time of the file, if the platform provides one.
+  modified: Option<SystemTime>,
+  /// A hash of the file's content.
+  hash: u64,
+}
+
+impl Fingerprint {
+  /// Reads the text file at `path`, and returns its content and fingerprint.
+  pub fn read(path: &Path) -> io::Result<(String, Fingerprint)> {
+    // The modification time is read first, so a change made while the file is read is detected.
+    let modified = fs::metadata(path)?.modified().ok();
+    let bytes = fs::read(path)?;
+    let hash = hash(&bytes);
+    let text = String::from_utf8(bytes).map_err(|_| {
+      io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+      )
+    })?;
+    Ok((text, Fingerprint { modified, hash }))
+  }
+
+  /// Returns true if the file at `path` is unchanged since it was read. Both the modification
+  /// time and the content are compared, since the modification time may be too coarse to show a
+  /// change made soon after the file was read.
+  pub fn is_current(&self, path: &Path) -> io::Result<bool> {
+    if fs::metadata(path)?.modified().ok() != self.modified {
+      return Ok(false);
+    }
+    Ok(hash(&fs::read(path)?) == self.hash)
+  }
+}
+
+/// Returns a hash of `bytes`.
+fn hash(bytes: &[u8]) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  bytes.hash(&mut hasher);
+  hasher.finish()
+}
@@ -0,0 +1,140 @@
+//! Applies the automatic fixes of several features to a document at once, resolving the fixes
+//! that conflict.
+//!
+//! Each feature that rewrites parts of a document (`--redact`, `--transform`, `--key-case` with
+//! `--fix`, `--hex-numbers`, `--normalize-floats`, and `--quotes`) adds its edits, computed from
+//! the same parse of the document, as a [pass](enum.Pass.html). Two fixes conflict if they change
+//! overlapping parts of the document, such as a transform and `--hex-numbers` rewriting the same
+//! number. The fix of the pass that comes first in [Pass](enum.Pass.html) is applied, and the other
+//! is reported and skipped, so the result does not depend on the order the features happen to run
+//! in. Lint checks run before the fixes, so their diagnostics point at the document as it was read.
+
+use crate::{edit::Edits, syntax};
+use std::ops::Range;
+
+/// A feature that fixes documents, in order of precedence: when two fixes conflict, the fix of
+/// the earlier pass is applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pass {
+  /// Replacing secret values (`--redact`), which comes first so a secret is never kept.
+  Redact,
+  /// Rewriting values with expressions (`--transform`).
+  Transform,
+  /// Renaming property names to the naming convention (`--key-case` with `--fix`).
+  Rename,
+  /// Rewriting hexadecimal numbers (`--hex-numbers`).
+  HexNumbers,
+  /// Rewriting decimal numbers in their shortest form (`--normalize-floats`).
+  NormalizeFloats,
+  /// Rewriting the quotes of strings (`--quotes`).
+  Quotes,
+}
+
+impl Pass {
+  /// Returns the option that enables the pass, for messages.
+  pub fn option(self) -> &'static str {
+    match self {
+      Pass::Redact => "--redact",
+      Pass::Transform => "--transform",
+      Pass::Rename => "--key-case",
+      Pass::HexNumbers => "--hex-numbers",
+      Pass::NormalizeFloats => "--normalize-floats",
+      Pass::Quotes => "--quotes",
+    }
+  }
+}
+
+/// A fix that was not applied because it conflicts with a fix of a pass with precedence.
+#[derive(Debug)]
+pub struct Conflict {
+  /// The 1-based line of the start of the skipped fix.
+  pub line: usize,
+  /// The 1-based character column of the start of the skipped fix.
+  pub col: usize,
+  /// The pass of the skipped fix.
+  pub pass: Pass,
+  /// The pass of the fix that was applied instead.
+  pub applied: Pass,
+}
+
+impl std::fmt::Display for Conflict {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{}:{}: The {} fix was not applied, since it conflicts with the {} fix",
+      self.line,
+      self.col,
+      self.pass.option(),
+      self.applied.option()
+    )
+  }
+}
+
+/// The fixes of every pass for a document.
+#[derive(Debug, Default)]
+pub struct Fixes {
+  /// Each fix, with its pass, replaced byte range, and replacement text.
+  fixes: Vec<(Pass, Range<usize>, String)>,
+}
+
+impl Fixes {
+  /// Adds the `edits` of the pass `pass`.
+  pub fn add(&mut self, pass: Pass, edits: Edits) {
+    self.fixes.extend(
+      edits
+        .into_vec()
+        .into_iter()
+        .map(|(range, text)| (pass, range, text)),
+    );
+  }
+
+  /// Returns true if there are no fixes.
+  pub fn is_empty(&self) -> bool {
+    self.fixes.is_empty()
+  }
+
+  /// Returns `source` with the fixes applied, and each fix that was skipped because it conflicts
+  /// with one that was applied, in source order.
+  pub fn apply(mut self, source: &str) -> (String, Vec<Conflict>) {
+    // Fixes are accepted in order of precedence, so each is checked against those that win over it.
+    self.fixes.sort_by_key(|(pass, _, _)| *pass);
+    let mut accepted: Vec<(Pass, Range<usize>)> = Vec::new();
+    let mut edits = Edits::default();
+    let mut conflicts = Vec::new();
+    for (pass, range, text) in self.fixes {
+      match accepted.iter().find(|(_, other)| overlaps(&range, other)) {
+        Some((applied, _)) => {
+          let (line, col) = syntax::end_position(&source[..range.start]);
+          conflicts.push(Conflict {
+            line,
+            col,
+            pass,
+            applied: *applied,
+          });
+        }
+        None => {
+          accepted.push((pass, range.clone()));
+          edits.replace(range, text);
+        }
+      }
+    }
+    conflicts.sort_by_key(|conflict| (conflict.line, conflict.col));
+    (edits.apply(source), conflicts)
+  }
+}
+
+/// Returns true if the edits of the ranges `a` and `b` would change the same text. An insertion
+/// conflicts with a replacement only if it is strictly inside the replaced range, and insertions
+/// at the same offset do not conflict.
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+  if a.is_empty() || b.is_empty() {
+    let (point, range) = if a.is_empty() {
+      (a.start, b)
+    } else {
+      (b.start, a)
+    };
+    range.start < point && point < range.end
+  } else {
+    a.start < b.end && b.start < a.end
+  }
+}
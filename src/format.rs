@@ -0,0 +1,634 @@
+//! Formats documents for other programs (such as pre-commit wrappers and editor integrations), with
+//! [format_str()](fn.format_str.html) and [format_file()](fn.format_file.html). Each returns a
+//! [FormatOutcome](struct.FormatOutcome.html) with the formatted document and what formatting did
+//! (whether the document changed, its lengths, the problems found, and how long it took), so
+//! callers can report it without comparing the documents themselves.
+//!
+//! A document is formatted in stages: its merge conflicts are resolved
+//! ([resolve_conflicts()](fn.resolve_conflicts.html)), it is repaired and checked against the
+//! dialect, parsed ([parse()](fn.parse.html)), its quotes and numbers are rewritten
+//! ([rewrite_values()](fn.rewrite_values.html)), and it is formatted with json5format and the
+//! passes that rewrite its output ([format_parsed()](fn.format_parsed.html)). The executable runs
+//! the same stages on each document, with its configuration files, lint rules, transforms,
+//! sorting, and plugins between them; those are not applied here.
+
+use crate::{
+  array_wrap::{self, ArrayWrap},
+  codes::{self, Code},
+  comment_attachment::{self, CommentAttachment},
+  conflicts::{self, Take},
+  diagnostics::Diagnostic,
+  document::{self, Document, SourceOptions},
+  edit::Edits,
+  empty::{self, EmptyComments},
+  eol_comments::{self, EolComments},
+  features::{self, Dialect, Feature},
+  fixes::{Fixes, Pass},
+  indentation::{self, Indentation},
+  literals::{self, HexNumbers, NormalizeFloats},
+  long_values::{self, LongValues},
+  quotes::{self, Quotes},
+  repair::{self, FixLevel},
+  syntax::{self, Node},
+};
+use json5format::FormatOptions;
+use std::{
+  fmt, fs, io,
+  path::Path,
+  time::{Duration, Instant},
+};
+
+/// The options a document is formatted with.
+#[derive(Clone, Debug)]
+pub struct Options {
+  /// The options of json5format (the indentation, trailing commas, one-element lines, and
+  /// sorting of arrays).
+  pub format: FormatOptions,
+  /// Which parts of the input belong to the document (preamble lines, trailing content, and
+  /// front matter).
+  pub source: SourceOptions,
+  /// Keep the indentation of the document (as `indent: "auto"` does), instead of indenting by
+  /// `format.indent_by`; see the [indentation](../indentation/index.html) module.
+  pub auto_indent: bool,
+  /// Which side of each merge conflict to keep, or `None` to fail on a document with merge
+  /// conflicts.
+  pub take: Option<Take>,
+  /// The repairs to apply before formatting.
+  pub fix: FixLevel,
+  /// The dialect the document is limited to; a document that uses an extension the dialect does
+  /// not allow fails, and strict JSON is written with its property names quoted.
+  pub dialect: Dialect,
+  /// How to quote strings.
+  pub quotes: Quotes,
+  /// How to write hexadecimal numbers.
+  pub hex_numbers: HexNumbers,
+  /// How to write floats.
+  pub normalize_floats: NormalizeFloats,
+  /// Which item each comment on a line of its own belongs to.
+  pub comment_attachment: CommentAttachment,
+  /// How to write containers that hold only comments.
+  pub empty_comments: EmptyComments,
+  /// Where to place the comments at the ends of lines.
+  pub eol_comments: EolComments,
+  /// How to wrap the items of arrays.
+  pub array_wrap: ArrayWrap,
+  /// What to do with lines longer than `max_width`.
+  pub long_values: LongValues,
+  /// The width that arrays are filled to, and long values are measured against.
+  pub max_width: usize,
+}
+
+impl Default for Options {
+  fn default() -> Self {
+    Options {
+      format: FormatOptions::default(),
+      source: SourceOptions::default(),
+      auto_indent: false,
+      take: None,
+      fix: FixLevel::None,
+      dialect: Dialect::Json5,
+      quotes: Quotes::default(),
+      hex_numbers: HexNumbers::Preserve,
+      normalize_floats: NormalizeFloats::Preserve,
+      comment_attachment: CommentAttachment::default(),
+      empty_comments: EmptyComments::default(),
+      eol_comments: EolComments::default(),
+      array_wrap: ArrayWrap::default(),
+      long_values: LongValues::default(),
+      max_width: long_values::DEFAULT_MAX_WIDTH,
+    }
+  }
+}
+
+/// A formatted document, and what formatting it did.
+#[derive(Clone, Debug)]
+pub struct FormatOutcome {
+  /// The formatted document.
+  pub formatted: String,
+  /// Whether the formatted document differs from the input.
+  pub changed: bool,
+  /// The length of the input, in bytes.
+  pub original_len: usize,
+  /// The length of the formatted document, in bytes.
+  pub formatted_len: usize,
+  /// The problems found: a warning for each repair (with the code of the parse error it repairs),
+  /// and with `LongValues::Warn`, for each value that is too long.
+  pub diagnostics: Vec<Diagnostic>,
+  /// How long formatting took.
+  pub duration: Duration,
+}
+
+/// An error that stops a document from being formatted.
+#[derive(Debug)]
+pub enum FormatError {
+  /// The file could not be read, or the output could not be written.
+  Io {
+    /// The name of the file.
+    file: String,
+    /// The error reading or writing it.
+    error: io::Error,
+  },
+  /// An option has a value that is not valid (such as a string option that names no variant, or
+  /// a json5format path that does not start with `/`).
+  Config {
+    /// What is wrong with the value.
+    message: String,
+  },
+  /// The document has unresolved merge conflicts (see the [conflicts](../conflicts/index.html)
+  /// module).
+  Conflict {
+    /// The name of the document.
+    file: String,
+    /// The 1-based first and last lines of each conflict, in order.
+    lines: Vec<(usize, usize)>,
+  },
+  /// The document uses JSON5 extensions that the dialect does not allow.
+  Dialect {
+    /// The name of the document.
+    file: String,
+    /// The dialect.
+    dialect: Dialect,
+    /// An error for each use of an extension the dialect does not allow, in source order.
+    diagnostics: Vec<Diagnostic>,
+  },
+  /// The document is not valid JSON5 (after any repairs).
+  Parse {
+    /// The code of the error: [PARSE_ERROR](../codes/constant.PARSE_ERROR.html), or
+    /// [TRAILING_CONTENT](../codes/constant.TRAILING_CONTENT.html) for content after the
+    /// document's value.
+    code: &'static Code,
+    /// The name of the document.
+    file: String,
+    /// The 1-based line of the error.
+    line: usize,
+    /// The 1-based character column of the error.
+    col: usize,
+    /// A description of the error.
+    message: String,
+  },
+  /// json5format could not parse or format a document that is valid JSON5.
+  Format {
+    /// The name of the document.
+    file: String,
+    /// json5format's message.
+    message: String,
+  },
+}
+
+impl FormatError {
+  /// Returns the error for `err`, an error of json5format for the document read from `file`.
+  pub fn json5format(err: json5format::Error, file: &str) -> Self {
+    match err {
+      json5format::Error::Configuration(message) => FormatError::Config { message },
+      json5format::Error::Parse(Some(location), message) => FormatError::Parse {
+        code: &codes::PARSE_ERROR,
+        file: file.to_string(),
+        line: location.line,
+        col: location.col,
+        message,
+      },
+      err => FormatError::Format {
+        file: file.to_string(),
+        message: err.to_string(),
+      },
+    }
+  }
+}
+
+impl fmt::Display for FormatError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FormatError::Io { file, error } => write!(f, "Input error: {}: {}", file, error),
+      FormatError::Config { message } => write!(f, "Configuration error: {}", message),
+      FormatError::Conflict { file, lines } => write!(
+        f,
+        "Conflict error[{}]: {}: The file contains unresolved merge conflicts at lines {} \
+         (resolve them, or use --take ours or --take theirs)",
+        codes::MERGE_CONFLICT.id,
+        file,
+        conflicts::describe(lines)
+      ),
+      FormatError::Dialect {
+        file,
+        dialect,
+        diagnostics,
+      } => write!(
+        f,
+        "Dialect error: {}: Found {} construct(s) not allowed in {}",
+        file,
+        diagnostics.len(),
+        dialect.describe()
+      ),
+      FormatError::Parse {
+        code,
+        file,
+        line,
+        col,
+        message,
+      } => write!(
+        f,
+        "Parse error[{}]: {}:{}:{}: {}",
+        code.id, file, line, col, message
+      ),
+      FormatError::Format { file, message } => write!(f, "Format error: {}: {}", file, message),
+    }
+  }
+}
+
+impl std::error::Error for FormatError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      FormatError::Io { error, .. } => Some(error),
+      FormatError::Config { .. }
+      | FormatError::Conflict { .. }
+      | FormatError::Dialect { .. }
+      | FormatError::Parse { .. }
+      | FormatError::Format { .. } => None,
+    }
+  }
+}
+
+/// Formats the file at `path` with the `options`. The file is not written; callers write
+/// `formatted` back if the outcome is `changed`.
+pub fn format_file(path: &Path, options: &Options) -> Result<FormatOutcome, FormatError> {
+  let file = path.display().to_string();
+  let source = fs::read_to_string(path).map_err(|error| FormatError::Io {
+    file: file.clone(),
+    error,
+  })?;
+  format_str(&source, &file, options)
+}
+
+/// Formats the document `source`, read from `file` (a name for errors), with the `options`.
+pub fn format_str(
+  source: &str,
+  file: &str,
+  options: &Options,
+) -> Result<FormatOutcome, FormatError> {
+  let start = Instant::now();
+  let (buffer, _) = resolve_conflicts(source.to_string(), file, options.take)?;
+  let preamble_len = document::preamble_len(&buffer, &options.source);
+  let (buffer, repairs) = repair::repair(
+    &buffer,
+    preamble_len,
+    options.fix,
+    options.dialect.allows(Feature::TrailingComma),
+  );
+  let mut diagnostics = repairs
+    .into_iter()
+    .map(|repair| Diagnostic::warning(&codes::PARSE_ERROR, repair.line, repair.col, repair.message))
+    .collect::<Vec<_>>();
+  if options.dialect != Dialect::Json5 {
+    let body = &buffer[..document::body_end(&buffer, &options.source)];
+    check_dialect(&features::find(body, preamble_len), file, options.dialect)?;
+  }
+
+  let mut parsed = parse(buffer, file, options, 1)?;
+  // The formatter's own parser rejects some documents that json5format accepts (such as one with a
+  // line break in a string).
+  let root = syntax::parse(&parsed.document.source).map_err(|err| FormatError::Parse {
+    code: &codes::PARSE_ERROR,
+    file: file.to_string(),
+    line: err.line,
+    col: err.col,
+    message: err.message,
+  })?;
+  if let Some(root) = root {
+    let mut fixes = Fixes::default();
+    rewrite_values(&root, &parsed.document.source, options, &mut fixes);
+    if !fixes.is_empty() {
+      let (source, _) = fixes.apply(&parsed.document.source);
+      parsed.document.set_source(source, file)?;
+    }
+  }
+  let (formatted, warnings) = format_parsed(&mut parsed, file, options)?;
+  diagnostics.extend(warnings);
+  Ok(FormatOutcome {
+    changed: formatted != source,
+    original_len: source.len(),
+    formatted_len: formatted.len(),
+    formatted,
+    diagnostics,
+    duration: start.elapsed(),
+  })
+}
+
+/// A document parsed to be formatted, as [parse()](fn.parse.html) returns it. The executable
+/// changes the document and the options of json5format (with its lint rules, transforms, and
+/// sorting) before it is formatted with [format_parsed()](fn.format_parsed.html).
+pub struct Parsed {
+  /// The document, with any tabs in its indentation replaced with spaces.
+  pub document: Document,
+  /// The options of json5format, with the width of the indentation detected.
+  pub format: FormatOptions,
+  /// The indentation detected, with `auto_indent`.
+  pub indentation: Option<Indentation>,
+  /// The length of the preamble, in bytes.
+  pub preamble_len: usize,
+}
+
+/// Returns the document `source` read from `file`, with each of its merge conflicts resolved by
+/// keeping the side `take`, and the 1-based first and last lines of the conflicts resolved. A
+/// document with merge conflicts fails if `take` is `None`.
+pub fn resolve_conflicts(
+  source: String,
+  file: &str,
+  take: Option<Take>,
+) -> Result<(String, Vec<(usize, usize)>), FormatError> {
+  let found = conflicts::find(&source);
+  match take {
+    _ if found.is_empty() => Ok((source, Vec::new())),
+    Some(take) => Ok((
+      conflicts::resolve(&source, &found, take),
+      conflicts::lines(&found),
+    )),
+    None => Err(FormatError::Conflict {
+      file: file.to_string(),
+      lines: conflicts::lines(&found),
+    }),
+  }
+}
+
+/// Fails with a [FormatError::Dialect](enum.FormatError.html#variant.Dialect) if any of the
+/// `usages` of JSON5 extensions in the document read from `file` are not allowed in `dialect`.
+pub fn check_dialect(
+  usages: &[features::Usage],
+  file: &str,
+  dialect: Dialect,
+) -> Result<(), FormatError> {
+  let diagnostics = features::violations(usages, dialect);
+  match diagnostics.is_empty() {
+    true => Ok(()),
+    false => Err(FormatError::Dialect {
+      file: file.to_string(),
+      dialect,
+      diagnostics,
+    }),
+  }
+}
+
+/// Parses the document `buffer` (after its repairs) read from `file`, to be formatted with the
+/// `options`, on up to `threads` threads (see the [chunked](../chunked/index.html) module).
+pub fn parse(
+  buffer: String,
+  file: &str,
+  options: &Options,
+  threads: usize,
+) -> Result<Parsed, FormatError> {
+  let preamble_len = document::preamble_len(&buffer, &options.source);
+  let mut document = Document::parse(buffer, file.to_string(), &options.source, threads)?;
+  let mut format = options.format.clone();
+  if !options.dialect.allows(Feature::TrailingComma) {
+    format.trailing_commas = false;
+  }
+  let indentation = match options.auto_indent {
+    true => indentation::detect(&document.source),
+    false => None,
+  };
+  if let Some(indentation) = indentation {
+    format.indent_by = indentation.width();
+  }
+  if indentation == Some(Indentation::Tabs) {
+    let source = indentation::spaces(&document.source, indentation::TAB_WIDTH);
+    document.set_source(source, file)?;
+  }
+  Ok(Parsed {
+    document,
+    format,
+    indentation,
+    preamble_len,
+  })
+}
+
+/// Adds to `fixes` the edits of the hexadecimal numbers, floats, and quotes of the document
+/// `source` (whose tree is `root`) that the `options` select.
+pub fn rewrite_values(root: &Node, source: &str, options: &Options, fixes: &mut Fixes) {
+  let mut edits = Edits::default();
+  literals::hex_numbers(root, source, options.hex_numbers, &mut edits);
+  fixes.add(Pass::HexNumbers, edits);
+  let mut edits = Edits::default();
+  literals::normalize_floats(root, source, options.normalize_floats, &mut edits);
+  fixes.add(Pass::NormalizeFloats, edits);
+  let mut edits = Edits::default();
+  quotes::quotes(root, source, options.quotes, &mut edits);
+  fixes.add(Pass::Quotes, edits);
+}
+
+/// Returns whether formatting the `parsed` document with the `options` rewrites json5format's
+/// output (for comments, strict JSON, array filling, long values, or tabs). If not, the document
+/// may be written by json5format alone, as it formats it.
+pub fn rewrites_output(parsed: &Parsed, options: &Options) -> bool {
+  let source = &parsed.document.source;
+  source.contains("//")
+    || source.contains("/*")
+    || !options.dialect.allows(Feature::UnquotedKey)
+    || options.array_wrap != ArrayWrap::Vertical
+    || options.long_values != LongValues::Allow
+    || parsed.indentation == Some(Indentation::Tabs)
+}
+
+/// Formats the `parsed` document read from `file` with json5format, and rewrites its output with
+/// the `options`. Returns the formatted document, and with `LongValues::Warn`, a warning for each
+/// value that is too long; the document's source is unchanged.
+pub fn format_parsed(
+  parsed: &mut Parsed,
+  file: &str,
+  options: &Options,
+) -> Result<(String, Vec<Diagnostic>), FormatError> {
+  let Parsed {
+    document,
+    format,
+    indentation,
+    preamble_len,
+  } = parsed;
+  let (indentation, preamble_len) = (*indentation, *preamble_len);
+  let indent_by = format.indent_by;
+  let unmarked = match comment_attachment::mark(&document.source, options.comment_attachment) {
+    Some(marked) => {
+      let unmarked = document.source.clone();
+      document.set_source(marked, file)?;
+      Some(unmarked)
+    }
+    None => None,
+  };
+  let formatted = document.format(format.clone(), file)?;
+  let mut formatted = String::from_utf8(formatted).map_err(|err| FormatError::Format {
+    file: file.to_string(),
+    message: err.to_string(),
+  })?;
+  if let Some(unmarked) = unmarked {
+    formatted = document.map_body(&formatted, comment_attachment::restore);
+    document.set_source(unmarked, file)?;
+  }
+  if !options.dialect.allows(Feature::UnquotedKey) {
+    formatted = features::quote_property_names(&formatted, preamble_len);
+  }
+  formatted = empty::format(&formatted, preamble_len, options.empty_comments, indent_by);
+  formatted = eol_comments::place(&formatted, preamble_len, options.eol_comments);
+  let max_width = options.max_width;
+  match options.array_wrap {
+    ArrayWrap::Vertical => {}
+    ArrayWrap::Fill => {
+      formatted = document.map_body(&formatted, |body| array_wrap::fill(body, max_width, false));
+    }
+    ArrayWrap::FillNumbers => {
+      formatted = document.map_body(&formatted, |body| array_wrap::fill(body, max_width, true));
+    }
+  }
+  let mut diagnostics = Vec::new();
+  match options.long_values {
+    LongValues::Allow => {}
+    LongValues::WrapMember => {
+      formatted = document.map_body(&formatted, |body| {
+        long_values::wrap(body, max_width, indent_by)
+      });
+    }
+    LongValues::Warn => diagnostics.extend(long_values::check(
+      &document.source,
+      document.body(&formatted),
+      max_width,
+    )),
+  }
+  if indentation == Some(Indentation::Tabs) {
+    formatted = document.map_body(&formatted, |body| indentation::tabs(body, indent_by));
+  }
+  Ok((formatted, diagnostics))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reports_whether_the_document_changed() {
+    let outcome = format_str("{a:1}", "a.json5", &Options::default()).unwrap();
+    assert_eq!(outcome.formatted, "{\n    a: 1,\n}\n");
+    assert!(outcome.changed);
+    assert_eq!(
+      (outcome.original_len, outcome.formatted_len),
+      (5, outcome.formatted.len())
+    );
+    let again = format_str(&outcome.formatted, "a.json5", &Options::default()).unwrap();
+    assert!(!again.changed);
+    assert!(again.diagnostics.is_empty());
+  }
+
+  #[test]
+  fn reports_repairs_as_warnings() {
+    let options = Options {
+      fix: FixLevel::Safe,
+      ..Options::default()
+    };
+    let outcome = format_str("[1,,2]", "a.json5", &options).unwrap();
+    assert_eq!(outcome.formatted, "[\n    1,\n    2,\n]\n");
+    let diagnostics = outcome
+      .diagnostics
+      .iter()
+      .map(|diagnostic| (diagnostic.code.id, diagnostic.line, diagnostic.col))
+      .collect::<Vec<_>>();
+    assert_eq!(diagnostics, [(codes::PARSE_ERROR.id, 1, 4)]);
+  }
+
+  #[test]
+  fn reports_parse_errors_at_their_position() {
+    let source = "#!/usr/bin/env formatjson5\n{a: 1 b: 2}";
+    match format_str(source, "a.json5", &Options::default()) {
+      Err(FormatError::Parse { file, line, .. }) => {
+        assert_eq!((file.as_str(), line), ("a.json5", 2))
+      }
+      other => panic!("expected a parse error, got {:?}", other),
+    }
+    match format_str("{a: 1} 2", "a.json5", &Options::default()) {
+      Err(FormatError::Parse { line, col, .. }) => assert_eq!((line, col), (1, 8)),
+      other => panic!("expected a parse error, got {:?}", other),
+    }
+    // json5format accepts the unclosed string.
+    match format_str("\"ab\\\"\n", "a.json5", &Options::default()) {
+      Err(FormatError::Parse { line, .. }) => assert_eq!(line, 1),
+      other => panic!("expected a parse error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn keeps_the_indentation_of_documents_indented_with_tabs() {
+    let options = Options {
+      auto_indent: true,
+      ..Options::default()
+    };
+    let source = "{\n\ta: [\n\t\t1,\n\t],\n}\n";
+    let outcome = format_str(source, "a.json5", &options).unwrap();
+    assert_eq!(outcome.formatted, source);
+    assert!(!outcome.changed);
+  }
+
+  #[test]
+  fn reports_merge_conflicts() {
+    let source = "{\n<<<<<<< HEAD\na: 1,\n=======\na: 2,\n>>>>>>> topic\n}\n";
+    match format_str(source, "a.json5", &Options::default()) {
+      Err(FormatError::Conflict { lines, .. }) => assert_eq!(lines, [(2, 6)]),
+      other => panic!("expected a conflict error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn resolves_merge_conflicts_with_take() {
+    let source = "{\n<<<<<<< HEAD\na: 1,\n=======\na: 2,\n>>>>>>> topic\n}\n";
+    let options = Options {
+      take: Some(Take::Theirs),
+      ..Options::default()
+    };
+    let outcome = format_str(source, "a.json5", &options).unwrap();
+    assert_eq!(outcome.formatted, "{\n    a: 2,\n}\n");
+  }
+
+  #[test]
+  fn limits_documents_to_the_dialect() {
+    let options = Options {
+      dialect: Dialect::Json,
+      ..Options::default()
+    };
+    let outcome = format_str("{\"a\": [1, 2]}\n", "a.json", &options).unwrap();
+    assert_eq!(
+      outcome.formatted,
+      "{\n    \"a\": [\n        1,\n        2\n    ]\n}\n"
+    );
+    let err = format_str("{a: 1, // b\n}\n", "a.json", &options).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "Dialect error: a.json: Found 3 construct(s) not allowed in JSON"
+    );
+    match err {
+      FormatError::Dialect { diagnostics, .. } => {
+        let positions = diagnostics
+          .iter()
+          .map(|diagnostic| (diagnostic.line, diagnostic.col))
+          .collect::<Vec<_>>();
+        assert_eq!(positions, [(1, 2), (1, 6), (1, 8)]);
+      }
+      other => panic!("expected a dialect error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn reports_option_values_that_are_not_valid() {
+    match "wide".parse::<ArrayWrap>() {
+      Err(FormatError::Config { message }) => {
+        assert_eq!(message, "Expected one of: vertical, fill, fill-numbers")
+      }
+      other => panic!("expected a configuration error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn reports_missing_files() {
+    let path = Path::new("missing/a.json5");
+    match format_file(path, &Options::default()) {
+      Err(err @ FormatError::Io { .. }) => {
+        assert!(err
+          .to_string()
+          .starts_with("Input error: missing/a.json5: "))
+      }
+      other => panic!("expected an input error, got {:?}", other),
+    }
+  }
+}
@@ -0,0 +1,167 @@
+//! Frames multiple documents on a single stream, so that a long-running process (such as an editor
+//! integration) can format many buffers through one formatter process. Each document read from
+//! stdin is written back to stdout, formatted, in the same framing.
+//!
+//! The documents of several files formatted to stdout are separated as selected with
+//! `--multi-doc-separator` (see [Separator](enum.Separator.html)), so a script can split the
+//! output back into files.
+
+use crate::syntax;
+use std::{
+  io::{self, BufRead, Write},
+  str::FromStr,
+};
+
+/// How documents are delimited on stdin and stdout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Framing {
+  /// Each document is followed by a NUL (`\0`) byte.
+  Nul,
+  /// Each document is preceded by its length in bytes, as a decimal number on a line of its own.
+  Len,
+}
+
+impl Framing {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["nul", "len"];
+
+  /// Reads the next document from `reader`, or returns `None` at the end of the stream. With
+  /// `Framing::Nul`, a final document without a terminating NUL byte is still returned.
+  pub fn read(self, reader: &mut impl BufRead) -> Result<Option<Vec<u8>>, io::Error> {
+    let mut frame = Vec::new();
+    match self {
+      Framing::Nul => {
+        if reader.read_until(b'\0', &mut frame)? == 0 {
+          return Ok(None);
+        }
+        if frame.last() == Some(&b'\0') {
+          frame.pop();
+        }
+      }
+      Framing::Len => {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+          return Ok(None);
+        }
+        let len = header.trim_end().parse::<usize>().map_err(|_| {
+          io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid frame length {:?}", header.trim_end()),
+          )
+        })?;
+        frame.resize(len, 0);
+        reader.read_exact(&mut frame)?;
+      }
+    }
+    Ok(Some(frame))
+  }
+
+  /// Writes the document `bytes` to `writer` as a single frame, and flushes it so the reader
+  /// receives it immediately.
+  pub fn write(self, writer: &mut impl Write, bytes: &[u8]) -> Result<(), io::Error> {
+    match self {
+      Framing::Nul => {
+        writer.write_all(bytes)?;
+        writer.write_all(b"\0")?;
+      }
+      Framing::Len => {
+        writeln!(writer, "{}", bytes.len())?;
+        writer.write_all(bytes)?;
+      }
+    }
+    writer.flush()
+  }
+}
+
+impl FromStr for Framing {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "nul" => Ok(Framing::Nul),
+      "len" => Ok(Framing::Len),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        Framing::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// How the documents of several files are separated on stdout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Separator {
+  /// Each document but the first is preceded by a blank line, and each document by its filename,
+  /// underlined with `=`.
+  Banner,
+  /// Each document but the first is preceded by a blank line.
+  BlankLine,
+  /// Each document is followed by a NUL (`\0`) byte.
+  Nul,
+  /// Each document is written as a line of JSON, `{"file": <filename>, "content": <document>}`.
+  RecordJsonl,
+  /// The documents are written one after another.
+  None,
+}
+
+impl Separator {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] =
+    &["banner", "blank-line", "nul", "record-jsonl", "none"];
+
+  /// Writes the document `bytes`, read from `filename`, to `writer`, separated from the `written`
+  /// documents written before it.
+  pub fn write(
+    self,
+    writer: &mut impl Write,
+    filename: &str,
+    bytes: &[u8],
+    written: usize,
+  ) -> Result<(), io::Error> {
+    match self {
+      Separator::Banner => {
+        if written > 0 {
+          writeln!(writer)?;
+        }
+        writeln!(writer, "{}:", filename)?;
+        writeln!(writer, "{}", "=".repeat(filename.len()))?;
+        writer.write_all(bytes)
+      }
+      Separator::BlankLine => {
+        if written > 0 {
+          writeln!(writer)?;
+        }
+        writer.write_all(bytes)
+      }
+      Separator::Nul => {
+        writer.write_all(bytes)?;
+        writer.write_all(b"\0")
+      }
+      Separator::RecordJsonl => writeln!(
+        writer,
+        "{{\"file\": {}, \"content\": {}}}",
+        syntax::quote(filename, '"'),
+        syntax::quote(&String::from_utf8_lossy(bytes), '"')
+      ),
+      Separator::None => writer.write_all(bytes),
+    }
+  }
+}
+
+impl FromStr for Separator {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "banner" => Ok(Separator::Banner),
+      "blank-line" => Ok(Separator::BlankLine),
+      "nul" => Ok(Separator::Nul),
+      "record-jsonl" => Ok(Separator::RecordJsonl),
+      "none" => Ok(Separator::None),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        Separator::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
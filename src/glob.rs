@@ -0,0 +1,88 @@
+//! Matches the paths of values in a document against patterns such as `/credentials/*` or
+//! `**/password`.
+//!
+//! A path has a segment for each property name or array index (in decimal) leading from the
+//! document's top-level value to the value. In a pattern, `*` matches any sequence of characters
+//! within a segment, and a segment of `**` matches any number of segments (including none). A
+//! pattern that does not start with `/` may match at any depth, as if it started with `/**/`.
+
+use std::str::FromStr;
+
+/// A pattern that matches value paths.
+#[derive(Clone, Debug)]
+pub struct PathGlob {
+  /// The pattern, as given.
+  pattern: String,
+  /// The segments of the pattern.
+  segments: Vec<String>,
+}
+
+impl PathGlob {
+  /// Returns true if the value at the path with the given `segments` matches the pattern.
+  pub fn matches(&self, segments: &[String]) -> bool {
+    matches_segments(&self.segments, segments)
+  }
+}
+
+impl std::fmt::Display for PathGlob {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.pattern)
+  }
+}
+
+impl FromStr for PathGlob {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut segments = Vec::new();
+    let relative = match s.strip_prefix('/') {
+      Some(rest) => rest,
+      None => {
+        segments.push("**".to_string());
+        s
+      }
+    };
+    if !relative.is_empty() {
+      for segment in relative.split('/') {
+        if segment.is_empty() {
+          return Err(anyhow::anyhow!("Empty path segment in pattern '{}'", s));
+        }
+        segments.push(segment.to_string());
+      }
+    }
+    Ok(Self {
+      pattern: s.to_string(),
+      segments,
+    })
+  }
+}
+
+/// Returns true if the path `segments` match the `pattern` segments.
+fn matches_segments(pattern: &[String], segments: &[String]) -> bool {
+  match pattern.split_first() {
+    None => segments.is_empty(),
+    Some((first, rest)) if first == "**" => {
+      (0..=segments.len()).any(|skip| matches_segments(rest, &segments[skip..]))
+    }
+    Some((first, rest)) => match segments.split_first() {
+      Some((segment, remaining)) => {
+        matches_wildcard(first, segment) && matches_segments(rest, remaining)
+      }
+      None => false,
+    },
+  }
+}
+
+/// Returns true if `text` matches `pattern`, where `*` in the pattern matches any sequence of
+/// characters.
+fn matches_wildcard(pattern: &str, text: &str) -> bool {
+  match pattern.split_once('*') {
+    None => pattern == text,
+    Some((prefix, rest)) => {
+      text.starts_with(prefix)
+        && (prefix.len()..=text.len())
+          .filter(|index| text.is_char_boundary(*index))
+          .any(|index| matches_wildcard(rest, &text[index..]))
+    }
+  }
+}
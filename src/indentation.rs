@@ -0,0 +1,181 @@
+//! Keeps the indentation of each file (`--indent auto`, or `indent: "auto"` in the configuration
+//! file), so that formatting a tree whose files are indented differently does not reindent them
+//! all to one width at once.
+//!
+//! The indentation of a file is detected from the lines that start outside of strings and
+//! comments: it is tabs if more of them are indented with tabs than with spaces, and otherwise
+//! the most frequent step (from 1 to 8 spaces, preferring the smaller of equally frequent steps)
+//! by which a line is indented more than the line before it. A file indented with tabs is
+//! formatted with an indentation of 4 spaces (with each tab at the start of its lines first
+//! replaced by 4 spaces), and each 4 spaces at the start of its lines are then replaced by a tab.
+//! A file whose indentation cannot be detected (such as a file on one line) is indented by the
+//! default of 4 spaces.
+
+use crate::{
+  format::FormatError,
+  lexer::{self, TokenKind},
+};
+use std::{ops::Range, str::FromStr};
+
+/// The number of spaces a file indented with tabs is formatted with, before they are replaced by
+/// tabs.
+pub const TAB_WIDTH: usize = 4;
+
+/// The largest step, in spaces, that is detected as the indentation of a file.
+const MAX_STEP: usize = 8;
+
+/// The value of the `indent` option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+  /// Indent by the given number of spaces.
+  Spaces(usize),
+  /// Keep the indentation of each file.
+  Auto,
+}
+
+impl FromStr for Indent {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "auto" => Ok(Indent::Auto),
+      _ => s
+        .parse()
+        .map(Indent::Spaces)
+        .map_err(|_| FormatError::Config {
+          message: "Expected a non-negative integer, or `auto`".to_string(),
+        }),
+    }
+  }
+}
+
+/// The indentation of a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indentation {
+  /// The given number of spaces per level.
+  Spaces(usize),
+  /// A tab per level.
+  Tabs,
+}
+
+impl Indentation {
+  /// Returns the number of spaces to format the file with.
+  pub fn width(self) -> usize {
+    match self {
+      Indentation::Spaces(width) => width,
+      Indentation::Tabs => TAB_WIDTH,
+    }
+  }
+
+  /// Describes the indentation, for messages.
+  pub fn describe(self) -> String {
+    match self {
+      Indentation::Spaces(1) => "1 space".to_string(),
+      Indentation::Spaces(width) => format!("{} spaces", width),
+      Indentation::Tabs => "tabs".to_string(),
+    }
+  }
+}
+
+/// Returns the indentation of the document `source`, or `None` if it has no indented lines.
+pub fn detect(source: &str) -> Option<Indentation> {
+  let mut tab_lines = 0;
+  let mut space_lines = 0;
+  let mut steps = [0; MAX_STEP + 1];
+  // The width of the previous line, if it is indented with spaces (or not indented).
+  let mut previous = None;
+  for range in indents(source) {
+    let indent = &source[range];
+    if indent.starts_with('\t') {
+      tab_lines += 1;
+      previous = None;
+      continue;
+    }
+    if !indent.bytes().all(|byte| byte == b' ') {
+      previous = None;
+      continue;
+    }
+    let width = indent.len();
+    if width > 0 {
+      space_lines += 1;
+    }
+    if let Some(step) = previous.and_then(|previous| width.checked_sub(previous)) {
+      if (1..=MAX_STEP).contains(&step) {
+        steps[step] += 1;
+      }
+    }
+    previous = Some(width);
+  }
+  if tab_lines > space_lines {
+    return Some(Indentation::Tabs);
+  }
+  (1..=MAX_STEP)
+    .filter(|&step| steps[step] > 0)
+    .max_by_key(|&step| (steps[step], std::cmp::Reverse(step)))
+    .map(Indentation::Spaces)
+}
+
+/// Returns the formatted document `formatted`, indented by `width` spaces per level, with each
+/// `width` spaces at the start of a line (outside of strings and comments) replaced by a tab.
+pub fn tabs(formatted: &str, width: usize) -> String {
+  if width == 0 {
+    return formatted.to_string();
+  }
+  let mut text = String::with_capacity(formatted.len());
+  let mut last = 0;
+  for range in indents(formatted) {
+    let indent = &formatted[range.clone()];
+    let levels = (indent.len() - indent.trim_start_matches(' ').len()) / width;
+    text.push_str(&formatted[last..range.start]);
+    text.push_str(&"\t".repeat(levels));
+    last = range.start + levels * width;
+  }
+  text.push_str(&formatted[last..]);
+  text
+}
+
+/// Returns `source` with each tab at the start of a line (outside of strings and comments)
+/// replaced by `width` spaces, so json5format finds each comment in the column it writes it in,
+/// and moves the lines of a block comment after the first by as much as it moves the first (by
+/// none, once formatted).
+pub fn spaces(source: &str, width: usize) -> String {
+  let mut text = String::with_capacity(source.len());
+  let mut last = 0;
+  for range in indents(source) {
+    text.push_str(&source[last..range.start]);
+    text.push_str(&source[range.clone()].replace('\t', &" ".repeat(width)));
+    last = range.end;
+  }
+  text.push_str(&source[last..]);
+  text
+}
+
+/// Returns the byte ranges of the whitespace at the start of each line of `source` that is not
+/// blank and that starts outside of strings and comments.
+fn indents(source: &str) -> Vec<Range<usize>> {
+  let tokens = lexer::tokenize(source);
+  let mut indents = Vec::new();
+  let mut index = 0;
+  while index < tokens.len() {
+    let start = tokens[index].start;
+    let mut first = index;
+    if tokens[first].kind == TokenKind::Whitespace {
+      first += 1;
+    }
+    match tokens.get(first) {
+      Some(token) if token.kind != TokenKind::Newline => {
+        indents.push(start..token.start);
+      }
+      _ => {}
+    }
+    // The start of the next line.
+    index = match tokens[first..]
+      .iter()
+      .position(|token| token.kind == TokenKind::Newline)
+    {
+      Some(newline) => first + newline + 1,
+      None => tokens.len(),
+    };
+  }
+  indents
+}
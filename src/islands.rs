@@ -0,0 +1,139 @@
+//! Formats the JSON5 blocks ("islands") inside files of other kinds, such as HTML pages, Vue
+//! components, or source files (the `embedded` option, and `--embedded`), which front matter (see
+//! the [document](../document/index.html) module) is a built-in case of. Each rule names a file
+//! extension and the delimiters around its blocks, separated by `...`:
+//!
+//!     {
+//!         embedded: [
+//!             "html:<script type=\"application/json5\">...</script>",
+//!             "vue:<i18n lang=\"json5\">...</i18n>",
+//!         ],
+//!     }
+//!
+//! In a file with one of the extensions, each block between an opening delimiter and the next
+//! closing delimiter is formatted as a document of its own, and the rest of the file is left
+//! unchanged. The formatted block starts on the line after its opening delimiter and is indented
+//! like the line the delimiter is on, and the closing delimiter is moved to a line of its own.
+//! Blocks that hold only whitespace are left unchanged. Messages about a block report lines of the
+//! file.
+
+use std::{fmt, str::FromStr};
+
+/// The delimiters of the JSON5 blocks in files with an extension.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IslandRule {
+  /// The extension of the files, starting with a `.`, such as `.html`.
+  pub extension: String,
+  /// The text that starts each block.
+  pub open: String,
+  /// The text that ends each block.
+  pub close: String,
+}
+
+impl FromStr for IslandRule {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || {
+      anyhow::anyhow!(
+        "Expected '<extension>:<open>...<close>', such as \
+         'html:<script type=\"application/json5\">...</script>', but found '{}'",
+        s
+      )
+    };
+    let (extension, delimiters) = s.split_once(':').ok_or_else(invalid)?;
+    let (open, close) = delimiters.split_once("...").ok_or_else(invalid)?;
+    let extension = extension.trim().trim_start_matches('.');
+    if extension.is_empty() || open.is_empty() || close.is_empty() {
+      return Err(invalid());
+    }
+    Ok(IslandRule {
+      extension: format!(".{}", extension),
+      open: open.to_string(),
+      close: close.to_string(),
+    })
+  }
+}
+
+impl fmt::Display for IslandRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}:{}...{}",
+      self.extension.trim_start_matches('.'),
+      self.open,
+      self.close
+    )
+  }
+}
+
+/// Parses the `rules` (as in the `embedded` option), and returns those for the file `filename`.
+pub fn rules_for(rules: &[String], filename: &str) -> Result<Vec<IslandRule>, anyhow::Error> {
+  let name = filename.to_lowercase();
+  let mut matching = Vec::new();
+  for rule in rules {
+    let rule = rule
+      .parse::<IslandRule>()
+      .map_err(|err| anyhow::anyhow!("Configuration error: Option `embedded`: {}", err))?;
+    if name.ends_with(&rule.extension.to_lowercase()) {
+      matching.push(rule);
+    }
+  }
+  Ok(matching)
+}
+
+/// Returns `buffer` with each block selected by `rules` replaced by `format` of its text. The text
+/// passed to `format` starts with a line break for each line before the block, so the line numbers
+/// of its messages are those of the file.
+pub fn format(
+  buffer: &str,
+  rules: &[IslandRule],
+  mut format: impl FnMut(String) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<Vec<u8>, anyhow::Error> {
+  let mut output = String::with_capacity(buffer.len());
+  let mut offset = 0;
+  while let Some((start, end, rule)) = next_block(buffer, offset, rules) {
+    output.push_str(&buffer[offset..start]);
+    offset = end;
+    let text = &buffer[start..end];
+    if text.trim().is_empty() {
+      output.push_str(text);
+      continue;
+    }
+    let padding = "\n".repeat(buffer[..start].matches('\n').count());
+    let formatted = String::from_utf8(format(padding + text)?)?;
+    let line_start = buffer[..start - rule.open.len()]
+      .rfind('\n')
+      .map_or(0, |newline| newline + 1);
+    let line = &buffer[line_start..];
+    let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    output.push('\n');
+    for line in formatted.trim().lines() {
+      if !line.is_empty() {
+        output.push_str(indent);
+      }
+      output.push_str(line);
+      output.push('\n');
+    }
+    output.push_str(indent);
+  }
+  output.push_str(&buffer[offset..]);
+  Ok(output.into_bytes())
+}
+
+/// Returns the start and end of the text of the first block in `buffer` after `offset`, and the
+/// rule that selects it, if any.
+fn next_block<'a>(
+  buffer: &str,
+  offset: usize,
+  rules: &'a [IslandRule],
+) -> Option<(usize, usize, &'a IslandRule)> {
+  rules
+    .iter()
+    .filter_map(|rule| {
+      let start = offset + buffer[offset..].find(&rule.open)? + rule.open.len();
+      let end = start + buffer[start..].find(&rule.close)?;
+      Some((start, end, rule))
+    })
+    .min_by_key(|(start, _, _)| *start)
+}
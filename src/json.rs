@@ -0,0 +1,89 @@
+//! Writes JSON, for machine-readable output such as syntax tree dumps and reports.
+
+use crate::syntax;
+
+/// A JSON value.
+#[derive(Clone, Debug)]
+pub enum Json {
+  /// `null`.
+  Null,
+  /// `true` or `false`.
+  Bool(bool),
+  /// A non-negative integer.
+  Number(usize),
+  /// A string.
+  String(String),
+  /// An array.
+  Array(Vec<Json>),
+  /// An object, with its properties in order.
+  Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+  /// Returns the value as indented JSON text, followed by a line break. The values left to write
+  /// are kept on a stack of their own, so deeply nested values cannot overflow the call stack.
+  pub fn to_pretty_string(&self) -> String {
+    let mut text = String::new();
+    let indent = |text: &mut String, depth: usize| {
+      text.push('\n');
+      text.push_str(&"  ".repeat(depth));
+    };
+    // The parts left to write, the next one last.
+    let mut pending = vec![Part::Value(self, 0)];
+    while let Some(part) = pending.pop() {
+      match part {
+        Part::Value(value, depth) => match value {
+          Json::Null => text.push_str("null"),
+          Json::Bool(value) => text.push_str(&value.to_string()),
+          Json::Number(value) => text.push_str(&value.to_string()),
+          Json::String(value) => text.push_str(&syntax::quote(value, '"')),
+          Json::Array(items) if items.is_empty() => text.push_str("[]"),
+          Json::Array(items) => {
+            text.push('[');
+            pending.push(Part::Close(']', depth));
+            for (index, item) in items.iter().enumerate().rev() {
+              pending.push(Part::Value(item, depth + 1));
+              pending.push(Part::Start(index == 0, None, depth + 1));
+            }
+          }
+          Json::Object(properties) if properties.is_empty() => text.push_str("{}"),
+          Json::Object(properties) => {
+            text.push('{');
+            pending.push(Part::Close('}', depth));
+            for (index, (name, value)) in properties.iter().enumerate().rev() {
+              pending.push(Part::Value(value, depth + 1));
+              pending.push(Part::Start(index == 0, Some(name), depth + 1));
+            }
+          }
+        },
+        Part::Start(first, name, depth) => {
+          if !first {
+            text.push(',');
+          }
+          indent(&mut text, depth);
+          if let Some(name) = name {
+            text.push_str(&syntax::quote(name, '"'));
+            text.push_str(": ");
+          }
+        }
+        Part::Close(bracket, depth) => {
+          indent(&mut text, depth);
+          text.push(bracket);
+        }
+      }
+    }
+    text.push('\n');
+    text
+  }
+}
+
+/// A part of the JSON text of a value, left to write.
+enum Part<'a> {
+  /// A value, at a nesting depth.
+  Value(&'a Json, usize),
+  /// The start of an item or property at a nesting depth: its comma (unless it is the first), its
+  /// line, and its name (for a property).
+  Start(bool, Option<&'static str>, usize),
+  /// The closing bracket or brace of an array or object at a nesting depth.
+  Close(char, usize),
+}
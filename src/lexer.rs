@@ -0,0 +1,375 @@
+//! A lightweight JSON5 lexer that records the source position of every token. The json5format
+//! parser does not expose source positions, so this is used to inspect the structure of a document
+//! before (or instead of) handing it to json5format.
+//!
+//! The lexer scans bytes with `memchr`; building with the `scalar-lexer` feature selects the
+//! earlier lexer, which reads one character at a time, and produces the same tokens.
+
+/// The kinds of tokens produced by [tokenize()](fn.tokenize.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+  /// A run of spaces, tabs, or other whitespace, excluding line breaks.
+  Whitespace,
+  /// A single line break (`\n` or `\r\n`).
+  Newline,
+  /// A `//` comment, excluding the line break that ends it.
+  LineComment,
+  /// A `/* */` comment, which may be unterminated at the end of the input.
+  BlockComment,
+  /// `{`
+  OpenBrace,
+  /// `}`
+  CloseBrace,
+  /// `[`
+  OpenBracket,
+  /// `]`
+  CloseBracket,
+  /// `:`
+  Colon,
+  /// `,`
+  Comma,
+  /// A single-quoted or double-quoted string, which may be unterminated at the end of the line.
+  String,
+  /// An unquoted run of identifier or number characters, such as a property name, a number,
+  /// `true`, `false`, `null`, `Infinity`, or `NaN`.
+  Word,
+  /// Any other character, which is never valid JSON5 outside of a string or comment.
+  Unknown,
+}
+
+/// A token and its position in the source buffer.
+#[derive(Clone, Copy, Debug)]
+pub struct Token {
+  /// The kind of token.
+  pub kind: TokenKind,
+  /// The byte offset of the first character of the token.
+  pub start: usize,
+  /// The byte offset just past the last character of the token.
+  pub end: usize,
+  /// The 1-based line number of the first character of the token.
+  pub line: usize,
+  /// The 1-based character column of the first character of the token.
+  pub col: usize,
+}
+
+impl TokenKind {
+  /// Returns the name of the kind, in snake case (such as "line_comment"), for machine-readable
+  /// output.
+  pub fn name(self) -> &'static str {
+    match self {
+      TokenKind::Whitespace => "whitespace",
+      TokenKind::Newline => "newline",
+      TokenKind::LineComment => "line_comment",
+      TokenKind::BlockComment => "block_comment",
+      TokenKind::OpenBrace => "open_brace",
+      TokenKind::CloseBrace => "close_brace",
+      TokenKind::OpenBracket => "open_bracket",
+      TokenKind::CloseBracket => "close_bracket",
+      TokenKind::Colon => "colon",
+      TokenKind::Comma => "comma",
+      TokenKind::String => "string",
+      TokenKind::Word => "word",
+      TokenKind::Unknown => "unknown",
+    }
+  }
+}
+
+impl Token {
+  /// Returns true for whitespace, line breaks, and comments, which carry no data.
+  pub fn is_trivia(&self) -> bool {
+    matches!(
+      self.kind,
+      TokenKind::Whitespace | TokenKind::Newline | TokenKind::LineComment | TokenKind::BlockComment
+    )
+  }
+}
+
+/// Returns true if `c` can appear in an unquoted property name or a non-string primitive.
+fn is_word_char(c: char) -> bool {
+  c.is_alphanumeric() || matches!(c, '_' | '$' | '+' | '-' | '.' | '\\')
+}
+
+/// Splits `source` into tokens. Lexing never fails: unrecognized characters are returned as
+/// `TokenKind::Unknown` tokens, leaving syntax errors to be reported by the parser.
+///
+/// The source is scanned as bytes: comments, strings, and line breaks are found with `memchr`,
+/// which compares many bytes at once (with SIMD instructions, where the CPU has them), and
+/// characters are only decoded outside of ASCII.
+#[cfg(not(feature = "scalar-lexer"))]
+pub fn tokenize(source: &str) -> Vec<Token> {
+  let bytes = source.as_bytes();
+  // Formatted documents average a little over 4 bytes per token.
+  let mut tokens = Vec::new();
+  let mut line = 1;
+  let mut col = 1;
+  let mut start = 0;
+  while start < bytes.len() {
+    let rest = &bytes[start..];
+    let (kind, len) = match rest[0] {
+      b'\n' => (TokenKind::Newline, 1),
+      b'\r' if rest.get(1) == Some(&b'\n') => (TokenKind::Newline, 2),
+      b'{' => (TokenKind::OpenBrace, 1),
+      b'}' => (TokenKind::CloseBrace, 1),
+      b'[' => (TokenKind::OpenBracket, 1),
+      b']' => (TokenKind::CloseBracket, 1),
+      b':' => (TokenKind::Colon, 1),
+      b',' => (TokenKind::Comma, 1),
+      b' ' | b'\t' => (TokenKind::Whitespace, run_len(source, start, is_space)),
+      b'/' if rest.get(1) == Some(&b'/') => {
+        let len = match memchr::memchr(b'\n', rest) {
+          Some(newline) if rest[newline - 1] == b'\r' => newline - 1,
+          Some(newline) => newline,
+          None => rest.len(),
+        };
+        (TokenKind::LineComment, len)
+      }
+      b'/' if rest.get(1) == Some(&b'*') => {
+        let len = memchr::memmem::find(&rest[2..], b"*/").map_or(rest.len(), |end| end + 4);
+        (TokenKind::BlockComment, len)
+      }
+      quote @ (b'"' | b'\'') => (TokenKind::String, string_len(rest, quote)),
+      byte if is_ascii_word_byte(byte) => (TokenKind::Word, run_len(source, start, is_word_char)),
+      byte if byte.is_ascii() => match (byte as char).is_whitespace() {
+        true => (TokenKind::Whitespace, run_len(source, start, is_space)),
+        false => (TokenKind::Unknown, 1),
+      },
+      _ => {
+        let c = source[start..].chars().next().unwrap_or_default();
+        if c.is_whitespace() {
+          (TokenKind::Whitespace, run_len(source, start, is_space))
+        } else if is_word_char(c) {
+          (TokenKind::Word, run_len(source, start, is_word_char))
+        } else {
+          (TokenKind::Unknown, c.len_utf8())
+        }
+      }
+    };
+    let end = start + len;
+    tokens.push(Token {
+      kind,
+      start,
+      end,
+      line,
+      col,
+    });
+    match kind {
+      TokenKind::Newline => {
+        line += 1;
+        col = 1;
+      }
+      // Only these tokens can span lines (a string by escaping a line break).
+      TokenKind::BlockComment | TokenKind::String => {
+        let text = &bytes[start..end];
+        match memchr::memrchr(b'\n', text) {
+          Some(newline) => {
+            line += memchr::memchr_iter(b'\n', text).count();
+            col = 1 + char_count(&text[newline + 1..]);
+          }
+          None => col += char_count(text),
+        }
+      }
+      _ if len == 1 => col += 1,
+      _ => col += char_count(&bytes[start..end]),
+    }
+    start = end;
+  }
+  tokens
+}
+
+/// Returns true if `byte` is an ASCII character that can appear in an unquoted property name or a
+/// non-string primitive (see [is_word_char()](fn.is_word_char.html)).
+#[cfg(not(feature = "scalar-lexer"))]
+fn is_ascii_word_byte(byte: u8) -> bool {
+  byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'$' | b'+' | b'-' | b'.' | b'\\')
+}
+
+/// Returns true if `c` is whitespace that continues a run of whitespace (any but a line break).
+#[cfg(not(feature = "scalar-lexer"))]
+fn is_space(c: char) -> bool {
+  c.is_whitespace() && c != '\n' && c != '\r'
+}
+
+/// Returns the length, in bytes, of the run of characters of `source` from byte offset `start`
+/// that satisfy `continues` (after the first character, which starts the run regardless).
+#[cfg(not(feature = "scalar-lexer"))]
+fn run_len(source: &str, start: usize, continues: impl Fn(char) -> bool) -> usize {
+  let bytes = source.as_bytes();
+  let first = utf8_len(bytes[start]);
+  let mut end = start + first;
+  // ASCII characters are tested without decoding, until the first character that is not.
+  while end < bytes.len() && bytes[end].is_ascii() {
+    if !continues(bytes[end] as char) {
+      return end - start;
+    }
+    end += 1;
+  }
+  let len = source[end..]
+    .char_indices()
+    .find(|(_, c)| !continues(*c))
+    .map_or(source.len() - end, |(index, _)| index);
+  end + len - start
+}
+
+/// Returns the length, in bytes, of the string at the start of `rest`, which starts with `quote`:
+/// up to and including its closing quote, or up to the end of its line if it is unterminated. A
+/// backslash escapes the character after it, even a line break.
+#[cfg(not(feature = "scalar-lexer"))]
+fn string_len(rest: &[u8], quote: u8) -> usize {
+  let mut at = 1;
+  loop {
+    let Some(found) = memchr::memchr3(quote, b'\\', b'\n', &rest[at..]) else {
+      return memchr::memchr(b'\r', &rest[at..]).map_or(rest.len(), |cr| at + cr);
+    };
+    if let Some(cr) = memchr::memchr(b'\r', &rest[at..at + found]) {
+      return at + cr;
+    }
+    at += found;
+    match rest[at] {
+      b'\n' => return at,
+      b'\\' if at + 1 < rest.len() => at += 1 + utf8_len(rest[at + 1]),
+      b'\\' => return rest.len(),
+      _ => return at + 1,
+    }
+  }
+}
+
+/// Returns the length, in bytes, of the UTF-8 sequence that starts with `byte`.
+#[cfg(not(feature = "scalar-lexer"))]
+fn utf8_len(byte: u8) -> usize {
+  match byte {
+    0xf0.. => 4,
+    0xe0.. => 3,
+    0xc0.. => 2,
+    _ => 1,
+  }
+}
+
+/// Returns the number of characters in the UTF-8 `text` (the bytes that do not continue a
+/// sequence).
+#[cfg(not(feature = "scalar-lexer"))]
+fn char_count(text: &[u8]) -> usize {
+  text.iter().filter(|byte| (**byte & 0xc0) != 0x80).count()
+}
+
+/// Splits `source` into tokens, one character at a time (with the `scalar-lexer` feature, for
+/// platforms where `memchr` is unavailable or suspected). Lexing never fails: unrecognized
+/// characters are returned as `TokenKind::Unknown` tokens, leaving syntax errors to be reported by
+/// the parser.
+#[cfg(feature = "scalar-lexer")]
+pub fn tokenize(source: &str) -> Vec<Token> {
+  let mut tokens = Vec::new();
+  let mut line = 1;
+  let mut col = 1;
+  let mut chars = source.char_indices().peekable();
+  while let Some((start, c)) = chars.next() {
+    let kind = match c {
+      '\n' => TokenKind::Newline,
+      '\r' if matches!(chars.peek(), Some((_, '\n'))) => {
+        chars.next();
+        TokenKind::Newline
+      }
+      '{' => TokenKind::OpenBrace,
+      '}' => TokenKind::CloseBrace,
+      '[' => TokenKind::OpenBracket,
+      ']' => TokenKind::CloseBracket,
+      ':' => TokenKind::Colon,
+      ',' => TokenKind::Comma,
+      '/' if matches!(chars.peek(), Some((_, '/'))) => {
+        while let Some((index, _)) = chars.peek() {
+          let rest = &source[*index..];
+          if rest.starts_with('\n') || rest.starts_with("\r\n") {
+            break;
+          }
+          chars.next();
+        }
+        TokenKind::LineComment
+      }
+      '/' if matches!(chars.peek(), Some((_, '*'))) => {
+        chars.next();
+        let mut previous = ' ';
+        for (_, next) in chars.by_ref() {
+          if previous == '*' && next == '/' {
+            break;
+          }
+          previous = next;
+        }
+        TokenKind::BlockComment
+      }
+      '"' | '\'' => {
+        while let Some((_, next)) = chars.peek() {
+          if *next == '\n' || *next == '\r' {
+            break;
+          }
+          let next = *next;
+          chars.next();
+          if next == '\\' {
+            chars.next();
+          } else if next == c {
+            break;
+          }
+        }
+        TokenKind::String
+      }
+      c if c.is_whitespace() => {
+        while let Some((_, next)) = chars.peek() {
+          if !next.is_whitespace() || *next == '\n' || *next == '\r' {
+            break;
+          }
+          chars.next();
+        }
+        TokenKind::Whitespace
+      }
+      c if is_word_char(c) => {
+        while let Some((_, next)) = chars.peek() {
+          if !is_word_char(*next) {
+            break;
+          }
+          chars.next();
+        }
+        TokenKind::Word
+      }
+      _ => TokenKind::Unknown,
+    };
+    let end = chars.peek().map_or(source.len(), |(index, _)| *index);
+    tokens.push(Token {
+      kind,
+      start,
+      end,
+      line,
+      col,
+    });
+    for c in source[start..end].chars() {
+      if c == '\n' {
+        line += 1;
+        col = 1;
+      } else {
+        col += 1;
+      }
+    }
+  }
+  tokens
+}
+
+/// Returns the first token that follows the document's top-level value, other than whitespace and
+/// comments. A JSON5 document holds exactly one value, so any such token starts trailing content
+/// that is not part of the document. Returns `None` if there is no trailing content, or if the
+/// document's braces are unbalanced (in which case the parser will report a more useful error).
+pub fn trailing_content(tokens: &[Token]) -> Option<Token> {
+  let mut depth = 0usize;
+  let mut value_complete = false;
+  for token in tokens.iter().filter(|token| !token.is_trivia()) {
+    if value_complete {
+      return Some(*token);
+    }
+    match token.kind {
+      TokenKind::OpenBrace | TokenKind::OpenBracket => depth += 1,
+      TokenKind::CloseBrace | TokenKind::CloseBracket => {
+        depth = depth.checked_sub(1)?;
+        value_complete = depth == 0;
+      }
+      TokenKind::String | TokenKind::Word => value_complete = depth == 0,
+      _ => {}
+    }
+  }
+  None
+}
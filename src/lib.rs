@@ -0,0 +1,37 @@
+//! The formatting core of formatjson5: the lexer and parser, the repairs of malformed documents,
+//! the edits that fix documents before json5format formats them, and the passes that rewrite its
+//! output. The executable is built on this library, which is also what the fuzz targets (in
+//! `fuzz`) run.
+//!
+//! Other programs format documents with [format_str()](fn.format_str.html) and
+//! [format_file()](fn.format_file.html) (see the [format](format/index.html) module), and generate
+//! them with the [builder](builder/index.html) module; the other modules are not a stable interface
+//! for other crates.
+
+#![warn(missing_docs)]
+
+pub mod array_wrap;
+pub mod builder;
+pub mod chunked;
+pub mod codes;
+pub mod comment_attachment;
+pub mod conflicts;
+pub mod diagnostics;
+pub mod document;
+pub mod edit;
+pub mod empty;
+pub mod eol_comments;
+pub mod features;
+pub mod fixes;
+pub mod format;
+pub mod indentation;
+pub mod lexer;
+pub mod literals;
+pub mod long_values;
+pub mod parallel;
+pub mod quotes;
+pub mod repair;
+pub mod suggest;
+pub mod syntax;
+
+pub use format::{format_file, format_str, FormatError, FormatOutcome, Options};
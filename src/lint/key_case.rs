@@ -0,0 +1,188 @@
+//! Checks that property names follow a naming convention, and renames those that do not (the
+//! `--key-case` option).
+
+use crate::{
+  codes,
+  diagnostics::Diagnostic,
+  edit::Edits,
+  repair::Repair,
+  syntax,
+  syntax::{Member, Node},
+};
+use heck::{KebabCase, MixedCase, SnakeCase};
+use std::str::FromStr;
+
+/// A naming convention for property names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyCase {
+  /// `camelCase`.
+  Camel,
+  /// `snake_case`.
+  Snake,
+  /// `kebab-case`.
+  Kebab,
+  /// Any name: property names are not checked.
+  Preserve,
+}
+
+impl KeyCase {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["camel", "snake", "kebab", "preserve"];
+
+  /// Returns `name` converted to this convention. A prefix of punctuation (such as the `$` of
+  /// `$schema`, or the `_` of `_comment`) is kept as it is.
+  pub fn convert(self, name: &str) -> String {
+    let words = name.trim_start_matches(|c: char| !c.is_alphanumeric());
+    let prefix = &name[..name.len() - words.len()];
+    let words = match self {
+      KeyCase::Camel => words.to_mixed_case(),
+      KeyCase::Snake => words.to_snake_case(),
+      KeyCase::Kebab => words.to_kebab_case(),
+      KeyCase::Preserve => words.to_string(),
+    };
+    format!("{}{}", prefix, words)
+  }
+
+  /// Returns the name of the convention, for messages.
+  fn describe(self) -> &'static str {
+    match self {
+      KeyCase::Camel => "camelCase",
+      KeyCase::Snake => "snake_case",
+      KeyCase::Kebab => "kebab-case",
+      KeyCase::Preserve => "any case",
+    }
+  }
+}
+
+impl FromStr for KeyCase {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "camel" => Ok(KeyCase::Camel),
+      "snake" => Ok(KeyCase::Snake),
+      "kebab" => Ok(KeyCase::Kebab),
+      "preserve" => Ok(KeyCase::Preserve),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        KeyCase::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// A property name that does not follow the naming convention.
+struct Mismatch<'a> {
+  /// The property.
+  member: &'a Member,
+  /// The name converted to the convention.
+  expected: String,
+  /// True if another property of the same object already has the expected name, or would be
+  /// renamed to it.
+  conflict: bool,
+}
+
+/// Calls `found` for each property in `node` and its descendants whose name does not follow the
+/// convention `case`, other than the names in `exclude`.
+fn mismatches<'a>(
+  node: &'a Node,
+  case: KeyCase,
+  exclude: &[String],
+  found: &mut impl FnMut(Mismatch<'a>),
+) {
+  match node {
+    Node::Object { members, .. } => {
+      for member in members {
+        let expected = case.convert(&member.name);
+        if expected != member.name && !exclude.contains(&member.name) {
+          let conflict = members.iter().any(|other| {
+            !std::ptr::eq(other, member)
+              && (other.name == expected || case.convert(&other.name) == expected)
+          });
+          found(Mismatch {
+            member,
+            expected,
+            conflict,
+          });
+        }
+        mismatches(&member.value, case, exclude, found);
+      }
+    }
+    Node::Array { items, .. } => {
+      for item in items {
+        mismatches(item, case, exclude, found);
+      }
+    }
+    Node::String { .. } | Node::Literal { .. } => {}
+  }
+}
+
+/// Returns a warning for each property name in `root` that does not follow the convention `case`,
+/// other than the names in `exclude`.
+pub fn check(root: &Node, case: KeyCase, exclude: &[String]) -> Vec<Diagnostic> {
+  let mut warnings = Vec::new();
+  mismatches(root, case, exclude, &mut |mismatch| {
+    warnings.push(Diagnostic::warning(
+      &codes::KEY_CASE,
+      mismatch.member.key.line,
+      mismatch.member.key.col,
+      format!(
+        "Property name `{}` is not {} (expected `{}`)",
+        mismatch.member.name,
+        case.describe(),
+        mismatch.expected
+      ),
+    ))
+  });
+  warnings
+}
+
+/// Adds edits that rename each property name in `root`, parsed from `source`, that does not follow
+/// the convention `case` (other than the names in `exclude`), and returns each rename, and a
+/// warning for each property that cannot be renamed. A quoted name keeps its quotes, and a new name
+/// that is not a valid identifier is quoted. A property cannot be renamed if its object already has
+/// a property with the new name.
+pub fn rename(
+  root: &Node,
+  source: &str,
+  case: KeyCase,
+  exclude: &[String],
+  edits: &mut Edits,
+) -> (Vec<Repair>, Vec<Diagnostic>) {
+  let mut renames = Vec::new();
+  let mut warnings = Vec::new();
+  mismatches(root, case, exclude, &mut |mismatch| {
+    let Mismatch {
+      member,
+      expected,
+      conflict,
+    } = mismatch;
+    if conflict {
+      warnings.push(Diagnostic::warning(
+        &codes::KEY_CASE_CONFLICT,
+        member.key.line,
+        member.key.col,
+        format!(
+          "Property name `{}` is not {}, but cannot be renamed, because another property has or would have the name `{}`",
+          member.name,
+          case.describe(),
+          expected
+        ),
+      ));
+      return;
+    }
+    let key = &source[member.key.start..member.key.end];
+    let text = match key.chars().next() {
+      Some(quote @ ('"' | '\'')) => syntax::quote(&expected, quote),
+      _ if syntax::is_identifier(&expected) => expected.clone(),
+      _ => syntax::quote(&expected, '"'),
+    };
+    edits.replace(member.key.start..member.key.end, text);
+    renames.push(Repair {
+      line: member.key.line,
+      col: member.key.col,
+      message: format!("Renamed `{}` to `{}`", member.name, expected),
+    });
+  });
+  (renames, warnings)
+}
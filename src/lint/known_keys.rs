@@ -0,0 +1,116 @@
+//! Detects misspelled property names, by comparing them to a list of known names (the
+//! `--known-keys` option).
+
+use crate::{
+  codes,
+  diagnostics::Diagnostic,
+  suggest,
+  syntax::{self, Node},
+};
+use std::{collections::BTreeSet, fs, path::Path};
+
+/// The property names that documents are expected to use, for detecting misspelled names.
+#[derive(Debug, Default)]
+pub struct KnownKeys {
+  /// The known names.
+  names: BTreeSet<String>,
+}
+
+impl KnownKeys {
+  /// Reads the known names from the file at `path`, which holds either a JSON5 array of names, a
+  /// JSON Schema (all of whose `properties` are known names), or one name per line (where blank
+  /// lines and lines starting with `#` are ignored).
+  pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+    let source = fs::read_to_string(path)
+      .map_err(|err| anyhow::anyhow!("Known keys error: {}: {}", path.display(), err))?;
+    let mut names = BTreeSet::new();
+    match syntax::parse(&source) {
+      Ok(Some(Node::Array { items, .. })) => {
+        for item in items {
+          match item {
+            Node::String { value, .. } => {
+              names.insert(value);
+            }
+            item => {
+              let span = item.span();
+              return Err(anyhow::anyhow!(
+                "Known keys error: {}:{}:{}: Expected a string, but found {}",
+                path.display(),
+                span.line,
+                span.col,
+                item.type_name(&source)
+              ));
+            }
+          }
+        }
+      }
+      Ok(Some(schema @ Node::Object { .. })) => schema_properties(&schema, &mut names),
+      _ => names.extend(
+        source
+          .lines()
+          .map(str::trim)
+          .filter(|line| !line.is_empty() && !line.starts_with('#'))
+          .map(str::to_string),
+      ),
+    }
+    Ok(Self { names })
+  }
+
+  /// Returns a warning for each property in the document `root` whose name is not known, but is
+  /// similar to a known name.
+  pub fn check(&self, root: &Node) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    self.check_node(root, &mut warnings);
+    warnings
+  }
+
+  /// Adds the warnings for `node` and its descendants to `warnings`.
+  fn check_node(&self, node: &Node, warnings: &mut Vec<Diagnostic>) {
+    match node {
+      Node::Object { members, .. } => {
+        for member in members {
+          if !self.names.contains(&member.name) {
+            if let Some(known) =
+              suggest::closest(&member.name, self.names.iter().map(String::as_str))
+            {
+              warnings.push(Diagnostic::warning(
+                &codes::UNKNOWN_KEY,
+                member.key.line,
+                member.key.col,
+                format!("Unknown key `{}` (did you mean `{}`?)", member.name, known),
+              ));
+            }
+          }
+          self.check_node(&member.value, warnings);
+        }
+      }
+      Node::Array { items, .. } => {
+        for item in items {
+          self.check_node(item, warnings);
+        }
+      }
+      Node::String { .. } | Node::Literal { .. } => {}
+    }
+  }
+}
+
+/// Adds the names of the `properties` of every schema in the JSON Schema `node` to `names`.
+fn schema_properties(node: &Node, names: &mut BTreeSet<String>) {
+  match node {
+    Node::Object { members, .. } => {
+      for member in members {
+        if let ("properties", Node::Object { members, .. }) = (member.name.as_str(), &member.value)
+        {
+          names.extend(members.iter().map(|property| property.name.clone()));
+        }
+        schema_properties(&member.value, names);
+      }
+    }
+    Node::Array { items, .. } => {
+      for item in items {
+        schema_properties(item, names);
+      }
+    }
+    Node::String { .. } | Node::Literal { .. } => {}
+  }
+}
@@ -0,0 +1,379 @@
+//! Checks documents for likely mistakes that are not syntax errors, and reports them through the
+//! [diagnostics](../diagnostics/index.html) renderer.
+//!
+//! Misspelled property names are found by comparing them to a list of known names (see
+//! [KnownKeys](struct.KnownKeys.html)), and property names that do not follow the naming
+//! convention selected by `--key-case` are reported, or renamed with `--fix` (see
+//! [key_case](key_case/index.html)). The other checks are lint rules (see
+//! [RULES](rules/constant.RULES.html)), which are all off by default, and are enabled in the
+//! configuration file with the level of each rule (`off`, `warn`, or `error`), and the rule's
+//! option, if it has one:
+//!
+//!     {
+//!         lint: {
+//!             "no-empty-object": "warn",
+//!             "max-depth": ["error", 8],
+//!             "key-naming-convention": ["warn", "snake_case"],
+//!             "keys-sorted": ["warn", "natural"],
+//!             "max-string-length": ["error", 255, ["/description", "**/license"]],
+//!         },
+//!     }
+//!
+//! Some rules also take an allowlist of the path patterns of values they skip (patterns match
+//! paths as in `--redact`), after the option, as in `max-string-length` above.
+//!
+//! Environment variables and directives set rules as a comma-separated list of
+//! `rule=level[:option[:pattern|pattern...]]` pairs, such as
+//! `JSON5FMT_LINT=no-empty-object=warn,max-depth=error:8`.
+//! Each layer of the configuration overrides only the rules it sets. A rule at level `error` makes
+//! formatting the document fail, after all of the document's findings are reported. On the
+//! command line, `--deny <rule>` reports the warnings of a rule as errors, and `--deny-warnings`
+//! every warning (of the lint rules and of the formatter's other checks), so CI can fail on them.
+//!
+//! The `lint_overrides` option sets rules for the files whose paths match a pattern, over the
+//! rules of every layer (patterns match path segments as in `--redact`, and a pattern without a
+//! leading `/` matches at any depth), with later patterns taking precedence:
+//!
+//!     {
+//!         lint: {"require-root": "error"},
+//!         lint_overrides: {
+//!             "*.list.json5": {"require-root": ["error", "array"]},
+//!             "fixtures/**": {"require-root": "off"},
+//!         },
+//!     }
+
+pub mod key_case;
+mod known_keys;
+pub mod rules;
+
+pub use key_case::KeyCase;
+pub use known_keys::KnownKeys;
+
+use crate::{
+  diagnostics::{Diagnostic, Severity},
+  glob::PathGlob,
+  suggest,
+  syntax::Node,
+};
+use rules::{Rule, RULES};
+use std::{collections::BTreeMap, str::FromStr};
+
+/// Whether a lint rule is checked, and how its findings are reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+  /// The rule is not checked.
+  Off,
+  /// Findings are reported as warnings.
+  Warn,
+  /// Findings are reported as errors, which make formatting the document fail.
+  Error,
+}
+
+impl Level {
+  /// The accepted spellings of each variant.
+  pub const VARIANTS: &'static [&'static str] = &["off", "warn", "error"];
+}
+
+impl FromStr for Level {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "off" => Ok(Level::Off),
+      "warn" => Ok(Level::Warn),
+      "error" => Ok(Level::Error),
+      _ => Err(format!(
+        "Expected a lint level ({}), but found `{}`",
+        Level::VARIANTS.join(", "),
+        s
+      )),
+    }
+  }
+}
+
+/// The configured level of a lint rule, and its option.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleConfig {
+  /// Whether the rule is checked, and how its findings are reported.
+  pub level: Level,
+  /// The rule's option, if given; otherwise the rule uses its default.
+  pub option: Option<String>,
+  /// The path patterns of the values the rule skips, for the rules that take an allowlist.
+  pub allow: Vec<String>,
+}
+
+/// The lint rules set for the files whose paths match a pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintOverride {
+  /// The pattern of the file paths, such as `fixtures/**` (see the [glob](../glob/index.html)
+  /// module).
+  pub pattern: String,
+  /// The rules set for the matching files, by rule name.
+  pub rules: BTreeMap<String, RuleConfig>,
+}
+
+/// Returns the rules for the file `filename`: `rules`, overridden by the rules of each of the
+/// `overrides` whose pattern matches its path, in order.
+pub fn rules_for(
+  rules: Option<&BTreeMap<String, RuleConfig>>,
+  overrides: &[LintOverride],
+  filename: &str,
+) -> BTreeMap<String, RuleConfig> {
+  let mut rules = rules.cloned().unwrap_or_default();
+  let segments = filename
+    .trim_start_matches("./")
+    .split('/')
+    .map(str::to_string)
+    .collect::<Vec<_>>();
+  for lint_override in overrides {
+    let matches = lint_override
+      .pattern
+      .parse::<PathGlob>()
+      .is_ok_and(|pattern| pattern.matches(&segments));
+    if matches {
+      rules.extend(lint_override.rules.clone());
+    }
+  }
+  rules
+}
+
+/// Parses lint overrides from `text`, a semicolon-separated list of
+/// `pattern=rule=level[:option],...` pairs, such as
+/// `fixtures/**=require-root=off;*.list.json5=require-root=error:array`.
+pub fn parse_overrides(text: &str) -> Option<Vec<LintOverride>> {
+  text
+    .split(';')
+    .map(str::trim)
+    .filter(|pair| !pair.is_empty())
+    .map(|pair| {
+      let (pattern, rules) = pair.split_once('=')?;
+      pattern.trim().parse::<PathGlob>().ok()?;
+      Some(LintOverride {
+        pattern: pattern.trim().to_string(),
+        rules: parse_rules(rules).ok()?,
+      })
+    })
+    .collect()
+}
+
+/// Returns the lint rule named `name`, or an error message suggesting the most similar rule name,
+/// if any.
+fn rule(name: &str) -> Result<&'static Rule, String> {
+  RULES.iter().find(|rule| rule.name == name).ok_or_else(|| {
+    match suggest::closest(name, RULES.iter().map(|rule| rule.name)) {
+      Some(suggestion) => format!(
+        "Unknown lint rule `{}` (did you mean `{}`?)",
+        name, suggestion
+      ),
+      None => format!("Unknown lint rule `{}`", name),
+    }
+  })
+}
+
+/// Validates the configuration of the rule `name` at `level`, with `option` and the path patterns
+/// of its allowlist, returning an error message if the rule, level, option, or a pattern is
+/// invalid.
+pub fn configure(
+  name: &str,
+  level: &str,
+  option: Option<&str>,
+  allow: &[String],
+) -> Result<RuleConfig, String> {
+  let rule = rule(name)?;
+  let level = level.parse::<Level>()?;
+  if let Some(option) = option {
+    let Some(spec) = &rule.option else {
+      return Err(format!("Lint rule `{}` does not take an option", name));
+    };
+    if !(spec.is_valid)(option) {
+      return Err(format!(
+        "The option of lint rule `{}` must be {}, but found `{}`",
+        name, spec.description, option
+      ));
+    }
+  }
+  if !allow.is_empty() && !rule.allowlist {
+    return Err(format!("Lint rule `{}` does not take an allowlist", name));
+  }
+  for pattern in allow {
+    if let Err(err) = pattern.parse::<PathGlob>() {
+      return Err(format!(
+        "Invalid pattern in the allowlist of lint rule `{}`: {}",
+        name, err
+      ));
+    }
+  }
+  Ok(RuleConfig {
+    level,
+    option: option.map(str::to_string),
+    allow: allow.to_vec(),
+  })
+}
+
+/// Parses rule configurations from `text`, a comma-separated list of
+/// `rule=level[:option[:pattern|pattern...]]` pairs.
+pub fn parse_rules(text: &str) -> Result<BTreeMap<String, RuleConfig>, String> {
+  let mut configs = BTreeMap::new();
+  for pair in text
+    .split(',')
+    .map(str::trim)
+    .filter(|pair| !pair.is_empty())
+  {
+    let Some((name, setting)) = pair.split_once('=') else {
+      return Err(format!("Expected `rule=level`, but found `{}`", pair));
+    };
+    let (level, option) = match setting.split_once(':') {
+      Some((level, option)) => (level, Some(option)),
+      None => (setting, None),
+    };
+    let (option, allow) = match option.and_then(|option| option.split_once(':')) {
+      Some((option, allow)) => (Some(option), allow.split('|').map(str::to_string).collect()),
+      None => (option, Vec::new()),
+    };
+    configs.insert(name.to_string(), configure(name, level, option, &allow)?);
+  }
+  Ok(configs)
+}
+
+/// Checks the document `root`, parsed from `source`, with each rule in `configs` that is not off,
+/// and returns the findings in source order.
+pub fn run(root: &Node, source: &str, configs: &BTreeMap<String, RuleConfig>) -> Vec<Diagnostic> {
+  let mut diagnostics = Vec::new();
+  for rule in RULES {
+    let Some(config) = configs.get(rule.name) else {
+      continue;
+    };
+    let severity = match config.level {
+      Level::Off => continue,
+      Level::Warn => Severity::Warning,
+      Level::Error => Severity::Error,
+    };
+    let option = config
+      .option
+      .as_deref()
+      .or(rule.option.as_ref().map(|option| option.default))
+      .unwrap_or("");
+    let allow = config
+      .allow
+      .iter()
+      .filter_map(|pattern| pattern.parse::<PathGlob>().ok())
+      .collect::<Vec<_>>();
+    let mut findings = Vec::new();
+    (rule.check)(root, source, option, &allow, &mut findings);
+    diagnostics.extend(findings.into_iter().map(|finding| Diagnostic {
+      code: rule.code,
+      severity,
+      rule: Some(rule.name),
+      ..finding
+    }));
+  }
+  diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.col));
+  diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::syntax;
+
+  #[test]
+  fn parses_levels_and_options() {
+    let configs = parse_rules("no-empty-object=warn, max-depth=error:8,keys-sorted=off").unwrap();
+    let config = |level, option: Option<&str>| RuleConfig {
+      level,
+      option: option.map(str::to_string),
+      allow: Vec::new(),
+    };
+    assert_eq!(
+      configs,
+      BTreeMap::from([
+        ("keys-sorted".to_string(), config(Level::Off, None)),
+        ("max-depth".to_string(), config(Level::Error, Some("8"))),
+        ("no-empty-object".to_string(), config(Level::Warn, None)),
+      ])
+    );
+  }
+
+  #[test]
+  fn rejects_invalid_configurations() {
+    assert_eq!(
+      parse_rules("no-empty-objects=warn").unwrap_err(),
+      "Unknown lint rule `no-empty-objects` (did you mean `no-empty-object`?)"
+    );
+    assert_eq!(
+      parse_rules("no-empty-object").unwrap_err(),
+      "Expected `rule=level`, but found `no-empty-object`"
+    );
+    assert_eq!(
+      configure("max-depth", "warning", None, &[]).unwrap_err(),
+      "Expected a lint level (off, warn, error), but found `warning`"
+    );
+    assert_eq!(
+      configure("max-depth", "warn", Some("0"), &[]).unwrap_err(),
+      "The option of lint rule `max-depth` must be a positive integer, but found `0`"
+    );
+    assert_eq!(
+      configure("no-empty-object", "warn", Some("1"), &[]).unwrap_err(),
+      "Lint rule `no-empty-object` does not take an option"
+    );
+  }
+
+  #[test]
+  fn reports_findings_at_the_configured_levels_in_source_order() {
+    let source = "{a: {}, b: {c: [1, 1]}}";
+    let root = syntax::parse(source).unwrap().unwrap();
+    let configs =
+      parse_rules("no-empty-object=error,no-duplicate-array-items=warn,max-depth=warn:2").unwrap();
+    let findings = run(&root, source, &configs)
+      .into_iter()
+      .map(|finding| (finding.line, finding.col, finding.severity, finding.rule))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      findings,
+      [
+        (1, 5, Severity::Error, Some("no-empty-object")),
+        (1, 16, Severity::Warning, Some("max-depth")),
+        (1, 20, Severity::Warning, Some("no-duplicate-array-items")),
+      ]
+    );
+  }
+
+  #[test]
+  fn skips_rules_that_are_off() {
+    let source = "{a: {}}";
+    let root = syntax::parse(source).unwrap().unwrap();
+    let configs = parse_rules("no-empty-object=off").unwrap();
+    assert!(run(&root, source, &configs).is_empty());
+  }
+
+  #[test]
+  fn parses_allowlists() {
+    let configs = parse_rules("max-string-length=warn:80:/description|**/license").unwrap();
+    assert_eq!(
+      configs["max-string-length"],
+      RuleConfig {
+        level: Level::Warn,
+        option: Some("80".to_string()),
+        allow: vec!["/description".to_string(), "**/license".to_string()],
+      }
+    );
+  }
+
+  #[test]
+  fn rejects_allowlists_of_rules_without_one() {
+    assert_eq!(
+      parse_rules("max-depth=warn:8:/a").unwrap_err(),
+      "Lint rule `max-depth` does not take an allowlist"
+    );
+  }
+
+  #[test]
+  fn skips_the_allowed_paths() {
+    let source = "{description: 'long text', name: 'long name'}";
+    let root = syntax::parse(source).unwrap().unwrap();
+    let configs = parse_rules("max-string-length=error:4:/description").unwrap();
+    let findings = run(&root, source, &configs);
+    assert_eq!(findings.len(), 1);
+    assert_eq!((findings[0].line, findings[0].col), (1, 34));
+  }
+}
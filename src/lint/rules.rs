@@ -0,0 +1,919 @@
+//! The lint rules, and the checks that implement them.
+
+use crate::{
+  codes::{self, Code},
+  diagnostics::Diagnostic,
+  glob::PathGlob,
+  syntax::Node,
+};
+use std::{cmp::Ordering, str::FromStr};
+
+/// Describes the option of a lint rule.
+#[derive(Debug)]
+pub struct RuleOption {
+  /// Describes the accepted values, for messages.
+  pub description: &'static str,
+  /// The value used if the option is not configured.
+  pub default: &'static str,
+  /// Returns true if the given value is accepted.
+  pub is_valid: fn(&str) -> bool,
+}
+
+/// A lint rule.
+#[derive(Debug)]
+pub struct Rule {
+  /// The rule name, used to configure it.
+  pub name: &'static str,
+  /// The code of the rule's findings.
+  pub code: &'static Code,
+  /// The rule's option, if it has one.
+  pub option: Option<RuleOption>,
+  /// Whether the rule takes an allowlist of the path patterns of values it skips.
+  pub allowlist: bool,
+  /// Adds a finding for each problem in the document (the root value and the source it was parsed
+  /// from) to the findings, given the rule's option (or an empty string, if it has none) and its
+  /// allowlist. The severity and rule of each finding are set by the caller.
+  pub check: fn(&Node, &str, &str, &[PathGlob], &mut Vec<Diagnostic>),
+}
+
+/// The lint rules, which are all off unless configured:
+///
+/// - `no-empty-object`: objects must have at least one property.
+/// - `no-duplicate-array-items`: arrays must not hold the same value more than once.
+/// - `max-depth`: objects and arrays must not be nested more deeply than the option (a positive
+///   integer; by default, 10).
+/// - `key-naming-convention`: property names must follow the option (`camelCase`, the default, or
+///   `snake_case`).
+/// - `keys-sorted`: the properties of each object must be sorted by name, in the order given by the
+///   option (`bytes`, the default, `case-insensitive`, or `natural`).
+/// - `require-root`: the top-level value must be of the type given by the option (`object`, the
+///   default, `array`, or `any`; see [RootKind](enum.RootKind.html)).
+/// - `max-key-length`: property names must not be longer, in bytes of UTF-8, than the option (a
+///   positive integer; by default, 64), except the properties at the paths of the allowlist.
+/// - `max-string-length`: string values must not be longer, in bytes of UTF-8, than the option (a
+///   positive integer; by default, 255), except the values at the paths of the allowlist.
+/// - `no-unsafe-integer`: integers must not be larger in magnitude than 2^53, the largest a double
+///   holds exactly, except the values at the paths of the allowlist.
+pub const RULES: &[Rule] = &[
+  Rule {
+    name: "no-empty-object",
+    code: &codes::NO_EMPTY_OBJECT,
+    option: None,
+    allowlist: false,
+    check: no_empty_object,
+  },
+  Rule {
+    name: "no-duplicate-array-items",
+    code: &codes::NO_DUPLICATE_ARRAY_ITEMS,
+    option: None,
+    allowlist: false,
+    check: no_duplicate_array_items,
+  },
+  Rule {
+    name: "max-depth",
+    code: &codes::MAX_DEPTH,
+    option: Some(RuleOption {
+      description: "a positive integer",
+      default: "10",
+      is_valid: |option| option.parse::<usize>().is_ok_and(|depth| depth > 0),
+    }),
+    allowlist: false,
+    check: max_depth,
+  },
+  Rule {
+    name: "key-naming-convention",
+    code: &codes::KEY_NAMING_CONVENTION,
+    option: Some(RuleOption {
+      description: "camelCase or snake_case",
+      default: "camelCase",
+      is_valid: |option| matches!(option, "camelCase" | "snake_case"),
+    }),
+    allowlist: false,
+    check: key_naming_convention,
+  },
+  Rule {
+    name: "keys-sorted",
+    code: &codes::KEYS_SORTED,
+    option: Some(RuleOption {
+      description: "bytes, case-insensitive, or natural",
+      default: "bytes",
+      is_valid: |option| matches!(option, "bytes" | "case-insensitive" | "natural"),
+    }),
+    allowlist: false,
+    check: keys_sorted,
+  },
+  Rule {
+    name: "require-root",
+    code: &codes::REQUIRE_ROOT,
+    option: Some(RuleOption {
+      description: "object, array, or any",
+      default: "object",
+      is_valid: |option| option.parse::<RootKind>().is_ok(),
+    }),
+    allowlist: false,
+    check: require_root,
+  },
+  Rule {
+    name: "max-key-length",
+    code: &codes::MAX_KEY_LENGTH,
+    option: Some(RuleOption {
+      description: "a positive integer",
+      default: "64",
+      is_valid: |option| option.parse::<usize>().is_ok_and(|length| length > 0),
+    }),
+    allowlist: true,
+    check: max_key_length,
+  },
+  Rule {
+    name: "max-string-length",
+    code: &codes::MAX_STRING_LENGTH,
+    option: Some(RuleOption {
+      description: "a positive integer",
+      default: "255",
+      is_valid: |option| option.parse::<usize>().is_ok_and(|length| length > 0),
+    }),
+    allowlist: true,
+    check: max_string_length,
+  },
+  Rule {
+    name: "no-unsafe-integer",
+    code: &codes::NO_UNSAFE_INTEGER,
+    option: None,
+    allowlist: true,
+    check: no_unsafe_integer,
+  },
+];
+
+/// The types of top-level value that the `require-root` rule accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RootKind {
+  /// An object.
+  Object,
+  /// An array.
+  Array,
+  /// Any value.
+  Any,
+}
+
+impl RootKind {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["object", "array", "any"];
+
+  /// Returns the spelling of the kind, as in the rule's option.
+  pub fn name(self) -> &'static str {
+    match self {
+      RootKind::Object => "object",
+      RootKind::Array => "array",
+      RootKind::Any => "any",
+    }
+  }
+}
+
+impl FromStr for RootKind {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "object" => Ok(RootKind::Object),
+      "array" => Ok(RootKind::Array),
+      "any" => Ok(RootKind::Any),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        RootKind::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// Reports objects with no properties.
+fn no_empty_object(
+  root: &Node,
+  _source: &str,
+  _option: &str,
+  _allow: &[PathGlob],
+  findings: &mut Vec<Diagnostic>,
+) {
+  root.walk(&mut |node, _| {
+    if let Node::Object { span, members } = node {
+      if members.is_empty() {
+        findings.push(Diagnostic::warning(
+          &codes::NO_EMPTY_OBJECT,
+          span.line,
+          span.col,
+          "Empty object".to_string(),
+        ));
+      }
+    }
+  });
+}
+
+/// Reports array items that are equal to an earlier item of the same array.
+fn no_duplicate_array_items(
+  root: &Node,
+  source: &str,
+  _option: &str,
+  _allow: &[PathGlob],
+  findings: &mut Vec<Diagnostic>,
+) {
+  root.walk(&mut |node, _| {
+    if let Node::Array { items, .. } = node {
+      let values = items
+        .iter()
+        .map(|item| canonical(item, source))
+        .collect::<Vec<_>>();
+      for (index, item) in items.iter().enumerate() {
+        if let Some(first) = values[..index]
+          .iter()
+          .position(|value| *value == values[index])
+        {
+          let span = item.span();
+          findings.push(Diagnostic::warning(
+            &codes::NO_DUPLICATE_ARRAY_ITEMS,
+            span.line,
+            span.col,
+            format!("Duplicate of array item {}", first + 1),
+          ));
+        }
+      }
+    }
+  });
+}
+
+/// Returns a representation of the value `node` that is the same for equal values, regardless of
+/// whitespace, comments, and how strings are quoted.
+fn canonical(node: &Node, source: &str) -> String {
+  match node {
+    Node::Object { members, .. } => {
+      let members = members
+        .iter()
+        .map(|member| format!("{:?}:{}", member.name, canonical(&member.value, source)))
+        .collect::<Vec<_>>();
+      format!("{{{}}}", members.join(","))
+    }
+    Node::Array { items, .. } => {
+      let items = items
+        .iter()
+        .map(|item| canonical(item, source))
+        .collect::<Vec<_>>();
+      format!("[{}]", items.join(","))
+    }
+    Node::String { value, .. } => format!("{:?}", value),
+    Node::Literal { span } => source[span.start..span.end].to_string(),
+  }
+}
+
+/// Reports the objects and arrays nested more deeply than the depth given by `option`, where the
+/// top-level value is at depth 1. Only the outermost container of each too-deep subtree is
+/// reported.
+fn max_depth(
+  root: &Node,
+  _source: &str,
+  option: &str,
+  _allow: &[PathGlob],
+  findings: &mut Vec<Diagnostic>,
+) {
+  let limit = option.parse::<usize>().unwrap_or(10);
+  report_depth(root, 1, limit, findings);
+}
+
+/// Reports `node`, or the too-deep containers among its descendants, for
+/// [max_depth()](fn.max_depth.html).
+fn report_depth(node: &Node, depth: usize, limit: usize, findings: &mut Vec<Diagnostic>) {
+  let children: Vec<&Node> = match node {
+    Node::Object { members, .. } => members.iter().map(|member| &member.value).collect(),
+    Node::Array { items, .. } => items.iter().collect(),
+    Node::String { .. } | Node::Literal { .. } => return,
+  };
+  if depth > limit {
+    let span = node.span();
+    findings.push(Diagnostic::warning(
+      &codes::MAX_DEPTH,
+      span.line,
+      span.col,
+      format!("Nesting depth {} exceeds the maximum of {}", depth, limit),
+    ));
+    return;
+  }
+  for child in children {
+    report_depth(child, depth + 1, limit, findings);
+  }
+}
+
+/// Reports the top-level value if it is not of the type given by `option`.
+fn require_root(
+  root: &Node,
+  source: &str,
+  option: &str,
+  _allow: &[PathGlob],
+  findings: &mut Vec<Diagnostic>,
+) {
+  let expected = option.parse::<RootKind>().unwrap_or(RootKind::Object);
+  let found = root.type_name(source);
+  if expected == RootKind::Any || found == expected.name() {
+    return;
+  }
+  let article = |type_name: &str| match type_name {
+    "object" | "array" => format!("an {}", type_name),
+    "null" => type_name.to_string(),
+    _ => format!("a {}", type_name),
+  };
+  let span = root.span();
+  findings.push(Diagnostic::warning(
+    &codes::REQUIRE_ROOT,
+    span.line,
+    span.col,
+    format!(
+      "The top-level value must be {}, but found {}",
+      article(expected.name()),
+      article(found)
+    ),
+  ));
+}
+
+/// Reports the property names longer than the length given by `option`, except the properties at
+/// paths matching one of the patterns of `allow`.
+fn max_key_length(
+  root: &Node,
+  _source: &str,
+  option: &str,
+  allow: &[PathGlob],
+  findings: &mut Vec<Diagnostic>,
+) {
+  let limit = option.parse::<usize>().unwrap_or(64);
+  root.walk(&mut |node, segments| {
+    let Node::Object { members, .. } = node else {
+      return;
+    };
+    for member in members.iter().filter(|member| member.name.len() > limit) {
+      let path = [segments, std::slice::from_ref(&member.name)].concat();
+      if !allow.iter().any(|pattern| pattern.matches(&path)) {
+        findings.push(Diagnostic::warning(
+          &codes::MAX_KEY_LENGTH,
+          member.key.line,
+          member.key.col,
+          format!(
+            "Property name `{}` is {} bytes long, longer than the maximum of {}",
+            member.name,
+            member.name.len(),
+            limit
+          ),
+        ));
+      }
+    }
+  });
+}
+
+/// Reports the string values longer than the length given by `option`, except the values at paths
+/// matching one of the patterns of `allow`.
+fn max_string_length(
+  root: &Node,
+  _source: &str,
+  option: &str,
+  allow: &[PathGlob],
+  findings: &mut Vec<Diagnostic>,
+) {
+  let limit = option.parse::<usize>().unwrap_or(255);
+  root.walk(&mut |node, segments| {
+    if let Node::String { value, span } = node {
+      if value.len() > limit && !allow.iter().any(|pattern| pattern.matches(segments)) {
+        findings.push(Diagnostic::warning(
+          &codes::MAX_STRING_LENGTH,
+          span.line,
+          span.col,
+          format!(
+            "String is {} bytes long, longer than the maximum of {}",
+            value.len(),
+            limit
+          ),
+        ));
+      }
+    }
+  });
+}
+
+/// Reports the integers, outside the paths of the allowlist `allow`, that are larger in magnitude
+/// than 2^53, so a double cannot hold them exactly.
+fn no_unsafe_integer(
+  root: &Node,
+  source: &str,
+  _option: &str,
+  allow: &[PathGlob],
+  findings: &mut Vec<Diagnostic>,
+) {
+  const SAFE_LIMIT: u128 = 1 << 53;
+  root.walk(&mut |node, segments| {
+    let Node::Literal { span } = node else {
+      return;
+    };
+    let text = &source[span.start..span.end];
+    let unsigned = text.trim_start_matches(['+', '-']);
+    let magnitude = match unsigned
+      .strip_prefix("0x")
+      .or_else(|| unsigned.strip_prefix("0X"))
+    {
+      Some(digits) => u128::from_str_radix(digits, 16),
+      None if unsigned.bytes().all(|byte| byte.is_ascii_digit()) => unsigned.parse::<u128>(),
+      // Not an integer (a fraction, an exponent, a keyword, `Infinity`, or `NaN`).
+      None => return,
+    };
+    // Digits too many for a `u128` are far outside the limit.
+    if magnitude.map_or(true, |magnitude| magnitude > SAFE_LIMIT)
+      && !allow.iter().any(|pattern| pattern.matches(segments))
+    {
+      findings.push(Diagnostic::warning(
+        &codes::NO_UNSAFE_INTEGER,
+        span.line,
+        span.col,
+        format!(
+          "Integer {} is larger in magnitude than 2^53, so parsers that read numbers as doubles \
+           lose its precision (write it as a string)",
+          text
+        ),
+      ));
+    }
+  });
+}
+
+/// Reports property names that do not follow the naming convention given by `option`.
+fn key_naming_convention(
+  root: &Node,
+  _source: &str,
+  option: &str,
+  _allow: &[PathGlob],
+  findings: &mut Vec<Diagnostic>,
+) {
+  root.walk(&mut |node, _| {
+    if let Node::Object { members, .. } = node {
+      for member in members {
+        let valid = match option {
+          "snake_case" => is_snake_case(&member.name),
+          _ => is_camel_case(&member.name),
+        };
+        if !valid {
+          findings.push(Diagnostic::warning(
+            &codes::KEY_NAMING_CONVENTION,
+            member.key.line,
+            member.key.col,
+            format!("Property name `{}` is not {}", member.name, option),
+          ));
+        }
+      }
+    }
+  });
+}
+
+/// Returns true if `name` is in camelCase: an ASCII lowercase letter, followed by ASCII letters and
+/// digits.
+fn is_camel_case(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars.next().is_some_and(|c| c.is_ascii_lowercase()) && chars.all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Returns true if `name` is in snake_case: ASCII lowercase words of letters and digits, starting
+/// with a letter and separated by single underscores.
+fn is_snake_case(name: &str) -> bool {
+  name.split('_').enumerate().all(|(index, word)| {
+    let mut chars = word.chars();
+    match chars.next() {
+      Some(c) => {
+        (c.is_ascii_lowercase() || (index > 0 && c.is_ascii_digit()))
+          && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+      }
+      None => false,
+    }
+  })
+}
+
+/// Reports each object whose property names are not sorted in the order given by `option`, at the
+/// first property that comes before the property preceding it.
+fn keys_sorted(
+  root: &Node,
+  _source: &str,
+  option: &str,
+  _allow: &[PathGlob],
+  findings: &mut Vec<Diagnostic>,
+) {
+  root.walk(&mut |node, _| {
+    if let Node::Object { members, .. } = node {
+      if let Some(pair) = members
+        .windows(2)
+        .find(|pair| compare_names(&pair[0].name, &pair[1].name, option) == Ordering::Greater)
+      {
+        findings.push(Diagnostic::warning(
+          &codes::KEYS_SORTED,
+          pair[1].key.line,
+          pair[1].key.col,
+          format!(
+            "Property `{}` should come before `{}` (the properties are not sorted)",
+            pair[1].name, pair[0].name
+          ),
+        ));
+      }
+    }
+  });
+}
+
+/// Compares the property names `a` and `b` in the order given by the option of
+/// [keys_sorted()](fn.keys_sorted.html).
+fn compare_names(a: &str, b: &str, option: &str) -> Ordering {
+  match option {
+    "case-insensitive" => a.to_lowercase().cmp(&b.to_lowercase()),
+    "natural" => natural_key(a).cmp(&natural_key(b)),
+    _ => a.cmp(b),
+  }
+}
+
+/// A part of a name, for natural ordering: a run of digits (compared by value, then by length, so
+/// `01` comes after `1`), or a run of other characters.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum NaturalPart<'a> {
+  /// A run of digits: the number of digits without leading zeros, those digits, and the length of
+  /// the run, which compare in that order.
+  Number(usize, &'a str, usize),
+  /// A run of other characters.
+  Text(&'a str),
+}
+
+/// Splits `name` into its parts, for natural ordering.
+fn natural_key(name: &str) -> Vec<NaturalPart<'_>> {
+  let mut parts = Vec::new();
+  let mut rest = name;
+  while let Some(first) = rest.chars().next() {
+    let digits = first.is_ascii_digit();
+    let end = rest
+      .find(|c: char| c.is_ascii_digit() != digits)
+      .unwrap_or(rest.len());
+    let (part, tail) = rest.split_at(end);
+    parts.push(match digits {
+      true => {
+        let value = part.trim_start_matches('0');
+        NaturalPart::Number(value.len(), value, part.len())
+      }
+      false => NaturalPart::Text(part),
+    });
+    rest = tail;
+  }
+  parts
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::syntax;
+
+  /// Returns the position and message of each finding of the rule `name` in `source`, with
+  /// `option` and the allowlist `allow`.
+  fn check(name: &str, source: &str, option: &str, allow: &[&str]) -> Vec<(usize, usize, String)> {
+    let rule = RULES.iter().find(|rule| rule.name == name).unwrap();
+    let root = syntax::parse(source).unwrap().unwrap();
+    let allow = allow
+      .iter()
+      .map(|pattern| pattern.parse().unwrap())
+      .collect::<Vec<PathGlob>>();
+    let mut findings = Vec::new();
+    (rule.check)(&root, source, option, &allow, &mut findings);
+    findings
+      .into_iter()
+      .map(|finding| (finding.line, finding.col, finding.message))
+      .collect()
+  }
+
+  /// Returns the positions of the findings of the rule `name` in `source`, with `option`.
+  fn positions(name: &str, source: &str, option: &str) -> Vec<(usize, usize)> {
+    check(name, source, option, &[])
+      .into_iter()
+      .map(|(line, col, _)| (line, col))
+      .collect()
+  }
+
+  #[test]
+  fn reports_empty_objects() {
+    assert_eq!(
+      check(
+        "no-empty-object",
+        "{a: {}, b: [1, { /* none */ }]}",
+        "",
+        &[]
+      ),
+      [
+        (1, 5, "Empty object".to_string()),
+        (1, 16, "Empty object".to_string())
+      ]
+    );
+  }
+
+  #[test]
+  fn accepts_objects_with_properties() {
+    assert_eq!(
+      positions("no-empty-object", "{a: {b: 1}, c: [], d: '{}'}", ""),
+      []
+    );
+  }
+
+  #[test]
+  fn reports_duplicate_array_items() {
+    let source = "[1, 'a', 1, \"a\", {x: 1, y: [2]}, { x: 1, 'y': [ 2 ] /* same */ }]";
+    assert_eq!(
+      check("no-duplicate-array-items", source, "", &[]),
+      [
+        (1, 10, "Duplicate of array item 1".to_string()),
+        (1, 13, "Duplicate of array item 2".to_string()),
+        (1, 34, "Duplicate of array item 5".to_string())
+      ]
+    );
+  }
+
+  #[test]
+  fn accepts_distinct_array_items() {
+    let source = "[1, 2, '1', [1], {x: 1}, {x: 2}, {y: 1}, {x: 1, y: 1}, [[1], [2]]]";
+    assert_eq!(positions("no-duplicate-array-items", source, ""), []);
+  }
+
+  #[test]
+  fn reports_the_outermost_container_past_the_maximum_depth() {
+    let source = "{a: {b: {c: {d: 1}}, e: [1]}, f: [[[]]]}";
+    assert_eq!(
+      check("max-depth", source, "2", &[]),
+      [
+        (1, 9, "Nesting depth 3 exceeds the maximum of 2".to_string()),
+        (
+          1,
+          25,
+          "Nesting depth 3 exceeds the maximum of 2".to_string()
+        ),
+        (
+          1,
+          35,
+          "Nesting depth 3 exceeds the maximum of 2".to_string()
+        )
+      ]
+    );
+  }
+
+  #[test]
+  fn accepts_containers_within_the_maximum_depth() {
+    let source = "{a: {b: {c: {d: 1}}, e: [1]}, f: [[[]]]}";
+    assert_eq!(positions("max-depth", source, "4"), []);
+    assert_eq!(positions("max-depth", "[1, 'a', null]", "1"), []);
+  }
+
+  #[test]
+  fn reports_names_that_are_not_camel_case() {
+    let source = "{fooBar: 1, foo_bar: 2, FooBar: 3, 'foo-bar': 4, '': 5}";
+    assert_eq!(
+      check("key-naming-convention", source, "camelCase", &[]),
+      [
+        (
+          1,
+          13,
+          "Property name `foo_bar` is not camelCase".to_string()
+        ),
+        (1, 25, "Property name `FooBar` is not camelCase".to_string()),
+        (
+          1,
+          36,
+          "Property name `foo-bar` is not camelCase".to_string()
+        ),
+        (1, 50, "Property name `` is not camelCase".to_string())
+      ]
+    );
+  }
+
+  #[test]
+  fn reports_names_that_are_not_snake_case() {
+    let source = "{foo_bar: 1, fooBar: 2, foo__bar: 3, _foo: 4, foo_: 5, 1_a: 6}";
+    assert_eq!(
+      positions("key-naming-convention", source, "snake_case"),
+      [(1, 14), (1, 25), (1, 38), (1, 47), (1, 56)]
+    );
+  }
+
+  #[test]
+  fn accepts_names_that_follow_the_convention() {
+    let source = "{foo: 1, fooBar2: {a1B: 2}}";
+    assert_eq!(positions("key-naming-convention", source, "camelCase"), []);
+    let source = "{foo: 1, foo_bar2: {a_1_b: 2}}";
+    assert_eq!(positions("key-naming-convention", source, "snake_case"), []);
+  }
+
+  #[test]
+  fn reports_the_first_unsorted_property_of_each_object() {
+    let source = "{b: 1, a: {d: 1, c: 2, b: 3}, c: 3}";
+    assert_eq!(
+      check("keys-sorted", source, "bytes", &[]),
+      [
+        (
+          1,
+          8,
+          "Property `a` should come before `b` (the properties are not sorted)".to_string()
+        ),
+        (
+          1,
+          18,
+          "Property `c` should come before `d` (the properties are not sorted)".to_string()
+        )
+      ]
+    );
+  }
+
+  #[test]
+  fn sorts_names_in_the_order_of_the_option() {
+    let source = "{B: 1, a: 2}";
+    assert_eq!(positions("keys-sorted", source, "bytes"), []);
+    assert_eq!(
+      positions("keys-sorted", source, "case-insensitive"),
+      [(1, 8)]
+    );
+    let source = "{a: 1, B: 2}";
+    assert_eq!(positions("keys-sorted", source, "bytes"), [(1, 8)]);
+    assert_eq!(positions("keys-sorted", source, "case-insensitive"), []);
+    let source = "{a9: 1, a10: 2}";
+    assert_eq!(positions("keys-sorted", source, "bytes"), [(1, 9)]);
+    assert_eq!(positions("keys-sorted", source, "natural"), []);
+    let source = "{a10: 1, a9: 2}";
+    assert_eq!(positions("keys-sorted", source, "bytes"), []);
+    assert_eq!(positions("keys-sorted", source, "natural"), [(1, 10)]);
+  }
+
+  #[test]
+  fn orders_leading_zeros_after_the_same_number() {
+    assert_eq!(
+      positions("keys-sorted", "{v1: 1, v01: 2, v2: 3}", "natural"),
+      []
+    );
+    assert_eq!(
+      positions("keys-sorted", "{v01: 1, v1: 2}", "natural"),
+      [(1, 10)]
+    );
+  }
+
+  #[test]
+  fn accepts_sorted_properties() {
+    let source = "{a: {x: 1, y: 2}, b: [{d: 1, e: 2}], c: {}}";
+    for option in ["bytes", "case-insensitive", "natural"] {
+      assert_eq!(positions("keys-sorted", source, option), []);
+    }
+  }
+
+  #[test]
+  fn reports_top_level_values_of_another_type() {
+    let message = |expected, found| {
+      format!(
+        "The top-level value must be {}, but found {}",
+        expected, found
+      )
+    };
+    assert_eq!(
+      check("require-root", "\n  [1, {}]", "object", &[]),
+      [(2, 3, message("an object", "an array"))]
+    );
+    assert_eq!(
+      check("require-root", "// list\n{a: []}", "array", &[]),
+      [(2, 1, message("an array", "an object"))]
+    );
+    for (source, found) in [
+      ("'text'", "a string"),
+      ("-1.5", "a number"),
+      ("true", "a boolean"),
+      ("null", "null"),
+    ] {
+      assert_eq!(
+        check("require-root", source, "object", &[]),
+        [(1, 1, message("an object", found))]
+      );
+    }
+  }
+
+  #[test]
+  fn accepts_top_level_values_of_the_required_type() {
+    assert_eq!(positions("require-root", "{a: [1]}", "object"), []);
+    assert_eq!(positions("require-root", "[{a: 1}]", "array"), []);
+    for source in ["{}", "[]", "'text'", "1", "false", "null"] {
+      assert_eq!(positions("require-root", source, "any"), []);
+    }
+  }
+
+  #[test]
+  fn parses_root_kinds() {
+    for name in RootKind::VARIANTS {
+      assert_eq!(name.parse::<RootKind>().unwrap().name(), *name);
+    }
+    assert_eq!(
+      "string".parse::<RootKind>().unwrap_err().to_string(),
+      "Expected one of: object, array, any"
+    );
+  }
+
+  #[test]
+  fn reports_long_property_names() {
+    let source = "{abcd: {efgh: 1, efg: 2}, 'é12': 3}";
+    assert_eq!(
+      check("max-key-length", source, "3", &[]),
+      [
+        (
+          1,
+          2,
+          "Property name `abcd` is 4 bytes long, longer than the maximum of 3".to_string()
+        ),
+        (
+          1,
+          27,
+          "Property name `é12` is 4 bytes long, longer than the maximum of 3".to_string()
+        ),
+        (
+          1,
+          9,
+          "Property name `efgh` is 4 bytes long, longer than the maximum of 3".to_string()
+        )
+      ]
+    );
+  }
+
+  #[test]
+  fn accepts_short_or_allowed_property_names() {
+    let source = "{abcd: {efgh: 1, efg: 2}, ls: [{ijkl: 3}]}";
+    assert_eq!(positions("max-key-length", source, "4"), []);
+    assert_eq!(
+      check(
+        "max-key-length",
+        source,
+        "3",
+        &["/abcd", "**/efgh", "/ls/*/ijkl"]
+      ),
+      []
+    );
+    assert_eq!(
+      check("max-key-length", source, "3", &["/abcd/*"])
+        .into_iter()
+        .map(|(line, col, _)| (line, col))
+        .collect::<Vec<_>>(),
+      [(1, 2), (1, 33)]
+    );
+  }
+
+  #[test]
+  fn reports_long_strings() {
+    let source = "{a: 'abcd', b: ['xyz', \"é12\"], c: 'a\\u0062c'}";
+    assert_eq!(
+      check("max-string-length", source, "3", &[]),
+      [
+        (
+          1,
+          5,
+          "String is 4 bytes long, longer than the maximum of 3".to_string()
+        ),
+        (
+          1,
+          24,
+          "String is 4 bytes long, longer than the maximum of 3".to_string()
+        )
+      ]
+    );
+  }
+
+  #[test]
+  fn accepts_short_or_allowed_strings() {
+    let source = "{a: 'abcd', b: ['xyz', 'wxyz'], long: 1234567}";
+    assert_eq!(positions("max-string-length", source, "4"), []);
+    assert_eq!(check("max-string-length", source, "3", &["/a", "b/1"]), []);
+    assert_eq!(
+      check("max-string-length", source, "3", &["/b/*"])
+        .into_iter()
+        .map(|(line, col, _)| (line, col))
+        .collect::<Vec<_>>(),
+      [(1, 5)]
+    );
+  }
+
+  #[test]
+  fn reports_integers_past_the_safe_limit() {
+    let source = "[9007199254740993, -9007199254740993, +0x20000000000001, \
+                  123456789012345678901234567890123456789012345678901234567890]";
+    assert_eq!(
+      positions("no-unsafe-integer", source, ""),
+      [(1, 2), (1, 20), (1, 39), (1, 58)]
+    );
+    assert_eq!(
+      check("no-unsafe-integer", "{id: 18446744073709551616}", "", &[]),
+      [(
+        1,
+        6,
+        "Integer 18446744073709551616 is larger in magnitude than 2^53, so parsers that read \
+         numbers as doubles lose its precision (write it as a string)"
+          .to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn accepts_safe_integers_and_other_numbers() {
+    let source = "[9007199254740992, -9007199254740992, 0x20000000000000, 1e300, \
+                  12345678901234567890.5, Infinity, NaN, '12345678901234567890', true]";
+    assert_eq!(positions("no-unsafe-integer", source, ""), []);
+    let source = "{ids: [9007199254740993], counts: {total: 9007199254740993}}";
+    assert_eq!(
+      check("no-unsafe-integer", source, "", &["/ids/*", "total"]),
+      []
+    );
+  }
+}
@@ -0,0 +1,187 @@
+//! Constrains the JSON5-only number literals a document may use: `Infinity` and `NaN` (the
+//! `--forbid-non-finite` option), and hexadecimal numbers (the `--hex-numbers` option), and
+//! normalizes decimal numbers (the `--normalize-floats` option).
+//!
+//! Numbers are written exactly as they are in the input, digit for digit, unless one of these
+//! options asks for a change: the formatter never parses a number into a double and prints it
+//! back, so `0.30000000000000004`, `1.50`, and `12345678901234567890` keep their text. With
+//! `--normalize-floats shortest`, each decimal number with a fraction or an exponent is written as
+//! the shortest text that parses to the same double (keeping a fraction or an exponent, so it
+//! still reads as a float): `1.50` becomes `1.5`, `1.0E+10` becomes `1e10`, and
+//! `0.1000000000000000055511151231257827` becomes `0.1`. Integers, hexadecimal numbers, and numbers
+//! too large for a double (such as `1e400`) are left as they are. The fixtures in `tests/numbers`
+//! check that numbers are written as they are, with `cargo test` or with:
+//!
+//!     formatjson5 --check-snapshots tests/numbers/*.json5
+
+use crate::{codes, diagnostics::Diagnostic, edit::Edits, format::FormatError, syntax::Node};
+use std::str::FromStr;
+
+/// How to write hexadecimal number literals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HexNumbers {
+  /// Keep hexadecimal numbers as they are written.
+  Preserve,
+  /// Convert hexadecimal numbers to decimal.
+  Decimal,
+  /// Write hexadecimal digits in lowercase, as in `0xff`.
+  Lower,
+  /// Write hexadecimal digits in uppercase, as in `0xFF`.
+  Upper,
+}
+
+impl HexNumbers {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["preserve", "decimal", "lower", "upper"];
+}
+
+impl FromStr for HexNumbers {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "preserve" => Ok(HexNumbers::Preserve),
+      "decimal" => Ok(HexNumbers::Decimal),
+      "lower" => Ok(HexNumbers::Lower),
+      "upper" => Ok(HexNumbers::Upper),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", HexNumbers::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// How to write decimal numbers with a fraction or an exponent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalizeFloats {
+  /// Keep numbers as they are written.
+  Preserve,
+  /// Write each number as the shortest text that parses to the same double.
+  Shortest,
+}
+
+impl NormalizeFloats {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["preserve", "shortest"];
+}
+
+impl FromStr for NormalizeFloats {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "preserve" => Ok(NormalizeFloats::Preserve),
+      "shortest" => Ok(NormalizeFloats::Shortest),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", NormalizeFloats::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// Calls `visit` with the source text of each literal in `node` and its descendants, and the
+/// literal's node.
+fn literals<'a>(node: &'a Node, source: &'a str, visit: &mut impl FnMut(&'a str, &'a Node)) {
+  match node {
+    Node::Object { members, .. } => {
+      for member in members {
+        literals(&member.value, source, visit);
+      }
+    }
+    Node::Array { items, .. } => {
+      for item in items {
+        literals(item, source, visit);
+      }
+    }
+    Node::Literal { span } => visit(&source[span.start..span.end], node),
+    Node::String { .. } => {}
+  }
+}
+
+/// Returns an error for each `Infinity` or `NaN` (with any sign) in `root`, parsed from `source`.
+pub fn non_finite(root: &Node, source: &str) -> Vec<Diagnostic> {
+  let mut errors = Vec::new();
+  literals(root, source, &mut |text, node| {
+    if matches!(text.trim_start_matches(['+', '-']), "Infinity" | "NaN") {
+      let span = node.span();
+      errors.push(Diagnostic::error(
+        &codes::NON_FINITE,
+        span.line,
+        span.col,
+        format!("`{}` is not allowed (--forbid-non-finite)", text),
+      ));
+    }
+  });
+  errors
+}
+
+/// Adds edits that rewrite each hexadecimal number in `root`, parsed from `source`, as selected by
+/// `mode`. Numbers too large to convert to decimal exactly are left as they are.
+pub fn hex_numbers(root: &Node, source: &str, mode: HexNumbers, edits: &mut Edits) {
+  if mode == HexNumbers::Preserve {
+    return;
+  }
+  literals(root, source, &mut |text, node| {
+    let unsigned = text.trim_start_matches(['+', '-']);
+    let sign = &text[..text.len() - unsigned.len()];
+    let Some(digits) = unsigned
+      .strip_prefix("0x")
+      .or_else(|| unsigned.strip_prefix("0X"))
+    else {
+      return;
+    };
+    let replacement = match mode {
+      HexNumbers::Decimal => match u128::from_str_radix(digits, 16) {
+        Ok(value) => format!("{}{}", sign, value),
+        Err(_) => return,
+      },
+      HexNumbers::Lower => format!("{}0x{}", sign, digits.to_ascii_lowercase()),
+      HexNumbers::Upper => format!("{}0x{}", sign, digits.to_ascii_uppercase()),
+      HexNumbers::Preserve => return,
+    };
+    if replacement != text {
+      let span = node.span();
+      edits.replace(span.start..span.end, replacement);
+    }
+  });
+}
+
+/// Adds edits that rewrite each decimal number with a fraction or an exponent in `root`, parsed
+/// from `source`, in its shortest form, if `mode` is `Shortest`.
+pub fn normalize_floats(root: &Node, source: &str, mode: NormalizeFloats, edits: &mut Edits) {
+  if mode == NormalizeFloats::Preserve {
+    return;
+  }
+  literals(root, source, &mut |text, node| {
+    if let Some(replacement) = shortest_float(text).filter(|replacement| replacement != text) {
+      let span = node.span();
+      edits.replace(span.start..span.end, replacement);
+    }
+  });
+}
+
+/// Returns the shortest text of the decimal number `text` that parses to the same double, with a
+/// fraction or an exponent, or `None` if `text` is not a finite decimal number with a fraction or
+/// an exponent.
+fn shortest_float(text: &str) -> Option<String> {
+  let unsigned = text.trim_start_matches(['+', '-']);
+  if !unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+    || unsigned.starts_with("0x")
+    || unsigned.starts_with("0X")
+    || !unsigned.contains(['.', 'e', 'E'])
+  {
+    return None;
+  }
+  let value = text.trim_start_matches('+').parse::<f64>().ok()?;
+  if !value.is_finite() {
+    return None;
+  }
+  // `{:?}` writes the shortest digits that parse to the same double, with a fraction (such as
+  // `10000000000.0`), or with an exponent for very large and small values.
+  let decimal = format!("{:?}", value);
+  let exponential = format!("{:e}", value);
+  match exponential.len() < decimal.len() {
+    true => Some(exponential),
+    false => Some(decimal),
+  }
+}
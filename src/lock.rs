@@ -0,0 +1,61 @@
+//! Advisory locks on the files being replaced, so two formatter runs that replace the same files at
+//! the same time (such as a format-on-save in an editor and a pre-commit hook) take turns, rather
+//! than interleaving their reads and writes.
+//!
+//! Each file is locked with `flock()` while it is replaced, one file at a time (so a run over many
+//! files holds a single lock, and a single open file for it). Under its lock, a run checks that
+//! the file is unchanged since it read it, and if another run replaced it in the meantime, reads
+//! and formats it again before writing it. The locks only exclude other processes that also lock
+//! the files; they do not stop other programs from reading or writing them. On platforms without
+//! `flock()`, files are not locked.
+
+use crate::paths;
+use std::{fs, io, path::Path};
+
+/// The lock held on a file, which is released when this is dropped.
+#[derive(Debug)]
+pub struct Lock {
+  /// The locked file, which holds the lock while it is open.
+  _file: fs::File,
+}
+
+/// Locks the local `file`, waiting for another run to release it if necessary. A file that cannot
+/// be opened is not locked (and `None` is returned), so the error is reported when it is read or
+/// written.
+pub fn lock(file: &Path) -> Result<Option<Lock>, anyhow::Error> {
+  let path = paths::extended(file);
+  let Ok(opened) = fs::File::open(&path) else {
+    return Ok(None);
+  };
+  lock_exclusive(&opened, file)
+    .map_err(|err| anyhow::anyhow!("Cannot lock {}: {}", file.display(), err))?;
+  Ok(Some(Lock { _file: opened }))
+}
+
+/// Takes an exclusive lock on `file`, opened from `path`, waiting (with a message) if another
+/// process holds a lock on it.
+#[cfg(unix)]
+fn lock_exclusive(file: &fs::File, path: &Path) -> io::Result<()> {
+  use std::os::unix::io::AsRawFd;
+
+  let flock = |operation| match unsafe { libc::flock(file.as_raw_fd(), operation) } {
+    0 => Ok(()),
+    _ => Err(io::Error::last_os_error()),
+  };
+  match flock(libc::LOCK_EX | libc::LOCK_NB) {
+    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+      eprintln!(
+        "Waiting for another run to finish formatting {} (use --no-lock to skip locking)",
+        path.display()
+      );
+      flock(libc::LOCK_EX)
+    }
+    result => result,
+  }
+}
+
+/// Does nothing, since files are not locked on this platform.
+#[cfg(not(unix))]
+fn lock_exclusive(_file: &fs::File, _path: &Path) -> io::Result<()> {
+  Ok(())
+}
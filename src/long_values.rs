@@ -0,0 +1,198 @@
+//! Handles single values too long for the maximum line width (the `max_width` and `long_value`
+//! options), such as long strings and URLs, which cannot be broken.
+//!
+//! json5format writes each property and array item on a line of its own, so a line is only wider
+//! than `max_width` when a value (with its indentation and property name) is. With `allow` (the
+//! default), such lines are left as they are. With `wrap-member`, a property whose value makes its
+//! line too long is split after its name, so the value starts on the next line, indented one level
+//! further:
+//!
+//!     description:
+//!         "A long description that does not fit on the line of its property name",
+//!
+//! With `warn`, each such value is reported (as [long-value](../codes/constant.LONG_VALUE.html)),
+//! at its position in the input, without changing the output.
+
+use crate::{
+  codes,
+  diagnostics::Diagnostic,
+  edit::Edits,
+  format::FormatError,
+  syntax::{self, Node, Span},
+};
+use std::{collections::HashMap, str::FromStr};
+
+/// The maximum line width, if `max_width` is not set.
+pub const DEFAULT_MAX_WIDTH: usize = 100;
+
+/// What to do with values that make their lines wider than the maximum width.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LongValues {
+  /// Leave the lines as they are.
+  #[default]
+  Allow,
+  /// Start the value of each such property on the line after its name.
+  WrapMember,
+  /// Report each such value.
+  Warn,
+}
+
+impl LongValues {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["allow", "wrap-member", "warn"];
+
+  /// Returns the spelling of the policy, as in the configuration file.
+  pub fn name(self) -> &'static str {
+    match self {
+      LongValues::Allow => "allow",
+      LongValues::WrapMember => "wrap-member",
+      LongValues::Warn => "warn",
+    }
+  }
+}
+
+impl FromStr for LongValues {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "allow" => Ok(LongValues::Allow),
+      "wrap-member" => Ok(LongValues::WrapMember),
+      "warn" => Ok(LongValues::Warn),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", LongValues::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// A string or other primitive value in a document.
+struct Scalar<'a> {
+  /// The path of the value, with `*` for each array index.
+  path: String,
+  /// The position of the value's property name, if it is the value of a property.
+  key: Option<Span>,
+  /// The value.
+  node: &'a Node,
+}
+
+impl Scalar<'_> {
+  /// Returns the text identifying the value at its path, which is the same in the input and the
+  /// formatted document `source`.
+  fn id(&self, source: &str) -> String {
+    match self.node {
+      Node::String { value, .. } => format!("{}={}", self.path, syntax::quote(value, '"')),
+      node => {
+        let span = node.span();
+        format!("{}={}", self.path, &source[span.start..span.end])
+      }
+    }
+  }
+}
+
+/// Returns `formatted` (the formatted text of a document, without any preamble or trailing
+/// content) with each property whose value makes its line wider than `max_width` split after the
+/// property name, indenting the value by `indent_by` spaces more than the name.
+pub fn wrap(formatted: &str, max_width: usize, indent_by: usize) -> String {
+  let Ok(Some(root)) = syntax::parse(formatted) else {
+    return formatted.to_string();
+  };
+  let mut edits = Edits::default();
+  for scalar in scalars(&root) {
+    let (Some(key), span) = (scalar.key, scalar.node.span()) else {
+      continue;
+    };
+    let separator = &formatted[key.end..span.start];
+    if key.line != span.line
+      || separator.trim_start_matches(':').trim() != ""
+      || width(formatted, span) <= max_width
+    {
+      continue;
+    }
+    let line = &formatted[line_start(formatted, key.start)..];
+    let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    edits.replace(
+      key.end..span.start,
+      format!(":\n{}{}", indent, " ".repeat(indent_by)),
+    );
+  }
+  edits.apply(formatted)
+}
+
+/// Returns a warning for each value in `source` (the input) whose line in `formatted` (its
+/// formatted text, without any preamble or trailing content) is wider than `max_width`.
+pub fn check(source: &str, formatted: &str, max_width: usize) -> Vec<Diagnostic> {
+  let (Ok(Some(input)), Ok(Some(output))) = (syntax::parse(source), syntax::parse(formatted))
+  else {
+    return Vec::new();
+  };
+  let widths = scalars(&output)
+    .into_iter()
+    .filter_map(|scalar| {
+      let width = width(formatted, scalar.node.span());
+      (width > max_width).then(|| (scalar.id(formatted), width))
+    })
+    .collect::<HashMap<_, _>>();
+  if widths.is_empty() {
+    return Vec::new();
+  }
+  scalars(&input)
+    .into_iter()
+    .filter_map(|scalar| {
+      let width = widths.get(&scalar.id(source))?;
+      let span = scalar.node.span();
+      Some(Diagnostic::warning(
+        &codes::LONG_VALUE,
+        span.line,
+        span.col,
+        format!(
+          "The {} makes its formatted line {} characters wide, more than the maximum width of {}",
+          scalar.node.type_name(source),
+          width,
+          max_width
+        ),
+      ))
+    })
+    .collect()
+}
+
+/// Returns the strings and other primitive values in the document whose top-level value is `root`.
+fn scalars(root: &Node) -> Vec<Scalar<'_>> {
+  /// Adds the primitive values in `node`, at `path`, to `scalars`.
+  fn visit<'a>(node: &'a Node, path: String, key: Option<Span>, scalars: &mut Vec<Scalar<'a>>) {
+    match node {
+      Node::Object { members, .. } => {
+        for member in members {
+          let path = format!("{}/{}", path, member.name);
+          visit(&member.value, path, Some(member.key), scalars);
+        }
+      }
+      Node::Array { items, .. } => {
+        for item in items {
+          visit(item, format!("{}/*", path), None, scalars);
+        }
+      }
+      Node::String { .. } | Node::Literal { .. } => scalars.push(Scalar { path, key, node }),
+    }
+  }
+  let mut scalars = Vec::new();
+  visit(root, String::new(), None, &mut scalars);
+  scalars
+}
+
+/// Returns the width, in characters, of the line of `formatted` holding the value at `span`, up to
+/// the end of the value and any comma after it.
+fn width(formatted: &str, span: Span) -> usize {
+  let end = match formatted[span.end..].starts_with(',') {
+    true => span.end + 1,
+    false => span.end,
+  };
+  formatted[line_start(formatted, span.start)..end]
+    .chars()
+    .count()
+}
+
+/// Returns the offset of the start of the line holding the offset `at` in `formatted`.
+fn line_start(formatted: &str, at: usize) -> usize {
+  formatted[..at].rfind('\n').map_or(0, |newline| newline + 1)
+}
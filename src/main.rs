@@ -6,144 +6,3916 @@
 //! # Usage
 //!
 //!     formatjson5 [FLAGS] [OPTIONS] [files]...
+//!     formatjson5 format [FLAGS] [OPTIONS] [files]...
+//!     formatjson5 check [--baseline <file>] [--update-baseline] [FORMAT OPTIONS] [files]...
+//!     formatjson5 convert --to <format> [--verify-roundtrip] [--out-dir <dir>] [files]...
+//!     formatjson5 diff [FORMAT OPTIONS] [files]...
+//!     formatjson5 edit [--no-lock] [FORMAT OPTIONS] [files]...
+//!     formatjson5 explain [code]
+//!     formatjson5 fix [--fix-level <level>] [--no-lock] [FORMAT OPTIONS] [files]...
+//!     formatjson5 lint [FORMAT OPTIONS] [files]...
+//!     formatjson5 merge-driver [FORMAT OPTIONS] <base> <ours> <theirs> [path]
+//!     formatjson5 migrate-config [--dry-run] [config]
+//!     formatjson5 self-update [--check]
+//!     formatjson5 serve [--no-ui] [--host <host>] [--port <port>] [FORMAT OPTIONS]
+//!     formatjson5 textconv <file>
+//!     formatjson5 to-json [--verify-roundtrip] [--out-dir <dir>] [files]...
+//!     formatjson5 to-yaml [--out-dir <dir>] [files]...
+//!     formatjson5 [arguments]... --persistent_worker
 //!
 //!     FLAGS:
+//!         --allow-trailing-garbage
+//!                                 Preserve any content after the end of the document verbatim,
+//!                                 instead of reporting it as an error
+//!         --assume-tty            Behave as if stdout is a terminal, even when it is redirected to
+//!                                 a file or pipe
+//!         --changed-only          Write the formatted documents to stdout only for the files that
+//!                                 formatting changes
+//!         --check                 Check that each file is formatted (and can be formatted without
+//!                                 errors), instead of writing the formatted documents, and fail
+//!                                 if any file has problems
+//!         --check-snapshots       Check that the formatted output of each file `<file>` is the
+//!                                 same as its snapshot file `<file>.formatted` (written by
+//!                                 --snapshot), and fail if any differs
+//!         --deny-warnings         Report warnings as errors, which make formatting each document
+//!                                 fail (like --deny for every code)
+//!         --deterministic         Fail before formatting if the output could depend on more than
+//!                                 the files and the configuration file: on environment variables
+//!                                 that set options, plugin commands, documents fetched from URLs,
+//!                                 or the order documents finish formatting in (for CI)
+//!         --fix                   Apply the automatic fixes of lint checks (renaming property
+//!                                 names to --key-case), instead of reporting the problems
+//!         --flush-per-document    Write each formatted document to stdout, and flush it, as soon
+//!                                 as it and the documents before it are formatted, instead of
+//!                                 after all of them are (so the next command in a pipeline can
+//!                                 start on them); with --output-order completion, as soon as it
+//!                                 is formatted
+//!         --forbid-non-finite     Report `Infinity` and `NaN` as errors
+//!         --force-overwrite       With --replace, overwrite files even if they changed on disk
+//!                                 after they were read (by default, such files are skipped with a
+//!                                 warning)
 //!     -h, --help                  Prints help information
-//!     -n, --no_trailing_commas    Suppress trailing commas (otherwise added by default)
-//!     -o, --one_element_lines     Objects or arrays with a single child should collapse to a
+//!         --front-matter          Format only the JSON5 front matter of Markdown files, between a
+//!                                 first line of `---` (or `---json5`) and the next line of `---`,
+//!                                 and leave the rest of each file unchanged (the default for `.md`
+//!                                 files)
+//!         --hermetic              Ignore the environment: read no configuration file other than
+//!                                 the one given with --config, ignore the environment variables
+//!                                 that set options, and treat stdout as not a terminal (unless
+//!                                 --assume-tty is given), so the output is the same in any build
+//!                                 sandbox
+//!         --ignore-required-version
+//!                                 Run even if the formatter's version does not satisfy the
+//!                                 `required_version` of the configuration, with a warning
+//!         --minimal-diff          Keep the original text of each changed part of a document where
+//!                                 only its line breaks and spacing differ from the formatted
+//!                                 document, and its lines are indented as formatted (to keep
+//!                                 `git blame` useful)
+//!         --no-config             Read no configuration file: neither the file named by
+//!                                 $JSON5FMT_CONFIG nor the nearest `.json5fmt.json5` (the
+//!                                 environment variables and the other options still apply)
+//!         --no-lock               Do not lock the files being replaced (by default, a run that
+//!                                 replaces a file waits for any other run replacing it to finish,
+//!                                 and formats it again if that run changed it)
+//!     -n, --no-trailing-commas    Suppress trailing commas (otherwise added by default)
+//!     -o, --one-element-lines     Objects or arrays with a single child should collapse to a
 //!                                 single line; no trailing comma
+//!         --paranoid              Parse the formatted output of each document and compare its
+//!                                 data with the input before writing it, and fail (leaving the
+//!                                 file unchanged) if any value, property, or comment differs
 //!     -r, --replace               Replace (overwrite) the input file with the formatted result
-//!     -s, --sort_arrays           Sort arrays of primitive values (string, number, boolean, or
+//!         --report-semantic-changes
+//!                                 Report each array whose items formatting reorders, and each
+//!                                 duplicate array item or property it removes, with their paths,
+//!                                 on stderr
+//!         --safe-replace          With --replace, replace each file only if its formatted
+//!                                 document, as written (after any post plugins), has the same
+//!                                 data as the input: the same properties, values, and number of
+//!                                 comments
+//!         --snapshot              Write the formatted output of each file `<file>` to the snapshot
+//!                                 file `<file>.formatted`, instead of to stdout
+//!     -s, --sort-arrays           Sort arrays of primitive values (string, number, boolean, or
 //!                                 null) lexicographically
+//!         --sort-keys             Sort the properties of each object by name
+//!         --sort-warn-heuristics  With --sort-arrays, keep arrays whose order looks significant
+//!                                 (mixed types, or items mentioning `first` or `fallback`) in
+//!                                 source order, and warn about them
+//!         --timings               Report how long each file takes to read, parse, format, and
+//!                                 write on stderr, followed by the slowest files
+//!         --unredacted-crash-report
+//!                                 Keep the strings of the document being formatted in the bug
+//!                                 report written if the formatter crashes, instead of replacing
+//!                                 them with a placeholder
+//!         --update-baseline       Record the current problems of the files in the --baseline file,
+//!                                 instead of failing on them (implies --check)
+//!         --verbose               Report details of how each document is formatted (such as the
+//!                                 detected input format) on stderr
 //!     -V, --version               Prints version information
+//!         --word-diff             Highlight the words that changed within each changed line of the
+//!                                 diffs printed
 //!
 //!     OPTIONS:
-//!     -i, --indent <indent>    Indent by the given number of spaces [default: 4]
+//!         --archive <archive>  Format the members of the given tar or zip archive, and rewrite the
+//!                              archive in place; the files given are then patterns for the member
+//!                              names to format, such as `configs/*.json5` (by default, every
+//!                              member with an extension in the file-type registry, such as
+//!                              `.json5`)
+//!         --array-wrap <style> How to wrap the items of arrays of primitive values across lines:
+//!                              vertical (one item per line), fill (as many items per line as
+//!                              fit in --max-width), or fill-numbers (fill arrays of numbers
+//!                              only) [default: vertical]
+//!         --baseline <file>    With --check, --update-baseline records the current problems of the
+//!                              files in this file, and later checks fail only on problems that
+//!                              are not recorded in it
+//!         --color <when>       Color the output for a terminal (such as the diffs of the `diff`
+//!                              subcommand): auto (only if stdout is a terminal), always, or
+//!                              never [default: auto]
+//!         --comment-attachment <attachment>
+//!                              Which item a comment on a line of its own moves with when sorting
+//!                              reorders items: next (the item after it), previous (the item
+//!                              before it), or blank-line-aware (the item it is not separated
+//!                              from by a blank line) [default: next]
+//!         --config <config>    Read options from the given configuration file, instead of the
+//!                              file named by $JSON5FMT_CONFIG or the nearest `.json5fmt.json5`
+//!                              in the current directory or its ancestors
+//!         --config-override <option=value>...
+//!                              Set an option of the configuration file, as `<option>=<value>`
+//!                              (such as `trailing_commas=false`), over the configuration file,
+//!                              the environment, and --options-json (may be repeated; the other
+//!                              options on the command line take precedence)
+//!         --banners <when>     When formatting multiple files to stdout, precede each document
+//!                              with a filename banner, rather than separating the documents with
+//!                              a blank line: auto (only if stdout is a terminal), always, or
+//!                              never (unless --multi-doc-separator is given) [default: auto]
+//!     -i, --indent <indent>    Indent by the given number of spaces, or keep the indentation
+//!                              (2 or 4 spaces, or tabs) of each file with `auto` (default: 4)
+//!     -j, --jobs <jobs>        Format files in parallel on the given number of threads (0 uses
+//!                              one thread per CPU), and split each very large document into
+//!                              chunks formatted in parallel [default: 1]
+//!         --deny <code>...     Report the warnings of the given lint rule or diagnostic code
+//!                              (such as `no-empty-object` or `J5F008`) as errors, which make
+//!                              formatting each document fail (may be repeated)
+//!         --dialect <dialect>  The JSON dialect of the input and output: json5, jsonc (JSON with
+//!                              comments and trailing commas), or json; input that uses an
+//!                              extension the dialect does not allow is an error [default: json5,
+//!                              or the dialect of the file's type]
+//!         --diff-context <lines>
+//!                              The number of unchanged lines shown around each change in the
+//!                              diffs printed [default: 3]
+//!         --diff-style <style> How to lay out the diffs printed: unified (the changed lines one
+//!                              after the other) or side-by-side (each old line beside the line
+//!                              that replaces it, with line numbers); `check` prints a diff of
+//!                              each file that is not formatted if this, --diff-context, or
+//!                              --word-diff is given [default: unified]
+//!         --embedded <rule>...
+//!                              Format the JSON5 blocks inside files of another kind, as selected
+//!                              by a rule of the form `<extension>:<open>...<close>` (for example,
+//!                              `html:<script type="application/json5">...</script>`; may be
+//!                              repeated)
+//!         --emit <what>        What to write for each document: formatted (the formatted
+//!                              document), ast (the syntax tree as JSON, with source spans and the
+//!                              comments attached to each value), or tokens (every token as JSON,
+//!                              with its kind, text, and source span) [default: formatted]
+//!         --empty-container-comments <style>
+//!                              How to write the comments in empty objects and arrays: expand (each
+//!                              on a line of its own) or inline (as in `{ /* todo */ }`, if they
+//!                              are all block comments) [default: expand]
+//!         --eol-comments <placement>
+//!                              Where to place the comments at the ends of lines: keep (after the
+//!                              value), move-above (on a line of their own above the value), or
+//!                              align (to the same column on consecutive lines) [default: keep]
+//!         --error-format <format>
+//!                              How to report errors, check failures, and diagnostics on stderr:
+//!                              human (messages with the source line of each problem), or github
+//!                              (GitHub Actions workflow commands, which annotate the lines of a
+//!                              pull request) [default: human]
+//!         --explain-match <path>
+//!                              Print whether the `include` and `exclude` options select the
+//!                              given path for formatting, and which pattern decided it, instead
+//!                              of formatting files
+//!         --format-embedded <path-glob>...
+//!                              Parse each string value whose path matches the given pattern as a
+//!                              JSON5 document, format it, and write it back into the string (for
+//!                              example, `/policy` or `**/rules/*`; may be repeated)
+//!         --hex-numbers <style>
+//!                              How to write hexadecimal numbers: preserve (as written), decimal,
+//!                              lower (as in `0xff`), or upper (as in `0xFF`) [default: preserve]
+//!         --input-format <format>
+//!                              How to treat the input: json5, json (require strict JSON, and
+//!                              write strict JSON), or auto (treat the input as strict JSON
+//!                              unless it uses a JSON5 extension, such as a comment or a trailing
+//!                              comma) [default: json5]
+//!         --key-case <case>    Check that property names follow a naming convention: camel
+//!                              (camelCase), snake (snake_case), kebab (kebab-case), or preserve
+//!                              (any name); with --fix, rename them instead [default: preserve]
+//!         --key-case-exclude <name>...
+//!                              A property name that is exempt from --key-case, such as a name
+//!                              defined by an external format (may be repeated)
+//!         --known-keys <file>  Warn about property names that are similar to, but not the same
+//!                              as, a name in the given file: a JSON5 array of names, a JSON
+//!                              Schema, or one name per line
+//!         --long-value <policy>
+//!                              What to do with a value (such as a long string or URL) that makes
+//!                              its line wider than --max-width: allow, wrap-member (start the
+//!                              value on the line after its property name), or warn [default:
+//!                              allow]
+//!         --max-width <columns>
+//!                              The width, in characters, that formatted lines should fit in, for
+//!                              --long-value and --array-wrap [default: 100]
+//!         --multi-doc-separator <separator>
+//!                              How to separate the documents written to stdout: banner (precede
+//!                              each with a filename banner), blank-line (precede each but the
+//!                              first with a blank line), nul (follow each with a NUL byte),
+//!                              record-jsonl (write each as a line of JSON, `{"file": ...,
+//!                              "content": ...}`), or none [default: banner or blank-line with
+//!                              multiple files, as set by --banners, otherwise none]
+//!         --normalize-floats <mode>
+//!                              How to write decimal numbers with a fraction or an exponent:
+//!                              preserve (exactly as written) or shortest (the shortest text that
+//!                              parses to the same double, as in `1.5` for `1.50`) [default:
+//!                              preserve]
+//!         --options-json <json5>
+//!                              Set options from a JSON5 object with the options of the
+//!                              configuration file, such as `{indent: 2, sort_keys: true}`, over
+//!                              those of the configuration file and the environment (the other
+//!                              options on the command line take precedence)
+//!         --output <output>    Write the formatted document to the given file, instead of stdout
+//!                              (with a single input file)
+//!         --output-order <order>
+//!                              The order to write formatted documents to stdout: input
+//!                              (buffered, in the order the files were given) or completion (as
+//!                              soon as each document is formatted) [default: input]
+//!         --preserve-preamble-lines <lines>
+//!                              Pass the given number of leading lines through unchanged, without
+//!                              parsing them as JSON5 (by default, only a leading `#!` line is
+//!                              passed through)
+//!         --quotes <style>     How to quote string values: preserve (as written), double, or
+//!                              single [default: preserve]
+//!         --redact <path-glob>...
+//!                              Replace string values whose paths match the given pattern with
+//!                              "<redacted>" (for example, `/credentials/*` or `**/password`; may
+//!                              be repeated)
+//!         --report[=<format>]  After formatting, report the problems found: summary (the number
+//!                              of diagnostics of each code that were reported, and that were
+//!                              silenced by suppression comments, on stderr), or, for CI servers,
+//!                              checkstyle or junit (an XML document listing the problems of each
+//!                              file), or sarif (a SARIF 2.1.0 log, for code scanning), written to
+//!                              the --report-file or stdout; the format must be given as
+//!                              `--report=<format>` [default: summary]
+//!         --report-file <path> Write the checkstyle, junit, or sarif --report to the given file,
+//!                              instead of stdout
+//!         --require-root <kind>
+//!                              Fail on documents whose top-level value is not of the given type:
+//!                              object, array, or any (the `require-root` lint rule at level
+//!                              `error`; any turns the rule off)
+//!         --stdin-framing <framing>
+//!                              Read multiple documents from stdin, each terminated by a NUL byte
+//!                              (nul) or preceded by its length in bytes on a line of its own
+//!                              (len), and write each formatted document to stdout with the same
+//!                              framing
+//!         --style <preset>     Set the options of a style preset, unless they are set otherwise:
+//!                              prettier (2-space indentation, no trailing commas, double quotes,
+//!                              and arrays of numbers filled to 80 characters, like Prettier) or
+//!                              fuchsia (the settings of Fuchsia's `cmc format`, with the
+//!                              canonical property order of component manifests)
+//!         --take <side>        Resolve each unresolved merge conflict by keeping our side (ours)
+//!                              or their side (theirs) of it, and format the result, instead of
+//!                              failing on the conflicts
+//!         --timings-slowest <n>
+//!                              The number of slowest files to list after --timings [default: 10]
+//!         --transform <transform>...
+//!                              Replace each value whose path matches a pattern with the result
+//!                              of an expression, in the form `<path-glob> = <expression>` (for
+//!                              example, `/version = bump_patch(.)` or `**/timeout = . * 2`; may
+//!                              be repeated)
+//!         --write-mode <mode>  How --replace writes each file: direct (truncate it and write it
+//!                              in place), atomic (write a temporary file next to it and rename
+//!                              it over the file), or copy (write a temporary file and copy it
+//!                              over the file, where renaming is not allowed) [default: direct]
 //!
 //!     ARGS:
-//!     <files>...    Files to format (use "-" for stdin)
+//!     <files>...    Files or directories to format (use "-" for stdin, or an http:// or
+//!                   https:// URL, if built with the `http` feature)
+//!
+//!     SUBCOMMANDS:
+//!     check             Check that each file is formatted (and can be formatted without errors),
+//!                       and fail if any file has problems (the same as `--check`)
+//!     compare-options   Print a unified diff of the changes between each file formatted with the
+//!                       `--base <options>` and with the `--candidate <options>`, each given as
+//!                       space-separated `name=value` pairs (such as `sort_keys=true`), to see
+//!                       what a change of style would change before adopting it
+//!     convert           Convert files to JSON or YAML (with `--to json` or `--to yaml`), dropping
+//!                       their comments, and print the results (or, with `--out-dir <dir>`, write
+//!                       each one under the directory, at its relative path with the extension of
+//!                       the format); with `--verify-roundtrip`, fail if converting a document to
+//!                       JSON loses any data, such as a comment
+//!     diff              Print a unified diff of the changes formatting would make to each file,
+//!                       without changing the files
+//!     edit              Apply the --transform and --redact edits to each file, and replace it with
+//!                       the edited and formatted result
+//!     explain           Print a detailed description of a diagnostic code (such as J5F003), with
+//!                       examples and how to configure or silence it, or list every code
+//!     fix               Repair common mistakes that prevent files from parsing, and replace each
+//!                       file with its repaired and formatted result
+//!     format            Format files (the same as giving no subcommand)
+//!     lint              Report the lint diagnostics of each file, without writing the formatted
+//!                       documents, and fail if any file has errors
+//!     merge-driver      Merge three versions of a file by their structure, for a git merge
+//!                       driver (`driver = formatjson5 merge-driver %O %A %B %P`): changes to
+//!                       different properties are merged, our comments and formatting are kept,
+//!                       and the merged file replaces `<ours>`, with conflict markers around
+//!                       each conflicting property
+//!     migrate-config    Rewrite deprecated options in a configuration file to their current names,
+//!                       and print the current spelling of each deprecated command line flag
+//!     rules             List the lint rules and the options of the configuration file, with
+//!                       their identifiers, defaults, and configuration keys; with `--json`, as
+//!                       JSON, for generating configuration UIs and documentation
+//!     self-update       Replace this executable with the newest release, after checking the
+//!                       download against the release's SHA-256 checksum (which detects a damaged
+//!                       download, not a tampered release, as the release is not signed), if built
+//!                       with the `self-update` feature; with `--check`, only report whether a
+//!                       newer release is available
+//!     serve             Serve an HTTP endpoint that formats documents: `POST /format` formats the
+//!                       request body, with options as query parameters (such as
+//!                       `?indent=2&sort_keys=true`), and `GET /` serves a single-page UI
+//!     stats             Print statistics about the structure of each file: its depth, the number
+//!                       of objects, arrays, properties, comments, and values of each type, its
+//!                       largest arrays, and its duplicate property names
+//!     textconv          Print a canonical formatting of a file, independent of the
+//!                       configuration, for a git diff driver's `textconv` command, so diffs show
+//!                       changes to data and comments rather than to formatting
+//!     to-json           The same as `convert --to json`
+//!     to-yaml           The same as `convert --to yaml`
+//!
+//! The global options (`--assume-tty`, `--color`, `--config`, `--config-override`,
+//! `--deterministic`, `--diff-context`, `--diff-style`, `--error-format`, `--hermetic`,
+//! `--ignore-required-version`, `--jobs`, `--no-config`, `--options-json`,
+//! `--unredacted-crash-report`, `--verbose`, and `--word-diff`) may be given before or after a
+//! subcommand. The other options belong to a subcommand, and are given after it; the subcommands
+//! that format documents (`check`, `compare-options`, `diff`, `edit`, `fix`, `lint`,
+//! `merge-driver`, and `serve`) accept the FORMAT OPTIONS, which are the options above that
+//! describe how each document is formatted and checked (such as `--indent`, `--sort-keys`,
+//! `--key-case`, and `--transform`), and `format` accepts all of the options above.
+//!
+//! The `convert`, `to-json`, and `to-yaml` subcommands belong to the `convert` feature, and `serve`
+//! to the `serve` feature, which are enabled by default; a build without them, such as the small
+//! binary of `cargo build --profile minimal --no-default-features`, fails on those subcommands.
+//!
+//! A directory given as a file is replaced by the files in it and in its subdirectories whose
+//! names end with an extension in the file-type registry (such as `.json5` and `.cml`; see the
+//! `file_types` option), in order of their paths, skipping the names that start with a `.`. The
+//! `include` and `exclude` options then select among them as among files given by name.
+//!
+//! Only an argument that is exactly the name of a subcommand runs it. Any other argument is a
+//! file, even one whose name looks like a subcommand's (such as `migrate.json5`), and a file named
+//! exactly like a subcommand is given by another path to it (such as `./check`).
+//!
+//! The underscore spellings of flags from earlier versions (such as `--no_trailing_commas`) are
+//! still accepted, but are not shown in the help.
+
+#![warn(missing_docs)]
+
+mod archive;
+mod baseline;
+mod catalog;
+mod config;
+mod convert;
+mod crash;
+mod determinism;
+mod diff;
+mod embedded;
+mod emit;
+mod error_format;
+mod file_types;
+mod fingerprint;
+mod framing;
+mod glob;
+mod islands;
+mod json;
+mod lint;
+mod lock;
+mod merge;
+mod minimal;
+mod order_sensitive;
+mod paranoid;
+mod path_options;
+mod paths;
+mod plugin;
+mod redact;
+mod remote;
+mod report;
+mod sarif;
+mod sections;
+mod selection;
+mod self_update;
+mod semantic_changes;
+mod serve;
+mod snapshot;
+mod sorting;
+mod stats;
+mod style;
+mod suppress;
+mod terminal;
+mod textconv;
+mod timings;
+mod transform;
+mod version;
+mod worker;
+mod write_mode;
+
+// The formatting core is the library, so the fuzz targets run the same lexer, repairs, edits,
+// and passes after json5format as the executable.
+use jsonpretty5er::{
+  array_wrap, codes, comment_attachment, conflicts, diagnostics, document, edit, empty,
+  eol_comments, features, fixes, format, indentation, lexer, literals, long_values, parallel,
+  quotes, repair, suggest, syntax, FormatError, Options,
+};
+
+use anyhow::{self, Result};
+use array_wrap::ArrayWrap;
+use baseline::{Baseline, Findings, Problems};
+use codes::Code;
+use comment_attachment::CommentAttachment;
+use config::{Config, Setting};
+use conflicts::Take;
+use convert::Target;
+use diagnostics::Diagnostic;
+use diagnostics::Severity;
+use diff::DiffStyle;
+use document::Document;
+use edit::Edits;
+use emit::Emit;
+use empty::EmptyComments;
+use eol_comments::EolComments;
+use error_format::ErrorFormat;
+use features::{Dialect, Feature, InputFormat, Usage};
+use fingerprint::Fingerprint;
+use fixes::{Fixes, Pass};
+use framing::{Framing, Separator};
+use glob::PathGlob;
+use indentation::Indent;
+use islands::IslandRule;
+use json5format::*;
+use lint::{key_case, rules::RootKind, KeyCase, KnownKeys, RuleConfig};
+use literals::{HexNumbers, NormalizeFloats};
+use long_values::LongValues;
+use parallel::OutputOrder;
+use path_options::PathRules;
+use plugin::Stage;
+use quotes::Quotes;
+use repair::FixLevel;
+use report::{Report, ReportFormat};
+use stats::Stats;
+use std::{
+  collections::BTreeMap,
+  ffi::OsString,
+  fs, io,
+  io::{Read, Write},
+  path::{Path, PathBuf},
+  time::Instant,
+};
+use structopt::{clap::AppSettings, StructOpt};
+use style::Style;
+use suppress::Suppressions;
+use terminal::{Terminal, When};
+use timings::{Phase, Timings};
+use transform::Transform;
+use version::Requirement;
+use write_mode::WriteMode;
+
+/// What to do with each document.
+#[derive(Clone)]
+struct Task<'a> {
+  /// The formatting options, before applying any options directive in the document.
+  config: &'a Config,
+
+  /// The repairs to apply before formatting.
+  fix: FixLevel,
+
+  /// If set, the side of each merge conflict to keep, instead of failing on the conflicts.
+  take: Option<Take>,
+
+  /// If set, warn about property names that are similar to, but not the same as, a known name.
+  known_keys: Option<&'a KnownKeys>,
+
+  /// Replace the string values at paths matching any of these patterns with a placeholder.
+  redact: &'a [PathGlob],
+
+  /// The paths of the string values that hold JSON5 documents to format.
+  format_embedded: &'a [PathGlob],
+
+  /// The naming convention that property names are checked against.
+  key_case: KeyCase,
+
+  /// Property names that are exempt from the naming convention.
+  key_case_exclude: &'a [String],
+
+  /// Whether to apply the automatic fixes of lint checks (renaming property names to the naming
+  /// convention), instead of reporting the problems.
+  fix_lint: bool,
+
+  /// Whether `Infinity` and `NaN` are errors.
+  forbid_non_finite: bool,
+
+  /// Whether every warning is reported as an error.
+  deny_warnings: bool,
+
+  /// The codes whose warnings are reported as errors.
+  deny: &'a [&'static Code],
+
+  /// Whether sorted arrays whose order looks significant are kept in source order, with a warning.
+  sort_warn_heuristics: bool,
+
+  /// How to write hexadecimal numbers.
+  hex_numbers: HexNumbers,
+
+  /// How to write decimal numbers with a fraction or an exponent.
+  normalize_floats: NormalizeFloats,
+
+  /// Rewrite the values at matching paths, before redacting them.
+  transforms: &'a [Transform],
+
+  /// What to write for each document.
+  emit: Emit,
+
+  /// Whether to keep the original text of changed lines where only their line breaks differ from
+  /// the formatted document.
+  minimal_diff: bool,
+
+  /// Whether to parse the formatted document and fail if its data differs from the input.
+  paranoid: bool,
+
+  /// Whether to report the arrays whose items formatting reorders, and the duplicates it removes.
+  report_semantic_changes: bool,
+
+  /// Whether to check, like `paranoid`, each document being replaced as it is written, after any
+  /// post plugins.
+  safe_replace: bool,
+
+  /// Whether to treat the input as JSON5, strict JSON, or either, depending on its content.
+  input_format: InputFormat,
+
+  /// Whether to report details of how each document is formatted on stderr.
+  verbose: bool,
+
+  /// How errors and diagnostics are reported on stderr.
+  error_format: ErrorFormat,
+
+  /// The number of threads a very large document may be formatted on (see the
+  /// [chunked](chunked/index.html) module).
+  threads: usize,
+
+  /// If set, the time spent on each file is recorded here.
+  timings: Option<&'a Timings>,
+
+  /// If set, the diagnostics reported and suppressed are counted here.
+  report: Option<&'a Report>,
+
+  /// If set, the error diagnostics reported for each file are recorded here.
+  findings: Option<&'a Findings>,
+}
+
+impl<'a> Task<'a> {
+  /// Returns the task of formatting documents with the configuration of `context`, as directed by
+  /// the command line `options`, checking property names against `known_keys`. The task makes no
+  /// repairs, writes the formatted documents, and records nothing.
+  fn new(context: &'a Context, options: &'a TaskOpt, known_keys: Option<&'a KnownKeys>) -> Self {
+    Task {
+      config: &context.config,
+      fix: FixLevel::None,
+      take: options.take,
+      known_keys,
+      key_case: options.key_case,
+      key_case_exclude: &options.key_case_exclude,
+      fix_lint: options.fix,
+      forbid_non_finite: options.forbid_non_finite,
+      deny_warnings: options.deny_warnings,
+      deny: &options.deny,
+      sort_warn_heuristics: options.sort_warn_heuristics,
+      hex_numbers: options.hex_numbers,
+      normalize_floats: options.normalize_floats,
+      redact: &options.redact,
+      format_embedded: &options.format_embedded,
+      transforms: &options.transform,
+      emit: Emit::Formatted,
+      minimal_diff: options.minimal_diff,
+      paranoid: options.paranoid,
+      report_semantic_changes: options.report_semantic_changes,
+      safe_replace: false,
+      input_format: options.input_format,
+      verbose: context.options.verbose,
+      error_format: context.options.error_format,
+      threads: context.threads(),
+      timings: None,
+      report: None,
+      findings: None,
+    }
+  }
+}
+
+/// The state shared by every subcommand that formats or converts documents: the global options,
+/// and the configuration and terminal they select.
+struct Context {
+  /// The options given for every subcommand.
+  options: GlobalOpt,
+
+  /// The formatting options, from the configuration file, the environment, and the command line.
+  config: Config,
+
+  /// The formatting options, without the options of their style preset, for layering more options
+  /// on.
+  unstyled: Config,
+
+  /// What is known about the output streams.
+  terminal: Terminal,
+}
+
+impl Context {
+  /// Loads the configuration selected by the global `options` (the configuration file, then the
+  /// environment variables, then `--options-json`, then `--config-override`, then the command
+  /// line `overrides`), and checks that this version of the formatter satisfies its
+  /// `required_version`. With `--hermetic`, only the `--config` file is read, and the environment
+  /// variables are ignored; with `--no-config`, no configuration file is read.
+  fn new(options: GlobalOpt, overrides: Config) -> Result<Self> {
+    let mut config = Config::default();
+    let config_path = match &options.config {
+      Some(path) => Some(path.clone()),
+      None if options.hermetic || options.no_config => None,
+      None => match std::env::var_os(config::CONFIG_ENV_VAR) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => config::find(&std::env::current_dir()?),
+      },
+    };
+    if let Some(path) = config_path {
+      let (file_config, warnings) = config::load(&path)?;
+      for warning in warnings {
+        eprintln!("Configuration warning: {}", warning);
+      }
+      config.merge(file_config);
+    }
+    if !options.hermetic {
+      let (env_config, warnings) = config::from_env(std::env::vars())?;
+      for warning in warnings {
+        eprintln!("Environment warning: {}", warning);
+      }
+      config.merge(env_config);
+    }
+    if let Some(text) = &options.options_json {
+      let (json_config, warnings) = config::from_json(text, "--options-json")?;
+      for warning in warnings {
+        eprintln!("Configuration warning: {}", warning);
+      }
+      config.merge(json_config);
+    }
+    let (override_config, warnings) =
+      config::from_overrides(&options.config_override, "--config-override")?;
+    for warning in warnings {
+      eprintln!("Option warning: --config-override: {}", warning);
+    }
+    config.merge(override_config);
+    config.merge(overrides);
+    let unstyled = config.clone();
+    let config = style::apply(config);
+    if let Some(requirement) = config.string("required_version") {
+      let requirement = requirement
+        .parse::<Requirement>()
+        .map_err(|message| anyhow::anyhow!("Configuration error: required_version: {}", message))?;
+      if let Err(message) = version::check(&requirement) {
+        match options.ignore_required_version {
+          true => eprintln!("Version warning: {}", message),
+          false => {
+            return Err(anyhow::anyhow!(
+              "Version error: {} (use --ignore-required-version to run anyway)",
+              message
+            ))
+          }
+        }
+      }
+    }
+    let terminal = match options.hermetic {
+      true => Terminal {
+        stdout_is_tty: options.assume_tty,
+      },
+      false => Terminal::detect(options.assume_tty),
+    };
+    Ok(Self {
+      options,
+      config,
+      unstyled,
+      terminal,
+    })
+  }
+
+  /// Returns the formatting options with those of `text`, the value of the command line flag
+  /// `flag`, which holds space-separated `name=value` pairs, taking precedence.
+  fn with_options(&self, text: &str, flag: &str) -> Result<Config> {
+    let (options, warnings) = config::from_pairs(text, flag)?;
+    for warning in warnings {
+      eprintln!("Option warning: {}: {}", flag, warning);
+    }
+    let mut config = self.unstyled.clone();
+    config.merge(options);
+    Ok(style::apply(config))
+  }
+
+  /// Returns the `files`, with each directory replaced by the files in it (see
+  /// [expand](#method.expand)), that the `include` and `exclude` options select (see the
+  /// [selection](selection/index.html) module), reporting the others with `--verbose`.
+  fn select(&self, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let files = self.expand(files)?;
+    let mut selected = Vec::with_capacity(files.len());
+    for file in &files {
+      let filename = file.to_string_lossy();
+      let selection = self.selection(&filename)?;
+      if selection.selected {
+        selected.push(file.clone());
+      } else if self.options.verbose {
+        eprintln!("Skipped: {}: {}", filename, selection.reason);
+      }
+    }
+    Ok(selected)
+  }
+
+  /// Returns the `files`, with each directory replaced by the files in it and in its
+  /// subdirectories whose names end with an extension in the file-type registry (see the
+  /// [file_types](file_types/index.html) module), in order of their paths. Files and directories
+  /// whose names start with a `.` (such as `.git`), and symbolic links to directories, are not
+  /// searched.
+  fn expand(&self, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let extensions = file_types::registry(&self.config)
+      .into_iter()
+      .map(|file_type| file_type.extension.to_lowercase())
+      .collect::<Vec<_>>();
+    let mut expanded = Vec::with_capacity(files.len());
+    for file in files {
+      let filename = file.to_string_lossy();
+      let is_directory = filename != "-"
+        && !remote::is_url(&filename)
+        && fs::metadata(paths::extended(file)).is_ok_and(|metadata| metadata.is_dir());
+      match is_directory {
+        true => walk(file, &extensions, &mut expanded)?,
+        false => expanded.push(file.clone()),
+      }
+    }
+    Ok(expanded)
+  }
+
+  /// Prints whether the `include` and `exclude` options select the file at `path`, and why.
+  fn explain_match(&self, path: &Path) -> Result<()> {
+    let filename = path.to_string_lossy();
+    let selection = self.selection(&filename)?;
+    let verdict = match selection.selected {
+      true => "selected",
+      false => "skipped",
+    };
+    println!("{}: {}: {}", filename, verdict, selection.reason);
+    Ok(())
+  }
+
+  /// Returns whether the `include` and `exclude` options select the file `filename`, and why.
+  fn selection(&self, filename: &str) -> Result<selection::Selection> {
+    selection::select(
+      filename,
+      self.config.strings("include"),
+      self.config.strings("exclude"),
+    )
+  }
+
+  /// Returns the number of threads to format files on.
+  fn threads(&self) -> usize {
+    parallel::thread_count(self.options.jobs)
+  }
+
+  /// Returns true if output should be colored for a terminal.
+  fn color(&self) -> bool {
+    self.options.color.enabled(self.terminal.stdout_is_tty)
+  }
+
+  /// Returns how diffs are printed.
+  fn rendering(&self) -> diff::Rendering {
+    diff::Rendering {
+      style: self.options.diff_style.unwrap_or_default(),
+      context: self.options.diff_context.unwrap_or(diff::DEFAULT_CONTEXT),
+      words: self.options.word_diff,
+      color: self.color(),
+    }
+  }
+
+  /// Returns how `check` prints the diff of each file that is not formatted, if a diff option is
+  /// given.
+  fn check_rendering(&self) -> Option<diff::Rendering> {
+    let options = &self.options;
+    (options.diff_style.is_some() || options.diff_context.is_some() || options.word_diff)
+      .then(|| self.rendering())
+  }
+
+  /// With `--deterministic`, returns an error if the output of the run, formatting `files` and
+  /// writing them to stdout in the given `order`, could depend on more than the files and the
+  /// configuration file.
+  fn audit(&self, files: &[PathBuf], order: OutputOrder) -> Result<()> {
+    if !self.options.deterministic {
+      return Ok(());
+    }
+    let mut audit = determinism::Audit::default();
+    // With --hermetic, the environment variables are not read.
+    let environment = !self.options.hermetic;
+    if environment
+      && self.options.config.is_none()
+      && !self.options.no_config
+      && std::env::var_os(config::CONFIG_ENV_VAR).is_some()
+    {
+      audit.flag(format!(
+        "the configuration file is named by ${}",
+        config::CONFIG_ENV_VAR
+      ));
+    }
+    let mut vars = std::env::vars()
+      .map(|(var, _)| var)
+      .filter(|var| {
+        environment && var.starts_with(config::ENV_PREFIX) && var != config::CONFIG_ENV_VAR
+      })
+      .collect::<Vec<_>>();
+    vars.sort();
+    for var in vars {
+      audit.flag(format!("the environment variable ${} sets an option", var));
+    }
+    for option in ["pre_format_plugins", "post_format_plugins"] {
+      for command_line in self.config.strings(option) {
+        audit.flag(format!(
+          "the plugin `{}` runs an external command",
+          command_line
+        ));
+      }
+    }
+    for file in files {
+      let filename = file.to_string_lossy();
+      if remote::is_url(&filename) {
+        audit.flag(format!("the document {} is fetched from a URL", filename));
+      }
+    }
+    if order == OutputOrder::Completion && files.len() > 1 && self.options.jobs != 1 {
+      audit.flag(
+        "--output-order completion writes documents in the order they finish formatting"
+          .to_string(),
+      );
+    }
+    audit.finish()
+  }
+}
+
+/// Reads the given `file` (or stdin, if `file` is "-", or a remote document, if `file` is a URL),
+/// and returns its content, and the fingerprint of the file as it was read (for a local file).
+fn read_input(
+  file: &Path,
+  task: &Task<'_>,
+) -> Result<(String, Option<Fingerprint>), anyhow::Error> {
+  let filename = file.to_string_lossy().to_string();
+  let result = (|| {
+    if filename == "-" {
+      let mut buffer = String::new();
+      Opt::from_stdin(&mut buffer)?;
+      Ok((buffer, None))
+    } else if remote::is_url(&filename) {
+      Ok((remote::fetch(&filename)?, None))
+    } else {
+      let (source, read) = Timings::time(task.timings, &filename, Phase::Read, || {
+        Fingerprint::read(&paths::extended(file))
+      })?;
+      Ok((source, Some(read)))
+    }
+  })();
+  if let (Some(report), Err(err)) = (task.report, &result) {
+    report.error(&filename, err);
+  }
+  result
+}
+
+/// Reads, parses, and formats the given `file` (see [read_input()](fn.read_input.html)) as
+/// directed by `task`, and returns the formatted bytes, and the fingerprint of the file as it was
+/// read (for a local file).
+fn format_file(
+  file: &Path,
+  task: &Task<'_>,
+) -> Result<(Vec<u8>, Option<Fingerprint>), anyhow::Error> {
+  let (buffer, fingerprint) = read_input(file, task)?;
+  let filename = file.to_string_lossy().to_string();
+  format_buffer(buffer, filename, task).map(|bytes| (bytes, fingerprint))
+}
+
+/// Parses and formats the document in `buffer`, read from `filename`, as directed by `task`, and
+/// returns the formatted bytes. If the configuration selects JSON5 blocks in files like
+/// `filename` (see the [islands](islands/index.html) module), each block is formatted as a
+/// document of its own, and the rest of the file is returned unchanged.
+fn format_buffer(
+  buffer: String,
+  filename: String,
+  task: &Task<'_>,
+) -> Result<Vec<u8>, anyhow::Error> {
+  // Without a sink, the formatted bytes are always returned.
+  format_buffer_to(buffer, filename, task, None).map(Option::unwrap_or_default)
+}
+
+/// Formats the document in `buffer` like [format_buffer()](fn.format_buffer.html), but if a `sink`
+/// is given and only json5format changes the document, writes it with the sink as it is formatted
+/// (see [format_document()](fn.format_document.html)), and returns `None`.
+fn format_buffer_to(
+  buffer: String,
+  filename: String,
+  task: &Task<'_>,
+  sink: Option<Sink<'_>>,
+) -> Result<Option<Vec<u8>>, anyhow::Error> {
+  if let Some(report) = task.report {
+    report.file(&filename);
+  }
+  let config = style::apply(file_types::options(task.config, &filename));
+  let result = islands::rules_for(config.strings("embedded"), &filename).and_then(|rules| {
+    match rules.is_empty() {
+      true => format_document(buffer, filename.clone(), task, sink),
+      false => islands::format(&buffer, &rules, |text| {
+        format_document(text, filename.clone(), task, None).map(Option::unwrap_or_default)
+      })
+      .map(Some),
+    }
+  });
+  if let (Some(report), Err(err)) = (task.report, &result) {
+    report.error(&filename, err);
+  }
+  result
+}
+
+/// Parses and formats the document in `buffer`, read from `filename`, as directed by `task` and
+/// any options directive in the document's first line, and returns the formatted bytes. The
+/// document goes through the stages of the library's `format_str()`, with the executable's
+/// plugins, checks, transforms, and sorting between them. Repairs and lint warnings are reported
+/// on stderr. If only json5format changes the document (no other pass rewrites its text) and a
+/// `sink` is given, the document is written with the sink as it is formatted instead, and `None`
+/// is returned.
+fn format_document(
+  buffer: String,
+  filename: String,
+  task: &Task<'_>,
+  sink: Option<Sink<'_>>,
+) -> Result<Option<Vec<u8>>, anyhow::Error> {
+  let start = Instant::now();
+  let buffer = match format::resolve_conflicts(buffer, &filename, task.take) {
+    Ok((buffer, resolved)) => {
+      if task.verbose && !resolved.is_empty() {
+        eprintln!(
+          "{}: Resolved the merge conflicts at lines {}",
+          filename,
+          conflicts::describe(&resolved)
+        );
+      }
+      buffer
+    }
+    Err(err) => {
+      if let Some(report) = task.report {
+        report.count(&codes::MERGE_CONFLICT, false, false);
+      }
+      return Err(err.into());
+    }
+  };
+  let _input = crash::Input::enter(&filename);
+  let original = (task.minimal_diff || task.report_semantic_changes).then(|| buffer.clone());
+  let config = style::apply(file_types::options(task.config, &filename));
+  let buffer = run_plugins(&config, Stage::Pre, &filename, buffer)?;
+  let (line, text) = document::first_line(&buffer, &config.source_options());
+  let config = match config::directive(text, line, &filename)? {
+    Some((directive, warnings)) => {
+      for warning in warnings {
+        eprintln!("Configuration warning: {}", warning);
+      }
+      let mut config = config.clone();
+      config.merge(directive);
+      style::apply(config)
+    }
+    None => config.clone(),
+  };
+
+  let mut options = core_options(&config, task);
+  if options.source.front_matter
+    && document::front_matter(&buffer)
+      .is_none_or(|(start, end)| buffer[start..end].trim().is_empty())
+  {
+    // Without front matter, a Markdown file has no document to format.
+    return Ok(Some(buffer.into_bytes()));
+  }
+  let preamble_len = document::preamble_len(&buffer, &options.source);
+  if task.emit == Emit::Tokens {
+    return Ok(Some(
+      emit::tokens(&buffer, preamble_len, &filename)
+        .to_pretty_string()
+        .into_bytes(),
+    ));
+  }
+  // The repairs come before the checks of the dialect, so that the trailing commas that strict
+  // JSON forbids are removed rather than reported.
+  let trailing_commas =
+    options.dialect.allows(Feature::TrailingComma) && task.input_format != InputFormat::Json;
+  let (buffer, repairs) = repair::repair(&buffer, preamble_len, options.fix, trailing_commas);
+  for repair in repairs {
+    eprintln!(
+      "Fixed: {}:{}:{}: {}",
+      filename, repair.line, repair.col, repair.message
+    );
+  }
+  if task.input_format != InputFormat::Json5 || options.dialect != Dialect::Json5 {
+    let usages = features::find(
+      &buffer[..document::body_end(&buffer, &options.source)],
+      preamble_len,
+    );
+    if let (InputFormat::Json, Some(usage)) = (task.input_format, usages.first()) {
+      return Err(anyhow::anyhow!(
+        "Input error[{}]: {}:{}:{}: Expected strict JSON, but found {}",
+        codes::DIALECT.id,
+        filename,
+        usage.line,
+        usage.col,
+        usage.feature.describe()
+      ));
+    }
+    if task.input_format == InputFormat::Auto {
+      if task.verbose {
+        match usages.first() {
+          Some(usage) => eprintln!("{}: Detected JSON5 (found {})", filename, usage),
+          None => eprintln!("{}: Detected JSON (no JSON5 extensions)", filename),
+        }
+      }
+      if usages.is_empty() {
+        // Keep strict JSON input strict JSON.
+        options.dialect = Dialect::Json;
+      }
+    }
+    check_dialect(&usages, options.dialect, &buffer, &filename, task)?;
+  }
+
+  let mut parsed = format::parse(buffer, &filename, &options, task.threads)?;
+  if let Some(indentation) = parsed.indentation.filter(|_| task.verbose) {
+    eprintln!(
+      "{}: Detected an indentation of {}",
+      filename,
+      indentation.describe()
+    );
+  }
+  let document = &mut parsed.document;
+  let format_options = &mut parsed.format;
+  let (suppressions, warnings) = Suppressions::parse(&document.source);
+  for warning in warnings {
+    eprintln!("Warning: {}:{}", filename, warning);
+  }
+  let check_keys = task.key_case != KeyCase::Preserve;
+  let rename_keys = check_keys && task.fix_lint;
+  let check_keys = check_keys && !task.fix_lint;
+  let sort_keys = config.bool("sort_keys") == Some(true);
+  let rules = lint::rules_for(
+    config.rules("lint"),
+    config.lint_overrides("lint_overrides"),
+    &filename,
+  );
+  let rules =
+    Some(&rules).filter(|rules| rules.values().any(|rule| rule.level != lint::Level::Off));
+  let sections = config.sections("sections");
+  let priorities = config.priorities("sort_priority");
+  if task.known_keys.is_some() || rules.is_some() || check_keys || task.forbid_non_finite {
+    if let Some(root) = syntax::parse(&document.source)? {
+      let mut diagnostics = Vec::new();
+      if let Some(known_keys) = task.known_keys {
+        diagnostics.extend(known_keys.check(&root));
+      }
+      if let Some(rules) = rules {
+        diagnostics.extend(lint::run(&root, &document.source, rules));
+      }
+      if task.forbid_non_finite {
+        diagnostics.extend(literals::non_finite(&root, &document.source));
+      }
+      if check_keys {
+        diagnostics.extend(key_case::check(&root, task.key_case, task.key_case_exclude));
+      }
+      report_diagnostics(
+        diagnostics,
+        &filename,
+        &document.source,
+        &suppressions,
+        task,
+      )
+      .check(&filename)?;
+    }
+  }
+  // The warnings reported from here on fail the document, if denied, once it is formatted.
+  let mut failures = Failures::default();
+  if !task.transforms.is_empty()
+    || rename_keys
+    || options.hex_numbers != HexNumbers::Preserve
+    || options.normalize_floats != NormalizeFloats::Preserve
+    || options.quotes != Quotes::Preserve
+    || !task.redact.is_empty()
+  {
+    if let Some(root) = syntax::parse(&document.source)? {
+      let mut fixes = Fixes::default();
+      let mut edits = Edits::default();
+      redact::redact(&root, task.redact, &mut edits);
+      fixes.add(Pass::Redact, edits);
+      let mut edits = Edits::default();
+      transform::transform(&root, &document.source, task.transforms, &mut edits)
+        .map_err(|message| anyhow::anyhow!("Transform error: {}:{}", filename, message))?;
+      fixes.add(Pass::Transform, edits);
+      if rename_keys {
+        let mut edits = Edits::default();
+        let (renames, warnings) = key_case::rename(
+          &root,
+          &document.source,
+          task.key_case,
+          task.key_case_exclude,
+          &mut edits,
+        );
+        for rename in renames {
+          eprintln!(
+            "Fixed: {}:{}:{}: {}",
+            filename, rename.line, rename.col, rename.message
+          );
+        }
+        failures.add(report_diagnostics(
+          warnings,
+          &filename,
+          &document.source,
+          &suppressions,
+          task,
+        ));
+        fixes.add(Pass::Rename, edits);
+      }
+      format::rewrite_values(&root, &document.source, &options, &mut fixes);
+      if !fixes.is_empty() {
+        let (source, conflicts) = fixes.apply(&document.source);
+        for conflict in conflicts {
+          eprintln!("Warning: {}:{}", filename, conflict);
+        }
+        document.set_source(source, &filename)?;
+      }
+    }
+    // The transforms may produce values that the dialect or `--forbid-non-finite` does not allow,
+    // so the transformed document is checked again.
+    if !task.transforms.is_empty() {
+      if options.dialect != Dialect::Json5 {
+        let usages = features::find(&document.source, 0);
+        check_dialect(&usages, options.dialect, &document.source, &filename, task)?;
+      }
+      if task.forbid_non_finite {
+        if let Some(root) = syntax::parse(&document.source)? {
+          report_diagnostics(
+            literals::non_finite(&root, &document.source),
+            &filename,
+            &document.source,
+            &suppressions,
+            task,
+          )
+          .check(&filename)?;
+        }
+      }
+    }
+  }
+  if !task.format_embedded.is_empty() {
+    if let Some(root) = syntax::parse(&document.source)? {
+      let mut edits = Edits::default();
+      let warnings = embedded::format(
+        &root,
+        &document.source,
+        task.format_embedded,
+        format_options,
+        &mut edits,
+      );
+      failures.add(report_diagnostics(
+        warnings,
+        &filename,
+        &document.source,
+        &suppressions,
+        task,
+      ));
+      if !edits.is_empty() {
+        let source = edits.apply(&document.source);
+        document.set_source(source, &filename)?;
+      }
+    }
+  }
+  let no_sort = document.source.contains(sorting::NO_SORT);
+  if sort_keys
+    || !sections.is_empty()
+    || !priorities.is_empty()
+    || !config.path_rules("paths").is_empty()
+    || no_sort
+    || (task.sort_warn_heuristics && format_options.sort_array_items)
+  {
+    if let Some(root) = syntax::parse(&document.source)? {
+      let mut rules = config.path_rules("paths").to_vec();
+      if no_sort {
+        rules.extend(sorting::no_sort_rules(&root, &document.source));
+      }
+      if task.sort_warn_heuristics && format_options.sort_array_items {
+        let (warnings, kept) = order_sensitive::check(
+          &root,
+          &document.source,
+          format_options.sort_array_items,
+          &PathRules::new(&rules),
+        );
+        failures.add(report_diagnostics(
+          warnings,
+          &filename,
+          &document.source,
+          &suppressions,
+          task,
+        ));
+        rules.extend(kept);
+      }
+      let path_rules = PathRules::new(&rules);
+      format_options.options_by_path = path_rules.path_options(&root, &document.source);
+      let orders = sorting::property_orders(
+        &root,
+        &document.source,
+        sort_keys,
+        sections,
+        priorities,
+        &path_rules,
+      );
+      for (path, order) in orders {
+        format_options
+          .options_by_path
+          .entry(path)
+          .or_default()
+          .extend(order);
+      }
+      if !options.dialect.allows(Feature::TrailingComma) {
+        for path_options in format_options.options_by_path.values_mut() {
+          path_options.remove(&PathOption::TrailingCommas(false));
+        }
+      }
+      format_options.options_by_path = path_options::compact(
+        std::mem::take(&mut format_options.options_by_path),
+        &root,
+        &document.source,
+      );
+      let mut edits = Edits::default();
+      if config.bool("section_comments") != Some(false) {
+        sections::comments(&root, &document.source, sections, &mut edits);
+      }
+      if !edits.is_empty() {
+        let source = edits.apply(&document.source);
+        document.set_source(source, &filename)?;
+      }
+    }
+  }
+  if task.emit == Emit::Ast {
+    return Ok(Some(
+      emit::ast(&document.source, &filename)?
+        .to_pretty_string()
+        .into_bytes(),
+    ));
+  }
+  if let Some(timings) = task.timings {
+    timings.record(&filename, Phase::Parse, start.elapsed());
+  }
+  let start = Instant::now();
+  let sorted_arrays = format_options.sort_array_items
+    || format_options
+      .options_by_path
+      .values()
+      .any(|path_options| path_options.contains(&PathOption::SortArrayItems(true)));
+  let only_json5format = !format::rewrites_output(&parsed, &options)
+    && !task.paranoid
+    && !task.safe_replace
+    && original.is_none()
+    && config.strings("post_format_plugins").is_empty();
+  if let Some(sink) = sink.filter(|_| only_json5format) {
+    failures.check(&filename)?;
+    sink(&parsed.document, std::mem::take(&mut parsed.format))?;
+    if let Some(timings) = task.timings {
+      timings.record(&filename, Phase::Format, start.elapsed());
+    }
+    return Ok(None);
+  }
+  let (formatted, warnings) = format::format_parsed(&mut parsed, &filename, &options)?;
+  let document = &parsed.document;
+  failures.add(report_diagnostics(
+    warnings,
+    &filename,
+    &document.source,
+    &suppressions,
+    task,
+  ));
+  failures.check(&filename)?;
+  if task.paranoid {
+    paranoid::check(&document.source, document.body(&formatted), sorted_arrays).map_err(
+      |message| {
+        anyhow::anyhow!(
+          "Paranoid error: {}: {} (the output was not written)",
+          filename,
+          message
+        )
+      },
+    )?;
+  }
+  let mut formatted = run_plugins(&config, Stage::Post, &filename, formatted)?;
+  if let Some(original) = original.as_ref().filter(|_| task.minimal_diff) {
+    formatted = minimal::minimal_diff(original, &formatted);
+  }
+  if task.safe_replace {
+    paranoid::check(&document.source, document.body(&formatted), sorted_arrays).map_err(
+      |message| {
+        anyhow::anyhow!(
+          "Safe replace error: {}: {} (the file was not replaced)",
+          filename,
+          message
+        )
+      },
+    )?;
+  }
+  if let Some(mut input) = original.filter(|_| task.report_semantic_changes) {
+    // Blank out the preamble, so the line numbers in messages match the file.
+    input.truncate(document::body_end(&input, &options.source));
+    let preamble_len = document::preamble_len(&input, &options.source);
+    let preamble = input[..preamble_len].replace(|c| c != '\n', " ");
+    input.replace_range(..preamble_len, &preamble);
+    for change in semantic_changes::changes(&input, document.body(&formatted)) {
+      eprintln!(
+        "Semantic change: {}:{}:{}: {}: {}",
+        filename, change.line, change.col, change.path, change.message
+      );
+    }
+  }
+  let formatted = formatted.into_bytes();
+  if let Some(timings) = task.timings {
+    timings.record(&filename, Phase::Format, start.elapsed());
+  }
+  Ok(Some(formatted))
+}
+
+/// Reports each of the `usages` of JSON5 extensions in the document `buffer` that `dialect` does
+/// not allow, and fails if there are any.
+fn check_dialect(
+  usages: &[Usage],
+  dialect: Dialect,
+  buffer: &str,
+  filename: &str,
+  task: &Task<'_>,
+) -> Result<()> {
+  let err = match format::check_dialect(usages, filename, dialect) {
+    Ok(()) => return Ok(()),
+    Err(err) => err,
+  };
+  if let FormatError::Dialect { diagnostics, .. } = &err {
+    for diagnostic in diagnostics {
+      if let Some(report) = task.report {
+        report.count(diagnostic.code, false, false);
+        report.diagnostic(filename, diagnostic, buffer);
+      }
+      eprintln!(
+        "{}",
+        task.error_format.diagnostic(diagnostic, filename, buffer)
+      );
+    }
+  }
+  Err(err.into())
+}
+
+/// Returns the options of the formatting core for a document that `task` formats with the
+/// `config`.
+fn core_options(config: &Config, task: &Task<'_>) -> Options {
+  Options {
+    format: config.format_options(),
+    source: config.source_options(),
+    auto_indent: config.string("indent").is_some(),
+    take: task.take,
+    fix: task.fix,
+    dialect: config
+      .string("dialect")
+      .and_then(|dialect| dialect.parse().ok())
+      .unwrap_or(Dialect::Json5),
+    quotes: config
+      .string("quotes")
+      .and_then(|style| style.parse().ok())
+      .unwrap_or_default(),
+    hex_numbers: task.hex_numbers,
+    normalize_floats: task.normalize_floats,
+    comment_attachment: config
+      .string("comment_attachment")
+      .and_then(|attachment| attachment.parse().ok())
+      .unwrap_or_default(),
+    empty_comments: config
+      .string("empty_container_comments")
+      .and_then(|style| style.parse().ok())
+      .unwrap_or_default(),
+    eol_comments: config
+      .string("eol_comments")
+      .and_then(|placement| placement.parse().ok())
+      .unwrap_or_default(),
+    array_wrap: config
+      .string("array_wrap")
+      .and_then(|style| style.parse().ok())
+      .unwrap_or_default(),
+    long_values: config
+      .string("long_value")
+      .and_then(|policy| policy.parse().ok())
+      .unwrap_or_default(),
+    max_width: config
+      .integer("max_width")
+      .unwrap_or(long_values::DEFAULT_MAX_WIDTH),
+  }
+}
+
+/// Appends to `files` each file in the directory `directory` and in its subdirectories whose name
+/// ends with one of the `extensions` (in lowercase), searching the entries of each directory in
+/// order of their names (see [Context::expand](struct.Context.html#method.expand)).
+fn walk(directory: &Path, extensions: &[String], files: &mut Vec<PathBuf>) -> Result<()> {
+  let read_error =
+    |err: io::Error| anyhow::anyhow!("Input error: {}: {}", directory.display(), err);
+  let mut entries = fs::read_dir(paths::extended(directory))
+    .map_err(read_error)?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(read_error)?;
+  entries.sort_by_key(|entry| entry.file_name());
+  for entry in entries {
+    let name = entry.file_name().to_string_lossy().to_lowercase();
+    if name.starts_with('.') {
+      continue;
+    }
+    let path = directory.join(entry.file_name());
+    if entry.file_type().map_err(read_error)?.is_dir() {
+      walk(&path, extensions, files)?;
+    } else if extensions
+      .iter()
+      .any(|extension| name.ends_with(extension.as_str()))
+      && path.is_file()
+    {
+      files.push(path);
+    }
+  }
+  Ok(())
+}
+
+/// The number of diagnostics reported for a document that make formatting it fail.
+#[derive(Clone, Copy, Debug, Default)]
+struct Failures {
+  /// The number of errors.
+  errors: usize,
+  /// The number of warnings reported as errors, by --deny-warnings or --deny.
+  denied: usize,
+}
+
+impl Failures {
+  /// Adds the failures counted in `other`.
+  fn add(&mut self, other: Failures) {
+    self.errors += other.errors;
+    self.denied += other.denied;
+  }
+
+  /// Returns an error for the document read from `filename`, if any diagnostics failed it.
+  fn check(self, filename: &str) -> Result<(), anyhow::Error> {
+    match (self.errors, self.denied) {
+      (0, 0) => Ok(()),
+      (errors, 0) => Err(anyhow::anyhow!(
+        "Lint error: {}: Found {} error(s)",
+        filename,
+        errors
+      )),
+      (0, denied) => Err(anyhow::anyhow!(
+        "Lint error: {}: Found {} denied warning(s)",
+        filename,
+        denied
+      )),
+      (errors, denied) => Err(anyhow::anyhow!(
+        "Lint error: {}: Found {} error(s) and {} denied warning(s)",
+        filename,
+        errors,
+        denied
+      )),
+    }
+  }
+}
+
+/// Reports the `diagnostics` found in the document `source`, read from `filename`, on stderr in
+/// source order, except those silenced by `suppressions`, and counts them in the report of `task`
+/// (if any). Warnings that `task` denies are reported as errors. Returns the number of errors and
+/// denied warnings reported.
+fn report_diagnostics(
+  mut diagnostics: Vec<Diagnostic>,
+  filename: &str,
+  source: &str,
+  suppressions: &Suppressions,
+  task: &Task<'_>,
+) -> Failures {
+  diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.col));
+  let mut failures = Failures::default();
+  for diagnostic in &mut diagnostics {
+    let suppressed = suppressions.allows(diagnostic);
+    let denied = diagnostic.severity == Severity::Warning
+      && (task.deny_warnings || task.deny.iter().any(|code| code.id == diagnostic.code.id));
+    if let Some(report) = task.report {
+      report.count(diagnostic.code, suppressed, denied);
+    }
+    if suppressed {
+      continue;
+    }
+    if denied {
+      diagnostic.severity = Severity::Error;
+      diagnostic.message.push_str(match task.deny_warnings {
+        true => " (a warning, denied by --deny-warnings)",
+        false => " (a warning, denied by --deny)",
+      });
+      failures.denied += 1;
+    } else if diagnostic.severity == Severity::Error {
+      failures.errors += 1;
+    }
+    if diagnostic.severity == Severity::Error {
+      if let Some(findings) = task.findings {
+        findings.record(filename, diagnostic.code);
+      }
+    }
+    if let Some(report) = task.report {
+      report.diagnostic(filename, diagnostic, source);
+    }
+    eprintln!(
+      "{}",
+      task.error_format.diagnostic(diagnostic, filename, source)
+    );
+  }
+  failures
+}
+
+/// Runs the plugins configured in `config` for the given `stage` on the document `source`, read
+/// from `filename`, in order, and returns the document as changed by the plugins. The plugins'
+/// diagnostics are reported on stderr, and the first plugin that reports an error fails.
+fn run_plugins(
+  config: &Config,
+  stage: Stage,
+  filename: &str,
+  mut source: String,
+) -> Result<String, anyhow::Error> {
+  let option = match stage {
+    Stage::Pre => "pre_format_plugins",
+    Stage::Post => "post_format_plugins",
+  };
+  for command_line in config.strings(option) {
+    let response = plugin::run(
+      command_line,
+      config.directory(option),
+      stage,
+      filename,
+      &source,
+    )?;
+    let mut errors = 0;
+    for diagnostic in &response.diagnostics {
+      let location = match (diagnostic.line, diagnostic.col) {
+        (Some(line), Some(col)) => format!("{}:{}:{}", filename, line, col),
+        (Some(line), None) => format!("{}:{}", filename, line),
+        _ => filename.to_string(),
+      };
+      let severity = if diagnostic.is_error {
+        errors += 1;
+        "Error"
+      } else {
+        "Warning"
+      };
+      eprintln!(
+        "{}: {}: {} [{}]",
+        severity, location, diagnostic.message, command_line
+      );
+    }
+    if errors > 0 {
+      return Err(anyhow::anyhow!(
+        "Plugin error: `{}`: Reported {} error(s) in {}",
+        command_line,
+        errors,
+        filename
+      ));
+    }
+    if let Some(replacement) = response.source {
+      source = replacement;
+    }
+  }
+  Ok(source)
+}
+
+/// Reads documents from stdin with the given `framing` until the end of the stream, and writes each
+/// one to stdout, formatted, in the same framing. A document that cannot be formatted is written
+/// back unchanged (so every input frame has exactly one output frame) and its error is reported on
+/// stderr; the command fails after the stream ends if any document had an error.
+fn format_frames(framing: Framing, task: &Task<'_>) -> Result<(), anyhow::Error> {
+  let (stdin, stdout) = (io::stdin(), io::stdout());
+  let (mut input, mut output) = (stdin.lock(), stdout.lock());
+  let mut failed = 0;
+  let mut index = 0;
+  while let Some(frame) = framing.read(&mut input)? {
+    index += 1;
+    let result = String::from_utf8(frame.clone())
+      .map_err(anyhow::Error::from)
+      .and_then(|buffer| format_buffer(buffer, "-".to_string(), task));
+    match result {
+      Ok(bytes) => framing.write(&mut output, &bytes)?,
+      Err(err) => {
+        eprintln!("Document {}: {}", index, err);
+        failed += 1;
+        framing.write(&mut output, &frame)?;
+      }
+    }
+  }
+  if failed > 0 {
+    return Err(anyhow::anyhow!(
+      "{} of {} document(s) could not be formatted",
+      failed,
+      index
+    ));
+  }
+  Ok(())
+}
+
+/// Returns the `write_mode` set in `config`.
+fn write_mode(config: &Config) -> WriteMode {
+  config
+    .string("write_mode")
+    .and_then(|mode| mode.parse().ok())
+    .unwrap_or_default()
+}
+
+/// Writes a document that only json5format changes, formatted with the given options, to its
+/// destination as it is formatted (see
+/// [Document::format_to_writer()](document/struct.Document.html#method.format_to_writer)), rather
+/// than returning its formatted bytes.
+type Sink<'a> = &'a mut dyn FnMut(&Document, FormatOptions) -> Result<(), anyhow::Error>;
+
+/// The formatted document of a file, as a worker leaves it for [Output](struct.Output.html).
+enum Produced {
+  /// The formatted bytes.
+  Bytes(Vec<u8>),
+  /// The path of a temporary file next to the file being replaced, which holds its formatted
+  /// document.
+  Staged(PathBuf),
+  /// Nothing, since the formatted document was already written to stdout.
+  Written,
+}
+
+impl Produced {
+  /// Removes the temporary file of a staged document, which is not written.
+  fn discard(&self) {
+    if let Produced::Staged(temporary) = self {
+      let _ = fs::remove_file(temporary);
+    }
+  }
+}
+
+/// Writes formatted documents to their destination.
+struct Output {
+  /// If true, each input file is overwritten by its formatted version. Otherwise, the formatted
+  /// documents are written to stdout.
+  replace: bool,
+
+  /// If true, input files are replaced even if they changed since they were read.
+  force_overwrite: bool,
+
+  /// If true, each replaced file is locked while it is written (see the [lock](lock/index.html)
+  /// module).
+  lock: bool,
+
+  /// How input files are replaced.
+  write_mode: WriteMode,
+
+  /// If set, the formatted document is written to this file, instead of to stdout.
+  path: Option<PathBuf>,
+
+  /// How the documents written to stdout are separated.
+  separator: Separator,
+
+  /// If true, each document written to stdout is written as soon as it and the documents before
+  /// it are formatted, and flushed.
+  flush: bool,
+
+  /// If true, the documents that formatting leaves unchanged are not written.
+  changed_only: bool,
+
+  /// The number of documents written so far.
+  written: usize,
+}
+
+impl Output {
+  /// Writes the formatted document read from `filename`, as `produced`, with the `fingerprint` of
+  /// the file as it was read (if it is a local file). Stdin cannot be replaced, so its document is
+  /// always written to stdout. A file that changed since it was read is not replaced, unless
+  /// `force_overwrite` is set.
+  fn write(
+    &mut self,
+    filename: &str,
+    produced: Produced,
+    fingerprint: Option<&Fingerprint>,
+  ) -> Result<(), anyhow::Error> {
+    let bytes = match produced {
+      Produced::Bytes(bytes) => bytes,
+      Produced::Staged(temporary) => {
+        if !self.replaceable(filename, fingerprint) {
+          let _ = fs::remove_file(&temporary);
+          return Ok(());
+        }
+        self.write_mode.replace_with(filename, &temporary)?;
+        self.written += 1;
+        return Ok(());
+      }
+      Produced::Written => {
+        self.written += 1;
+        return Ok(());
+      }
+    };
+    if let Some(path) = &self.path {
+      Opt::write_to_file(&path.to_string_lossy(), &bytes)?;
+    } else if self.replace && filename != "-" {
+      if !self.replaceable(filename, fingerprint) {
+        return Ok(());
+      }
+      self.write_mode.write(filename, &bytes)?;
+    } else {
+      // The bytes go to stdout as they are, in one write, rather than through `print!`.
+      let mut stdout = io::stdout().lock();
+      self
+        .separator
+        .write(&mut stdout, filename, &bytes, self.written)?;
+      if self.flush {
+        stdout.flush()?;
+      }
+    }
+    self.written += 1;
+    Ok(())
+  }
+
+  /// Returns true if the file `filename` may be replaced: if it is unchanged since it was read
+  /// (with the `fingerprint`), or `force_overwrite` is set. Otherwise, warns that it is not.
+  fn replaceable(&self, filename: &str, fingerprint: Option<&Fingerprint>) -> bool {
+    let (Some(fingerprint), false) = (fingerprint, self.force_overwrite) else {
+      return true;
+    };
+    let path = paths::extended(Path::new(filename));
+    if fingerprint.is_current(&path).unwrap_or(false) {
+      return true;
+    }
+    eprintln!(
+      "Warning: {}: The file changed since it was read, so it was not replaced (use \
+       --force-overwrite to replace it anyway)",
+      filename
+    );
+    false
+  }
+
+  /// Returns true if the formatted documents of the local files among a number of `files` are
+  /// written as they are formatted: to a temporary file next to each file that then replaces it
+  /// (with a write mode that writes a temporary file anyway), or to stdout (for a single document,
+  /// whose separator adds nothing).
+  fn streams(&self, files: usize) -> bool {
+    match (self.replace, &self.path) {
+      (true, None) => self.write_mode != WriteMode::Direct,
+      (false, None) => files == 1 && self.separator == Separator::None && !self.changed_only,
+      (_, Some(_)) => false,
+    }
+  }
+}
+
+/// Reads and formats the given `file` like [format_file()](fn.format_file.html), and if only
+/// json5format changes its document, writes it as it is formatted: to a temporary file next to the
+/// file, if `replace` is true (and returns the temporary file's path), or otherwise to stdout.
+fn stream_file(
+  file: &Path,
+  task: &Task<'_>,
+  replace: bool,
+) -> Result<(Produced, Option<Fingerprint>), anyhow::Error> {
+  let (buffer, fingerprint) = read_input(file, task)?;
+  let filename = file.to_string_lossy().to_string();
+  let temporary = write_mode::temporary_path(&paths::extended(file));
+  let mut staged = false;
+  let mut sink = |document: &Document, options: FormatOptions| -> Result<(), anyhow::Error> {
+    if replace {
+      staged = true;
+      let mut writer = io::BufWriter::new(fs::File::create(&temporary)?);
+      document.format_to_writer(options, &filename, &mut writer)?;
+      writer.into_inner().map_err(|err| err.into_error())?;
+    } else {
+      let mut writer = io::BufWriter::new(io::stdout().lock());
+      document.format_to_writer(options, &filename, &mut writer)?;
+      writer.flush()?;
+    }
+    Ok(())
+  };
+  let result = format_buffer_to(buffer, filename.clone(), task, Some(&mut sink));
+  match result {
+    Ok(Some(bytes)) => Ok((Produced::Bytes(bytes), fingerprint)),
+    Ok(None) if replace => Ok((Produced::Staged(temporary), fingerprint)),
+    Ok(None) => Ok((Produced::Written, fingerprint)),
+    Err(err) => {
+      if staged {
+        let _ = fs::remove_file(&temporary);
+      }
+      Err(err)
+    }
+  }
+}
+
+/// Formats each of the given `files` as directed by `task` on up to `threads` threads, and writes
+/// the results to `output`. By default, if any file fails to parse or format, the command aborts
+/// without writing any of the documents. When writing to stdout with `OutputOrder::Completion`,
+/// each document is instead written as soon as it is formatted, and with `output.flush`, as soon as
+/// it and the documents before it are formatted (up to the first that fails); the first error (if
+/// any) is then returned after all files are processed. Where
+/// [Output::streams()](struct.Output.html) allows, each document that only json5format changes is
+/// written as it is formatted (see [stream_file()](fn.stream_file.html)), rather than built in
+/// memory first.
+fn format_files(
+  files: &[PathBuf],
+  task: &Task<'_>,
+  threads: usize,
+  order: OutputOrder,
+  output: &mut Output,
+) -> Result<(), anyhow::Error> {
+  let stream = order == OutputOrder::Completion && !output.replace;
+  let in_order = output.flush && !stream && !output.replace;
+  let changed_only = output.changed_only;
+  let (streams, replace) = (output.streams(files.len()), output.replace);
+  let mut results: Vec<Option<Result<_, anyhow::Error>>> = files.iter().map(|_| None).collect();
+  // With `in_order`, the index of the first document not written yet.
+  let mut next = 0;
+  let mut write_error = None;
+  // Writes the formatted document of the file at `index`, unless it is unchanged and only changed
+  // documents are written.
+  let mut write =
+    |index: usize, (produced, fingerprint, changed): (Produced, Option<Fingerprint>, bool)| {
+      if !changed {
+        return Ok(());
+      }
+      let file = &files[index];
+      let filename = file.to_string_lossy().to_string();
+      let held = match output.lock && output.replace && filename != "-" {
+        true => lock::lock(file)?,
+        false => None,
+      };
+      // Another run may have replaced the file since it was read, so format it again as it is now.
+      let stale = |read: &Fingerprint| !read.is_current(&paths::extended(file)).unwrap_or(true);
+      let (produced, fingerprint) = match &fingerprint {
+        Some(read) if held.is_some() && !output.force_overwrite && stale(read) => {
+          if task.verbose {
+            eprintln!("{}: Formatting the file again, since it changed", filename);
+          }
+          produced.discard();
+          let (bytes, fingerprint) = format_file(file, task)?;
+          (Produced::Bytes(bytes), fingerprint)
+        }
+        _ => (produced, fingerprint),
+      };
+      Timings::time(task.timings, &filename, Phase::Write, || {
+        output.write(&filename, produced, fingerprint.as_ref())
+      })
+    };
+  parallel::run(
+    files,
+    threads,
+    |file| match changed_only {
+      // The input is kept to compare the formatted document with.
+      true => {
+        let (buffer, fingerprint) = read_input(file, task)?;
+        let original = buffer.clone();
+        let bytes = format_buffer(buffer, file.to_string_lossy().to_string(), task)?;
+        let changed = bytes != original.as_bytes();
+        Ok((Produced::Bytes(bytes), fingerprint, changed))
+      }
+      // A document replacing stdin or a URL is written to stdout, and never staged.
+      false
+        if streams
+          && !(replace && (file.as_os_str() == "-" || remote::is_url(&file.to_string_lossy()))) =>
+      {
+        stream_file(file, task, replace)
+          .map(|(produced, fingerprint)| (produced, fingerprint, true))
+      }
+      false => format_file(file, task)
+        .map(|(bytes, fingerprint)| (Produced::Bytes(bytes), fingerprint, true)),
+    },
+    |index, result| match result {
+      Ok(formatted) if stream => {
+        if let Err(err) = write(index, formatted) {
+          write_error.get_or_insert(err);
+        }
+      }
+      result => {
+        results[index] = Some(result);
+        while in_order && write_error.is_none() && matches!(results.get(next), Some(Some(Ok(_)))) {
+          if let Some(Ok(formatted)) = results[next].take() {
+            if let Err(err) = write(next, formatted) {
+              write_error = Some(err);
+            }
+          }
+          next += 1;
+        }
+      }
+    },
+  );
+  // Report the first error in input order, so the result does not depend on thread timing.
+  // Documents already written (streamed) have no result left.
+  let mut error = write_error;
+  let mut pending = Vec::new();
+  for (index, result) in results.into_iter().enumerate() {
+    match result {
+      Some(Ok(formatted)) => pending.push((index, formatted)),
+      Some(Err(err)) => {
+        error.get_or_insert(err);
+      }
+      None => {}
+    }
+  }
+  // The documents not written leave no temporary files behind.
+  let discard = |pending: &mut dyn Iterator<Item = (usize, (Produced, _, _))>| {
+    pending.for_each(|(_, (produced, _, _))| produced.discard())
+  };
+  if let Some(err) = error {
+    discard(&mut pending.into_iter());
+    return Err(err);
+  }
+  let mut pending = pending.into_iter();
+  while let Some((index, formatted)) = pending.next() {
+    if let Err(err) = write(index, formatted) {
+      discard(&mut pending);
+      return Err(err);
+    }
+  }
+  Ok(())
+}
+
+/// Converts each of the given `files` (or stdin, if a file is "-") to `target`, with the
+/// indentation from `config`, and prints the results, or writes each converted file under
+/// `out_dir`, with the same path relative to the current directory and the extension of `target`.
+/// If `verify` is true, each converted document is parsed again, and a document that lost any data
+/// (such as comments) is reported and not written.
+#[cfg(feature = "convert")]
+fn convert_files(
+  target: Target,
+  files: &[PathBuf],
+  out_dir: Option<&Path>,
+  verify: bool,
+  config: &Config,
+) -> Result<()> {
+  let current_dir = std::env::current_dir()?;
+  let indent = config.format_options().indent_by;
+  for file in files {
+    let filename = file.to_string_lossy().to_string();
+    let mut buffer = String::new();
+    if filename == "-" {
+      if out_dir.is_some() {
+        return Err(anyhow::anyhow!(
+          "Stdin cannot be converted with --out-dir, since it has no filename"
+        ));
+      }
+      Opt::from_stdin(&mut buffer)?;
+    } else if remote::is_url(&filename) {
+      buffer = remote::fetch(&filename)?;
+    } else {
+      buffer = fs::read_to_string(paths::extended(file))?;
+    }
+    // Blank out the preamble, so the line numbers in messages match the file, and drop the rest of
+    // the file after its front matter.
+    let source_options = config.source_options();
+    buffer.truncate(document::body_end(&buffer, &source_options));
+    let preamble_len = document::preamble_len(&buffer, &source_options);
+    let preamble = buffer[..preamble_len].replace(|c| c != '\n', " ");
+    buffer.replace_range(..preamble_len, &preamble);
+    let root = syntax::parse(&buffer)
+      .map_err(|err| anyhow::anyhow!("Syntax error: {}:{}", filename, err))?
+      .ok_or_else(|| {
+        anyhow::anyhow!(
+          "Conversion error: {}: The document holds no value",
+          filename
+        )
+      })?;
+    let converted = convert::convert(&root, &buffer, target, indent)
+      .map_err(|message| anyhow::anyhow!("Conversion error: {}:{}", filename, message))?;
+    if verify {
+      let mut losses = convert::verify(&root, &buffer, &converted);
+      if preamble_len > 0 {
+        losses.insert(0, "1:1: The preamble is lost".to_string());
+      }
+      for loss in &losses {
+        eprintln!("Error: {}:{}", filename, loss);
+      }
+      if !losses.is_empty() {
+        return Err(anyhow::anyhow!(
+          "Round-trip error: {}: Converting the document lost data in {} place(s)",
+          filename,
+          losses.len()
+        ));
+      }
+    }
+    match out_dir {
+      Some(out_dir) => {
+        let path = convert::output_path(file, &current_dir, out_dir, target)
+          .map_err(|message| anyhow::anyhow!("{}", message))?;
+        if let Some(parent) = path.parent() {
+          fs::create_dir_all(paths::extended(parent))?;
+        }
+        Opt::write_to_file(&path.to_string_lossy(), converted.as_bytes())?;
+      }
+      None => print!("{}", converted),
+    }
+  }
+  Ok(())
+}
+
+/// Fails, because converting requires the `convert` feature.
+#[cfg(not(feature = "convert"))]
+fn convert_files(
+  _target: Target,
+  _files: &[PathBuf],
+  _out_dir: Option<&Path>,
+  _verify: bool,
+  _config: &Config,
+) -> Result<()> {
+  Err(anyhow::anyhow!(
+    "Converting requires building formatjson5 with the `convert` feature"
+  ))
+}
+
+/// Runs the `explain` subcommand: prints the explanation of the diagnostic `code`, or if no code
+/// is given, lists every code.
+fn explain(code: Option<&str>) -> Result<()> {
+  match code {
+    Some(code) => {
+      let code = codes::find(code).map_err(|message| anyhow::anyhow!("{}", message))?;
+      println!("{} ({}): {}\n", code.id, code.name, code.title);
+      println!("{}", code.explanation);
+      if suppress::is_suppressible(code) {
+        println!(
+          "\nTo silence it in a document, add `// json5format-allow: {}` on the line before it,\n\
+           or `// json5format-allow-file: {}` anywhere in the document.",
+          code.name, code.name
+        );
+      }
+    }
+    None => {
+      for code in codes::CODES {
+        println!("{}  {:<26}{}", code.id, code.name, code.title);
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Checks that each of the given `files` is formatted, and can be formatted without errors, as
+/// directed by `task` on up to `threads` threads, and reports each file that has problems. If a
+/// `baseline` file is given, only problems that are not in it are reported (and make the check
+/// fail), or if `update` is true, the current problems are recorded in it instead. If `rendering`
+/// is given, a diff of each file that is not formatted is printed as it directs.
+fn check_files(
+  files: &[PathBuf],
+  task: &Task<'_>,
+  threads: usize,
+  baseline: Option<&Path>,
+  update: bool,
+  rendering: Option<&diff::Rendering>,
+) -> Result<()> {
+  let known = match baseline {
+    Some(path) => Baseline::load(path)?,
+    None => Baseline::default(),
+  };
+  let mut results = files.iter().map(|_| None).collect::<Vec<_>>();
+  parallel::run(
+    files,
+    threads,
+    |file| {
+      let filename = file.to_string_lossy().to_string();
+      read_input(file, task).and_then(|(source, _)| {
+        let formatted = format_buffer(source.clone(), filename.clone(), task)?;
+        let formatted = String::from_utf8_lossy(&formatted);
+        let diff = rendering.map(|rendering| diff::file(&filename, &source, &formatted, rendering));
+        Ok((snapshot::first_difference(&source, &formatted), diff))
+      })
+    },
+    |index, result| results[index] = Some(result),
+  );
+
+  let mut current = Baseline::default();
+  let (mut failing, mut improved) = (0, 0);
+  for (file, result) in files.iter().zip(results.into_iter().flatten()) {
+    let filename = file.to_string_lossy().to_string();
+    let mut changed_line = None;
+    let mut problems = Problems {
+      errors: task
+        .findings
+        .map_or_else(Default::default, |findings| findings.errors(&filename)),
+      ..Problems::default()
+    };
+    match result {
+      Ok((first_change, diff)) => {
+        problems.unformatted = first_change.is_some();
+        changed_line = first_change;
+        print!("{}", diff.unwrap_or_default());
+      }
+      Err(err) => {
+        eprintln!("{}", task.error_format.error(&err, Some(&filename)));
+        problems.failed = true;
+      }
+    }
+    let baseline_problems = known.files.get(&filename).cloned().unwrap_or_default();
+    if !update {
+      let new = problems.new_since(&baseline_problems);
+      for problem in &new {
+        // An unformatted file is reported at the first line that formatting changes.
+        let line = changed_line.filter(|_| problem == "Not formatted");
+        // The other problems are recorded as the errors and diagnostics that cause them.
+        if let Some(report) = task.report.filter(|_| line.is_some()) {
+          report.unformatted(&filename, line, problem);
+        }
+        eprintln!("{}", task.error_format.problem(&filename, line, problem));
+      }
+      if !new.is_empty() {
+        failing += 1;
+      } else if problems.count() < baseline_problems.count() {
+        improved += 1;
+      }
+    }
+    if !problems.is_empty() {
+      current.files.insert(filename, problems);
+    }
+  }
+
+  if let (Some(path), true) = (baseline, update) {
+    current.save(path)?;
+    eprintln!(
+      "{}: Recorded the problems of {} file(s)",
+      path.display(),
+      current.files.len()
+    );
+    return Ok(());
+  }
+  if improved > 0 {
+    eprintln!(
+      "Note: {} file(s) have fewer problems than the baseline; use --update-baseline to record \
+       them",
+      improved
+    );
+  }
+  if failing > 0 {
+    return Err(anyhow::anyhow!(
+      "Check failed: {} file(s) have {}problems",
+      failing,
+      if baseline.is_some() { "new " } else { "" }
+    ));
+  }
+  Ok(())
+}
+
+/// Runs the `lint` subcommand: formats each of the given `files` as directed by `task` on up to
+/// `threads` threads, reporting their diagnostics without writing the formatted documents, and
+/// fails if any file has errors.
+fn lint_files(files: &[PathBuf], task: &Task<'_>, threads: usize) -> Result<()> {
+  let mut results = files.iter().map(|_| None).collect::<Vec<_>>();
+  parallel::run(
+    files,
+    threads,
+    |file| format_file(file, task).map(drop),
+    |index, result| results[index] = Some(result),
+  );
+  let mut failing = 0;
+  for (file, result) in files.iter().zip(results.into_iter().flatten()) {
+    if let Err(err) = result {
+      let filename = file.to_string_lossy();
+      eprintln!("{}", task.error_format.error(&err, Some(&filename)));
+      failing += 1;
+    }
+  }
+  if failing > 0 {
+    return Err(anyhow::anyhow!(
+      "Lint failed: {} file(s) have errors",
+      failing
+    ));
+  }
+  Ok(())
+}
+
+/// Runs the `diff` subcommand: formats each of the given `files` as directed by `task` on up to
+/// `threads` threads, and prints a diff of the changes to each one, as `rendering` directs. Fails
+/// after printing the diffs if any file could not be formatted.
+fn diff_files(
+  files: &[PathBuf],
+  task: &Task<'_>,
+  threads: usize,
+  rendering: &diff::Rendering,
+) -> Result<()> {
+  let mut results = files.iter().map(|_| None).collect::<Vec<_>>();
+  parallel::run(
+    files,
+    threads,
+    |file| {
+      let filename = file.to_string_lossy().to_string();
+      read_input(file, task).and_then(|(source, _)| {
+        let formatted = format_buffer(source.clone(), filename, task)?;
+        Ok((source, String::from_utf8(formatted)?))
+      })
+    },
+    |index, result| results[index] = Some(result),
+  );
+  let mut failing = 0;
+  for (file, result) in files.iter().zip(results.into_iter().flatten()) {
+    match result {
+      Ok((source, formatted)) => print!(
+        "{}",
+        diff::file(&file.to_string_lossy(), &source, &formatted, rendering)
+      ),
+      Err(err) => {
+        let filename = file.to_string_lossy();
+        eprintln!("{}", task.error_format.error(&err, Some(&filename)));
+        failing += 1;
+      }
+    }
+  }
+  if failing > 0 {
+    return Err(anyhow::anyhow!(
+      "{} file(s) could not be formatted",
+      failing
+    ));
+  }
+  Ok(())
+}
+
+/// Formats each of the given `files` as directed by `task`, once with the options `base` and once
+/// with the options `candidate`, and prints a diff of the changes from the first formatted document
+/// to the second, as `rendering` directs, for the `compare-options` subcommand.
+fn compare_files(
+  files: &[PathBuf],
+  task: &Task<'_>,
+  base: &Config,
+  candidate: &Config,
+  threads: usize,
+  rendering: &diff::Rendering,
+) -> Result<()> {
+  let base = Task {
+    config: base,
+    ..task.clone()
+  };
+  let candidate = Task {
+    config: candidate,
+    ..task.clone()
+  };
+  let mut results = files.iter().map(|_| None).collect::<Vec<_>>();
+  parallel::run(
+    files,
+    threads,
+    |file| {
+      let filename = file.to_string_lossy().to_string();
+      read_input(file, task).and_then(|(source, _)| {
+        let old = format_buffer(source.clone(), filename.clone(), &base)?;
+        let new = format_buffer(source, filename, &candidate)?;
+        Ok((String::from_utf8(old)?, String::from_utf8(new)?))
+      })
+    },
+    |index, result| results[index] = Some(result),
+  );
+  let (mut changed, mut failing) = (0, 0);
+  for (file, result) in files.iter().zip(results.into_iter().flatten()) {
+    match result {
+      Ok((old, new)) => {
+        if old != new {
+          changed += 1;
+        }
+        let filename = file.to_string_lossy();
+        print!(
+          "{}",
+          diff::labeled(
+            &format!("{} (base)", filename),
+            &format!("{} (candidate)", filename),
+            &old,
+            &new,
+            rendering
+          )
+        );
+      }
+      Err(err) => {
+        let filename = file.to_string_lossy();
+        eprintln!("{}", task.error_format.error(&err, Some(&filename)));
+        failing += 1;
+      }
+    }
+  }
+  eprintln!(
+    "{} of {} file(s) would change with the candidate options",
+    changed,
+    files.len() - failing
+  );
+  if failing > 0 {
+    return Err(anyhow::anyhow!(
+      "{} file(s) could not be formatted",
+      failing
+    ));
+  }
+  Ok(())
+}
+
+/// Formats each of the given `files` as directed by `task`, and replaces each one with its
+/// formatted document (or for stdin, writes it to stdout), for the `fix` and `edit`
+/// subcommands. Unless `no_lock` is true, the files are locked while they are replaced.
+fn replace_files(
+  context: &Context,
+  files: &[PathBuf],
+  task: &Task<'_>,
+  no_lock: bool,
+) -> Result<()> {
+  if files
+    .iter()
+    .any(|file| remote::is_url(&file.to_string_lossy()))
+  {
+    return Err(anyhow::anyhow!(
+      "A URL cannot be replaced; use --output to save the formatted document"
+    ));
+  }
+  let mut output = Output {
+    replace: true,
+    force_overwrite: false,
+    lock: !no_lock,
+    write_mode: write_mode(task.config),
+    path: None,
+    separator: Separator::None,
+    flush: false,
+    changed_only: false,
+    written: 0,
+  };
+  format_files(
+    files,
+    task,
+    context.threads(),
+    OutputOrder::Input,
+    &mut output,
+  )
+}
+
+/// Formats each of the given `files` as directed by `task` on up to `threads` threads, and writes
+/// the output of each to its [snapshot](snapshot/index.html) file, or if `check` is true, reports
+/// each file whose output differs from its snapshot.
+fn snapshot_files(files: &[PathBuf], task: &Task<'_>, threads: usize, check: bool) -> Result<()> {
+  let mut results = files.iter().map(|_| None).collect::<Vec<_>>();
+  parallel::run(
+    files,
+    threads,
+    |file| format_file(file, task).map(|(bytes, _)| bytes),
+    |index, result| results[index] = Some(result),
+  );
+
+  let (mut failed, mut written) = (0, 0);
+  for (file, result) in files.iter().zip(results.into_iter().flatten()) {
+    let formatted = match result {
+      Ok(formatted) => formatted,
+      Err(err) => {
+        let filename = file.to_string_lossy();
+        eprintln!("{}", task.error_format.error(&err, Some(&filename)));
+        failed += 1;
+        continue;
+      }
+    };
+    let path = snapshot::path(file);
+    if !check {
+      fs::write(paths::extended(&path), &formatted)
+        .map_err(|err| anyhow::anyhow!("Snapshot error: {}: {}", path.display(), err))?;
+      written += 1;
+      continue;
+    }
+    let expected = match fs::read(paths::extended(&path)) {
+      Ok(expected) => expected,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => {
+        eprintln!(
+          "{}: There is no snapshot {} (use --snapshot to write it)",
+          file.display(),
+          path.display()
+        );
+        failed += 1;
+        continue;
+      }
+      Err(err) => {
+        return Err(anyhow::anyhow!(
+          "Snapshot error: {}: {}",
+          path.display(),
+          err
+        ))
+      }
+    };
+    let expected = String::from_utf8_lossy(&expected);
+    let actual = String::from_utf8_lossy(&formatted);
+    if let Some(line) = snapshot::first_difference(&expected, &actual) {
+      eprintln!(
+        "{}: The output differs from the snapshot {}, starting at line {}",
+        file.display(),
+        path.display(),
+        line
+      );
+      failed += 1;
+    }
+  }
+  if !check {
+    eprintln!("Wrote {} snapshot(s)", written);
+  }
+  if failed > 0 {
+    return Err(anyhow::anyhow!(
+      "Snapshot {} failed: {} file(s) {}",
+      if check { "check" } else { "update" },
+      failed,
+      if check {
+        "do not match their snapshots"
+      } else {
+        "could not be formatted"
+      }
+    ));
+  }
+  Ok(())
+}
+
+/// Runs the `migrate-config` subcommand: rewrites the deprecated options in the configuration file
+/// at `path` (or the file found from the current directory), or prints the migrated file to stdout
+/// if `dry_run` is true, and then prints the replacement for each deprecated command line flag.
+fn migrate_config(path: Option<&Path>, dry_run: bool) -> Result<(), anyhow::Error> {
+  let path = match path {
+    Some(path) => Some(path.to_path_buf()),
+    None => config::find(&std::env::current_dir()?),
+  };
+  match path {
+    Some(path) => {
+      let filename = path.to_string_lossy().to_string();
+      let source = fs::read_to_string(paths::extended(&path))
+        .map_err(|err| anyhow::anyhow!("Configuration error: {}: {}", filename, err))?;
+      let (migrated, changes) = config::migrate(&source, &filename)?;
+      if dry_run {
+        print!("{}", migrated);
+      } else if !changes.is_empty() {
+        Opt::write_to_file(&filename, migrated.as_bytes())?;
+      }
+      if changes.is_empty() {
+        eprintln!("{}: No deprecated options", filename);
+      }
+      for change in changes {
+        eprintln!("{}", change);
+      }
+    }
+    None => eprintln!("No {} file found", config::CONFIG_FILENAME),
+  }
+  eprintln!();
+  eprintln!("Deprecated command line flags:");
+  for deprecation in config::DEPRECATED_FLAGS {
+    eprintln!(
+      "    {} is now {}",
+      deprecation.flag, deprecation.replacement
+    );
+  }
+  Ok(())
+}
+
+/// Formats the members of the archive at `path` whose names match the `patterns` (or every member
+/// with an extension in the file-type registry, if no patterns are given), and rewrites the
+/// archive in place.
+fn format_archive(path: &Path, patterns: &[PathBuf], task: &Task<'_>) -> Result<()> {
+  let patterns = match patterns.is_empty() {
+    true => file_types::registry(task.config)
+      .iter()
+      .map(|file_type| format!("*{}", file_type.extension).parse::<PathGlob>())
+      .collect::<Result<Vec<_>>>()?,
+    false => patterns
+      .iter()
+      .map(|pattern| pattern.to_string_lossy().parse::<PathGlob>())
+      .collect::<Result<Vec<_>>>()?,
+  };
+  let changed = archive::format_archive(path, &patterns, |name, buffer| {
+    format_buffer(buffer, name.to_string(), task)
+  })?;
+  eprintln!("{}: {} member(s) changed", path.display(), changed);
+  Ok(())
+}
+
+/// The entry point for the [formatjson5](index.html) command line interface.
+fn main() -> Result<()> {
+  parallel::with_stack(|| {
+    if let Some(startup) = worker::startup_arguments() {
+      return worker::run(startup, |arguments| {
+        let (global, command) = Opt::args_from(arguments)?;
+        execute(global, command)
+      });
+    }
+    let (global, command) = Opt::args()?;
+    let error_format = global.error_format;
+    execute(global, command).inspect_err(|err| {
+      // The error is printed for people when main returns; annotate it for GitHub as well.
+      if error_format == ErrorFormat::Github {
+        eprintln!("{}", error_format.error(err, None));
+      }
+    })
+  })
+}
+
+/// Runs the subcommand `command` with the `global` options.
+fn execute(global: GlobalOpt, command: Command) -> Result<()> {
+  crash::install(global.unredacted_crash_report);
 
-#![warn(missing_docs)]
+  match command {
+    Command::MigrateConfig { config, dry_run } => migrate_config(config.as_deref(), dry_run),
+    Command::Explain { code } => explain(code.as_deref()),
+    Command::Rules { json } => {
+      match json {
+        true => print!("{}", catalog::json().to_pretty_string()),
+        false => print!("{}", catalog::human()),
+      }
+      Ok(())
+    }
+    Command::SelfUpdate { check } => self_update::self_update(check),
+    Command::Format(mut options) => {
+      let context = Context::new(global, options.task.config_overrides())?;
+      if let Some(path) = &options.explain_match {
+        return context.explain_match(path);
+      }
+      // With --archive, the files are patterns of the archive's members.
+      if options.archive.is_none() && !options.files.is_empty() {
+        options.files = context.select(&options.files)?;
+        if options.files.is_empty() {
+          return Ok(());
+        }
+      }
+      format(&context, &options)
+    }
+    Command::Check {
+      files,
+      task,
+      baseline,
+      update_baseline,
+    } => {
+      let context = Context::new(global, task.config_overrides())?;
+      require_files(&files, "check")?;
+      let files = context.select(&files)?;
+      context.audit(&files, OutputOrder::Input)?;
+      if update_baseline && baseline.is_none() {
+        return Err(anyhow::anyhow!(
+          "--update-baseline requires --baseline <file>"
+        ));
+      }
+      run_task(&context, &task, |task| {
+        check_files(
+          &files,
+          &task,
+          context.threads(),
+          baseline.as_deref(),
+          update_baseline,
+          context.check_rendering().as_ref(),
+        )
+      })
+    }
+    Command::Lint { files, task } => {
+      let context = Context::new(global, task.config_overrides())?;
+      require_files(&files, "lint")?;
+      let files = context.select(&files)?;
+      context.audit(&files, OutputOrder::Input)?;
+      run_task(&context, &task, |task| {
+        lint_files(&files, &task, context.threads())
+      })
+    }
+    Command::CompareOptions {
+      files,
+      base,
+      candidate,
+      task,
+    } => {
+      let context = Context::new(global, task.config_overrides())?;
+      require_files(&files, "compare")?;
+      let files = context.select(&files)?;
+      let base = context.with_options(base.as_deref().unwrap_or_default(), "--base")?;
+      let candidate = context.with_options(&candidate, "--candidate")?;
+      context.audit(&files, OutputOrder::Input)?;
+      run_task(&context, &task, |task| {
+        compare_files(
+          &files,
+          &task,
+          &base,
+          &candidate,
+          context.threads(),
+          &context.rendering(),
+        )
+      })
+    }
+    Command::Diff { files, task } => {
+      let context = Context::new(global, task.config_overrides())?;
+      require_files(&files, "compare")?;
+      let files = context.select(&files)?;
+      context.audit(&files, OutputOrder::Input)?;
+      run_task(&context, &task, |task| {
+        diff_files(&files, &task, context.threads(), &context.rendering())
+      })
+    }
+    Command::Edit {
+      files,
+      task,
+      no_lock,
+    } => {
+      let context = Context::new(global, task.config_overrides())?;
+      require_files(&files, "edit")?;
+      let files = context.select(&files)?;
+      if task.transform.is_empty() && task.redact.is_empty() {
+        return Err(anyhow::anyhow!(
+          "Nothing to edit: give at least one --transform or --redact"
+        ));
+      }
+      context.audit(&files, OutputOrder::Input)?;
+      run_task(&context, &task, |task| {
+        replace_files(&context, &files, &task, no_lock)
+      })
+    }
+    Command::Fix {
+      files,
+      task,
+      fix_level,
+      no_lock,
+    } => {
+      let context = Context::new(global, task.config_overrides())?;
+      require_files(&files, "repair")?;
+      let files = context.select(&files)?;
+      context.audit(&files, OutputOrder::Input)?;
+      run_task(&context, &task, |task| {
+        let task = Task {
+          fix: fix_level,
+          fix_lint: true,
+          ..task
+        };
+        replace_files(&context, &files, &task, no_lock)
+      })
+    }
+    Command::Serve {
+      task: options,
+      port,
+      host,
+      no_ui,
+    } => {
+      let context = Context::new(global, options.config_overrides())?;
+      context.audit(&[], OutputOrder::Input)?;
+      let known_keys = match &options.known_keys {
+        Some(path) => Some(KnownKeys::load(path)?),
+        None => None,
+      };
+      let address = format!("{}:{}", host, port);
+      serve::serve(&address, !no_ui, &context.config, |document, config| {
+        let task = Task {
+          config,
+          ..Task::new(&context, &options, known_keys.as_ref())
+        };
+        format_buffer(document, "request".to_string(), &task)
+      })
+    }
+    Command::Convert {
+      files,
+      to,
+      out_dir,
+      verify_roundtrip,
+    } => {
+      if verify_roundtrip && to != Target::Json {
+        return Err(anyhow::anyhow!(
+          "--verify-roundtrip can only be used with --to json"
+        ));
+      }
+      convert(global, &files, to, out_dir.as_deref(), verify_roundtrip)
+    }
+    Command::MergeDriver {
+      base,
+      ours,
+      theirs,
+      path,
+      task,
+    } => {
+      let context = Context::new(global, task.config_overrides())?;
+      let path = path.unwrap_or_else(|| ours.clone());
+      run_task(&context, &task, |task| {
+        merge_driver(&base, &ours, &theirs, &path, &task)
+      })
+    }
+    Command::Stats { files } => stats(global, &files),
+    Command::Textconv { file } => textconv(&file),
+    Command::ToJson {
+      files,
+      out_dir,
+      verify_roundtrip,
+    } => convert(
+      global,
+      &files,
+      Target::Json,
+      out_dir.as_deref(),
+      verify_roundtrip,
+    ),
+    Command::ToYaml { files, out_dir } => {
+      convert(global, &files, Target::Yaml, out_dir.as_deref(), false)
+    }
+  }
+}
 
-use anyhow::{self, Result};
-use json5format::*;
-use std::{
-  fs, io,
-  io::{Read, Write},
-  path::PathBuf,
-};
-use structopt::StructOpt;
+/// Returns an error if no `files` were given to the subcommand that would `verb` them.
+fn require_files(files: &[PathBuf], verb: &str) -> Result<()> {
+  match files.is_empty() {
+    true => Err(anyhow::anyhow!("No files to {}", verb)),
+    false => Ok(()),
+  }
+}
 
-/// Parses each file in the given `files` vector and returns a parsed object for each JSON5
-/// document. If the parser encounters an error in any input file, the command aborts without
-/// formatting any of the documents.
-fn parse_documents(
-  files: Vec<PathBuf>,
-) -> Result<Vec<ParsedDocument>, anyhow::Error> {
-  let mut parsed_documents = Vec::with_capacity(files.len());
+/// Runs `run` with the task directed by the command line `options` and the configuration of
+/// `context`, which records the timings, report, and findings that `options` ask for, and then
+/// prints the timings and the report.
+fn run_task(
+  context: &Context,
+  options: &TaskOpt,
+  run: impl FnOnce(Task<'_>) -> Result<()>,
+) -> Result<()> {
+  let known_keys = match &options.known_keys {
+    Some(path) => Some(KnownKeys::load(path)?),
+    None => None,
+  };
+  let timings = options.timings.then(Timings::default);
+  let report = options.report.map(|_| Report::default());
+  let findings = Findings::default();
+  let result = run(Task {
+    timings: timings.as_ref(),
+    report: report.as_ref(),
+    findings: Some(&findings),
+    ..Task::new(context, options, known_keys.as_ref())
+  });
+  if let Some(timings) = &timings {
+    eprint!("{}", timings.report(options.timings_slowest));
+  }
+  if let Some(report) = &report {
+    let text = report.render(options.report.flatten().unwrap_or_default());
+    match (options.report, &options.report_file) {
+      (Some(None | Some(ReportFormat::Summary)), _) => eprint!("{}", text),
+      (_, Some(path)) => fs::write(paths::extended(path), text)
+        .map_err(|err| anyhow::anyhow!("Cannot write {}: {}", path.display(), err))?,
+      (_, None) => print!("{}", text),
+    }
+  }
+  result
+}
+
+/// Runs the `format` subcommand (or the command without a subcommand) with the given `options`.
+fn format(context: &Context, options: &FormatOpt) -> Result<()> {
+  let files = &options.files;
+  if options.stdin_framing.is_some() && !files.is_empty() {
+    return Err(anyhow::anyhow!(
+      "--stdin-framing reads from stdin, so no files may be given"
+    ));
+  }
+  if options.multi_doc_separator.is_some() && options.stdin_framing.is_some() {
+    return Err(anyhow::anyhow!(
+      "--stdin-framing writes each document in its own framing, so it cannot be used with \
+       --multi-doc-separator"
+    ));
+  }
+  if options.archive.is_some()
+    && (options.replace
+      || options.stdin_framing.is_some()
+      || options.output.is_some()
+      || options.emit != Emit::Formatted)
+  {
+    return Err(anyhow::anyhow!(
+      "--archive rewrites the archive in place, so it cannot be used with --replace, \
+       --stdin-framing, --output, or --emit"
+    ));
+  }
+  if files.is_empty() && options.stdin_framing.is_none() && options.archive.is_none() {
+    return Err(anyhow::anyhow!("No files to format"));
+  }
+  context.audit(files, options.output_order)?;
+  run_task(context, &options.task, |task| {
+    let task = Task {
+      emit: options.emit,
+      safe_replace: options.safe_replace,
+      ..task
+    };
+    run(context, options, &task)
+  })
+}
+
+/// Runs the `to-json`, `to-yaml`, or `convert` subcommand with the `global` options: converts the
+/// `files` to `target` (see [convert_files()](fn.convert_files.html)).
+fn convert(
+  global: GlobalOpt,
+  files: &[PathBuf],
+  target: Target,
+  out_dir: Option<&Path>,
+  verify: bool,
+) -> Result<()> {
+  let context = Context::new(global, Config::default())?;
+  require_files(files, "convert")?;
+  context.audit(files, OutputOrder::Input)?;
+  convert_files(target, files, out_dir, verify, &context.config)
+}
+
+/// Runs the `stats` subcommand: prints the statistics of the structure of each of the `files`.
+fn stats(global: GlobalOpt, files: &[PathBuf]) -> Result<()> {
+  let context = Context::new(global, Config::default())?;
+  require_files(files, "report on")?;
+  context.audit(files, OutputOrder::Input)?;
+  let source_options = context.config.source_options();
   for file in files {
-    let filename = file.clone().into_os_string().to_string_lossy().to_string();
+    let filename = file.to_string_lossy().to_string();
     let mut buffer = String::new();
     if filename == "-" {
       Opt::from_stdin(&mut buffer)?;
+    } else if remote::is_url(&filename) {
+      buffer = remote::fetch(&filename)?;
     } else {
-      fs::File::open(&file)?.read_to_string(&mut buffer)?;
+      buffer = fs::read_to_string(paths::extended(file))?;
     }
+    // Blank out the preamble, so the line numbers in messages match the file, and drop the rest of
+    // the file after its front matter.
+    buffer.truncate(document::body_end(&buffer, &source_options));
+    let preamble_len = document::preamble_len(&buffer, &source_options);
+    let preamble = buffer[..preamble_len].replace(|c| c != '\n', " ");
+    buffer.replace_range(..preamble_len, &preamble);
+    let root = syntax::parse(&buffer)
+      .map_err(|err| anyhow::anyhow!("Syntax error: {}:{}", filename, err))?
+      .ok_or_else(|| anyhow::anyhow!("Stats error: {}: The document holds no value", filename))?;
+    print!(
+      "{}",
+      Stats::new(&root, &buffer, preamble_len).report(&filename)
+    );
+  }
+  Ok(())
+}
 
-    parsed_documents.push(ParsedDocument::from_string(buffer, Some(filename))?);
+/// Runs the `merge-driver` subcommand: replaces the file `ours` with its merge with `theirs`,
+/// whose common ancestor is `base` (see the [merge](merge/index.html) module), and formats the
+/// result as directed by `task`, with the options for the repository file `path`, unless it has
+/// conflicts. Fails if the merge has conflicts, as git expects.
+fn merge_driver(
+  base: &Path,
+  ours: &Path,
+  theirs: &Path,
+  path: &Path,
+  task: &Task<'_>,
+) -> Result<()> {
+  let read = |file: &Path| {
+    fs::read_to_string(paths::extended(file))
+      .map_err(|err| anyhow::anyhow!("Merge error: {}: {}", file.display(), err))
+  };
+  let merged = merge::merge(&read(base)?, &read(ours)?, &read(theirs)?);
+  let filename = path.to_string_lossy().to_string();
+  if merged.conflicts > 0 {
+    fs::write(paths::extended(ours), &merged.text)?;
+    return Err(anyhow::anyhow!(
+      "Merge conflict: {}: {} conflict(s), marked in the file",
+      filename,
+      merged.conflicts
+    ));
   }
-  Ok(parsed_documents)
+  let bytes = match format_buffer(merged.text.clone(), filename.clone(), task) {
+    Ok(bytes) => bytes,
+    Err(err) => {
+      eprintln!(
+        "Warning: {}: The merged document is not formatted: {}",
+        filename, err
+      );
+      merged.text.into_bytes()
+    }
+  };
+  fs::write(paths::extended(ours), bytes)?;
+  Ok(())
 }
 
-/// Formats the given parsed documents, applying the given format `options`. If `replace` is true,
-/// each input file is overwritten by its formatted version.
-fn format_documents(
-  parsed_documents: Vec<ParsedDocument>,
-  options: FormatOptions,
-  replace: bool,
-) -> Result<(), anyhow::Error> {
-  let format = Json5Format::with_options(options)?;
-  for (index, parsed_document) in parsed_documents.iter().enumerate() {
-    let filename = parsed_document.filename().as_ref().unwrap();
-    let bytes = format.to_utf8(&parsed_document)?;
-    if replace {
-      Opt::write_to_file(filename, &bytes)?;
-    } else {
-      if index > 0 {
-        println!();
-      }
-      if parsed_documents.len() > 1 {
-        println!("{}:", filename);
-        println!("{}", "=".repeat(filename.len()));
-      }
-      print!("{}", std::str::from_utf8(&bytes)?);
+/// Runs the `textconv` subcommand: prints the canonical formatting of the `file` (see the
+/// [textconv](textconv/index.html) module). A file that is not UTF-8 is printed unchanged.
+fn textconv(file: &Path) -> Result<()> {
+  let bytes = match file.to_str() {
+    Some("-") => {
+      let mut bytes = Vec::new();
+      io::stdin().read_to_end(&mut bytes)?;
+      bytes
     }
-  }
+    _ => fs::read(paths::extended(file))?,
+  };
+  let bytes = match String::from_utf8(bytes) {
+    Ok(buffer) => textconv::canonical(buffer, &file.to_string_lossy()),
+    Err(err) => err.into_bytes(),
+  };
+  io::stdout().lock().write_all(&bytes)?;
   Ok(())
 }
 
-/// The entry point for the [formatjson5](index.html) command line interface.
-fn main() -> Result<()> {
-  let args = Opt::args();
+/// Formats the documents given by the `format` subcommand's `options` (the files and whether to
+/// replace them, or the documents from stdin or an archive) as directed by `task`.
+fn run(context: &Context, options: &FormatOpt, task: &Task<'_>) -> Result<()> {
+  let (files, replace) = (&options.files, options.replace);
+  if let Some(framing) = options.stdin_framing {
+    return format_frames(framing, task);
+  }
+  if let Some(path) = &options.archive {
+    let _lock = match options.no_lock {
+      true => None,
+      false => lock::lock(path)?,
+    };
+    return format_archive(path, files, task);
+  }
 
-  if args.files.len() == 0 {
-    return Err(anyhow::anyhow!("No files to format"));
+  if options.check || options.update_baseline {
+    if replace || options.output.is_some() || options.emit != Emit::Formatted {
+      return Err(anyhow::anyhow!(
+        "--check only reports problems, so it cannot be used with --replace, --output, or --emit"
+      ));
+    }
+    if options.update_baseline && options.baseline.is_none() {
+      return Err(anyhow::anyhow!(
+        "--update-baseline requires --baseline <file>"
+      ));
+    }
+    return check_files(
+      files,
+      task,
+      context.threads(),
+      options.baseline.as_deref(),
+      options.update_baseline,
+      context.check_rendering().as_ref(),
+    );
   }
 
-  let parsed_documents = parse_documents(args.files)?;
+  if options.snapshot || options.check_snapshots {
+    if replace || options.output.is_some() || options.check {
+      return Err(anyhow::anyhow!(
+        "--snapshot and --check-snapshots cannot be used with --replace, --output, or --check"
+      ));
+    }
+    if let Some(file) = files.iter().find(|file| {
+      let filename = file.to_string_lossy();
+      filename == "-" || remote::is_url(&filename)
+    }) {
+      return Err(anyhow::anyhow!(
+        "{}: Only local files can have snapshots",
+        file.display()
+      ));
+    }
+    return snapshot_files(files, task, context.threads(), options.check_snapshots);
+  }
 
-  let options = FormatOptions {
-    indent_by: args.indent,
-    trailing_commas: !args.no_trailing_commas,
-    collapse_containers_of_one: args.one_element_lines,
-    sort_array_items: args.sort_arrays,
-    ..Default::default()
+  if options.safe_replace && !replace {
+    return Err(anyhow::anyhow!(
+      "--safe-replace only checks files being replaced, so it requires --replace"
+    ));
+  }
+  if replace
+    && files
+      .iter()
+      .any(|file| remote::is_url(&file.to_string_lossy()))
+  {
+    return Err(anyhow::anyhow!(
+      "A URL cannot be replaced; use --output to save the formatted document"
+    ));
+  }
+  if options.emit != Emit::Formatted && replace {
+    return Err(anyhow::anyhow!("--emit cannot replace the input files"));
+  }
+  if options.changed_only && (replace || options.output.is_some()) {
+    return Err(anyhow::anyhow!(
+      "--changed-only leaves out the unchanged documents written to stdout, so it cannot be used \
+       with --replace or --output"
+    ));
+  }
+  if options.flush_per_document && (replace || options.output.is_some()) {
+    return Err(anyhow::anyhow!(
+      "--flush-per-document writes to stdout, so it cannot be used with --replace or --output"
+    ));
+  }
+  if options.output.is_some() && (replace || files.len() > 1) {
+    return Err(anyhow::anyhow!(
+      "--output can only be used with a single file, and not with --replace"
+    ));
+  }
+  let mut output = Output {
+    replace,
+    force_overwrite: options.force_overwrite,
+    lock: !options.no_lock,
+    write_mode: write_mode(&context.config),
+    path: options.output.clone(),
+    separator: options.multi_doc_separator.unwrap_or(
+      match (
+        files.len() > 1,
+        options.banners.enabled(context.terminal.stdout_is_tty),
+      ) {
+        (true, true) => Separator::Banner,
+        (true, false) => Separator::BlankLine,
+        (false, _) => Separator::None,
+      },
+    ),
+    flush: options.flush_per_document,
+    changed_only: options.changed_only,
+    written: 0,
   };
-
-  format_documents(parsed_documents, options, args.replace)
+  format_files(
+    files,
+    task,
+    context.threads(),
+    options.output_order,
+    &mut output,
+  )
 }
 
 /// Command line options defined via the structopt! macrorule. These definitions generate the
-/// option parsing, validation, and [usage documentation](index.html).
+/// option parsing, validation, and [usage documentation](index.html). Without a subcommand, the
+/// files are formatted, as with the `format` subcommand.
 #[derive(Debug, StructOpt)]
 #[structopt(
   name = "json5format",
-  about = "Format JSON5 documents to a consistent style, preserving comments."
+  about = "Format JSON5 documents to a consistent style, preserving comments.",
+  global_settings = &[AppSettings::DisableHelpSubcommand],
+  // Only an argument that is exactly the name of a subcommand runs it: any other is a file, even
+  // if it looks like a misspelled subcommand name (such as `check.json5`). (No external
+  // subcommand is ever run, because the files take every other argument.)
+  settings = &[AppSettings::AllowExternalSubcommands]
 )]
 struct Opt {
-  /// Files to format (use "-" for stdin)
-  #[structopt(parse(from_os_str))]
-  files: Vec<PathBuf>,
+  /// A subcommand to run instead of formatting files.
+  #[structopt(subcommand)]
+  command: Option<Command>,
 
-  /// Replace (overwrite) the input file with the formatted result
-  #[structopt(short, long)]
-  replace: bool,
+  /// The options shared by every subcommand.
+  #[structopt(flatten)]
+  global: GlobalOpt,
+
+  /// The options for formatting files without a subcommand.
+  #[structopt(flatten)]
+  format: FormatOpt,
+}
+
+/// The names of the [global options](struct.GlobalOpt.html), which may be given before or after a
+/// subcommand.
+const GLOBAL_OPTIONS: &[&str] = &[
+  "assume-tty",
+  "color",
+  "config",
+  "config-override",
+  "deterministic",
+  "diff-context",
+  "diff-style",
+  "error-format",
+  "hermetic",
+  "ignore-required-version",
+  "jobs",
+  "no-config",
+  "options-json",
+  "unredacted-crash-report",
+  "verbose",
+  "word-diff",
+];
+
+// The options shared by every subcommand, which are parsed once, and may be given before or after
+// the subcommand. (These are not doc comments, because structopt would use them as the `about`
+// text of each command the options are flattened into.)
+#[derive(Debug, StructOpt)]
+struct GlobalOpt {
+  /// Read options from the given configuration file, instead of the file named by
+  /// $JSON5FMT_CONFIG or the nearest `.json5fmt.json5` in the current directory or its ancestors
+  #[structopt(long, parse(from_os_str), global = true)]
+  config: Option<PathBuf>,
+
+  /// Read no configuration file: neither the file named by $JSON5FMT_CONFIG nor the nearest
+  /// `.json5fmt.json5` (the environment variables and the other options still apply)
+  #[structopt(long, conflicts_with = "config", global = true)]
+  no_config: bool,
+
+  /// Set an option of the configuration file, as `<option>=<value>` (such as
+  /// `trailing_commas=false`), over the configuration file, the environment, and --options-json
+  /// (may be repeated; the other options on the command line take precedence)
+  #[structopt(long, value_name = "option=value", number_of_values = 1, global = true)]
+  config_override: Vec<String>,
+
+  /// Set options from a JSON5 object with the options of the configuration file, such as
+  /// `{indent: 2, sort_keys: true}`, over those of the configuration file and the environment (the
+  /// other options on the command line take precedence)
+  #[structopt(long, value_name = "json5", global = true)]
+  options_json: Option<String>,
+
+  /// Format files in parallel on the given number of threads (0 uses one thread per CPU), and split
+  /// each very large document into chunks formatted in parallel
+  #[structopt(short, long, default_value = "1", global = true)]
+  jobs: usize,
+
+  /// Color the output for a terminal (such as the diffs of the `diff` subcommand): auto (only if
+  /// stdout is a terminal), always, or never
+  #[structopt(long, value_name = "when", default_value = "auto", possible_values = When::VARIANTS, global = true)]
+  color: When,
+
+  /// How to lay out the diffs printed: unified (the changed lines one after the other) or
+  /// side-by-side (each old line beside the line that replaces it, with line numbers); `check`
+  /// prints a diff of each file that is not formatted if this, --diff-context, or --word-diff is
+  /// given [default: unified]
+  #[structopt(long, value_name = "style", possible_values = DiffStyle::VARIANTS, global = true)]
+  diff_style: Option<DiffStyle>,
+
+  /// The number of unchanged lines shown around each change in the diffs printed [default: 3]
+  #[structopt(long, value_name = "lines", global = true)]
+  diff_context: Option<usize>,
+
+  /// Highlight the words that changed within each changed line of the diffs printed
+  #[structopt(long, global = true)]
+  word_diff: bool,
 
+  /// How to report errors, check failures, and diagnostics on stderr: human (messages with the
+  /// source line of each problem), or github (GitHub Actions workflow commands, which annotate the
+  /// lines of a pull request)
+  #[structopt(long, value_name = "format", default_value = "human", possible_values = ErrorFormat::VARIANTS, global = true)]
+  error_format: ErrorFormat,
+
+  /// Report details of how each document is formatted (such as the detected input format) on
+  /// stderr
+  #[structopt(long, global = true)]
+  verbose: bool,
+
+  /// Behave as if stdout is a terminal, even when it is redirected to a file or pipe
+  #[structopt(long, global = true)]
+  assume_tty: bool,
+
+  /// Fail before formatting if the output could depend on more than the files and the
+  /// configuration file: on environment variables that set options, plugin commands, documents
+  /// fetched from URLs, or the order documents finish formatting in (for CI)
+  #[structopt(long, global = true)]
+  deterministic: bool,
+
+  /// Ignore the environment: read no configuration file other than the one given with --config,
+  /// ignore the environment variables that set options, and treat stdout as not a terminal (unless
+  /// --assume-tty is given), so the output is the same in any build sandbox
+  #[structopt(long, global = true)]
+  hermetic: bool,
+
+  /// Run even if the formatter's version does not satisfy the `required_version` of the
+  /// configuration, with a warning
+  #[structopt(long, global = true)]
+  ignore_required_version: bool,
+
+  /// Keep the strings of the document being formatted in the bug report written if the formatter
+  /// crashes, instead of replacing them with a placeholder
+  #[structopt(long, global = true)]
+  unredacted_crash_report: bool,
+}
+
+// The options that direct how each document is formatted and checked, for the subcommands that
+// format documents.
+#[derive(Debug, StructOpt)]
+struct TaskOpt {
   /// Suppress trailing commas (otherwise added by default)
-  #[structopt(short, long)]
+  #[structopt(short, long, alias = "no_trailing_commas")]
   no_trailing_commas: bool,
 
   /// Objects or arrays with a single child should collapse to a single line; no trailing comma
-  #[structopt(short, long)]
+  #[structopt(short, long, alias = "one_element_lines")]
   one_element_lines: bool,
 
   /// Sort arrays of primitive values (string, number, boolean, or null) lexicographically
-  #[structopt(short, long)]
+  #[structopt(short, long, alias = "sort_arrays")]
   sort_arrays: bool,
 
-  /// Indent by the given number of spaces
-  #[structopt(short, long, default_value = "4")]
-  indent: usize,
+  /// Sort the properties of each object by name
+  #[structopt(long)]
+  sort_keys: bool,
+
+  /// Indent by the given number of spaces, or keep the indentation (2 or 4 spaces, or tabs) of
+  /// each file with `auto` (default: 4)
+  #[structopt(short, long)]
+  indent: Option<Indent>,
+
+  /// Preserve any content after the end of the document verbatim, instead of reporting it as an
+  /// error
+  #[structopt(long)]
+  allow_trailing_garbage: bool,
+
+  /// Pass the given number of leading lines through unchanged, without parsing them as JSON5 (by
+  /// default, only a leading `#!` line is passed through)
+  #[structopt(long, value_name = "lines")]
+  preserve_preamble_lines: Option<usize>,
+
+  /// Format the JSON5 blocks inside files of another kind, as selected by a rule of the form
+  /// `<extension>:<open>...<close>` (for example,
+  /// `html:<script type="application/json5">...</script>`; may be repeated)
+  #[structopt(long, value_name = "rule", number_of_values = 1)]
+  embedded: Vec<IslandRule>,
+
+  /// Format only the JSON5 front matter of Markdown files, between a first line of `---` (or
+  /// `---json5`) and the next line of `---`, and leave the rest of each file unchanged (the default
+  /// for `.md` files)
+  #[structopt(long)]
+  front_matter: bool,
+
+  /// The JSON dialect of the input and output: json5, jsonc (JSON with comments and trailing
+  /// commas), or json; input that uses an extension the dialect does not allow is an error
+  /// [default: json5, or the dialect of the file's type]
+  #[structopt(long, value_name = "dialect", possible_values = Dialect::VARIANTS)]
+  dialect: Option<Dialect>,
+
+  /// How to treat the input: json5, json (require strict JSON, and write strict JSON), or auto
+  /// (treat the input as strict JSON unless it uses a JSON5 extension, such as a comment or a
+  /// trailing comma)
+  #[structopt(long, value_name = "format", default_value = "json5", possible_values = InputFormat::VARIANTS)]
+  input_format: InputFormat,
+
+  /// Apply the automatic fixes of lint checks (renaming property names to --key-case), instead of
+  /// reporting the problems
+  #[structopt(long)]
+  fix: bool,
+
+  /// Report `Infinity` and `NaN` as errors
+  #[structopt(long)]
+  forbid_non_finite: bool,
+
+  /// Report warnings as errors, which make formatting each document fail (like --deny for every
+  /// code)
+  #[structopt(long)]
+  deny_warnings: bool,
+
+  /// Report the warnings of the given lint rule or diagnostic code (such as `no-empty-object` or
+  /// `J5F008`) as errors, which make formatting each document fail (may be repeated)
+  #[structopt(long, value_name = "code", number_of_values = 1, parse(try_from_str = codes::find))]
+  deny: Vec<&'static Code>,
+
+  /// Resolve each unresolved merge conflict by keeping our side (ours) or their side (theirs) of
+  /// it, and format the result, instead of failing on the conflicts
+  #[structopt(long, value_name = "side", possible_values = Take::VARIANTS)]
+  take: Option<Take>,
+
+  /// How --replace writes each file: direct (truncate it and write it in place), atomic (write a
+  /// temporary file next to it and rename it over the file), or copy (write a temporary file and
+  /// copy it over the file, where renaming is not allowed) [default: direct]
+  #[structopt(long, value_name = "mode", possible_values = WriteMode::VARIANTS)]
+  write_mode: Option<WriteMode>,
+
+  /// With --sort-arrays, keep arrays whose order looks significant (mixed types, or items
+  /// mentioning `first` or `fallback`) in source order, and warn about them
+  #[structopt(long)]
+  sort_warn_heuristics: bool,
+
+  /// Warn about property names that are similar to, but not the same as, a name in the given file:
+  /// a JSON5 array of names, a JSON Schema, or one name per line
+  #[structopt(long, value_name = "file", parse(from_os_str))]
+  known_keys: Option<PathBuf>,
+
+  /// Check that property names follow a naming convention: camel (camelCase), snake
+  /// (snake_case), kebab (kebab-case), or preserve (any name); with --fix, rename them instead
+  #[structopt(long, value_name = "case", default_value = "preserve", possible_values = KeyCase::VARIANTS)]
+  key_case: KeyCase,
+
+  /// A property name that is exempt from --key-case, such as a name defined by an external format
+  /// (may be repeated)
+  #[structopt(long, value_name = "name", number_of_values = 1)]
+  key_case_exclude: Vec<String>,
+
+  /// How to write hexadecimal numbers: preserve (as written), decimal, lower (as in `0xff`), or
+  /// upper (as in `0xFF`)
+  #[structopt(long, value_name = "style", default_value = "preserve", possible_values = HexNumbers::VARIANTS)]
+  hex_numbers: HexNumbers,
+
+  /// How to write decimal numbers with a fraction or an exponent: preserve (exactly as written) or
+  /// shortest (the shortest text that parses to the same double, as in `1.5` for `1.50`)
+  #[structopt(long, value_name = "mode", default_value = "preserve", possible_values = NormalizeFloats::VARIANTS)]
+  normalize_floats: NormalizeFloats,
+
+  /// How to write the comments in empty objects and arrays: expand (each on a line of its own) or
+  /// inline (as in `{ /* todo */ }`, if they are all block comments) [default: expand]
+  #[structopt(long, value_name = "style", possible_values = EmptyComments::VARIANTS)]
+  empty_container_comments: Option<EmptyComments>,
+
+  /// Where to place the comments at the ends of lines: keep (after the value), move-above (on a
+  /// line of their own above the value), or align (to the same column on consecutive lines)
+  /// [default: keep]
+  #[structopt(long, value_name = "placement", possible_values = EolComments::VARIANTS)]
+  eol_comments: Option<EolComments>,
+
+  /// Which item a comment on a line of its own moves with when sorting reorders items: next (the
+  /// item after it), previous (the item before it), or blank-line-aware (the item it is not
+  /// separated from by a blank line) [default: next]
+  #[structopt(long, value_name = "attachment", possible_values = CommentAttachment::VARIANTS)]
+  comment_attachment: Option<CommentAttachment>,
+
+  /// The width, in characters, that formatted lines should fit in, for --long-value and
+  /// --array-wrap [default: 100]
+  #[structopt(long, value_name = "columns")]
+  max_width: Option<usize>,
+
+  /// What to do with a value (such as a long string or URL) that makes its line wider than
+  /// --max-width: allow, wrap-member (start the value on the line after its property name), or
+  /// warn [default: allow]
+  #[structopt(long, value_name = "policy", possible_values = LongValues::VARIANTS)]
+  long_value: Option<LongValues>,
+
+  /// How to wrap the items of arrays of primitive values across lines: vertical (one item per
+  /// line), fill (as many items per line as fit in --max-width), or fill-numbers (fill arrays of
+  /// numbers only) [default: vertical]
+  #[structopt(long, value_name = "style", possible_values = ArrayWrap::VARIANTS)]
+  array_wrap: Option<ArrayWrap>,
+
+  /// How to quote string values: preserve (as written), double, or single [default: preserve]
+  #[structopt(long, value_name = "style", possible_values = Quotes::VARIANTS)]
+  quotes: Option<Quotes>,
+
+  /// Fail on documents whose top-level value is not of the given type: object, array, or any
+  /// (the `require-root` lint rule at level `error`; any turns the rule off)
+  #[structopt(long, value_name = "kind", possible_values = RootKind::VARIANTS)]
+  require_root: Option<RootKind>,
+
+  /// Set the options of a style preset, unless they are set otherwise: prettier (2-space
+  /// indentation, no trailing commas, double quotes, and arrays of numbers filled to 80
+  /// characters, like Prettier) or fuchsia (the settings of Fuchsia's `cmc format`, with the
+  /// canonical property order of component manifests)
+  #[structopt(long, value_name = "preset", possible_values = Style::VARIANTS)]
+  style: Option<Style>,
+
+  /// Replace string values whose paths match the given pattern with "<redacted>" (for example,
+  /// `/credentials/*` or `**/password`; may be repeated)
+  #[structopt(long, value_name = "path-glob", number_of_values = 1)]
+  redact: Vec<PathGlob>,
+
+  /// Parse each string value whose path matches the given pattern as a JSON5 document, format it,
+  /// and write it back into the string (for example, `/policy` or `**/rules/*`; may be repeated)
+  #[structopt(long, value_name = "path-glob", number_of_values = 1)]
+  format_embedded: Vec<PathGlob>,
+
+  /// Replace each value whose path matches a pattern with the result of an expression, in the form
+  /// `<path-glob> = <expression>` (for example, `/version = bump_patch(.)` or `**/timeout = . * 2`;
+  /// may be repeated)
+  #[structopt(long, value_name = "transform", number_of_values = 1)]
+  transform: Vec<Transform>,
+
+  /// Keep the original text of each changed part of a document where only its line breaks and
+  /// spacing differ from the formatted document, and its lines are indented as formatted (to keep
+  /// `git blame` useful)
+  #[structopt(long)]
+  minimal_diff: bool,
+
+  /// Parse the formatted output of each document and compare its data with the input before
+  /// writing it, and fail (leaving the file unchanged) if any value, property, or comment differs
+  #[structopt(long)]
+  paranoid: bool,
+
+  /// After formatting, report the problems found: summary (the number of diagnostics of each
+  /// code that were reported, and that were silenced by suppression comments, on stderr), or, for
+  /// CI servers, checkstyle or junit (an XML document listing the problems of each file), or sarif
+  /// (a SARIF 2.1.0 log, for code scanning), written to the --report-file or stdout; the format
+  /// must be given as `--report=<format>` [default: summary]
+  #[structopt(long, value_name = "format", possible_values = ReportFormat::VARIANTS, require_equals = true)]
+  report: Option<Option<ReportFormat>>,
+
+  /// Write the checkstyle, junit, or sarif --report to the given file, instead of stdout
+  #[structopt(long, value_name = "path", parse(from_os_str))]
+  report_file: Option<PathBuf>,
+
+  /// Report each array whose items formatting reorders, and each duplicate array item or property
+  /// it removes, with their paths, on stderr
+  #[structopt(long)]
+  report_semantic_changes: bool,
+
+  /// Report how long each file takes to read, parse, format, and write on stderr, followed by the
+  /// slowest files
+  #[structopt(long)]
+  timings: bool,
+
+  /// The number of slowest files to list after --timings
+  #[structopt(long, value_name = "n", default_value = "10")]
+  timings_slowest: usize,
+}
+
+// The options of the `format` subcommand, which are also the options when no subcommand is given.
+#[derive(Debug, StructOpt)]
+struct FormatOpt {
+  /// Files or directories to format (use "-" for stdin, or an http:// or https:// URL, if built
+  /// with the `http` feature)
+  #[structopt(parse(from_os_str))]
+  files: Vec<PathBuf>,
+
+  /// How to format each document.
+  #[structopt(flatten)]
+  task: TaskOpt,
+
+  /// Print whether the `include` and `exclude` options select the given path for formatting, and
+  /// which pattern decided it, instead of formatting files
+  #[structopt(long, value_name = "path", parse(from_os_str))]
+  explain_match: Option<PathBuf>,
+
+  /// Replace (overwrite) the input file with the formatted result
+  #[structopt(short, long)]
+  replace: bool,
+
+  /// With --replace, overwrite files even if they changed on disk after they were read (by
+  /// default, such files are skipped with a warning)
+  #[structopt(long)]
+  force_overwrite: bool,
+
+  /// With --replace, replace each file only if its formatted document, as written (after any post
+  /// plugins), has the same data as the input: the same properties, values, and number of comments
+  #[structopt(long)]
+  safe_replace: bool,
+
+  /// Do not lock the files being replaced (by default, a run that replaces a file waits for any
+  /// other run replacing it to finish, and formats it again if that run changed it)
+  #[structopt(long)]
+  no_lock: bool,
+
+  /// Check that each file is formatted (and can be formatted without errors), instead of writing
+  /// the formatted documents, and fail if any file has problems
+  #[structopt(long)]
+  check: bool,
+
+  /// Write the formatted documents to stdout only for the files that formatting changes
+  #[structopt(long)]
+  changed_only: bool,
+
+  /// With --check, --update-baseline records the current problems of the files in this file, and
+  /// later checks fail only on problems that are not recorded in it
+  #[structopt(long, value_name = "file", parse(from_os_str))]
+  baseline: Option<PathBuf>,
+
+  /// Record the current problems of the files in the --baseline file, instead of failing on them
+  /// (implies --check)
+  #[structopt(long)]
+  update_baseline: bool,
+
+  /// Write the formatted output of each file `<file>` to the snapshot file `<file>.formatted`,
+  /// instead of to stdout
+  #[structopt(long)]
+  snapshot: bool,
+
+  /// Check that the formatted output of each file `<file>` is the same as its snapshot file
+  /// `<file>.formatted` (written by --snapshot), and fail if any differs
+  #[structopt(long)]
+  check_snapshots: bool,
+
+  /// When formatting multiple files to stdout, precede each document with a filename banner,
+  /// rather than separating the documents with a blank line: auto (only if stdout is a terminal),
+  /// always, or never (unless --multi-doc-separator is given)
+  #[structopt(long, value_name = "when", default_value = "auto", possible_values = When::VARIANTS)]
+  banners: When,
+
+  /// How to separate the documents written to stdout: banner (precede each with a filename
+  /// banner), blank-line (precede each but the first with a blank line), nul (follow each with a
+  /// NUL byte), record-jsonl (write each as a line of JSON, `{"file": ..., "content": ...}`), or
+  /// none [default: banner or blank-line with multiple files, as set by --banners, otherwise none]
+  #[structopt(long, value_name = "separator", possible_values = Separator::VARIANTS)]
+  multi_doc_separator: Option<Separator>,
+
+  /// The order to write formatted documents to stdout: input (buffered, in the order the files
+  /// were given) or completion (as soon as each document is formatted)
+  #[structopt(long, value_name = "order", default_value = "input", possible_values = OutputOrder::VARIANTS)]
+  output_order: OutputOrder,
+
+  /// Write each formatted document to stdout, and flush it, as soon as it and the documents before
+  /// it are formatted, instead of after all of them are (so the next command in a pipeline can
+  /// start on them); with --output-order completion, as soon as it is formatted
+  #[structopt(long)]
+  flush_per_document: bool,
+
+  /// Read multiple documents from stdin, each terminated by a NUL byte (nul) or preceded by its
+  /// length in bytes on a line of its own (len), and write each formatted document to stdout with
+  /// the same framing
+  #[structopt(long, value_name = "framing", possible_values = Framing::VARIANTS)]
+  stdin_framing: Option<Framing>,
+
+  /// What to write for each document: formatted (the formatted document), ast (the syntax tree as
+  /// JSON, with source spans and the comments attached to each value), or tokens (every token as
+  /// JSON, with its kind, text, and source span)
+  #[structopt(long, value_name = "what", default_value = "formatted", possible_values = Emit::VARIANTS)]
+  emit: Emit,
+
+  /// Write the formatted document to the given file, instead of stdout (with a single input file)
+  #[structopt(long, parse(from_os_str))]
+  output: Option<PathBuf>,
+
+  /// Format the members of the given tar or zip archive, and rewrite the archive in place; the
+  /// files given are then patterns for the member names to format, such as `configs/*.json5` (by
+  /// default, every member with an extension in the file-type registry, such as `.json5`)
+  #[structopt(long, value_name = "archive", parse(from_os_str))]
+  archive: Option<PathBuf>,
+}
+
+// Subcommands, each of which takes the files and options it applies to after its name. (This is
+// not a doc comment, because structopt would use it as the `about` text of the whole command.)
+#[derive(Debug, StructOpt)]
+enum Command {
+  /// Check that each file is formatted (and can be formatted without errors), and fail if any file
+  /// has problems (the same as `--check`)
+  Check {
+    /// Files to check (use "-" for stdin)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+
+    /// How to format each document.
+    #[structopt(flatten)]
+    task: TaskOpt,
+
+    /// With --update-baseline, records the current problems of the files in this file, and later
+    /// checks fail only on problems that are not recorded in it
+    #[structopt(long, value_name = "file", parse(from_os_str))]
+    baseline: Option<PathBuf>,
+
+    /// Record the current problems of the files in the --baseline file, instead of failing on them
+    #[structopt(long)]
+    update_baseline: bool,
+  },
+
+  /// Print a unified diff of the changes between each file formatted with the --base options and
+  /// formatted with the --candidate options, to see what a change of style would change
+  CompareOptions {
+    /// Files to format with both sets of options (use "-" for stdin)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+
+    /// How to format each document, with both sets of options.
+    #[structopt(flatten)]
+    task: TaskOpt,
+
+    /// The options to format with first, as space-separated `name=value` pairs (such as
+    /// `indent=2`), which override the other options (by default, none)
+    #[structopt(long, value_name = "options")]
+    base: Option<String>,
+
+    /// The options to compare with the --base options, as space-separated `name=value` pairs (such
+    /// as `sort_keys=true`), which override the other options
+    #[structopt(long, value_name = "options")]
+    candidate: String,
+  },
+
+  /// Convert files to JSON or YAML, dropping their comments, and print the results
+  Convert {
+    /// Files to convert (use "-" for stdin)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+
+    /// The format to convert to: json or yaml
+    #[structopt(long, value_name = "format", possible_values = Target::VARIANTS)]
+    to: Target,
+
+    /// Write each converted file under this directory, at its path relative to the current
+    /// directory with the extension of the format, instead of printing it
+    #[structopt(long, value_name = "dir", parse(from_os_str))]
+    out_dir: Option<PathBuf>,
+
+    /// With `--to json`, parse each converted document again and compare it with the original,
+    /// failing (without writing the document) if any data was lost, such as a comment
+    #[structopt(long)]
+    verify_roundtrip: bool,
+  },
+
+  /// Print a unified diff of the changes formatting would make to each file, without changing
+  /// the files
+  Diff {
+    /// Files to compare with their formatted documents (use "-" for stdin)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+
+    /// How to format each document.
+    #[structopt(flatten)]
+    task: TaskOpt,
+  },
+
+  /// Apply the --transform and --redact edits to each file, and replace it with the edited and
+  /// formatted result
+  Edit {
+    /// Files to edit (use "-" for stdin, and write the result to stdout)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+
+    /// How to edit and format each document.
+    #[structopt(flatten)]
+    task: TaskOpt,
+
+    /// Do not lock the files being replaced
+    #[structopt(long)]
+    no_lock: bool,
+  },
+
+  /// Print a detailed description of a diagnostic code (such as J5F003), with examples and how to
+  /// configure or silence it, or list every code
+  Explain {
+    /// The code to explain, or the name of its check (such as `max-depth`)
+    code: Option<String>,
+  },
+
+  /// Repair common mistakes that prevent files from parsing, and replace each file with its
+  /// repaired and formatted result
+  Fix {
+    /// Files to repair (use "-" for stdin, and write the result to stdout)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+
+    /// How to format each document.
+    #[structopt(flatten)]
+    task: TaskOpt,
+
+    /// The repairs to apply: safe (remove stray commas, insert missing commas between values on
+    /// separate lines, and with --dialect json, remove trailing commas) or all (also close an
+    /// unterminated string and unclosed objects and arrays at the end of the document)
+    #[structopt(long, value_name = "level", default_value = "safe", possible_values = FixLevel::VARIANTS)]
+    fix_level: FixLevel,
+
+    /// Do not lock the files being replaced
+    #[structopt(long)]
+    no_lock: bool,
+  },
+
+  /// Format files (the same as giving no subcommand)
+  Format(FormatOpt),
+
+  /// Report the lint diagnostics of each file, without writing the formatted documents, and fail
+  /// if any file has errors
+  Lint {
+    /// Files to lint (use "-" for stdin)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+
+    /// How to check each document.
+    #[structopt(flatten)]
+    task: TaskOpt,
+  },
+
+  /// Merge three versions of a file by their structure, for a git merge driver, and replace our
+  /// version with the result, formatted unless it has conflicts
+  MergeDriver {
+    /// The common ancestor of the two versions (git's `%O`)
+    #[structopt(parse(from_os_str))]
+    base: PathBuf,
+
+    /// Our version, which is replaced with the merged file (git's `%A`)
+    #[structopt(parse(from_os_str))]
+    ours: PathBuf,
+
+    /// Their version (git's `%B`)
+    #[structopt(parse(from_os_str))]
+    theirs: PathBuf,
+
+    /// The path of the file in the repository (git's `%P`), which selects the options for its
+    /// file type (by default, the path of our version)
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+
+    /// How to format the merged document.
+    #[structopt(flatten)]
+    task: TaskOpt,
+  },
+
+  /// Rewrite deprecated options in a configuration file to their current names, and print the
+  /// current spelling of each deprecated command line flag
+  MigrateConfig {
+    /// The configuration file to migrate (by default, the file that is used when formatting files
+    /// in the current directory)
+    #[structopt(name = "file", value_name = "config", parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// Print the migrated configuration to stdout, instead of rewriting the file
+    #[structopt(long)]
+    dry_run: bool,
+  },
+
+  /// List the lint rules and the options of the configuration file, with their identifiers,
+  /// defaults, and configuration keys
+  Rules {
+    /// Print the list as JSON, for generating configuration UIs and documentation
+    #[structopt(long)]
+    json: bool,
+  },
+
+  /// Replace this executable with the newest release, after checking the download against the
+  /// release's SHA-256 checksum (which detects a damaged download, not a tampered release, as the
+  /// release is not signed), if built with the `self-update` feature
+  SelfUpdate {
+    /// Only report whether a newer release is available
+    #[structopt(long)]
+    check: bool,
+  },
+
+  /// Serve an HTTP endpoint that formats documents: `POST /format` formats the request body, with
+  /// options as query parameters (such as `?indent=2&sort_keys=true`), and `GET /` serves a
+  /// single-page UI
+  Serve {
+    /// How to format each document, before the options of the request.
+    #[structopt(flatten)]
+    task: TaskOpt,
+
+    /// The port to listen on
+    #[structopt(long, default_value = "8080")]
+    port: u16,
+
+    /// The address to listen on (use 0.0.0.0 to accept connections from other hosts)
+    #[structopt(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Serve only the `/format` endpoint, without the single-page UI
+    #[structopt(long)]
+    no_ui: bool,
+  },
+
+  /// Print statistics about the structure of each file: its depth, the number of objects, arrays,
+  /// properties, comments, and values of each type, its largest arrays, and its duplicate property
+  /// names
+  Stats {
+    /// Files to report on (use "-" for stdin)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+  },
+
+  /// Print a canonical formatting of a file, independent of the configuration, for a git diff
+  /// driver's `textconv` command, so diffs show changes to data and comments rather than to
+  /// formatting
+  Textconv {
+    /// The file to print (use "-" for stdin)
+    #[structopt(parse(from_os_str))]
+    file: PathBuf,
+  },
+
+  /// Convert files to JSON, dropping their comments, and print the results (the same as
+  /// `convert --to json`)
+  ToJson {
+    /// Files to convert (use "-" for stdin)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+
+    /// Write each converted file under this directory, at its path relative to the current
+    /// directory with a `.json` extension, instead of printing it
+    #[structopt(long, value_name = "dir", parse(from_os_str))]
+    out_dir: Option<PathBuf>,
+
+    /// Parse each converted document again and compare it with the original, failing (without
+    /// writing the document) if any data was lost, such as a comment
+    #[structopt(long)]
+    verify_roundtrip: bool,
+  },
+
+  /// Convert files to YAML, dropping their comments, and print the results (the same as
+  /// `convert --to yaml`)
+  ToYaml {
+    /// Files to convert (use "-" for stdin)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+
+    /// Write each converted file under this directory, at its path relative to the current
+    /// directory with a `.yaml` extension, instead of printing it
+    #[structopt(long, value_name = "dir", parse(from_os_str))]
+    out_dir: Option<PathBuf>,
+  },
+}
+
+impl TaskOpt {
+  /// Returns the configuration options set on the command line, which override those in the
+  /// configuration file.
+  fn config_overrides(&self) -> Config {
+    let mut config = Config::default();
+    match self.indent {
+      Some(Indent::Spaces(indent)) => config.set("indent", Setting::Integer(indent)),
+      Some(Indent::Auto) => config.set("indent", Setting::String("auto".to_string())),
+      None => {}
+    }
+    if self.no_trailing_commas {
+      config.set("trailing_commas", Setting::Bool(false));
+    }
+    if self.one_element_lines {
+      config.set("one_element_lines", Setting::Bool(true));
+    }
+    if self.sort_arrays {
+      config.set("sort_arrays", Setting::Bool(true));
+    }
+    if self.sort_keys {
+      config.set("sort_keys", Setting::Bool(true));
+    }
+    if self.allow_trailing_garbage {
+      config.set("allow_trailing_garbage", Setting::Bool(true));
+    }
+    if let Some(lines) = self.preserve_preamble_lines {
+      config.set("preserve_preamble_lines", Setting::Integer(lines));
+    }
+    if self.front_matter {
+      config.set("front_matter", Setting::Bool(true));
+    }
+    if !self.embedded.is_empty() {
+      let rules = self.embedded.iter().map(IslandRule::to_string).collect();
+      config.set("embedded", Setting::StringList(rules));
+    }
+    if let Some(style) = self.empty_container_comments {
+      config.set(
+        "empty_container_comments",
+        Setting::String(style.name().to_string()),
+      );
+    }
+    if let Some(dialect) = self.dialect {
+      config.set("dialect", Setting::String(dialect.name().to_string()));
+    }
+    if let Some(columns) = self.max_width {
+      config.set("max_width", Setting::Integer(columns));
+    }
+    if let Some(policy) = self.long_value {
+      config.set("long_value", Setting::String(policy.name().to_string()));
+    }
+    if let Some(style) = self.array_wrap {
+      config.set("array_wrap", Setting::String(style.name().to_string()));
+    }
+    if let Some(style) = self.quotes {
+      config.set("quotes", Setting::String(style.name().to_string()));
+    }
+    if let Some(style) = self.style {
+      config.set("style", Setting::String(style.name().to_string()));
+    }
+    if let Some(kind) = self.require_root {
+      let rule = match kind {
+        RootKind::Any => RuleConfig {
+          level: lint::Level::Off,
+          option: None,
+          allow: Vec::new(),
+        },
+        kind => RuleConfig {
+          level: lint::Level::Error,
+          option: Some(kind.name().to_string()),
+          allow: Vec::new(),
+        },
+      };
+      config.set(
+        "lint",
+        Setting::Rules(BTreeMap::from([("require-root".to_string(), rule)])),
+      );
+    }
+    if let Some(placement) = self.eol_comments {
+      config.set(
+        "eol_comments",
+        Setting::String(placement.name().to_string()),
+      );
+    }
+    if let Some(mode) = self.write_mode {
+      config.set("write_mode", Setting::String(mode.name().to_string()));
+    }
+    if let Some(attachment) = self.comment_attachment {
+      config.set(
+        "comment_attachment",
+        Setting::String(attachment.name().to_string()),
+      );
+    }
+    config
+  }
+}
+
+/// Returns the value of each occurrence of the option `option` (as `<option> <value>` or
+/// `<option>=<value>`) in the command line `arguments` (starting with the program name), up to any
+/// `--` argument.
+fn repeated_option(arguments: &[OsString], option: &str) -> Vec<String> {
+  let mut values = Vec::new();
+  let mut arguments = arguments
+    .iter()
+    .skip(1)
+    .map(|argument| argument.to_string_lossy());
+  while let Some(argument) = arguments.next() {
+    if argument == "--" {
+      break;
+    }
+    if argument == option {
+      values.extend(arguments.next().map(|value| value.to_string()));
+    } else if let Some(value) = argument
+      .strip_prefix(option)
+      .and_then(|rest| rest.strip_prefix('='))
+    {
+      values.push(value.to_string());
+    }
+  }
+  values
 }
 
-#[cfg(not(test))]
 impl Opt {
-  fn args() -> Self {
-    Self::from_args()
+  /// Parses the command line, and returns the global options and the subcommand to run (`format`,
+  /// if none is given).
+  fn args() -> Result<(GlobalOpt, Command)> {
+    let arguments = std::env::args_os().collect::<Vec<_>>();
+    Self::from_matches(Self::clap().get_matches_from(&arguments), &arguments)
+  }
+
+  /// Parses the command line `arguments` (after the program name) of a request to a persistent
+  /// worker (see the [worker](worker/index.html) module), and returns the global options and the
+  /// subcommand to run. Unlike `args`, this returns the help and usage errors instead of printing
+  /// them and exiting.
+  fn args_from(arguments: Vec<OsString>) -> Result<(GlobalOpt, Command)> {
+    let arguments = std::iter::once(OsString::from("formatjson5"))
+      .chain(arguments)
+      .collect::<Vec<_>>();
+    let matches = Self::clap()
+      .get_matches_from_safe(&arguments)
+      .map_err(|err| anyhow::anyhow!("{}", err.message.trim_start_matches("error: ")))?;
+    Self::from_matches(matches, &arguments)
+  }
+
+  /// Returns the global options and the subcommand to run of the parsed command line `matches`,
+  /// parsed from `arguments` (starting with the program name).
+  fn from_matches(
+    matches: structopt::clap::ArgMatches<'_>,
+    arguments: &[OsString],
+  ) -> Result<(GlobalOpt, Command)> {
+    let mut opt = Self::from_clap(&matches);
+    // clap keeps only the values given on one side of the subcommand.
+    opt.global.config_override = repeated_option(arguments, "--config-override");
+    match opt.command {
+      None => Ok((opt.global, Command::Format(opt.format))),
+      Some(command) => {
+        // The files and formatting options without a subcommand are also accepted before one.
+        let misplaced = matches
+          .args
+          .keys()
+          .any(|name| matches.occurrences_of(name) > 0 && !GLOBAL_OPTIONS.contains(name));
+        if misplaced {
+          return Err(anyhow::anyhow!(
+            "Files and formatting options must be given after the subcommand, not before it"
+          ));
+        }
+        Ok((opt.global, command))
+      }
+    }
   }
 
-  fn from_stdin(mut buf: &mut String) -> Result<usize, io::Error> {
-    io::stdin().read_to_string(&mut buf)
+  fn from_stdin(buf: &mut String) -> Result<usize, io::Error> {
+    io::stdin().read_to_string(buf)
   }
 
   fn write_to_file(filename: &str, bytes: &[u8]) -> Result<(), io::Error> {
@@ -151,7 +3923,7 @@ impl Opt {
       .create(true)
       .truncate(true)
       .write(true)
-      .open(filename)?
-      .write_all(&bytes)
+      .open(paths::extended(Path::new(filename)))?
+      .write_all(bytes)
   }
 }
@@ -0,0 +1,649 @@
+//! Merges three versions of a document by their structure, for git (the `merge-driver`
+//! subcommand), so changes to different properties of a configuration file never conflict, however
+//! each side formatted it:
+//!
+//!     # .gitattributes
+//!     *.json5 merge=json5
+//!
+//!     # .git/config (or ~/.gitconfig)
+//!     [merge "json5"]
+//!         name = JSON5 structural merge
+//!         driver = formatjson5 merge-driver %O %A %B %P
+//!
+//! The merge starts from our version, so its comments and formatting are kept, and applies the
+//! changes their version made to the common ancestor: a value they changed (and we did not) is
+//! replaced with theirs, a property they removed is removed, and a property they added is added at
+//! the end of its object. Values are compared by their data, so a change that only reformats a
+//! value is not a change. Comments are changes too: a comment they changed (and we did not) is
+//! replaced with theirs, along with the property it belongs to (the comments before its name, and
+//! after its value on the same line), the value it is in, or the object or document it ends.
+//! Where both sides changed the same value differently, the objects are merged property by
+//! property, and any other value both sides changed (such as an array, or a property one side
+//! changed and the other removed), or comments both sides changed differently, is a conflict,
+//! marked as git marks conflicts:
+//!
+//!     <<<<<<< ours
+//!         timeout: 30,
+//!     =======
+//!         timeout: 60,
+//!     >>>>>>> theirs
+//!
+//! A version that cannot be parsed makes the whole file a conflict.
+
+use crate::{
+  lexer::{self, TokenKind},
+  syntax::{self, Member, Node, Span},
+};
+
+/// The result of a merge.
+#[derive(Debug)]
+pub struct Merge {
+  /// The merged document, with conflict markers around each conflict.
+  pub text: String,
+  /// The number of conflicts.
+  pub conflicts: usize,
+}
+
+/// Returns the merge of the documents `ours` and `theirs`, whose common ancestor is `base`.
+pub fn merge(base: &str, ours: &str, theirs: &str) -> Merge {
+  let whole = || Merge {
+    text: markers(ours, theirs),
+    conflicts: 1,
+  };
+  if ours == theirs || base == theirs {
+    return Merge {
+      text: ours.to_string(),
+      conflicts: 0,
+    };
+  }
+  if base == ours {
+    return Merge {
+      text: theirs.to_string(),
+      conflicts: 0,
+    };
+  }
+  let (Ok(Some(base_root)), Ok(Some(our_root)), Ok(Some(their_root))) = (
+    syntax::parse(base),
+    syntax::parse(ours),
+    syntax::parse(theirs),
+  ) else {
+    return whole();
+  };
+  let mut merger = Merger {
+    base,
+    ours,
+    theirs,
+    conflicts: 0,
+  };
+  let Some(value) = merger.value(&base_root, &our_root, &their_root) else {
+    return whole();
+  };
+  let (base_span, our_span, their_span) = (base_root.span(), our_root.span(), their_root.span());
+  // The text before or after the value of our version, or of theirs if only they changed its
+  // comments, or both between conflict markers.
+  let mut around = |base: &str, ours: &str, theirs: &str| match side(
+    &comments(base),
+    &comments(ours),
+    &comments(theirs),
+  ) {
+    Some(Side::Ours) => ours.to_string(),
+    Some(Side::Theirs) => theirs.to_string(),
+    None => {
+      merger.conflicts += 1;
+      markers(ours.trim(), theirs.trim())
+    }
+  };
+  let before = around(
+    &base[..base_span.start],
+    &ours[..our_span.start],
+    &theirs[..their_span.start],
+  );
+  let mut after = around(
+    &base[base_span.end..],
+    &ours[our_span.end..],
+    &theirs[their_span.end..],
+  );
+  if after.starts_with("<<<<<<<") {
+    after.insert(0, '\n');
+  }
+  Merge {
+    text: format!("{}{}{}", before, value, after),
+    conflicts: merger.conflicts,
+  }
+}
+
+/// The sources of the three versions of a document being merged.
+struct Merger<'a> {
+  /// The common ancestor.
+  base: &'a str,
+  /// Our version, which the merge starts from.
+  ours: &'a str,
+  /// Their version.
+  theirs: &'a str,
+  /// The number of conflicts marked so far.
+  conflicts: usize,
+}
+
+/// The side of a three-way change to keep.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Side {
+  /// Our version.
+  Ours,
+  /// Their version.
+  Theirs,
+}
+
+/// Returns the side to keep of a part of the document whose versions are `base`, `ours` and
+/// `theirs`: ours, unless only their side changed it, or `None` if both sides changed it
+/// differently.
+fn side<T: PartialEq + ?Sized>(base: &T, ours: &T, theirs: &T) -> Option<Side> {
+  if theirs == base || ours == theirs {
+    Some(Side::Ours)
+  } else if ours == base {
+    Some(Side::Theirs)
+  } else {
+    None
+  }
+}
+
+/// A property of an object, and the text around it.
+struct Property<'a> {
+  /// The property.
+  member: &'a Member,
+  /// The text from the end of the previous property (or the opening brace) to the name.
+  lead: &'a str,
+  /// The text from the end of the value to the end of the property: its comma (if any), and the
+  /// comments after it on the same line.
+  tail: &'a str,
+  /// Whether the property is followed by a comma.
+  comma: bool,
+  /// The spaces and tabs that start the line of the name.
+  indent: &'a str,
+  /// The comments of the property outside of its value: those before its name, between its name
+  /// and value, and after its value on the same line.
+  comments: Vec<&'a str>,
+}
+
+impl Property<'_> {
+  /// Appends the property to `output`, with the text of the value replaced by `value`, and with a
+  /// comma if it has none. `source` is the document that holds the property.
+  fn write(&self, source: &str, value: &str, output: &mut String) {
+    output.push_str(self.lead);
+    output.push_str(&source[self.member.key.start..self.member.value.span().start]);
+    output.push_str(value);
+    if !self.comma {
+      output.push(',');
+    }
+    output.push_str(self.tail);
+  }
+
+  /// Returns the property as it is shown between conflict markers: its leading comments (if any)
+  /// on lines of their own, each line after the first indented by `indent`, then its name and value
+  /// followed by a comma and its trailing comments. `source` is the document that holds the
+  /// property.
+  fn marked(&self, source: &str, indent: &str) -> String {
+    let mut text = String::new();
+    let lead = self.lead.trim();
+    if !lead.is_empty() {
+      text.push_str(lead);
+      text.push('\n');
+      text.push_str(indent);
+    }
+    text.push_str(member_text(self.member, source));
+    text.push(',');
+    for comment in comments(self.tail) {
+      text.push(' ');
+      text.push_str(comment);
+    }
+    text
+  }
+}
+
+/// The properties of an object, and the text around them.
+struct Object<'a> {
+  /// The properties, in source order.
+  properties: Vec<Property<'a>>,
+  /// The text after the last property (or the opening brace), through the closing brace.
+  closing: &'a str,
+}
+
+impl<'a> Object<'a> {
+  /// Returns the object at `span` in `source`, whose properties are `members`.
+  fn new(members: &'a [Member], span: Span, source: &'a str) -> Self {
+    let mut from = span.start + 1;
+    let mut properties = Vec::new();
+    for member in members {
+      let value = member.value.span();
+      let comma = comma_after(source, value.end);
+      let end = line_end(source, comma.unwrap_or(value.end));
+      let (lead, tail) = (&source[from..member.key.start], &source[value.end..end]);
+      let mut all = comments(lead);
+      all.extend(comments(&source[member.key.end..value.start]));
+      all.extend(comments(tail));
+      properties.push(Property {
+        member,
+        lead,
+        tail,
+        comma: comma.is_some(),
+        indent: indentation(source, member.key.start),
+        comments: all,
+      });
+      from = end;
+    }
+    Object {
+      properties,
+      closing: &source[from..span.end],
+    }
+  }
+
+  /// Returns the first property named `name`, if any.
+  fn find(&self, name: &str) -> Option<&Property<'a>> {
+    self
+      .properties
+      .iter()
+      .find(|property| property.member.name == name)
+  }
+}
+
+impl Merger<'_> {
+  /// Returns the merged text of our value `ours`, whose common ancestor is `base` and whose
+  /// version on their side is `theirs`, or `None` if both sides changed it and the changes cannot
+  /// be merged.
+  fn value(&mut self, base: &Node, ours: &Node, theirs: &Node) -> Option<String> {
+    match side(
+      &version(base, self.base),
+      &version(ours, self.ours),
+      &version(theirs, self.theirs),
+    ) {
+      Some(Side::Ours) => return Some(text(ours, self.ours).to_string()),
+      Some(Side::Theirs) => return Some(text(theirs, self.theirs).to_string()),
+      None => {}
+    }
+    match (base, ours, theirs) {
+      (
+        Node::Object {
+          members: base,
+          span: base_span,
+        },
+        Node::Object {
+          members: ours,
+          span: our_span,
+        },
+        Node::Object {
+          members: theirs,
+          span: their_span,
+        },
+      ) => Some(self.object(
+        &Object::new(base, *base_span, self.base),
+        &Object::new(ours, *our_span, self.ours),
+        &Object::new(theirs, *their_span, self.theirs),
+      )),
+      _ => None,
+    }
+  }
+
+  /// Returns the merged text of our object `ours`, whose common ancestor is `base` and whose
+  /// version on their side is `theirs`, merged property by property.
+  fn object(&mut self, base: &Object<'_>, ours: &Object<'_>, theirs: &Object<'_>) -> String {
+    let mut output = String::from("{");
+    let mut indent = "";
+    for our in &ours.properties {
+      indent = our.indent;
+      let our_version = || version(&our.member.value, self.ours);
+      // The property to write (with its source and merged value), or their version of the
+      // property (if any) if it conflicts.
+      let merged = match (base.find(&our.member.name), theirs.find(&our.member.name)) {
+        (Some(old), Some(their)) => {
+          match self.value(&old.member.value, &our.member.value, &their.member.value) {
+            Some(value) => match side(&old.comments, &our.comments, &their.comments) {
+              Some(Side::Ours) => Ok((our, self.ours, value)),
+              Some(Side::Theirs) => Ok((their, self.theirs, value)),
+              None => Err(Some(their)),
+            },
+            None => Err(Some(their)),
+          }
+        }
+        // They removed the property, and we did not change it.
+        (Some(old), None)
+          if version(&old.member.value, self.base) == our_version()
+            && old.comments == our.comments =>
+        {
+          continue
+        }
+        (Some(_), None) => Err(None),
+        // Both sides added the property.
+        (None, Some(their)) if version(&their.member.value, self.theirs) == our_version() => {
+          let value = text(&our.member.value, self.ours).to_string();
+          match side(&[][..], &our.comments[..], &their.comments[..]) {
+            Some(Side::Ours) => Ok((our, self.ours, value)),
+            Some(Side::Theirs) => Ok((their, self.theirs, value)),
+            None => Err(Some(their)),
+          }
+        }
+        (None, Some(their)) => Err(Some(their)),
+        (None, None) => Ok((
+          our,
+          self.ours,
+          text(&our.member.value, self.ours).to_string(),
+        )),
+      };
+      match merged {
+        Ok((property, source, value)) => property.write(source, &value, &mut output),
+        Err(their) => {
+          self.conflicts += 1;
+          output.push_str(&member_markers(
+            indent,
+            Some(&our.marked(self.ours, indent)),
+            their
+              .map(|their| their.marked(self.theirs, indent))
+              .as_deref(),
+          ));
+        }
+      }
+    }
+    for their in &theirs.properties {
+      if ours.find(&their.member.name).is_some() {
+        continue;
+      }
+      if ours.properties.is_empty() {
+        indent = their.indent;
+      }
+      match base.find(&their.member.name) {
+        // The property is new on their side.
+        None => {
+          output.push('\n');
+          output.push_str(indent);
+          output.push_str(&their.marked(self.theirs, indent));
+        }
+        // We removed the property, and they did not change it.
+        Some(old)
+          if version(&old.member.value, self.base) == version(&their.member.value, self.theirs)
+            && old.comments == their.comments => {}
+        Some(_) => {
+          self.conflicts += 1;
+          output.push_str(&member_markers(
+            indent,
+            None,
+            Some(&their.marked(self.theirs, indent)),
+          ));
+        }
+      }
+    }
+    match side(
+      &comments(base.closing),
+      &comments(ours.closing),
+      &comments(theirs.closing),
+    ) {
+      Some(Side::Ours) => output.push_str(ours.closing),
+      Some(Side::Theirs) => output.push_str(theirs.closing),
+      None => {
+        self.conflicts += 1;
+        // The comments before the closing brace of each side, and the line of our closing brace.
+        let inner = |closing: &str| closing[..closing.len() - 1].trim().to_string();
+        let (our_inner, their_inner) = (inner(ours.closing), inner(theirs.closing));
+        output.push_str(&member_markers(
+          indent,
+          Some(&our_inner)
+            .filter(|inner| !inner.is_empty())
+            .map(String::as_str),
+          Some(&their_inner)
+            .filter(|inner| !inner.is_empty())
+            .map(String::as_str),
+        ));
+        output.push('\n');
+        output.push_str(ours.closing.rsplit('\n').next().unwrap_or("}"));
+      }
+    }
+    output
+  }
+}
+
+/// Returns the text of `node` in `source`.
+fn text<'a>(node: &Node, source: &'a str) -> &'a str {
+  let span = node.span();
+  &source[span.start..span.end]
+}
+
+/// Returns the text of `member` in `source`, from its name to the end of its value.
+fn member_text<'a>(member: &Member, source: &'a str) -> &'a str {
+  &source[member.key.start..member.value.span().end]
+}
+
+/// Returns the version of the value `node`, read from `source`, that the merge compares: its data
+/// (see [`data`]), and the text of each comment in it.
+fn version<'a>(node: &Node, source: &'a str) -> (String, Vec<&'a str>) {
+  (data(node, source), comments(text(node, source)))
+}
+
+/// Returns the text of each comment in `text`.
+fn comments(text: &str) -> Vec<&str> {
+  lexer::tokenize(text)
+    .into_iter()
+    .filter(|token| matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment))
+    .map(|token| &text[token.start..token.end])
+    .collect()
+}
+
+/// Returns a text for the value `node`, read from `source`, that is the same for equal data: the
+/// properties of objects sorted by name, the items of arrays in order, and strings decoded.
+fn data(node: &Node, source: &str) -> String {
+  match node {
+    Node::Object { members, .. } => {
+      let mut members = members
+        .iter()
+        .map(|member| {
+          format!(
+            "{}:{}",
+            syntax::quote(&member.name, '"'),
+            data(&member.value, source)
+          )
+        })
+        .collect::<Vec<_>>();
+      members.sort();
+      format!("{{{}}}", members.join(","))
+    }
+    Node::Array { items, .. } => {
+      let items = items
+        .iter()
+        .map(|item| data(item, source))
+        .collect::<Vec<_>>();
+      format!("[{}]", items.join(","))
+    }
+    Node::String { value, .. } => syntax::quote(value, '"'),
+    Node::Literal { span } => source[span.start..span.end].to_string(),
+  }
+}
+
+/// Returns the offset just past the comma that follows the value ending at `offset` in `source`
+/// (after any whitespace and comments), if any.
+fn comma_after(source: &str, offset: usize) -> Option<usize> {
+  let mut rest = &source[offset..];
+  loop {
+    let trimmed = rest.trim_start();
+    if let Some(comment) = trimmed.strip_prefix("//") {
+      rest = comment.find('\n').map_or("", |newline| &comment[newline..]);
+    } else if let Some(comment) = trimmed.strip_prefix("/*") {
+      rest = comment.find("*/").map_or("", |close| &comment[close + 2..]);
+    } else {
+      return trimmed
+        .starts_with(',')
+        .then(|| source.len() - trimmed.len() + 1);
+    }
+  }
+}
+
+/// Returns the offset of the line break that ends the line holding the byte `offset` of `source`,
+/// or the end of `source`, if only spaces, tabs and comments follow `offset` on that line, and
+/// otherwise `offset`.
+fn line_end(source: &str, offset: usize) -> usize {
+  let mut rest = &source[offset..];
+  loop {
+    let trimmed = rest.trim_start_matches([' ', '\t', '\r']);
+    let end = source.len() - trimmed.len();
+    if trimmed.is_empty() || trimmed.starts_with('\n') {
+      return end;
+    }
+    if trimmed.starts_with("//") {
+      return trimmed
+        .find('\n')
+        .map_or(source.len(), |newline| end + newline);
+    }
+    match trimmed
+      .strip_prefix("/*")
+      .and_then(|comment| comment.find("*/").map(|close| &comment[..close]))
+    {
+      Some(comment) if !comment.contains('\n') => rest = &trimmed[comment.len() + 4..],
+      _ => return offset,
+    }
+  }
+}
+
+/// Returns the spaces and tabs that start the line holding the byte `offset` of `source`.
+fn indentation(source: &str, offset: usize) -> &str {
+  let line_start = source[..offset]
+    .rfind('\n')
+    .map_or(0, |newline| newline + 1);
+  let line = &source[line_start..];
+  &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Returns the conflict markers around our and their versions of a whole document, or of the
+/// comments before or after its value.
+fn markers(ours: &str, theirs: &str) -> String {
+  let line = |text: &str| match text.ends_with('\n') {
+    true => text.to_string(),
+    false => format!("{}\n", text),
+  };
+  format!(
+    "<<<<<<< ours\n{}=======\n{}>>>>>>> theirs\n",
+    line(ours),
+    line(theirs)
+  )
+}
+
+/// Returns the conflict markers around our and their versions of a property or of comments (if
+/// any), each indented by `indent` and on lines of its own. The markers start on a line of their
+/// own, and end without a line break.
+fn member_markers(indent: &str, ours: Option<&str>, theirs: Option<&str>) -> String {
+  let side = |text: Option<&str>| match text {
+    Some(text) => format!("{}{}\n", indent, text),
+    None => String::new(),
+  };
+  format!(
+    "\n<<<<<<< ours\n{}=======\n{}>>>>>>> theirs",
+    side(ours),
+    side(theirs)
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Asserts that merging `ours` and `theirs`, whose common ancestor is `base`, gives `expected`
+  /// with `conflicts` conflicts.
+  fn assert_merges(base: &str, ours: &str, theirs: &str, expected: &str, conflicts: usize) {
+    let merged = merge(base, ours, theirs);
+    assert_eq!(merged.text, expected);
+    assert_eq!(merged.conflicts, conflicts);
+  }
+
+  #[test]
+  fn merges_changes_to_different_properties() {
+    assert_merges(
+      "{\n  a: 1,\n  b: 2,\n}\n",
+      "{\n  a: 10,\n  b: 2,\n}\n",
+      "{\n  a: 1,\n  b: 20,\n}\n",
+      "{\n  a: 10,\n  b: 20,\n}\n",
+      0,
+    );
+  }
+
+  #[test]
+  fn takes_a_comment_only_they_changed() {
+    assert_merges(
+      "{\n  // The timeout.\n  a: 1,\n  b: 2, // Two.\n}\n",
+      "{\n  // The timeout.\n  a: 1,\n  b: 3, // Two.\n}\n",
+      "{\n  // The timeout, in seconds.\n  a: 1,\n  b: 2, // Seconds.\n}\n",
+      "{\n  // The timeout, in seconds.\n  a: 1,\n  b: 3, // Seconds.\n}\n",
+      0,
+    );
+  }
+
+  #[test]
+  fn keeps_a_comment_only_we_changed() {
+    assert_merges(
+      "{\n  // The timeout.\n  a: 1,\n  b: 2,\n}\n",
+      "{\n  // The timeout, in seconds.\n  a: 1,\n  b: 2,\n}\n",
+      "{\n  // The timeout.\n  a: 1,\n  b: 3,\n}\n",
+      "{\n  // The timeout, in seconds.\n  a: 1,\n  b: 3,\n}\n",
+      0,
+    );
+  }
+
+  #[test]
+  fn merges_a_comment_both_changed_the_same_way() {
+    assert_merges(
+      "{\n  // The timeout.\n  a: 1,\n  b: 2,\n}\n",
+      "{\n  // Seconds.\n  a: 1,\n  b: 3,\n}\n",
+      "{\n  // Seconds.\n  a: 1,\n  b: 2,\n}\n",
+      "{\n  // Seconds.\n  a: 1,\n  b: 3,\n}\n",
+      0,
+    );
+  }
+
+  #[test]
+  fn marks_a_comment_both_changed_differently() {
+    assert_merges(
+      "{\n  // The timeout.\n  a: 1,\n  b: 2,\n}\n",
+      "{\n  // Seconds.\n  a: 1,\n  b: 2,\n}\n",
+      "{\n  // Minutes.\n  a: 1,\n  b: 2,\n}\n",
+      "{\n<<<<<<< ours\n  // Seconds.\n  a: 1,\n=======\n  // Minutes.\n  a: 1,\n>>>>>>> theirs\n  b: 2,\n}\n",
+      1,
+    );
+  }
+
+  #[test]
+  fn takes_a_comment_they_changed_in_a_value() {
+    assert_merges(
+      "{\n  a: [1, /* one */ 2],\n  b: 2,\n}\n",
+      "{\n  a: [1, /* one */ 2],\n  b: 3,\n}\n",
+      "{\n  a: [1, /* two */ 2],\n  b: 2,\n}\n",
+      "{\n  a: [1, /* two */ 2],\n  b: 3,\n}\n",
+      0,
+    );
+  }
+
+  #[test]
+  fn takes_comments_they_changed_at_the_end_of_an_object_and_document() {
+    assert_merges(
+      "// Settings.\n{\n  a: 1,\n  // More.\n}\n",
+      "// Settings.\n{\n  a: 2,\n  // More.\n}\n",
+      "// All settings.\n{\n  a: 1,\n  // No more.\n}\n// The end.\n",
+      "// All settings.\n{\n  a: 2,\n  // No more.\n}\n// The end.\n",
+      0,
+    );
+  }
+
+  #[test]
+  fn adds_a_property_they_added_with_its_comments() {
+    assert_merges(
+      "{\n  a: 1,\n}\n",
+      "{\n  a: 2,\n}\n",
+      "{\n  a: 1,\n  // New.\n  b: 2, // Two.\n}\n",
+      "{\n  a: 2,\n  // New.\n  b: 2, // Two.\n}\n",
+      0,
+    );
+  }
+
+  #[test]
+  fn marks_a_value_both_changed_differently() {
+    assert_merges(
+      "{\n  a: 1,\n}\n",
+      "{\n  a: 2,\n}\n",
+      "{\n  a: 3,\n}\n",
+      "{\n<<<<<<< ours\n  a: 2,\n=======\n  a: 3,\n>>>>>>> theirs\n}\n",
+      1,
+    );
+  }
+}
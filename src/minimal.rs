@@ -0,0 +1,173 @@
+//! Keeps as much of a document's original text as possible when formatting it (the
+//! `--minimal-diff` option), so reformatting a large file does not rewrite lines whose only
+//! problem was how they were broken, and `git blame` keeps pointing at their authors.
+//!
+//! The original and formatted documents are compared line by line. Each run of lines that differs
+//! keeps its original text where that text has the same tokens (including comments) as the
+//! formatted text, so only the line breaks and the spacing within lines differ, and each original
+//! line starts, with the formatted indentation, at a token the formatter also starts a line with.
+//! For example, an array with several items on each line is kept, if the items are indented as
+//! the formatter would indent them. Any other run of lines is replaced by the formatted text.
+
+use crate::{
+  diff::{self, Op},
+  lexer::{self, Token, TokenKind},
+};
+
+/// The largest number of inserted and deleted lines to compare the documents with. Documents that
+/// differ in more lines are formatted as usual.
+const MAX_CHANGES: usize = 2000;
+
+/// Returns the `formatted` text of the document `original`, with the original text of each run of
+/// changed lines whose original line breaks are acceptable, as described in the
+/// [module documentation](index.html). Returns `formatted` if the documents differ too much to
+/// compare.
+pub fn minimal_diff(original: &str, formatted: &str) -> String {
+  let old = original
+    .lines()
+    .map(|line| line.strip_suffix('\r').unwrap_or(line))
+    .collect::<Vec<_>>();
+  let new = formatted.lines().collect::<Vec<_>>();
+  let Some(ops) = diff::lines(&old, &new, MAX_CHANGES) else {
+    return formatted.to_string();
+  };
+  let mut result = String::with_capacity(formatted.len());
+  let (mut x, mut y) = (0, 0);
+  let mut index = 0;
+  while index < ops.len() {
+    if ops[index] == Op::Equal {
+      result.push_str(new[y]);
+      result.push('\n');
+      x += 1;
+      y += 1;
+      index += 1;
+      continue;
+    }
+    let (start_x, start_y) = (x, y);
+    while index < ops.len() && ops[index] != Op::Equal {
+      match ops[index] {
+        Op::Delete => x += 1,
+        Op::Insert => y += 1,
+        Op::Equal => unreachable!(),
+      }
+      index += 1;
+    }
+    let lines = match acceptable(&old[start_x..x], &new[start_y..y]) {
+      true => &old[start_x..x],
+      false => &new[start_y..y],
+    };
+    for line in lines {
+      result.push_str(line);
+      result.push('\n');
+    }
+  }
+  if !formatted.ends_with('\n') {
+    result.pop();
+  }
+  // The comparison works on fragments of the documents, which could be mistokenized (such as
+  // inside a multi-line comment), so the result must still have the formatted tokens.
+  match significant(&result) == significant(formatted) {
+    true => result,
+    false => formatted.to_string(),
+  }
+}
+
+/// Returns true if the `old` lines can replace the `new` (formatted) lines of a run of changes.
+fn acceptable(old: &[&str], new: &[&str]) -> bool {
+  if old.is_empty() || new.is_empty() {
+    return false;
+  }
+  let old_text = old.join("\n");
+  let new_text = new.join("\n");
+  let old_tokens = significant_tokens(&old_text);
+  let new_tokens = significant_tokens(&new_text);
+  if old_tokens.len() != new_tokens.len()
+    || old_tokens
+      .iter()
+      .zip(&new_tokens)
+      .any(|(a, b)| old_text[a.start..a.end] != new_text[b.start..b.end])
+  {
+    return false;
+  }
+  old.iter().enumerate().all(|(line, text)| {
+    if text.trim_end() != *text {
+      return false;
+    }
+    let Some(first) = old_tokens.iter().position(|token| token.line == line + 1) else {
+      return false;
+    };
+    let starts_line = first == 0 || new_tokens[first - 1].line != new_tokens[first].line;
+    let indent = &text[..text.len() - text.trim_start().len()];
+    let expected = new[new_tokens[first].line - 1];
+    starts_line && expected[..expected.len() - expected.trim_start().len()] == *indent
+  })
+}
+
+/// Returns the tokens of `source` other than whitespace and line breaks.
+fn significant_tokens(source: &str) -> Vec<Token> {
+  lexer::tokenize(source)
+    .into_iter()
+    .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Newline))
+    .collect()
+}
+
+/// Returns the text of each token of `source` other than whitespace and line breaks.
+fn significant(source: &str) -> Vec<&str> {
+  significant_tokens(source)
+    .iter()
+    .map(|token| &source[token.start..token.end])
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A formatted document with an array of one item on each line.
+  const FORMATTED: &str = "{\n  a: [\n    1,\n    2,\n    3,\n  ],\n  b: 4,\n}\n";
+
+  #[test]
+  fn keeps_the_line_breaks_of_indented_runs() {
+    let original = "{\n  a: [\n    1, 2,\n    3,\n  ],\n  b: 4,\n}\n";
+    assert_eq!(minimal_diff(original, FORMATTED), original);
+    let original = "{\r\n  a: [\r\n    1, 2, 3,\r\n  ],\r\n  b: 4,\r\n}\r\n";
+    assert_eq!(
+      minimal_diff(original, FORMATTED),
+      "{\n  a: [\n    1, 2, 3,\n  ],\n  b: 4,\n}\n"
+    );
+  }
+
+  #[test]
+  fn formats_runs_indented_otherwise() {
+    let original = "{\n  a: [\n  1, 2,\n    3,\n  ],\n  b: 4,\n}\n";
+    assert_eq!(minimal_diff(original, FORMATTED), FORMATTED);
+  }
+
+  #[test]
+  fn formats_runs_with_other_tokens() {
+    let original = "{\n  a: [\n    1, 2,\n    3\n  ],\n  b: 4,\n}\n";
+    assert_eq!(minimal_diff(original, FORMATTED), FORMATTED);
+  }
+
+  #[test]
+  fn formats_runs_with_trailing_whitespace() {
+    let original = "{\n  a: [\n    1, 2, \n    3,\n  ],\n  b: 4,\n}\n";
+    assert_eq!(minimal_diff(original, FORMATTED), FORMATTED);
+  }
+
+  #[test]
+  fn formats_lines_that_start_within_a_formatted_line() {
+    let original = "{\n  a: [\n    1,\n    2,\n    3,\n  ],\n  b:\n    4,\n}\n";
+    assert_eq!(minimal_diff(original, FORMATTED), FORMATTED);
+  }
+
+  #[test]
+  fn keeps_the_formatted_end_of_the_document() {
+    let formatted = FORMATTED.trim_end();
+    let original = "{\n  a: [\n    1, 2, 3,\n  ],\n  b: 4,\n}\n";
+    assert_eq!(
+      minimal_diff(original, formatted),
+      "{\n  a: [\n    1, 2, 3,\n  ],\n  b: 4,\n}"
+    );
+  }
+}
@@ -0,0 +1,177 @@
+//! Keeps arrays whose order looks significant in source order when arrays are sorted (with
+//! `--sort-warn-heuristics`), and warns about each of them instead of silently reordering it. An
+//! array that sorting would reorder looks order-sensitive if:
+//!
+//! - its items are of more than one type, as in `["--jobs", 4]`;
+//! - one of its strings holds a word about order, such as `first`, `fallback`, or `then`; or
+//! - its property name is about order, such as `args`, `steps`, or `fallbacks`.
+//!
+//! Each such array gets a `sort_arrays: false` [path rule](../path_options/index.html), so it is
+//! written in source order. Arrays whose paths a `paths` rule sets `sort_arrays` for are sorted or
+//! kept as the rule says, so a rule sorts an array the heuristics would keep.
+
+use crate::{
+  codes,
+  diagnostics::Diagnostic,
+  path_options::{PathRule, PathRules},
+  syntax::Node,
+};
+use std::{cmp::Ordering, collections::BTreeSet};
+
+/// The words that, in a string item, suggest the order of the items is significant.
+const ORDER_WORDS: &[&str] = &[
+  "after",
+  "before",
+  "fallback",
+  "first",
+  "last",
+  "next",
+  "primary",
+  "priority",
+  "secondary",
+  "then",
+];
+
+/// The property names of arrays whose order is usually significant.
+const ORDER_KEYS: &[&str] = &[
+  "args",
+  "arguments",
+  "argv",
+  "command",
+  "fallbacks",
+  "order",
+  "pipeline",
+  "priority",
+  "sequence",
+  "stages",
+  "steps",
+];
+
+/// Returns a warning, and a path rule keeping the source order, for each array in the document
+/// whose top-level value is `root`, read from `source`, that looks order-sensitive and would be
+/// sorted by `sort_arrays` (the option for the whole document). Arrays whose paths `rules` set the
+/// option for are sorted or kept as the rules say.
+pub fn check(
+  root: &Node,
+  source: &str,
+  sort_arrays: bool,
+  rules: &PathRules<'_>,
+) -> (Vec<Diagnostic>, Vec<PathRule>) {
+  let mut found = (Vec::new(), Vec::new());
+  visit(
+    root,
+    source,
+    None,
+    sort_arrays,
+    rules,
+    &mut Vec::new(),
+    &mut found,
+  );
+  found
+}
+
+/// Checks `node`, the value of the property `key` (if any), whose path has the decoded `segments`,
+/// and its descendants.
+fn visit(
+  node: &Node,
+  source: &str,
+  key: Option<&str>,
+  sort_arrays: bool,
+  rules: &PathRules<'_>,
+  segments: &mut Vec<String>,
+  found: &mut (Vec<Diagnostic>, Vec<PathRule>),
+) {
+  match node {
+    Node::Object { members, .. } => {
+      for member in members {
+        // Names that cannot be written in a pattern are left as selected by the options.
+        if member.name.contains('/') || member.name.contains('*') {
+          continue;
+        }
+        segments.push(member.name.clone());
+        visit(
+          &member.value,
+          source,
+          Some(&member.name),
+          sort_arrays,
+          rules,
+          segments,
+          found,
+        );
+        segments.pop();
+      }
+    }
+    Node::Array { items, span } => {
+      let chosen = rules.get("sort_arrays", segments).is_some();
+      if sort_arrays && !chosen && reordered(items, source) {
+        if let Some(reason) = reason(items, source, key) {
+          found.0.push(Diagnostic::warning(
+            &codes::ORDER_SENSITIVE_ARRAY,
+            span.line,
+            span.col,
+            format!(
+              "The order of this array looks significant ({}), so it is kept unsorted \
+               (--sort-warn-heuristics)",
+              reason
+            ),
+          ));
+          found.1.push(PathRule {
+            pattern: format!("/{}", segments.join("/")),
+            options: [("sort_arrays".to_string(), false)].into(),
+          });
+        }
+      }
+      segments.push("*".to_string());
+      for item in items {
+        visit(item, source, None, sort_arrays, rules, segments, found);
+      }
+      segments.pop();
+    }
+    Node::String { .. } | Node::Literal { .. } => {}
+  }
+}
+
+/// Returns true if sorting `items` the way json5format does (by the source text of primitive
+/// values, case-insensitively first) changes their order.
+fn reordered(items: &[Node], source: &str) -> bool {
+  let text = |node: &Node| match node {
+    Node::String { span, .. } | Node::Literal { span } => Some(&source[span.start..span.end]),
+    Node::Object { .. } | Node::Array { .. } => None,
+  };
+  let mut order = (0..items.len()).collect::<Vec<_>>();
+  order.sort_by(
+    |&left, &right| match (text(&items[left]), text(&items[right])) {
+      (Some(left), Some(right)) => left
+        .to_lowercase()
+        .cmp(&right.to_lowercase())
+        .then_with(|| left.cmp(right)),
+      _ => Ordering::Equal,
+    },
+  );
+  order.iter().enumerate().any(|(index, &item)| index != item)
+}
+
+/// Returns why the `items` of the array that is the value of the property `key` (if any) look
+/// order-sensitive, or `None` if they do not.
+fn reason(items: &[Node], source: &str, key: Option<&str>) -> Option<String> {
+  let types = items
+    .iter()
+    .map(|item| item.type_name(source))
+    .collect::<BTreeSet<_>>();
+  if types.len() > 1 {
+    let types = types.into_iter().collect::<Vec<_>>();
+    return Some(format!("it mixes {}", types.join(" and ")));
+  }
+  let word = items.iter().find_map(|item| match item {
+    Node::String { value, .. } => value
+      .split(|c: char| !c.is_alphanumeric())
+      .map(str::to_lowercase)
+      .find(|word| ORDER_WORDS.contains(&word.as_str())),
+    _ => None,
+  });
+  if let Some(word) = word {
+    return Some(format!("an item mentions `{}`", word));
+  }
+  let key = key.filter(|key| ORDER_KEYS.contains(&key.to_lowercase().as_str()))?;
+  Some(format!("its name is `{}`", key))
+}
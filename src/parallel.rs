@@ -0,0 +1,111 @@
+//! Runs independent jobs, such as formatting each input file, on a fixed number of threads, each
+//! with a stack of [STACK_SIZE](constant.STACK_SIZE.html).
+
+use crate::format::FormatError;
+use std::{
+  str::FromStr,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc,
+  },
+  thread,
+};
+
+/// The stack size of the threads that format documents. The formatter's own parse trees and JSON
+/// output are built without recursion, but json5format formats nested values recursively, and at
+/// its nesting limit (1000 levels) needs several megabytes of stack in a debug build: more than
+/// threads get by default (2 MiB), or the main thread on some platforms (1 MiB on Windows).
+pub const STACK_SIZE: usize = 16 * 1024 * 1024;
+
+/// The order in which results of parallel jobs are written to stdout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputOrder {
+  /// Buffer results and write them in the order the inputs were given.
+  Input,
+  /// Write each result as soon as it is available.
+  Completion,
+}
+
+impl OutputOrder {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["input", "completion"];
+}
+
+impl FromStr for OutputOrder {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "input" => Ok(OutputOrder::Input),
+      "completion" => Ok(OutputOrder::Completion),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", OutputOrder::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// Returns the number of threads to use for the requested number of `jobs`, where 0 selects one
+/// thread per available CPU.
+pub fn thread_count(jobs: usize) -> usize {
+  match jobs {
+    0 => thread::available_parallelism().map_or(1, |count| count.get()),
+    jobs => jobs,
+  }
+}
+
+/// Calls `work` for each of the `items` on up to `threads` threads, and calls `on_result` on the
+/// calling thread with the index of each item and its result, in the order the results complete. If
+/// `threads` is 1, the items are processed in order on the calling thread.
+pub fn run<T, R, W, C>(items: &[T], threads: usize, work: W, mut on_result: C)
+where
+  T: Sync,
+  R: Send,
+  W: Fn(&T) -> R + Sync,
+  C: FnMut(usize, R),
+{
+  if threads <= 1 || items.len() <= 1 {
+    for (index, item) in items.iter().enumerate() {
+      on_result(index, work(item));
+    }
+    return;
+  }
+
+  let next_index = AtomicUsize::new(0);
+  let (sender, receiver) = mpsc::channel();
+  thread::scope(|scope| {
+    for _ in 0..threads.min(items.len()) {
+      let sender = sender.clone();
+      let (next_index, work) = (&next_index, &work);
+      thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn_scoped(scope, move || loop {
+          let index = next_index.fetch_add(1, Ordering::SeqCst);
+          let Some(item) = items.get(index) else { return };
+          if sender.send((index, work(item))).is_err() {
+            return; // the receiver is only dropped if `on_result` panicked
+          }
+        })
+        .expect("failed to spawn thread");
+    }
+    drop(sender);
+    for (index, result) in receiver {
+      on_result(index, result);
+    }
+  });
+}
+
+/// Returns the result of `work`, run on a thread with a stack of
+/// [STACK_SIZE](constant.STACK_SIZE.html) (so the jobs `run` runs on the calling thread have the
+/// stack of the jobs it spawns threads for). A panic in `work` is resumed on the calling thread.
+pub fn with_stack<R: Send>(work: impl FnOnce() -> R + Send) -> R {
+  thread::scope(|scope| {
+    let worker = thread::Builder::new()
+      .stack_size(STACK_SIZE)
+      .spawn_scoped(scope, work)
+      .expect("failed to spawn thread");
+    worker
+      .join()
+      .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+  })
+}
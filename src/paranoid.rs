@@ -0,0 +1,196 @@
+//! Verifies that formatting did not change a document's data (the `--paranoid` option), by parsing
+//! the formatted output and comparing it with the input, before the output is written. The
+//! `--safe-replace` option makes the same check on each file being replaced, as it is written.
+//!
+//! The documents must have the same values: objects with the same properties (in any order, since
+//! the properties may be sorted), arrays with the same items in the same order (or in any order, if
+//! arrays are sorted), strings with the same decoded value (quotes and escapes may change), and
+//! other literals with the same text. They must also have the same number of comments, since a
+//! lost comment is lost data, too.
+
+use crate::{
+  lexer::{self, TokenKind},
+  syntax::{self, Node},
+};
+
+/// Returns an error describing the first difference between the data of the document `before` and
+/// its formatted text `after`, if any. If `sorted_arrays` is true, the items of arrays may be in
+/// any order.
+pub fn check(before: &str, after: &str, sorted_arrays: bool) -> Result<(), String> {
+  let expected = syntax::parse(before).map_err(|err| format!("The input is invalid: {}", err))?;
+  let actual = syntax::parse(after)
+    .map_err(|err| format!("The formatted document cannot be parsed: {}", err))?;
+  match (&expected, &actual) {
+    (Some(expected), Some(actual)) => compare(expected, before, actual, after, sorted_arrays, "")
+      .map_err(|(path, message)| match path.is_empty() {
+      true => format!("The formatted document differs from the input: {}", message),
+      false => format!(
+        "The formatted document differs from the input at {}: {}",
+        path, message
+      ),
+    })?,
+    (None, None) => {}
+    (Some(_), None) => return Err("The formatted document is empty".to_string()),
+    (None, Some(_)) => {
+      return Err("The formatted document has a value, but the input is empty".to_string())
+    }
+  }
+  let (expected, actual) = (comments(before), comments(after));
+  if expected != actual {
+    return Err(format!(
+      "The input has {} comment(s), but the formatted document has {}",
+      expected, actual
+    ));
+  }
+  Ok(())
+}
+
+/// Returns the path and description of the first difference between the value `expected`, in the
+/// document `before`, and the value `actual`, in the document `after`, at `path`.
+fn compare(
+  expected: &Node,
+  before: &str,
+  actual: &Node,
+  after: &str,
+  sorted_arrays: bool,
+  path: &str,
+) -> Result<(), (String, String)> {
+  let differs = |message: String| Err((path.to_string(), message));
+  match (expected, actual) {
+    (
+      Node::Object {
+        members: expected, ..
+      },
+      Node::Object {
+        members: actual, ..
+      },
+    ) => {
+      let mut expected = expected.iter().collect::<Vec<_>>();
+      let mut actual = actual.iter().collect::<Vec<_>>();
+      // A stable sort keeps duplicate properties in their order.
+      expected.sort_by(|a, b| a.name.cmp(&b.name));
+      actual.sort_by(|a, b| a.name.cmp(&b.name));
+      for (index, member) in expected.iter().enumerate() {
+        match actual.get(index) {
+          Some(other) if other.name == member.name => compare(
+            &member.value,
+            before,
+            &other.value,
+            after,
+            sorted_arrays,
+            &format!("{}/{}", path, member.name),
+          )?,
+          _ => return differs(format!("The property `{}` is missing", member.name)),
+        }
+      }
+      if let Some(extra) = actual.get(expected.len()) {
+        return differs(format!("The property `{}` was added", extra.name));
+      }
+      Ok(())
+    }
+    (
+      Node::Array {
+        items: expected, ..
+      },
+      Node::Array { items: actual, .. },
+    ) => {
+      if expected.len() != actual.len() {
+        return differs(format!(
+          "The array has {} item(s), but the input has {}",
+          actual.len(),
+          expected.len()
+        ));
+      }
+      if sorted_arrays {
+        let mut expected = expected
+          .iter()
+          .map(|item| canonical(item, before))
+          .collect::<Vec<_>>();
+        let mut actual = actual
+          .iter()
+          .map(|item| canonical(item, after))
+          .collect::<Vec<_>>();
+        expected.sort();
+        actual.sort();
+        if expected != actual {
+          return differs("The array has different items".to_string());
+        }
+        return Ok(());
+      }
+      for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+        compare(
+          expected,
+          before,
+          actual,
+          after,
+          sorted_arrays,
+          &format!("{}/{}", path, index),
+        )?;
+      }
+      Ok(())
+    }
+    (
+      Node::String {
+        value: expected, ..
+      },
+      Node::String { value: actual, .. },
+    ) => match expected == actual {
+      true => Ok(()),
+      false => differs(format!("The string {:?} became {:?}", expected, actual)),
+    },
+    (Node::Literal { span: expected }, Node::Literal { span: actual }) => {
+      let (expected, actual) = (
+        &before[expected.start..expected.end],
+        &after[actual.start..actual.end],
+      );
+      match expected == actual {
+        true => Ok(()),
+        false => differs(format!("`{}` became `{}`", expected, actual)),
+      }
+    }
+    _ => differs(format!(
+      "The {} became {}",
+      expected.type_name(before),
+      actual.type_name(after)
+    )),
+  }
+}
+
+/// Returns a text for the value `node`, read from `source`, that is the same for equal values, for
+/// comparing the items of sorted arrays.
+pub fn canonical(node: &Node, source: &str) -> String {
+  match node {
+    Node::Object { members, .. } => {
+      let mut members = members
+        .iter()
+        .map(|member| {
+          format!(
+            "{}:{}",
+            syntax::quote(&member.name, '"'),
+            canonical(&member.value, source)
+          )
+        })
+        .collect::<Vec<_>>();
+      members.sort();
+      format!("{{{}}}", members.join(","))
+    }
+    Node::Array { items, .. } => {
+      let mut items = items
+        .iter()
+        .map(|item| canonical(item, source))
+        .collect::<Vec<_>>();
+      items.sort();
+      format!("[{}]", items.join(","))
+    }
+    Node::String { value, .. } => syntax::quote(value, '"'),
+    Node::Literal { span } => source[span.start..span.end].to_string(),
+  }
+}
+
+/// Returns the number of comments in `source`.
+fn comments(source: &str) -> usize {
+  lexer::tokenize(source)
+    .iter()
+    .filter(|token| matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment))
+    .count()
+}
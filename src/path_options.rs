@@ -0,0 +1,435 @@
+//! Sets formatting options for the objects and arrays at matching paths, for the `paths` option:
+//!
+//!     {
+//!         paths: {
+//!             "/services/**/env": {sort_keys: true},
+//!             "/*/flags": {sort_arrays: true, one_element_lines: true},
+//!             "/services/legacy/env": {sort_keys: false},
+//!         },
+//!     }
+//!
+//! Each pattern is a [path pattern](../glob/index.html), and each of its options (`sort_keys`,
+//! `sort_arrays`, `one_element_lines`, and `trailing_commas`) applies to the objects and arrays
+//! whose paths match it, overriding the option for the whole document. When several patterns set
+//! the same option for a path, the pattern listed last takes precedence, so general patterns are
+//! listed first and more specific ones after them. json5format applies options to the objects in
+//! an array together, so a pattern matches array items with a segment that matches `*` (such as
+//! `*` itself), not with their indices.
+
+use crate::{glob::PathGlob, sorting, syntax::Node};
+use json5format::PathOption;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// The options that can be set for matching paths.
+pub const OPTIONS: &[&str] = &[
+  "sort_keys",
+  "sort_arrays",
+  "one_element_lines",
+  "trailing_commas",
+];
+
+/// The options set for the objects and arrays whose paths match a pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathRule {
+  /// The path pattern, which is a valid [PathGlob](../glob/struct.PathGlob.html).
+  pub pattern: String,
+  /// The value of each option the pattern sets, by name (one of [OPTIONS](constant.OPTIONS.html)).
+  pub options: BTreeMap<String, bool>,
+}
+
+/// Parses path rules from `text`, a semicolon-separated list of `pattern=option:value,...` pairs,
+/// such as `/deps=sort_arrays:true,one_element_lines:false` (for environment variables and
+/// directives).
+pub fn parse(text: &str) -> Option<Vec<PathRule>> {
+  text
+    .split(';')
+    .map(str::trim)
+    .filter(|rule| !rule.is_empty())
+    .map(|rule| {
+      let (pattern, options) = rule.split_once('=')?;
+      let pattern = pattern.trim();
+      pattern.parse::<PathGlob>().ok()?;
+      let options = options
+        .split(',')
+        .map(|option| {
+          let (name, value) = option.split_once(':')?;
+          let name = name.trim();
+          let value = value.trim().parse::<bool>().ok()?;
+          OPTIONS.contains(&name).then(|| (name.to_string(), value))
+        })
+        .collect::<Option<BTreeMap<_, _>>>()?;
+      Some(PathRule {
+        pattern: pattern.to_string(),
+        options,
+      })
+    })
+    .collect()
+}
+
+/// Returns the json5format path options `by_path` for the document whose top-level value is
+/// `root`, read from `source`, with the same effect and fewer paths. json5format copies its options
+/// for each path, at a cost that grows with the number of paths, so thousands of paths take
+/// quadratic time and memory. Where several properties of an object have the same options (and the
+/// same options below them), their paths are replaced by a single `*` path, which json5format
+/// applies to each property without options of its own; the other properties whose values are
+/// objects or arrays are given empty options of their own, so the `*` path does not apply to them.
+pub fn compact(
+  by_path: HashMap<&'static str, HashSet<PathOption>>,
+  root: &Node,
+  source: &str,
+) -> HashMap<&'static str, HashSet<PathOption>> {
+  let mut tree = Subpaths::default();
+  for (path, options) in by_path {
+    let mut node = &mut tree;
+    for segment in path
+      .split('/')
+      .skip(1)
+      .filter(|segment| !segment.is_empty())
+    {
+      node = node.children.entry(segment.to_string()).or_default();
+    }
+    node.options = Some(options);
+  }
+  let mut containers = HashMap::new();
+  containers_of(root, source, "/*".to_string(), &mut containers);
+  tree.compact("", &containers, &mut HashMap::new());
+  let mut by_path = HashMap::new();
+  tree.flatten("", &mut by_path);
+  by_path
+}
+
+/// The identifiers of path options, by their sorted options and the identifiers of the paths below
+/// them, by segment.
+type Ids = HashMap<(Vec<String>, Vec<(String, usize)>), usize>;
+
+/// The json5format options of a path and of the paths below it.
+#[derive(Default)]
+struct Subpaths {
+  /// The options of the path, if it is given any.
+  options: Option<HashSet<PathOption>>,
+  /// The paths one segment below, by segment.
+  children: BTreeMap<String, Subpaths>,
+}
+
+impl Subpaths {
+  /// Replaces the paths below this one, at `path`, by `*` paths where that makes fewer paths (see
+  /// [compact()](fn.compact.html)), where `containers` are the properties whose values are objects
+  /// or arrays, by json5format path. Returns an identifier that is equal for equal options, from
+  /// the identifiers given so far, in `ids`.
+  fn compact(
+    &mut self,
+    path: &str,
+    containers: &HashMap<String, Option<BTreeSet<String>>>,
+    ids: &mut Ids,
+  ) -> usize {
+    let mut keys = BTreeMap::new();
+    for (segment, child) in &mut self.children {
+      let id = child.compact(&format!("{}/{}", path, segment), containers, ids);
+      keys.insert(segment.clone(), id);
+    }
+    // Only the paths of the properties of objects, all of whose objects and arrays can be given
+    // paths of their own.
+    if let (false, Some(Some(named))) = (self.children.contains_key("*"), containers.get(path)) {
+      let mut sizes = HashMap::<usize, usize>::new();
+      for id in keys.values() {
+        *sizes.entry(*id).or_default() += 1;
+      }
+      let largest = sizes
+        .into_iter()
+        .max_by_key(|&(id, size)| (size, std::cmp::Reverse(id)));
+      let unlisted = named
+        .iter()
+        .filter(|name| !self.children.contains_key(*name))
+        .cloned()
+        .collect::<Vec<_>>();
+      if let Some((id, _)) = largest.filter(|&(_, size)| size > 1 + unlisted.len()) {
+        let mut wildcard = Subpaths::default();
+        self
+          .children
+          .retain(|segment, child| match keys[segment] == id {
+            true => {
+              wildcard = std::mem::take(child);
+              false
+            }
+            false => true,
+          });
+        keys.retain(|_, other| *other != id);
+        for name in unlisted {
+          let empty = Subpaths {
+            options: Some(HashSet::new()),
+            ..Subpaths::default()
+          };
+          keys.insert(name.clone(), empty.id(&BTreeMap::new(), ids));
+          self.children.insert(name, empty);
+        }
+        self.children.insert("*".to_string(), wildcard);
+        keys.insert("*".to_string(), id);
+      }
+    }
+    self.id(&keys, ids)
+  }
+
+  /// Returns the identifier of these options, where `keys` are the identifiers of the paths below,
+  /// by segment.
+  fn id(&self, keys: &BTreeMap<String, usize>, ids: &mut Ids) -> usize {
+    let mut options = self
+      .options
+      .iter()
+      .flatten()
+      .map(|option| format!("{:?}", option))
+      .collect::<Vec<_>>();
+    options.sort();
+    let key = (options, keys.clone().into_iter().collect());
+    let next = ids.len();
+    *ids.entry(key).or_insert(next)
+  }
+
+  /// Adds the options of this path, `path`, and the paths below it to `by_path`.
+  fn flatten(self, path: &str, by_path: &mut HashMap<&'static str, HashSet<PathOption>>) {
+    if let Some(options) = self.options {
+      let path = match path {
+        "" => "/".to_string(),
+        _ => path.to_string(),
+      };
+      by_path.insert(sorting::intern(path), options);
+    }
+    for (segment, child) in self.children {
+      child.flatten(&format!("{}/{}", path, segment), by_path);
+    }
+  }
+}
+
+/// Records, for `node` at the json5format `path` and its descendants, the names of the properties
+/// of each object whose values are objects or arrays (or `None` if one of them cannot be written in
+/// a path).
+fn containers_of(
+  node: &Node,
+  source: &str,
+  path: String,
+  containers: &mut HashMap<String, Option<BTreeSet<String>>>,
+) {
+  match node {
+    Node::Object { members, .. } => {
+      let mut named = Some(BTreeSet::new());
+      for member in members {
+        if !matches!(member.value, Node::Object { .. } | Node::Array { .. }) {
+          continue;
+        }
+        let stored = sorting::stored_name(member, source);
+        if stored.contains('/') || stored == "*" {
+          named = None;
+          continue;
+        }
+        containers_of(
+          &member.value,
+          source,
+          format!("{}/{}", path, stored),
+          containers,
+        );
+        if let Some(named) = &mut named {
+          named.insert(stored);
+        }
+      }
+      let entry = containers
+        .entry(path)
+        .or_insert_with(|| Some(BTreeSet::new()));
+      match (entry.as_mut(), named) {
+        (Some(listed), Some(named)) => listed.extend(named),
+        _ => *entry = None,
+      }
+    }
+    Node::Array { items, .. } => {
+      for item in items {
+        containers_of(item, source, format!("{}/*", path), containers);
+      }
+    }
+    Node::String { .. } | Node::Literal { .. } => {}
+  }
+}
+
+/// The compiled patterns of a list of path rules.
+pub struct PathRules<'a> {
+  /// Each rule and its compiled pattern, in the order listed.
+  rules: Vec<(PathGlob, &'a PathRule)>,
+}
+
+impl<'a> PathRules<'a> {
+  /// Compiles the patterns of `rules`.
+  pub fn new(rules: &'a [PathRule]) -> Self {
+    let rules = rules
+      .iter()
+      .filter_map(|rule| Some((rule.pattern.parse::<PathGlob>().ok()?, rule)))
+      .collect();
+    Self { rules }
+  }
+
+  /// Returns true if there are no rules.
+  pub fn is_empty(&self) -> bool {
+    self.rules.is_empty()
+  }
+
+  /// Returns the value of the option `name` for the path with the given `segments`, from the last
+  /// matching rule that sets it, if any.
+  pub fn get(&self, name: &str, segments: &[String]) -> Option<bool> {
+    self
+      .rules
+      .iter()
+      .rev()
+      .filter(|(glob, _)| glob.matches(segments))
+      .find_map(|(_, rule)| rule.options.get(name).copied())
+  }
+
+  /// Returns json5format path options for the objects and arrays in the document whose top-level
+  /// value is `root`, read from `source`, that set `sort_arrays`, `one_element_lines`, or
+  /// `trailing_commas`. (The `sort_keys` option is applied by ordering the properties; see
+  /// [property_orders()](../sorting/fn.property_orders.html).)
+  pub fn path_options(
+    &self,
+    root: &Node,
+    source: &str,
+  ) -> HashMap<&'static str, HashSet<PathOption>> {
+    let mut by_path = HashMap::new();
+    if !self.is_empty() {
+      self.visit(
+        root,
+        source,
+        "/*".to_string(),
+        &mut Vec::new(),
+        &mut by_path,
+      );
+    }
+    by_path
+  }
+
+  /// Adds the path options of `node`, at the json5format `path` whose decoded segments are
+  /// `segments`, and its descendants.
+  fn visit(
+    &self,
+    node: &Node,
+    source: &str,
+    path: String,
+    segments: &mut Vec<String>,
+    by_path: &mut HashMap<&'static str, HashSet<PathOption>>,
+  ) {
+    let children: Vec<(String, String, &Node)> = match node {
+      Node::Object { members, .. } => members
+        .iter()
+        .filter_map(|member| {
+          let stored = sorting::stored_name(member, source);
+          let path = format!("{}/{}", path, stored);
+          (!stored.contains('/') && stored != "*")
+            .then(|| (path, member.name.clone(), &member.value))
+        })
+        .collect(),
+      Node::Array { items, .. } => items
+        .iter()
+        .map(|item| (format!("{}/*", path), "*".to_string(), item))
+        .collect(),
+      Node::String { .. } | Node::Literal { .. } => return,
+    };
+    let mut options = HashSet::new();
+    if let Some(value) = self.get("sort_arrays", segments) {
+      options.insert(PathOption::SortArrayItems(value));
+    }
+    if let Some(value) = self.get("one_element_lines", segments) {
+      options.insert(PathOption::CollapseContainersOfOne(value));
+    }
+    if let Some(value) = self.get("trailing_commas", segments) {
+      options.insert(PathOption::TrailingCommas(value));
+    }
+    if !options.is_empty() && !by_path.contains_key(path.as_str()) {
+      by_path.insert(sorting::intern(path), options);
+    }
+    for (path, segment, child) in children {
+      segments.push(segment);
+      self.visit(child, source, path, segments, by_path);
+      segments.pop();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::syntax;
+  use json5format::{FormatOptions, Json5Format, ParsedDocument};
+
+  /// Returns `source` formatted with keys sorted and the path `rules` (as read by
+  /// [parse()](fn.parse.html)), with the path options compacted if `compacted` is true.
+  fn format(source: &str, rules: &str, compacted: bool) -> String {
+    let root = syntax::parse(source).unwrap().unwrap();
+    let rules = parse(rules).unwrap();
+    let rules = PathRules::new(&rules);
+    let mut by_path = rules.path_options(&root, source);
+    for (path, order) in sorting::property_orders(&root, source, true, &[], &[], &rules) {
+      by_path.entry(path).or_default().extend(order);
+    }
+    let paths = by_path.len();
+    if compacted {
+      by_path = compact(by_path, &root, source);
+      assert!(by_path.len() < paths);
+    }
+    let options = FormatOptions {
+      options_by_path: by_path,
+      ..FormatOptions::default()
+    };
+    let document = ParsedDocument::from_str(source, None).unwrap();
+    let formatted = Json5Format::with_options(options)
+      .unwrap()
+      .to_utf8(&document)
+      .unwrap();
+    String::from_utf8(formatted).unwrap()
+  }
+
+  #[test]
+  fn compacts_paths_without_changing_the_output() {
+    let source = r#"{
+      b: {y: 1, x: 2, list: [3, 1, 2]},
+      a: {y: 1, x: 2, list: [3, 1, 2]},
+      c: {y: 1, x: 2, list: [3, 1, 2]},
+      "quoted name": {y: 1, x: 2, list: [3, 1, 2]},
+      different: {z: 1, list: [2, 1]},
+      unsorted: [{d: 1, c: 2}],
+      number: 1,
+    }"#;
+    let compacted = format(source, "/*/list=sort_arrays:true", true);
+    assert_eq!(compacted, format(source, "/*/list=sort_arrays:true", false));
+    assert!(compacted.contains("list: [\n            1,\n            2,\n            3,"));
+  }
+
+  #[test]
+  fn keeps_the_options_of_containers_without_paths() {
+    let source = "{a: [3, 1, 2], b: [3, 1, 2], c: [3, 1, 2], other: [3, 1, 2]}";
+    let rules = "/a=sort_arrays:true;/b=sort_arrays:true;/c=sort_arrays:true";
+    let compacted = format(source, rules, true);
+    assert_eq!(compacted, format(source, rules, false));
+    assert!(compacted.contains("other: [\n        3,\n        1,"));
+  }
+
+  #[test]
+  fn does_not_compact_objects_with_properties_that_have_no_path() {
+    let source = r#"{a: {y: 1, x: 2}, b: {y: 1, x: 2}, c: {y: 1, x: 2}, "d/e": {y: 1, x: 2}}"#;
+    let root = syntax::parse(source).unwrap().unwrap();
+    let rules = PathRules::new(&[]);
+    let orders = sorting::property_orders(&root, source, true, &[], &[], &rules);
+    let paths = orders.len();
+    assert_eq!(compact(orders, &root, source).len(), paths);
+  }
+
+  #[test]
+  fn compacts_the_paths_of_many_objects() {
+    let objects = (0..20_000)
+      .map(|index| format!("k{}: {{b: 1, a: [2]}}", index))
+      .collect::<Vec<_>>()
+      .join(", ");
+    let source = format!("{{{}}}", objects);
+    let root = syntax::parse(&source).unwrap().unwrap();
+    let rules = PathRules::new(&[]);
+    let orders = sorting::property_orders(&root, &source, true, &[], &[], &rules);
+    assert_eq!(orders.len(), 20_001);
+    let compacted = compact(orders, &root, &source);
+    let mut paths = compacted.keys().copied().collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(paths, ["/*", "/*/*"]);
+  }
+}
@@ -0,0 +1,117 @@
+//! Converts paths to a form the operating system accepts regardless of their length.
+//!
+//! On Windows, most file APIs reject paths longer than 260 characters (`MAX_PATH`) unless they use
+//! the extended-length `\\?\` prefix, and shares are addressed as `\\?\UNC\server\share\...`.
+//! Extended-length paths are passed to the file system without normalization, so they must be
+//! absolute and use only `\` separators, with `.` and `..` components resolved. On other platforms,
+//! paths are used unchanged.
+
+use std::path::{Path, PathBuf};
+
+/// Returns `path` in a form that can be opened, written, and renamed regardless of its length: on
+/// Windows, an absolute extended-length path; otherwise, `path` itself.
+#[cfg(not(windows))]
+pub fn extended(path: &Path) -> PathBuf {
+  path.to_path_buf()
+}
+
+/// Returns `path` in a form that can be opened, written, and renamed regardless of its length: on
+/// Windows, an absolute extended-length path; otherwise, `path` itself.
+#[cfg(windows)]
+pub fn extended(path: &Path) -> PathBuf {
+  use std::path::{Component, Prefix};
+
+  let absolute = match std::path::absolute(path) {
+    Ok(absolute) => absolute,
+    Err(_) => return path.to_path_buf(),
+  };
+  let mut components = absolute.components();
+  let Some(Component::Prefix(prefix)) = components.next() else {
+    return absolute;
+  };
+  let mut extended = match prefix.kind() {
+    Prefix::Disk(letter) => PathBuf::from(format!(r"\\?\{}:\", letter as char)),
+    Prefix::UNC(server, share) => PathBuf::from(format!(
+      r"\\?\UNC\{}\{}\",
+      server.to_string_lossy(),
+      share.to_string_lossy()
+    )),
+    // Verbatim and device paths are passed through unchanged.
+    _ => return absolute,
+  };
+  for component in components {
+    match component {
+      Component::Normal(name) => extended.push(name),
+      Component::ParentDir => {
+        extended.pop();
+      }
+      Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+    }
+  }
+  extended
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[cfg(not(windows))]
+  #[test]
+  fn leaves_paths_unchanged() {
+    for path in ["a/../b.json5", "/tmp/./a.json5", "a.json5"] {
+      assert_eq!(extended(Path::new(path)), PathBuf::from(path));
+    }
+  }
+
+  #[cfg(windows)]
+  #[test]
+  fn prefixes_drive_paths() {
+    assert_eq!(
+      extended(Path::new(r"C:\config\..\files\.\a.json5")),
+      PathBuf::from(r"\\?\C:\files\a.json5")
+    );
+    assert_eq!(
+      extended(Path::new("C:/config/a.json5")),
+      PathBuf::from(r"\\?\C:\config\a.json5")
+    );
+  }
+
+  #[cfg(windows)]
+  #[test]
+  fn prefixes_unc_paths() {
+    assert_eq!(
+      extended(Path::new(r"\\server\share\config\..\a.json5")),
+      PathBuf::from(r"\\?\UNC\server\share\a.json5")
+    );
+  }
+
+  #[cfg(windows)]
+  #[test]
+  fn leaves_prefixed_paths_unchanged() {
+    for path in [
+      r"\\?\C:\config\..\a.json5",
+      r"\\?\UNC\server\share\a.json5",
+      r"\\.\pipe\json5",
+    ] {
+      assert_eq!(extended(Path::new(path)), PathBuf::from(path));
+    }
+  }
+
+  #[cfg(windows)]
+  #[test]
+  fn makes_relative_paths_absolute() {
+    let extended = extended(Path::new(r"config\.\..\files\a.json5"));
+    let text = extended.to_string_lossy();
+    assert!(text.starts_with(r"\\?\"), "{}", text);
+    assert!(text.ends_with(r"\files\a.json5"), "{}", text);
+    assert!(
+      !text.contains(r"\.\") && !text.contains(r"\..\"),
+      "{}",
+      text
+    );
+    let current = std::env::current_dir().unwrap();
+    let current = current.to_string_lossy();
+    let current = current.trim_start_matches(r"\\?\").trim_end_matches('\\');
+    assert!(text.contains(current.trim_start_matches(r"\\")), "{}", text);
+  }
+}
@@ -0,0 +1,257 @@
+//! Runs external plugin commands on each document, for custom lint rules and transformations.
+//!
+//! Plugins are configured in the configuration file as command lines (split on whitespace):
+//!
+//!     {
+//!         pre_format_plugins: ["./tools/check-ids"],
+//!         post_format_plugins: ["python3 tools/add-header.py"],
+//!     }
+//!
+//! A program given as a relative path (such as `./tools/check-ids`, but not `python3`) is relative
+//! to the directory of the configuration file that names it. The arguments are passed unchanged.
+//!
+//! Pre-format plugins run on the input before it is parsed, in order, and post-format plugins run
+//! on the formatted output. Each plugin is run once per document, and receives a JSON object on
+//! stdin:
+//!
+//!     {"version": 1, "stage": "pre", "filename": "config.json5", "source": "{a: 1}"}
+//!
+//! It must exit successfully and write a JSON (or JSON5) object to stdout, where every property is
+//! optional:
+//!
+//!     {
+//!         "source": "{a: 2}",
+//!         "diagnostics": [
+//!             {"line": 1, "col": 2, "severity": "warning", "message": "..."},
+//!         ],
+//!     }
+//!
+//! If `source` is given, it replaces the document for the following steps. Diagnostics are
+//! reported with the document's filename; a diagnostic with the severity `error` makes formatting
+//! the document fail, after all of the plugin's diagnostics are reported.
+
+use crate::syntax::{self, Node};
+use std::{
+  io::Write,
+  path::Path,
+  process::{Command, Stdio},
+};
+
+/// The version of the plugin protocol, sent to each plugin.
+pub const PROTOCOL_VERSION: usize = 1;
+
+/// When a plugin runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+  /// Before the document is parsed.
+  Pre,
+  /// After the document is formatted.
+  Post,
+}
+
+/// A diagnostic reported by a plugin.
+#[derive(Debug)]
+pub struct Diagnostic {
+  /// The 1-based line number the diagnostic applies to, if any.
+  pub line: Option<usize>,
+  /// The 1-based column number the diagnostic applies to, if any.
+  pub col: Option<usize>,
+  /// True if the diagnostic is an error, rather than a warning.
+  pub is_error: bool,
+  /// A description of the problem.
+  pub message: String,
+}
+
+/// The response of a plugin.
+#[derive(Debug, Default)]
+pub struct Response {
+  /// The replacement for the document, if the plugin changed it.
+  pub source: Option<String>,
+  /// The diagnostics reported by the plugin.
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Runs the plugin `command_line`, named by a configuration file in `directory` (if any), at the
+/// given `stage` on the document `source`, read from `filename`, and returns its response.
+pub fn run(
+  command_line: &str,
+  directory: Option<&Path>,
+  stage: Stage,
+  filename: &str,
+  source: &str,
+) -> Result<Response, anyhow::Error> {
+  let error = |message: String| anyhow::anyhow!("Plugin error: `{}`: {}", command_line, message);
+  let mut words = command_line.split_whitespace();
+  let program = Path::new(
+    words
+      .next()
+      .ok_or_else(|| error("The command is empty".to_string()))?,
+  );
+  // A bare program name is found on the `PATH`, and an absolute path is used as it is.
+  let program = match directory {
+    Some(directory) if program.is_relative() && program.components().count() > 1 => {
+      directory.join(program)
+    }
+    _ => program.to_path_buf(),
+  };
+  let mut child = Command::new(program)
+    .args(words)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .spawn()
+    .map_err(|err| error(err.to_string()))?;
+
+  let request = format!(
+    "{{\"version\": {}, \"stage\": \"{}\", \"filename\": {}, \"source\": {}}}\n",
+    PROTOCOL_VERSION,
+    match stage {
+      Stage::Pre => "pre",
+      Stage::Post => "post",
+    },
+    syntax::quote(filename, '"'),
+    syntax::quote(source, '"')
+  );
+  // Write the request on another thread, so a plugin that writes its response before reading all
+  // of its input cannot deadlock.
+  let mut stdin = child.stdin.take().expect("stdin is piped");
+  let writer = std::thread::spawn(move || stdin.write_all(request.as_bytes()));
+  let output = child
+    .wait_with_output()
+    .map_err(|err| error(err.to_string()))?;
+  // A plugin may exit without reading its input; only its exit status matters.
+  let _ = writer.join();
+  if !output.status.success() {
+    return Err(error(format!("The plugin failed ({})", output.status)));
+  }
+  let stdout = String::from_utf8(output.stdout).map_err(|err| error(err.to_string()))?;
+  parse_response(&stdout).map_err(|message| error(format!("Invalid response: {}", message)))
+}
+
+/// Parses a plugin's response from its output, `stdout`.
+fn parse_response(stdout: &str) -> Result<Response, String> {
+  let members = match syntax::parse(stdout).map_err(|err| err.to_string())? {
+    None => return Ok(Response::default()),
+    Some(Node::Object { members, .. }) => members,
+    Some(node) => {
+      return Err(format!(
+        "Expected an object, but found {}",
+        node.type_name(stdout)
+      ))
+    }
+  };
+  let mut response = Response::default();
+  for member in members {
+    match (member.name.as_str(), member.value) {
+      ("source", Node::String { value, .. }) => response.source = Some(value),
+      ("diagnostics", Node::Array { items, .. }) => {
+        for item in items {
+          response.diagnostics.push(parse_diagnostic(item, stdout)?);
+        }
+      }
+      (name, _) => return Err(format!("Unexpected or invalid property `{}`", name)),
+    }
+  }
+  Ok(response)
+}
+
+/// Parses a diagnostic object in a plugin's response.
+fn parse_diagnostic(node: Node, stdout: &str) -> Result<Diagnostic, String> {
+  let Node::Object { members, .. } = node else {
+    return Err("Expected each diagnostic to be an object".to_string());
+  };
+  let mut diagnostic = Diagnostic {
+    line: None,
+    col: None,
+    is_error: false,
+    message: String::new(),
+  };
+  for member in members {
+    match (member.name.as_str(), member.value) {
+      ("line" | "col", Node::Literal { span }) => {
+        let number = stdout[span.start..span.end]
+          .parse()
+          .ok()
+          .filter(|number| *number > 0)
+          .ok_or_else(|| format!("Expected `{}` to be a positive integer", member.name))?;
+        if member.name == "line" {
+          diagnostic.line = Some(number);
+        } else {
+          diagnostic.col = Some(number);
+        }
+      }
+      ("severity", Node::String { value, .. }) => {
+        diagnostic.is_error = match value.as_str() {
+          "error" => true,
+          "warning" => false,
+          _ => return Err(format!("Unknown severity \"{}\"", value)),
+        }
+      }
+      ("message", Node::String { value, .. }) => diagnostic.message = value,
+      (name, _) => {
+        return Err(format!(
+          "Unexpected or invalid diagnostic property `{}`",
+          name
+        ))
+      }
+    }
+  }
+  Ok(diagnostic)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_responses() {
+    let response = parse_response(
+      r#"{"source": "{a: 2}", "diagnostics": [{"line": 3, "col": 1, "severity": "error",
+         "message": "No"}, {"message": "Maybe"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(response.source.as_deref(), Some("{a: 2}"));
+    let diagnostics = response
+      .diagnostics
+      .iter()
+      .map(|diagnostic| {
+        (
+          diagnostic.line,
+          diagnostic.col,
+          diagnostic.is_error,
+          diagnostic.message.as_str(),
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      diagnostics,
+      [(Some(3), Some(1), true, "No"), (None, None, false, "Maybe")]
+    );
+    assert!(parse_response("").unwrap().diagnostics.is_empty());
+  }
+
+  #[test]
+  fn rejects_positions_that_are_not_positive() {
+    for position in ["0", "-1", "1.5"] {
+      assert_eq!(
+        parse_response(&format!(r#"{{"diagnostics": [{{"line": {}}}]}}"#, position)).unwrap_err(),
+        "Expected `line` to be a positive integer"
+      );
+    }
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn runs_relative_programs_from_the_configuration_directory() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let dir = crate::write_mode::private_directory("jsonpretty5er-plugin-test").unwrap();
+    fs::create_dir_all(dir.join("tools")).unwrap();
+    let program = dir.join("tools").join("plugin");
+    fs::write(&program, "#!/bin/sh\necho '{\"source\": \"[1]\"}'\n").unwrap();
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o700)).unwrap();
+    let response = run("./tools/plugin", Some(&dir), Stage::Pre, "a.json5", "{}").unwrap();
+    assert_eq!(response.source.as_deref(), Some("[1]"));
+    assert!(run("./tools/plugin", None, Stage::Pre, "a.json5", "{}").is_err());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,80 @@
+//! Rewrites the quotes of string values (the `quotes` option): with `double`, each single-quoted
+//! string is written with double quotes, and with `single`, each double-quoted string with single
+//! quotes, escaping the new quote character where it appears in the string. Property names are not
+//! changed (see `--dialect` for quoting them).
+
+use crate::{
+  edit::Edits,
+  format::FormatError,
+  syntax::{self, Node},
+};
+use std::str::FromStr;
+
+/// How to quote string values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quotes {
+  /// Keep the quotes of each string as written.
+  #[default]
+  Preserve,
+  /// Write strings with double quotes.
+  Double,
+  /// Write strings with single quotes.
+  Single,
+}
+
+impl Quotes {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["preserve", "double", "single"];
+
+  /// Returns the spelling of the style, as in the configuration file.
+  pub fn name(self) -> &'static str {
+    match self {
+      Quotes::Preserve => "preserve",
+      Quotes::Double => "double",
+      Quotes::Single => "single",
+    }
+  }
+}
+
+impl FromStr for Quotes {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "preserve" => Ok(Quotes::Preserve),
+      "double" => Ok(Quotes::Double),
+      "single" => Ok(Quotes::Single),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", Quotes::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// Adds edits that rewrite each string value in `root`, parsed from `source`, that is not quoted as
+/// selected by `mode`.
+pub fn quotes(root: &Node, source: &str, mode: Quotes, edits: &mut Edits) {
+  let quote = match mode {
+    Quotes::Preserve => return,
+    Quotes::Double => '"',
+    Quotes::Single => '\'',
+  };
+  match root {
+    Node::Object { members, .. } => {
+      for member in members {
+        quotes(&member.value, source, mode, edits);
+      }
+    }
+    Node::Array { items, .. } => {
+      for item in items {
+        quotes(item, source, mode, edits);
+      }
+    }
+    Node::String { span, value } => {
+      if !source[span.start..].starts_with(quote) {
+        edits.replace(span.start..span.end, syntax::quote(value, quote));
+      }
+    }
+    Node::Literal { .. } => {}
+  }
+}
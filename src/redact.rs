@@ -0,0 +1,20 @@
+//! Replaces secret string values with a placeholder (the `--redact` option), so formatted
+//! documents can be shared safely.
+
+use crate::{edit::Edits, glob::PathGlob, syntax::Node};
+
+/// The string that replaces each redacted value.
+pub const PLACEHOLDER: &str = "\"<redacted>\"";
+
+/// Adds an edit to `edits` that replaces each string in the document `root` whose path matches
+/// one of the `globs` with the [PLACEHOLDER](constant.PLACEHOLDER.html). Other values, including
+/// objects and arrays whose paths match, are unchanged.
+pub fn redact(root: &Node, globs: &[PathGlob], edits: &mut Edits) {
+  root.walk(&mut |node, path| {
+    if let Node::String { span, .. } = node {
+      if globs.iter().any(|glob| glob.matches(path)) {
+        edits.replace(span.start..span.end, PLACEHOLDER);
+      }
+    }
+  });
+}
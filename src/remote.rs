@@ -0,0 +1,41 @@
+//! Reads documents from `http://` and `https://` URLs given in place of files, when built with the
+//! `http` feature. The document is fetched with the `curl` command, which avoids linking a TLS
+//! implementation into the formatter.
+
+/// Returns true if `name` (an input file argument) is a URL.
+pub fn is_url(name: &str) -> bool {
+  name.starts_with("http://") || name.starts_with("https://")
+}
+
+/// Fetches the document at `url`.
+#[cfg(feature = "http")]
+pub fn fetch(url: &str) -> Result<String, anyhow::Error> {
+  let output = std::process::Command::new("curl")
+    .args([
+      "--fail",
+      "--silent",
+      "--show-error",
+      "--location",
+      "--",
+      url,
+    ])
+    .output()
+    .map_err(|err| anyhow::anyhow!("{}: Cannot run curl: {}", url, err))?;
+  if !output.status.success() {
+    return Err(anyhow::anyhow!(
+      "{}: {}",
+      url,
+      String::from_utf8_lossy(&output.stderr).trim()
+    ));
+  }
+  Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Fails, because reading from URLs requires the `http` feature.
+#[cfg(not(feature = "http"))]
+pub fn fetch(url: &str) -> Result<String, anyhow::Error> {
+  Err(anyhow::anyhow!(
+    "{}: Reading from URLs requires building formatjson5 with the `http` feature",
+    url
+  ))
+}
@@ -0,0 +1,341 @@
+//! Repairs common mistakes in JSON5 documents that would otherwise fail to parse, for the `fix`
+//! subcommand. Each repair is a small edit to the source text, reported so the author can review
+//! it.
+
+use crate::{
+  edit::Edits,
+  format::FormatError,
+  lexer::{self, Token, TokenKind},
+};
+use std::str::FromStr;
+
+/// Which repairs to apply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FixLevel {
+  /// Apply no repairs.
+  None,
+  /// Apply repairs that cannot change the meaning of a document that was written as intended:
+  /// removing stray commas (a comma that does not follow a value, as in `[1,,2]`), inserting a
+  /// missing comma between values on separate lines, and, for strict JSON, removing trailing
+  /// commas (as in `[1, 2,]`).
+  Safe,
+  /// Also close an unterminated string and any unclosed objects and arrays at the end of the
+  /// document, which assumes that the document was cut off rather than malformed.
+  All,
+}
+
+impl FixLevel {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["none", "safe", "all"];
+}
+
+impl FromStr for FixLevel {
+  type Err = FormatError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "none" => Ok(FixLevel::None),
+      "safe" => Ok(FixLevel::Safe),
+      "all" => Ok(FixLevel::All),
+      _ => Err(FormatError::Config {
+        message: format!("Expected one of: {}", FixLevel::VARIANTS.join(", ")),
+      }),
+    }
+  }
+}
+
+/// A repair made to a document.
+#[derive(Debug)]
+pub struct Repair {
+  /// The 1-based line number of the repair, in the original source.
+  pub line: usize,
+  /// The 1-based character column of the repair, in the original source.
+  pub col: usize,
+  /// A description of the repair.
+  pub message: String,
+}
+
+/// Applies the repairs selected by `level` to the document that starts at byte offset `start` of
+/// `buffer` (after any preamble), and returns the repaired buffer and the repairs that were made.
+/// Trailing commas are removed only if `trailing_commas` (whether the dialect allows them) is
+/// false. Content after the end of the document's top-level value is not changed.
+pub fn repair(
+  buffer: &str,
+  start: usize,
+  level: FixLevel,
+  trailing_commas: bool,
+) -> (String, Vec<Repair>) {
+  if level == FixLevel::None {
+    return (buffer.to_string(), Vec::new());
+  }
+  let first_line = buffer[..start].matches('\n').count();
+  let tokens = lexer::tokenize(&buffer[start..]);
+  // The edits are relative to `start`.
+  let mut edits = Edits::default();
+  let mut repairs = Vec::new();
+  let mut report = |token: &Token, message: String| {
+    repairs.push(Repair {
+      line: first_line + token.line,
+      col: token.col,
+      message,
+    })
+  };
+  let mut open: Vec<Token> = Vec::new();
+  let mut previous: Option<Token> = None;
+  let mut newline_since_previous = false;
+  let mut complete = false;
+  for token in &tokens {
+    if token.kind == TokenKind::Newline {
+      newline_since_previous = true;
+    }
+    if token.is_trivia() {
+      continue;
+    }
+    if complete {
+      break;
+    }
+    let after = previous.map(|previous| previous.kind);
+    match token.kind {
+      TokenKind::Comma
+        if matches!(
+          after,
+          Some(TokenKind::OpenBrace | TokenKind::OpenBracket | TokenKind::Comma)
+        ) =>
+      {
+        report(token, "Removed a stray ','".to_string());
+        edits.replace(token.start..token.end, "");
+        continue; // the previous token is unchanged
+      }
+      TokenKind::CloseBrace | TokenKind::CloseBracket
+        if !trailing_commas && after == Some(TokenKind::Comma) =>
+      {
+        let comma = previous.unwrap();
+        report(&comma, "Removed a trailing ','".to_string());
+        edits.replace(comma.start..comma.end, "");
+      }
+      TokenKind::String | TokenKind::Word | TokenKind::OpenBrace | TokenKind::OpenBracket
+        if newline_since_previous && !open.is_empty() && after.is_some_and(ends_value) =>
+      {
+        let previous = previous.unwrap();
+        report(
+          &previous,
+          "Inserted a missing ',' after the value".to_string(),
+        );
+        edits.insert(previous.end, ",");
+      }
+      _ => {}
+    }
+    match token.kind {
+      TokenKind::OpenBrace | TokenKind::OpenBracket => open.push(*token),
+      TokenKind::CloseBrace | TokenKind::CloseBracket => {
+        open.pop();
+        complete = open.is_empty();
+      }
+      TokenKind::String | TokenKind::Word => complete = open.is_empty(),
+      _ => {}
+    }
+    previous = Some(*token);
+    newline_since_previous = false;
+  }
+
+  let end = buffer.len() - start;
+  if level >= FixLevel::All {
+    // An unterminated string ends at the end of its line, and is the last token before any
+    // comments and whitespace at the end of the document.
+    let last_token = tokens.iter().rev().find(|token| !token.is_trivia());
+    if let Some(last) = previous.filter(|last| {
+      last.kind == TokenKind::String && last_token.is_some_and(|token| token.start == last.start)
+    }) {
+      let literal = &buffer[start + last.start..start + last.end];
+      if unterminated(literal) {
+        report(
+          &last,
+          "Closed a string that is unterminated at the end of the document".to_string(),
+        );
+        // The string ends before the line break, so the quote closes it on its line.
+        edits.insert(last.end, &literal[..1]);
+      }
+    }
+    for token in open.iter().rev() {
+      let (kind, close) = match token.kind {
+        TokenKind::OpenBrace => ("object", "}"),
+        _ => ("array", "]"),
+      };
+      report(
+        token,
+        format!(
+          "Closed an {} that is unclosed at the end of the document",
+          kind
+        ),
+      );
+      edits.insert(end, close);
+    }
+  }
+
+  let repaired = buffer[..start].to_string() + &edits.apply(&buffer[start..]);
+  (repaired, repairs)
+}
+
+/// Returns true if the string `literal` (from its opening quote to the end of the token) has no
+/// closing quote.
+fn unterminated(literal: &str) -> bool {
+  let mut chars = literal.chars();
+  let Some(quote) = chars.next() else {
+    return false;
+  };
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => {
+        chars.next();
+      }
+      c if c == quote => return false,
+      _ => {}
+    }
+  }
+  true
+}
+
+/// Returns true if a token of the given `kind` can be the last token of a value.
+fn ends_value(kind: TokenKind) -> bool {
+  matches!(
+    kind,
+    TokenKind::String | TokenKind::Word | TokenKind::CloseBrace | TokenKind::CloseBracket
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the repaired `source` and the messages of the repairs, at the `level`, in a dialect
+  /// that allows trailing commas if `trailing_commas` is true.
+  fn repaired(source: &str, level: FixLevel, trailing_commas: bool) -> (String, Vec<String>) {
+    let (repaired, repairs) = repair(source, 0, level, trailing_commas);
+    let messages = repairs
+      .into_iter()
+      .map(|repair| format!("{}:{}: {}", repair.line, repair.col, repair.message))
+      .collect();
+    (repaired, messages)
+  }
+
+  #[test]
+  fn none_makes_no_repairs() {
+    let source = "[1,,2\n3";
+    assert_eq!(
+      repaired(source, FixLevel::None, false),
+      (source.to_string(), vec![])
+    );
+  }
+
+  #[test]
+  fn removes_stray_commas() {
+    assert_eq!(
+      repaired("[,1,,2]\n", FixLevel::Safe, true),
+      (
+        "[1,2]\n".to_string(),
+        vec![
+          "1:2: Removed a stray ','".to_string(),
+          "1:5: Removed a stray ','".to_string()
+        ]
+      )
+    );
+  }
+
+  #[test]
+  fn inserts_missing_commas_between_lines() {
+    assert_eq!(
+      repaired("{\n  a: 1\n  b: [1]\n  c: {}\n}\n", FixLevel::Safe, true),
+      (
+        "{\n  a: 1,\n  b: [1],\n  c: {}\n}\n".to_string(),
+        vec![
+          "2:6: Inserted a missing ',' after the value".to_string(),
+          "3:8: Inserted a missing ',' after the value".to_string()
+        ]
+      )
+    );
+  }
+
+  #[test]
+  fn removes_trailing_commas_only_for_strict_json() {
+    let source = "{\"a\": [1, 2,],}\n";
+    assert_eq!(
+      repaired(source, FixLevel::Safe, false),
+      (
+        "{\"a\": [1, 2]}\n".to_string(),
+        vec![
+          "1:12: Removed a trailing ','".to_string(),
+          "1:14: Removed a trailing ','".to_string()
+        ]
+      )
+    );
+    assert_eq!(
+      repaired(source, FixLevel::Safe, true),
+      (source.to_string(), vec![])
+    );
+  }
+
+  #[test]
+  fn closes_an_unterminated_string_before_the_final_line_break() {
+    assert_eq!(
+      repaired("{\n  a: 'abc\n", FixLevel::All, true),
+      (
+        "{\n  a: 'abc'\n}".to_string(),
+        vec![
+          "2:6: Closed a string that is unterminated at the end of the document".to_string(),
+          "1:1: Closed an object that is unclosed at the end of the document".to_string()
+        ]
+      )
+    );
+  }
+
+  #[test]
+  fn closes_an_unterminated_string_before_trailing_comments() {
+    let (repaired, _) = repaired("[\"abc\n// end\n", FixLevel::All, true);
+    assert_eq!(repaired, "[\"abc\"\n// end\n]");
+  }
+
+  #[test]
+  fn leaves_a_terminated_string_unchanged() {
+    let (repaired, messages) = repaired("[\"a\\\"\"\n", FixLevel::All, true);
+    assert_eq!(repaired, "[\"a\\\"\"\n]");
+    assert_eq!(
+      messages,
+      vec!["1:1: Closed an array that is unclosed at the end of the document".to_string()]
+    );
+  }
+
+  #[test]
+  fn leaves_an_unterminated_string_before_other_values_unchanged() {
+    let (_, messages) = repaired("{a: \"x\nb: 1}", FixLevel::All, true);
+    assert!(messages
+      .iter()
+      .all(|message| !message.contains("Closed a string")));
+  }
+
+  #[test]
+  fn closes_brackets_only_at_level_all() {
+    let source = "{\n  a: [1,\n";
+    assert_eq!(
+      repaired(source, FixLevel::Safe, true),
+      (source.to_string(), vec![])
+    );
+    assert_eq!(
+      repaired(source, FixLevel::All, true),
+      (
+        "{\n  a: [1,\n]}".to_string(),
+        vec![
+          "2:6: Closed an array that is unclosed at the end of the document".to_string(),
+          "1:1: Closed an object that is unclosed at the end of the document".to_string()
+        ]
+      )
+    );
+  }
+
+  #[test]
+  fn leaves_content_after_the_document_unchanged() {
+    assert_eq!(
+      repaired("[1]\n[,2\n", FixLevel::All, false),
+      ("[1]\n[,2\n".to_string(), vec![])
+    );
+  }
+}
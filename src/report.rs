@@ -0,0 +1,530 @@
+//! Records the problems found in a run, for the report printed by `--report`. By default
+//! (`--report` or `--report=summary`) the report counts the diagnostics reported and suppressed,
+//! by code, on stderr. Warnings reported as errors (by `--deny-warnings` or `--deny`) are counted
+//! as reported, and also as denied, so the summary tells them apart from the diagnostics that are
+//! always errors.
+//!
+//! The other formats list every problem, for CI servers to ingest, and are written to the file
+//! given with `--report-file` (or to stdout):
+//!
+//! - `checkstyle` writes a Checkstyle XML document, with an `<error>` for each diagnostic, error,
+//!   and check failure of each file, which the quality gates of Jenkins and GitLab read.
+//! - `junit` writes a JUnit XML document, with a test case for each file, which fails if the file
+//!   has errors (or is not formatted, for `check`), and lists the file's warnings otherwise.
+//! - `sarif` writes a SARIF 2.1.0 log (see the [sarif](../sarif/index.html) module), with the
+//!   metadata of each code, for GitHub code scanning and other SARIF viewers.
+//!
+//! Errors that fail a file whose error diagnostics were already recorded (such as `Lint error:
+//! <file>: Found 2 error(s)`) are not recorded again.
+
+use crate::{
+  codes::{self, Code},
+  diagnostics::{Diagnostic, Severity},
+  error_format::ErrorParts,
+  lexer::{self, TokenKind},
+  sarif,
+};
+use std::{collections::BTreeMap, str::FromStr, sync::Mutex};
+
+/// The name of the tool, in the reports.
+pub const TOOL: &str = "formatjson5";
+
+/// How a report is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+  /// The number of diagnostics of each code, for people.
+  #[default]
+  Summary,
+  /// A Checkstyle XML document.
+  Checkstyle,
+  /// A JUnit XML document.
+  Junit,
+  /// A SARIF log.
+  Sarif,
+}
+
+impl ReportFormat {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["summary", "checkstyle", "junit", "sarif"];
+}
+
+impl FromStr for ReportFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "summary" => Ok(ReportFormat::Summary),
+      "checkstyle" => Ok(ReportFormat::Checkstyle),
+      "junit" => Ok(ReportFormat::Junit),
+      "sarif" => Ok(ReportFormat::Sarif),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        ReportFormat::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// The number of diagnostics of each code that were reported, denied, and suppressed, and the
+/// problems of each file, which may be recorded from several threads.
+#[derive(Debug, Default)]
+pub struct Report {
+  /// The counts of each code, by code.
+  counts: Mutex<BTreeMap<&'static str, Counts>>,
+  /// The problems of each file processed, by file name.
+  files: Mutex<BTreeMap<String, Vec<Problem>>>,
+}
+
+/// The number of diagnostics of a code.
+#[derive(Debug)]
+struct Counts {
+  /// The code.
+  code: &'static Code,
+  /// The number of diagnostics reported.
+  reported: usize,
+  /// The number of the reported diagnostics that were warnings reported as errors.
+  denied: usize,
+  /// The number of diagnostics silenced by suppression comments.
+  suppressed: usize,
+}
+
+/// A problem found in a file.
+#[derive(Debug)]
+pub struct Problem {
+  /// The 1-based line of the problem, if known.
+  pub line: Option<usize>,
+  /// The 1-based character column of the problem, if known.
+  pub col: Option<usize>,
+  /// The 1-based line and character column just past the end of the problem, if known.
+  pub end: Option<(usize, usize)>,
+  /// How serious the problem is.
+  pub severity: Severity,
+  /// The kind of problem, if it has a code.
+  pub code: Option<&'static Code>,
+  /// The name of the lint rule that found the problem, if any.
+  pub rule: Option<&'static str>,
+  /// Whether the problem is that the file is not formatted.
+  pub unformatted: bool,
+  /// A description of the problem.
+  pub message: String,
+}
+
+impl Problem {
+  /// Returns the name of the check that found the problem, such as `formatjson5.no-empty-object`.
+  fn source(&self) -> String {
+    match (self.rule, self.code) {
+      (Some(rule), _) => format!("{}.{}", TOOL, rule),
+      (None, Some(code)) => format!("{}.{}", TOOL, code.name),
+      (None, None) => TOOL.to_string(),
+    }
+  }
+
+  /// Returns the problem as a line of text, such as `config.json5:3:6: Error[J5F001]: ...`.
+  fn describe(&self, filename: &str) -> String {
+    let location = match (self.line, self.col) {
+      (Some(line), Some(col)) => format!("{}:{}:{}", filename, line, col),
+      (Some(line), None) => format!("{}:{}", filename, line),
+      _ => filename.to_string(),
+    };
+    let code = self
+      .code
+      .map_or_else(String::new, |code| format!("[{}]", code.id));
+    format!(
+      "{}: {}{}: {}",
+      location,
+      self.severity.label(),
+      code,
+      self.message
+    )
+  }
+}
+
+impl Report {
+  /// Counts a diagnostic of the kind `code`, which was suppressed if `suppressed` is true, and is
+  /// a warning reported as an error if `denied` is true.
+  pub fn count(&self, code: &'static Code, suppressed: bool, denied: bool) {
+    let mut counts = self.counts.lock().unwrap_or_else(|err| err.into_inner());
+    let counts = counts.entry(code.id).or_insert(Counts {
+      code,
+      reported: 0,
+      denied: 0,
+      suppressed: 0,
+    });
+    match (suppressed, denied) {
+      (true, _) => counts.suppressed += 1,
+      (false, true) => {
+        counts.reported += 1;
+        counts.denied += 1;
+      }
+      (false, false) => counts.reported += 1,
+    }
+  }
+
+  /// Records that the file `filename` was processed, so the reports list it even if it has no
+  /// problems.
+  pub fn file(&self, filename: &str) {
+    self.add(filename, None);
+  }
+
+  /// Records the reported `diagnostic` of the file `filename`, found in the document `source`.
+  pub fn diagnostic(&self, filename: &str, diagnostic: &Diagnostic, source: &str) {
+    self.add(
+      filename,
+      Some(Problem {
+        line: Some(diagnostic.line),
+        col: Some(diagnostic.col),
+        end: token_end(source, diagnostic.line, diagnostic.col),
+        severity: diagnostic.severity,
+        code: Some(diagnostic.code),
+        rule: diagnostic.rule,
+        unformatted: false,
+        message: diagnostic.message.clone(),
+      }),
+    );
+  }
+
+  /// Records the error `err`, which failed the file `filename`, unless an error of the file was
+  /// already recorded.
+  pub fn error(&self, filename: &str, err: &anyhow::Error) {
+    let message = err.to_string();
+    let parts = ErrorParts::new(&message, Some(filename));
+    let problem = Problem {
+      line: parts.location.line,
+      col: parts.location.col,
+      end: None,
+      severity: Severity::Error,
+      code: parts.code().and_then(|id| codes::find(id).ok()),
+      rule: None,
+      unformatted: false,
+      // The code tells the kind of an error that has one.
+      message: match (parts.title, parts.code()) {
+        ("Error", _) | (_, Some(_)) => parts.text.to_string(),
+        (title, None) => format!("{}: {}", title, parts.text),
+      },
+    };
+    let mut files = self.files.lock().unwrap_or_else(|err| err.into_inner());
+    let problems = files.entry(filename.to_string()).or_default();
+    if !problems
+      .iter()
+      .any(|problem| problem.severity == Severity::Error)
+    {
+      problems.push(problem);
+    }
+  }
+
+  /// Records that the file `filename` is not formatted, with the `message`, at the 1-based `line`
+  /// that formatting changes first, if known.
+  pub fn unformatted(&self, filename: &str, line: Option<usize>, message: &str) {
+    self.add(
+      filename,
+      Some(Problem {
+        line,
+        col: None,
+        end: None,
+        severity: Severity::Error,
+        code: None,
+        rule: None,
+        unformatted: true,
+        message: message.to_string(),
+      }),
+    );
+  }
+
+  /// Records that the file `filename` was processed, with the `problem`, if any.
+  fn add(&self, filename: &str, problem: Option<Problem>) {
+    let mut files = self.files.lock().unwrap_or_else(|err| err.into_inner());
+    let problems = files.entry(filename.to_string()).or_default();
+    problems.extend(problem);
+  }
+
+  /// Returns the report, in the `format`.
+  pub fn render(&self, format: ReportFormat) -> String {
+    match format {
+      ReportFormat::Summary => self.summary(),
+      ReportFormat::Checkstyle => self.checkstyle(),
+      ReportFormat::Junit => self.junit(),
+      ReportFormat::Sarif => {
+        let files = self.files.lock().unwrap_or_else(|err| err.into_inner());
+        sarif::log(&files).to_pretty_string()
+      }
+    }
+  }
+
+  /// Returns the summary of the diagnostics counted, by code.
+  fn summary(&self) -> String {
+    let counts = self.counts.lock().unwrap_or_else(|err| err.into_inner());
+    let mut summary = "Diagnostics:\n".to_string();
+    let (mut total_reported, mut total_denied, mut total_suppressed) = (0, 0, 0);
+    for counts in counts.values() {
+      summary.push_str(&format!(
+        "  {} {}: {} reported{}, {} suppressed\n",
+        counts.code.id,
+        counts.code.name,
+        counts.reported,
+        denied_note(counts.denied),
+        counts.suppressed
+      ));
+      total_reported += counts.reported;
+      total_denied += counts.denied;
+      total_suppressed += counts.suppressed;
+    }
+    summary.push_str(&format!(
+      "Total: {} reported{}, {} suppressed\n",
+      total_reported,
+      denied_note(total_denied),
+      total_suppressed
+    ));
+    summary
+  }
+
+  /// Returns the problems of each file, as a Checkstyle XML document.
+  fn checkstyle(&self) -> String {
+    let files = self.files.lock().unwrap_or_else(|err| err.into_inner());
+    let mut xml =
+      "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n".to_string();
+    for (filename, problems) in files.iter() {
+      if problems.is_empty() {
+        xml.push_str(&format!("  <file name=\"{}\"/>\n", escape(filename)));
+        continue;
+      }
+      xml.push_str(&format!("  <file name=\"{}\">\n", escape(filename)));
+      for problem in problems {
+        let mut attributes = Vec::new();
+        if let Some(line) = problem.line {
+          attributes.push(format!("line=\"{}\"", line));
+        }
+        if let Some(col) = problem.col {
+          attributes.push(format!("column=\"{}\"", col));
+        }
+        let severity = match problem.severity {
+          Severity::Warning => "warning",
+          Severity::Error => "error",
+        };
+        attributes.push(format!("severity=\"{}\"", severity));
+        attributes.push(format!("message=\"{}\"", escape(&problem.message)));
+        attributes.push(format!("source=\"{}\"", escape(&problem.source())));
+        xml.push_str(&format!("    <error {}/>\n", attributes.join(" ")));
+      }
+      xml.push_str("  </file>\n");
+    }
+    xml.push_str("</checkstyle>\n");
+    xml
+  }
+
+  /// Returns the problems of each file, as a JUnit XML document with a test case for each file.
+  fn junit(&self) -> String {
+    let files = self.files.lock().unwrap_or_else(|err| err.into_inner());
+    let failing = |problems: &[Problem]| {
+      problems
+        .iter()
+        .any(|problem| problem.severity == Severity::Error)
+    };
+    let failures = files.values().filter(|problems| failing(problems)).count();
+    let counts = format!("tests=\"{}\" failures=\"{}\"", files.len(), failures);
+    let mut xml = format!(
+      "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"{}\" {}>\n  <testsuite name=\"{}\" {} errors=\"0\">\n",
+      TOOL, counts, TOOL, counts
+    );
+    for (filename, problems) in files.iter() {
+      let testcase = format!(
+        "    <testcase classname=\"{}\" name=\"{}\"",
+        TOOL,
+        escape(filename)
+      );
+      if problems.is_empty() {
+        xml.push_str(&format!("{}/>\n", testcase));
+        continue;
+      }
+      let text = problems
+        .iter()
+        .map(|problem| escape(&problem.describe(filename)))
+        .collect::<Vec<_>>()
+        .join("\n");
+      xml.push_str(&format!("{}>\n", testcase));
+      match problems
+        .iter()
+        .find(|problem| problem.severity == Severity::Error)
+      {
+        Some(first) => xml.push_str(&format!(
+          "      <failure message=\"{}\" type=\"{}\">{}</failure>\n",
+          escape(&first.message),
+          escape(&first.source()),
+          text
+        )),
+        None => xml.push_str(&format!("      <system-out>{}</system-out>\n", text)),
+      }
+      xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+  }
+}
+
+/// Returns the note of the number of `denied` warnings among the diagnostics reported, if any.
+fn denied_note(denied: usize) -> String {
+  match denied {
+    0 => String::new(),
+    denied => format!(" ({} denied warning(s))", denied),
+  }
+}
+
+/// Returns the 1-based line and character column just past the token at `line` and `col` of
+/// `source` (or `None` if no token starts there, or it ends on another line).
+fn token_end(source: &str, line: usize, col: usize) -> Option<(usize, usize)> {
+  let text = source.split('\n').nth(line.checked_sub(1)?)?;
+  let (start, _) = text.char_indices().nth(col.checked_sub(1)?)?;
+  let token = *lexer::tokenize(&text[start..]).first()?;
+  match token.kind {
+    TokenKind::Whitespace | TokenKind::Newline => None,
+    _ => Some((line, col + text[start..start + token.end].chars().count())),
+  }
+}
+
+/// Returns `text` escaped for XML text and attribute values, without the control characters that
+/// XML cannot hold.
+fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      '\n' | '\t' | '\r' => escaped.push(c),
+      c if c.is_control() && (c as u32) < 0x20 => escaped.push('\u{fffd}'),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn counts_the_diagnostics_of_each_code() {
+    let report = Report::default();
+    report.count(&codes::NO_EMPTY_OBJECT, false, false);
+    report.count(&codes::NO_EMPTY_OBJECT, true, false);
+    report.count(&codes::NO_EMPTY_OBJECT, false, false);
+    report.count(&codes::UNKNOWN_KEY, true, false);
+    assert_eq!(
+      report.render(ReportFormat::Summary),
+      "\
+Diagnostics:
+  J5F003 unknown-key: 0 reported, 1 suppressed
+  J5F008 no-empty-object: 2 reported, 1 suppressed
+Total: 2 reported, 2 suppressed
+"
+    );
+  }
+
+  #[test]
+  fn summarizes_a_run_without_diagnostics() {
+    assert_eq!(
+      Report::default().render(ReportFormat::Summary),
+      "Diagnostics:\nTotal: 0 reported, 0 suppressed\n"
+    );
+  }
+
+  #[test]
+  fn counts_denied_warnings_as_reported() {
+    let report = Report::default();
+    report.count(&codes::NO_EMPTY_OBJECT, false, true);
+    report.count(&codes::NO_EMPTY_OBJECT, false, false);
+    report.count(&codes::UNKNOWN_KEY, false, false);
+    assert_eq!(
+      report.render(ReportFormat::Summary),
+      "\
+Diagnostics:
+  J5F003 unknown-key: 1 reported, 0 suppressed
+  J5F008 no-empty-object: 2 reported (1 denied warning(s)), 0 suppressed
+Total: 3 reported (1 denied warning(s)), 0 suppressed
+"
+    );
+  }
+
+  /// Returns a report of a warning of `a.json5`, an error of `b <1>.json5` (and a second error,
+  /// which is not recorded), and no problems of `c.json5`.
+  fn sample() -> Report {
+    let report = Report::default();
+    let source = "{a: {}}";
+    let diagnostic = Diagnostic {
+      code: &codes::NO_EMPTY_OBJECT,
+      severity: Severity::Warning,
+      line: 1,
+      col: 5,
+      message: "Empty object".to_string(),
+      rule: Some("no-empty-object"),
+    };
+    report.diagnostic("a.json5", &diagnostic, source);
+    let err =
+      anyhow::anyhow!("Parse error[J5F001]: b <1>.json5:2:3: Expected a value \"&\":\n  }}\n  ^");
+    report.error("b <1>.json5", &err);
+    report.error(
+      "b <1>.json5",
+      &anyhow::anyhow!("Parse error: b <1>.json5: Found 1 error(s)"),
+    );
+    report.file("c.json5");
+    report
+  }
+
+  #[test]
+  fn writes_checkstyle_documents() {
+    assert_eq!(
+      sample().render(ReportFormat::Checkstyle),
+      r#"<?xml version="1.0" encoding="UTF-8"?>
+<checkstyle version="4.3">
+  <file name="a.json5">
+    <error line="1" column="5" severity="warning" message="Empty object" source="formatjson5.no-empty-object"/>
+  </file>
+  <file name="b &lt;1&gt;.json5">
+    <error line="2" column="3" severity="error" message="Expected a value &quot;&amp;&quot;" source="formatjson5.parse-error"/>
+  </file>
+  <file name="c.json5"/>
+</checkstyle>
+"#
+    );
+  }
+
+  #[test]
+  fn writes_junit_documents() {
+    assert_eq!(
+      sample().render(ReportFormat::Junit),
+      r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="formatjson5" tests="3" failures="1">
+  <testsuite name="formatjson5" tests="3" failures="1" errors="0">
+    <testcase classname="formatjson5" name="a.json5">
+      <system-out>a.json5:1:5: Warning[J5F008]: Empty object</system-out>
+    </testcase>
+    <testcase classname="formatjson5" name="b &lt;1&gt;.json5">
+      <failure message="Expected a value &quot;&amp;&quot;" type="formatjson5.parse-error">b &lt;1&gt;.json5:2:3: Error[J5F001]: Expected a value &quot;&amp;&quot;</failure>
+    </testcase>
+    <testcase classname="formatjson5" name="c.json5"/>
+  </testsuite>
+</testsuites>
+"#
+    );
+  }
+
+  #[test]
+  fn escapes_xml_text() {
+    assert_eq!(
+      escape("<a href='x'>\"&\"</a>\u{1}\t"),
+      "&lt;a href=&apos;x&apos;&gt;&quot;&amp;&quot;&lt;/a&gt;\u{fffd}\t"
+    );
+  }
+
+  #[test]
+  fn finds_the_end_of_the_token_at_a_position() {
+    let source = "{\n  name: 'é x',\n  list: [ ],\n}";
+    assert_eq!(token_end(source, 2, 3), Some((2, 7)));
+    assert_eq!(token_end(source, 2, 9), Some((2, 14)));
+    assert_eq!(token_end(source, 3, 9), Some((3, 10)));
+    assert_eq!(token_end(source, 3, 10), None);
+    assert_eq!(token_end(source, 5, 1), None);
+    assert_eq!(token_end(source, 1, 3), None);
+  }
+}
@@ -0,0 +1,426 @@
+//! Writes the problems of a run as a SARIF 2.1.0 log (`--report=sarif`), which GitHub code
+//! scanning and other SARIF viewers show on the lines of each file.
+//!
+//! The log has a single run, whose tool lists a rule for each code (see `formatjson5 explain`),
+//! with its name, title, and explanation, and two rules of its own: `not-formatted`, for the files
+//! that `check` finds are not formatted, and `error`, for the errors that have no code (such as an
+//! unreadable file). Each result has the rule's id, and a region of the file: for diagnostics,
+//! the token they point at (or only their line and column, if no token starts there).
+//!
+//! Columns are counted in characters (Unicode code points), as in the formatter's messages, and
+//! file names are written as relative URIs, unless they are absolute.
+
+use crate::{
+  codes::{Code, CODES},
+  diagnostics::Severity,
+  json::Json,
+  report::{Problem, TOOL},
+  version,
+};
+use std::collections::BTreeMap;
+
+/// The address of the SARIF 2.1.0 schema.
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The address of the formatter's documentation.
+const INFORMATION_URI: &str = "https://github.com/gamma-delta/jsonpretty5er";
+
+/// The rule of the files that are not formatted.
+const NOT_FORMATTED: Rule = Rule {
+  id: "not-formatted",
+  name: "not-formatted",
+  title: "The file is not formatted",
+  explanation: "Formatting the file changes it. Run `formatjson5 --replace` on it to format it.",
+};
+
+/// The rule of the errors that have no code.
+const ERROR: Rule = Rule {
+  id: "error",
+  name: "error",
+  title: "The file could not be processed",
+  explanation: "The file could not be read or formatted; the message tells why.",
+};
+
+/// The metadata of a rule.
+struct Rule {
+  /// The stable id of the rule.
+  id: &'static str,
+  /// A short name of the rule.
+  name: &'static str,
+  /// A one-line summary of the problems the rule finds.
+  title: &'static str,
+  /// The detailed description of the problems the rule finds.
+  explanation: &'static str,
+}
+
+impl Rule {
+  /// Returns the rule of problems of the kind `code`.
+  fn of(code: &Code) -> Self {
+    Rule {
+      id: code.id,
+      name: code.name,
+      title: code.title,
+      explanation: code.explanation,
+    }
+  }
+
+  /// Returns the rule as a SARIF reporting descriptor.
+  fn descriptor(&self) -> Json {
+    Json::Object(vec![
+      ("id", Json::String(self.id.to_string())),
+      ("name", Json::String(self.name.to_string())),
+      ("shortDescription", text(self.title)),
+      ("fullDescription", text(self.explanation)),
+      ("help", text(self.explanation)),
+    ])
+  }
+}
+
+/// Returns the SARIF log of the `problems` of each file, by file name.
+pub fn log(problems: &BTreeMap<String, Vec<Problem>>) -> Json {
+  let rules = CODES
+    .iter()
+    .map(|code| Rule::of(code))
+    .chain([NOT_FORMATTED, ERROR])
+    .collect::<Vec<_>>();
+  let index = |id: &str| rules.iter().position(|rule| rule.id == id).unwrap_or(0);
+  let mut results = Vec::new();
+  let mut artifacts = Vec::new();
+  for (filename, problems) in problems {
+    let uri = uri(filename);
+    artifacts.push(Json::Object(vec![(
+      "location",
+      Json::Object(vec![("uri", Json::String(uri.clone()))]),
+    )]));
+    for problem in problems {
+      let rule = match (problem.code, problem.unformatted) {
+        (Some(code), _) => code.id,
+        (None, true) => NOT_FORMATTED.id,
+        (None, false) => ERROR.id,
+      };
+      let level = match problem.severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+      };
+      let message = match problem.rule {
+        Some(name) => format!("{} [{}]", problem.message, name),
+        None => problem.message.clone(),
+      };
+      let mut location = vec![(
+        "artifactLocation",
+        Json::Object(vec![
+          ("uri", Json::String(uri.clone())),
+          ("index", Json::Number(artifacts.len() - 1)),
+        ]),
+      )];
+      if let Some(region) = region(problem) {
+        location.push(("region", region));
+      }
+      results.push(Json::Object(vec![
+        ("ruleId", Json::String(rule.to_string())),
+        ("ruleIndex", Json::Number(index(rule))),
+        ("level", Json::String(level.to_string())),
+        ("message", text(&message)),
+        (
+          "locations",
+          Json::Array(vec![Json::Object(vec![(
+            "physicalLocation",
+            Json::Object(location),
+          )])]),
+        ),
+      ]));
+    }
+  }
+  let driver = Json::Object(vec![
+    ("name", Json::String(TOOL.to_string())),
+    ("version", Json::String(version::VERSION.to_string())),
+    ("informationUri", Json::String(INFORMATION_URI.to_string())),
+    (
+      "rules",
+      Json::Array(rules.iter().map(Rule::descriptor).collect()),
+    ),
+  ]);
+  Json::Object(vec![
+    ("$schema", Json::String(SCHEMA.to_string())),
+    ("version", Json::String("2.1.0".to_string())),
+    (
+      "runs",
+      Json::Array(vec![Json::Object(vec![
+        ("tool", Json::Object(vec![("driver", driver)])),
+        ("columnKind", Json::String("unicodeCodePoints".to_string())),
+        ("artifacts", Json::Array(artifacts)),
+        ("results", Json::Array(results)),
+      ])]),
+    ),
+  ])
+}
+
+/// Returns the region of the file that `problem` is in, if its line is known.
+fn region(problem: &Problem) -> Option<Json> {
+  let mut region = vec![("startLine", Json::Number(problem.line?))];
+  if let Some(col) = problem.col {
+    region.push(("startColumn", Json::Number(col)));
+  }
+  if let Some((line, col)) = problem.end {
+    region.push(("endLine", Json::Number(line)));
+    region.push(("endColumn", Json::Number(col)));
+  }
+  Some(Json::Object(region))
+}
+
+/// Returns a SARIF message with the plain `text`.
+fn text(text: &str) -> Json {
+  Json::Object(vec![("text", Json::String(text.to_string()))])
+}
+
+/// Returns the URI of the file `filename`: a relative URI for a relative path (without a leading
+/// `./`), a `file:` URI for an absolute path, and the URL itself for a document fetched from a URL.
+fn uri(filename: &str) -> String {
+  if filename.contains("://") {
+    return filename.to_string();
+  }
+  let path = filename.replace('\\', "/");
+  let path = path.trim_start_matches("./");
+  let mut uri = String::new();
+  if path.starts_with('/') {
+    uri.push_str("file://");
+  } else if path.as_bytes().get(1) == Some(&b':') {
+    // A Windows path with a drive letter.
+    uri.push_str("file:///");
+  }
+  for byte in path.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+        uri.push(byte as char)
+      }
+      byte => uri.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+  uri
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::codes;
+
+  /// Returns the value in `json` at the `path` of property names and array indexes.
+  fn get<'a>(json: &'a Json, path: &[&str]) -> &'a Json {
+    path.iter().fold(json, |json, segment| match json {
+      Json::Object(properties) => {
+        &properties
+          .iter()
+          .find(|(name, _)| name == segment)
+          .unwrap_or_else(|| panic!("No property `{}`", segment))
+          .1
+      }
+      Json::Array(items) => &items[segment.parse::<usize>().unwrap()],
+      _ => panic!("No value at `{}`", segment),
+    })
+  }
+
+  /// Returns the JSON text of the value in `json` at the `path`, without its final line break.
+  fn text_at(json: &Json, path: &[&str]) -> String {
+    get(json, path).to_pretty_string().trim_end().to_string()
+  }
+
+  /// Returns a problem of the file, with the given position and kind.
+  fn problem(
+    position: (Option<usize>, Option<usize>, Option<(usize, usize)>),
+    severity: Severity,
+    code: Option<&'static Code>,
+    unformatted: bool,
+    message: &str,
+  ) -> Problem {
+    let (line, col, end) = position;
+    Problem {
+      line,
+      col,
+      end,
+      severity,
+      code,
+      rule: code.map(|code| code.name),
+      unformatted,
+      message: message.to_string(),
+    }
+  }
+
+  /// Returns the log of a diagnostic and a check failure of `./dir/a b.json5`, an error of
+  /// `/abs/c.json5`, and no problems of `d.json5`.
+  fn sample() -> Json {
+    let problems = BTreeMap::from([
+      (
+        "./dir/a b.json5".to_string(),
+        vec![
+          problem(
+            (Some(2), Some(3), Some((2, 5))),
+            Severity::Warning,
+            Some(&codes::NO_EMPTY_OBJECT),
+            false,
+            "Empty object",
+          ),
+          problem(
+            (Some(1), None, None),
+            Severity::Error,
+            None,
+            true,
+            "Not formatted",
+          ),
+        ],
+      ),
+      (
+        "/abs/c.json5".to_string(),
+        vec![problem(
+          (None, None, None),
+          Severity::Error,
+          None,
+          false,
+          "Permission denied",
+        )],
+      ),
+      ("d.json5".to_string(), Vec::new()),
+    ]);
+    log(&problems)
+  }
+
+  #[test]
+  fn writes_a_run_of_the_tool() {
+    let log = sample();
+    assert_eq!(text_at(&log, &["$schema"]), format!("\"{}\"", SCHEMA));
+    assert_eq!(text_at(&log, &["version"]), "\"2.1.0\"");
+    let driver = get(&log, &["runs", "0", "tool", "driver"]);
+    assert_eq!(text_at(driver, &["name"]), "\"formatjson5\"");
+    assert_eq!(
+      text_at(driver, &["version"]),
+      format!("\"{}\"", version::VERSION)
+    );
+    assert_eq!(
+      text_at(&log, &["runs", "0", "columnKind"]),
+      "\"unicodeCodePoints\""
+    );
+  }
+
+  #[test]
+  fn lists_a_rule_for_each_code_and_its_own_rules() {
+    let log = sample();
+    let Json::Array(rules) = get(&log, &["runs", "0", "tool", "driver", "rules"]) else {
+      panic!("The rules are not an array");
+    };
+    assert_eq!(rules.len(), CODES.len() + 2);
+    let index = CODES
+      .iter()
+      .position(|code| code.id == codes::NO_EMPTY_OBJECT.id)
+      .unwrap();
+    assert_eq!(text_at(&rules[index], &["id"]), "\"J5F008\"");
+    assert_eq!(text_at(&rules[index], &["name"]), "\"no-empty-object\"");
+    assert_eq!(
+      text_at(&rules[index], &["shortDescription", "text"]),
+      "\"Empty object\""
+    );
+    assert_eq!(text_at(&rules[CODES.len()], &["id"]), "\"not-formatted\"");
+    assert_eq!(text_at(&rules[CODES.len() + 1], &["id"]), "\"error\"");
+  }
+
+  #[test]
+  fn writes_a_result_for_each_problem() {
+    let log = sample();
+    let run = get(&log, &["runs", "0"]);
+    assert_eq!(
+      text_at(run, &["artifacts"]),
+      r#"[
+  {
+    "location": {
+      "uri": "dir/a%20b.json5"
+    }
+  },
+  {
+    "location": {
+      "uri": "file:///abs/c.json5"
+    }
+  },
+  {
+    "location": {
+      "uri": "d.json5"
+    }
+  }
+]"#
+    );
+    let index = CODES
+      .iter()
+      .position(|code| code.id == codes::NO_EMPTY_OBJECT.id)
+      .unwrap();
+    assert_eq!(
+      text_at(run, &["results", "0"]),
+      format!(
+        r#"{{
+  "ruleId": "J5F008",
+  "ruleIndex": {},
+  "level": "warning",
+  "message": {{
+    "text": "Empty object [no-empty-object]"
+  }},
+  "locations": [
+    {{
+      "physicalLocation": {{
+        "artifactLocation": {{
+          "uri": "dir/a%20b.json5",
+          "index": 0
+        }},
+        "region": {{
+          "startLine": 2,
+          "startColumn": 3,
+          "endLine": 2,
+          "endColumn": 5
+        }}
+      }}
+    }}
+  ]
+}}"#,
+        index
+      )
+    );
+    let Json::Array(results) = get(run, &["results"]) else {
+      panic!("The results are not an array");
+    };
+    assert_eq!(results.len(), 3);
+    assert_eq!(text_at(&results[1], &["ruleId"]), "\"not-formatted\"");
+    assert_eq!(
+      text_at(&results[1], &["ruleIndex"]),
+      CODES.len().to_string()
+    );
+    assert_eq!(text_at(&results[1], &["level"]), "\"error\"");
+    assert_eq!(
+      text_at(
+        &results[1],
+        &["locations", "0", "physicalLocation", "region"]
+      ),
+      "{\n  \"startLine\": 1\n}"
+    );
+    assert_eq!(text_at(&results[2], &["ruleId"]), "\"error\"");
+    assert_eq!(
+      text_at(&results[2], &["ruleIndex"]),
+      (CODES.len() + 1).to_string()
+    );
+    assert_eq!(
+      text_at(&results[2], &["locations", "0", "physicalLocation"]),
+      r#"{
+  "artifactLocation": {
+    "uri": "file:///abs/c.json5",
+    "index": 1
+  }
+}"#
+    );
+  }
+
+  #[test]
+  fn writes_file_names_as_uris() {
+    assert_eq!(uri("./a/b.json5"), "a/b.json5");
+    assert_eq!(uri(r"a\b c.json5"), "a/b%20c.json5");
+    assert_eq!(uri("/tmp/#1.json5"), "file:///tmp/%231.json5");
+    assert_eq!(uri(r"C:\config\a.json5"), "file:///C:/config/a.json5");
+    assert_eq!(
+      uri("https://example.com/a.json5"),
+      "https://example.com/a.json5"
+    );
+  }
+}
@@ -0,0 +1,189 @@
+//! Groups object properties into sections, for the `sections` option. Each section has a header
+//! and a list of property names; the properties of each section are ordered after those of no
+//! section, in the order the sections and names are listed, and a header comment is placed before
+//! the first property of each section present in an object:
+//!
+//!     {
+//!         sections: {
+//!             Networking: ["host", "port"],
+//!             Storage: ["data_dir"],
+//!         },
+//!     }
+//!
+//! formats `{data_dir: "/var", port: 80, verbose: true, host: "localhost"}` as:
+//!
+//!     {
+//!         verbose: true,
+//!
+//!         // --- Networking ---
+//!         host: "localhost",
+//!         port: 80,
+//!
+//!         // --- Storage ---
+//!         data_dir: "/var",
+//!     }
+//!
+//! Existing header comments of the configured sections are moved to the first property of their
+//! section, so formatting again does not add more headers. If `section_comments` is false, the
+//! properties are grouped, and comments are left alone.
+
+use crate::{
+  edit::Edits,
+  lexer::{self, Token, TokenKind},
+  syntax::{Member, Node},
+};
+
+/// A section of object properties.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+  /// The section header, without the comment markers.
+  pub header: String,
+  /// The names of the properties in the section, in order.
+  pub keys: Vec<String>,
+}
+
+impl Section {
+  /// Returns the comment that introduces the section.
+  fn comment(&self) -> String {
+    format!("// --- {} ---", self.header)
+  }
+}
+
+/// Parses sections from `text`, a semicolon-separated list of `header=key,key` pairs (for
+/// environment variables and directives).
+pub fn parse(text: &str) -> Option<Vec<Section>> {
+  text
+    .split(';')
+    .map(str::trim)
+    .filter(|section| !section.is_empty())
+    .map(|section| {
+      let (header, keys) = section.split_once('=')?;
+      Some(Section {
+        header: header.trim().to_string(),
+        keys: keys.split(',').map(|key| key.trim().to_string()).collect(),
+      })
+    })
+    .collect()
+}
+
+/// Adds edits that place a header comment before the first property of each of the `sections` in
+/// every object in `root`, parsed from `source`, and remove the existing header comments of those
+/// sections elsewhere in the object.
+pub fn comments(root: &Node, source: &str, sections: &[Section], edits: &mut Edits) {
+  let tokens = lexer::tokenize(source);
+  let headers = sections.iter().map(Section::comment).collect::<Vec<_>>();
+  let context = Context {
+    source,
+    tokens: &tokens,
+    headers: &headers,
+  };
+  context.visit(root, sections, edits);
+}
+
+/// The document being edited, for [comments()](fn.comments.html).
+struct Context<'a> {
+  /// The document source.
+  source: &'a str,
+  /// The tokens of the document.
+  tokens: &'a [Token],
+  /// The header comments of the sections.
+  headers: &'a [String],
+}
+
+impl Context<'_> {
+  /// Adds the edits for `node` and its descendants.
+  fn visit(&self, node: &Node, sections: &[Section], edits: &mut Edits) {
+    match node {
+      Node::Object { members, .. } => {
+        let firsts = sections
+          .iter()
+          .enumerate()
+          .filter_map(|(index, section)| {
+            let first = section
+              .keys
+              .iter()
+              .find_map(|key| members.iter().find(|member| member.name == *key))?;
+            Some((index, first))
+          })
+          .collect::<Vec<_>>();
+        if !firsts.is_empty() {
+          for member in members {
+            self.remove_headers(member, edits);
+          }
+          for (index, member) in firsts {
+            edits.insert(
+              self.leading_start(member),
+              format!("\n{}\n", self.headers[index]),
+            );
+          }
+        }
+        for member in members {
+          self.visit(&member.value, sections, edits);
+        }
+      }
+      Node::Array { items, .. } => {
+        for item in items {
+          self.visit(item, sections, edits);
+        }
+      }
+      Node::String { .. } | Node::Literal { .. } => {}
+    }
+  }
+
+  /// Returns the tokens between the end of the value (or opening brace) before `member`, and its
+  /// name, excluding a comment on the same line as the previous value, which belongs to it.
+  fn leading(&self, member: &Member) -> &[Token] {
+    let end = self
+      .tokens
+      .partition_point(|token| token.start < member.key.start);
+    let start = self.tokens[..end]
+      .iter()
+      .rposition(|token| !token.is_trivia())
+      .map_or(0, |index| index + 1);
+    let mut leading = &self.tokens[start..end];
+    if let Some(newline) = leading
+      .iter()
+      .position(|token| token.kind == TokenKind::Newline)
+    {
+      leading = &leading[newline..];
+    }
+    leading
+  }
+
+  /// Returns the offset where a header comment is inserted before `member`: before its first
+  /// leading comment that is not a section header, or before its name.
+  fn leading_start(&self, member: &Member) -> usize {
+    self
+      .leading(member)
+      .iter()
+      .find(|token| self.is_comment(token) && !self.is_header(token))
+      .map_or(member.key.start, |token| token.start)
+  }
+
+  /// Adds edits that remove the section header comments before `member`, with the rest of their
+  /// lines.
+  fn remove_headers(&self, member: &Member, edits: &mut Edits) {
+    for token in self.leading(member) {
+      if self.is_header(token) {
+        let end = self.source[token.end..]
+          .find('\n')
+          .map_or(self.source.len(), |index| token.end + index + 1);
+        edits.replace(token.start..end, "");
+      }
+    }
+  }
+
+  /// Returns true if `token` is a comment.
+  fn is_comment(&self, token: &Token) -> bool {
+    matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment)
+  }
+
+  /// Returns true if `token` is the header comment of a section.
+  fn is_header(&self, token: &Token) -> bool {
+    token.kind == TokenKind::LineComment
+      && self
+        .headers
+        .iter()
+        .any(|header| self.source[token.start..token.end].trim_end() == header)
+  }
+}
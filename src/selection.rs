@@ -0,0 +1,104 @@
+//! Selects which of the files given on the command line are formatted (the `include` and
+//! `exclude` options), so a shell glob or a list of changed files can name more files than the
+//! project formats:
+//!
+//!     {
+//!         include: ["config", "*.json5"],
+//!         exclude: ["vendor", "**/*.generated.json5", "!vendor/acme"],
+//!     }
+//!
+//! A directory given on the command line is first replaced by the files in it with an extension in
+//! the file-type registry, each selected as if it was given by name. Patterns match the segments of
+//! a file's path as it was given or found (without a leading `./`), as in `lint_overrides`, and a
+//! pattern that matches a directory matches every file in it. A file is selected by these rules, in
+//! order of precedence:
+//!
+//! 1. Stdin (`-`) and URLs are always selected.
+//! 2. The last `exclude` pattern that matches the file decides whether it is excluded: a pattern
+//!    excludes the file, and a pattern starting with `!` includes it again, as in a `.gitignore`
+//!    file. An excluded file is skipped, even if an `include` pattern matches it.
+//! 3. If `include` is set, a file that no `include` pattern matches is skipped.
+//! 4. Any other file is selected.
+//!
+//! Skipped files are reported with `--verbose`, and `--explain-match <path>` prints which rule
+//! selects or skips a path.
+
+use crate::{glob::PathGlob, remote};
+
+/// Whether a file is selected, and the rule that decided it.
+#[derive(Debug)]
+pub struct Selection {
+  /// Whether the file is formatted.
+  pub selected: bool,
+  /// The rule that selected or skipped the file.
+  pub reason: String,
+}
+
+/// Returns whether the `include` and `exclude` patterns select the file `filename`, and why.
+pub fn select(
+  filename: &str,
+  include: &[String],
+  exclude: &[String],
+) -> Result<Selection, anyhow::Error> {
+  let selection = |selected, reason: String| Ok(Selection { selected, reason });
+  if filename == "-" || remote::is_url(filename) {
+    return selection(true, "stdin and URLs are always selected".to_string());
+  }
+  let segments = filename
+    .trim_start_matches("./")
+    .split('/')
+    .map(str::to_string)
+    .collect::<Vec<_>>();
+  let mut readmitted = None;
+  for pattern in exclude.iter().rev() {
+    let (negated, glob) = match pattern.strip_prefix('!') {
+      Some(glob) => (true, glob),
+      None => (false, pattern.as_str()),
+    };
+    if matches(glob, &segments, "exclude")? {
+      match negated {
+        true => readmitted = Some(pattern),
+        false => return selection(false, format!("excluded by `{}` in `exclude`", pattern)),
+      }
+      break;
+    }
+  }
+  let readmitted = match readmitted {
+    Some(pattern) => format!(", after `{}` in `exclude` included it again", pattern),
+    None => String::new(),
+  };
+  if include.is_empty() {
+    return selection(
+      true,
+      format!("matched by no pattern in `exclude`{}", readmitted),
+    );
+  }
+  for pattern in include {
+    if matches(pattern, &segments, "include")? {
+      return selection(
+        true,
+        format!("included by `{}` in `include`{}", pattern, readmitted),
+      );
+    }
+  }
+  selection(
+    false,
+    format!(
+      "matched by no pattern in `include` ({})",
+      include
+        .iter()
+        .map(|pattern| format!("`{}`", pattern))
+        .collect::<Vec<_>>()
+        .join(", ")
+    ),
+  )
+}
+
+/// Returns true if `pattern`, a pattern of the option `option`, matches the path `segments` or
+/// one of the directories it is in.
+fn matches(pattern: &str, segments: &[String], option: &str) -> Result<bool, anyhow::Error> {
+  let glob = pattern
+    .parse::<PathGlob>()
+    .map_err(|err| anyhow::anyhow!("Configuration error: Option `{}`: {}", option, err))?;
+  Ok((1..=segments.len()).any(|len| glob.matches(&segments[..len])))
+}
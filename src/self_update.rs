@@ -0,0 +1,340 @@
+//! Replaces the running executable with the newest release, for the `self-update` subcommand,
+//! when built with the `self-update` feature. This is for the prebuilt binaries installed outside
+//! of a package manager or `cargo install`, which should be updated by those instead.
+//!
+//! The newest release is read from the GitHub releases of the repository. Each release has a
+//! binary for each platform, named `formatjson5-<arch>-<os>` (with `.exe` on Windows, such as
+//! `formatjson5-x86_64-linux`), and a `<binary>.sha256` file holding its SHA-256 checksum (in the
+//! format `sha256sum` writes). The binary is downloaded next to the executable, its checksum is
+//! verified, and it is then renamed over the executable. Releases are fetched with the `curl`
+//! command, as for [remote](../remote/index.html) documents.
+//!
+//! The checksum is a check of integrity only: it is downloaded from the same release as the binary,
+//! so it detects a download that was cut short or damaged, but not a release (or a connection)
+//! that was tampered with. Releases are not signed, so the update trusts GitHub and HTTPS for the
+//! authenticity of the binary.
+
+/// The repository whose releases are checked, as `owner/name`.
+#[cfg(feature = "self-update")]
+pub const REPOSITORY: &str = "gamma-delta/jsonpretty5er";
+
+/// Checks for a newer release, and unless `check_only` is true, replaces the running executable
+/// with it.
+#[cfg(feature = "self-update")]
+pub fn self_update(check_only: bool) -> Result<(), anyhow::Error> {
+  use crate::{
+    syntax::{self, Node},
+    version::Version,
+  };
+  use std::{env, fs};
+
+  let url = format!(
+    "https://api.github.com/repos/{}/releases/latest",
+    REPOSITORY
+  );
+  let release = String::from_utf8(download(&url)?)?;
+  let root =
+    syntax::parse(&release).map_err(|err| anyhow::anyhow!("Update error: {}: {}", url, err))?;
+  let Some(Node::Object { members, .. }) = &root else {
+    return Err(anyhow::anyhow!("Update error: {}: Expected an object", url));
+  };
+  let string = |members: &[syntax::Member], name: &str| {
+    members.iter().find_map(|member| match &member.value {
+      Node::String { value, .. } if member.name == name => Some(value.clone()),
+      _ => None,
+    })
+  };
+  let tag = string(members, "tag_name")
+    .ok_or_else(|| anyhow::anyhow!("Update error: {}: The release has no tag", url))?;
+  let latest = tag
+    .trim_start_matches('v')
+    .parse::<Version>()
+    .map_err(|message| anyhow::anyhow!("Update error: Release {}: {}", tag, message))?;
+  let current = Version::current();
+  if latest <= current {
+    eprintln!("Version {} is the newest release", current);
+    return Ok(());
+  }
+  if check_only {
+    eprintln!(
+      "Version {} is available (this is version {}); run `formatjson5 self-update` to install it",
+      latest, current
+    );
+    return Ok(());
+  }
+
+  let binary = format!(
+    "formatjson5-{}-{}{}",
+    env::consts::ARCH,
+    env::consts::OS,
+    env::consts::EXE_SUFFIX
+  );
+  let assets = members
+    .iter()
+    .find(|member| member.name == "assets")
+    .and_then(|member| match &member.value {
+      Node::Array { items, .. } => Some(items),
+      _ => None,
+    })
+    .map(|items| {
+      items
+        .iter()
+        .filter_map(|item| match item {
+          Node::Object { members, .. } => Some((
+            string(members, "name")?,
+            string(members, "browser_download_url")?,
+          )),
+          _ => None,
+        })
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  let asset = |name: &str| {
+    assets
+      .iter()
+      .find(|(asset, _)| asset == name)
+      .map(|(_, url)| url.clone())
+      .ok_or_else(|| {
+        anyhow::anyhow!(
+          "Update error: Release {} has no `{}` (this platform may need to build from source)",
+          tag,
+          name
+        )
+      })
+  };
+  let binary_url = asset(&binary)?;
+  let checksum_url = asset(&format!("{}.sha256", binary))?;
+
+  let checksum = String::from_utf8(download(&checksum_url)?)?;
+  let expected = checksum
+    .split_whitespace()
+    .next()
+    .unwrap_or("")
+    .to_ascii_lowercase();
+  let bytes = download(&binary_url)?;
+  let actual = sha256::hex(&bytes);
+  if actual != expected {
+    return Err(anyhow::anyhow!(
+      "Update error: The checksum of {} is {}, but the release lists {}; the executable was not \
+       replaced",
+      binary,
+      actual,
+      expected
+    ));
+  }
+
+  let executable = env::current_exe()?;
+  let mut staged = executable.clone().into_os_string();
+  staged.push(".update");
+  let staged = std::path::PathBuf::from(staged);
+  fs::write(&staged, &bytes)?;
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+  }
+  if let Err(err) = replace(&executable, &staged) {
+    let _ = fs::remove_file(&staged);
+    return Err(anyhow::anyhow!(
+      "Update error: {}: {}",
+      executable.display(),
+      err
+    ));
+  }
+  eprintln!(
+    "Updated {} from version {} to {}",
+    executable.display(),
+    current,
+    latest
+  );
+  Ok(())
+}
+
+/// Renames the file `staged` over the running `executable`.
+#[cfg(all(feature = "self-update", not(windows)))]
+fn replace(executable: &std::path::Path, staged: &std::path::Path) -> std::io::Result<()> {
+  std::fs::rename(staged, executable)
+}
+
+/// Renames the file `staged` over the running `executable`. Windows cannot replace a running
+/// executable, but can rename it out of the way, to the executable's name with `.old` appended; if
+/// `staged` then cannot be renamed, the executable is renamed back, so a failed update does not
+/// leave it missing.
+#[cfg(all(feature = "self-update", windows))]
+fn replace(executable: &std::path::Path, staged: &std::path::Path) -> std::io::Result<()> {
+  use std::fs;
+
+  let mut old = executable.as_os_str().to_owned();
+  old.push(".old");
+  let _ = fs::remove_file(&old);
+  fs::rename(executable, &old)?;
+  if let Err(err) = fs::rename(staged, executable) {
+    let _ = fs::rename(&old, executable);
+    return Err(err);
+  }
+  Ok(())
+}
+
+/// Fails, because updating requires the `self-update` feature.
+#[cfg(not(feature = "self-update"))]
+pub fn self_update(_check_only: bool) -> Result<(), anyhow::Error> {
+  Err(anyhow::anyhow!(
+    "Updating requires building formatjson5 with the `self-update` feature; otherwise, update it \
+     the way it was installed (such as with `cargo install`)"
+  ))
+}
+
+/// Downloads the content at `url` with `curl`.
+#[cfg(feature = "self-update")]
+fn download(url: &str) -> Result<Vec<u8>, anyhow::Error> {
+  let output = std::process::Command::new("curl")
+    .args([
+      "--fail",
+      "--silent",
+      "--show-error",
+      "--location",
+      "--header",
+      "Accept: application/vnd.github+json",
+      "--",
+      url,
+    ])
+    .output()
+    .map_err(|err| anyhow::anyhow!("Update error: {}: Cannot run curl: {}", url, err))?;
+  if !output.status.success() {
+    return Err(anyhow::anyhow!(
+      "Update error: {}: {}",
+      url,
+      String::from_utf8_lossy(&output.stderr).trim()
+    ));
+  }
+  Ok(output.stdout)
+}
+
+/// The SHA-256 hash function (FIPS 180-4), for verifying downloads without a dependency.
+#[cfg(feature = "self-update")]
+mod sha256 {
+  /// The round constants.
+  const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+  ];
+
+  /// Returns the SHA-256 hash of `data`, in lowercase hexadecimal.
+  pub fn hex(data: &[u8]) -> String {
+    let mut state: [u32; 8] = [
+      0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+      0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+      message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for block in message.chunks(64) {
+      let mut w = [0u32; 64];
+      for (i, word) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+      }
+      for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+          .wrapping_add(s0)
+          .wrapping_add(w[i - 7])
+          .wrapping_add(s1);
+      }
+      let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+      for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let t1 = h
+          .wrapping_add(s1)
+          .wrapping_add(choice)
+          .wrapping_add(K[i])
+          .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(majority);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+      }
+      for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(value);
+      }
+    }
+    state.iter().map(|word| format!("{:08x}", word)).collect()
+  }
+}
+
+#[cfg(all(test, feature = "self-update"))]
+mod tests {
+  use super::*;
+  use std::{fs, path::PathBuf};
+
+  /// Returns a new, empty directory for the test `name`.
+  fn directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("jsonpretty5er-{}", name));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+  }
+
+  #[test]
+  fn hashes_the_known_answers() {
+    // The examples of FIPS 180-4, and the long message of the NIST test vectors.
+    assert_eq!(
+      sha256::hex(b""),
+      "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+      sha256::hex(b"abc"),
+      "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+      sha256::hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+      "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+    assert_eq!(
+      sha256::hex(&vec![b'a'; 1_000_000]),
+      "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+  }
+
+  #[test]
+  fn replaces_the_executable() {
+    let directory = directory("replace");
+    let executable = directory.join("formatjson5");
+    let staged = directory.join("formatjson5.update");
+    fs::write(&executable, "old").unwrap();
+    fs::write(&staged, "new").unwrap();
+    replace(&executable, &staged).unwrap();
+    assert_eq!(fs::read_to_string(&executable).unwrap(), "new");
+    assert!(!staged.exists());
+  }
+
+  #[test]
+  #[cfg(windows)]
+  fn restores_the_executable_if_the_update_cannot_be_renamed() {
+    let directory = directory("restore");
+    let executable = directory.join("formatjson5.exe");
+    fs::write(&executable, "old").unwrap();
+    // The staged binary is missing, so renaming it fails after the executable was renamed.
+    let staged = directory.join("formatjson5.exe.update");
+    assert!(replace(&executable, &staged).is_err());
+    assert_eq!(fs::read_to_string(&executable).unwrap(), "old");
+    assert!(!directory.join("formatjson5.exe.old").exists());
+  }
+}
@@ -0,0 +1,131 @@
+//! Reports the changes to a document's data that formatting made (the `--report-semantic-changes`
+//! option), so they can be reviewed apart from changes to whitespace and comments: each array
+//! whose items were reordered (such as by `--sort-arrays`), and each array item or property that
+//! was removed as a duplicate of another (such as by a plugin). For example:
+//!
+//!     Semantic change: config.json5:3:11: /deps: Reordered the items of the array
+//!     Semantic change: config.json5:7:5: /tags/2: Removed a duplicate of /tags/0
+//!
+//! Values are matched by their data, so a value changed by other means (such as `--transform`) is
+//! not reported, and neither are the items of an array that holds different values after
+//! formatting.
+
+use crate::{
+  paranoid,
+  syntax::{self, Node},
+};
+
+/// A change to the data of a document.
+#[derive(Debug)]
+pub struct Change {
+  /// The path of the changed array, or of the removed value, in the input.
+  pub path: String,
+  /// The 1-based line of the changed value in the input.
+  pub line: usize,
+  /// The 1-based column of the changed value in the input.
+  pub col: usize,
+  /// What changed.
+  pub message: String,
+}
+
+/// Returns the changes between the data of the document `before` and of its formatted text
+/// `after`, in input order. Documents that cannot be parsed have no changes.
+pub fn changes(before: &str, after: &str) -> Vec<Change> {
+  let mut changes = Vec::new();
+  if let (Ok(Some(expected)), Ok(Some(actual))) = (syntax::parse(before), syntax::parse(after)) {
+    compare(&expected, before, &actual, after, "", &mut changes);
+  }
+  changes.sort_by_key(|change| (change.line, change.col));
+  changes
+}
+
+/// Adds the changes between the value `expected`, in the document `before`, and the value
+/// `actual`, in the document `after`, at `path`, and between their descendants.
+fn compare(
+  expected: &Node,
+  before: &str,
+  actual: &Node,
+  after: &str,
+  path: &str,
+  changes: &mut Vec<Change>,
+) {
+  let change = |node: &Node, path: String, message: String| {
+    let span = node.span();
+    Change {
+      path: match path.is_empty() {
+        true => "/".to_string(),
+        false => path,
+      },
+      line: span.line,
+      col: span.col,
+      message,
+    }
+  };
+  match (expected, actual) {
+    (Node::Object { members: from, .. }, Node::Object { members: to, .. }) => {
+      for (index, member) in from.iter().enumerate() {
+        let path = format!("{}/{}", path, member.name);
+        // The properties of each name are matched in order.
+        let nth = from[..index]
+          .iter()
+          .filter(|other| other.name == member.name)
+          .count();
+        match to.iter().filter(|other| other.name == member.name).nth(nth) {
+          Some(other) => compare(&member.value, before, &other.value, after, &path, changes),
+          None if nth > 0 => changes.push(change(
+            &member.value,
+            path,
+            format!("Removed a duplicate of the property `{}`", member.name),
+          )),
+          None => {}
+        }
+      }
+    }
+    (Node::Array { items: from, .. }, Node::Array { items: to, .. }) => {
+      let canonical = |items: &[Node], source| {
+        items
+          .iter()
+          .map(|item| paranoid::canonical(item, source))
+          .collect::<Vec<_>>()
+      };
+      let (values, formatted) = (canonical(from, before), canonical(to, after));
+      // Each formatted item is matched with the first unmatched input item of the same value.
+      let mut matched = vec![None; from.len()];
+      let mut order = Vec::new();
+      for (index, value) in formatted.iter().enumerate() {
+        if let Some(item) =
+          (0..from.len()).find(|&item| matched[item].is_none() && values[item] == *value)
+        {
+          matched[item] = Some(index);
+          order.push(item);
+        }
+      }
+      if order.windows(2).any(|pair| pair[0] > pair[1]) {
+        changes.push(change(
+          expected,
+          path.to_string(),
+          "Reordered the items of the array".to_string(),
+        ));
+      }
+      for (index, item) in from.iter().enumerate() {
+        let item_path = format!("{}/{}", path, index);
+        match matched[index] {
+          Some(to_index) => compare(item, before, &to[to_index], after, &item_path, changes),
+          None => {
+            if let Some(first) = values[..index]
+              .iter()
+              .position(|value| *value == values[index])
+            {
+              changes.push(change(
+                item,
+                item_path,
+                format!("Removed a duplicate of {}/{}", path, first),
+              ));
+            }
+          }
+        }
+      }
+    }
+    _ => {}
+  }
+}
@@ -0,0 +1,418 @@
+//! A small HTTP server that formats documents on request (the `serve` subcommand), so a team can
+//! host a formatting service backed by the exact version of the formatter they use.
+//!
+//! `POST /format` formats the request body, with any options given as query parameters using the
+//! configuration file's option names (for example, `/format?indent=2&sort_keys=true`), and
+//! responds with the formatted document, or with status 400 and the error message. `GET /` serves
+//! a single-page UI for formatting documents from a browser, unless it is disabled. Every response
+//! carries the formatter's version in an `X-Formatter-Version` header.
+//!
+//! Requests are handled one at a time, which is ample for an internal service, so each is limited:
+//! the request line and headers to 64 KiB and 100 headers (status 431), the body to 10 MiB
+//! (status 413, and 411 without a `Content-Length`), and the whole request to 30 seconds. The
+//! server is part of the `serve` feature, which is enabled by default.
+
+#[cfg(feature = "serve")]
+use crate::config;
+use crate::config::Config;
+#[cfg(feature = "serve")]
+use std::{
+  io::{self, BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream},
+  time::{Duration, Instant},
+};
+
+/// The largest request body the server accepts.
+#[cfg(feature = "serve")]
+const MAX_BODY: usize = 10 * 1024 * 1024;
+
+/// The largest request line and headers the server accepts, together.
+#[cfg(feature = "serve")]
+const MAX_HEAD: usize = 64 * 1024;
+
+/// The most headers the server accepts in a request.
+#[cfg(feature = "serve")]
+const MAX_HEADERS: usize = 100;
+
+/// How long the server waits for a client to send or receive data.
+#[cfg(feature = "serve")]
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the server waits for a client to send all of a request.
+#[cfg(feature = "serve")]
+const DEADLINE: Duration = Duration::from_secs(30);
+
+/// The page served at `/`.
+#[cfg(feature = "serve")]
+const UI: &str = include_str!("serve.html");
+
+/// An HTTP response.
+#[cfg(feature = "serve")]
+struct Response {
+  /// The status code and reason phrase, such as `200 OK`.
+  status: &'static str,
+  /// The media type of the body.
+  content_type: &'static str,
+  /// Additional headers, as name and value.
+  headers: Vec<(&'static str, String)>,
+  /// The response body.
+  body: Vec<u8>,
+}
+
+#[cfg(feature = "serve")]
+impl Response {
+  /// Returns a plain text response with the given `status`.
+  fn text(status: &'static str, body: impl Into<Vec<u8>>) -> Self {
+    Response {
+      status,
+      content_type: "text/plain; charset=utf-8",
+      headers: Vec::new(),
+      body: body.into(),
+    }
+  }
+
+  /// Writes the response to `stream`.
+  fn write(&self, stream: &mut impl Write) -> std::io::Result<()> {
+    write!(
+      stream,
+      "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nX-Formatter-Version: {}\r\n",
+      self.status,
+      self.content_type,
+      self.body.len(),
+      env!("CARGO_PKG_VERSION")
+    )?;
+    for (name, value) in &self.headers {
+      write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    write!(stream, "Connection: close\r\n\r\n")?;
+    stream.write_all(&self.body)?;
+    stream.flush()
+  }
+}
+
+/// Listens on `address` and serves formatting requests until the process is stopped. Each
+/// document is formatted by `format`, given the document and the options from `base` overridden
+/// by any options in the request. If `ui` is false, the single-page UI is not served.
+#[cfg(feature = "serve")]
+pub fn serve(
+  address: &str,
+  ui: bool,
+  base: &Config,
+  format: impl Fn(String, &Config) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+  let listener = TcpListener::bind(address)
+    .map_err(|err| anyhow::anyhow!("Cannot listen on {}: {}", address, err))?;
+  eprintln!("Listening on http://{}", listener.local_addr()?);
+  for stream in listener.incoming() {
+    let mut stream = match stream {
+      Ok(stream) => stream,
+      Err(err) => {
+        eprintln!("Connection error: {}", err);
+        continue;
+      }
+    };
+    let response = match handle(&mut stream, ui, base, &format) {
+      Ok(response) => response,
+      Err(err) => Response::text("400 Bad Request", format!("{}\n", err)),
+    };
+    if let Err(err) = response.write(&mut stream) {
+      eprintln!("Connection error: {}", err);
+    }
+  }
+  Ok(())
+}
+
+/// Fails, because serving requires the `serve` feature.
+#[cfg(not(feature = "serve"))]
+pub fn serve(
+  _address: &str,
+  _ui: bool,
+  _base: &Config,
+  _format: impl Fn(String, &Config) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+  Err(anyhow::anyhow!(
+    "Serving requires building formatjson5 with the `serve` feature"
+  ))
+}
+
+/// Reads a request from `stream` and returns the response to it.
+#[cfg(feature = "serve")]
+fn handle(
+  stream: &mut TcpStream,
+  ui: bool,
+  base: &Config,
+  format: &impl Fn(String, &Config) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<Response, anyhow::Error> {
+  stream.set_write_timeout(Some(TIMEOUT))?;
+  let mut reader = BufReader::new(Deadline {
+    stream,
+    end: Instant::now() + DEADLINE,
+  });
+  respond(&mut reader, ui, base, format)
+}
+
+/// A stream whose reads fail once its deadline has passed, so a client that sends a request
+/// slowly cannot hold the server for longer.
+#[cfg(feature = "serve")]
+struct Deadline<'a> {
+  /// The stream read from.
+  stream: &'a TcpStream,
+  /// When reads start to fail.
+  end: Instant,
+}
+
+#[cfg(feature = "serve")]
+impl Read for Deadline<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let left = self.end.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+      return Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        "The request took too long to send",
+      ));
+    }
+    self.stream.set_read_timeout(Some(left.min(TIMEOUT)))?;
+    self.stream.read(buf)
+  }
+}
+
+/// Reads a request from `reader` and returns the response to it.
+#[cfg(feature = "serve")]
+fn respond(
+  reader: &mut impl BufRead,
+  ui: bool,
+  base: &Config,
+  format: &impl Fn(String, &Config) -> Result<Vec<u8>, anyhow::Error>,
+) -> Result<Response, anyhow::Error> {
+  let too_large = || {
+    Response::text(
+      "431 Request Header Fields Too Large",
+      format!(
+        "The request line and headers are limited to {} bytes and {} headers\n",
+        MAX_HEAD, MAX_HEADERS
+      ),
+    )
+  };
+  let mut head = reader.by_ref().take(MAX_HEAD as u64);
+  let mut request_line = String::new();
+  head.read_line(&mut request_line)?;
+  if !request_line.ends_with('\n') && head.limit() == 0 {
+    return Ok(too_large());
+  }
+  let mut parts = request_line.split_whitespace();
+  let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+    return Err(anyhow::anyhow!("Malformed request line"));
+  };
+  let mut content_length = None;
+  let mut headers = 0;
+  loop {
+    let mut header = String::new();
+    if head.read_line(&mut header)? == 0 {
+      return match head.limit() {
+        0 => Ok(too_large()),
+        _ => Err(anyhow::anyhow!("Unexpected end of the request headers")),
+      };
+    }
+    if !header.ends_with('\n') && head.limit() == 0 {
+      return Ok(too_large());
+    }
+    let header = header.trim_end();
+    if header.is_empty() {
+      break;
+    }
+    headers += 1;
+    if headers > MAX_HEADERS {
+      return Ok(too_large());
+    }
+    if let Some((name, value)) = header.split_once(':') {
+      match name.trim().to_ascii_lowercase().as_str() {
+        "content-length" => content_length = Some(value.trim().parse::<usize>()?),
+        "transfer-encoding" => return Ok(length_required()),
+        _ => {}
+      }
+    }
+  }
+
+  let (path, query) = target.split_once('?').unwrap_or((target, ""));
+  match (method, path) {
+    ("GET", "/") if ui => Ok(Response {
+      content_type: "text/html; charset=utf-8",
+      ..Response::text("200 OK", UI)
+    }),
+    ("POST", "/format") => {
+      let Some(length) = content_length else {
+        return Ok(length_required());
+      };
+      if length > MAX_BODY {
+        return Ok(Response::text(
+          "413 Payload Too Large",
+          format!("Documents are limited to {} bytes\n", MAX_BODY),
+        ));
+      }
+      let mut body = vec![0; length];
+      reader.read_exact(&mut body)?;
+      let document =
+        String::from_utf8(body).map_err(|_| anyhow::anyhow!("The document is not UTF-8 text"))?;
+      let (overrides, warnings) = config::from_query(&parse_query(query)?)?;
+      let mut config = base.clone();
+      config.merge(overrides);
+      let mut response = Response::text("200 OK", format(document, &config)?);
+      for warning in warnings {
+        response.headers.push(("X-Formatter-Warning", warning));
+      }
+      Ok(response)
+    }
+    (_, "/format") => Ok(Response {
+      headers: vec![("Allow", "POST".to_string())],
+      ..Response::text("405 Method Not Allowed", "Use POST to format a document\n")
+    }),
+    _ => Ok(Response::text("404 Not Found", "Not found\n")),
+  }
+}
+
+/// Returns the response to a document sent without a `Content-Length` header.
+#[cfg(feature = "serve")]
+fn length_required() -> Response {
+  Response::text(
+    "411 Length Required",
+    "Send the document with a Content-Length header\n",
+  )
+}
+
+/// Splits a URL query string into decoded name and value pairs.
+#[cfg(feature = "serve")]
+fn parse_query(query: &str) -> Result<Vec<(String, String)>, anyhow::Error> {
+  query
+    .split('&')
+    .filter(|pair| !pair.is_empty())
+    .map(|pair| {
+      let (name, value) = pair.split_once('=').unwrap_or((pair, "true"));
+      Ok((percent_decode(name)?, percent_decode(value)?))
+    })
+    .collect()
+}
+
+/// Decodes the `%XX` escapes and `+` spaces in a URL query component.
+#[cfg(feature = "serve")]
+fn percent_decode(text: &str) -> Result<String, anyhow::Error> {
+  let mut bytes = Vec::with_capacity(text.len());
+  let mut rest = text.as_bytes();
+  while let Some((&byte, tail)) = rest.split_first() {
+    rest = tail;
+    match byte {
+      b'+' => bytes.push(b' '),
+      b'%' => {
+        let hex = rest
+          .get(..2)
+          .and_then(|hex| std::str::from_utf8(hex).ok())
+          .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+          .ok_or_else(|| anyhow::anyhow!("Invalid escape in the query string `{}`", text))?;
+        bytes.push(hex);
+        rest = &rest[2..];
+      }
+      byte => bytes.push(byte),
+    }
+  }
+  String::from_utf8(bytes).map_err(|_| anyhow::anyhow!("The query string is not UTF-8 text"))
+}
+
+#[cfg(all(test, feature = "serve"))]
+mod tests {
+  use super::*;
+
+  /// Returns the response to the request `request`, formatting documents by echoing them with the
+  /// value of the `indent` option.
+  fn respond_to(request: &[u8]) -> Response {
+    let format = |document: String, config: &Config| {
+      Ok(format!("{:?} {}", config.integer("indent"), document).into_bytes())
+    };
+    respond(&mut &request[..], true, &Config::default(), &format).unwrap()
+  }
+
+  #[test]
+  fn decodes_query_strings() {
+    assert_eq!(
+      parse_query("indent=2&sort_keys&&name=a%20b+c%C3%A9").unwrap(),
+      [
+        ("indent".to_string(), "2".to_string()),
+        ("sort_keys".to_string(), "true".to_string()),
+        ("name".to_string(), "a b cé".to_string()),
+      ]
+    );
+    assert!(percent_decode("%zz").is_err());
+    assert!(percent_decode("a%4").is_err());
+    assert!(percent_decode("%FF").is_err());
+  }
+
+  #[test]
+  fn formats_posted_documents() {
+    let response = respond_to(b"POST /format?indent=2 HTTP/1.1\r\nContent-Length: 5\r\n\r\n{a:1}");
+    assert_eq!(response.status, "200 OK");
+    assert_eq!(response.body, b"Some(2) {a:1}");
+    assert_eq!(
+      respond_to(b"GET /format HTTP/1.1\r\n\r\n").status,
+      "405 Method Not Allowed"
+    );
+  }
+
+  #[test]
+  fn requires_the_length_of_documents() {
+    assert_eq!(
+      respond_to(b"POST /format HTTP/1.1\r\n\r\n{a:1}").status,
+      "411 Length Required"
+    );
+    let request = format!(
+      "POST /format HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+      MAX_BODY + 1
+    );
+    assert_eq!(
+      respond_to(request.as_bytes()).status,
+      "413 Payload Too Large"
+    );
+  }
+
+  #[test]
+  fn limits_the_request_headers() {
+    let long = format!(
+      "POST /format HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+      "a".repeat(MAX_HEAD)
+    );
+    assert_eq!(
+      respond_to(long.as_bytes()).status,
+      "431 Request Header Fields Too Large"
+    );
+    let many = format!(
+      "POST /format HTTP/1.1\r\n{}Content-Length: 0\r\n\r\n",
+      "X-Header: a\r\n".repeat(MAX_HEADERS)
+    );
+    assert_eq!(
+      respond_to(many.as_bytes()).status,
+      "431 Request Header Fields Too Large"
+    );
+    let endless = "a".repeat(2 * MAX_HEAD);
+    assert_eq!(
+      respond_to(endless.as_bytes()).status,
+      "431 Request Header Fields Too Large"
+    );
+  }
+
+  #[test]
+  fn stops_reading_at_the_deadline() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    let start = Instant::now();
+    let mut deadline = Deadline {
+      stream: &stream,
+      end: start + Duration::from_millis(100),
+    };
+    let err = deadline.read(&mut [0; 16]).unwrap_err();
+    assert!(matches!(
+      err.kind(),
+      io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    ));
+    assert!(start.elapsed() < TIMEOUT);
+    let err = deadline.read(&mut [0; 16]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+  }
+}
@@ -0,0 +1,34 @@
+//! Snapshot files, which record the expected formatted output of each input file, so a project
+//! can check that upgrading the formatter (or changing its configuration) does not change how its
+//! files are formatted.
+//!
+//! `--snapshot` writes the formatted output of each file `foo.json5` to `foo.json5.formatted`,
+//! next to it, and `--check-snapshots` fails if the output of any file differs from its snapshot.
+
+use std::path::{Path, PathBuf};
+
+/// The extension added to the name of an input file to name its snapshot.
+pub const EXTENSION: &str = "formatted";
+
+/// Returns the path of the snapshot of the input `file`.
+pub fn path(file: &Path) -> PathBuf {
+  let mut path = file.as_os_str().to_os_string();
+  path.push(".");
+  path.push(EXTENSION);
+  PathBuf::from(path)
+}
+
+/// Returns the 1-based number of the first line where `expected` and `actual` differ, or None if
+/// they are the same.
+pub fn first_difference(expected: &str, actual: &str) -> Option<usize> {
+  if expected == actual {
+    return None;
+  }
+  let mut expected_lines = expected.split('\n');
+  let mut actual_lines = actual.split('\n');
+  let mut line = 1;
+  while expected_lines.next() == actual_lines.next() {
+    line += 1;
+  }
+  Some(line)
+}
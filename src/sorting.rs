@@ -0,0 +1,346 @@
+//! Sorts object properties by name, for the `sort_keys` option, groups them into sections, for
+//! the `sections` option, and moves chosen properties first, for the `sort_priority` option.
+//! json5format can only order the properties of the objects at a given path by an explicit list of
+//! names, so the lists are computed from the document being formatted.
+//!
+//! The `sort_priority` option maps [path patterns](../glob/index.html) to lists of property names.
+//! In each object whose path matches a pattern, the listed properties come first, in the order
+//! they are listed, and the other properties follow, sorted by name:
+//!
+//!     {
+//!         sort_priority: {
+//!             "/build/*": ["name", "deps", "sources"],
+//!         },
+//!     }
+//!
+//! If several patterns match an object, the last one listed is used. json5format orders the objects
+//! in an array together, so a pattern matches array items with a segment that matches `*` (such as
+//! `*` itself), not with their indices.
+//!
+//! A `// json5format: no-sort` comment directly inside an object or array (not inside one of its
+//! values) keeps the order of that object's properties or that array's items, whatever
+//! `sort_keys`, `sort_arrays`, and `paths` select, for lists whose order matters:
+//!
+//!     {
+//!         fallbacks: [
+//!             // json5format: no-sort
+//!             "primary",
+//!             "secondary",
+//!         ],
+//!     }
+//!
+//! Properties listed by `sort_priority` still come first, and those listed by `sections` last. As
+//! with path patterns, the comment applies to every object or array at the same path in an array of
+//! objects.
+
+use crate::{
+  glob::PathGlob,
+  lexer::{self, TokenKind},
+  path_options::{PathRule, PathRules},
+  sections::Section,
+  syntax::{is_identifier, Member, Node},
+};
+use json5format::PathOption;
+use std::{
+  collections::{BTreeMap, HashMap, HashSet},
+  sync::Mutex,
+};
+
+/// The text of a comment that keeps the order of the object or array it is directly inside.
+pub const NO_SORT: &str = "json5format: no-sort";
+
+/// The paths and property names given to json5format so far, which it requires to be `'static`.
+static INTERNED: Mutex<Option<HashSet<&'static str>>> = Mutex::new(None);
+
+/// The properties that come first in the objects whose paths match a pattern, for the
+/// `sort_priority` option.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Priority {
+  /// The path pattern, which is a valid [PathGlob](../glob/struct.PathGlob.html).
+  pub pattern: String,
+  /// The names of the properties that come first, in order.
+  pub keys: Vec<String>,
+}
+
+/// Parses priorities from `text`, a semicolon-separated list of `pattern=key,key` pairs (for
+/// environment variables and directives).
+pub fn parse_priorities(text: &str) -> Option<Vec<Priority>> {
+  text
+    .split(';')
+    .map(str::trim)
+    .filter(|priority| !priority.is_empty())
+    .map(|priority| {
+      let (pattern, keys) = priority.split_once('=')?;
+      let pattern = pattern.trim();
+      pattern.parse::<PathGlob>().ok()?;
+      Some(Priority {
+        pattern: pattern.to_string(),
+        keys: keys.split(',').map(|key| key.trim().to_string()).collect(),
+      })
+    })
+    .collect()
+}
+
+/// Returns json5format path options that order the properties of every object in the document
+/// whose top-level value is `root`, read from `source`: the properties that are named in none of
+/// the `sections` come first, sorted by name if `sort_keys` is true (or the `rules` set
+/// `sort_keys` for the object's path), and otherwise in their original order, followed by the
+/// properties of the sections, in the order they are listed. In an object whose path matches one
+/// of the `priorities`, its properties come before all others, and the properties of no section
+/// are sorted by name.
+///
+/// json5format identifies objects by path, where `*` matches any array item (and the document's
+/// top-level value is the first item of an implicit array), so the objects in an array share a
+/// single list of names. Objects below a property whose name cannot be written in a path (because
+/// it contains `/` or is `*`) are left unsorted. json5format requires `'static` paths and names, so
+/// they are [interned](fn.intern.html).
+pub fn property_orders(
+  root: &Node,
+  source: &str,
+  sort_keys: bool,
+  sections: &[Section],
+  priorities: &[Priority],
+  rules: &PathRules<'_>,
+) -> HashMap<&'static str, HashSet<PathOption>> {
+  let priorities = priorities
+    .iter()
+    .filter_map(|priority| Some((priority.pattern.parse::<PathGlob>().ok()?, &priority.keys)))
+    .collect::<Vec<_>>();
+  let mut names_by_path = BTreeMap::new();
+  collect(
+    root,
+    source,
+    "/*".to_string(),
+    &mut Vec::new(),
+    &mut names_by_path,
+  );
+  names_by_path
+    .into_iter()
+    .filter_map(|(path, (segments, mut names))| {
+      let mut order = Vec::new();
+      let mut ordered = HashSet::new();
+      let priority = priorities
+        .iter()
+        .rev()
+        .find(|(glob, _)| glob.matches(&segments))
+        .map(|(_, keys)| keys.as_slice())
+        .unwrap_or_default();
+      let mut stored_by_name = HashMap::<&str, Vec<&str>>::new();
+      for (name, stored) in &names {
+        stored_by_name.entry(name).or_default().push(stored);
+      }
+      let mut listed = |keys: &mut dyn Iterator<Item = &String>, order: &mut Vec<String>| {
+        for key in keys {
+          for stored in stored_by_name.remove(key.as_str()).unwrap_or_default() {
+            if ordered.insert(stored.to_string()) {
+              order.push(stored.to_string());
+            }
+          }
+        }
+      };
+      listed(&mut priority.iter(), &mut order);
+      let mut grouped = Vec::new();
+      listed(
+        &mut sections.iter().flat_map(|section| &section.keys),
+        &mut grouped,
+      );
+      let sort_keys = rules.get("sort_keys", &segments).unwrap_or(sort_keys);
+      let sort = sort_keys || !priority.is_empty();
+      if sort {
+        names.sort();
+      }
+      // The properties of no section come before the first section, so they do not look like
+      // part of the last.
+      if sort || !grouped.is_empty() {
+        for (_, stored) in names {
+          if !ordered.contains(&stored) {
+            ordered.insert(stored.clone());
+            order.push(stored);
+          }
+        }
+      }
+      order.extend(grouped);
+      if order.is_empty() {
+        return None;
+      }
+      let names = order.into_iter().map(intern).collect();
+      let path = intern(path);
+      Some((path, [PathOption::PropertyNameOrder(names)].into()))
+    })
+    .collect()
+}
+
+/// Returns `text` as a `'static` string, for json5format. Each distinct text is leaked once per
+/// process, so the memory grows with the distinct paths and names formatted (not with the number
+/// of documents, for `serve` and the persistent worker, which format the same paths repeatedly).
+pub fn intern(text: String) -> &'static str {
+  let mut interned = INTERNED.lock().unwrap_or_else(|error| error.into_inner());
+  let interned = interned.get_or_insert_with(HashSet::new);
+  match interned.get(text.as_str()) {
+    Some(text) => text,
+    None => {
+      let text = Box::leak(text.into_boxed_str());
+      interned.insert(text);
+      text
+    }
+  }
+}
+
+/// The decoded path segments of the objects at a json5format path (with `*` for array items),
+/// and their property names, as pairs of the decoded name (for sorting) and the name as
+/// json5format stores it.
+type Names = (Vec<String>, Vec<(String, String)>);
+
+/// Returns path rules that turn `sort_keys` and `sort_arrays` off for each object and array in the
+/// document whose top-level value is `root`, read from `source`, that directly holds a
+/// [NO_SORT](constant.NO_SORT.html) comment. Objects and arrays below a property whose name cannot
+/// be written in a pattern (because it contains `/` or `*`) are left as selected by the options.
+pub fn no_sort_rules(root: &Node, source: &str) -> Vec<PathRule> {
+  let inside = |node: &Node, offset: usize| {
+    let span = node.span();
+    matches!(node, Node::Object { .. } | Node::Array { .. })
+      && span.start < offset
+      && offset < span.end
+  };
+  let mut rules = Vec::new();
+  for token in lexer::tokenize(source) {
+    let comment = &source[token.start..token.end];
+    let text = match token.kind {
+      TokenKind::LineComment => &comment[2..],
+      TokenKind::BlockComment => comment[2..].strip_suffix("*/").unwrap_or(&comment[2..]),
+      _ => continue,
+    };
+    if text.trim() != NO_SORT || !inside(root, token.start) {
+      continue;
+    }
+    // The innermost object or array holding the comment.
+    let (mut node, mut segments) = (root, Vec::new());
+    loop {
+      let child = match node {
+        Node::Object { members, .. } => members
+          .iter()
+          .find(|member| inside(&member.value, token.start))
+          .map(|member| (member.name.clone(), &member.value)),
+        Node::Array { items, .. } => items
+          .iter()
+          .find(|item| inside(item, token.start))
+          .map(|item| ("*".to_string(), item)),
+        Node::String { .. } | Node::Literal { .. } => None,
+      };
+      let Some((segment, child)) = child else {
+        break;
+      };
+      segments.push((segment, matches!(node, Node::Array { .. })));
+      node = child;
+    }
+    if segments
+      .iter()
+      .any(|(name, item)| !item && (name.contains('/') || name.contains('*')))
+    {
+      continue;
+    }
+    let names = segments
+      .into_iter()
+      .map(|(name, _)| name)
+      .collect::<Vec<_>>();
+    rules.push(PathRule {
+      pattern: format!("/{}", names.join("/")),
+      options: [
+        ("sort_keys".to_string(), false),
+        ("sort_arrays".to_string(), false),
+      ]
+      .into(),
+    });
+  }
+  rules
+}
+
+/// Records the property names of `node` and its descendants, where `node` is at `path`, whose
+/// decoded segments are `segments`.
+fn collect(
+  node: &Node,
+  source: &str,
+  path: String,
+  segments: &mut Vec<String>,
+  names_by_path: &mut BTreeMap<String, Names>,
+) {
+  match node {
+    Node::Object { members, .. } => {
+      for member in members {
+        let stored = stored_name(member, source);
+        if !stored.contains('/') && stored != "*" {
+          segments.push(member.name.clone());
+          collect(
+            &member.value,
+            source,
+            format!("{}/{}", path, stored),
+            segments,
+            names_by_path,
+          );
+          segments.pop();
+        }
+        names_by_path
+          .entry(path.clone())
+          .or_insert_with(|| (segments.clone(), Vec::new()))
+          .1
+          .push((member.name.clone(), stored));
+      }
+    }
+    Node::Array { items, .. } => {
+      segments.push("*".to_string());
+      for item in items {
+        collect(item, source, format!("{}/*", path), segments, names_by_path);
+      }
+      segments.pop();
+    }
+    Node::String { .. } | Node::Literal { .. } => {}
+  }
+}
+
+/// Returns the name of a property as json5format stores it: without quotes if the name is a valid
+/// unquoted property name, and otherwise exactly as written, including its quotes.
+pub fn stored_name(member: &Member, source: &str) -> String {
+  let key = &source[member.key.start..member.key.end];
+  match key.chars().next() {
+    Some('"' | '\'') if is_identifier(&key[1..key.len() - 1]) => key[1..key.len() - 1].to_string(),
+    _ => key.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::syntax;
+
+  #[test]
+  fn orders_the_properties_of_many_objects() {
+    let items = vec!["{b: 1, a: 2}"; 20_000].join(", ");
+    let objects = (0..20_000)
+      .map(|index| format!("k{}: {{b: 1, a: 2}}", index))
+      .collect::<Vec<_>>()
+      .join(", ");
+    let source = format!("{{items: [{}], objects: {{{}}}}}", items, objects);
+    let root = syntax::parse(&source).unwrap().unwrap();
+    let orders = property_orders(&root, &source, true, &[], &[], &PathRules::new(&[]));
+    assert_eq!(orders.len(), 20_003);
+    let ordered = |names: &[&'static str]| [PathOption::PropertyNameOrder(names.to_vec())].into();
+    assert_eq!(orders["/*/items/*"], ordered(&["a", "b"]));
+    assert_eq!(orders["/*/objects/k123"], ordered(&["a", "b"]));
+    let mut keys = (0..20_000)
+      .map(|index| format!("k{}", index))
+      .collect::<Vec<_>>();
+    keys.sort();
+    let keys = keys
+      .iter()
+      .map(|key| intern(key.clone()))
+      .collect::<Vec<_>>();
+    assert_eq!(orders["/*/objects"], ordered(&keys));
+  }
+
+  #[test]
+  fn interns_each_text_once() {
+    let first = intern("interned".to_string());
+    let second = intern("interned".to_string());
+    assert!(std::ptr::eq(first, second));
+  }
+}
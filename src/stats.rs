@@ -0,0 +1,180 @@
+//! Reports statistics about the structure of documents, for the `stats` subcommand: how deeply
+//! their values nest, how many objects, arrays, and properties they hold, how many comments, how
+//! many values of each primitive type, which arrays are the largest, and how many properties
+//! repeat the name of an earlier property of the same object. For example:
+//!
+//!     config.json5:
+//!       depth: 3
+//!       objects: 4 (11 properties)
+//!       arrays: 2 (9 items)
+//!       comments: 5 (4 line, 1 block)
+//!       values: 12 strings, 5 numbers, 2 booleans, 1 null
+//!       largest arrays: /dependencies (7 items), /targets/0/sources (2 items)
+//!       duplicate keys: 1 (/targets/0/name)
+//!
+//! The depth of a document is the number of objects and arrays its most deeply nested value is
+//! in, so a document that is a single string has a depth of 0.
+
+use crate::{
+  lexer::{self, TokenKind},
+  syntax::Node,
+};
+use std::collections::HashSet;
+
+/// The number of largest arrays reported.
+const LARGEST_ARRAYS: usize = 3;
+
+/// The statistics of a document.
+#[derive(Debug, Default)]
+pub struct Stats {
+  /// The number of objects and arrays the most deeply nested value is in.
+  pub depth: usize,
+  /// The number of objects.
+  pub objects: usize,
+  /// The number of properties of all objects.
+  pub members: usize,
+  /// The number of arrays.
+  pub arrays: usize,
+  /// The number of items of all arrays.
+  pub items: usize,
+  /// The number of line comments.
+  pub line_comments: usize,
+  /// The number of block comments.
+  pub block_comments: usize,
+  /// The number of strings, not counting property names.
+  pub strings: usize,
+  /// The number of numbers (including `Infinity` and `NaN`).
+  pub numbers: usize,
+  /// The number of `true` and `false` values.
+  pub booleans: usize,
+  /// The number of `null` values.
+  pub nulls: usize,
+  /// The paths and lengths of the largest non-empty arrays, largest first.
+  pub largest_arrays: Vec<(String, usize)>,
+  /// The paths of the properties that repeat the name of an earlier property of the same object.
+  pub duplicate_keys: Vec<String>,
+}
+
+impl Stats {
+  /// Returns the statistics of the document `root`, parsed from `source`, whose content starts
+  /// after the preamble that ends at byte offset `start`.
+  pub fn new(root: &Node, source: &str, start: usize) -> Self {
+    let mut stats = Stats::default();
+    let mut arrays = Vec::new();
+    stats.visit(root, source, "", 0, &mut arrays);
+    // A stable sort keeps arrays of the same length in document order.
+    arrays.sort_by(|(_, a), (_, b)| b.cmp(a));
+    arrays.truncate(LARGEST_ARRAYS);
+    stats.largest_arrays = arrays;
+    for token in lexer::tokenize(&source[start..]) {
+      match token.kind {
+        TokenKind::LineComment => stats.line_comments += 1,
+        TokenKind::BlockComment => stats.block_comments += 1,
+        _ => {}
+      }
+    }
+    stats
+  }
+
+  /// Counts `node`, at `path` and in `depth` objects and arrays, and the values in it, adding the
+  /// path and length of each non-empty array to `arrays`.
+  fn visit(
+    &mut self,
+    node: &Node,
+    source: &str,
+    path: &str,
+    depth: usize,
+    arrays: &mut Vec<(String, usize)>,
+  ) {
+    self.depth = self.depth.max(depth);
+    match node {
+      Node::Object { members, .. } => {
+        self.objects += 1;
+        self.members += members.len();
+        let mut names = HashSet::new();
+        for member in members {
+          let path = format!("{}/{}", path, member.name);
+          if !names.insert(member.name.as_str()) {
+            self.duplicate_keys.push(path.clone());
+          }
+          self.visit(&member.value, source, &path, depth + 1, arrays);
+        }
+      }
+      Node::Array { items, .. } => {
+        self.arrays += 1;
+        self.items += items.len();
+        if !items.is_empty() {
+          let path = match path.is_empty() {
+            true => "/".to_string(),
+            false => path.to_string(),
+          };
+          arrays.push((path, items.len()));
+        }
+        for (index, item) in items.iter().enumerate() {
+          let path = format!("{}/{}", path, index);
+          self.visit(item, source, &path, depth + 1, arrays);
+        }
+      }
+      Node::String { .. } => self.strings += 1,
+      Node::Literal { .. } => match node.type_name(source) {
+        "boolean" => self.booleans += 1,
+        "null" => self.nulls += 1,
+        _ => self.numbers += 1,
+      },
+    }
+  }
+
+  /// Returns the report of the statistics of the document read from `filename`, one statistic per
+  /// line, ending with a line break.
+  pub fn report(&self, filename: &str) -> String {
+    let plural = |count: usize, noun: &str| match count {
+      1 => format!("{} {}", count, noun),
+      _ => format!("{} {}s", count, noun),
+    };
+    let mut report = format!("{}:\n", filename);
+    report.push_str(&format!("  depth: {}\n", self.depth));
+    report.push_str(&format!(
+      "  objects: {} ({})\n",
+      self.objects,
+      match self.members {
+        1 => "1 property".to_string(),
+        members => format!("{} properties", members),
+      }
+    ));
+    report.push_str(&format!(
+      "  arrays: {} ({})\n",
+      self.arrays,
+      plural(self.items, "item")
+    ));
+    report.push_str(&format!(
+      "  comments: {} ({} line, {} block)\n",
+      self.line_comments + self.block_comments,
+      self.line_comments,
+      self.block_comments
+    ));
+    report.push_str(&format!(
+      "  values: {}, {}, {}, {}\n",
+      plural(self.strings, "string"),
+      plural(self.numbers, "number"),
+      plural(self.booleans, "boolean"),
+      plural(self.nulls, "null")
+    ));
+    if !self.largest_arrays.is_empty() {
+      let arrays = self
+        .largest_arrays
+        .iter()
+        .map(|(path, len)| format!("{} ({})", path, plural(*len, "item")))
+        .collect::<Vec<_>>();
+      report.push_str(&format!("  largest arrays: {}\n", arrays.join(", ")));
+    }
+    match self.duplicate_keys.is_empty() {
+      true => report.push_str("  duplicate keys: 0\n"),
+      false => report.push_str(&format!(
+        "  duplicate keys: {} ({})\n",
+        self.duplicate_keys.len(),
+        self.duplicate_keys.join(", ")
+      )),
+    }
+    report
+  }
+}
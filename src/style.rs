@@ -0,0 +1,179 @@
+//! Style presets (the `style` option), which set several formatting options at once, to match the
+//! output of another formatter. The options a preset sets are defaults: any option set in the
+//! configuration file, the environment, or on the command line takes precedence.
+//!
+//! The `prettier` preset approximates Prettier's output for JSON5 files: it indents by 2 spaces,
+//! writes no trailing commas, quotes string values with double quotes, and packs the items of
+//! arrays of numbers onto lines of up to 80 characters (Prettier's print width). Prettier
+//! also writes an object or array that fits on one line on a single line, unless (for objects) the
+//! input has a line break after its opening brace; json5format always writes the properties and
+//! items of a non-empty object or array on lines of their own, so such values differ. The
+//! conformance tests in `tests/prettier` hold documents Prettier and the preset format the same
+//! way, checked by `cargo test` or with:
+//!
+//!     formatjson5 --style prettier --check-snapshots tests/prettier/*.json5
+//!
+//! The `fuchsia` preset follows the settings the Fuchsia project formats JSON5 with (`cmc format`,
+//! where json5format comes from), for component manifests (`.cml` files): it indents by 4 spaces,
+//! writes trailing commas, collapses objects and arrays of one item onto one line, sorts arrays of
+//! primitive values (except the `args` and other arrays of a `program`), and orders the top-level
+//! sections of a manifest, the properties of its `program`, and the properties of the items of
+//! its sections (such as `use` and `offer`) in the canonical order. Unlike `cmc format`, the
+//! properties not in an order follow the listed ones sorted by name (see
+//! [sorting](../sorting/index.html)).
+
+use crate::{
+  config::{Config, Setting},
+  path_options::PathRule,
+  sorting::Priority,
+};
+use std::{collections::BTreeMap, str::FromStr};
+
+/// The order of the top-level properties of a Fuchsia component manifest.
+const FUCHSIA_MANIFEST_ORDER: &[&str] = &[
+  "include",
+  "disable",
+  "program",
+  "children",
+  "collections",
+  "capabilities",
+  "use",
+  "offer",
+  "expose",
+  "environments",
+  "facets",
+  "config",
+];
+
+/// The order of the properties of the `program` of a Fuchsia component manifest.
+const FUCHSIA_PROGRAM_ORDER: &[&str] = &["runner", "binary", "args"];
+
+/// The order of the properties of the items of the sections of a Fuchsia component manifest (such
+/// as the capabilities in `use`, `offer`, and `expose`).
+const FUCHSIA_ITEM_ORDER: &[&str] = &[
+  "name",
+  "url",
+  "startup",
+  "environment",
+  "durability",
+  "service",
+  "protocol",
+  "directory",
+  "storage",
+  "runner",
+  "resolver",
+  "event",
+  "event_stream",
+  "from",
+  "as",
+  "to",
+  "rights",
+  "path",
+  "subdir",
+  "filter",
+  "dependency",
+  "extends",
+  "runners",
+  "resolvers",
+  "debug",
+];
+
+/// A style preset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+  /// The output of Prettier.
+  Prettier,
+  /// The settings of the Fuchsia project, for component manifests.
+  Fuchsia,
+}
+
+impl Style {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["prettier", "fuchsia"];
+
+  /// Returns the spelling of the preset, as in the configuration file.
+  pub fn name(self) -> &'static str {
+    match self {
+      Style::Prettier => "prettier",
+      Style::Fuchsia => "fuchsia",
+    }
+  }
+
+  /// Returns the options the preset sets.
+  pub fn preset(self) -> Config {
+    let mut config = Config::default();
+    match self {
+      Style::Prettier => {
+        config.set("indent", Setting::Integer(2));
+        config.set("trailing_commas", Setting::Bool(false));
+        config.set("quotes", Setting::String("double".to_string()));
+        config.set("max_width", Setting::Integer(80));
+        config.set("array_wrap", Setting::String("fill-numbers".to_string()));
+      }
+      Style::Fuchsia => {
+        config.set("indent", Setting::Integer(4));
+        config.set("trailing_commas", Setting::Bool(true));
+        config.set("one_element_lines", Setting::Bool(true));
+        config.set("sort_arrays", Setting::Bool(true));
+        let rule = |pattern: &str, options: &[(&str, bool)]| PathRule {
+          pattern: pattern.to_string(),
+          options: options
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect::<BTreeMap<_, _>>(),
+        };
+        config.set(
+          "paths",
+          Setting::PathRules(vec![
+            rule("/program", &[("one_element_lines", false)]),
+            rule("/program/**", &[("sort_arrays", false)]),
+          ]),
+        );
+        let priority = |pattern: &str, keys: &[&str]| Priority {
+          pattern: pattern.to_string(),
+          keys: keys.iter().map(|key| key.to_string()).collect(),
+        };
+        config.set(
+          "sort_priority",
+          Setting::Priorities(vec![
+            priority("/", FUCHSIA_MANIFEST_ORDER),
+            priority("/program", FUCHSIA_PROGRAM_ORDER),
+            priority("/*/*", FUCHSIA_ITEM_ORDER),
+          ]),
+        );
+      }
+    }
+    config
+  }
+}
+
+impl FromStr for Style {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "prettier" => Ok(Style::Prettier),
+      "fuchsia" => Ok(Style::Fuchsia),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        Style::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// Returns `config` with the options of its `style` preset, if any, for the options it does not
+/// set.
+pub fn apply(config: Config) -> Config {
+  match config
+    .string("style")
+    .and_then(|style| style.parse::<Style>().ok())
+  {
+    Some(style) => {
+      let mut preset = style.preset();
+      preset.merge(config);
+      preset
+    }
+    None => config,
+  }
+}
@@ -0,0 +1,14 @@
+//! Finds the closest match for a misspelled name, for "did you mean ...?" hints.
+
+/// Returns the candidate closest to `name` (by optimal string alignment distance, so transposed
+/// letters count as a single edit), or `None` if no candidate is similar enough to be a likely
+/// misspelling. An exact match is not returned as a suggestion.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+  let max_distance = (name.chars().count() / 3).clamp(1, 3);
+  candidates
+    .into_iter()
+    .map(|candidate| (strsim::osa_distance(name, candidate), candidate))
+    .filter(|(distance, _)| *distance > 0 && *distance <= max_distance)
+    .min_by_key(|(distance, _)| *distance)
+    .map(|(_, candidate)| candidate)
+}
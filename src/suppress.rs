@@ -0,0 +1,96 @@
+//! Suppression comments, which silence specific lint diagnostics in a document:
+//!
+//!     {
+//!         // json5format-allow: no-empty-object
+//!         defaults: {},
+//!     }
+//!
+//! A `json5format-allow:` comment suppresses the listed diagnostics (by name or code, such as
+//! `no-empty-object` or `J5F008`, separated by commas) on the line after the comment, and a
+//! `json5format-allow-file:` comment anywhere in the document suppresses them in the whole
+//! document. Parse errors and dialect errors cannot be suppressed, since the document cannot be
+//! formatted while they remain.
+
+use crate::{
+  codes::{self, Code},
+  diagnostics::Diagnostic,
+  lexer::{self, TokenKind},
+};
+
+/// The marker of a comment that suppresses diagnostics on the next line.
+const ALLOW: &str = "json5format-allow:";
+
+/// The marker of a comment that suppresses diagnostics in the whole document.
+const ALLOW_FILE: &str = "json5format-allow-file:";
+
+/// Returns true if diagnostics of the kind `code` can be suppressed.
+pub fn is_suppressible(code: &Code) -> bool {
+  ![
+    codes::PARSE_ERROR.id,
+    codes::TRAILING_CONTENT.id,
+    codes::DIALECT.id,
+  ]
+  .contains(&code.id)
+}
+
+/// The suppression comments of a document.
+#[derive(Debug, Default)]
+pub struct Suppressions {
+  /// The codes suppressed on each line, by the 1-based line number they apply to.
+  lines: Vec<(usize, &'static Code)>,
+  /// The codes suppressed in the whole document.
+  file: Vec<&'static Code>,
+}
+
+impl Suppressions {
+  /// Finds the suppression comments in `source`. Returns the suppressions, and a warning message
+  /// (with the location of the comment) for each name that is not a diagnostic code.
+  pub fn parse(source: &str) -> (Suppressions, Vec<String>) {
+    let mut suppressions = Suppressions::default();
+    let mut warnings = Vec::new();
+    for token in lexer::tokenize(source) {
+      if !matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment) {
+        continue;
+      }
+      let comment = &source[token.start..token.end];
+      let text = match token.kind {
+        TokenKind::LineComment => &comment[2..],
+        _ => comment[2..].strip_suffix("*/").unwrap_or(&comment[2..]),
+      }
+      .trim();
+      let (names, file) = match (text.strip_prefix(ALLOW), text.strip_prefix(ALLOW_FILE)) {
+        (Some(names), _) => (names, false),
+        (_, Some(names)) => (names, true),
+        _ => continue,
+      };
+      // A block comment may span lines; it applies to the line after its end.
+      let line = token.line + source[token.start..token.end].matches('\n').count() + 1;
+      for name in names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+      {
+        match codes::find(name) {
+          Ok(code) if !is_suppressible(code) => warnings.push(format!(
+            "{}:{}: {} ({}) cannot be suppressed",
+            token.line, token.col, code.id, code.name
+          )),
+          Ok(code) if file => suppressions.file.push(code),
+          Ok(code) => suppressions.lines.push((line, code)),
+          Err(message) => warnings.push(format!("{}:{}: {}", token.line, token.col, message)),
+        }
+      }
+    }
+    (suppressions, warnings)
+  }
+
+  /// Returns true if `diagnostic` is suppressed.
+  pub fn allows(&self, diagnostic: &Diagnostic) -> bool {
+    let code = diagnostic.code.id;
+    self.file.iter().any(|allowed| allowed.id == code)
+      || self
+        .lines
+        .iter()
+        .any(|(line, allowed)| *line == diagnostic.line && allowed.id == code)
+  }
+}
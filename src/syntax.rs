@@ -0,0 +1,604 @@
+//! A parse tree for JSON5 documents that records where each value is in the source. The
+//! json5format parser only exposes a position when it fails, so this tree (built from the
+//! [lexer](../lexer/index.html) tokens) is used wherever a value must be reported or edited at its
+//! location in the source. [Node::walk](enum.Node.html#method.walk) visits each value of a tree
+//! with its path, for the checks and edits that apply to values wherever they are, and
+//! [Comments](struct.Comments.html) records the position of each comment and the value or property
+//! it belongs to.
+
+use crate::lexer::{self, Token, TokenKind};
+use std::collections::HashMap;
+
+/// The position of a token or value in the source buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+  /// The byte offset of the first character.
+  pub start: usize,
+  /// The byte offset just past the last character.
+  pub end: usize,
+  /// The 1-based line number of the first character.
+  pub line: usize,
+  /// The 1-based character column of the first character.
+  pub col: usize,
+}
+
+impl Span {
+  /// Returns the span of a single token.
+  fn of(token: &Token) -> Self {
+    Span {
+      start: token.start,
+      end: token.end,
+      line: token.line,
+      col: token.col,
+    }
+  }
+
+  /// Returns the span from the start of `self` to the end of `other`.
+  fn to(self, other: Span) -> Self {
+    Span {
+      end: other.end,
+      ..self
+    }
+  }
+}
+
+/// A property of an object.
+#[derive(Debug)]
+pub struct Member {
+  /// The property name, with any quotes removed and escapes decoded.
+  pub name: String,
+  /// The position of the property name, including any quotes.
+  pub key: Span,
+  /// The property value.
+  pub value: Node,
+}
+
+/// A JSON5 value and its position in the source.
+#[derive(Debug)]
+pub enum Node {
+  /// An object, and its properties in source order.
+  Object {
+    /// The position of the object, from its opening to its closing brace.
+    span: Span,
+    /// The properties of the object.
+    members: Vec<Member>,
+  },
+  /// An array, and its items in source order.
+  Array {
+    /// The position of the array, from its opening to its closing bracket.
+    span: Span,
+    /// The items of the array.
+    items: Vec<Node>,
+  },
+  /// A single- or double-quoted string.
+  String {
+    /// The position of the string, including its quotes.
+    span: Span,
+    /// The string value, with quotes removed and escapes decoded.
+    value: String,
+  },
+  /// Any other primitive: a number, `true`, `false`, `null`, `Infinity`, or `NaN`.
+  Literal {
+    /// The position of the literal.
+    span: Span,
+  },
+}
+
+impl Node {
+  /// Returns the position of the value in the source.
+  pub fn span(&self) -> Span {
+    match self {
+      Node::Object { span, .. }
+      | Node::Array { span, .. }
+      | Node::String { span, .. }
+      | Node::Literal { span } => *span,
+    }
+  }
+
+  /// Returns a description of the value's type (such as "object" or "number"), for messages.
+  pub fn type_name(&self, source: &str) -> &'static str {
+    match self {
+      Node::Object { .. } => "object",
+      Node::Array { .. } => "array",
+      Node::String { .. } => "string",
+      Node::Literal { span } => match &source[span.start..span.end] {
+        "true" | "false" => "boolean",
+        "null" => "null",
+        _ => "number",
+      },
+    }
+  }
+
+  /// Calls `visit` on the value and on each of its descendants, in source order (each value before
+  /// its descendants), with its path from the value: the names of the properties and the indexes of
+  /// the array items that lead to it, so the value itself has the empty path. The values left to
+  /// visit are kept on a stack of their own, so deeply nested values cannot overflow the call
+  /// stack.
+  pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Node, &[String])) {
+    let mut path = Vec::new();
+    // Each value left to visit, the length of its parent's path, and its last path segment.
+    let mut pending = vec![(self, 0, None)];
+    while let Some((node, depth, segment)) = pending.pop() {
+      path.truncate(depth);
+      path.extend(segment);
+      visit(node, &path);
+      let depth = path.len();
+      match node {
+        Node::Object { members, .. } => pending.extend(
+          members
+            .iter()
+            .rev()
+            .map(|member| (&member.value, depth, Some(member.name.clone()))),
+        ),
+        Node::Array { items, .. } => pending.extend(
+          items
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, item)| (item, depth, Some(index.to_string()))),
+        ),
+        Node::String { .. } | Node::Literal { .. } => {}
+      }
+    }
+  }
+}
+
+/// The comments of a document, attached to the values and properties around them the way the
+/// formatter moves them: a comment on the same line after a value (and its comma) trails that
+/// value; other comments lead the next property name or value, or, if no value follows, end the
+/// enclosing object or array (or the document).
+#[derive(Debug)]
+pub struct Comments {
+  /// The index of the token other than whitespace and comments starting at each byte offset.
+  by_start: HashMap<usize, usize>,
+  /// The index of the token other than whitespace and comments ending at each byte offset.
+  by_end: HashMap<usize, usize>,
+  /// Whether the token at each index is a comma.
+  commas: Vec<bool>,
+  /// For each token index, the comments before that token that trail the previous token, and the
+  /// comments that lead it. The last entry holds the comments after the last token.
+  gaps: Vec<(Vec<Span>, Vec<Span>)>,
+}
+
+impl Comments {
+  /// Finds and groups the comments of the document in `source`.
+  pub fn new(source: &str) -> Self {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut gaps = vec![(Vec::new(), Vec::new())];
+    for token in lexer::tokenize(source) {
+      match token.kind {
+        TokenKind::LineComment | TokenKind::BlockComment => {
+          let trails = tokens.last().is_some_and(|previous| {
+            previous.line == token.line
+              && !matches!(
+                previous.kind,
+                TokenKind::OpenBrace | TokenKind::OpenBracket | TokenKind::Colon
+              )
+              && gaps.last().unwrap().1.is_empty()
+          });
+          let gap = gaps.last_mut().unwrap();
+          match trails {
+            true => gap.0.push(Span::of(&token)),
+            false => gap.1.push(Span::of(&token)),
+          }
+        }
+        _ if token.is_trivia() => {}
+        _ => {
+          tokens.push(token);
+          gaps.push((Vec::new(), Vec::new()));
+        }
+      }
+    }
+    let index_by = |offset: fn(&Token) -> usize| {
+      tokens
+        .iter()
+        .enumerate()
+        .map(|(index, token)| (offset(token), index))
+        .collect::<HashMap<_, _>>()
+    };
+    Self {
+      by_start: index_by(|token| token.start),
+      by_end: index_by(|token| token.end),
+      commas: tokens
+        .iter()
+        .map(|token| token.kind == TokenKind::Comma)
+        .collect(),
+      gaps,
+    }
+  }
+
+  /// Returns the comments that lead the property name or value starting at byte offset `start`.
+  pub fn leading(&self, start: usize) -> &[Span] {
+    self
+      .by_start
+      .get(&start)
+      .map_or(&[], |&index| &self.gaps[index].1)
+  }
+
+  /// Returns the comments that trail the value ending at byte offset `end`, including comments
+  /// after its comma.
+  pub fn trailing(&self, end: usize) -> Vec<Span> {
+    let Some(&index) = self.by_end.get(&end) else {
+      return Vec::new();
+    };
+    let next = index + 1;
+    let mut comments = self.gaps[next].0.clone();
+    if self.commas.get(next) == Some(&true) {
+      comments.extend(self.gaps[next + 1].0.iter().copied());
+    }
+    comments
+  }
+
+  /// Returns the comments before the closing brace or bracket that ends at byte offset `end`, which
+  /// do not trail the last value.
+  pub fn closing(&self, end: usize) -> &[Span] {
+    self
+      .by_end
+      .get(&end)
+      .map_or(&[], |&index| &self.gaps[index].1)
+  }
+
+  /// Returns the comments after the document's value that do not trail it, or every comment of a
+  /// document that holds no value.
+  pub fn end(&self) -> &[Span] {
+    &self.gaps.last().unwrap().1
+  }
+}
+
+/// A syntax error, at a 1-based line and column.
+#[derive(Debug)]
+pub struct SyntaxError {
+  /// The line number where the error was detected.
+  pub line: usize,
+  /// The column number where the error was detected.
+  pub col: usize,
+  /// A description of the error.
+  pub message: String,
+}
+
+impl std::fmt::Display for SyntaxError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}:{}: {}", self.line, self.col, self.message)
+  }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// The maximum nesting depth of objects and arrays, matching json5format's default limit, since
+/// json5format formats nested values recursively (see
+/// [STACK_SIZE](../parallel/constant.STACK_SIZE.html)).
+pub const NESTING_LIMIT: usize = 1000;
+
+/// Parses the JSON5 document in `source`, returning its top-level value, or `None` if the document
+/// holds no value (only whitespace and comments).
+pub fn parse(source: &str) -> Result<Option<Node>, SyntaxError> {
+  let tokens = lexer::tokenize(source);
+  let tokens = tokens
+    .iter()
+    .filter(|token| !token.is_trivia())
+    .copied()
+    .collect::<Vec<_>>();
+  let mut parser = Parser {
+    source,
+    tokens: &tokens,
+    next: 0,
+  };
+  if parser.peek().is_none() {
+    return Ok(None);
+  }
+  let value = parser.value()?;
+  match parser.peek() {
+    Some(token) => Err(parser.error(&token, "Unexpected content after the end of the document")),
+    None => Ok(Some(value)),
+  }
+}
+
+/// A parser over the non-trivia tokens of a document. The objects and arrays being parsed are kept
+/// on a stack of its own, so deeply nested documents cannot overflow the call stack.
+struct Parser<'a> {
+  /// The document source.
+  source: &'a str,
+  /// The tokens of the document, excluding whitespace and comments.
+  tokens: &'a [Token],
+  /// The index of the next unconsumed token.
+  next: usize,
+}
+
+/// An object or array whose closing brace or bracket has not been parsed yet.
+enum Container {
+  /// An object, with its opening brace, the properties parsed so far, and the name and position of
+  /// the property whose value is being parsed.
+  Object {
+    open: Token,
+    members: Vec<Member>,
+    key: Option<(String, Span)>,
+  },
+  /// An array, with its opening bracket and the items parsed so far.
+  Array { open: Token, items: Vec<Node> },
+}
+
+/// What follows an object or array element (or its opening token).
+enum Next {
+  /// Another value of the container.
+  Value(Container),
+  /// The end of the container, which is complete.
+  Closed(Node),
+}
+
+impl<'a> Parser<'a> {
+  /// Returns the next token without consuming it.
+  fn peek(&self) -> Option<Token> {
+    self.tokens.get(self.next).copied()
+  }
+
+  /// Consumes and returns the next token, or fails if the document ended.
+  fn bump(&mut self, expected: &str) -> Result<Token, SyntaxError> {
+    match self.peek() {
+      Some(token) => {
+        self.next += 1;
+        Ok(token)
+      }
+      None => {
+        let (line, col) = end_position(self.source);
+        Err(SyntaxError {
+          line,
+          col,
+          message: format!("Expected {} before the end of the document", expected),
+        })
+      }
+    }
+  }
+
+  /// Returns an error at the position of `token`.
+  fn error(&self, token: &Token, message: &str) -> SyntaxError {
+    SyntaxError {
+      line: token.line,
+      col: token.col,
+      message: message.to_string(),
+    }
+  }
+
+  /// Parses a value of any type, enforcing the nesting limit.
+  fn value(&mut self) -> Result<Node, SyntaxError> {
+    // The objects and arrays that are open, innermost last.
+    let mut open = Vec::new();
+    loop {
+      let token = self.bump("a value")?;
+      let mut node = match token.kind {
+        TokenKind::OpenBrace | TokenKind::OpenBracket => {
+          if open.len() == NESTING_LIMIT {
+            return Err(self.error(&token, "The document exceeds the maximum nesting depth"));
+          }
+          let container = match token.kind {
+            TokenKind::OpenBrace => Container::Object {
+              open: token,
+              members: Vec::new(),
+              key: None,
+            },
+            _ => Container::Array {
+              open: token,
+              items: Vec::new(),
+            },
+          };
+          match self.element(container)? {
+            Next::Value(container) => {
+              open.push(container);
+              continue;
+            }
+            Next::Closed(node) => node,
+          }
+        }
+        TokenKind::String => Node::String {
+          span: Span::of(&token),
+          value: unquote(&self.source[token.start..token.end])
+            .map_err(|message| self.error(&token, &message))?,
+        },
+        TokenKind::Word => Node::Literal {
+          span: Span::of(&token),
+        },
+        _ => return Err(self.error(&token, "Expected a value")),
+      };
+      // Add the value to the innermost open container, closing each container that ends with it.
+      loop {
+        let Some(container) = open.pop() else {
+          return Ok(node);
+        };
+        match self.add(container, node)? {
+          Next::Value(container) => {
+            open.push(container);
+            break;
+          }
+          Next::Closed(closed) => node = closed,
+        }
+      }
+    }
+  }
+
+  /// Parses what follows the opening token of `container`, or the comma after one of its
+  /// elements, up to its next value: the name of the next property of an object, and its colon.
+  fn element(&mut self, container: Container) -> Result<Next, SyntaxError> {
+    match container {
+      Container::Object { open, members, .. } => {
+        let token = self.bump("a property name or '}'")?;
+        let name = match token.kind {
+          TokenKind::CloseBrace => {
+            return Ok(Next::Closed(Node::Object {
+              span: Span::of(&open).to(Span::of(&token)),
+              members,
+            }))
+          }
+          TokenKind::Word => self.source[token.start..token.end].to_string(),
+          TokenKind::String => unquote(&self.source[token.start..token.end])
+            .map_err(|message| self.error(&token, &message))?,
+          _ => return Err(self.error(&token, "Expected a property name or '}'")),
+        };
+        let colon = self.bump("':'")?;
+        if colon.kind != TokenKind::Colon {
+          return Err(self.error(&colon, "Expected ':' after the property name"));
+        }
+        Ok(Next::Value(Container::Object {
+          open,
+          members,
+          key: Some((name, Span::of(&token))),
+        }))
+      }
+      Container::Array { open, items } => match self
+        .peek()
+        .filter(|token| token.kind == TokenKind::CloseBracket)
+      {
+        Some(close) => {
+          self.next += 1;
+          Ok(Next::Closed(Node::Array {
+            span: Span::of(&open).to(Span::of(&close)),
+            items,
+          }))
+        }
+        None => Ok(Next::Value(Container::Array { open, items })),
+      },
+    }
+  }
+
+  /// Adds the parsed `value` to `container`, and parses what follows it: a comma and the start of
+  /// the next element (see `element`), or the end of the container.
+  fn add(&mut self, container: Container, value: Node) -> Result<Next, SyntaxError> {
+    match container {
+      Container::Object {
+        open,
+        mut members,
+        key,
+      } => {
+        let (name, key) = key.expect("a property value follows its name");
+        members.push(Member { name, key, value });
+        let separator = self.bump("',' or '}'")?;
+        match separator.kind {
+          TokenKind::Comma => self.element(Container::Object {
+            open,
+            members,
+            key: None,
+          }),
+          TokenKind::CloseBrace => Ok(Next::Closed(Node::Object {
+            span: Span::of(&open).to(Span::of(&separator)),
+            members,
+          })),
+          _ => Err(self.error(&separator, "Expected ',' or '}' after the property value")),
+        }
+      }
+      Container::Array { open, mut items } => {
+        items.push(value);
+        let separator = self.bump("',' or ']'")?;
+        match separator.kind {
+          TokenKind::Comma => self.element(Container::Array { open, items }),
+          TokenKind::CloseBracket => Ok(Next::Closed(Node::Array {
+            span: Span::of(&open).to(Span::of(&separator)),
+            items,
+          })),
+          _ => Err(self.error(&separator, "Expected ',' or ']' after the array item")),
+        }
+      }
+    }
+  }
+}
+
+/// Returns the 1-based line and column just past the end of `source`.
+pub fn end_position(source: &str) -> (usize, usize) {
+  let line = source.matches('\n').count() + 1;
+  let col = source.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+  (line, col)
+}
+
+/// Decodes a single- or double-quoted JSON5 string literal (including its quotes), returning the
+/// string value or a description of why the literal is invalid.
+pub fn unquote(literal: &str) -> Result<String, String> {
+  let mut chars = literal.chars();
+  let quote = chars.next().ok_or("Empty string literal")?;
+  let mut value = String::new();
+  loop {
+    let c = chars.next().ok_or("Unclosed string")?;
+    if c == quote {
+      break;
+    }
+    if c != '\\' {
+      value.push(c);
+      continue;
+    }
+    let escaped = chars.next().ok_or("Unclosed string")?;
+    match escaped {
+      'b' => value.push('\u{8}'),
+      'f' => value.push('\u{c}'),
+      'n' => value.push('\n'),
+      'r' => value.push('\r'),
+      't' => value.push('\t'),
+      'v' => value.push('\u{b}'),
+      '0' => value.push('\0'),
+      '\n' | '\u{2028}' | '\u{2029}' => {} // line continuation
+      '\r' => {
+        chars = match chars.as_str().strip_prefix('\n') {
+          Some(rest) => rest.chars(),
+          None => chars,
+        }
+      }
+      'x' => value.push(char::from_u32(hex_escape(&mut chars, 2)?).unwrap_or('\u{FFFD}')),
+      'u' => {
+        let mut code = hex_escape(&mut chars, 4)?;
+        if (0xD800..0xDC00).contains(&code) && chars.as_str().starts_with("\\u") {
+          let mut low_chars = chars.clone();
+          low_chars.nth(1);
+          let low = hex_escape(&mut low_chars, 4)?;
+          if (0xDC00..0xE000).contains(&low) {
+            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+            chars = low_chars;
+          }
+        }
+        value.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+      }
+      other => value.push(other),
+    }
+  }
+  if chars.next().is_some() {
+    return Err("Unexpected characters after the closing quote".to_string());
+  }
+  Ok(value)
+}
+
+/// Returns true if `name` may be written as an unquoted property name.
+pub fn is_identifier(name: &str) -> bool {
+  let word = |c: char| c == '$' || c == '_' || c.is_alphanumeric();
+  let mut chars = name.chars();
+  matches!(chars.next(), Some(c) if word(c) && !c.is_ascii_digit())
+    && chars.all(word)
+    && !matches!(name, "true" | "false" | "null")
+}
+
+/// Returns a JSON5 string literal for `value`, enclosed in `quote` (either `"` or `'`).
+pub fn quote(value: &str, quote: char) -> String {
+  let mut literal = String::with_capacity(value.len() + 2);
+  literal.push(quote);
+  for c in value.chars() {
+    match c {
+      '\\' => literal.push_str("\\\\"),
+      '\n' => literal.push_str("\\n"),
+      '\r' => literal.push_str("\\r"),
+      '\t' => literal.push_str("\\t"),
+      c if c == quote => {
+        literal.push('\\');
+        literal.push(c);
+      }
+      c if c.is_control() || c == '\u{2028}' || c == '\u{2029}' => {
+        literal.push_str(&format!("\\u{:04x}", c as u32))
+      }
+      c => literal.push(c),
+    }
+  }
+  literal.push(quote);
+  literal
+}
+
+/// Decodes `digits` hexadecimal characters from `chars` (as in `\xHH` or `\uHHHH`) to a code point.
+fn hex_escape(chars: &mut std::str::Chars<'_>, digits: usize) -> Result<u32, String> {
+  let hex = chars.by_ref().take(digits).collect::<String>();
+  if hex.len() != digits {
+    return Err("Incomplete hexadecimal escape sequence".to_string());
+  }
+  u32::from_str_radix(&hex, 16)
+    .map_err(|_| format!("Invalid hexadecimal escape sequence '{}'", hex))
+}
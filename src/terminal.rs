@@ -0,0 +1,64 @@
+//! Decides how output that is only useful to a person at a terminal (such as banners between
+//! multiple documents) is produced. By default, such output is only produced when stdout is a
+//! terminal, so the formatter's output is safe to consume from scripts and other programs.
+
+use std::str::FromStr;
+
+/// A setting for terminal-dependent behavior: `auto` enables the behavior only when the output is
+/// (or is assumed to be) a terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum When {
+  /// Enable the behavior only when the output is a terminal.
+  Auto,
+  /// Always enable the behavior.
+  Always,
+  /// Never enable the behavior.
+  Never,
+}
+
+impl When {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["auto", "always", "never"];
+
+  /// Returns whether the behavior is enabled, given whether the output is a terminal.
+  pub fn enabled(self, is_tty: bool) -> bool {
+    match self {
+      When::Auto => is_tty,
+      When::Always => true,
+      When::Never => false,
+    }
+  }
+}
+
+impl FromStr for When {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "auto" => Ok(When::Auto),
+      "always" => Ok(When::Always),
+      "never" => Ok(When::Never),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        When::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// What is known about the process's output streams.
+#[derive(Clone, Copy, Debug)]
+pub struct Terminal {
+  /// True if stdout is a terminal, or is assumed to be one.
+  pub stdout_is_tty: bool,
+}
+
+impl Terminal {
+  /// Detects whether stdout is a terminal. If `assume_tty` is true, stdout is treated as a
+  /// terminal regardless.
+  pub fn detect(assume_tty: bool) -> Self {
+    Self {
+      stdout_is_tty: assume_tty || atty::is(atty::Stream::Stdout),
+    }
+  }
+}
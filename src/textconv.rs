@@ -0,0 +1,44 @@
+//! Prints a canonical formatting of a document for git (the `textconv` subcommand), so diffs of
+//! JSON5 files show changes to their data and comments rather than to their formatting:
+//!
+//!     # .gitattributes
+//!     *.json5 diff=json5
+//!
+//!     # .git/config (or ~/.gitconfig)
+//!     [diff "json5"]
+//!         textconv = formatjson5 textconv
+//!         cachetextconv = true
+//!
+//! The canonical formatting does not depend on the configuration: it is json5format's default
+//! style (4-space indentation and trailing commas) with the properties of each object sorted by
+//! name, since their order does not change the data, and the items of arrays in their order. The
+//! command reads no configuration and writes nothing but stdout, and a document that cannot be
+//! parsed is printed unchanged, so a diff never fails because of one file.
+
+use crate::{
+  document::{Document, SourceOptions},
+  path_options::{self, PathRules},
+  sorting, syntax,
+};
+use json5format::FormatOptions;
+
+/// Returns the canonical formatting of the document `buffer`, read from `filename`, or `buffer`
+/// itself if it cannot be parsed.
+pub fn canonical(buffer: String, filename: &str) -> Vec<u8> {
+  let source_options = SourceOptions {
+    // Content after the document is kept, so it still shows in diffs.
+    allow_trailing_garbage: true,
+    ..SourceOptions::default()
+  };
+  let formatted = (|| -> Result<Vec<u8>, anyhow::Error> {
+    let document = Document::parse(buffer.clone(), filename.to_string(), &source_options, 1)?;
+    let mut options = FormatOptions::default();
+    if let Some(root) = syntax::parse(&document.source)? {
+      let rules = PathRules::new(&[]);
+      let orders = sorting::property_orders(&root, &document.source, true, &[], &[], &rules);
+      options.options_by_path = path_options::compact(orders, &root, &document.source);
+    }
+    Ok(document.format(options, filename)?)
+  })();
+  formatted.unwrap_or_else(|_| buffer.into_bytes())
+}
@@ -0,0 +1,108 @@
+//! Measures how long each file takes to read, parse, format, and write (for `--timings`), to find
+//! the documents that slow down a large run.
+
+use std::{
+  collections::BTreeMap,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+/// A stage of processing a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+  /// Reading the file.
+  Read,
+  /// Parsing the document, and checking and editing it before it is formatted.
+  Parse,
+  /// Formatting the document.
+  Format,
+  /// Writing the formatted document.
+  Write,
+}
+
+impl Phase {
+  /// Every phase, in the order they happen.
+  const ALL: [Phase; 4] = [Phase::Read, Phase::Parse, Phase::Format, Phase::Write];
+
+  /// Returns the name of the phase, for reports.
+  fn name(self) -> &'static str {
+    match self {
+      Phase::Read => "read",
+      Phase::Parse => "parse",
+      Phase::Format => "format",
+      Phase::Write => "write",
+    }
+  }
+}
+
+/// The time spent in each phase of each file, which may be recorded from several threads.
+#[derive(Debug, Default)]
+pub struct Timings {
+  /// The time spent in each phase (indexed in the order of `Phase::ALL`), by filename.
+  files: Mutex<BTreeMap<String, [Duration; 4]>>,
+}
+
+impl Timings {
+  /// Adds `duration` to the time spent in `phase` of the file `filename`.
+  pub fn record(&self, filename: &str, phase: Phase, duration: Duration) {
+    let mut files = self.files.lock().unwrap_or_else(|err| err.into_inner());
+    let durations = files.entry(filename.to_string()).or_default();
+    durations[phase as usize] += duration;
+  }
+
+  /// Calls `f`, and adds the time it takes to the time spent in `phase` of the file `filename`.
+  pub fn time<T>(
+    timings: Option<&Timings>,
+    filename: &str,
+    phase: Phase,
+    f: impl FnOnce() -> T,
+  ) -> T {
+    let start = Instant::now();
+    let result = f();
+    if let Some(timings) = timings {
+      timings.record(filename, phase, start.elapsed());
+    }
+    result
+  }
+
+  /// Returns the report of the time spent on each file, in the order of their names, followed by
+  /// the `slowest` files that took the most time in total.
+  pub fn report(&self, slowest: usize) -> String {
+    let files = self.files.lock().unwrap_or_else(|err| err.into_inner());
+    let phases = Phase::ALL.map(Phase::name).join(", ");
+    let mut report = format!("Timings ({}):\n", phases);
+    for (filename, durations) in files.iter() {
+      let durations = durations
+        .iter()
+        .map(|duration| millis(*duration))
+        .collect::<Vec<_>>();
+      report.push_str(&format!("  {}: {}\n", filename, durations.join(", ")));
+    }
+    let mut totals = files
+      .iter()
+      .map(|(filename, durations)| (filename, durations.iter().sum::<Duration>()))
+      .collect::<Vec<_>>();
+    totals.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+    report.push_str(&format!("Slowest {} file(s):\n", slowest.min(totals.len())));
+    for (rank, (filename, total)) in totals.iter().take(slowest).enumerate() {
+      report.push_str(&format!(
+        "  {}. {}: {}\n",
+        rank + 1,
+        filename,
+        millis(*total)
+      ));
+    }
+    let total = totals.iter().map(|(_, total)| *total).sum::<Duration>();
+    report.push_str(&format!(
+      "Total: {} file(s), {}\n",
+      totals.len(),
+      millis(total)
+    ));
+    report
+  }
+}
+
+/// Returns `duration` in milliseconds, for reports.
+fn millis(duration: Duration) -> String {
+  format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
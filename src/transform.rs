@@ -0,0 +1,589 @@
+//! Rewrites values during formatting (the `--transform` option), with a tiny expression language.
+//!
+//! A transform has the form `<path-glob> = <expression>`, such as `/version = bump_patch(.)` or
+//! `**/timeout_ms = . * 2`. Each primitive value whose path matches the [glob](../glob/index.html)
+//! is replaced by the result of the expression, where `.` is the current value. Only the value's
+//! text is replaced, so surrounding comments are preserved.
+//!
+//! Expressions are made of numbers, strings (single- or double-quoted), `true`, `false`, `null`,
+//! `.`, parentheses, the operators `+` (addition, or concatenation if either operand is a
+//! string), `-`, `*`, `/`, and `%`, and the functions `upper(s)`, `lower(s)`, `string(x)`,
+//! `number(s)`, `round(n)`, `bump_major(v)`, `bump_minor(v)`, and `bump_patch(v)` (which
+//! increment part of a `major.minor.patch` version string, resetting the parts after it).
+//!
+//! Numbers are computed as 64-bit floating-point numbers, so an integer whose magnitude is above
+//! 2^53 (such as `9007199254740993`) cannot be transformed, be a constant in an expression, or be
+//! the result of one: it is an error rather than a silently rounded value.
+
+use crate::{
+  document::error_context,
+  edit::Edits,
+  glob::PathGlob,
+  syntax::{self, Node, NESTING_LIMIT},
+};
+use std::str::FromStr;
+
+/// A rule that replaces the values at matching paths with the result of an expression.
+#[derive(Clone, Debug)]
+pub struct Transform {
+  /// The paths of the values to replace.
+  glob: PathGlob,
+  /// The expression that computes each replacement.
+  expr: Expr,
+}
+
+impl FromStr for Transform {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (glob, expr) = s
+      .split_once('=')
+      .ok_or_else(|| anyhow::anyhow!("Expected '<path-glob> = <expression>'"))?;
+    if glob.trim().is_empty() {
+      return Err(anyhow::anyhow!("Expected a path glob before '='"));
+    }
+    let glob = glob.trim().parse()?;
+    let mut parser = ExprParser {
+      text: expr,
+      offset: 0,
+    };
+    let expr = parser.expr()?;
+    parser.skip_whitespace();
+    if parser.offset < parser.text.len() {
+      return Err(parser.error("Unexpected characters after the expression"));
+    }
+    Ok(Self { glob, expr })
+  }
+}
+
+/// A value computed by an expression.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+  /// A number.
+  Number(f64),
+  /// A string.
+  String(String),
+  /// `true` or `false`.
+  Bool(bool),
+  /// `null`.
+  Null,
+}
+
+impl Value {
+  /// Describes the type of the value, for error messages.
+  fn type_name(&self) -> &'static str {
+    match self {
+      Value::Number(_) => "a number",
+      Value::String(_) => "a string",
+      Value::Bool(_) => "a boolean",
+      Value::Null => "null",
+    }
+  }
+
+  /// Returns the text of the value as it appears in a string concatenation.
+  fn to_text(&self) -> String {
+    match self {
+      Value::Number(number) => format_number(*number),
+      Value::String(string) => string.clone(),
+      Value::Bool(value) => value.to_string(),
+      Value::Null => "null".to_string(),
+    }
+  }
+
+  /// Returns the JSON5 source text of the value, quoting strings with `quote`.
+  fn to_source(&self, quote: char) -> String {
+    match self {
+      Value::String(string) => syntax::quote(string, quote),
+      value => value.to_text(),
+    }
+  }
+}
+
+/// A parsed expression.
+#[derive(Clone, Debug)]
+enum Expr {
+  /// A constant value.
+  Constant(Value),
+  /// `.`, the value being replaced.
+  Current,
+  /// A negated number.
+  Negate(Box<Expr>),
+  /// A binary operation, such as `. * 2`.
+  Binary(char, Box<Expr>, Box<Expr>),
+  /// A call of a built-in function.
+  Call(String, Vec<Expr>),
+}
+
+impl Expr {
+  /// Evaluates the expression, where `current` is the value being replaced.
+  fn eval(&self, current: &Value) -> Result<Value, String> {
+    match self {
+      Expr::Constant(value) => Ok(value.clone()),
+      Expr::Current => Ok(current.clone()),
+      Expr::Negate(operand) => match operand.eval(current)? {
+        Value::Number(number) => Ok(Value::Number(-number)),
+        value => Err(format!("Cannot negate {}", value.type_name())),
+      },
+      Expr::Binary(op, left, right) => match (*op, left.eval(current)?, right.eval(current)?) {
+        ('+', left @ Value::String(_), right) | ('+', left, right @ Value::String(_)) => {
+          Ok(Value::String(left.to_text() + &right.to_text()))
+        }
+        (op, Value::Number(left), Value::Number(right)) => Ok(Value::Number(match op {
+          '+' => left + right,
+          '-' => left - right,
+          '*' => left * right,
+          '/' => left / right,
+          _ => left % right,
+        })),
+        (op, left, right) => Err(format!(
+          "Cannot apply '{}' to {} and {}",
+          op,
+          left.type_name(),
+          right.type_name()
+        )),
+      },
+      Expr::Call(name, args) => {
+        let args = args
+          .iter()
+          .map(|arg| arg.eval(current))
+          .collect::<Result<Vec<_>, _>>()?;
+        call(name, &args)
+      }
+    }
+  }
+}
+
+/// The built-in functions, and the number of arguments each takes.
+const FUNCTIONS: &[(&str, usize)] = &[
+  ("upper", 1),
+  ("lower", 1),
+  ("string", 1),
+  ("number", 1),
+  ("round", 1),
+  ("bump_major", 1),
+  ("bump_minor", 1),
+  ("bump_patch", 1),
+];
+
+/// Calls the built-in function `name` (which takes the number of arguments given in
+/// [FUNCTIONS](constant.FUNCTIONS.html)) with `args`.
+fn call(name: &str, args: &[Value]) -> Result<Value, String> {
+  match (name, &args[0]) {
+    ("upper", Value::String(string)) => Ok(Value::String(string.to_uppercase())),
+    ("lower", Value::String(string)) => Ok(Value::String(string.to_lowercase())),
+    ("string", value) => Ok(Value::String(value.to_text())),
+    ("number", Value::String(string)) => parse_number(string)
+      .map(Value::Number)
+      .ok_or_else(|| format!("Cannot convert \"{}\" to a number", string)),
+    ("number", Value::Number(number)) => Ok(Value::Number(*number)),
+    ("round", Value::Number(number)) => Ok(Value::Number(number.round())),
+    ("bump_major" | "bump_minor" | "bump_patch", Value::String(version)) => {
+      bump_version(name, version).map(Value::String)
+    }
+    (name, value) => Err(format!(
+      "Function '{}' does not accept {}",
+      name,
+      value.type_name()
+    )),
+  }
+}
+
+/// Increments the part of the `major.minor.patch` `version` selected by the function `name`,
+/// resetting the parts after it to 0. A leading `v` and any suffix after the patch number (such as
+/// `-beta`) are retained.
+fn bump_version(name: &str, version: &str) -> Result<String, String> {
+  let invalid = || format!("\"{}\" is not a major.minor.patch version", version);
+  let (prefix, rest) = match version.strip_prefix('v') {
+    Some(rest) => ("v", rest),
+    None => ("", version),
+  };
+  let end = rest
+    .find(|c: char| !c.is_ascii_digit() && c != '.')
+    .unwrap_or(rest.len());
+  let (numbers, suffix) = rest.split_at(end);
+  let mut parts = numbers
+    .split('.')
+    .map(|part| part.parse::<u64>().map_err(|_| invalid()))
+    .collect::<Result<Vec<_>, _>>()?;
+  if parts.len() != 3 {
+    return Err(invalid());
+  }
+  let index = match name {
+    "bump_major" => 0,
+    "bump_minor" => 1,
+    _ => 2,
+  };
+  parts[index] += 1;
+  for part in &mut parts[index + 1..] {
+    *part = 0;
+  }
+  Ok(format!(
+    "{}{}.{}.{}{}",
+    prefix, parts[0], parts[1], parts[2], suffix
+  ))
+}
+
+/// Parses a JSON5 number, including hexadecimal numbers, `Infinity`, and `NaN`.
+fn parse_number(text: &str) -> Option<f64> {
+  let (negative, digits) = match text.strip_prefix('-') {
+    Some(digits) => (true, digits),
+    None => (false, text.strip_prefix('+').unwrap_or(text)),
+  };
+  let magnitude = if let Some(hex) = digits
+    .strip_prefix("0x")
+    .or_else(|| digits.strip_prefix("0X"))
+  {
+    u64::from_str_radix(hex, 16).ok()? as f64
+  } else {
+    match digits {
+      "Infinity" => f64::INFINITY,
+      "NaN" => f64::NAN,
+      digits if digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') => {
+        digits.parse().ok()?
+      }
+      _ => return None,
+    }
+  };
+  Some(if negative { -magnitude } else { magnitude })
+}
+
+/// Returns an error if the JSON5 number `text` is an integer that cannot be represented exactly
+/// as a 64-bit floating-point number.
+fn exact(text: &str) -> Result<(), String> {
+  let digits = text.trim_start_matches(['-', '+']);
+  let magnitude = match digits
+    .strip_prefix("0x")
+    .or_else(|| digits.strip_prefix("0X"))
+  {
+    Some(hex) => u128::from_str_radix(hex, 16).ok(),
+    None if digits.bytes().all(|byte| byte.is_ascii_digit()) => digits.parse::<u128>().ok(),
+    None => return Ok(()),
+  };
+  match magnitude {
+    Some(magnitude) if magnitude <= 1 << 53 => Ok(()),
+    _ => Err(format!(
+      "The integer {} cannot be represented exactly as a number",
+      text
+    )),
+  }
+}
+
+/// Formats a number as JSON5 source, without a fraction if it is a whole number.
+fn format_number(number: f64) -> String {
+  if number.is_nan() {
+    "NaN".to_string()
+  } else if number.is_infinite() {
+    if number > 0.0 {
+      "Infinity"
+    } else {
+      "-Infinity"
+    }
+    .to_string()
+  } else if number.fract() == 0.0 && number.abs() < 1e15 {
+    format!("{}", number as i64)
+  } else {
+    format!("{}", number)
+  }
+}
+
+/// Parses the text of an expression.
+struct ExprParser<'a> {
+  /// The expression text.
+  text: &'a str,
+  /// The byte offset of the next unparsed character.
+  offset: usize,
+}
+
+impl<'a> ExprParser<'a> {
+  /// Returns an error at the current position.
+  fn error(&self, message: &str) -> anyhow::Error {
+    anyhow::anyhow!("{} at '{}'", message, self.text[self.offset..].trim_end())
+  }
+
+  /// Skips any whitespace at the current position.
+  fn skip_whitespace(&mut self) {
+    let rest = &self.text[self.offset..];
+    self.offset += rest.len() - rest.trim_start().len();
+  }
+
+  /// Skips whitespace, and returns the next character without consuming it.
+  fn peek(&mut self) -> Option<char> {
+    self.skip_whitespace();
+    self.text[self.offset..].chars().next()
+  }
+
+  /// Consumes the next character if it is `c`.
+  fn eat(&mut self, c: char) -> bool {
+    if self.peek() == Some(c) {
+      self.offset += c.len_utf8();
+      true
+    } else {
+      false
+    }
+  }
+
+  /// Parses a sum or difference of terms.
+  fn expr(&mut self) -> Result<Expr, anyhow::Error> {
+    let mut expr = self.term()?;
+    while let Some(op @ ('+' | '-')) = self.peek() {
+      self.offset += 1;
+      expr = Expr::Binary(op, Box::new(expr), Box::new(self.term()?));
+    }
+    Ok(expr)
+  }
+
+  /// Parses a product, quotient, or remainder of unary expressions.
+  fn term(&mut self) -> Result<Expr, anyhow::Error> {
+    let mut expr = self.unary()?;
+    while let Some(op @ ('*' | '/' | '%')) = self.peek() {
+      self.offset += 1;
+      expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+    }
+    Ok(expr)
+  }
+
+  /// Parses a primary expression, optionally negated.
+  fn unary(&mut self) -> Result<Expr, anyhow::Error> {
+    if self.eat('-') {
+      return Ok(Expr::Negate(Box::new(self.unary()?)));
+    }
+    self.primary()
+  }
+
+  /// Parses a constant, `.`, a function call, or a parenthesized expression.
+  fn primary(&mut self) -> Result<Expr, anyhow::Error> {
+    let rest = match self.peek() {
+      Some(_) => &self.text[self.offset..],
+      None => return Err(self.error("Expected an expression")),
+    };
+    if self.eat('(') {
+      let expr = self.expr()?;
+      if !self.eat(')') {
+        return Err(self.error("Expected ')'"));
+      }
+      return Ok(expr);
+    }
+    if let Some(quote @ ('"' | '\'')) = rest.chars().next() {
+      let end = string_end(rest, quote).ok_or_else(|| self.error("Unclosed string"))?;
+      let value = syntax::unquote(&rest[..end]).map_err(|message| self.error(&message))?;
+      self.offset += end;
+      return Ok(Expr::Constant(Value::String(value)));
+    }
+    let len = rest
+      .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+      .unwrap_or(rest.len());
+    let word = &rest[..len];
+    if word == "." {
+      self.offset += 1;
+      return Ok(Expr::Current);
+    }
+    if let Some(number) = parse_number(word) {
+      exact(word).map_err(|message| self.error(&message))?;
+      self.offset += len;
+      return Ok(Expr::Constant(Value::Number(number)));
+    }
+    let constant = match word {
+      "true" => Some(Value::Bool(true)),
+      "false" => Some(Value::Bool(false)),
+      "null" => Some(Value::Null),
+      _ => None,
+    };
+    if let Some(constant) = constant {
+      self.offset += len;
+      return Ok(Expr::Constant(constant));
+    }
+    let Some(&(name, arity)) = FUNCTIONS.iter().find(|(name, _)| *name == word) else {
+      return Err(match word {
+        "" => self.error("Expected an expression"),
+        word => self.error(&format!("Unknown function or constant '{}'", word)),
+      });
+    };
+    self.offset += len;
+    if !self.eat('(') {
+      return Err(self.error("Expected '(' after the function name"));
+    }
+    let mut args = vec![self.expr()?];
+    while self.eat(',') {
+      args.push(self.expr()?);
+    }
+    if !self.eat(')') {
+      return Err(self.error("Expected ')'"));
+    }
+    if args.len() != arity {
+      return Err(anyhow::anyhow!(
+        "Function '{}' takes {} argument(s), but was given {}",
+        name,
+        arity,
+        args.len()
+      ));
+    }
+    Ok(Expr::Call(name.to_string(), args))
+  }
+}
+
+/// Returns the byte length of the string literal at the start of `text`, which starts with
+/// `quote`, or `None` if it is unclosed.
+fn string_end(text: &str, quote: char) -> Option<usize> {
+  let mut escaped = false;
+  for (index, c) in text.char_indices().skip(1) {
+    match c {
+      _ if escaped => escaped = false,
+      '\\' => escaped = true,
+      c if c == quote => return Some(index + 1),
+      _ => {}
+    }
+  }
+  None
+}
+
+/// Adds an edit to `edits` for each value in the document `root`, read from `source`, that is
+/// replaced by one of the `transforms`. If several transforms match a value, the first is used.
+/// Returns a description of the first expression that fails to evaluate, with its line, column,
+/// and source context.
+pub fn transform(
+  root: &Node,
+  source: &str,
+  transforms: &[Transform],
+  edits: &mut Edits,
+) -> Result<(), String> {
+  visit(root, source, &mut Vec::new(), transforms, edits)
+}
+
+/// Transforms `node`, at `path`, and its descendants. Fails if `node` is nested more deeply than
+/// the parser allows, since the descendants are visited recursively.
+fn visit(
+  node: &Node,
+  source: &str,
+  path: &mut Vec<String>,
+  transforms: &[Transform],
+  edits: &mut Edits,
+) -> Result<(), String> {
+  if path.len() > NESTING_LIMIT {
+    let span = node.span();
+    return Err(format!(
+      "{}:{}: The value is nested more than {} levels deep",
+      span.line, span.col, NESTING_LIMIT
+    ));
+  }
+  let current = match node {
+    Node::Object { members, .. } => {
+      for member in members {
+        path.push(member.name.clone());
+        visit(&member.value, source, path, transforms, edits)?;
+        path.pop();
+      }
+      return Ok(());
+    }
+    Node::Array { items, .. } => {
+      for (index, item) in items.iter().enumerate() {
+        path.push(index.to_string());
+        visit(item, source, path, transforms, edits)?;
+        path.pop();
+      }
+      return Ok(());
+    }
+    Node::String { value, .. } => Ok(Value::String(value.clone())),
+    Node::Literal { span } => match &source[span.start..span.end] {
+      "true" => Ok(Value::Bool(true)),
+      "false" => Ok(Value::Bool(false)),
+      "null" => Ok(Value::Null),
+      text => exact(text).map(|()| Value::Number(parse_number(text).unwrap_or(f64::NAN))),
+    },
+  };
+  let Some(transform) = transforms
+    .iter()
+    .find(|transform| transform.glob.matches(path))
+  else {
+    return Ok(());
+  };
+  let span = node.span();
+  // A result of 2^53 may itself be a rounded 2^53 + 1.
+  let evaluated = current.and_then(|current| match transform.expr.eval(&current)? {
+    Value::Number(number) if number.fract() == 0.0 && number.abs() >= (1u64 << 53) as f64 => {
+      Err(format!(
+        "The result {} cannot be represented exactly as a number",
+        format_number(number)
+      ))
+    }
+    replacement => Ok((replacement, current)),
+  });
+  let (replacement, current) = evaluated.map_err(|message| {
+    format!(
+      "{}:{}: {} (in the transform for {}):\n{}",
+      span.line,
+      span.col,
+      message,
+      transform.glob,
+      error_context(source, span.line, span.col)
+    )
+  })?;
+  if replacement != current {
+    let text = &source[span.start..span.end];
+    let quote = if text.starts_with('\'') { '\'' } else { '"' };
+    edits.replace(span.start..span.end, replacement.to_source(quote));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the document `source` with the `transforms` applied, or the error.
+  fn transformed(source: &str, transforms: &[&str]) -> Result<String, String> {
+    let transforms = transforms
+      .iter()
+      .map(|transform| transform.parse().unwrap())
+      .collect::<Vec<Transform>>();
+    let root = syntax::parse(source).unwrap().unwrap();
+    let mut edits = Edits::default();
+    transform(&root, source, &transforms, &mut edits)?;
+    Ok(edits.apply(source))
+  }
+
+  #[test]
+  fn replaces_matching_values() {
+    assert_eq!(
+      transformed(
+        "{a: {timeout: 30}, version: '1.2.3'}",
+        &["**/timeout = . * 2", "/version = bump_minor(.)"]
+      ),
+      Ok("{a: {timeout: 60}, version: '1.3.0'}".to_string())
+    );
+  }
+
+  #[test]
+  fn rejects_an_empty_glob() {
+    let err = "= 1".parse::<Transform>().unwrap_err();
+    assert_eq!(err.to_string(), "Expected a path glob before '='");
+    assert!(" \t= 1".parse::<Transform>().is_err());
+  }
+
+  #[test]
+  fn rejects_integers_that_cannot_be_represented_exactly() {
+    let source = "{a: 9007199254740993, b: 9007199254740992, c: 0x20000000000001}";
+    assert!(transformed(source, &["/a = . + 1"])
+      .unwrap_err()
+      .contains("The integer 9007199254740993 cannot be represented exactly"));
+    assert!(transformed(source, &["/b = . + 1"])
+      .unwrap_err()
+      .contains("The result 9007199254740992 cannot be represented exactly"));
+    assert!(transformed(source, &["/c = . - 1"])
+      .unwrap_err()
+      .contains("The integer 0x20000000000001 cannot be represented exactly"));
+    assert_eq!(
+      transformed(source, &["/b = . - 1"]),
+      Ok("{a: 9007199254740993, b: 9007199254740991, c: 0x20000000000001}".to_string())
+    );
+    assert!("/a = 9007199254740993".parse::<Transform>().is_err());
+  }
+
+  #[test]
+  fn leaves_values_that_do_not_match_unchanged() {
+    let source = "{a: 9007199254740993, b: [1, 2]}";
+    assert_eq!(
+      transformed(source, &["/b/* = . + 1"]),
+      Ok("{a: 9007199254740993, b: [2, 3]}".to_string())
+    );
+  }
+}
@@ -0,0 +1,195 @@
+//! Checks the formatter's version against the `required_version` option, so a project can make
+//! sure that everyone (and CI) formats its files with a version that produces the same output.
+//!
+//! A requirement is a comma-separated list of comparisons, all of which must hold, such as
+//! `>=0.3, <0.5`. Each comparison is an operator (`>=`, `>`, `<=`, `<`, `=`, `^`, or `~`) and a
+//! version of one to three numbers. As in Cargo, a version without an operator means `^`: `^0.3`
+//! allows any version from 0.3.0 up to (but not including) 0.4.0, and `^1.2` allows any 1.x
+//! version from 1.2.0. `~1.2` allows any 1.2.x version, and missing numbers in other comparisons
+//! are zero (so `<0.5` means `<0.5.0`). Pre-release and build suffixes are not supported.
+
+use std::{fmt, str::FromStr};
+
+/// The version of this formatter.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A `major.minor.patch` version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(u64, u64, u64);
+
+impl Version {
+  /// Returns the version of this formatter.
+  pub fn current() -> Version {
+    VERSION.parse().expect("the package version is valid")
+  }
+}
+
+impl fmt::Display for Version {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}.{}.{}", self.0, self.1, self.2)
+  }
+}
+
+impl FromStr for Version {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (version, _) = parse_partial(s)?;
+    Ok(version)
+  }
+}
+
+/// Parses a version of one to three numbers, and returns it (with the missing numbers as zero) and
+/// the number of numbers given.
+fn parse_partial(text: &str) -> Result<(Version, usize), String> {
+  let parts = text
+    .split('.')
+    .map(|part| part.parse::<u64>().ok())
+    .collect::<Option<Vec<_>>>()
+    .filter(|parts| (1..=3).contains(&parts.len()))
+    .ok_or_else(|| {
+      format!(
+        "Expected a version such as `0.3` or `1.2.0`, but found `{}`",
+        text
+      )
+    })?;
+  let number = |index: usize| parts.get(index).copied().unwrap_or(0);
+  Ok((Version(number(0), number(1), number(2)), parts.len()))
+}
+
+/// An operator of a comparison in a requirement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+  /// `>=`
+  AtLeast,
+  /// `>`
+  Greater,
+  /// `<=`
+  AtMost,
+  /// `<`
+  Less,
+  /// `=`
+  Exact,
+  /// `^`, or no operator: compatible versions, which have the same leftmost nonzero number.
+  Caret,
+  /// `~`: versions with the same major and minor numbers (or major number, if only it is given).
+  Tilde,
+}
+
+/// A comparison of a requirement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Comparison {
+  /// The operator.
+  operator: Operator,
+  /// The version compared with, with missing numbers as zero.
+  version: Version,
+  /// The number of numbers given in the version.
+  given: usize,
+}
+
+impl Comparison {
+  /// Returns true if `version` satisfies the comparison.
+  fn matches(&self, version: Version) -> bool {
+    let Version(major, minor, _) = self.version;
+    match self.operator {
+      Operator::AtLeast => version >= self.version,
+      Operator::Greater => version > self.version,
+      Operator::AtMost => version <= self.version,
+      Operator::Less => version < self.version,
+      Operator::Exact => match self.given {
+        1 => version.0 == major,
+        2 => (version.0, version.1) == (major, minor),
+        _ => version == self.version,
+      },
+      Operator::Tilde => {
+        version >= self.version
+          && match self.given {
+            1 => version.0 == major,
+            _ => (version.0, version.1) == (major, minor),
+          }
+      }
+      Operator::Caret => {
+        version >= self.version
+          && match (major, minor, self.given) {
+            (0, 0, 3) => version == self.version,
+            (0, _, 1) => version.0 == 0,
+            (0, _, _) => (version.0, version.1) == (0, minor),
+            _ => version.0 == major,
+          }
+      }
+    }
+  }
+}
+
+/// A version requirement, such as `>=0.3, <0.5`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Requirement {
+  /// The requirement, as written.
+  text: String,
+  /// The comparisons, all of which must hold.
+  comparisons: Vec<Comparison>,
+}
+
+impl Requirement {
+  /// Returns true if `version` satisfies the requirement.
+  pub fn matches(&self, version: Version) -> bool {
+    self
+      .comparisons
+      .iter()
+      .all(|comparison| comparison.matches(version))
+  }
+}
+
+impl fmt::Display for Requirement {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.text)
+  }
+}
+
+impl FromStr for Requirement {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let comparisons = s
+      .split(',')
+      .map(|comparison| {
+        let comparison = comparison.trim();
+        let operators = [
+          (">=", Operator::AtLeast),
+          ("<=", Operator::AtMost),
+          (">", Operator::Greater),
+          ("<", Operator::Less),
+          ("=", Operator::Exact),
+          ("^", Operator::Caret),
+          ("~", Operator::Tilde),
+        ];
+        let (operator, version) = operators
+          .iter()
+          .find_map(|(prefix, operator)| Some((*operator, comparison.strip_prefix(prefix)?)))
+          .unwrap_or((Operator::Caret, comparison));
+        let (version, given) = parse_partial(version.trim())?;
+        Ok(Comparison {
+          operator,
+          version,
+          given,
+        })
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+    Ok(Requirement {
+      text: s.to_string(),
+      comparisons,
+    })
+  }
+}
+
+/// Returns an error message if this formatter's version does not satisfy `requirement`.
+pub fn check(requirement: &Requirement) -> Result<(), String> {
+  let current = Version::current();
+  match requirement.matches(current) {
+    true => Ok(()),
+    false => Err(format!(
+      "This is version {} of the formatter, but the configuration requires `{}`",
+      current, requirement
+    )),
+  }
+}
@@ -0,0 +1,342 @@
+//! Runs as a persistent worker of Bazel (or Buck), with the JSON worker protocol (the
+//! `--persistent_worker` flag), so a build that formats or checks thousands of files as actions
+//! starts the formatter once rather than once per action:
+//!
+//!     ctx.actions.run(
+//!         executable = ctx.executable._formatjson5,
+//!         arguments = ["check", "@" + args_file.path],
+//!         execution_requirements = {
+//!             "supports-workers": "1",
+//!             "requires-worker-protocol": "json",
+//!         },
+//!         ...
+//!     )
+//!
+//! Each request is a line of JSON, such as `{"arguments": ["check", "a.json5"], "requestId": 1}`,
+//! whose `arguments` are a command line after the program name, following the arguments the
+//! worker was started with (other than `--persistent_worker`). An argument `@<file>` is replaced
+//! by the lines of the file, one argument per line. The worker runs the command and responds with a
+//! line of JSON, such as `{"exitCode": 1, "output": "...", "requestId": 1}`, whose `output` holds
+//! everything the command wrote to stdout and stderr. Requests are run one at a time, in order, and
+//! commands read no input (stdin is empty), since stdin carries the requests. A request to cancel
+//! another request is ignored, since each request has been answered before the next one is read.
+
+use crate::syntax::{self, Node};
+use std::{
+  ffi::OsString,
+  fs,
+  io::{BufRead, Write},
+};
+
+/// The flag that Bazel adds to the command line of a persistent worker.
+pub const FLAG: &str = "--persistent_worker";
+
+/// A work request.
+#[derive(Debug)]
+struct Request {
+  /// The identifier of the request, which its response repeats (0 for a worker that runs one
+  /// request at a time).
+  id: usize,
+  /// The command line of the request, after the program name.
+  arguments: Vec<String>,
+  /// Whether the request cancels an earlier request, instead of running a command.
+  cancel: bool,
+}
+
+/// Returns the arguments the worker was started with, without the program name and
+/// [FLAG](constant.FLAG.html), if the command line holds [FLAG](constant.FLAG.html).
+pub fn startup_arguments() -> Option<Vec<OsString>> {
+  let arguments = std::env::args_os().skip(1).collect::<Vec<_>>();
+  arguments.iter().any(|argument| argument == FLAG).then(|| {
+    arguments
+      .into_iter()
+      .filter(|argument| argument != FLAG)
+      .collect()
+  })
+}
+
+/// Serves work requests from stdin until it is closed, running each by calling `execute` with the
+/// `startup` arguments followed by the arguments of the request.
+pub fn run(
+  startup: Vec<OsString>,
+  mut execute: impl FnMut(Vec<OsString>) -> Result<(), anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+  let mut streams = Streams::open()?;
+  let mut line = String::new();
+  loop {
+    line.clear();
+    if streams.requests.read_line(&mut line)? == 0 {
+      return Ok(());
+    }
+    if line.trim().is_empty() {
+      continue;
+    }
+    let request = match parse_request(&line) {
+      Ok(request) => request,
+      Err(err) => {
+        writeln!(streams.log, "Worker error: {}", err)?;
+        continue;
+      }
+    };
+    if request.cancel {
+      continue;
+    }
+    let (exit_code, output) = match expand(&request.arguments) {
+      Ok(arguments) => {
+        let mut command_line = startup.clone();
+        command_line.extend(arguments.into_iter().map(OsString::from));
+        streams.capture(command_line, &mut execute)?
+      }
+      Err(err) => (1, format!("Error: {}\n", err)),
+    };
+    writeln!(
+      streams.responses,
+      "{{\"exitCode\": {}, \"output\": {}, \"requestId\": {}}}",
+      exit_code,
+      syntax::quote(&output, '"'),
+      request.id
+    )?;
+    streams.responses.flush()?;
+  }
+}
+
+/// Parses the work request in `line`.
+fn parse_request(line: &str) -> Result<Request, anyhow::Error> {
+  let invalid =
+    |message: &str| anyhow::anyhow!("Invalid work request: {}: {}", message, line.trim());
+  let Some(Node::Object { members, .. }) =
+    syntax::parse(line).map_err(|err| invalid(&err.message))?
+  else {
+    return Err(invalid("expected an object"));
+  };
+  let mut request = Request {
+    id: 0,
+    arguments: Vec::new(),
+    cancel: false,
+  };
+  for member in &members {
+    let text = |node: &Node| {
+      let span = node.span();
+      line[span.start..span.end].to_string()
+    };
+    match (member.name.as_str(), &member.value) {
+      ("requestId", value @ Node::Literal { .. }) => {
+        request.id = text(value)
+          .parse()
+          .map_err(|_| invalid("`requestId` is not a request identifier"))?;
+      }
+      ("arguments", Node::Array { items, .. }) => {
+        for item in items {
+          match item {
+            Node::String { value, .. } => request.arguments.push(value.clone()),
+            _ => return Err(invalid("`arguments` holds a value that is not a string")),
+          }
+        }
+      }
+      ("cancel", value @ Node::Literal { .. }) => request.cancel = text(value) == "true",
+      // Inputs and their digests, the sandbox directory, and the verbosity are not used.
+      _ => {}
+    }
+  }
+  Ok(request)
+}
+
+/// Returns the `arguments` with each `@<file>` argument replaced by the lines of the file.
+fn expand(arguments: &[String]) -> Result<Vec<String>, anyhow::Error> {
+  let mut expanded = Vec::new();
+  for argument in arguments {
+    match argument.strip_prefix('@') {
+      Some(path) => {
+        let text = fs::read_to_string(path)
+          .map_err(|err| anyhow::anyhow!("Cannot read the arguments file {}: {}", path, err))?;
+        expanded.extend(text.lines().map(str::to_string));
+      }
+      None => expanded.push(argument.clone()),
+    }
+  }
+  Ok(expanded)
+}
+
+/// The streams of the worker: the requests and responses of the protocol, the log (the original
+/// stderr), and the file that collects the output of a request.
+#[cfg(unix)]
+struct Streams {
+  /// The requests, read from the original stdin.
+  requests: std::io::BufReader<fs::File>,
+  /// The responses, written to the original stdout.
+  responses: fs::File,
+  /// The worker's own messages, written to the original stderr.
+  log: fs::File,
+  /// The path of the file that stdout and stderr are redirected to, in a directory of its own
+  /// that only the current user can access.
+  output_path: std::path::PathBuf,
+  /// The file that stdout and stderr are redirected to.
+  output: fs::File,
+}
+
+#[cfg(unix)]
+impl Streams {
+  /// Sets the original stdin, stdout, and stderr aside for the protocol, and redirects stdin to
+  /// an empty file, and stdout and stderr to the output file.
+  fn open() -> Result<Self, anyhow::Error> {
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    let duplicate = |fd| match unsafe { libc::dup(fd) } {
+      -1 => Err(std::io::Error::last_os_error()),
+      // The duplicate is a new descriptor, owned by the file.
+      copy => Ok(unsafe { fs::File::from_raw_fd(copy) }),
+    };
+    let redirect = |file: &fs::File, fd| match unsafe { libc::dup2(file.as_raw_fd(), fd) } {
+      -1 => Err(std::io::Error::last_os_error()),
+      _ => Ok(()),
+    };
+    let requests = std::io::BufReader::new(duplicate(0)?);
+    let responses = duplicate(1)?;
+    let log = duplicate(2)?;
+    let output_path = crate::write_mode::private_directory("formatjson5-worker")?.join("output");
+    let output = fs::OpenOptions::new()
+      .create_new(true)
+      .read(true)
+      .append(true)
+      .open(&output_path)?;
+    redirect(&fs::File::open("/dev/null")?, 0)?;
+    redirect(&output, 1)?;
+    redirect(&output, 2)?;
+    Ok(Self {
+      requests,
+      responses,
+      log,
+      output_path,
+      output,
+    })
+  }
+
+  /// Runs `execute` with the `command_line`, and returns the exit code and everything it wrote
+  /// to stdout and stderr.
+  fn capture(
+    &mut self,
+    command_line: Vec<OsString>,
+    execute: &mut impl FnMut(Vec<OsString>) -> Result<(), anyhow::Error>,
+  ) -> Result<(i32, String), anyhow::Error> {
+    self.output.set_len(0)?;
+    let exit_code = match execute(command_line) {
+      Ok(()) => 0,
+      Err(err) => {
+        // As `main` reports errors.
+        eprintln!("Error: {:?}", err);
+        1
+      }
+    };
+    std::io::stdout().flush()?;
+    let output = fs::read(&self.output_path)?;
+    Ok((exit_code, String::from_utf8_lossy(&output).into_owned()))
+  }
+}
+
+#[cfg(unix)]
+impl Drop for Streams {
+  fn drop(&mut self) {
+    let _ = fs::remove_file(&self.output_path);
+    if let Some(directory) = self.output_path.parent() {
+      let _ = fs::remove_dir(directory);
+    }
+  }
+}
+
+/// The streams of the worker: the requests and responses of the protocol, and the log. Without
+/// a way to redirect the standard streams, each request runs as a process of its own.
+#[cfg(not(unix))]
+struct Streams {
+  /// The requests, read from stdin.
+  requests: std::io::StdinLock<'static>,
+  /// The responses, written to stdout.
+  responses: std::io::Stdout,
+  /// The worker's own messages, written to stderr.
+  log: std::io::Stderr,
+}
+
+#[cfg(not(unix))]
+impl Streams {
+  /// Opens the standard streams.
+  fn open() -> Result<Self, anyhow::Error> {
+    Ok(Self {
+      requests: std::io::stdin().lock(),
+      responses: std::io::stdout(),
+      log: std::io::stderr(),
+    })
+  }
+
+  /// Runs the formatter with the `command_line` in a process of its own, instead of calling
+  /// `execute`, and returns its exit code and everything it wrote to stdout and stderr.
+  fn capture(
+    &mut self,
+    command_line: Vec<OsString>,
+    _execute: &mut impl FnMut(Vec<OsString>) -> Result<(), anyhow::Error>,
+  ) -> Result<(i32, String), anyhow::Error> {
+    let output = std::process::Command::new(std::env::current_exe()?)
+      .args(command_line)
+      .stdin(std::process::Stdio::null())
+      .output()?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((output.status.code().unwrap_or(1), text))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_work_requests() {
+    let line = r#"{"arguments": ["check", "a \"b\".json5"], "inputs": [{"path": "a.json5"}], "#;
+    let request = parse_request(&format!("{}\"requestId\": 12}}", line)).unwrap();
+    assert_eq!(request.id, 12);
+    assert_eq!(request.arguments, ["check", "a \"b\".json5"]);
+    assert!(!request.cancel);
+    let request = parse_request(r#"{"requestId": 3, "cancel": true}"#).unwrap();
+    assert_eq!((request.id, request.cancel), (3, true));
+    let request = parse_request(r#"{"arguments": []}"#).unwrap();
+    assert_eq!(request.id, 0);
+    assert!(request.arguments.is_empty());
+  }
+
+  #[test]
+  fn rejects_invalid_work_requests() {
+    for (line, message) in [
+      ("[1]", "expected an object: [1]"),
+      (
+        r#"{"requestId": -1}"#,
+        r#"`requestId` is not a request identifier: {"requestId": -1}"#,
+      ),
+      (
+        r#"{"arguments": ["check", 1]}"#,
+        r#"`arguments` holds a value that is not a string: {"arguments": ["check", 1]}"#,
+      ),
+    ] {
+      assert_eq!(
+        parse_request(line).unwrap_err().to_string(),
+        format!("Invalid work request: {}", message)
+      );
+    }
+    assert!(parse_request("{\"arguments\": [").is_err());
+  }
+
+  #[test]
+  fn expands_arguments_files() {
+    let dir = crate::write_mode::private_directory("jsonpretty5er-worker-test").unwrap();
+    let path = dir.join("arguments");
+    fs::write(&path, "--indent\n2\na b.json5\n").unwrap();
+    let argument = format!("@{}", path.display());
+    assert_eq!(
+      expand(&["check".to_string(), argument, "c.json5".to_string()]).unwrap(),
+      ["check", "--indent", "2", "a b.json5", "c.json5"]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+    let missing = format!("@{}", path.display());
+    assert!(expand(&[missing])
+      .unwrap_err()
+      .to_string()
+      .starts_with("Cannot read the arguments file "));
+  }
+}
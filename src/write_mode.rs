@@ -0,0 +1,140 @@
+//! Chooses how `--replace` writes the files it replaces (the `write_mode` option, and
+//! `--write-mode`), since sandboxed and network filesystems differ in what they allow:
+//!
+//! - `direct` (the default) truncates each file and writes its formatted document in place, so
+//!   the file keeps its inode, permissions, and links, but a reader (or a crash) can see it
+//!   partially written.
+//! - `atomic` writes the formatted document to a temporary file next to the original, with the
+//!   original's permissions, and renames it over the original, so readers see either the old or
+//!   the new document. The file gets a new inode, so hard links to it keep the old document.
+//! - `copy` writes the formatted document to a temporary file next to the original, then copies it
+//!   over the original and removes it, for filesystems that forbid renaming over a file. The file
+//!   keeps its inode, permissions, and links.
+
+use crate::paths;
+use std::{
+  fs, io,
+  path::{Path, PathBuf},
+  str::FromStr,
+};
+
+/// How a replaced file is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WriteMode {
+  /// Write a temporary file, and rename it over the file.
+  Atomic,
+  /// Truncate the file, and write it in place.
+  #[default]
+  Direct,
+  /// Write a temporary file, copy it over the file, and remove it.
+  Copy,
+}
+
+impl WriteMode {
+  /// The accepted spellings of each variant, for command line help.
+  pub const VARIANTS: &'static [&'static str] = &["atomic", "direct", "copy"];
+
+  /// Returns the spelling of the mode, as in the configuration file.
+  pub fn name(self) -> &'static str {
+    match self {
+      WriteMode::Atomic => "atomic",
+      WriteMode::Direct => "direct",
+      WriteMode::Copy => "copy",
+    }
+  }
+
+  /// Replaces the contents of the file `filename` (which is created if it does not exist) with
+  /// `bytes`.
+  pub fn write(self, filename: &str, bytes: &[u8]) -> Result<(), io::Error> {
+    let path = paths::extended(Path::new(filename));
+    if self == WriteMode::Direct {
+      return fs::write(&path, bytes);
+    }
+    let temporary = temporary_path(&path);
+    if let Err(err) = fs::write(&temporary, bytes) {
+      let _ = fs::remove_file(&temporary);
+      return Err(err);
+    }
+    self.replace_with(filename, &temporary)
+  }
+
+  /// Replaces the contents of the file `filename` with those of `temporary`, a file next to it
+  /// (see [temporary_path()](fn.temporary_path.html)), which is renamed over it or copied into it
+  /// and removed. Modes that write in place copy the contents, as `copy` does.
+  pub fn replace_with(self, filename: &str, temporary: &Path) -> Result<(), io::Error> {
+    let path = paths::extended(Path::new(filename));
+    let result = match self {
+      WriteMode::Atomic => (|| {
+        if let Ok(metadata) = fs::metadata(&path) {
+          fs::set_permissions(temporary, metadata.permissions())?;
+        }
+        fs::rename(temporary, &path)
+      })(),
+      // `fs::copy` would also copy the temporary file's permissions over the original's.
+      WriteMode::Direct | WriteMode::Copy => (|| {
+        io::copy(
+          &mut fs::File::open(temporary)?,
+          &mut fs::File::create(&path)?,
+        )?;
+        fs::remove_file(temporary)
+      })(),
+    };
+    if result.is_err() {
+      let _ = fs::remove_file(temporary);
+    }
+    result
+  }
+}
+
+impl FromStr for WriteMode {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "atomic" => Ok(WriteMode::Atomic),
+      "direct" => Ok(WriteMode::Direct),
+      "copy" => Ok(WriteMode::Copy),
+      _ => Err(anyhow::anyhow!(
+        "Expected one of: {}",
+        WriteMode::VARIANTS.join(", ")
+      )),
+    }
+  }
+}
+
+/// Returns a temporary path next to `path`, for writing a file that replaces it.
+pub fn temporary_path(path: &Path) -> PathBuf {
+  let mut name = path.file_name().unwrap_or_default().to_os_string();
+  name.push(format!(".json5format-{}.tmp", std::process::id()));
+  path.with_file_name(name)
+}
+
+/// Creates a new directory in the system's temporary directory, whose name starts with `prefix`,
+/// that only the current user can access, and returns its path. The directory is created under a
+/// name that did not exist, so that another user cannot have created it (or a link in its place)
+/// beforehand.
+pub fn private_directory(prefix: &str) -> io::Result<PathBuf> {
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::time::{SystemTime, UNIX_EPOCH};
+
+  static COUNTER: AtomicUsize = AtomicUsize::new(0);
+  let mut builder = fs::DirBuilder::new();
+  #[cfg(unix)]
+  std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+  loop {
+    let nanos = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |time| time.subsec_nanos());
+    let path = std::env::temp_dir().join(format!(
+      "{}-{}-{}-{}",
+      prefix,
+      std::process::id(),
+      COUNTER.fetch_add(1, Ordering::Relaxed),
+      nanos
+    ));
+    match builder.create(&path) {
+      Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+      result => return result.map(|()| path),
+    }
+  }
+}
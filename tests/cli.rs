@@ -0,0 +1,250 @@
+//! Runs the formatter's executable on files in a temporary directory, and checks its output.
+
+use std::{fs, path::PathBuf, process::Command};
+
+/// A document that is not formatted.
+const UNFORMATTED: &str = "{a:1,b:[1,2]}\n";
+
+/// The document `UNFORMATTED`, formatted.
+const FORMATTED: &str = "{\n    a: 1,\n    b: [\n        1,\n        2,\n    ],\n}\n";
+
+/// Returns a new, empty directory for the test `name`.
+fn directory(name: &str) -> PathBuf {
+  let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+  let _ = fs::remove_dir_all(&directory);
+  fs::create_dir_all(&directory).unwrap();
+  directory
+}
+
+/// Writes `UNFORMATTED` to the file `filename` in a new directory, formats the file by giving its
+/// name as the only argument, and checks that the formatted document is printed.
+fn format_file_named(filename: &str) {
+  let directory = directory(&format!("format-{}", filename));
+  fs::write(directory.join(filename), UNFORMATTED).unwrap();
+  let output = Command::new(env!("CARGO_BIN_EXE_jsonpretty5er"))
+    .arg(filename)
+    .current_dir(&directory)
+    .output()
+    .unwrap();
+  assert!(
+    output.status.success(),
+    "{}: {}",
+    filename,
+    String::from_utf8_lossy(&output.stderr)
+  );
+  assert_eq!(String::from_utf8_lossy(&output.stdout), FORMATTED);
+}
+
+#[test]
+fn formats_file_named_like_a_subcommand() {
+  format_file_named("migrate.json5");
+}
+
+#[test]
+fn formats_files_named_like_misspelled_subcommands() {
+  for filename in [
+    "check.json5",
+    "t.json5",
+    "fix.json5",
+    "lint.json5",
+    "stats.json5",
+  ] {
+    format_file_named(filename);
+  }
+}
+
+#[test]
+fn replaces_files_atomically_only_if_all_of_them_format() {
+  let directory = directory("replace-atomic");
+  fs::write(directory.join("a.json5"), UNFORMATTED).unwrap();
+  fs::write(directory.join("b.json5"), "{b:").unwrap();
+  let replace = |files: &[&str]| {
+    Command::new(env!("CARGO_BIN_EXE_jsonpretty5er"))
+      .args(["--write-mode", "atomic", "--replace"])
+      .args(files)
+      .current_dir(&directory)
+      .output()
+      .unwrap()
+  };
+  assert!(!replace(&["a.json5", "b.json5"]).status.success());
+  assert_eq!(
+    fs::read_to_string(directory.join("a.json5")).unwrap(),
+    UNFORMATTED
+  );
+  // No temporary file is left behind.
+  assert_eq!(fs::read_dir(&directory).unwrap().count(), 2);
+  assert!(replace(&["a.json5"]).status.success());
+  assert_eq!(
+    fs::read_to_string(directory.join("a.json5")).unwrap(),
+    FORMATTED
+  );
+  assert_eq!(fs::read_dir(&directory).unwrap().count(), 2);
+}
+
+#[test]
+fn checks_transformed_values_against_the_dialect() {
+  let directory = directory("transform-dialect");
+  fs::write(directory.join("a.json"), "{\"x\": 2}\n").unwrap();
+  let transform = |options: &[&str]| {
+    Command::new(env!("CARGO_BIN_EXE_jsonpretty5er"))
+      .args(options)
+      .args(["--transform", "**/x = . / 0", "a.json"])
+      .current_dir(&directory)
+      .output()
+      .unwrap()
+  };
+  for options in [&["--dialect", "json"][..], &["--forbid-non-finite"]] {
+    let output = transform(options);
+    assert!(!output.status.success(), "{:?}", options);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Infinity"));
+    assert!(output.stdout.is_empty());
+  }
+  assert!(String::from_utf8_lossy(&transform(&[]).stdout).contains("Infinity"));
+}
+
+#[test]
+fn formats_the_files_in_directories_in_order() {
+  let directory = directory("directories");
+  for subdirectory in ["tree/b", "tree/a", "tree/.git"] {
+    fs::create_dir_all(directory.join(subdirectory)).unwrap();
+  }
+  for file in [
+    "tree/b/x.json5",
+    "tree/a.json5",
+    "tree/a/m.cml",
+    "tree/.git/h.json5",
+    "tree/notes.txt",
+  ] {
+    fs::write(directory.join(file), UNFORMATTED).unwrap();
+  }
+  let output = Command::new(env!("CARGO_BIN_EXE_jsonpretty5er"))
+    .args(["--check", "tree"])
+    .current_dir(&directory)
+    .output()
+    .unwrap();
+  assert!(!output.status.success());
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  let checked = stderr
+    .lines()
+    .filter_map(|line| line.strip_suffix(": Not formatted"))
+    .collect::<Vec<_>>();
+  assert_eq!(checked, ["tree/a/m.cml", "tree/a.json5", "tree/b/x.json5"]);
+  let output = Command::new(env!("CARGO_BIN_EXE_jsonpretty5er"))
+    .args(["--replace", "tree/b"])
+    .current_dir(&directory)
+    .output()
+    .unwrap();
+  assert!(output.status.success());
+  assert_eq!(
+    fs::read_to_string(directory.join("tree/b/x.json5")).unwrap(),
+    FORMATTED
+  );
+  assert_eq!(
+    fs::read_to_string(directory.join("tree/.git/h.json5")).unwrap(),
+    UNFORMATTED
+  );
+}
+
+#[test]
+fn separates_the_documents_of_several_files_written_to_a_pipe() {
+  let directory = directory("separators");
+  fs::write(directory.join("a.json5"), UNFORMATTED).unwrap();
+  fs::write(directory.join("b.json5"), UNFORMATTED).unwrap();
+  let format = |options: &[&str]| {
+    let output = Command::new(env!("CARGO_BIN_EXE_jsonpretty5er"))
+      .args(options)
+      .args(["a.json5", "b.json5"])
+      .current_dir(&directory)
+      .output()
+      .unwrap();
+    assert!(output.status.success());
+    String::from_utf8_lossy(&output.stdout).into_owned()
+  };
+  assert_eq!(format(&[]), format!("{}\n{}", FORMATTED, FORMATTED));
+  assert_eq!(
+    format(&["--banners", "always"]),
+    format!(
+      "a.json5:\n=======\n{}\nb.json5:\n=======\n{}",
+      FORMATTED, FORMATTED
+    )
+  );
+  assert_eq!(
+    format(&["--multi-doc-separator", "none"]),
+    format!("{}{}", FORMATTED, FORMATTED)
+  );
+}
+
+#[test]
+fn orders_the_properties_of_no_section_before_the_sections() {
+  let directory = directory("sections");
+  fs::write(
+    directory.join("a.json5"),
+    "{data_dir: \"/var\", other: 1, port: 80, host: \"localhost\"}\n",
+  )
+  .unwrap();
+  let output = Command::new(env!("CARGO_BIN_EXE_jsonpretty5er"))
+    .arg("a.json5")
+    .env("JSON5FMT_SECTIONS", "Networking=host,port;Storage=data_dir")
+    .current_dir(&directory)
+    .output()
+    .unwrap();
+  assert!(
+    output.status.success(),
+    "{}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    "{\n    other: 1,\n\n    // --- Networking ---\n    host: \"localhost\",\n    port: 80,\n\n    \
+     // --- Storage ---\n    data_dir: \"/var\",\n}\n"
+  );
+}
+
+#[test]
+fn answers_work_requests_in_order() {
+  use std::{io::Write, process::Stdio};
+
+  let directory = directory("persistent-worker");
+  fs::write(directory.join("a.json5"), UNFORMATTED).unwrap();
+  fs::write(directory.join("b.json5"), FORMATTED).unwrap();
+  fs::write(directory.join("arguments"), "a.json5\n").unwrap();
+  let mut worker = Command::new(env!("CARGO_BIN_EXE_jsonpretty5er"))
+    .arg("--persistent_worker")
+    .env("RUST_BACKTRACE", "0")
+    .env("RUST_LIB_BACKTRACE", "0")
+    .current_dir(&directory)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  worker
+    .stdin
+    .take()
+    .unwrap()
+    .write_all(
+      b"{\"arguments\": [\"check\", \"b.json5\"], \"requestId\": 1}\n\
+        {\"arguments\": [\"check\", \"@arguments\"], \"requestId\": 2}\n\
+        {\"cancel\": true, \"requestId\": 1}\n\
+        [\"check\"]\n\
+        {\"arguments\": [\"a.json5\"], \"requestId\": 3}\n",
+    )
+    .unwrap();
+  let output = worker.wait_with_output().unwrap();
+  assert!(output.status.success());
+  let formatted = FORMATTED.replace('\n', "\\n");
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    format!(
+      "{{\"exitCode\": 0, \"output\": \"\", \"requestId\": 1}}\n\
+       {{\"exitCode\": 1, \"output\": \"a.json5: Not formatted\\nError: Check failed: 1 file(s) \
+       have problems\\n\", \"requestId\": 2}}\n\
+       {{\"exitCode\": 0, \"output\": \"{}\", \"requestId\": 3}}\n",
+      formatted
+    )
+  );
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    "Worker error: Invalid work request: expected an object: [\"check\"]\n"
+  );
+}
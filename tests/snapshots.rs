@@ -0,0 +1,48 @@
+//! Checks that each fixture in the `tests` directory formats to its snapshot, `<file>.formatted`.
+
+use std::{fs, path::Path, process::Command};
+
+/// Checks that each `.json5` file in the directory `tests/<name>` formats to its snapshot, with the
+/// `options`.
+fn check_snapshots(name: &str, options: &[&str]) {
+  let directory = Path::new(env!("CARGO_MANIFEST_DIR"))
+    .join("tests")
+    .join(name);
+  let mut fixtures = fs::read_dir(&directory)
+    .unwrap()
+    .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+    .filter(|name| name.ends_with(".json5"))
+    .collect::<Vec<_>>();
+  fixtures.sort();
+  assert!(!fixtures.is_empty(), "{}: No fixtures", directory.display());
+  for fixture in &fixtures {
+    assert!(
+      directory.join(format!("{}.formatted", fixture)).is_file(),
+      "{}: No snapshot",
+      fixture
+    );
+  }
+  let output = Command::new(env!("CARGO_BIN_EXE_jsonpretty5er"))
+    .args(options)
+    .arg("--check-snapshots")
+    .args(&fixtures)
+    .current_dir(&directory)
+    .output()
+    .unwrap();
+  assert!(
+    output.status.success(),
+    "{}: {}",
+    directory.display(),
+    String::from_utf8_lossy(&output.stderr)
+  );
+}
+
+#[test]
+fn formats_the_prettier_fixtures_in_the_prettier_style() {
+  check_snapshots("prettier", &["--style", "prettier"]);
+}
+
+#[test]
+fn formats_the_number_fixtures() {
+  check_snapshots("numbers", &[]);
+}